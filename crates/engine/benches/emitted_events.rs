@@ -0,0 +1,74 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares reading the recorded events of the engine via the cloning accessor
+//! `get_emitted_events` to the accessors borrowing them.
+//!
+//! Run with `cargo bench -p ink_engine --bench emitted_events`.
+
+use ink_engine::ext::Engine;
+use std::{
+    hint::black_box,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+const EVENTS: usize = 100_000;
+const READS: u32 = 10;
+
+/// Returns the average time taken by `read`.
+fn measure(engine: &Engine, read: impl Fn(&Engine) -> usize) -> Duration {
+    let start = Instant::now();
+    for _ in 0..READS {
+        black_box(read(black_box(engine)));
+    }
+    start.elapsed() / READS
+}
+
+fn main() {
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    for i in 0..EVENTS {
+        engine.deposit_event(
+            &scale::Encode::encode(&Vec::<Vec<u8>>::new()),
+            &i.to_le_bytes(),
+        );
+    }
+
+    let cloned_count = measure(&engine, |engine| engine.get_emitted_events().count());
+    let count = measure(&engine, Engine::emitted_event_count);
+    let cloned_bytes = measure(&engine, |engine| {
+        engine
+            .get_emitted_events()
+            .map(|event| event.data.len())
+            .sum()
+    });
+    let borrowed_bytes = measure(&engine, |engine| {
+        let mut bytes = 0;
+        engine.for_each_emitted_event(|event| bytes += event.data.len());
+        bytes
+    });
+    println!(
+        "counting {EVENTS} events: {:>9} us cloned, {:>9} us borrowed",
+        cloned_count.as_micros(),
+        count.as_micros(),
+    );
+    println!(
+        "reading {EVENTS} events:  {:>9} us cloned, {:>9} us borrowed",
+        cloned_bytes.as_micros(),
+        borrowed_bytes.as_micros(),
+    );
+}
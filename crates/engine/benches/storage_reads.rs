@@ -0,0 +1,66 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares storage reads served from the read cache of the engine to reads which
+//! miss it, since every read is preceded by a write to the key.
+//!
+//! Run with `cargo bench -p ink_engine --bench storage_reads`.
+
+use ink_engine::ext::Engine;
+use std::{
+    hint::black_box,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+const READS: u32 = 100_000;
+const KEY: [u8; 32] = [1; 32];
+
+fn engine_with_value(len: usize) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    engine.set_storage(&KEY, &vec![7; len]);
+    engine
+}
+
+/// Returns the time taken by `READS` reads, each preceded by `before_read`.
+fn measure(engine: &mut Engine, mut before_read: impl FnMut(&mut Engine)) -> Duration {
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..READS {
+        before_read(engine);
+        let start = Instant::now();
+        black_box(engine.get_storage(black_box(&KEY)).expect("reading failed"));
+        elapsed += start.elapsed();
+    }
+    elapsed
+}
+
+fn main() {
+    for len in [32, 1024, 9600] {
+        let mut engine = engine_with_value(len);
+        let cached = measure(&mut engine, |_| ());
+        let value = vec![7; len];
+        let mut engine = engine_with_value(len);
+        let uncached = measure(&mut engine, |engine| {
+            engine.set_storage(&KEY, &value);
+        });
+        println!(
+            "{len:>5} byte values: {:>6} ns per cached read, {:>6} ns per uncached read",
+            cached.as_nanos() / u128::from(READS),
+            uncached.as_nanos() / u128::from(READS),
+        );
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares seeding the storage of a contract via `Engine::seed_storage` to
+//! writing the same cells one by one via `Engine::set_storage`.
+//!
+//! Run with `cargo bench -p ink_engine --bench storage_seeding`.
+
+use ink_engine::ext::Engine;
+use std::{
+    hint::black_box,
+    time::Instant,
+};
+
+const CELLS: u32 = 100_000;
+const CONTRACT: [u8; 32] = [1; 32];
+
+fn entries() -> impl Iterator<Item = (Vec<u8>, Vec<u8>)> {
+    (0..CELLS).map(|n| (n.to_le_bytes().to_vec(), vec![7; 32]))
+}
+
+fn contract_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_callee(CONTRACT.to_vec());
+    engine
+}
+
+fn main() {
+    let mut engine = contract_engine();
+    let entries_one_by_one: Vec<_> = entries().collect();
+    let start = Instant::now();
+    for (key, value) in &entries_one_by_one {
+        black_box(engine.set_storage(key, value));
+    }
+    let one_by_one = start.elapsed();
+
+    let mut engine = contract_engine();
+    let entries_batched: Vec<_> = entries().collect();
+    let start = Instant::now();
+    black_box(engine.seed_storage(&CONTRACT, entries_batched));
+    let batched = start.elapsed();
+
+    println!(
+        "{CELLS} cells: {:>6} ms one by one, {:>6} ms seeded, {:.1}x faster",
+        one_by_one.as_millis(),
+        batched.as_millis(),
+        one_by_one.as_secs_f64() / batched.as_secs_f64(),
+    );
+}
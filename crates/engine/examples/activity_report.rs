@@ -0,0 +1,74 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deploys a mock tip jar contract, calls it and prints a JSON report of the
+//! activity, using only `ink_engine::api`.
+//!
+//! Run with `cargo run -p ink_engine --example activity_report`.
+
+use ink_engine::api::{
+    AccountId,
+    ApiError,
+    Config,
+    Simulator,
+};
+
+const TIP: [u8; 4] = [0, 0, 0, 1];
+const WITHDRAW: [u8; 4] = [0, 0, 0, 2];
+const TIPS: &[u8] = b"tips";
+const OWNER: AccountId = [1; 32];
+const ALICE: AccountId = [2; 32];
+
+/// Counts the tips it received and pays out its balance to the owner.
+fn tip_jar(
+    sim: &mut Simulator,
+    selector: [u8; 4],
+    _args: &[u8],
+) -> Result<Vec<u8>, Vec<u8>> {
+    match selector {
+        TIP => {
+            let tips = sim.get_storage(TIPS).map_or(0, |tips| tips[0]) + 1;
+            sim.set_storage(TIPS, &[tips])
+                .map_err(|_| b"full".to_vec())?;
+            sim.emit_event(vec![[tips; 32]], &sim.caller())
+                .map_err(|_| b"event".to_vec())?;
+            sim.debug_message(&format!("tip #{tips}"));
+            Ok(vec![tips])
+        }
+        WITHDRAW if sim.caller() == OWNER => {
+            let balance = sim.balance(sim.address());
+            sim.transfer(OWNER, balance)
+                .map_err(|_| b"transfer".to_vec())?;
+            Ok(Vec::new())
+        }
+        WITHDRAW => Err(b"not the owner".to_vec()),
+        _ => Err(b"unknown selector".to_vec()),
+    }
+}
+
+fn main() -> Result<(), ApiError> {
+    let mut sim = Simulator::new(Config::default());
+    let jar = sim.deploy(tip_jar, b"tip-jar", 100);
+
+    sim.call(ALICE, jar, TIP, &[])?;
+    sim.call(ALICE, jar, TIP, &[])?;
+    let denied = sim.call(ALICE, jar, WITHDRAW, &[]);
+    assert_eq!(denied, Err(ApiError::Reverted(b"not the owner".to_vec())));
+    sim.call(OWNER, jar, WITHDRAW, &[])?;
+
+    let report = serde_json::to_string_pretty(&sim.activity_report())
+        .expect("the report serializes to JSON");
+    println!("{report}");
+    Ok(())
+}
@@ -0,0 +1,173 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export of the recorded interactions with the engine for external diff tools.
+//!
+//! An [`ActivityLog`] holds the operations of a scenario being recorded, see
+//! [`crate::scenario`], i.e. the host functions with their inputs and outputs, the
+//! deposited events and the balance changes. Its encoding is the
+//! [`ACTIVITY_LOG_VERSION`] byte followed by the SCALE encoded operations and the
+//! numbers of recorded entries dropped by the engine, see
+//! [`crate::recorder::DroppedRecords`], hence logs of different branches can be
+//! compared, e.g. in CI.
+
+use crate::{
+    ext::Engine,
+    recorder::DroppedRecords,
+    scenario::Operation,
+};
+use ink_prelude::{
+    format,
+    string::String,
+    vec::Vec,
+};
+
+/// The version of the encoding of [`ActivityLog`].
+///
+/// Increased whenever the encoding of [`Operation`] changes in a way other than
+/// by appending variants. Logs of version `1` lack the dropped records and are
+/// still decoded.
+pub const ACTIVITY_LOG_VERSION: u8 = 2;
+
+/// The interactions with the engine in the order they happened.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActivityLog {
+    /// The recorded operations.
+    pub operations: Vec<Operation>,
+    /// The number of recorded entries dropped by the engine when the log was
+    /// exported.
+    pub dropped: DroppedRecords,
+}
+
+/// The first difference between two activity logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivityDiff {
+    /// The index of the first differing operation.
+    pub index: usize,
+    /// A human readable description of the difference.
+    pub description: String,
+}
+
+/// Errors which can happen when decoding an [`ActivityLog`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum ActivityLogError {
+    /// The log was encoded with a version this engine does not support.
+    UnsupportedVersion(u8),
+    /// The log is empty and thereby lacks the version byte.
+    MissingVersion,
+    /// The operations could not be decoded.
+    Decoding(scale::Error),
+}
+
+impl ActivityLog {
+    /// Returns the version byte followed by the SCALE encoded operations and
+    /// dropped records.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::from([ACTIVITY_LOG_VERSION]);
+        scale::Encode::encode_to(&self.operations, &mut encoded);
+        let dropped = &self.dropped;
+        let counts = [dropped.events, dropped.printlns, dropped.calls]
+            .map(|count| u64::try_from(count).expect("usize to u64 conversion failed"));
+        scale::Encode::encode_to(&counts, &mut encoded);
+        encoded
+    }
+
+    /// Decodes a log encoded via [`ActivityLog::encode`].
+    ///
+    /// # Errors
+    ///
+    /// If the version byte is missing or unsupported, or if the operations cannot be
+    /// decoded.
+    pub fn decode(encoded: &[u8]) -> Result<Self, ActivityLogError> {
+        let (&version, mut operations) = encoded
+            .split_first()
+            .ok_or(ActivityLogError::MissingVersion)?;
+        if version == 1 {
+            let operations = scale::DecodeAll::decode_all(&mut operations)
+                .map_err(ActivityLogError::Decoding)?;
+            return Ok(Self {
+                operations,
+                dropped: DroppedRecords::default(),
+            })
+        }
+        if version != ACTIVITY_LOG_VERSION {
+            return Err(ActivityLogError::UnsupportedVersion(version))
+        }
+        let (operations, [events, printlns, calls]) =
+            <(Vec<Operation>, [u64; 3]) as scale::DecodeAll>::decode_all(&mut operations)
+                .map_err(ActivityLogError::Decoding)?;
+        let count = |count: u64| usize::try_from(count).unwrap_or(usize::MAX);
+        Ok(Self {
+            operations,
+            dropped: DroppedRecords {
+                events: count(events),
+                printlns: count(printlns),
+                calls: count(calls),
+            },
+        })
+    }
+
+    /// Returns the first difference between this log and `other`, `None` if they
+    /// are equal.
+    ///
+    /// If one log is a prefix of the other, the difference is the first operation
+    /// missing from the shorter one.
+    pub fn diff(&self, other: &ActivityLog) -> Option<ActivityDiff> {
+        let index = self
+            .operations
+            .iter()
+            .zip(&other.operations)
+            .position(|(ours, theirs)| ours != theirs)
+            .or_else(|| {
+                (self.operations.len() != other.operations.len()).then(|| {
+                    core::cmp::min(self.operations.len(), other.operations.len())
+                })
+            })?;
+        let operations = (self.operations.get(index), other.operations.get(index));
+        let description = match operations {
+            (Some(ours), Some(theirs)) => {
+                format!("operation {index} differs: {ours:?} != {theirs:?}")
+            }
+            (Some(ours), None) => {
+                format!("operation {index} is missing from the other log: {ours:?}")
+            }
+            (None, Some(theirs)) => {
+                format!("operation {index} is missing from this log: {theirs:?}")
+            }
+            (None, None) => unreachable!("the index is within one of the logs"),
+        };
+        Some(ActivityDiff { index, description })
+    }
+}
+
+impl Engine {
+    /// Returns the operations recorded so far by the scenario being recorded as an
+    /// encoded [`ActivityLog`], see [`ActivityLog::encode`].
+    ///
+    /// The recording is not stopped. The log has no operations if no recording was
+    /// started via [`Engine::start_recording_scenario`]. The dropped records are
+    /// the ones of [`Engine::dropped_records`].
+    pub fn export_activity(&self) -> Vec<u8> {
+        let operations = self
+            .scenario
+            .as_ref()
+            .map(|scenario| scenario.operations.clone())
+            .unwrap_or_default();
+        ActivityLog {
+            operations,
+            dropped: self.dropped_records(),
+        }
+        .encode()
+    }
+}
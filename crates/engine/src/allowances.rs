@@ -0,0 +1,102 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Allowances on the balances of the engine.
+//!
+//! Mirrors the semantics of allowance or proxy pallets of the runtime: an owner
+//! allows a spender to move up to a certain amount of the owner's balance.
+//!
+//! The core `transfer` of the engine does not consult allowances.
+
+use crate::{
+    ext::Engine,
+    types::Balance,
+};
+use scale::KeyedVec;
+
+const ALLOWANCE_OF: &[u8] = b"allowance:";
+
+/// Returns the database key under which to find the amount `spender` is allowed
+/// to move from the balance of `owner`.
+fn allowance_of_key(owner: &[u8], spender: &[u8]) -> [u8; 32] {
+    let keyed = owner
+        .to_vec()
+        .to_keyed_vec(spender)
+        .to_keyed_vec(ALLOWANCE_OF);
+    let mut hashed_key: [u8; 32] = [0; 32];
+    super::hashing::blake2b_256(&keyed[..], &mut hashed_key);
+    hashed_key
+}
+
+/// Errors which can happen when spending an allowance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AllowanceError {
+    /// The spender is allowed to move less than the requested amount.
+    InsufficientAllowance { allowance: Balance, amount: Balance },
+    /// The owner has less than the requested amount.
+    InsufficientBalance { balance: Balance, amount: Balance },
+}
+
+impl Engine {
+    /// Allows `spender` to move up to `amount` of the balance of `owner`.
+    ///
+    /// Replaces a previously set allowance.
+    pub fn set_allowance(&mut self, owner: &[u8], spender: &[u8], amount: Balance) {
+        let hashed_key = allowance_of_key(owner, spender);
+        self.database
+            .insert(hashed_key.to_vec(), scale::Encode::encode(&amount));
+    }
+
+    /// Returns the amount `spender` is allowed to move from the balance of `owner`.
+    pub fn allowance(&self, owner: &[u8], spender: &[u8]) -> Balance {
+        let hashed_key = allowance_of_key(owner, spender);
+        self.database
+            .get(&hashed_key)
+            .map(|encoded_allowance| {
+                scale::Decode::decode(&mut &encoded_allowance[..])
+                    .expect("unable to decode allowance from database")
+            })
+            .unwrap_or_default()
+    }
+
+    /// Moves `amount` from the balance of `owner` to `dest` on behalf of `spender`,
+    /// reducing the allowance of `spender` by `amount`.
+    ///
+    /// # Errors
+    ///
+    /// If the allowance of `spender` or the balance of `owner` is less than
+    /// `amount`. Neither balances nor the allowance are changed in this case.
+    pub fn transfer_from(
+        &mut self,
+        spender: &[u8],
+        owner: &[u8],
+        dest: &[u8],
+        amount: Balance,
+    ) -> Result<(), AllowanceError> {
+        let allowance = self.allowance(owner, spender);
+        if allowance < amount {
+            return Err(AllowanceError::InsufficientAllowance { allowance, amount })
+        }
+        let balance = self.database.get_balance(owner).unwrap_or_default();
+        if balance < amount {
+            return Err(AllowanceError::InsufficientBalance { balance, amount })
+        }
+
+        self.set_allowance(owner, spender, allowance - amount);
+        self.database.set_balance(owner, balance - amount);
+        let dest_balance = self.database.get_balance(dest).unwrap_or_default();
+        self.database.set_balance(dest, dest_balance + amount);
+        Ok(())
+    }
+}
@@ -0,0 +1,649 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A minimal API over the engine for external tooling, e.g. block explorers and
+//! simulators.
+//!
+//! The rest of the crate evolves with the needs of `ink_env` and changes between
+//! versions. The items of this module follow semantic versioning instead: they are
+//! only changed in a breaking way with a major version of the crate. New fields of
+//! [`Config`] and variants of [`ApiError`] are not breaking, both are
+//! `#[non_exhaustive]`.
+//!
+//! Functions take and return owned data and nothing borrows from the engine,
+//! hence they can be exposed via FFI or `wasm-bindgen`. The returned records are
+//! [`serde::Serialize`], bytes are serialized as `0x` prefixed hex strings.
+//!
+//! Contracts are mock contracts, i.e. plain functions dispatched by a
+//! [`Simulator`], see [`Contract`]. While executing they access their storage,
+//! emit events and call other contracts via the simulator.
+//!
+//! ```
+//! use ink_engine::api::{
+//!     Config,
+//!     Simulator,
+//! };
+//!
+//! fn counter(
+//!     sim: &mut Simulator,
+//!     _selector: [u8; 4],
+//!     _args: &[u8],
+//! ) -> Result<Vec<u8>, Vec<u8>> {
+//!     let count = sim.get_storage(b"count").map_or(0, |count| count[0]);
+//!     sim.set_storage(b"count", &[count + 1])
+//!         .map_err(|_| Vec::new())?;
+//!     Ok(vec![count + 1])
+//! }
+//!
+//! let mut sim = Simulator::new(Config::default());
+//! let contract = sim.deploy(counter, b"counter", 0);
+//! assert_eq!(sim.call([1; 32], contract, [0; 4], &[]), Ok(vec![1]));
+//! assert_eq!(sim.call([1; 32], contract, [0; 4], &[]), Ok(vec![2]));
+//! ```
+
+use crate::{
+    ext::{
+        Engine,
+        Error,
+    },
+    hashing,
+    recorder::DroppedRecords,
+    storage_overlay::CommitMode,
+    test_api::{
+        FrameItem,
+        FrameRecord,
+    },
+    types::encode_hex,
+};
+use ink_prelude::{
+    collections::BTreeMap,
+    format,
+    string::String,
+    vec::Vec,
+};
+use serde::{
+    Serialize,
+    Serializer,
+};
+
+pub use crate::state::{
+    EngineState,
+    StateError,
+};
+
+/// The id of an account.
+pub type AccountId = [u8; 32];
+
+/// The balance of an account.
+pub type Balance = u128;
+
+/// The code of a contract deployed on a [`Simulator`].
+///
+/// Invoked with the simulator, the selector of the called message and its SCALE
+/// encoded arguments. Returns the SCALE encoded output, or the data the contract
+/// reverted with as error.
+pub type Contract = fn(&mut Simulator, [u8; 4], &[u8]) -> Result<Vec<u8>, Vec<u8>>;
+
+/// The configuration of a [`Simulator`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Config {
+    /// Reading the caller or callee outside of calls fails instead of returning a
+    /// default value.
+    pub strict: bool,
+    /// The gas limit of top-level calls.
+    pub gas_limit: u64,
+    /// The storage writes of reverted calls are discarded, like on-chain.
+    pub revert_storage_on_failure: bool,
+    /// Debug messages are recorded.
+    pub debug_messages: bool,
+}
+
+impl Default for Config {
+    /// The defaults of the engine, except that storage writes of reverted calls are
+    /// discarded.
+    fn default() -> Self {
+        let config = crate::ext::EngineConfig::default();
+        Self {
+            strict: config.strict,
+            gas_limit: config.gas_limit,
+            revert_storage_on_failure: true,
+            debug_messages: config.println_enabled,
+        }
+    }
+}
+
+/// Errors of the [`Simulator`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ApiError {
+    /// The called contract reverted with the data.
+    Reverted(Vec<u8>),
+    /// No contract is deployed at the called account.
+    NotCallable,
+    /// The state to import is invalid, see [`StateError`].
+    InvalidState(StateError),
+    /// Any other error of the engine, described for humans.
+    ///
+    /// The description is not part of the stable API.
+    Engine(String),
+}
+
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::CalleeReverted(data) => Self::Reverted(data),
+            Error::NotCallable => Self::NotCallable,
+            error => Self::Engine(format!("{error:?}")),
+        }
+    }
+}
+
+impl From<crate::Error> for ApiError {
+    fn from(error: crate::Error) -> Self {
+        Self::Engine(format!("{error:?}"))
+    }
+}
+
+impl From<StateError> for ApiError {
+    fn from(error: StateError) -> Self {
+        Self::InvalidState(error)
+    }
+}
+
+/// A call dispatched by the [`Simulator`], including calls nested in others.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CallRecord {
+    /// The id of the call, ids are increasing in the order calls are made.
+    pub id: usize,
+    /// The number of calls the call is nested in.
+    pub depth: usize,
+    /// The account which made the call.
+    #[serde(serialize_with = "hex")]
+    pub caller: Vec<u8>,
+    /// The called contract.
+    #[serde(serialize_with = "hex")]
+    pub callee: Vec<u8>,
+}
+
+/// An event emitted via [`Simulator::emit_event`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EventRecord {
+    /// The id of the call which emitted the event, `0` outside of calls.
+    pub call: usize,
+    /// The contract which emitted the event, `None` outside of calls.
+    #[serde(serialize_with = "hex_option")]
+    pub emitter: Option<Vec<u8>>,
+    /// The topics of the event.
+    #[serde(serialize_with = "hex_list")]
+    pub topics: Vec<Vec<u8>>,
+    /// The data of the event.
+    #[serde(serialize_with = "hex")]
+    pub data: Vec<u8>,
+}
+
+/// A transfer of value between accounts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TransferRecord {
+    /// The id of the call which transferred, `0` outside of calls.
+    pub call: usize,
+    /// The account the value was transferred from.
+    #[serde(serialize_with = "hex")]
+    pub from: Vec<u8>,
+    /// The account the value was transferred to.
+    #[serde(serialize_with = "hex")]
+    pub to: Vec<u8>,
+    /// The transferred value.
+    pub value: Balance,
+}
+
+/// The interactions of a contract with the [`Simulator`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Statistics {
+    /// The number of reads of the storage of the contract.
+    pub storage_reads: usize,
+    /// The number of writes to the storage of the contract.
+    pub storage_writes: usize,
+    /// The number of events emitted by the contract.
+    pub events_emitted: u32,
+    /// The number of bytes of the topics and data of the events emitted by the
+    /// contract.
+    pub event_bytes_emitted: u64,
+}
+
+/// The statistics of a deployed contract, see [`ActivityReport`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContractReport {
+    /// The account of the contract.
+    #[serde(serialize_with = "hex")]
+    pub contract: Vec<u8>,
+    /// The interactions of the contract.
+    pub statistics: Statistics,
+}
+
+/// Everything which happened on a [`Simulator`], see [`Simulator::activity_report`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ActivityReport {
+    /// The dispatched calls in the order they were made.
+    pub calls: Vec<CallRecord>,
+    /// The emitted events in the order they were emitted.
+    pub events: Vec<EventRecord>,
+    /// The transfers in the order they happened.
+    pub transfers: Vec<TransferRecord>,
+    /// The debug messages in the order they were printed.
+    pub debug_messages: Vec<String>,
+    /// The statistics of the deployed contracts, ordered by their accounts.
+    pub contracts: Vec<ContractReport>,
+    /// The number of recorded entries dropped by the engine, e.g. debug messages
+    /// missing from [`ActivityReport::debug_messages`].
+    pub dropped: DroppedRecords,
+}
+
+/// An engine with mock contracts deployed on it.
+pub struct Simulator {
+    /// The engine holding the state.
+    engine: Engine,
+    /// The code of the deployed contracts by their account id.
+    contracts: BTreeMap<Vec<u8>, Contract>,
+}
+
+impl Simulator {
+    /// Creates a simulator without any contracts on a new engine.
+    pub fn new(config: Config) -> Self {
+        let mut engine = Engine::new();
+        engine.config.strict = config.strict;
+        engine.config.gas_limit = config.gas_limit;
+        engine.config.println_enabled = config.debug_messages;
+        engine.config.commit_mode = if config.revert_storage_on_failure {
+            CommitMode::OnFrameSuccess
+        } else {
+            CommitMode::Immediate
+        };
+        Self {
+            engine,
+            contracts: BTreeMap::new(),
+        }
+    }
+
+    /// Imports the balances and contract storages of `state` on top of the current
+    /// state, see [`Engine::import_state`].
+    ///
+    /// # Errors
+    ///
+    /// [`ApiError::InvalidState`] if the state cannot be imported. Nothing is
+    /// imported in this case.
+    pub fn import_state(&mut self, state: EngineState) -> Result<(), ApiError> {
+        self.engine.import_state(state).map_err(Into::into)
+    }
+
+    /// Returns the balances and contract storages.
+    pub fn export_state(&self) -> EngineState {
+        self.engine.export_state()
+    }
+
+    /// Returns the balances and contract storages as JSON, see
+    /// [`Engine::export_state_json`].
+    pub fn export_state_json(&self) -> String {
+        self.engine.export_state_json()
+    }
+
+    /// Deploys `code` under an account id derived from `salt` and funds the
+    /// contract with `endowment`.
+    ///
+    /// The same `salt` always results in the same account id.
+    ///
+    /// # Panics
+    ///
+    /// If a contract has already been deployed with `salt`.
+    pub fn deploy(
+        &mut self,
+        code: Contract,
+        salt: &[u8],
+        endowment: Balance,
+    ) -> AccountId {
+        let mut account_id = [0; 32];
+        let input = [b"test-contract:", salt].concat();
+        hashing::blake2b_256(&input[..], &mut account_id);
+        assert!(
+            !self.contracts.contains_key(&account_id[..]),
+            "a contract has already been deployed with the salt {salt:?}"
+        );
+        self.contracts.insert(account_id.to_vec(), code);
+        self.engine
+            .deploy_test_contract(account_id.to_vec(), endowment);
+        account_id
+    }
+
+    /// Calls the message with `selector` of `contract` with the SCALE encoded
+    /// `args` and returns the SCALE encoded output.
+    ///
+    /// Outside of any call, `caller` becomes the caller and origin of the call.
+    /// Calls made by contracts are nested in the current call, their caller is the
+    /// calling contract. Panics of the contract propagate to the caller.
+    ///
+    /// # Errors
+    ///
+    /// - [`ApiError::Reverted`] with the data the contract reverted with.
+    /// - [`ApiError::NotCallable`] if no contract is deployed at `contract`.
+    ///
+    /// # Panics
+    ///
+    /// If a nested call is made with a `caller` other than the calling contract.
+    pub fn call(
+        &mut self,
+        caller: AccountId,
+        contract: AccountId,
+        selector: [u8; 4],
+        args: &[u8],
+    ) -> Result<Vec<u8>, ApiError> {
+        let code = *self
+            .contracts
+            .get(&contract[..])
+            .ok_or(ApiError::NotCallable)?;
+        if self.engine.exec_context.depth() == 0 {
+            self.engine.try_set_caller(caller.to_vec())?;
+        } else {
+            assert_eq!(
+                self.engine.get_callee(),
+                caller,
+                "the caller of a nested call must be the calling contract"
+            );
+        }
+        let input = [&selector[..], args].concat();
+        self.engine.try_enter_frame(contract.to_vec(), input)?;
+        match code(self, selector, args) {
+            Ok(output) => {
+                self.engine.return_from_frame(output.clone());
+                Ok(output)
+            }
+            Err(data) => {
+                self.engine.revert_frame(data.clone());
+                Err(ApiError::Reverted(data))
+            }
+        }
+    }
+
+    /// Returns the balance of `account_id`, zero for unknown accounts.
+    pub fn balance(&self, account_id: AccountId) -> Balance {
+        self.engine
+            .get_balance(account_id.to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Sets the balance of `account_id`.
+    ///
+    /// # Errors
+    ///
+    /// If the balance of the account cannot be set, e.g. for the all-zero account.
+    pub fn set_balance(
+        &mut self,
+        account_id: AccountId,
+        balance: Balance,
+    ) -> Result<(), ApiError> {
+        self.engine
+            .try_set_balance(account_id.to_vec(), balance)
+            .map_err(Into::into)
+    }
+
+    /// Returns the contract currently executing.
+    ///
+    /// # Panics
+    ///
+    /// Outside of any call.
+    pub fn address(&self) -> AccountId {
+        self.current_contract()
+    }
+
+    /// Returns the caller of the contract currently executing.
+    ///
+    /// # Panics
+    ///
+    /// Outside of any call.
+    pub fn caller(&self) -> AccountId {
+        self.current_contract();
+        let caller = self
+            .engine
+            .exec_context
+            .caller()
+            .expect("every call has a caller");
+        account_id(caller.as_bytes())
+    }
+
+    /// Returns the value at `key` of the storage of the contract currently
+    /// executing, if any.
+    ///
+    /// # Panics
+    ///
+    /// Outside of any call.
+    pub fn get_storage(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        self.current_contract();
+        self.engine.get_storage(key).ok().map(<[u8]>::to_vec)
+    }
+
+    /// Writes `value` to `key` of the storage of the contract currently executing.
+    ///
+    /// # Errors
+    ///
+    /// If the write exceeds a limit of the engine, e.g. its storage deposit.
+    ///
+    /// # Panics
+    ///
+    /// Outside of any call.
+    pub fn set_storage(&mut self, key: &[u8], value: &[u8]) -> Result<(), ApiError> {
+        self.current_contract();
+        self.engine.try_set_storage(key, value)?;
+        Ok(())
+    }
+
+    /// Removes the value at `key` of the storage of the contract currently
+    /// executing, returning whether there was one.
+    ///
+    /// # Panics
+    ///
+    /// Outside of any call.
+    pub fn clear_storage(&mut self, key: &[u8]) -> bool {
+        self.current_contract();
+        self.engine.clear_storage(key).is_some()
+    }
+
+    /// Emits an event with `topics` and `data` from the contract currently
+    /// executing.
+    ///
+    /// # Errors
+    ///
+    /// If the event exceeds a limit of the engine, e.g. its number of topics.
+    ///
+    /// # Panics
+    ///
+    /// Outside of any call.
+    pub fn emit_event(
+        &mut self,
+        topics: Vec<[u8; 32]>,
+        data: &[u8],
+    ) -> Result<(), ApiError> {
+        self.current_contract();
+        let topics: Vec<&[u8]> = topics.iter().map(<[u8; 32]>::as_slice).collect();
+        let encoded = scale::Encode::encode(&topics);
+        self.engine
+            .try_deposit_event(&encoded, data)
+            .map_err(Into::into)
+    }
+
+    /// Records the debug `message`, see [`Simulator::debug_messages`].
+    ///
+    /// Unlike [`Engine::debug_message`] the message is not printed to the standard
+    /// output, which tools may use otherwise.
+    pub fn debug_message(&mut self, message: &str) {
+        if self.engine.config.println_enabled {
+            self.engine.record_println(message);
+        } else {
+            self.engine.debug_message(message);
+        }
+    }
+
+    /// Transfers `value` from the contract currently executing to `to`.
+    ///
+    /// # Errors
+    ///
+    /// If the balance of the contract does not cover the transfer.
+    ///
+    /// # Panics
+    ///
+    /// Outside of any call.
+    pub fn transfer(&mut self, to: AccountId, value: Balance) -> Result<(), ApiError> {
+        self.current_contract();
+        self.engine
+            .transfer(&to, &scale::Encode::encode(&value))
+            .map_err(Into::into)
+    }
+
+    /// Returns the dispatched calls in the order they were made.
+    pub fn calls(&self) -> Vec<CallRecord> {
+        self.engine
+            .recorded_frames()
+            .into_iter()
+            .filter_map(|frame| {
+                Some(CallRecord {
+                    id: frame.id,
+                    depth: frame.depth,
+                    caller: frame.caller?,
+                    callee: frame.callee?,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the emitted events in the order they were emitted.
+    pub fn events(&self) -> Vec<EventRecord> {
+        let frames = self.engine.recorded_frames();
+        let mut events = Vec::new();
+        collect_events(&frames, 0, &mut events);
+        events
+    }
+
+    /// Returns the transfers in the order they happened.
+    pub fn transfers(&self) -> Vec<TransferRecord> {
+        self.engine
+            .recorded_transfers()
+            .iter()
+            .map(|transfer| {
+                TransferRecord {
+                    call: transfer.frame,
+                    from: transfer.from.clone(),
+                    to: transfer.to.clone(),
+                    value: transfer.value,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the debug messages in the order they were printed.
+    pub fn debug_messages(&self) -> Vec<String> {
+        self.engine
+            .get_emitted_debug_messages()
+            .into_iter()
+            .collect()
+    }
+
+    /// Returns the interactions of `contract`.
+    pub fn statistics(&self, contract: AccountId) -> Statistics {
+        let statistics = self.engine.contract_statistics(&contract);
+        Statistics {
+            storage_reads: statistics.storage_reads,
+            storage_writes: statistics.storage_writes,
+            events_emitted: statistics.events_emitted,
+            event_bytes_emitted: statistics.event_bytes_emitted,
+        }
+    }
+
+    /// Returns everything which happened on the simulator.
+    pub fn activity_report(&self) -> ActivityReport {
+        ActivityReport {
+            calls: self.calls(),
+            events: self.events(),
+            transfers: self.transfers(),
+            debug_messages: self.debug_messages(),
+            contracts: self
+                .contracts
+                .keys()
+                .map(|contract| {
+                    ContractReport {
+                        contract: contract.clone(),
+                        statistics: self.statistics(account_id(contract)),
+                    }
+                })
+                .collect(),
+            dropped: self.engine.dropped_records(),
+        }
+    }
+
+    /// Returns the contract currently executing.
+    ///
+    /// # Panics
+    ///
+    /// Outside of any call.
+    fn current_contract(&self) -> AccountId {
+        assert!(
+            self.engine.exec_context.depth() > 0,
+            "no contract is executing outside of calls"
+        );
+        account_id(&self.engine.get_callee())
+    }
+}
+
+/// Appends the events of the frame with `id` and the frames nested in it to
+/// `events`, in the order they were emitted.
+fn collect_events(frames: &[FrameRecord], id: usize, events: &mut Vec<EventRecord>) {
+    let Some(frame) = frames.get(id) else { return };
+    for item in &frame.items {
+        match item {
+            FrameItem::Event(event) => {
+                events.push(EventRecord {
+                    call: frame.id,
+                    emitter: frame.callee.clone(),
+                    topics: event.topics.clone(),
+                    data: event.data.clone(),
+                })
+            }
+            FrameItem::Call(nested) => collect_events(frames, *nested, events),
+            _ => (),
+        }
+    }
+}
+
+/// Returns the account id of `bytes`, padded with zeros or truncated to 32 bytes.
+fn account_id(bytes: &[u8]) -> AccountId {
+    let mut account_id = [0; 32];
+    let len = bytes.len().min(account_id.len());
+    account_id[..len].copy_from_slice(&bytes[..len]);
+    account_id
+}
+
+/// Serializes `bytes` as a `0x` prefixed hex string.
+fn hex<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&encode_hex(bytes))
+}
+
+/// Serializes `bytes` as a `0x` prefixed hex string, if any.
+fn hex_option<S: Serializer>(
+    bytes: &Option<Vec<u8>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    bytes.as_deref().map(encode_hex).serialize(serializer)
+}
+
+/// Serializes every item of `list` as a `0x` prefixed hex string.
+fn hex_list<S: Serializer>(list: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(list.iter().map(|bytes| encode_hex(bytes)))
+}
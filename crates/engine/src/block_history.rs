@@ -0,0 +1,184 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Snapshots of the state at block boundaries, to simulate re-orgs.
+//!
+//! Whenever the chain advances by a block, see [`Engine::advance_block`], the state
+//! at the end of the finished block is retained: the storage, balances and nonces,
+//! the storage deposits and the number of events emitted so far. At most
+//! [`crate::ext::EngineConfig::block_history_len`] blocks are retained, older ones
+//! are dropped.
+//!
+//! [`Engine::revert_to_block`] makes a retained block the head of the chain again,
+//! as if the blocks after it had been retracted by a re-org. Events emitted after
+//! the block are dropped from the in-memory recorder.
+
+use crate::{
+    database::Database,
+    ext::Engine,
+    recorder::InMemoryRecorder,
+    scenario::Operation,
+    storage_deposit::StorageDeposits,
+    types::{
+        AccountId,
+        BlockNumber,
+        BlockTimestamp,
+    },
+};
+use core::fmt;
+use ink_prelude::{
+    collections::{
+        BTreeMap,
+        VecDeque,
+    },
+    vec::Vec,
+};
+
+/// The state at the end of a block.
+#[derive(Clone)]
+struct BlockSnapshot {
+    /// The number of the block.
+    block_number: BlockNumber,
+    /// The timestamp of the block.
+    timestamp: BlockTimestamp,
+    /// The storage, balances and nonces.
+    database: Database,
+    /// The storage cells used by each account.
+    cells_per_account: BTreeMap<AccountId, BTreeMap<Vec<u8>, bool>>,
+    /// The reserved storage deposits.
+    storage_deposits: StorageDeposits,
+    /// The number of events emitted until the end of the block, including the
+    /// dropped ones.
+    event_count: usize,
+}
+
+/// The snapshots of the retained blocks, oldest first.
+#[derive(Clone, Default)]
+pub(crate) struct BlockHistory {
+    snapshots: VecDeque<BlockSnapshot>,
+}
+
+impl BlockHistory {
+    /// Drops all snapshots.
+    pub(crate) fn reset(&mut self) {
+        self.snapshots.clear();
+    }
+}
+
+/// The block to revert to is neither the current block nor retained, see
+/// [`Engine::revert_to_block`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockNotRetained {
+    /// The block to revert to.
+    pub block_number: BlockNumber,
+    /// The retained blocks in ascending order.
+    pub retained: Vec<BlockNumber>,
+}
+
+impl fmt::Display for BlockNotRetained {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block {} is not retained", self.block_number)?;
+        match (self.retained.first(), self.retained.last()) {
+            (Some(oldest), Some(newest)) => {
+                write!(f, ", retained blocks: {oldest} to {newest}")
+            }
+            _ => write!(f, ", no blocks are retained"),
+        }
+    }
+}
+
+impl Engine {
+    /// Retains the state at the end of the current block, dropping the oldest
+    /// snapshot if [`crate::ext::EngineConfig::block_history_len`] is exceeded.
+    pub(crate) fn retain_block(&mut self) {
+        let limit = self.config.block_history_len;
+        if limit == 0 {
+            return
+        }
+        while self.block_history.snapshots.len() >= limit {
+            self.block_history.snapshots.pop_front();
+        }
+        let snapshot = BlockSnapshot {
+            block_number: self.exec_context.block_number(),
+            timestamp: self.exec_context.timestamp(),
+            database: self.database.clone(),
+            cells_per_account: self.debug_info.cells_per_account.clone(),
+            storage_deposits: self.storage_deposits.clone(),
+            event_count: self
+                .recorder
+                .in_memory()
+                .map_or(0, InMemoryRecorder::total_events),
+        };
+        self.block_history.snapshots.push_back(snapshot);
+    }
+
+    /// Returns the numbers of the retained blocks in ascending order.
+    pub fn retained_blocks(&self) -> Vec<BlockNumber> {
+        self.block_history
+            .snapshots
+            .iter()
+            .map(|snapshot| snapshot.block_number)
+            .collect()
+    }
+
+    /// Makes the block `block_number` the head of the chain again, restoring the
+    /// storage, balances, storage deposits, block number and timestamp to the end
+    /// of the block.
+    ///
+    /// The retained blocks after it are dropped, as are the events emitted after
+    /// it from the in-memory recorder. Reverting to the current block leaves the
+    /// state unchanged. Everything else, e.g. registered contracts or recorded
+    /// debug messages, is left untouched.
+    ///
+    /// # Errors
+    ///
+    /// If the block is neither the current block nor retained.
+    pub fn revert_to_block(
+        &mut self,
+        block_number: BlockNumber,
+    ) -> Result<(), BlockNotRetained> {
+        self.record_operation(Operation::RevertToBlock(block_number));
+        if block_number == self.exec_context.block_number() {
+            return Ok(())
+        }
+        let Some(index) = self
+            .block_history
+            .snapshots
+            .iter()
+            .position(|snapshot| snapshot.block_number == block_number)
+        else {
+            return Err(BlockNotRetained {
+                block_number,
+                retained: self.retained_blocks(),
+            })
+        };
+        let snapshot = self
+            .block_history
+            .snapshots
+            .drain(index..)
+            .next()
+            .expect("the snapshot exists at index");
+        self.database = snapshot.database;
+        self.debug_info.cells_per_account = snapshot.cells_per_account;
+        self.storage_deposits = snapshot.storage_deposits;
+        self.exec_context.set_block_number(snapshot.block_number);
+        self.exec_context.set_block_timestamp(snapshot.timestamp);
+        self.read_cache.clear();
+        if let Some(recorder) = self.recorder.in_memory_mut() {
+            let retained = snapshot.event_count.saturating_sub(recorder.dropped_events);
+            recorder.emitted_events.truncate(retained);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The version and the capabilities of the engine API.
+//!
+//! Environments built on the engine check them before using the engine, so that a
+//! mismatch of the `ink_engine` version fails with a message naming the missing
+//! host function instead of an `unimplemented!` panic deep inside a test.
+
+use core::ops::BitOr;
+
+/// The version of the engine API.
+///
+/// Incremented whenever a host function is added to the engine.
+pub const ENGINE_API_VERSION: u32 = 3;
+
+/// A set of host functions implemented by the engine.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities(u64);
+
+impl Capabilities {
+    /// Reading and writing the contract storage.
+    pub const STORAGE: Self = Self(1 << 0);
+    /// Transferring value between accounts.
+    pub const TRANSFER: Self = Self(1 << 1);
+    /// Depositing events.
+    pub const EVENTS: Self = Self(1 << 2);
+    /// Recording debug messages.
+    pub const DEBUG_MESSAGE: Self = Self(1 << 3);
+    /// Terminating contracts.
+    pub const TERMINATE: Self = Self(1 << 4);
+    /// Calling contracts.
+    pub const CALL: Self = Self(1 << 5);
+    /// Delegate calls of registered code.
+    pub const DELEGATE_CALL: Self = Self(1 << 6);
+    /// Instantiating contracts.
+    pub const INSTANTIATE: Self = Self(1 << 7);
+    /// Dispatching runtime calls.
+    pub const CALL_RUNTIME: Self = Self(1 << 8);
+    /// Calling chain extensions.
+    pub const CHAIN_EXTENSION: Self = Self(1 << 9);
+    /// The hash functions.
+    pub const HASHING: Self = Self(1 << 10);
+    /// Recovering ECDSA public keys.
+    pub const ECDSA_RECOVER: Self = Self(1 << 11);
+    /// Querying the gas left.
+    pub const GAS_LEFT: Self = Self(1 << 12);
+    /// Querying the reentrance count of the executed contract.
+    pub const REENTRANCE_COUNT: Self = Self(1 << 13);
+
+    /// The name of every capability, in the order of their bits.
+    const NAMES: [(Self, &'static str); 14] = [
+        (Self::STORAGE, "storage"),
+        (Self::TRANSFER, "transfer"),
+        (Self::EVENTS, "deposit_event"),
+        (Self::DEBUG_MESSAGE, "debug_message"),
+        (Self::TERMINATE, "terminate"),
+        (Self::CALL, "call"),
+        (Self::DELEGATE_CALL, "delegate_call"),
+        (Self::INSTANTIATE, "instantiate"),
+        (Self::CALL_RUNTIME, "call_runtime"),
+        (Self::CHAIN_EXTENSION, "call_chain_extension"),
+        (Self::HASHING, "hashing"),
+        (Self::ECDSA_RECOVER, "ecdsa_recover"),
+        (Self::GAS_LEFT, "gas_left"),
+        (Self::REENTRANCE_COUNT, "reentrance_count"),
+    ];
+
+    /// Returns the empty set.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns `true` if all capabilities of `other` are in the set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the set without the capabilities of `other`.
+    pub const fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// Returns the names of the capabilities of `required` which are not in the set.
+    pub fn missing(self, required: Self) -> impl Iterator<Item = &'static str> {
+        Self::NAMES
+            .into_iter()
+            .filter(move |(capability, _)| {
+                required.contains(*capability) && !self.contains(*capability)
+            })
+            .map(|(_, name)| name)
+    }
+}
+
+impl BitOr for Capabilities {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// Returns the host functions implemented by this version of the engine.
+pub fn capabilities() -> Capabilities {
+    let capabilities = Capabilities::STORAGE
+        | Capabilities::TRANSFER
+        | Capabilities::EVENTS
+        | Capabilities::DEBUG_MESSAGE
+        | Capabilities::TERMINATE
+        | Capabilities::CALL
+        | Capabilities::DELEGATE_CALL
+        | Capabilities::INSTANTIATE
+        | Capabilities::CALL_RUNTIME
+        | Capabilities::CHAIN_EXTENSION
+        | Capabilities::HASHING
+        | Capabilities::GAS_LEFT
+        | Capabilities::REENTRANCE_COUNT;
+    if cfg!(feature = "std") {
+        capabilities | Capabilities::ECDSA_RECOVER
+    } else {
+        capabilities
+    }
+}
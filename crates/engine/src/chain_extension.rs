@@ -13,25 +13,72 @@
 // limitations under the License.
 
 use super::Error;
-use derive_more::From;
-use std::collections::{
+use crate::{
+    ext::{
+        Engine,
+        Shared,
+    },
     hash_map::Entry,
     HashMap,
 };
+use core::cell::RefCell;
+use derive_more::From;
+use ink_prelude::{
+    boxed::Box,
+    rc::Rc,
+    vec::Vec,
+};
 
 /// Chain extension registry.
 ///
 /// Allows to register chain extension methods and call them.
+#[derive(Clone)]
 pub struct ChainExtensionHandler {
     /// The currently registered runtime call handler.
-    registered: HashMap<ExtensionId, Box<dyn ChainExtension>>,
+    registered: HashMap<ExtensionId, Shared<Box<dyn ChainExtension>>>,
     /// The output buffer used and reused for chain extension method call results.
     output: Vec<u8>,
+    /// The weights of the chain extension methods by their full id.
+    weights: HashMap<u32, u64>,
+    /// The invoked chain extension methods in the order they were called.
+    calls: Vec<ChainExtensionCall>,
+    /// The chain extension methods not invoked because the gas left did not cover
+    /// their weight.
+    out_of_gas_calls: Vec<ChainExtensionCall>,
+}
+
+/// A call of a chain extension method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainExtensionCall {
+    /// The full id of the method as passed to `call_chain_extension`.
+    pub func_id: u32,
+    /// The input passed to the method.
+    pub input: Vec<u8>,
+    /// The weight charged, or to be charged, for the call.
+    pub weight: u64,
+    /// What the method returned.
+    pub outcome: ChainExtensionOutcome,
 }
 
+/// The outcome of a call of a chain extension method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainExtensionOutcome {
+    /// The method was not invoked, e.g. since the gas left did not cover its weight.
+    NotInvoked,
+    /// The method returned a status code, which the contract translates into a
+    /// result, and its output.
+    Returned { status: u32, output: Vec<u8> },
+    /// The method trapped, see [`ChainExtension::try_call_with_engine`].
+    Trapped,
+}
+
+/// Signals that a chain extension method trapped, aborting the calling contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainExtensionTrapped;
+
 /// The unique ID of the registered chain extension.
 #[derive(
-    Debug, From, scale::Encode, scale::Decode, PartialEq, Eq, PartialOrd, Ord, Hash,
+    Debug, Clone, From, scale::Encode, scale::Decode, PartialEq, Eq, PartialOrd, Ord, Hash,
 )]
 pub struct ExtensionId(u16);
 
@@ -52,6 +99,42 @@ pub trait ChainExtension {
     /// result.
     #[allow(clippy::ptr_arg)]
     fn call(&mut self, func_id: u16, input: &[u8], output: &mut Vec<u8>) -> u32;
+
+    /// Calls the chain extension with the given input and access to the engine,
+    /// e.g. to move assets via [`Engine::asset_transfer`].
+    ///
+    /// Chain extensions can not be called from within this method.
+    ///
+    /// Defaults to [`ChainExtension::call`].
+    #[allow(clippy::ptr_arg)]
+    fn call_with_engine(
+        &mut self,
+        _engine: &mut Engine,
+        func_id: u16,
+        input: &[u8],
+        output: &mut Vec<u8>,
+    ) -> u32 {
+        self.call(func_id, input, output)
+    }
+
+    /// Calls the chain extension with the given input and access to the engine, like
+    /// [`ChainExtension::call_with_engine`], but may trap instead of returning a
+    /// status code.
+    ///
+    /// Unlike a non-zero status code, which the contract handles, a trap aborts the
+    /// contract, see [`Engine::try_call_chain_extension`].
+    ///
+    /// Defaults to [`ChainExtension::call_with_engine`].
+    #[allow(clippy::ptr_arg)]
+    fn try_call_with_engine(
+        &mut self,
+        engine: &mut Engine,
+        func_id: u16,
+        input: &[u8],
+        output: &mut Vec<u8>,
+    ) -> Result<u32, ChainExtensionTrapped> {
+        Ok(self.call_with_engine(engine, func_id, input, output))
+    }
 }
 
 impl Default for ChainExtensionHandler {
@@ -68,6 +151,9 @@ impl ChainExtensionHandler {
         Self {
             registered: HashMap::new(),
             output: Vec::new(),
+            weights: HashMap::new(),
+            calls: Vec::new(),
+            out_of_gas_calls: Vec::new(),
         }
     }
 
@@ -75,18 +161,101 @@ impl ChainExtensionHandler {
     pub fn reset(&mut self) {
         self.registered.clear();
         self.output.clear();
+        self.weights.clear();
+        self.calls.clear();
+        self.out_of_gas_calls.clear();
     }
 
     /// Register a new chain extension.
     pub fn register(&mut self, extension: Box<dyn ChainExtension>) {
         let ext_id = extension.ext_id();
-        self.registered.insert(ExtensionId::from(ext_id), extension);
+        self.registered
+            .insert(ExtensionId::from(ext_id), Rc::new(RefCell::new(extension)));
+    }
+
+    /// Registers a new chain extension and declares the `weight` of its method
+    /// `func_id`.
+    ///
+    /// `func_id` is the full id as passed to `call_chain_extension`. The weight is
+    /// charged from the gas left before the method is invoked, see
+    /// [`Engine::try_call_chain_extension`]. Methods without a declared weight are
+    /// free.
+    pub fn register_with_weight(
+        &mut self,
+        func_id: u32,
+        weight: u64,
+        extension: Box<dyn ChainExtension>,
+    ) {
+        self.weights.insert(func_id, weight);
+        self.register(extension);
+    }
+
+    /// Returns the weight declared for the chain extension method `func_id`.
+    pub(crate) fn weight(&self, func_id: u32) -> u64 {
+        self.weights.get(&func_id).copied().unwrap_or_default()
+    }
+
+    /// Returns the invoked chain extension methods in the order they were called.
+    pub fn calls(&self) -> &[ChainExtensionCall] {
+        &self.calls
+    }
+
+    /// Returns the chain extension methods which were not invoked because the gas
+    /// left did not cover their weight.
+    pub fn out_of_gas_calls(&self) -> &[ChainExtensionCall] {
+        &self.out_of_gas_calls
+    }
+
+    /// Returns the status code returned by the last call of the chain extension
+    /// method `func_id`, `None` if it has not returned yet or the last call trapped.
+    pub fn last_status(&self, func_id: u32) -> Option<u32> {
+        let call = self
+            .calls
+            .iter()
+            .rev()
+            .find(|call| call.func_id == func_id)?;
+        match call.outcome {
+            ChainExtensionOutcome::Returned { status, .. } => Some(status),
+            _ => None,
+        }
+    }
+
+    /// Records the invocation of the chain extension method `func_id`.
+    pub(crate) fn record_call(
+        &mut self,
+        func_id: u32,
+        input: &[u8],
+        weight: u64,
+        outcome: ChainExtensionOutcome,
+    ) {
+        self.calls.push(ChainExtensionCall {
+            func_id,
+            input: input.to_vec(),
+            weight,
+            outcome,
+        });
+    }
+
+    /// Records a call of the chain extension method `func_id` which ran out of gas.
+    pub(crate) fn record_out_of_gas(&mut self, func_id: u32, input: &[u8], weight: u64) {
+        self.out_of_gas_calls.push(ChainExtensionCall {
+            func_id,
+            input: input.to_vec(),
+            weight,
+            outcome: ChainExtensionOutcome::NotInvoked,
+        });
     }
 
     /// Evaluates the chain extension with the given parameters.
     ///
-    /// Upon success returns the values returned by the evaluated chain extension.
-    pub fn eval(&mut self, id: u32, input: &[u8]) -> Result<(u32, &[u8]), Error> {
+    /// Upon success returns the values returned by the evaluated chain extension, or
+    /// that it trapped.
+    pub fn eval(
+        &mut self,
+        engine: &mut Engine,
+        id: u32,
+        input: &[u8],
+    ) -> Result<Result<(u32, &[u8]), ChainExtensionTrapped>, Error> {
         self.output.clear();
 
         let func_id = (id & 0x0000FFFF) as u16;
@@ -95,9 +264,13 @@ impl ChainExtensionHandler {
         let extension_id = ExtensionId::from(ext_id);
         match self.registered.entry(extension_id) {
             Entry::Occupied(occupied) => {
-                let status_code =
-                    occupied.into_mut().call(func_id, input, &mut self.output);
-                Ok((status_code, &mut self.output))
+                let status_code = occupied.get().borrow_mut().try_call_with_engine(
+                    engine,
+                    func_id,
+                    input,
+                    &mut self.output,
+                );
+                Ok(status_code.map(|status_code| (status_code, &self.output[..])))
             }
             Entry::Vacant(_vacant) => Err(Error::UnregisteredChainExtension),
         }
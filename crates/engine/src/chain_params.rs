@@ -0,0 +1,164 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A registry of chain parameters which are not modeled by the engine otherwise.
+//!
+//! Parameters are stored SCALE encoded under their name, see
+//! [`Engine::set_chain_param`]. Chain extensions and hooks can consult them via
+//! [`Engine::get_chain_param`] to mirror the configuration of the simulated chain.
+//!
+//! The engine itself consults the parameters in [`RECOGNIZED_CHAIN_PARAMS`]:
+//!
+//! - [`STORAGE_DEPOSIT_LIMIT`]: the maximum sum of storage deposits reserved from the
+//!   balance of a single depositor, encoded as `Balance`. Storage writes exceeding it
+//!   fail with [`Error::StorageDepositLimitExhausted`]. Unlimited if not set.
+//!
+//! [`Error::StorageDepositLimitExhausted`]: crate::ext::Error::StorageDepositLimitExhausted
+
+use crate::ext::Engine;
+use core::fmt;
+use ink_prelude::{
+    collections::BTreeMap,
+    string::{
+        String,
+        ToString,
+    },
+    vec::Vec,
+};
+
+/// The maximum sum of storage deposits reserved from a single depositor, encoded
+/// as `Balance`.
+pub const STORAGE_DEPOSIT_LIMIT: &str = "storage_deposit_limit";
+
+/// The names of the chain parameters consulted by the engine.
+pub const RECOGNIZED_CHAIN_PARAMS: &[&str] = &[STORAGE_DEPOSIT_LIMIT];
+
+/// A chain parameter could not be read, see [`Engine::get_chain_param`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainParamError {
+    /// No value has been set for the parameter.
+    Unset {
+        /// The name of the parameter.
+        name: String,
+    },
+    /// The value of the parameter could not be decoded as the requested type.
+    Decoding {
+        /// The name of the parameter.
+        name: String,
+    },
+}
+
+impl fmt::Display for ChainParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unset { name } => {
+                write!(
+                    f,
+                    "chain parameter `{name}` has not been set, see \
+                     `Engine::set_chain_param`"
+                )?;
+                if !RECOGNIZED_CHAIN_PARAMS.contains(&name.as_str()) {
+                    write!(
+                        f,
+                        ", the engine recognizes: {}",
+                        RECOGNIZED_CHAIN_PARAMS.join(", ")
+                    )?;
+                }
+                Ok(())
+            }
+            Self::Decoding { name } => {
+                write!(
+                    f,
+                    "the value of chain parameter `{name}` could not be decoded"
+                )
+            }
+        }
+    }
+}
+
+/// The chain parameters set for the engine.
+#[derive(Clone, Default)]
+pub(crate) struct ChainParams {
+    /// The encoded value of every set parameter, by name.
+    params: BTreeMap<String, Vec<u8>>,
+}
+
+impl ChainParams {
+    /// Removes all parameters.
+    pub(crate) fn reset(&mut self) {
+        self.params.clear();
+    }
+}
+
+impl Engine {
+    /// Sets the chain parameter `name` to the SCALE `encoded` value, replacing any
+    /// value set before.
+    ///
+    /// See [`RECOGNIZED_CHAIN_PARAMS`] for the parameters consulted by the engine,
+    /// other names are only visible to chain extensions and hooks.
+    pub fn set_chain_param(&mut self, name: &str, encoded: Vec<u8>) {
+        self.chain_params.params.insert(name.to_string(), encoded);
+    }
+
+    /// Returns the encoded value of the chain parameter `name`.
+    ///
+    /// # Errors
+    ///
+    /// If the parameter has not been set.
+    pub fn get_chain_param(&self, name: &str) -> Result<&[u8], ChainParamError> {
+        self.chain_params
+            .params
+            .get(name)
+            .map(Vec::as_slice)
+            .ok_or_else(|| {
+                ChainParamError::Unset {
+                    name: name.to_string(),
+                }
+            })
+    }
+
+    /// Returns the value of the chain parameter `name` decoded as `T`.
+    ///
+    /// # Errors
+    ///
+    /// If the parameter has not been set or its value does not decode as `T`.
+    pub fn decode_chain_param<T: scale::Decode>(
+        &self,
+        name: &str,
+    ) -> Result<T, ChainParamError> {
+        let mut encoded = self.get_chain_param(name)?;
+        T::decode(&mut encoded).map_err(|_| {
+            ChainParamError::Decoding {
+                name: name.to_string(),
+            }
+        })
+    }
+
+    /// Returns the value of a parameter in [`RECOGNIZED_CHAIN_PARAMS`], `None` if it
+    /// has not been set.
+    ///
+    /// # Panics
+    ///
+    /// If the value does not decode as `T`.
+    pub(crate) fn recognized_chain_param<T: scale::Decode>(
+        &self,
+        name: &str,
+    ) -> Option<T> {
+        match self.decode_chain_param(name) {
+            Ok(value) => Some(value),
+            Err(ChainParamError::Unset { .. }) => None,
+            Err(error) => panic!("{error}"),
+        }
+    }
+}
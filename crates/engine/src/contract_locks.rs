@@ -0,0 +1,92 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Locks preventing the termination of contracts.
+//!
+//! On-chain a contract cannot be removed while e.g. its code is still referenced
+//! or it holds deposits which cannot be refunded. The engine models such conditions
+//! as locks: [`Engine::terminate`] fails with [`Error::TerminationDenied`] while the
+//! contract holds any lock.
+//!
+//! [`Error::TerminationDenied`]: crate::ext::Error::TerminationDenied
+
+use crate::ext::Engine;
+use ink_prelude::{
+    collections::BTreeMap,
+    string::{
+        String,
+        ToString,
+    },
+    vec::Vec,
+};
+
+/// The outstanding locks of the contracts, by contract.
+#[derive(Clone, Default)]
+pub(crate) struct ContractLocks {
+    /// The reasons of the outstanding locks of every locked contract.
+    locks: BTreeMap<Vec<u8>, Vec<String>>,
+}
+
+impl ContractLocks {
+    /// Removes all locks.
+    pub(crate) fn reset(&mut self) {
+        self.locks.clear();
+    }
+}
+
+impl Engine {
+    /// Adds a lock for `reason` to the contract `account_id`, preventing its
+    /// termination until all of its locks are removed.
+    ///
+    /// A contract can hold several locks for the same reason.
+    pub fn lock_contract(&mut self, account_id: &[u8], reason: &str) {
+        self.contract_locks
+            .locks
+            .entry(account_id.to_vec())
+            .or_default()
+            .push(reason.to_string());
+    }
+
+    /// Removes one lock for `reason` from the contract `account_id`.
+    ///
+    /// Returns `false` if the contract holds no lock for `reason`.
+    pub fn unlock_contract(&mut self, account_id: &[u8], reason: &str) -> bool {
+        let Some(reasons) = self.contract_locks.locks.get_mut(account_id) else {
+            return false
+        };
+        let Some(index) = reasons.iter().position(|locked| locked == reason) else {
+            return false
+        };
+        reasons.remove(index);
+        if reasons.is_empty() {
+            self.contract_locks.locks.remove(account_id);
+        }
+        true
+    }
+
+    /// Returns the reasons of the outstanding locks of the contract `account_id`, in
+    /// the order they were added.
+    pub fn contract_locks(&self, account_id: &[u8]) -> Vec<String> {
+        self.contract_locks
+            .locks
+            .get(account_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if the contract `account_id` holds any lock.
+    pub(crate) fn is_contract_locked(&self, account_id: &[u8]) -> bool {
+        self.contract_locks.locks.contains_key(account_id)
+    }
+}
@@ -12,14 +12,25 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::types::Balance;
+use crate::types::{
+    AssetId,
+    Balance,
+};
+use ink_prelude::{
+    collections::BTreeMap,
+    vec::Vec,
+};
 use scale::KeyedVec;
-use std::collections::HashMap;
 
 const BALANCE_OF: &[u8] = b"balance:";
 const STORAGE_OF: &[u8] = b"contract-storage:";
 
-/// Returns the database key under which to find the balance for account `who`.
+/// Returns the database key under which the balance for account `who` was stored
+/// before balances were kept apart from the contract storage.
+///
+/// Only used to migrate states of version 1, see
+/// [`crate::state::EngineState::migrate_v1_to_v2`], and removed together with the
+/// support for them.
 pub fn balance_of_key(who: &[u8]) -> [u8; 32] {
     let keyed = who.to_vec().to_keyed_vec(BALANCE_OF);
     let mut hashed_key: [u8; 32] = [0; 32];
@@ -35,32 +46,80 @@ pub fn storage_of_contract_key(who: &[u8], key: &[u8]) -> [u8; 32] {
     hashed_key
 }
 
+/// The estimated memory used by an entry of the database in addition to the bytes
+/// of its key and value, i.e. the overhead of the map and of the two vectors.
+const ENTRY_OVERHEAD: usize = 2 * core::mem::size_of::<Vec<u8>>();
+
+/// Returns the estimated memory used by an entry with a key and a value of the
+/// given lengths.
+fn entry_size(key_len: usize, value_len: usize) -> usize {
+    key_len + value_len + ENTRY_OVERHEAD
+}
+
 /// The chain database.
 ///
 /// Everything is stored in here: accounts, balances, contract storage, etc..
-/// Just like in Substrate a prefix hash is computed for every contract.
-#[derive(Default)]
+/// Just like in Substrate a prefix hash is computed for every contract. Balances are
+/// kept apart, by account.
+///
+/// Entries are ordered by their key bytes, which makes iterating over them
+/// (e.g. in the `Debug` output) deterministic.
+#[derive(Debug, Clone, Default)]
 pub struct Database {
-    hmap: HashMap<Vec<u8>, Vec<u8>>,
+    map: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// The balances of the native asset, per account.
+    balances: BTreeMap<Vec<u8>, Balance>,
+    /// The balances of assets other than the native one, per account.
+    assets: BTreeMap<Vec<u8>, BTreeMap<AssetId, Balance>>,
+    /// The estimated memory used by the entries, see
+    /// [`Database::approximate_memory_usage`].
+    usage: usize,
 }
 
 impl Database {
     /// Creates a new database instance.
     pub fn new() -> Self {
         Database {
-            hmap: HashMap::new(),
+            map: BTreeMap::new(),
+            balances: BTreeMap::new(),
+            assets: BTreeMap::new(),
+            usage: 0,
         }
     }
 
+    /// Returns the estimated number of bytes used by the entries of the database.
+    ///
+    /// The estimate is the sum of the lengths of all keys and values plus a fixed
+    /// overhead per entry.
+    pub fn approximate_memory_usage(&self) -> usize {
+        self.usage
+    }
+
+    /// Returns the estimated memory usage after inserting a value of `value_len`
+    /// bytes into the contract storage of `account_id` at storage key `key`.
+    pub(crate) fn memory_usage_after_insert(
+        &self,
+        account_id: &[u8],
+        key: &[u8],
+        value_len: usize,
+    ) -> usize {
+        let hashed_key = storage_of_contract_key(account_id, key);
+        let previous = self
+            .map
+            .get(&hashed_key[..])
+            .map_or(0, |value| entry_size(hashed_key.len(), value.len()));
+        self.usage - previous + entry_size(hashed_key.len(), value_len)
+    }
+
     /// Returns the amount of entries in the database.
     #[cfg(test)]
     fn len(&self) -> usize {
-        self.hmap.len()
+        self.map.len()
     }
 
     /// Returns a reference to the value corresponding to the key.
-    fn get(&self, key: &[u8]) -> Option<&Vec<u8>> {
-        self.hmap.get(key)
+    pub(crate) fn get(&self, key: &[u8]) -> Option<&Vec<u8>> {
+        self.map.get(key)
     }
 
     /// Returns a reference to the value corresponding to the key.
@@ -70,7 +129,7 @@ impl Database {
         key: &[u8],
     ) -> Option<&Vec<u8>> {
         let hashed_key = storage_of_contract_key(account_id, key);
-        self.hmap.get(&hashed_key.to_vec())
+        self.map.get(&hashed_key.to_vec())
     }
 
     /// Inserts `value` into the contract storage of `account_id` at storage key `key`.
@@ -81,7 +140,36 @@ impl Database {
         value: Vec<u8>,
     ) -> Option<Vec<u8>> {
         let hashed_key = storage_of_contract_key(account_id, key);
-        self.hmap.insert(hashed_key.to_vec(), value)
+        self.insert(hashed_key.to_vec(), value)
+    }
+
+    /// Inserts the `entries` of storage keys and values into the contract storage of
+    /// `account_id`, overwriting existing values.
+    ///
+    /// The entries are sorted and merged into the database at once, which is
+    /// considerably faster than inserting large amounts of entries one by one.
+    pub fn extend_contract_storage<I>(&mut self, account_id: &[u8], entries: I)
+    where
+        I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    {
+        let mut hashed: Vec<_> = entries
+            .into_iter()
+            .map(|(key, value)| (storage_of_contract_key(account_id, &key), value))
+            .collect();
+        // Sorting the fixed size keys first is faster than building the map from
+        // unsorted keys. The sort is stable, so later entries for a key win.
+        hashed.sort_by_key(|(key, _)| *key);
+        let mut batch: BTreeMap<Vec<u8>, Vec<u8>> = hashed
+            .into_iter()
+            .map(|(key, value)| (key.to_vec(), value))
+            .collect();
+        for (key, value) in &batch {
+            if let Some(previous) = self.map.get(key) {
+                self.usage -= entry_size(key.len(), previous.len());
+            }
+            self.usage += entry_size(key.len(), value.len());
+        }
+        self.map.append(&mut batch);
     }
 
     /// Removes the value at the contract storage of `account_id` at storage key `key`.
@@ -91,42 +179,87 @@ impl Database {
         key: &[u8],
     ) -> Option<Vec<u8>> {
         let hashed_key = storage_of_contract_key(account_id, key);
-        self.hmap.remove(&hashed_key.to_vec())
+        self.remove(&hashed_key)
     }
 
     /// Removes a key from the storage, returning the value at the key if the key
     /// was previously in storage.
     pub fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
-        self.hmap.remove(key)
+        let removed = self.map.remove(key);
+        if let Some(value) = &removed {
+            self.usage -= entry_size(key.len(), value.len());
+        }
+        removed
     }
 
     /// Sets the value of the entry, and returns the entry's old value.
     pub fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) -> Option<Vec<u8>> {
-        self.hmap.insert(key, value)
+        let size = entry_size(key.len(), value.len());
+        let key_len = key.len();
+        let previous = self.map.insert(key, value);
+        if let Some(value) = &previous {
+            self.usage -= entry_size(key_len, value.len());
+        }
+        self.usage += size;
+        previous
     }
 
     /// Clears the database, removing all key-value pairs.
+    ///
+    /// The memory of the entries is released.
     pub fn clear(&mut self) {
-        self.hmap.clear();
+        self.map.clear();
+        self.balances.clear();
+        self.assets.clear();
+        self.usage = 0;
+    }
+
+    /// Returns the accounts which have been assigned a balance, ordered by their
+    /// bytes.
+    pub fn accounts(&self) -> impl Iterator<Item = &[u8]> {
+        self.balances.keys().map(Vec::as_slice)
     }
 
     /// Returns the balance of `account_id`, if available.
     pub fn get_balance(&self, account_id: &[u8]) -> Option<Balance> {
-        let hashed_key = balance_of_key(account_id);
-        self.get(&hashed_key).map(|encoded_balance| {
-            scale::Decode::decode(&mut &encoded_balance[..])
-                .expect("unable to decode balance from database")
-        })
+        self.balances.get(account_id).copied()
     }
 
     /// Sets the balance of `account_id` to `new_balance`.
     pub fn set_balance(&mut self, account_id: &[u8], new_balance: Balance) {
-        let hashed_key = balance_of_key(account_id);
-        let encoded_balance = scale::Encode::encode(&new_balance);
-        self.hmap
-            .entry(hashed_key.to_vec())
-            .and_modify(|v| *v = encoded_balance.clone())
-            .or_insert(encoded_balance);
+        if self
+            .balances
+            .insert(account_id.to_vec(), new_balance)
+            .is_none()
+        {
+            self.usage += entry_size(account_id.len(), core::mem::size_of::<Balance>());
+        }
+    }
+
+    /// Returns the balance of `asset` held by `account_id`, if available.
+    pub fn get_asset_balance(
+        &self,
+        account_id: &[u8],
+        asset: AssetId,
+    ) -> Option<Balance> {
+        self.assets.get(account_id)?.get(&asset).copied()
+    }
+
+    /// Sets the balance of `asset` held by `account_id` to `new_balance`.
+    pub fn set_asset_balance(
+        &mut self,
+        account_id: &[u8],
+        asset: AssetId,
+        new_balance: Balance,
+    ) {
+        let assets = self.assets.entry(account_id.to_vec()).or_default();
+        let new_account = assets.is_empty();
+        if assets.insert(asset, new_balance).is_none() {
+            self.usage += core::mem::size_of::<(AssetId, Balance)>();
+            if new_account {
+                self.usage += entry_size(account_id.len(), 0);
+            }
+        }
     }
 }
 
@@ -0,0 +1,232 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{
+    types::encode_hex,
+    HashMap,
+};
+use core::fmt;
+use ink_prelude::{
+    collections::{
+        btree_map,
+        BTreeMap,
+    },
+    vec::Vec,
+};
+
+/// The entry points of a contract.
+///
+/// These are the functions which are exported as `deploy` and `call` by a contract
+/// compiled for the on-chain environment. They read their input via `input` and
+/// terminate via `return_value`.
+#[derive(Debug, Clone, Copy)]
+pub struct EntryPoints {
+    /// Dispatches the input to the constructors of the contract.
+    pub deploy: fn(),
+    /// Dispatches the input to the messages of the contract.
+    pub call: fn(),
+}
+
+/// The value a contract returned from its execution via `return_value`.
+///
+/// The engine unwinds with this value as payload, it can be retrieved by
+/// catching the unwind around the invoked entry point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReturnValue {
+    /// The flags the contract returned with.
+    pub flags: u32,
+    /// The SCALE encoded data the contract returned.
+    pub data: Vec<u8>,
+}
+
+/// A handler for a single message of a mocked contract was already registered for
+/// the selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateSelector {
+    /// The account of the mocked contract.
+    pub account_id: Vec<u8>,
+    /// The selector the handler was registered for.
+    pub selector: [u8; 4],
+}
+
+/// No handler of a mocked contract is registered for the selector of a call, and
+/// the contract has neither a fallback handler nor entry points.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct UnknownSelector {
+    /// The account of the mocked contract.
+    pub account_id: Vec<u8>,
+    /// The selector of the call.
+    pub selector: [u8; 4],
+    /// The selectors with a registered handler in ascending order.
+    pub registered: Vec<[u8; 4]>,
+}
+
+impl fmt::Display for UnknownSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown selector {} of the mocked contract at {}, registered selectors: ",
+            encode_hex(&self.selector),
+            encode_hex(&self.account_id)
+        )?;
+        if self.registered.is_empty() {
+            return f.write_str("none")
+        }
+        for (n, selector) in self.registered.iter().enumerate() {
+            let separator = if n == 0 { "" } else { ", " };
+            write!(f, "{separator}{}", encode_hex(selector))?;
+        }
+        Ok(())
+    }
+}
+
+/// The message handlers of a mocked contract.
+#[derive(Clone, Default)]
+struct MessageHandlers {
+    /// The handlers by the selector of their message.
+    by_selector: BTreeMap<[u8; 4], fn()>,
+    /// The handler for selectors without a registered handler.
+    fallback: Option<fn()>,
+}
+
+/// Registry for the entry points of contracts.
+///
+/// Allows to dispatch calls to contracts by their account.
+#[derive(Clone)]
+pub struct DispatchHandler {
+    /// The entry points of the registered contracts.
+    registered: HashMap<Vec<u8>, EntryPoints>,
+    /// The message handlers of the mocked contracts.
+    messages: HashMap<Vec<u8>, MessageHandlers>,
+    /// The entry points of the registered code by code hash, `None` for uploaded
+    /// code without entry points.
+    codes: HashMap<Vec<u8>, Option<EntryPoints>>,
+}
+
+impl Default for DispatchHandler {
+    fn default() -> Self {
+        DispatchHandler::new()
+    }
+}
+
+impl DispatchHandler {
+    /// Creates a new dispatch handler.
+    ///
+    /// Initialized without any registered contracts.
+    pub fn new() -> Self {
+        Self {
+            registered: HashMap::new(),
+            messages: HashMap::new(),
+            codes: HashMap::new(),
+        }
+    }
+
+    /// Resets the dispatch handler to uninitialized state.
+    pub fn reset(&mut self) {
+        self.registered.clear();
+        self.messages.clear();
+        self.codes.clear();
+    }
+
+    /// Registers the entry points of the contract at `account_id`.
+    ///
+    /// Replaces the entry points of a contract previously registered there.
+    pub fn register(&mut self, account_id: Vec<u8>, entry_points: EntryPoints) {
+        self.registered.insert(account_id, entry_points);
+    }
+
+    /// Returns the entry points of the contract at `account_id`, if any.
+    pub fn entry_points(&self, account_id: &[u8]) -> Option<EntryPoints> {
+        self.registered.get(account_id).copied()
+    }
+
+    /// Registers the entry points of the code with `code_hash`.
+    ///
+    /// Replaces the entry points of code previously registered there.
+    pub fn register_code(&mut self, code_hash: Vec<u8>, entry_points: EntryPoints) {
+        self.codes.insert(code_hash, Some(entry_points));
+    }
+
+    /// Registers the code with `code_hash` without entry points, unless it is
+    /// registered already.
+    pub fn register_code_hash(&mut self, code_hash: Vec<u8>) {
+        self.codes.entry(code_hash).or_insert(None);
+    }
+
+    /// Returns `true` if code is registered under `code_hash`.
+    pub fn code_exists(&self, code_hash: &[u8]) -> bool {
+        self.codes.contains_key(code_hash)
+    }
+
+    /// Returns the entry points of the code with `code_hash`, if any.
+    pub fn code(&self, code_hash: &[u8]) -> Option<EntryPoints> {
+        self.codes.get(code_hash).copied().flatten()
+    }
+
+    /// Registers `handler` for the message with `selector` of the mocked contract at
+    /// `account_id`.
+    ///
+    /// # Errors
+    ///
+    /// If a handler was already registered for `selector`. The registered handler
+    /// is kept in this case.
+    pub fn register_message(
+        &mut self,
+        account_id: Vec<u8>,
+        selector: [u8; 4],
+        handler: fn(),
+    ) -> Result<(), DuplicateSelector> {
+        let handlers = self.messages.entry(account_id.clone()).or_default();
+        match handlers.by_selector.entry(selector) {
+            btree_map::Entry::Occupied(_) => {
+                Err(DuplicateSelector {
+                    account_id,
+                    selector,
+                })
+            }
+            btree_map::Entry::Vacant(vacant) => {
+                vacant.insert(handler);
+                Ok(())
+            }
+        }
+    }
+
+    /// Registers `handler` for all messages of the mocked contract at `account_id`
+    /// without a handler registered for their selector.
+    ///
+    /// Replaces a fallback handler previously registered there.
+    pub fn register_fallback_message(&mut self, account_id: Vec<u8>, handler: fn()) {
+        self.messages.entry(account_id).or_default().fallback = Some(handler);
+    }
+
+    /// Returns the handler for the message with `selector` of the mocked contract at
+    /// `account_id`, falling back to its fallback handler, if any.
+    pub fn message_handler(&self, account_id: &[u8], selector: [u8; 4]) -> Option<fn()> {
+        let handlers = self.messages.get(account_id)?;
+        handlers
+            .by_selector
+            .get(&selector)
+            .copied()
+            .or(handlers.fallback)
+    }
+
+    /// Returns the selectors with a registered handler of the mocked contract at
+    /// `account_id` in ascending order.
+    pub fn registered_selectors(&self, account_id: &[u8]) -> Vec<[u8; 4]> {
+        self.messages
+            .get(account_id)
+            .map(|handlers| handlers.by_selector.keys().copied().collect())
+            .unwrap_or_default()
+    }
+}
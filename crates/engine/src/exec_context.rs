@@ -12,16 +12,43 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+// The fields of `ExecContext` are deprecated for users of the crate only.
+#![allow(deprecated)]
+
 use super::types::{
     AccountId,
     Balance,
     BlockNumber,
     BlockTimestamp,
 };
+use ink_prelude::{
+    collections::BTreeSet,
+    vec::Vec,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// The origin of a top-level execution, i.e. who dispatched the transaction.
+#[derive(
+    Debug, Clone, PartialEq, Eq, Serialize, Deserialize, scale::Encode, scale::Decode,
+)]
+pub enum Origin {
+    /// The transaction was signed by the account.
+    Signed(Vec<u8>),
+    /// The transaction was dispatched by root, e.g. by governance.
+    Root,
+    /// The transaction was not signed.
+    None,
+}
 
 /// The context of a contract execution.
+///
+/// The context is read and changed via its methods, [`ExecContext::snapshot`]
+/// captures it for assertions.
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct ExecContext {
     /// The caller of the contract execution. Might be user or another contract.
     ///
@@ -29,6 +56,7 @@ pub struct ExecContext {
     /// type of default `AccountId` makes sense ‒ they are left to be initialized
     /// by the crate which uses the `engine`. Methods which require a caller might
     /// panic when it has not been set.
+    #[deprecated(note = "use `ExecContext::caller` and `ExecContext::set_caller`")]
     pub caller: Option<AccountId>,
     /// The callee of the contract execution. Might be user or another contract.
     ///
@@ -36,15 +64,72 @@ pub struct ExecContext {
     /// type of default `AccountId` makes sense ‒ they are left to be initialized
     /// by the crate which uses the `engine`. Methods which require a callee might
     /// panic when it has not been set.
+    #[deprecated(note = "use `ExecContext::callee` and `ExecContext::set_callee`")]
     pub callee: Option<AccountId>,
     /// The value transferred to the contract as part of the call.
+    #[deprecated(
+        note = "use `ExecContext::value_transferred` and `ExecContext::set_value_transferred`"
+    )]
     pub value_transferred: Balance,
     /// The current block number.
+    #[deprecated(
+        note = "use `ExecContext::block_number` and `ExecContext::set_block_number`"
+    )]
     pub block_number: BlockNumber,
     /// The current block timestamp.
+    #[deprecated(
+        note = "use `ExecContext::timestamp` and `ExecContext::set_block_timestamp`"
+    )]
     pub block_timestamp: BlockTimestamp,
     /// Known contract accounts
+    #[deprecated(note = "use `ExecContext::contracts` and `ExecContext::add_contract`")]
+    pub contracts: Vec<Vec<u8>>,
+    /// The SCALE encoded input of the contract execution.
+    #[deprecated(note = "use `ExecContext::input` and `ExecContext::set_input`")]
+    pub input: Vec<u8>,
+    /// The execution contexts of the enclosing contract executions, innermost last.
+    pub(crate) frames: Vec<Frame>,
+    /// The properties which have been set explicitly, e.g. `"caller"`.
+    pub(crate) initialized: BTreeSet<&'static str>,
+    /// The origin of the top-level execution if it is not signed, see
+    /// [`ExecContext::set_origin`].
+    pub(crate) unsigned_origin: Option<Origin>,
+}
+
+/// The parts of an execution context which are replaced by a nested execution.
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+#[derive(Clone)]
+pub(crate) struct Frame {
+    caller: Option<AccountId>,
+    callee: Option<AccountId>,
+    input: Vec<u8>,
+    /// Whether the frame was pushed by [`ExecContext::push_caller_frame`].
+    synthetic: bool,
+    /// Whether the frame was pushed by [`ExecContext::push_delegate_frame`].
+    delegate: bool,
+}
+
+/// The state of an [`ExecContext`] at a point in time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecContextSnapshot {
+    /// The caller of the contract execution, if any.
+    pub caller: Option<Vec<u8>>,
+    /// The callee of the contract execution, if any.
+    pub callee: Option<Vec<u8>>,
+    /// The value transferred to the contract as part of the call.
+    pub value_transferred: Balance,
+    /// The current block number.
+    pub block_number: BlockNumber,
+    /// The current block timestamp.
+    pub block_timestamp: BlockTimestamp,
+    /// The known contract accounts.
     pub contracts: Vec<Vec<u8>>,
+    /// The SCALE encoded input of the contract execution.
+    pub input: Vec<u8>,
+    /// The number of enclosing contract executions.
+    pub depth: usize,
+    /// The origin of the top-level execution, see [`ExecContext::runtime_origin`].
+    pub origin: Option<Origin>,
 }
 
 impl ExecContext {
@@ -53,13 +138,259 @@ impl ExecContext {
         Default::default()
     }
 
-    /// Returns the callee.
-    pub fn callee(&self) -> Vec<u8> {
-        self.callee
-            .as_ref()
-            .expect("no callee has been set")
-            .as_bytes()
-            .into()
+    /// Returns the caller, if any.
+    pub fn caller(&self) -> Option<&AccountId> {
+        self.caller.as_ref()
+    }
+
+    /// Returns the callee, if any.
+    pub fn callee(&self) -> Option<&AccountId> {
+        self.callee.as_ref()
+    }
+
+    /// Returns the value transferred to the contract as part of the call.
+    pub fn value_transferred(&self) -> Balance {
+        self.value_transferred
+    }
+
+    /// Returns the current block number.
+    pub fn block_number(&self) -> BlockNumber {
+        self.block_number
+    }
+
+    /// Returns the current block timestamp.
+    pub fn timestamp(&self) -> BlockTimestamp {
+        self.block_timestamp
+    }
+
+    /// Returns the known contract accounts in the order they became known.
+    pub fn contracts(&self) -> &[Vec<u8>] {
+        &self.contracts
+    }
+
+    /// Returns the SCALE encoded input of the contract execution.
+    pub fn input(&self) -> &[u8] {
+        &self.input
+    }
+
+    /// Sets the caller.
+    ///
+    /// Outside of any execution this makes the origin signed by `caller`.
+    pub fn set_caller(&mut self, caller: Option<AccountId>) {
+        if self.frames.is_empty() {
+            self.unsigned_origin = None;
+        }
+        self.caller = caller;
+        self.mark_initialized("caller");
+    }
+
+    /// Sets the origin of the next top-level execution.
+    ///
+    /// A signed origin is the caller of the top-level execution, like set via
+    /// [`ExecContext::set_caller`]. Executions dispatched by root or without origin
+    /// have no caller, reading it fails with [`crate::Error::UnsignedOrigin`].
+    /// Executions called by them have the calling contract as caller.
+    pub fn set_origin(&mut self, origin: Origin) {
+        match origin {
+            Origin::Signed(account_id) => self.set_caller(Some(account_id.into())),
+            unsigned => {
+                self.caller = None;
+                self.unsigned_origin = Some(unsigned);
+                self.mark_initialized("caller");
+            }
+        }
+    }
+
+    /// Returns the origin of the top-level execution.
+    ///
+    /// Returns `None` if the origin is signed but no caller has been set.
+    pub fn runtime_origin(&self) -> Option<Origin> {
+        match &self.unsigned_origin {
+            Some(unsigned) => Some(unsigned.clone()),
+            None => {
+                self.origin()
+                    .map(|origin| Origin::Signed(origin.as_bytes().to_vec()))
+            }
+        }
+    }
+
+    /// Sets the callee.
+    pub fn set_callee(&mut self, callee: Option<AccountId>) {
+        self.callee = callee;
+        self.mark_initialized("callee");
+    }
+
+    /// Sets the value transferred to the contract as part of the call.
+    pub fn set_value_transferred(&mut self, value: Balance) {
+        self.value_transferred = value;
+        self.mark_initialized("value_transferred");
+    }
+
+    /// Makes `account_id` a known contract account.
+    pub fn add_contract(&mut self, account_id: Vec<u8>) {
+        self.contracts.push(account_id);
+    }
+
+    /// Removes `account_id` from the known contract accounts.
+    pub(crate) fn remove_contract(&mut self, account_id: &[u8]) {
+        self.contracts.retain(|contract| contract != account_id);
+    }
+
+    /// Sets the SCALE encoded input of the contract execution.
+    pub fn set_input(&mut self, input: Vec<u8>) {
+        self.input = input;
+    }
+
+    /// Returns the current state of the execution context.
+    pub fn snapshot(&self) -> ExecContextSnapshot {
+        ExecContextSnapshot {
+            caller: self
+                .caller
+                .as_ref()
+                .map(|caller| caller.as_bytes().to_vec()),
+            callee: self
+                .callee
+                .as_ref()
+                .map(|callee| callee.as_bytes().to_vec()),
+            value_transferred: self.value_transferred,
+            block_number: self.block_number,
+            block_timestamp: self.block_timestamp,
+            contracts: self.contracts.clone(),
+            input: self.input.clone(),
+            depth: self.depth(),
+            origin: self.runtime_origin(),
+        }
+    }
+
+    /// Returns the caller of the top-level execution, i.e. the current caller outside
+    /// of any execution.
+    pub(crate) fn origin(&self) -> Option<&AccountId> {
+        self.frames
+            .first()
+            .map_or(self.caller.as_ref(), |frame| frame.caller.as_ref())
+    }
+
+    /// Returns the number of entered executions, `0` outside of any execution.
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns the number of times the current callee was called by the executions
+    /// enclosing the current one, `0` without reentrancy.
+    ///
+    /// Delegated and synthetic executions do not count, they run in the context of
+    /// the contract which entered them.
+    pub fn reentrance_count(&self) -> u32 {
+        let Some(current) = self.callee.as_ref() else {
+            return 0
+        };
+        // Every frame saves the callee of the enclosing execution, hence the callee
+        // of the execution entered by a frame is saved by the next one.
+        let entered = self
+            .frames
+            .iter()
+            .skip(1)
+            .map(|frame| frame.callee.as_ref())
+            .chain(Some(Some(current)));
+        let calls = self
+            .frames
+            .iter()
+            .zip(entered)
+            .filter(|(frame, callee)| {
+                !frame.synthetic && !frame.delegate && *callee == Some(current)
+            })
+            .count();
+        calls.saturating_sub(1) as u32
+    }
+
+    /// Enters the execution of `callee` with `input`.
+    ///
+    /// The caller of a nested execution is the callee of the enclosing one,
+    /// the caller of a top-level execution remains unchanged.
+    pub(crate) fn push_frame(&mut self, callee: AccountId, input: Vec<u8>) {
+        let caller = if self.frames.is_empty() {
+            self.caller.clone()
+        } else {
+            self.callee.clone()
+        };
+        let frame = Frame {
+            caller: core::mem::replace(&mut self.caller, caller),
+            callee: self.callee.replace(callee),
+            input: core::mem::replace(&mut self.input, input),
+            synthetic: false,
+            delegate: false,
+        };
+        self.frames.push(frame);
+        self.mark_initialized("caller");
+        self.mark_initialized("callee");
+    }
+
+    /// Enters the execution of delegated code with `input`, if any.
+    ///
+    /// The caller and callee remain unchanged, the input is kept if `input` is `None`.
+    pub(crate) fn push_delegate_frame(&mut self, input: Option<Vec<u8>>) {
+        let input = input.unwrap_or_else(|| self.input.clone());
+        let frame = Frame {
+            caller: self.caller.clone(),
+            callee: self.callee.clone(),
+            input: core::mem::replace(&mut self.input, input),
+            synthetic: false,
+            delegate: true,
+        };
+        self.frames.push(frame);
+    }
+
+    /// Enters a synthetic execution of the current callee called by `caller`.
+    ///
+    /// The current caller becomes the caller of the enclosing execution, i.e. the
+    /// origin if no execution has been entered. The callee and input remain
+    /// unchanged.
+    pub(crate) fn push_caller_frame(&mut self, caller: AccountId) {
+        let frame = Frame {
+            caller: self.caller.replace(caller),
+            callee: self.callee.clone(),
+            input: self.input.clone(),
+            synthetic: true,
+            delegate: false,
+        };
+        self.frames.push(frame);
+        self.mark_initialized("caller");
+    }
+
+    /// Exits the synthetic execution entered by [`ExecContext::push_caller_frame`].
+    ///
+    /// # Panics
+    ///
+    /// If the current execution was not entered by
+    /// [`ExecContext::push_caller_frame`].
+    pub(crate) fn pop_caller_frame(&mut self) {
+        assert!(
+            matches!(self.frames.last(), Some(frame) if frame.synthetic),
+            "no caller frame has been pushed"
+        );
+        self.pop_frame();
+    }
+
+    /// Exits the current execution, restoring the enclosing execution context.
+    ///
+    /// # Panics
+    ///
+    /// If no execution has been entered.
+    pub(crate) fn pop_frame(&mut self) {
+        let frame = self.frames.pop().expect("no execution has been entered");
+        self.caller = frame.caller;
+        self.callee = frame.callee;
+        self.input = frame.input;
+    }
+
+    /// Marks `property` as explicitly set.
+    pub(crate) fn mark_initialized(&mut self, property: &'static str) {
+        self.initialized.insert(property);
+    }
+
+    /// Returns `true` if `property` has been set explicitly.
+    pub(crate) fn is_initialized(&self, property: &'static str) -> bool {
+        self.initialized.contains(property)
     }
 
     /// Resets the execution context
@@ -69,12 +400,14 @@ impl ExecContext {
 
     /// Set the block timestamp for the execution context.
     pub fn set_block_timestamp(&mut self, block_timestamp: BlockTimestamp) {
-        self.block_timestamp = block_timestamp
+        self.block_timestamp = block_timestamp;
+        self.mark_initialized("block_timestamp");
     }
 
     /// Set the block number for the execution context.
     pub fn set_block_number(&mut self, block_number: BlockNumber) {
-        self.block_number = block_number
+        self.block_number = block_number;
+        self.mark_initialized("block_number");
     }
 }
 
@@ -89,14 +422,42 @@ mod tests {
     fn basic_operations() {
         let mut exec_cont = ExecContext::new();
 
-        exec_cont.callee = Some(AccountId::from_bytes(&[13]));
-        exec_cont.caller = Some(AccountId::from_bytes(&[14]));
-        exec_cont.value_transferred = 15;
-        assert_eq!(exec_cont.callee(), vec![13]);
+        exec_cont.set_callee(Some(AccountId::from_bytes(&[13])));
+        exec_cont.set_caller(Some(AccountId::from_bytes(&[14])));
+        exec_cont.set_value_transferred(15);
+        assert_eq!(exec_cont.callee(), Some(&AccountId::from_bytes(&[13])));
+        assert_eq!(exec_cont.caller(), Some(&AccountId::from_bytes(&[14])));
+        assert_eq!(exec_cont.value_transferred(), 15);
 
         exec_cont.reset();
 
         let new_exec_cont = ExecContext::new();
         assert_eq!(exec_cont, new_exec_cont);
     }
+
+    #[test]
+    fn snapshot_is_restored_by_reset() {
+        // given
+        let mut exec_cont = ExecContext::new();
+        let initial = exec_cont.snapshot();
+        exec_cont.set_caller(Some(AccountId::from_bytes(&[1])));
+        exec_cont.set_callee(Some(AccountId::from_bytes(&[2])));
+        exec_cont.set_block_number(3);
+        exec_cont.set_block_timestamp(4);
+        exec_cont.add_contract(vec![2]);
+        exec_cont.push_frame(AccountId::from_bytes(&[5]), vec![6]);
+        let changed = exec_cont.snapshot();
+        assert_eq!(changed.caller, Some(vec![1]));
+        assert_eq!(changed.callee, Some(vec![5]));
+        assert_eq!(changed.input, vec![6]);
+        assert_eq!(changed.depth, 1);
+        assert_ne!(changed, initial);
+
+        // when
+        exec_cont.reset();
+
+        // then
+        assert_eq!(exec_cont.snapshot(), initial);
+        assert_eq!(exec_cont.snapshot(), ExecContext::new().snapshot());
+    }
 }
@@ -18,24 +18,313 @@
 //! for more information.
 
 use crate::{
-    chain_extension::ChainExtensionHandler,
+    block_history::BlockHistory,
+    chain_extension::{
+        ChainExtensionHandler,
+        ChainExtensionOutcome,
+    },
+    chain_params::ChainParams,
+    contract_locks::ContractLocks,
     database::Database,
+    dispatch::{
+        DispatchHandler,
+        ReturnValue,
+        UnknownSelector,
+    },
     exec_context::ExecContext,
+    fee_accounting::FeeAccounting,
+    hooks::{
+        EngineHooks,
+        HostCallInfo,
+    },
+    nonce::AddressDerivationInput,
+    read_cache::ReadCache,
+    recorder::{
+        InMemoryRecorder,
+        Recorder,
+    },
+    runtime_call::{
+        RuntimeCall,
+        RuntimeCallHandler,
+    },
+    scenario::{
+        Operation,
+        Scenario,
+    },
+    storage_codec::{
+        IdentityCodec,
+        StorageCodec,
+    },
+    storage_deposit::StorageDeposits,
+    storage_overlay::{
+        CommitMode,
+        StorageOverlay,
+    },
     test_api::{
+        AccountKind,
         DebugInfo,
+        Diagnostic,
         EmittedEvent,
+        Fault,
+        FrameItem,
+        Warning,
     },
     types::{
         AccountId,
+        AssetId,
         Balance,
+        BlockNumber,
         BlockTimestamp,
     },
+    weights::WeightTable,
+    AccountError,
+};
+use core::{
+    cell::RefCell,
+    convert::Infallible,
+};
+use ink_prelude::{
+    boxed::Box,
+    collections::BTreeSet,
+    rc::Rc,
+    string::String,
+    vec::Vec,
 };
-pub use pallet_contracts_uapi::ReturnErrorCode as Error;
 use scale::Encode;
-use std::panic::panic_any;
+
+macro_rules! define_error_codes {
+    (
+        $(
+            $( #[$attr:meta] )*
+            $name:ident $( ( $payload:ty ) )? = $discr:literal,
+        )*
+    ) => {
+        /// Every error that can be returned to a contract when it calls any of the host
+        /// functions.
+        ///
+        /// Some errors carry context about the failure. The context is not part
+        /// of the numeric return code.
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub enum Error {
+            $(
+                $( #[$attr] )*
+                $name $( ( $payload ) )?,
+            )*
+            /// Returns if an unknown error was received from the host module.
+            /// Carries the raw return code.
+            UnknownError(u32),
+        }
+
+        impl Error {
+            /// Returns the numeric return code of the error.
+            pub fn code(&self) -> u32 {
+                match self {
+                    $(
+                        Self::$name { .. } => $discr,
+                    )*
+                    Self::UnknownError(code) => *code,
+                }
+            }
+        }
+
+        impl From<ReturnCode> for Result<(), Error> {
+            /// Maps a raw return code to the corresponding error.
+            ///
+            /// The context of errors carrying context is left empty, unknown codes are
+            /// kept in [`Error::UnknownError`].
+            #[inline]
+            fn from(return_code: ReturnCode) -> Self {
+                match return_code.0 {
+                    0 => Ok(()),
+                    $(
+                        $discr => Err(Error::$name $( (<$payload>::default()) )?),
+                    )*
+                    code => Err(Error::UnknownError(code)),
+                }
+            }
+        }
+    };
+}
+
+define_error_codes! {
+    /// The called function trapped and has its state changes reverted.
+    /// In this case no output buffer is returned.
+    /// Can only be returned from `call` and `instantiate`.
+    CalleeTrapped = 1,
+    /// The called function ran to completion but decided to revert its state.
+    /// Carries the output buffer returned by the callee.
+    /// Can only be returned from `call` and `instantiate`.
+    CalleeReverted(Vec<u8>) = 2,
+    /// The passed key does not exist in storage.
+    /// Carries the key which was not found.
+    KeyNotFound(Vec<u8>) = 3,
+    /// Transfer failed for other not further specified reason. Most probably
+    /// reserved or locked balance of the sender that was preventing the transfer.
+    TransferFailed = 5,
+    /// No code could be found at the supplied code hash.
+    CodeNotFound = 7,
+    /// The account that was called is no contract.
+    NotCallable = 8,
+    /// The call to `debug_message` had no effect because debug message
+    /// recording was disabled.
+    LoggingDisabled = 9,
+    /// The call dispatched by `call_runtime` was executed but returned an error.
+    CallRuntimeFailed = 10,
+    /// ECDSA public key recovery failed. Most probably wrong recovery id or signature.
+    EcdsaRecoveryFailed = 11,
+    /// sr25519 signature verification failed.
+    Sr25519VerifyFailed = 12,
+    /// The `xcm_execute` call failed.
+    XcmExecutionFailed = 13,
+    /// The `xcm_send` call failed.
+    XcmSendFailed = 14,
+    /// The transferred value could not be decoded as a balance.
+    /// Only returned by the off-chain engine.
+    InvalidValue = 15,
+    /// The callee is no valid account id of the chain.
+    /// Carries the callee. Only returned by the off-chain engine.
+    InvalidCallee(Vec<u8>) = 16,
+    /// The balance of the depositor does not cover the storage deposit.
+    StorageDepositLimitExhausted = 17,
+    /// The contract holds locks preventing its termination.
+    /// Only returned by the off-chain engine.
+    TerminationDenied = 18,
+    /// The balance of the origin does not cover the fee of the execution.
+    /// Only returned by the off-chain engine.
+    FeeWithdrawalFailed = 19,
+    /// The output buffer is too small for the value to be written to it.
+    /// Carries the needed size in bytes. Only returned by the off-chain engine.
+    BufferTooSmall(usize) = 20,
+    /// Writing the value would exceed the memory hard cap of the engine.
+    /// Only returned by the off-chain engine.
+    MemoryLimitExceeded = 21,
+    /// The event exceeds a limit of the engine configuration.
+    /// Carries the violated limit. Only returned by the off-chain engine.
+    EventLimitExceeded(EventLimitViolation) = 22,
+    /// The gas left does not cover the weight of the chain extension method or of
+    /// hashing. Only returned by the off-chain engine.
+    OutOfGas = 23,
+    /// The chain extension method trapped.
+    /// Only returned by the off-chain engine.
+    ChainExtensionTrapped = 24,
+    /// The uploaded code exceeds [`EngineConfig::max_code_len`].
+    /// Only returned by the off-chain engine.
+    CodeTooLarge = 25,
+    /// No handler of the called mocked contract is registered for the selector.
+    /// Carries the selector and the registered ones. Only returned by the off-chain
+    /// engine.
+    UnknownSelector(UnknownSelector) = 26,
+    /// The function requires a callee, but none has been set.
+    /// Only returned by the off-chain engine under [`MisusePolicy::Error`].
+    NoCallee = 27,
+    /// The account has never been assigned a balance.
+    /// Carries the account. Only returned by the off-chain engine under
+    /// [`MisusePolicy::Error`].
+    NoBalance(Vec<u8>) = 28,
+}
+
+/// The limit of the engine configuration an event exceeds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EventLimit {
+    /// The length of the event data, see [`EngineConfig::max_event_data_len`].
+    #[default]
+    DataLen,
+    /// The number of topics, see [`EngineConfig::max_topic_count`].
+    TopicCount,
+}
+
+/// Why moving value between two balances failed, see [`Engine::move_balance`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TransferRejection {
+    /// The destination would have been created while implicit account creation is
+    /// disabled.
+    DestinationMissing,
+    /// The transfer failed for any other reason.
+    Failed,
+}
+
+/// How the engine reports mistakes in the setup of a test, e.g. an unset callee or
+/// an output buffer too small for the value to be written to it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MisusePolicy {
+    /// Panic with a message describing the mistake and how to fix it.
+    #[default]
+    Panic,
+    /// Return the mistake as error of the host function, e.g.
+    /// [`Error::NoCallee`] or [`crate::Error::BufferTooSmall`].
+    ///
+    /// Host functions which cannot fail, e.g. [`Engine::contains_storage`], panic
+    /// nonetheless.
+    Error,
+}
+
+/// The encoding of the topics of an event, see
+/// [`Engine::try_deposit_event_with_format`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TopicsFormat {
+    /// A SCALE encoded `Vec<Vec<u8>>`, i.e. the compact encoded number of topics
+    /// followed by each topic with its compact encoded length.
+    #[default]
+    Framed,
+    /// The compact encoded number of topics followed by the concatenated topics,
+    /// which are split evenly. Wasm contracts emit their topics in this format,
+    /// passing it to [`Engine::try_deposit_event_with_format`] is deprecated.
+    Concatenated,
+}
+
+/// An event exceeding a limit of the engine configuration.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EventLimitViolation {
+    /// The exceeded limit.
+    pub limit: EventLimit,
+    /// The actual size of the event.
+    pub actual: usize,
+    /// The maximum size allowed by the limit.
+    pub allowed: usize,
+}
+
+impl core::fmt::Display for EventLimitViolation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.limit {
+            EventLimit::DataLen => {
+                write!(
+                    f,
+                    "event data of {} bytes exceeds the maximum of {} bytes",
+                    self.actual, self.allowed
+                )
+            }
+            EventLimit::TopicCount => {
+                write!(
+                    f,
+                    "event with {} topics exceeds the maximum of {} topics",
+                    self.actual, self.allowed
+                )
+            }
+        }
+    }
+}
+
+/// The validated inputs of a call to a contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallInput {
+    /// The account id of the called contract.
+    pub callee: Vec<u8>,
+    /// The gas limit of the call, `None` if the gas left of the caller is inherited.
+    pub gas_limit: Option<u64>,
+    /// The value transferred to the callee.
+    pub value: Balance,
+}
+
+/// The raw return code returned by the host side.
+#[repr(transparent)]
+pub struct ReturnCode(pub u32);
 
 /// The off-chain engine.
+///
+/// Cloning the engine yields an independent copy of its state, e.g. to explore the
+/// outcome of a call without affecting the original. The registered callbacks and
+/// handlers, like chain extensions and the clock, are shared with the clone.
+#[derive(Clone)]
 pub struct Engine {
     /// The environment database.
     pub database: Database,
@@ -45,13 +334,225 @@ pub struct Engine {
     /// This is specifically about debug info. This info is
     /// not available in the `contracts` pallet.
     pub(crate) debug_info: DebugInfo,
+    /// Receives the statistics of the storage accesses, emitted events and debug
+    /// messages, see [`Engine::set_recorder`].
+    pub(crate) recorder: Box<dyn Recorder>,
     /// The chain specification.
     pub chain_spec: ChainSpec,
+    /// The configuration of the engine.
+    pub config: EngineConfig,
+    /// Callbacks invoked around every host function.
+    pub(crate) hooks: Rc<EngineHooks>,
+    /// Determines the representation in which contract storage is stored.
+    pub(crate) storage_codec: Rc<dyn StorageCodec>,
+    /// Holds the value last read by `get_storage`, which returns a reference to it.
+    pub(crate) storage_buffer: Rc<[u8]>,
+    /// The storage reads cached in the current frame.
+    pub(crate) read_cache: ReadCache,
+    /// The snapshots of the retained blocks.
+    pub(crate) block_history: BlockHistory,
+    /// The contracts which terminated themselves.
+    pub(crate) tombstones: BTreeSet<Vec<u8>>,
+    /// The storage deposits reserved for the contract storage.
+    pub(crate) storage_deposits: StorageDeposits,
+    /// The buffered storage writes of the entered frames.
+    pub(crate) storage_overlay: StorageOverlay,
+    /// The outstanding locks preventing the termination of contracts.
+    pub(crate) contract_locks: ContractLocks,
+    /// The chain parameters not modeled by the engine otherwise.
+    pub(crate) chain_params: ChainParams,
+    /// The fees paid by the origins of top-level executions.
+    pub(crate) fee_accounting: FeeAccounting,
     /// Handler for registered chain extensions.
     pub chain_extension_handler: ChainExtensionHandler,
+    /// Determines the outcome of runtime calls, rejects them if not set.
+    pub(crate) runtime_call_handler: Option<Shared<RuntimeCallHandler>>,
+    /// The runtime calls dispatched by contracts.
+    pub(crate) runtime_calls: Vec<RuntimeCall>,
+    /// Faults injected by tests which have not been triggered yet.
+    pub(crate) faults: Vec<Fault>,
+    /// Handler for the entry points of registered contracts.
+    pub(crate) dispatch_handler: DispatchHandler,
+    /// Derives the block timestamp from the block number, if set by a test.
+    pub(crate) clock: Option<Shared<Clock>>,
+    /// Computes the fee for an amount of gas, if set by a test.
+    pub(crate) weight_to_fee_fn: Option<Rc<WeightToFee>>,
+    /// Derives the addresses of instantiated contracts, if set by a test.
+    pub(crate) address_derivation: Option<Rc<AddressDerivation>>,
+    /// The callbacks invoked whenever the chain advances by a block.
+    pub(crate) block_callbacks: Vec<Shared<BlockCallback>>,
+    /// The interactions with the engine, if a scenario is being recorded.
+    pub(crate) scenario: Option<Scenario>,
+    /// Whether the memory usage is above the soft cap since it was last checked.
+    pub(crate) memory_soft_cap_exceeded: bool,
+    /// The uploaded Wasm blobs and the contracts executing them.
+    #[cfg(feature = "wasm-exec")]
+    pub(crate) wasm_code: crate::wasm_exec::WasmCode,
+}
+
+/// A callback shared between an engine and its clones.
+pub(crate) type Shared<T> = Rc<RefCell<T>>;
+
+/// Derives the timestamp of a block from its number.
+pub type Clock = Box<dyn FnMut(BlockNumber) -> BlockTimestamp>;
+
+/// Computes the fee for an amount of gas.
+pub type WeightToFee = Box<dyn Fn(u64) -> Balance>;
+
+/// Derives the address of an instantiated contract.
+pub type AddressDerivation = Box<dyn Fn(&AddressDerivationInput) -> Vec<u8>>;
+
+/// Invoked with the number of the new block whenever the chain advances by a block.
+pub type BlockCallback = Box<dyn FnMut(&mut Engine, BlockNumber)>;
+
+/// The configuration of the engine.
+#[derive(Debug, Clone, Copy)]
+pub struct EngineConfig {
+    /// Reading a property of the execution context which has never been set
+    /// returns [`crate::Error::UninitializedProperty`] instead of a default value.
+    ///
+    /// Applies to the caller, callee, value transferred, block number and block
+    /// timestamp. Reading a caller or callee which is the all-zero account fails
+    /// with [`AccountError::ZeroAddressForbidden`], which is always forbidden in
+    /// strict mode, see [`EngineConfig::allow_zero_address`].
+    pub strict: bool,
+    /// The storage deposit reserved for every byte of a stored value, if any.
+    ///
+    /// See [`crate::storage_deposit`].
+    pub storage_deposit_per_byte: Option<Balance>,
+    /// The storage deposit reserved for every stored cell, if any.
+    ///
+    /// See [`crate::storage_deposit`].
+    pub storage_deposit_per_cell: Option<Balance>,
+    /// The gas limit of top-level executions.
+    ///
+    /// Used to compute the fee paid by the origin, see [`crate::fee_accounting`],
+    /// and to charge the weight of chain extension methods, see
+    /// [`Engine::gas_remaining`].
+    pub gas_limit: u64,
+    /// The estimated memory usage of the database in bytes above which a
+    /// [`crate::test_api::Warning::MemorySoftCapExceeded`] is recorded, if any.
+    ///
+    /// See [`Engine::approximate_memory_usage`].
+    pub memory_soft_cap: Option<usize>,
+    /// The estimated memory usage of the database in bytes which storage writes
+    /// must not exceed, if any.
+    ///
+    /// See [`Engine::approximate_memory_usage`].
+    pub memory_hard_cap: Option<usize>,
+    /// The maximum length of the data of an event in bytes.
+    pub max_event_data_len: usize,
+    /// The maximum number of topics of an event.
+    pub max_topic_count: usize,
+    /// Determines when the storage writes of a contract execution are applied.
+    ///
+    /// See [`crate::storage_overlay`].
+    pub commit_mode: CommitMode,
+    /// The weights charged by host functions.
+    ///
+    /// See [`crate::weights`].
+    pub weights: WeightTable,
+    /// Transfers to accounts which have never been assigned a balance create them.
+    ///
+    /// If disabled, such transfers fail with [`Error::TransferFailed`] and are
+    /// recorded, see [`Engine::rejected_transfers`].
+    pub allow_implicit_account_creation: bool,
+    /// Debug messages are recorded and printed.
+    ///
+    /// On-chain they only reach the node on development chains. If disabled, debug
+    /// messages are dropped and a [`crate::test_api::Warning::PrintlnDenied`] is
+    /// recorded instead.
+    pub println_enabled: bool,
+    /// Hashing fails with [`Error::OutOfGas`] if its weight exceeds the gas
+    /// remaining of [`EngineConfig::gas_limit`], see [`Engine::try_hash`].
+    ///
+    /// If disabled, weights only count towards the consumed gas.
+    pub gas_metering: bool,
+    /// The all-zero account may be funded, set as caller or callee and take part in
+    /// transfers.
+    ///
+    /// Unset accounts decode to it, hence using it likely hints at a bug. If
+    /// disabled or in [`EngineConfig::strict`] mode, setting it fails with
+    /// [`AccountError::ZeroAddressForbidden`] and transfers involving it fail with
+    /// [`Error::TransferFailed`].
+    pub allow_zero_address: bool,
+    /// The maximum length of uploaded code in bytes, see [`Engine::upload_code`].
+    pub max_code_len: usize,
+    /// Reads served from the read cache of the current frame do not count as reads
+    /// of the contract storage, see [`crate::read_cache`].
+    pub count_physical_reads_only: bool,
+    /// The number of finished blocks whose state is retained to revert to, see
+    /// [`crate::block_history`].
+    pub block_history_len: usize,
+    /// How mistakes in the setup of a test are reported, see [`MisusePolicy`].
+    pub misuse_policy: MisusePolicy,
+    /// The maximum number of events retained by the
+    /// [`crate::recorder::InMemoryRecorder`] and within the execution frames,
+    /// unlimited if `None`.
+    ///
+    /// See [`crate::recorder`].
+    pub max_recorded_events: Option<usize>,
+    /// The maximum number of debug messages retained by the
+    /// [`crate::recorder::InMemoryRecorder`] and within the execution frames,
+    /// unlimited if `None`.
+    ///
+    /// See [`crate::recorder`].
+    pub max_recorded_printlns: Option<usize>,
+    /// The maximum number of calls retained, see [`Engine::recorded_calls`],
+    /// unlimited if `None`.
+    pub max_recorded_calls: Option<usize>,
+    /// The maximum number of storage reads and writes retained within the execution
+    /// frames, see [`Engine::recorded_frames`], unlimited if `None`.
+    ///
+    /// See [`crate::recorder`].
+    pub max_recorded_storage_accesses: Option<usize>,
+}
+
+/// The default configuration matches the limits of `pallet-contracts`:
+///
+///   * `max_event_data_len`: 16 KiB
+///   * `max_topic_count`: 4
+///   * `max_code_len`: 123 KiB
+///
+/// Like on a development chain, transfers create their destination account and
+/// debug messages are enabled. The weights approximate `pallet-contracts`, see
+/// [`WeightTable`], and only count towards the consumed gas, the gas limit is `0`.
+/// Storage writes are applied immediately. The state of the last 16 blocks is
+/// retained to revert to, mistakes in the setup of a test panic and recorded
+/// events, debug messages, calls and storage accesses are retained without limit. Strict
+/// mode, the zero address, storage deposits, memory caps and counting only physical reads
+/// are disabled.
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            strict: false,
+            storage_deposit_per_byte: None,
+            storage_deposit_per_cell: None,
+            gas_limit: 0,
+            memory_soft_cap: None,
+            memory_hard_cap: None,
+            max_event_data_len: 16 * 1024,
+            max_topic_count: 4,
+            commit_mode: CommitMode::Immediate,
+            weights: WeightTable::default(),
+            allow_implicit_account_creation: true,
+            println_enabled: true,
+            gas_metering: false,
+            allow_zero_address: false,
+            max_code_len: 123 * 1024,
+            count_physical_reads_only: false,
+            block_history_len: 16,
+            misuse_policy: MisusePolicy::Panic,
+            max_recorded_events: None,
+            max_recorded_printlns: None,
+            max_recorded_calls: None,
+            max_recorded_storage_accesses: None,
+        }
+    }
 }
 
 /// The chain specification.
+#[derive(Clone)]
 pub struct ChainSpec {
     /// The current gas price.
     pub gas_price: Balance,
@@ -60,6 +561,8 @@ pub struct ChainSpec {
     pub minimum_balance: Balance,
     /// The targeted block time.
     pub block_time: BlockTimestamp,
+    /// The length of the account ids of the chain in bytes.
+    pub account_length: usize,
 }
 
 /// The default values for the chain specification are:
@@ -67,6 +570,7 @@ pub struct ChainSpec {
 ///   * `gas_price`: 100
 ///   * `minimum_balance`: 42
 ///   * `block_time`: 6
+///   * `account_length`: 32
 ///
 /// There is no particular reason behind choosing them this way.
 impl Default for ChainSpec {
@@ -75,6 +579,7 @@ impl Default for ChainSpec {
             gas_price: 100,
             minimum_balance: 1000000,
             block_time: 6,
+            account_length: 32,
         }
     }
 }
@@ -86,8 +591,33 @@ impl Engine {
             database: Database::new(),
             exec_context: ExecContext::new(),
             debug_info: DebugInfo::new(),
+            recorder: Box::new(InMemoryRecorder::new()),
             chain_spec: ChainSpec::default(),
+            config: EngineConfig::default(),
+            hooks: Rc::default(),
+            storage_codec: Rc::new(IdentityCodec),
+            storage_buffer: Rc::from(Vec::new()),
+            read_cache: ReadCache::default(),
+            block_history: BlockHistory::default(),
+            tombstones: BTreeSet::new(),
+            storage_deposits: StorageDeposits::default(),
+            storage_overlay: StorageOverlay::default(),
+            contract_locks: ContractLocks::default(),
+            chain_params: ChainParams::default(),
+            fee_accounting: FeeAccounting::default(),
             chain_extension_handler: ChainExtensionHandler::new(),
+            runtime_call_handler: None,
+            runtime_calls: Vec::new(),
+            faults: Vec::new(),
+            dispatch_handler: DispatchHandler::new(),
+            clock: None,
+            weight_to_fee_fn: None,
+            address_derivation: None,
+            block_callbacks: Vec::new(),
+            scenario: None,
+            memory_soft_cap_exceeded: false,
+            #[cfg(feature = "wasm-exec")]
+            wasm_code: Default::default(),
         }
     }
 }
@@ -100,190 +630,764 @@ impl Default for Engine {
 
 impl Engine {
     /// Transfers value from the contract to the destination account.
-    pub fn transfer(&mut self, account_id: &[u8], mut value: &[u8]) -> Result<(), Error> {
+    pub fn transfer(&mut self, account_id: &[u8], value: &[u8]) -> Result<(), Error> {
+        let info = HostCallInfo {
+            input_len: account_id.len() + value.len(),
+        };
+        self.host_call_mut("transfer", info, |engine| {
+            engine.charge_transfer();
+            let result = engine.transfer_value(account_id, value);
+            engine.record_operation(Operation::Transfer {
+                account_id: account_id.to_vec(),
+                value: value.to_vec(),
+                error: result.as_ref().err().map(Error::code),
+            });
+            result
+        })
+    }
+
+    fn transfer_value(
+        &mut self,
+        account_id: &[u8],
+        mut value: &[u8],
+    ) -> Result<(), Error> {
         // Note that a transfer of `0` is allowed here
         let increment = <u128 as scale::Decode>::decode(&mut value)
             .map_err(|_| Error::TransferFailed)?;
+        if let Some(error) = self.take_transfer_fault() {
+            return Err(error)
+        }
 
         let dest = account_id.to_vec();
-        // Note that the destination account does not have to exist
-        let dest_old_balance = self.get_balance(dest.clone()).unwrap_or_default();
-
-        let contract = self.get_callee();
-        let contract_old_balance = self
-            .get_balance(contract.clone())
-            .map_err(|_| Error::TransferFailed)?;
+        let contract = self.require_callee("transfer")?;
+        if increment == 0 {
+            self.debug_info.record_diagnostic(|frame| {
+                Diagnostic::ZeroValueTransfer {
+                    frame,
+                    from: contract.clone(),
+                    to: dest.clone(),
+                }
+            });
+        }
+        match self.move_balance(&contract, &dest, increment) {
+            Ok(()) => {
+                self.debug_info
+                    .record_transfer(contract, dest, increment, false);
+                Ok(())
+            }
+            Err(TransferRejection::DestinationMissing) => {
+                self.debug_info
+                    .record_transfer(contract, dest, increment, true);
+                Err(Error::TransferFailed)
+            }
+            Err(TransferRejection::Failed) => Err(Error::TransferFailed),
+        }
+    }
 
-        self.database
-            .set_balance(&contract, contract_old_balance - increment);
-        self.database
-            .set_balance(&dest, dest_old_balance + increment);
+    /// Moves `value` from the balance of `from` to the balance of `to`.
+    ///
+    /// Shared by transfers of contracts and fixture transfers, see
+    /// [`Engine::transfer_between`]. No balance is changed if the transfer fails.
+    ///
+    /// # Errors
+    ///
+    /// - [`TransferRejection::DestinationMissing`] if `to` has never been assigned a
+    ///   balance while [`EngineConfig::allow_implicit_account_creation`] is disabled.
+    /// - [`TransferRejection::Failed`] if either account is the forbidden all-zero
+    ///   account, the balance of `from` does not cover `value` or the balance of `to`
+    ///   would overflow.
+    pub(crate) fn move_balance(
+        &mut self,
+        from: &[u8],
+        to: &[u8],
+        value: Balance,
+    ) -> Result<(), TransferRejection> {
+        if self.ensure_not_zero_address(to).is_err()
+            || self.ensure_not_zero_address(from).is_err()
+        {
+            return Err(TransferRejection::Failed)
+        }
+        // Note that the destination account does not have to exist, unless implicit
+        // account creation is disabled
+        let to_old_balance = self
+            .database
+            .get_balance(to)
+            .or(self.config.allow_implicit_account_creation.then_some(0))
+            .ok_or(TransferRejection::DestinationMissing)?;
+        let from_new_balance = self
+            .database
+            .get_balance(from)
+            .and_then(|balance| balance.checked_sub(value))
+            .ok_or(TransferRejection::Failed)?;
+        if from == to {
+            return Ok(())
+        }
+        let to_new_balance = to_old_balance
+            .checked_add(value)
+            .ok_or(TransferRejection::Failed)?;
+        self.database.set_balance(from, from_new_balance);
+        self.database.set_balance(to, to_new_balance);
         Ok(())
     }
 
     /// Deposits an event identified by the supplied topics and data.
+    ///
+    /// The `topics` are in the [`TopicsFormat::Framed`] format, topics in the
+    /// deprecated [`TopicsFormat::Concatenated`] format are accepted by
+    /// [`Engine::try_deposit_event_with_format`].
+    ///
+    /// # Panics
+    ///
+    /// If the event exceeds a limit of the engine configuration, like the contract
+    /// traps on-chain. Use [`Engine::try_deposit_event`] to handle this case.
     pub fn deposit_event(&mut self, topics: &[u8], data: &[u8]) {
-        // The first byte contains the number of topics in the slice
-        let topics_count: scale::Compact<u32> = scale::Decode::decode(&mut &topics[0..1])
-            .unwrap_or_else(|err| panic!("decoding number of topics failed: {err}"));
-        let topics_count = topics_count.0 as usize;
-
-        let topics_vec = if topics_count > 0 {
-            // The rest of the slice contains the topics
-            let topics = &topics[1..];
-            let bytes_per_topic = topics.len() / topics_count;
-            let topics_vec: Vec<Vec<u8>> = topics
-                .chunks(bytes_per_topic)
-                .map(|chunk| chunk.to_vec())
-                .collect();
-            assert_eq!(topics_count, topics_vec.len());
-            topics_vec
-        } else {
-            Vec::new()
+        self.try_deposit_event(topics, data)
+            .unwrap_or_else(|error| panic!("contract trapped: {error:?}"))
+    }
+
+    /// Deposits an event identified by the supplied topics and data.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::EventLimitExceeded`] if the event has more topics than
+    ///   [`EngineConfig::max_topic_count`] or more data than
+    ///   [`EngineConfig::max_event_data_len`]. No event is recorded in this case.
+    pub fn try_deposit_event(&mut self, topics: &[u8], data: &[u8]) -> Result<(), Error> {
+        self.try_deposit_event_with_format(topics, TopicsFormat::Framed, data)
+    }
+
+    /// Deposits an event identified by the supplied topics in the given `format` and
+    /// data.
+    ///
+    /// Each event with topics in the [`TopicsFormat::Concatenated`] format records
+    /// [`Warning::DeprecatedTopicsFormat`].
+    ///
+    /// # Errors
+    ///
+    /// Like [`Engine::try_deposit_event`].
+    ///
+    /// # Panics
+    ///
+    /// If `topics` do not decode in the given `format`.
+    pub fn try_deposit_event_with_format(
+        &mut self,
+        topics: &[u8],
+        format: TopicsFormat,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let deprecated = format == TopicsFormat::Concatenated;
+        self.deposit_event_in_format(topics, format, data, deprecated)
+    }
+
+    /// Deposits an event like [`Engine::try_deposit_event_with_format`], recording
+    /// [`Warning::DeprecatedTopicsFormat`] only if `deprecated` is set.
+    pub(crate) fn deposit_event_in_format(
+        &mut self,
+        topics: &[u8],
+        format: TopicsFormat,
+        data: &[u8],
+        deprecated: bool,
+    ) -> Result<(), Error> {
+        let info = HostCallInfo {
+            input_len: topics.len() + data.len(),
         };
+        self.host_call_mut("deposit_event", info, |engine| {
+            let topics_vec = decode_topics(topics, format);
+            if deprecated {
+                engine
+                    .debug_info
+                    .record_warning(Warning::DeprecatedTopicsFormat {
+                        topics_count: topics_vec.len(),
+                    });
+            }
+            let topics_count = topics_vec.len();
+            engine.charge_event(topics_count, data.len());
+            engine.check_event_limits(topics_count, data.len())?;
+            if data.is_empty() {
+                let emitter = engine.exec_context.callee().cloned();
+                engine.debug_info.record_diagnostic(|frame| {
+                    Diagnostic::EventWithEmptyData {
+                        frame,
+                        emitter: emitter.map(|emitter| emitter.as_bytes().to_vec()),
+                        topics: topics_vec.clone(),
+                    }
+                });
+            }
 
-        self.debug_info.record_event(EmittedEvent {
-            topics: topics_vec,
-            data: data.to_vec(),
-        });
+            // Recorded in the framed format, which replaying the operation expects.
+            let framed_topics = match format {
+                TopicsFormat::Framed => topics.to_vec(),
+                TopicsFormat::Concatenated => scale::Encode::encode(&topics_vec),
+            };
+            engine.record_event(EmittedEvent {
+                topics: topics_vec,
+                data: data.to_vec(),
+            });
+            engine.record_operation(Operation::DepositEvent {
+                topics: framed_topics,
+                data: data.to_vec(),
+            });
+            Ok(())
+        })
+    }
+
+    /// Returns the violated limit if an event with `topics_count` topics and data
+    /// of `data_len` bytes exceeds a limit of the engine configuration.
+    fn check_event_limits(
+        &self,
+        topics_count: usize,
+        data_len: usize,
+    ) -> Result<(), Error> {
+        let violation = |limit, actual, allowed| {
+            Err(Error::EventLimitExceeded(EventLimitViolation {
+                limit,
+                actual,
+                allowed,
+            }))
+        };
+        if topics_count > self.config.max_topic_count {
+            return violation(
+                EventLimit::TopicCount,
+                topics_count,
+                self.config.max_topic_count,
+            )
+        }
+        if data_len > self.config.max_event_data_len {
+            return violation(
+                EventLimit::DataLen,
+                data_len,
+                self.config.max_event_data_len,
+            )
+        }
+        Ok(())
     }
 
     /// Writes the encoded value into the storage at the given key.
     /// Returns the size of the previously stored value at the key if any.
+    ///
+    /// # Panics
+    ///
+    /// If the storage deposit cannot be reserved, like the contract traps on-chain.
+    /// Use [`Engine::try_set_storage`] to handle this case.
     pub fn set_storage(&mut self, key: &[u8], encoded_value: &[u8]) -> Option<u32> {
-        let callee = self.get_callee();
-        let account_id = AccountId::from_bytes(&callee[..]);
+        self.try_set_storage(key, encoded_value)
+            .unwrap_or_else(|error| panic!("contract trapped: {error:?}"))
+    }
 
-        self.debug_info.inc_writes(account_id.clone());
-        self.debug_info
-            .record_cell_for_account(account_id, key.to_vec());
+    /// Writes the encoded value into the storage at the given key.
+    /// Returns the size of the previously stored value at the key if any.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::StorageDepositLimitExhausted`] if the balance of the depositor does not
+    ///   cover the storage deposit. The storage is not changed in this case.
+    /// - [`Error::MemoryLimitExceeded`] if the write would exceed the configured
+    ///   [`EngineConfig::memory_hard_cap`]. The storage is not changed in this case.
+    ///
+    /// # Panics
+    ///
+    /// If the write triggers an injected [`Fault::FailNthStorageWrite`], like the
+    /// contract traps on-chain. The write is not applied, the writes before it are
+    /// discarded once the frame of the trapping call is reverted with
+    /// [`CommitMode::OnFrameSuccess`].
+    pub fn try_set_storage(
+        &mut self,
+        key: &[u8],
+        encoded_value: &[u8],
+    ) -> Result<Option<u32>, Error> {
+        let info = HostCallInfo {
+            input_len: key.len() + encoded_value.len(),
+        };
+        self.host_call_mut("set_storage", info, |engine| {
+            if engine.take_storage_write_fault() {
+                panic!("injected fault: storage write failed")
+            }
+            engine.charge_storage_write(encoded_value.len());
+            let callee = engine.require_callee("set_storage")?;
+            let usage = engine.memory_usage_after_write(&callee, key, encoded_value);
+            if matches!(engine.config.memory_hard_cap, Some(cap) if usage > cap) {
+                return Err(Error::MemoryLimitExceeded)
+            }
+            engine.charge_storage_deposit(&callee, key, encoded_value.len())?;
+            let account_id = AccountId::from_bytes(&callee[..]);
 
-        self.database
-            .insert_into_contract_storage(&callee, key, encoded_value.to_vec())
-            .map(|v| <u32>::try_from(v.len()).expect("usize to u32 conversion failed"))
+            if engine.debug_info.diagnostics_enabled()
+                && !engine.debug_info.read_in_current_frame(key)
+                && engine.read_storage(&callee, key).is_some()
+            {
+                engine.debug_info.record_diagnostic(|frame| {
+                    Diagnostic::OverwriteWithoutRead {
+                        frame,
+                        account_id: callee.clone(),
+                        key: key.to_vec(),
+                    }
+                });
+            }
+            engine.recorder.inc_writes(&account_id);
+            engine
+                .debug_info
+                .record_cell_for_account(account_id, key.to_vec());
+            engine.record_storage_access(FrameItem::StorageWrite(key.to_vec()));
+
+            let previous_size =
+                engine.write_storage(&callee, key, encoded_value).map(|v| {
+                    <u32>::try_from(v.len()).expect("usize to u32 conversion failed")
+                });
+            if previous_size.is_none() {
+                engine
+                    .recorder
+                    .inc_new_cells(&AccountId::from_bytes(&callee[..]));
+            }
+            engine.check_memory_soft_cap();
+            engine.record_operation(Operation::SetStorage {
+                key: key.to_vec(),
+                value: encoded_value.to_vec(),
+                previous_size,
+            });
+            Ok(previous_size)
+        })
     }
 
     /// Returns the contract storage bytes at the key if any.
     pub fn get_storage(&mut self, key: &[u8]) -> Result<&[u8], Error> {
-        let callee = self.get_callee();
+        self.on_host_call(
+            "get_storage",
+            HostCallInfo {
+                input_len: key.len(),
+            },
+        );
+        let callee = match self.require_callee("get_storage") {
+            Ok(callee) => callee,
+            Err(error) => {
+                self.on_host_return("get_storage", false);
+                return Err(error)
+            }
+        };
         let account_id = AccountId::from_bytes(&callee[..]);
 
-        self.debug_info.inc_reads(account_id);
-        match self.database.get_from_contract_storage(&callee, key) {
-            Some(val) => Ok(val),
-            None => Err(Error::KeyNotFound),
+        let (value, cached) = self.cached_read(&callee, key);
+        if !cached || !self.config.count_physical_reads_only {
+            self.recorder.inc_reads(&account_id);
+        }
+        self.record_storage_access(FrameItem::StorageRead(key.to_vec()));
+        if value.is_none() {
+            self.debug_info.check_uninitialized_read(&callee, key);
+        }
+        self.charge_storage_read(value.as_ref().map_or(0, |value| value.len()));
+        self.on_host_return("get_storage", value.is_some());
+        self.record_operation(Operation::GetStorage {
+            key: key.to_vec(),
+            value: value.as_deref().map(<[u8]>::to_vec),
+        });
+        match value {
+            Some(val) => {
+                self.storage_buffer = val;
+                Ok(&self.storage_buffer)
+            }
+            None => Err(Error::KeyNotFound(key.to_vec())),
         }
     }
 
+    /// Writes the contract storage bytes at the key to `output`, like
+    /// [`Engine::get_storage`].
+    ///
+    /// Returns the number of bytes written to `output`.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::KeyNotFound`] if there is no value at the key.
+    /// - [`Error::BufferTooSmall`] if the value does not fit into `output`, see
+    ///   [`Engine::misuse`]. Nothing is written to `output` in this case.
+    pub fn get_storage_into(
+        &mut self,
+        key: &[u8],
+        output: &mut &mut [u8],
+    ) -> Result<usize, Error> {
+        self.get_storage(key)?;
+        let value = Rc::clone(&self.storage_buffer);
+        self.write_output("get_storage", output, &value, Error::BufferTooSmall)
+    }
+
     /// Removes the storage entries at the given key,
     /// returning previously stored value at the key if any.
     pub fn take_storage(&mut self, key: &[u8]) -> Result<Vec<u8>, Error> {
-        let callee = self.get_callee();
-        let account_id = AccountId::from_bytes(&callee[..]);
+        let info = HostCallInfo {
+            input_len: key.len(),
+        };
+        self.host_call_mut("take_storage", info, |engine| {
+            let callee = engine.require_callee("take_storage")?;
+            let account_id = AccountId::from_bytes(&callee[..]);
 
-        self.debug_info.inc_writes(account_id);
-        match self.database.remove_contract_storage(&callee, key) {
-            Some(val) => Ok(val),
-            None => Err(Error::KeyNotFound),
-        }
+            engine.recorder.inc_writes(&account_id);
+            engine.record_storage_access(FrameItem::StorageWrite(key.to_vec()));
+            let value = engine.remove_storage(&callee, key);
+            engine.charge_storage_write(value.as_ref().map_or(0, Vec::len));
+            engine.refund_storage_deposit(&callee, key);
+            engine.record_operation(Operation::TakeStorage {
+                key: key.to_vec(),
+                value: value.clone(),
+            });
+            match value {
+                Some(val) => Ok(val),
+                None => Err(Error::KeyNotFound(key.to_vec())),
+            }
+        })
     }
 
     /// Returns the size of the value stored in the contract storage at the key if any.
+    ///
+    /// # Panics
+    ///
+    /// If no callee has been set, like [`Engine::try_contains_storage`] does under
+    /// [`MisusePolicy::Panic`].
     pub fn contains_storage(&mut self, key: &[u8]) -> Option<u32> {
-        let callee = self.get_callee();
-        let account_id = AccountId::from_bytes(&callee[..]);
+        self.try_contains_storage(key)
+            .unwrap_or_else(|error| panic!("contract trapped: {error:?}"))
+    }
+
+    /// Returns the size of the value stored in the contract storage at the key if any.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::NoCallee`] under [`MisusePolicy::Error`] if no callee has been set.
+    /// Panics under [`MisusePolicy::Panic`].
+    pub fn try_contains_storage(&mut self, key: &[u8]) -> Result<Option<u32>, Error> {
+        let info = HostCallInfo {
+            input_len: key.len(),
+        };
+        self.host_call_mut("contains_storage", info, |engine| {
+            let callee = engine.require_callee("contains_storage")?;
+            let account_id = AccountId::from_bytes(&callee[..]);
 
-        self.debug_info.inc_reads(account_id);
-        self.database
-            .get_from_contract_storage(&callee, key)
-            .map(|val| val.len() as u32)
+            let (value, cached) = engine.cached_read(&callee, key);
+            if !cached || !engine.config.count_physical_reads_only {
+                engine.recorder.inc_reads(&account_id);
+            }
+            engine.record_storage_access(FrameItem::StorageRead(key.to_vec()));
+            let size = value.map(|val| val.len() as u32);
+            engine.charge_storage_read(0);
+            engine.record_operation(Operation::ContainsStorage {
+                key: key.to_vec(),
+                size,
+            });
+            Ok(size)
+        })
     }
 
     /// Removes the storage entries at the given key.
     /// Returns the size of the previously stored value at the key if any.
+    ///
+    /// Clearing a key without a value is reported to the recorder as a no-op clear
+    /// instead of a write, see [`Recorder::inc_noop_clears`].
+    ///
+    /// # Panics
+    ///
+    /// If no callee has been set, like [`Engine::try_clear_storage`] does under
+    /// [`MisusePolicy::Panic`].
     pub fn clear_storage(&mut self, key: &[u8]) -> Option<u32> {
-        let callee = self.get_callee();
-        let account_id = AccountId::from_bytes(&callee[..]);
-        self.debug_info.inc_writes(account_id.clone());
-        let _ = self
-            .debug_info
-            .remove_cell_for_account(account_id, key.to_vec());
-        self.database
-            .remove_contract_storage(&callee, key)
-            .map(|val| val.len() as u32)
+        self.try_clear_storage(key)
+            .unwrap_or_else(|error| panic!("contract trapped: {error:?}"))
+    }
+
+    /// Removes the storage entries at the given key, like [`Engine::clear_storage`].
+    /// Returns the size of the previously stored value at the key if any.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::NoCallee`] under [`MisusePolicy::Error`] if no callee has been set.
+    /// Panics under [`MisusePolicy::Panic`].
+    pub fn try_clear_storage(&mut self, key: &[u8]) -> Result<Option<u32>, Error> {
+        let info = HostCallInfo {
+            input_len: key.len(),
+        };
+        self.host_call_mut("clear_storage", info, |engine| {
+            let callee = engine.require_callee("clear_storage")?;
+            let account_id = AccountId::from_bytes(&callee[..]);
+            engine.record_storage_access(FrameItem::StorageWrite(key.to_vec()));
+            let _ = engine
+                .debug_info
+                .remove_cell_for_account(account_id.clone(), key.to_vec());
+            let size = engine
+                .remove_storage(&callee, key)
+                .map(|val| val.len() as u32);
+            if size.is_some() {
+                engine.recorder.inc_writes(&account_id);
+            } else {
+                engine.recorder.inc_noop_clears(&account_id);
+                engine.debug_info.record_diagnostic(|frame| {
+                    Diagnostic::ClearOfMissingKey {
+                        frame,
+                        account_id: callee.clone(),
+                        key: key.to_vec(),
+                    }
+                });
+            }
+            engine.charge_storage_write(size.unwrap_or_default() as usize);
+            engine.refund_storage_deposit(&callee, key);
+            engine.record_operation(Operation::ClearStorage {
+                key: key.to_vec(),
+                size,
+            });
+            Ok(size)
+        })
     }
 
     /// Remove the calling account and transfer remaining balance.
     ///
-    /// This function never returns. Either the termination was successful and the
-    /// execution of the destroyed contract is halted. Or it failed during the
-    /// termination which is considered fatal.
-    pub fn terminate(&mut self, beneficiary: &[u8]) -> ! {
+    /// This function only returns if the termination was denied. Either the
+    /// termination was successful and the execution of the destroyed contract is
+    /// halted. Or it failed during the termination which is considered fatal.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::TerminationDenied`] if the contract holds locks, see
+    ///   [`crate::contract_locks`]. No balance is transferred in this case.
+    /// - [`Error::NoCallee`] or [`Error::NoBalance`] under [`MisusePolicy::Error`] if no
+    ///   callee or no balance of it has been set. Panics under [`MisusePolicy::Panic`].
+    ///
+    /// The terminated contract becomes a tombstone, calls to it fail with
+    /// [`Error::NotCallable`], see [`Engine::account_kind`].
+    pub fn terminate(&mut self, beneficiary: &[u8]) -> Result<Infallible, Error> {
+        self.on_host_call(
+            "terminate",
+            HostCallInfo {
+                input_len: beneficiary.len(),
+            },
+        );
+        let contract = match self.require_callee("terminate") {
+            Ok(contract) => contract,
+            Err(error) => {
+                self.on_host_return("terminate", false);
+                return Err(error)
+            }
+        };
+        if self.is_contract_locked(&contract) {
+            self.on_host_return("terminate", false);
+            return Err(Error::TerminationDenied)
+        }
+
         // Send the remaining balance to the beneficiary
-        let contract = self.get_callee();
-        let all = self
-            .get_balance(contract)
-            .unwrap_or_else(|err| panic!("could not get balance: {err:?}"));
+        let Ok(all) = self.get_balance(contract.clone()) else {
+            let error = self.misuse(
+                Error::NoBalance(contract.clone()),
+                format_args!(
+                    "`terminate` requires a balance of the contract {contract:?}, but \
+                    none has been set, see `Engine::set_balance`"
+                ),
+            );
+            self.on_host_return("terminate", false);
+            return Err(error)
+        };
         let value = &scale::Encode::encode(&all)[..];
         self.transfer(beneficiary, value)
             .unwrap_or_else(|err| panic!("transfer did not work: {err:?}"));
+        self.entomb(&contract);
 
         // Encode the result of the termination and panic with it.
         // This enables testing for the proper result and makes sure this
         // method returns `Never`.
         let res = (all, beneficiary.to_vec());
-        panic_any(scale::Encode::encode(&res));
+        #[cfg(feature = "std")]
+        std::panic::panic_any(scale::Encode::encode(&res));
+        #[cfg(not(feature = "std"))]
+        panic!("contract terminated: {res:?}");
+    }
+
+    /// Returns the input of the current contract execution.
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn input(&self, output: &mut &mut [u8]) -> Result<usize, crate::Error> {
+        self.host_call("input", HostCallInfo::default(), || {
+            self.write_output(
+                "input",
+                output,
+                self.exec_context.input(),
+                crate::Error::BufferTooSmall,
+            )
+        })
+    }
+
+    /// Returns `data` with `flags` to the caller of the current contract execution.
+    ///
+    /// This function never returns. The execution is unwound with a [`ReturnValue`]
+    /// as payload, which is retrieved by the one who invoked the entry point of the
+    /// contract. Without the `std` feature the engine cannot unwind with a payload
+    /// and panics instead.
+    pub fn return_value(&mut self, flags: u32, data: &[u8]) -> ! {
+        self.on_host_call(
+            "return_value",
+            HostCallInfo {
+                input_len: data.len(),
+            },
+        );
+        let value = ReturnValue {
+            flags,
+            data: data.to_vec(),
+        };
+        // Unwinding skips the panic hook, returning is not an error.
+        #[cfg(feature = "std")]
+        std::panic::resume_unwind(ink_prelude::boxed::Box::new(value));
+        #[cfg(not(feature = "std"))]
+        panic!("contract returned: {value:?}");
     }
 
     /// Returns the address of the caller.
-    pub fn caller(&self, output: &mut &mut [u8]) {
-        let caller = self
-            .exec_context
-            .caller
-            .as_ref()
-            .expect("no caller has been set")
-            .as_bytes();
-        set_output(output, caller);
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn caller(&self, output: &mut &mut [u8]) -> Result<usize, crate::Error> {
+        self.host_call("caller", HostCallInfo::default(), || {
+            self.ensure_initialized("caller")?;
+            let caller = match self.exec_context.caller() {
+                Some(caller) => caller.as_bytes(),
+                None if self.exec_context.unsigned_origin.is_some() => {
+                    return Err(crate::Error::UnsignedOrigin)
+                }
+                None => return Err(crate::Error::UninitializedExecutionContext),
+            };
+            if self.config.strict {
+                self.ensure_not_zero_address(caller)?;
+            }
+            self.write_output("caller", output, caller, crate::Error::BufferTooSmall)
+        })
+    }
+
+    /// Returns `true` if the caller is the origin of the execution, i.e. if the
+    /// current execution is not called by a contract.
+    pub fn caller_is_origin(&self) -> bool {
+        self.host_call("caller_is_origin", HostCallInfo::default(), || {
+            self.exec_context.caller() == self.exec_context.origin()
+        })
+    }
+
+    /// Writes the SCALE encoded number of times the executed contract was called by
+    /// the executions enclosing the current one into `output`, `0` without
+    /// reentrancy.
+    ///
+    /// Delegate calls execute in the context of the calling contract, hence they
+    /// leave the count unchanged, see [`ExecContext::reentrance_count`].
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn reentrance_count(
+        &self,
+        output: &mut &mut [u8],
+    ) -> Result<usize, crate::Error> {
+        self.host_call("reentrance_count", HostCallInfo::default(), || {
+            let count = scale::Encode::encode(&self.exec_context.reentrance_count());
+            self.write_output(
+                "reentrance_count",
+                output,
+                &count,
+                crate::Error::BufferTooSmall,
+            )
+        })
     }
 
     /// Returns the balance of the executed contract.
-    pub fn balance(&self, output: &mut &mut [u8]) {
-        let contract = self
-            .exec_context
-            .callee
-            .as_ref()
-            .expect("no callee has been set");
-
-        let balance_in_storage = self
-            .database
-            .get_balance(contract.as_bytes())
-            .expect("currently executing contract must exist");
-        let balance = scale::Encode::encode(&balance_in_storage);
-        set_output(output, &balance[..])
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn balance(&self, output: &mut &mut [u8]) -> Result<usize, crate::Error> {
+        self.host_call("balance", HostCallInfo::default(), || {
+            let contract = self
+                .exec_context
+                .callee()
+                .ok_or(crate::Error::UninitializedExecutionContext)?;
+
+            let balance_in_storage = self
+                .database
+                .get_balance(contract.as_bytes())
+                .ok_or_else(|| {
+                    AccountError::NoAccountForId(contract.as_bytes().to_vec())
+                })?;
+            let balance = scale::Encode::encode(&balance_in_storage);
+            self.write_output(
+                "balance",
+                output,
+                &balance[..],
+                crate::Error::BufferTooSmall,
+            )
+        })
     }
 
     /// Returns the transferred value for the called contract.
-    pub fn value_transferred(&self, output: &mut &mut [u8]) {
-        let value_transferred: Vec<u8> =
-            scale::Encode::encode(&self.exec_context.value_transferred);
-        set_output(output, &value_transferred[..])
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn value_transferred(
+        &self,
+        output: &mut &mut [u8],
+    ) -> Result<usize, crate::Error> {
+        self.host_call("value_transferred", HostCallInfo::default(), || {
+            self.ensure_initialized("value_transferred")?;
+            let value_transferred: Vec<u8> =
+                scale::Encode::encode(&self.exec_context.value_transferred());
+            self.write_output(
+                "value_transferred",
+                output,
+                &value_transferred[..],
+                crate::Error::BufferTooSmall,
+            )
+        })
+    }
+
+    /// Returns the nonce of `account_id`, see [`crate::nonce`].
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn account_nonce(
+        &self,
+        account_id: &[u8],
+        output: &mut &mut [u8],
+    ) -> Result<usize, crate::Error> {
+        let info = HostCallInfo {
+            input_len: account_id.len(),
+        };
+        self.host_call("account_nonce", info, || {
+            let nonce = scale::Encode::encode(&self.get_nonce(account_id));
+            self.write_output(
+                "account_nonce",
+                output,
+                &nonce[..],
+                crate::Error::BufferTooSmall,
+            )
+        })
     }
 
     /// Returns the address of the executed contract.
-    pub fn address(&self, output: &mut &mut [u8]) {
-        let callee = self
-            .exec_context
-            .callee
-            .as_ref()
-            .expect("no callee has been set")
-            .as_bytes();
-        set_output(output, callee)
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn address(&self, output: &mut &mut [u8]) -> Result<usize, crate::Error> {
+        self.host_call("address", HostCallInfo::default(), || {
+            self.ensure_initialized("callee")?;
+            let callee = self
+                .exec_context
+                .callee()
+                .ok_or(crate::Error::UninitializedExecutionContext)?
+                .as_bytes();
+            if self.config.strict {
+                self.ensure_not_zero_address(callee)?;
+            }
+            self.write_output("address", output, callee, crate::Error::BufferTooSmall)
+        })
     }
 
     /// Records the given debug message and appends to stdout.
+    ///
+    /// Without the `std` feature the message is only recorded. If debug messages are
+    /// disabled via [`EngineConfig::println_enabled`], only a warning is recorded.
     pub fn debug_message(&mut self, message: &str) {
-        self.debug_info.record_debug_message(String::from(message));
-        print!("{message}");
+        let info = HostCallInfo {
+            input_len: message.len(),
+        };
+        self.host_call_mut("debug_message", info, |engine| {
+            engine.record_operation(Operation::DebugMessage(String::from(message)));
+            if !engine.config.println_enabled {
+                engine.debug_info.record_warning(Warning::PrintlnDenied {
+                    message: String::from(message),
+                });
+                return
+            }
+            engine.record_println(message);
+            #[cfg(feature = "std")]
+            print!("{message}");
+        })
     }
 
     /// Conduct the BLAKE-2 256-bit hash and place the result into `output`.
@@ -306,142 +1410,770 @@ impl Engine {
         super::hashing::keccak_256(input, output);
     }
 
+    /// Conducts `hash` of `input` into `output` after charging the weight of hashing
+    /// `input`, e.g. `engine.try_hash(input, output, Engine::hash_blake2_256)`.
+    ///
+    /// The hash functions above charge nothing as they have no access to the engine.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::OutOfGas`] if [`EngineConfig::gas_metering`] is enabled and the gas
+    ///   remaining does not cover the weight. Nothing is hashed or charged in this case.
+    pub fn try_hash<O>(
+        &mut self,
+        input: &[u8],
+        output: &mut O,
+        hash: impl FnOnce(&[u8], &mut O),
+    ) -> Result<(), Error> {
+        self.try_charge_hashing(input.len())?;
+        hash(input, output);
+        Ok(())
+    }
+
     /// Returns the current block number.
-    pub fn block_number(&self, output: &mut &mut [u8]) {
-        let block_number: Vec<u8> =
-            scale::Encode::encode(&self.exec_context.block_number);
-        set_output(output, &block_number[..])
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn block_number(&self, output: &mut &mut [u8]) -> Result<usize, crate::Error> {
+        self.host_call("block_number", HostCallInfo::default(), || {
+            self.ensure_initialized("block_number")?;
+            let block_number: Vec<u8> =
+                scale::Encode::encode(&self.exec_context.block_number());
+            self.write_output(
+                "block_number",
+                output,
+                &block_number[..],
+                crate::Error::BufferTooSmall,
+            )
+        })
     }
 
     /// Returns the timestamp of the current block.
-    pub fn block_timestamp(&self, output: &mut &mut [u8]) {
-        let block_timestamp: Vec<u8> =
-            scale::Encode::encode(&self.exec_context.block_timestamp);
-        set_output(output, &block_timestamp[..])
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn block_timestamp(&self, output: &mut &mut [u8]) -> Result<usize, crate::Error> {
+        self.host_call("block_timestamp", HostCallInfo::default(), || {
+            self.ensure_initialized("block_timestamp")?;
+            let block_timestamp: Vec<u8> =
+                scale::Encode::encode(&self.exec_context.timestamp());
+            self.write_output(
+                "block_timestamp",
+                output,
+                &block_timestamp[..],
+                crate::Error::BufferTooSmall,
+            )
+        })
     }
 
-    pub fn gas_left(&self, _output: &mut &mut [u8]) {
-        unimplemented!("off-chain environment does not yet support `gas_left`");
+    /// Returns an error in strict mode if `property` has never been set.
+    fn ensure_initialized(&self, property: &'static str) -> Result<(), crate::Error> {
+        if self.config.strict && !self.exec_context.is_initialized(property) {
+            return Err(crate::Error::UninitializedProperty(property))
+        }
+        Ok(())
+    }
+
+    /// Returns an error if `account_id` is the all-zero account and it is forbidden,
+    /// see [`EngineConfig::allow_zero_address`].
+    pub(crate) fn ensure_not_zero_address(
+        &self,
+        account_id: &[u8],
+    ) -> Result<(), crate::Error> {
+        let forbidden = self.config.strict || !self.config.allow_zero_address;
+        if forbidden && !account_id.is_empty() && account_id.iter().all(|b| *b == 0) {
+            return Err(AccountError::ZeroAddressForbidden.into())
+        }
+        Ok(())
+    }
+
+    /// Writes the SCALE encoded gas left to the ongoing top-level execution into
+    /// `output`, see [`Engine::gas_remaining`].
+    ///
+    /// The weight of the query is charged before, hence reading the gas left twice
+    /// yields strictly decreasing values unless it is free, see [`crate::weights`].
+    pub fn gas_left(&mut self, output: &mut &mut [u8]) -> Result<usize, crate::Error> {
+        self.host_call_mut("gas_left", HostCallInfo::default(), |engine| {
+            engine.charge_gas_left();
+            let gas_left = scale::Encode::encode(&engine.gas_remaining());
+            engine.write_output(
+                "gas_left",
+                output,
+                &gas_left,
+                crate::Error::BufferTooSmall,
+            )
+        })
     }
 
     /// Returns the minimum balance that is required for creating an account
     /// (i.e. the chain's existential deposit).
-    pub fn minimum_balance(&self, output: &mut &mut [u8]) {
-        let minimum_balance: Vec<u8> =
-            scale::Encode::encode(&self.chain_spec.minimum_balance);
-        set_output(output, &minimum_balance[..])
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn minimum_balance(&self, output: &mut &mut [u8]) -> Result<usize, crate::Error> {
+        self.host_call("minimum_balance", HostCallInfo::default(), || {
+            let minimum_balance: Vec<u8> =
+                scale::Encode::encode(&self.chain_spec.minimum_balance);
+            self.write_output(
+                "minimum_balance",
+                output,
+                &minimum_balance[..],
+                crate::Error::BufferTooSmall,
+            )
+        })
     }
 
+    /// Instantiates a contract from the code with `code_hash`, funding it with
+    /// `endowment` from the current contract, and writes its address to
+    /// `out_address`.
+    ///
+    /// The address is derived via [`Engine::derive_contract_address`]. The contract
+    /// becomes a known contract, calls to it are dispatched to the entry points of
+    /// the code, if it has any, see [`Engine::upload_code`]. The constructor is not
+    /// executed, since the code interacts with the engine. Nothing is written to
+    /// `out_return_value`.
+    ///
+    /// Under the `wasm-exec` feature, the `deploy` export of an uploaded Wasm blob is
+    /// executed with `input` instead, and `out_return_value` is truncated to the data
+    /// it returned with. If the constructor reverts or traps, the endowment is
+    /// refunded and no contract is instantiated, see the `wasm_exec` module.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::CodeNotFound`] if no code has been uploaded or registered under
+    ///   `code_hash`.
+    /// - [`Error::InvalidValue`] if the endowment cannot be decoded.
+    /// - [`Error::TransferFailed`] if the balance of the current contract does not cover
+    ///   the endowment.
+    /// - [`Error::CalleeReverted`] or [`Error::CalleeTrapped`] if the constructor of a
+    ///   Wasm blob reverted or trapped.
+    ///
+    /// No contract is instantiated in these cases.
     #[allow(clippy::too_many_arguments)]
     pub fn instantiate(
         &mut self,
-        _code_hash: &[u8],
+        code_hash: &[u8],
         _gas_limit: u64,
-        _endowment: &[u8],
-        _input: &[u8],
-        _out_address: &mut &mut [u8],
-        _out_return_value: &mut &mut [u8],
-        _salt: &[u8],
+        endowment: &[u8],
+        input: &[u8],
+        out_address: &mut &mut [u8],
+        #[cfg_attr(not(feature = "wasm-exec"), allow(unused_variables))]
+        out_return_value: &mut &mut [u8],
+        salt: &[u8],
     ) -> Result<(), Error> {
-        unimplemented!("off-chain environment does not yet support `instantiate`");
+        let info = HostCallInfo {
+            input_len: code_hash.len() + endowment.len() + input.len() + salt.len(),
+        };
+        self.host_call_mut("instantiate", info, |engine| {
+            engine.charge_call();
+            if !engine.dispatch_handler.code_exists(code_hash) {
+                return Err(Error::CodeNotFound)
+            }
+            let value = <Balance as scale::Decode>::decode(&mut &endowment[..])
+                .map_err(|_| Error::InvalidValue)?;
+            let account_id = engine.derive_contract_address(code_hash, input, salt);
+            if value > 0 {
+                engine.transfer_value(&account_id, endowment)?;
+            }
+            #[cfg(feature = "wasm-exec")]
+            if let Some(code) = engine.wasm_code(code_hash) {
+                let deployed = engine
+                    .try_enter_frame(account_id.clone(), input.to_vec())
+                    .and_then(|()| engine.execute_wasm(&code, "deploy"));
+                match deployed {
+                    Ok(data) => {
+                        let len = engine.write_output(
+                            "instantiate",
+                            out_return_value,
+                            &data,
+                            Error::BufferTooSmall,
+                        )?;
+                        truncate_output(out_return_value, len);
+                        engine.register_wasm_contract(&account_id, code_hash);
+                    }
+                    Err(error) => {
+                        if value > 0 {
+                            let deployer = engine.require_callee("instantiate")?;
+                            // Moving back the endowment which was just moved is not
+                            // rejected.
+                            let _ = engine.move_balance(&account_id, &deployer, value);
+                        }
+                        return Err(error)
+                    }
+                }
+            }
+            engine.register_callable_contract(&account_id);
+            if let Some(entry_points) = engine.dispatch_handler.code(code_hash) {
+                engine
+                    .dispatch_handler
+                    .register(account_id.clone(), entry_points);
+            }
+            engine.write_output(
+                "instantiate",
+                out_address,
+                &account_id,
+                Error::BufferTooSmall,
+            )?;
+            Ok(())
+        })
     }
 
+    /// Calls the contract at `callee` with `input`, transferring `value` from the
+    /// current contract to it.
+    ///
+    /// Only contracts executing a Wasm blob under the `wasm-exec` feature can be
+    /// called this way: the `call` export of the blob is executed and `output` is
+    /// truncated to the data it returned with, see the `wasm_exec` module. Other
+    /// contracts interact with the engine themselves, hence their calls are entered
+    /// via [`Engine::enter_call`].
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::InvalidCallee`] or [`Error::InvalidValue`] if the inputs are invalid,
+    ///   see [`Engine::validate_call`].
+    /// - [`Error::NotCallable`] if `callee` is no contract.
+    /// - [`Error::TransferFailed`] if the balance of the current contract does not cover
+    ///   `value`.
+    /// - [`Error::CalleeReverted`] or [`Error::CalleeTrapped`] if the contract reverted
+    ///   or trapped.
+    ///
+    /// # Panics
+    ///
+    /// If the contract does not execute a Wasm blob.
     pub fn call(
         &mut self,
-        _callee: &[u8],
-        _gas_limit: u64,
-        _value: &[u8],
-        _input: &[u8],
-        _output: &mut &mut [u8],
+        callee: &[u8],
+        gas_limit: u64,
+        value: &[u8],
+        input: &[u8],
+        #[cfg_attr(not(feature = "wasm-exec"), allow(unused_variables))]
+        output: &mut &mut [u8],
+    ) -> Result<(), Error> {
+        let info = HostCallInfo {
+            input_len: callee.len() + value.len() + input.len(),
+        };
+        self.host_call_mut("call", info, |engine| {
+            engine.charge_call();
+            let call = engine.validate_call(callee, gas_limit, value)?;
+            if engine.account_kind(&call.callee) != AccountKind::Contract {
+                return Err(Error::NotCallable)
+            }
+            #[cfg(feature = "wasm-exec")]
+            if let Some(code) = engine.wasm_code_of_contract(&call.callee) {
+                engine.transfer_call_value(&call, value)?;
+                engine.try_enter_frame(call.callee, input.to_vec())?;
+                let data = engine.execute_wasm(&code, "call")?;
+                let len =
+                    engine.write_output("call", output, &data, Error::BufferTooSmall)?;
+                truncate_output(output, len);
+                return Ok(())
+            }
+            unimplemented!("off-chain environment does not yet support `call`");
+        })
+    }
+
+    /// Enters a delegate call of the code with `code_hash` and returns the `call`
+    /// entry point of the code.
+    ///
+    /// The code is executed in the context of the current contract execution: the
+    /// caller, the callee and thereby the storage remain unchanged, no value is
+    /// transferred. Only the input is replaced by `input`, it is kept if `input` is
+    /// `None`.
+    ///
+    /// The engine cannot execute the entry point itself, since the code interacts
+    /// with the engine. Hence the one who invoked the delegate call executes it and
+    /// exits via [`Engine::return_from_frame`].
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::CodeNotFound`] if no code is registered under `code_hash`. No execution
+    ///   is entered in this case.
+    pub fn delegate_call(
+        &mut self,
+        code_hash: &[u8],
+        input: Option<Vec<u8>>,
+    ) -> Result<fn(), Error> {
+        let info = HostCallInfo {
+            input_len: code_hash.len() + input.as_ref().map_or(0, Vec::len),
+        };
+        self.host_call_mut("delegate_call", info, |engine| {
+            engine.charge_call();
+            let entry_points = engine
+                .dispatch_handler
+                .code(code_hash)
+                .ok_or(Error::CodeNotFound)?;
+            engine.enter_delegate_frame(input);
+            Ok(entry_points.call)
+        })
+    }
+
+    /// Enters a call of the contract at `callee` with `input`, transferring `value`
+    /// from the current contract to it, and returns the function to dispatch the call
+    /// to.
+    ///
+    /// The call is dispatched to the handler of a mocked contract registered for the
+    /// selector of `input`, see [`Engine::register_contract_message`], or else to the
+    /// `call` entry point of the contract. The current input is kept if `input` is
+    /// `None`. The value transferred of the execution context is not changed.
+    ///
+    /// The engine cannot execute the function itself, since the code interacts with
+    /// the engine. Hence the one who invoked the call executes it and exits via
+    /// [`Engine::return_from_frame`]. The data it returned with is the output of the
+    /// call.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::InvalidCallee`] or [`Error::InvalidValue`] if the inputs are invalid,
+    ///   see [`Engine::validate_call`].
+    /// - [`Error::NotCallable`] if `callee` is no contract, e.g. a user account or a
+    ///   terminated contract, see [`Engine::account_kind`], or no contract is registered
+    ///   there.
+    /// - [`Error::UnknownSelector`] if `callee` is a mocked contract without a handler
+    ///   for the selector of `input`.
+    /// - [`Error::TransferFailed`] if the balance of the current contract does not cover
+    ///   `value`.
+    ///
+    /// No execution is entered in these cases.
+    pub fn enter_call(
+        &mut self,
+        callee: &[u8],
+        value: &[u8],
+        input: Option<Vec<u8>>,
+    ) -> Result<fn(), Error> {
+        let info = HostCallInfo {
+            input_len: callee.len() + value.len() + input.as_ref().map_or(0, Vec::len),
+        };
+        self.host_call_mut("call", info, |engine| {
+            engine.charge_call();
+            let call = engine.validate_call(callee, 0, value)?;
+            if engine.account_kind(&call.callee) != AccountKind::Contract {
+                return Err(Error::NotCallable)
+            }
+            let input = input.unwrap_or_else(|| engine.exec_context.input().to_vec());
+            let selector = input
+                .get(..4)
+                .and_then(|selector| <[u8; 4]>::try_from(selector).ok());
+            engine.record_dispatch_input(&input);
+            let function = selector
+                .and_then(|selector| engine.get_message_handler(&call.callee, selector))
+                .or_else(|| {
+                    engine
+                        .get_entry_points(&call.callee)
+                        .map(|entry_points| entry_points.call)
+                })
+                .ok_or_else(|| {
+                    selector
+                        .and_then(|selector| {
+                            engine.unknown_selector(&call.callee, selector)
+                        })
+                        .map_or(Error::NotCallable, Error::UnknownSelector)
+                })?;
+            engine.transfer_call_value(&call, value)?;
+            engine.enter_frame(call.callee, input);
+            Ok(function)
+        })
+    }
+
+    /// Transfers the SCALE encoded `value` of `call` from the current contract to the
+    /// callee, if any.
+    fn transfer_call_value(
+        &mut self,
+        call: &CallInput,
+        value: &[u8],
     ) -> Result<(), Error> {
-        unimplemented!("off-chain environment does not yet support `call`");
+        if call.value > 0 {
+            let contract = self.require_callee("call")?;
+            let balance = self.get_balance(contract).unwrap_or_default();
+            if balance < call.value {
+                return Err(Error::TransferFailed)
+            }
+            self.transfer(&call.callee, value)?;
+        }
+        Ok(())
+    }
+
+    /// Validates the inputs of a call the way the `contracts` pallet does.
+    ///
+    /// A `gas_limit` of `0` means the call inherits the gas left of its caller.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::InvalidCallee`] if `callee` is shorter than the account length of the
+    ///   chain specification.
+    /// - [`Error::InvalidValue`] if `value` can not be decoded as a balance.
+    pub fn validate_call(
+        &self,
+        callee: &[u8],
+        gas_limit: u64,
+        mut value: &[u8],
+    ) -> Result<CallInput, Error> {
+        let account_length = self.chain_spec.account_length;
+        if callee.len() < account_length {
+            return Err(Error::InvalidCallee(callee.to_vec()))
+        }
+        let value = <Balance as scale::Decode>::decode(&mut value)
+            .map_err(|_| Error::InvalidValue)?;
+        Ok(CallInput {
+            callee: callee[..account_length].to_vec(),
+            gas_limit: (gas_limit != 0).then_some(gas_limit),
+            value,
+        })
     }
 
     /// Emulates gas price calculation.
-    pub fn weight_to_fee(&self, gas: u64, output: &mut &mut [u8]) {
-        let fee = self.chain_spec.gas_price.saturating_mul(gas.into());
-        let fee: Vec<u8> = scale::Encode::encode(&fee);
-        set_output(output, &fee[..])
+    ///
+    /// Uses the fee function set via [`Engine::set_weight_to_fee_fn`], if any, and
+    /// the [`ChainSpec::gas_price`] per unit of gas otherwise.
+    pub fn weight_to_fee(&mut self, gas: u64, output: &mut &mut [u8]) {
+        self.host_call_mut("weight_to_fee", HostCallInfo::default(), |engine| {
+            let fee = match &engine.weight_to_fee_fn {
+                Some(weight_to_fee) => weight_to_fee(gas),
+                None => engine.chain_spec.gas_price.saturating_mul(gas.into()),
+            };
+            engine.record_operation(Operation::WeightToFee { gas, fee });
+            let fee: Vec<u8> = scale::Encode::encode(&fee);
+            set_output(output, &fee[..]);
+        })
+    }
+
+    /// Dispatches the SCALE encoded runtime `call` on behalf of the executed contract.
+    ///
+    /// The outcome is determined by the handler registered via
+    /// [`Engine::register_runtime_call_handler`], see [`crate::runtime_call`].
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::CallRuntimeFailed`] if the handler rejects the call.
+    pub fn call_runtime(&mut self, call: &[u8]) -> Result<(), Error> {
+        let info = HostCallInfo {
+            input_len: call.len(),
+        };
+        self.host_call_mut("call_runtime", info, |engine| {
+            engine
+                .dispatch_runtime_call(call)
+                .map_err(|_| Error::CallRuntimeFailed)
+        })
     }
 
     /// Calls the chain extension method registered at `func_id` with `input`.
+    ///
+    /// Returns the number of bytes written to `output`, see
+    /// [`Engine::try_call_chain_extension`].
+    ///
+    /// # Panics
+    ///
+    /// If the gas left does not cover the weight of the method, the method traps or
+    /// its output does not fit into `output`, like the contract traps on-chain. Use
+    /// [`Engine::try_call_chain_extension`] to handle these cases.
     pub fn call_chain_extension(
         &mut self,
         id: u32,
         input: &[u8],
         output: &mut &mut [u8],
-    ) {
-        let encoded_input = input.encode();
-        let (status_code, out) = self
-            .chain_extension_handler
-            .eval(id, &encoded_input)
-            .unwrap_or_else(|error| {
+    ) -> usize {
+        self.try_call_chain_extension(id, input, output)
+            .unwrap_or_else(|error| panic!("contract trapped: {error:?}"))
+    }
+
+    /// Calls the chain extension method registered at `func_id` with `input`.
+    ///
+    /// The weight the method was registered with is charged before it is invoked,
+    /// see [`ChainExtensionHandler::register_with_weight`].
+    ///
+    /// The status code and output of the method are written SCALE encoded to
+    /// `output`. Returns the number of bytes written, only those are to be decoded.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::OutOfGas`] if the gas left does not cover the weight of the method. The
+    ///   method is not invoked and no gas is charged in this case.
+    /// - [`Error::ChainExtensionTrapped`] if the method trapped, see
+    ///   [`ChainExtension::try_call_with_engine`](crate::ChainExtension::try_call_with_engine).
+    /// - [`Error::BufferTooSmall`] with the length of the encoded status code and output
+    ///   if they do not fit into `output`. Nothing is written to `output` in this case.
+    ///
+    /// The status code and output returned by the method are recorded, see
+    /// [`ChainExtensionHandler::last_status`].
+    pub fn try_call_chain_extension(
+        &mut self,
+        id: u32,
+        input: &[u8],
+        output: &mut &mut [u8],
+    ) -> Result<usize, Error> {
+        let info = HostCallInfo {
+            input_len: input.len(),
+        };
+        self.host_call_mut("call_chain_extension", info, |engine| {
+            if let Some(status) = engine.take_chain_extension_fault(id) {
+                let res = (status, Vec::<u8>::new());
+                return Ok(set_output(output, &scale::Encode::encode(&res)[..]))
+            }
+            let weight = engine.chain_extension_handler.weight(id);
+            if weight > engine.gas_remaining() {
+                engine
+                    .chain_extension_handler
+                    .record_out_of_gas(id, input, weight);
+                return Err(Error::OutOfGas)
+            }
+            engine.consume_gas(weight);
+            let encoded_input = input.encode();
+            // The handler is moved out of the engine, so that the chain extension can
+            // access the engine.
+            let mut handler = core::mem::take(&mut engine.chain_extension_handler);
+            let res = handler
+                .eval(engine, id, &encoded_input)
+                .map(|res| res.map(|(status, output)| (status, output.to_vec())));
+            engine.chain_extension_handler = handler;
+            let returned = res.unwrap_or_else(|error| {
                 panic!(
                     "Encountered unexpected missing chain extension method: {error:?}"
                 );
             });
-        let res = (status_code, out);
-        let decoded: Vec<u8> = scale::Encode::encode(&res);
-        set_output(output, &decoded[..])
+            let Ok((status, returned_output)) = returned else {
+                engine.chain_extension_handler.record_call(
+                    id,
+                    input,
+                    weight,
+                    ChainExtensionOutcome::Trapped,
+                );
+                return Err(Error::ChainExtensionTrapped)
+            };
+            let encoded = scale::Encode::encode(&(status, &returned_output));
+            engine.chain_extension_handler.record_call(
+                id,
+                input,
+                weight,
+                ChainExtensionOutcome::Returned {
+                    status,
+                    output: returned_output,
+                },
+            );
+            if encoded.len() > output.len() {
+                return Err(Error::BufferTooSmall(encoded.len()))
+            }
+            Ok(set_output(output, &encoded[..]))
+        })
+    }
+
+    /// Transfers `value` of `asset` from the contract to the destination account.
+    ///
+    /// Intended as building block for chain extensions which move assets, see
+    /// [`ChainExtension::call_with_engine`](crate::ChainExtension::call_with_engine).
+    /// The native balances are not affected.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::TransferFailed`] if the contract holds less than `value` of `asset`
+    /// or the balance of the destination would overflow. No balance is changed in
+    /// this case.
+    pub fn asset_transfer(
+        &mut self,
+        asset: AssetId,
+        dest: &[u8],
+        value: Balance,
+    ) -> Result<(), Error> {
+        let info = HostCallInfo {
+            input_len: dest.len(),
+        };
+        self.host_call_mut("asset_transfer", info, |engine| {
+            let contract = engine.require_callee("asset_transfer")?;
+            let contract_old_balance = engine
+                .database
+                .get_asset_balance(&contract, asset)
+                .unwrap_or_default();
+            let contract_new_balance = contract_old_balance
+                .checked_sub(value)
+                .ok_or(Error::TransferFailed)?;
+            if dest == &contract[..] {
+                return Ok(())
+            }
+            let dest_new_balance = engine
+                .database
+                .get_asset_balance(dest, asset)
+                .unwrap_or_default()
+                .checked_add(value)
+                .ok_or(Error::TransferFailed)?;
+            engine
+                .database
+                .set_asset_balance(&contract, asset, contract_new_balance);
+            engine
+                .database
+                .set_asset_balance(dest, asset, dest_new_balance);
+            Ok(())
+        })
     }
 
     /// Recovers the compressed ECDSA public key for given `signature` and `message_hash`,
     /// and stores the result in `output`.
+    #[cfg(feature = "std")]
     pub fn ecdsa_recover(
         &mut self,
         signature: &[u8; 65],
         message_hash: &[u8; 32],
         output: &mut [u8; 33],
     ) -> Result<(), Error> {
-        use secp256k1::{
-            ecdsa::{
-                RecoverableSignature,
-                RecoveryId,
-            },
-            Message,
-            SECP256K1,
+        let info = HostCallInfo {
+            input_len: signature.len() + message_hash.len(),
         };
+        self.host_call("ecdsa_recover", info, || {
+            use secp256k1::{
+                ecdsa::{
+                    RecoverableSignature,
+                    RecoveryId,
+                },
+                Message,
+                SECP256K1,
+            };
 
-        // In most implementations, the v is just 0 or 1 internally, but 27 was added
-        // as an arbitrary number for signing Bitcoin messages and Ethereum adopted that
-        // as well.
-        let recovery_byte = if signature[64] > 26 {
-            signature[64] - 27
-        } else {
-            signature[64]
-        };
+            // In most implementations, the v is just 0 or 1 internally, but 27 was added
+            // as an arbitrary number for signing Bitcoin messages and Ethereum adopted
+            // that as well.
+            let recovery_byte = if signature[64] > 26 {
+                signature[64] - 27
+            } else {
+                signature[64]
+            };
 
-        let recovery_id = RecoveryId::from_i32(recovery_byte as i32)
-            .unwrap_or_else(|error| panic!("Unable to parse the recovery id: {error}"));
+            let recovery_id =
+                RecoveryId::from_i32(recovery_byte as i32).unwrap_or_else(|error| {
+                    panic!("Unable to parse the recovery id: {error}")
+                });
 
-        let message = Message::from_digest_slice(message_hash).unwrap_or_else(|error| {
-            panic!("Unable to create the message from hash: {error}")
-        });
-        let signature =
-            RecoverableSignature::from_compact(&signature[0..64], recovery_id)
-                .unwrap_or_else(|error| panic!("Unable to parse the signature: {error}"));
-
-        let pub_key = SECP256K1.recover_ecdsa(&message, &signature);
-        match pub_key {
-            Ok(pub_key) => {
-                *output = pub_key.serialize();
-                Ok(())
+            let message =
+                Message::from_digest_slice(message_hash).unwrap_or_else(|error| {
+                    panic!("Unable to create the message from hash: {error}")
+                });
+            let signature =
+                RecoverableSignature::from_compact(&signature[0..64], recovery_id)
+                    .unwrap_or_else(|error| {
+                        panic!("Unable to parse the signature: {error}")
+                    });
+
+            let pub_key = SECP256K1.recover_ecdsa(&message, &signature);
+            match pub_key {
+                Ok(pub_key) => {
+                    *output = pub_key.serialize();
+                    Ok(())
+                }
+                Err(_) => Err(Error::EcdsaRecoveryFailed),
+            }
+        })
+    }
+
+    /// Reports a mistake in the setup of a test according to
+    /// [`EngineConfig::misuse_policy`], returning `error` under
+    /// [`MisusePolicy::Error`].
+    ///
+    /// # Panics
+    ///
+    /// Under [`MisusePolicy::Panic`] with `message`.
+    fn misuse<E>(&self, error: E, message: core::fmt::Arguments) -> E {
+        match self.config.misuse_policy {
+            MisusePolicy::Panic => panic!("{message}"),
+            MisusePolicy::Error => error,
+        }
+    }
+
+    /// Returns the callee required by the host function `host_fn`.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::NoCallee`] if no callee has been set, see [`Engine::misuse`].
+    fn require_callee(&self, host_fn: &str) -> Result<Vec<u8>, Error> {
+        match self.exec_context.callee() {
+            Some(callee) => Ok(callee.as_bytes().to_vec()),
+            None => {
+                Err(self.misuse(
+                    Error::NoCallee,
+                    format_args!(
+                        "`{host_fn}` requires a callee, but none has been set, see \
+                        `Engine::set_callee`"
+                    ),
+                ))
             }
-            Err(_) => Err(Error::EcdsaRecoveryFailed),
         }
     }
+
+    /// Copies `value` into the `output` of the host function `host_fn` and returns
+    /// the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// `buffer_too_small` with the length of `value` if it does not fit into
+    /// `output`, see [`Engine::misuse`]. Nothing is written to `output` in this
+    /// case.
+    fn write_output<E>(
+        &self,
+        host_fn: &str,
+        output: &mut &mut [u8],
+        value: &[u8],
+        buffer_too_small: fn(usize) -> E,
+    ) -> Result<usize, E> {
+        if value.len() > output.len() {
+            return Err(self.misuse(
+                buffer_too_small(value.len()),
+                format_args!(
+                    "the output buffer of `{host_fn}` is too small: the value is of \
+                    size {} bytes, but the output buffer has only room for {}",
+                    value.len(),
+                    output.len(),
+                ),
+            ))
+        }
+        Ok(set_output(output, value))
+    }
 }
 
-/// Copies the `slice` into `output`.
+/// Decodes the `topics` of an event in the given `format`, see
+/// [`Engine::try_deposit_event_with_format`].
+///
+/// # Panics
+///
+/// If `topics` do not decode in the given `format`.
+// `usize::is_multiple_of` requires Rust 1.87, newer than the toolchains `ink_engine`
+// supports.
+#[allow(clippy::manual_is_multiple_of)]
+fn decode_topics(topics: &[u8], format: TopicsFormat) -> Vec<Vec<u8>> {
+    if format == TopicsFormat::Framed {
+        return <Vec<Vec<u8>> as scale::DecodeAll>::decode_all(&mut &topics[..])
+            .unwrap_or_else(|err| panic!("decoding topics failed: {err}"))
+    }
+    let mut concatenated = topics;
+    let topics_count: scale::Compact<u32> = scale::Decode::decode(&mut concatenated)
+        .unwrap_or_else(|err| panic!("decoding number of topics failed: {err}"));
+    let topics_count = topics_count.0 as usize;
+    if topics_count == 0 {
+        return Vec::new()
+    }
+    if concatenated.len() % topics_count != 0 {
+        panic!(
+            "{} bytes of topics cannot be split evenly into {topics_count} topics",
+            concatenated.len()
+        )
+    }
+    let bytes_per_topic = concatenated.len() / topics_count;
+    (0..topics_count)
+        .map(|index| concatenated[index * bytes_per_topic..][..bytes_per_topic].to_vec())
+        .collect()
+}
+
+/// Truncates `output` to its first `len` bytes, e.g. to the bytes written to it.
+#[cfg(feature = "wasm-exec")]
+fn truncate_output(output: &mut &mut [u8], len: usize) {
+    let buffer = core::mem::take(output);
+    *output = &mut buffer[..len];
+}
+
+/// Copies the `slice` into `output` and returns the number of bytes written.
+///
+/// # Panics
 ///
-/// Panics if the slice is too large and does not fit.
-fn set_output(output: &mut &mut [u8], slice: &[u8]) {
+/// If the slice is too large and does not fit.
+fn set_output(output: &mut &mut [u8], slice: &[u8]) -> usize {
     assert!(
         slice.len() <= output.len(),
-        "the output buffer is too small! the decoded storage is of size {} bytes, \
-        but the output buffer has only room for {}.",
+        "the output buffer is too small: the value is of size {} bytes, but the \
+        output buffer has only room for {}",
         slice.len(),
         output.len(),
     );
     output[..slice.len()].copy_from_slice(slice);
+    slice.len()
 }
@@ -0,0 +1,187 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transaction fees paid by the origin of top-level executions.
+//!
+//! Mirrors the fee withdrawal of the runtime: when a top-level execution is
+//! entered, the fee for [`EngineConfig::gas_limit`] at [`ChainSpec::gas_price`] is
+//! withdrawn from the balance of its caller, the origin. When the execution is
+//! exited, the fee for the gas which was not consumed is refunded.
+//!
+//! The engine does not meter gas, the consumed gas is simulated via
+//! [`Engine::consume_gas`], by the weights of host functions, see
+//! [`crate::weights`], and by the weights of chain extension methods, see
+//! [`Engine::gas_remaining`].
+//!
+//! [`EngineConfig::gas_limit`]: crate::ext::EngineConfig::gas_limit
+//! [`ChainSpec::gas_price`]: crate::ext::ChainSpec::gas_price
+
+use crate::{
+    ext::{
+        Engine,
+        Error,
+    },
+    types::Balance,
+};
+use ink_prelude::{
+    collections::BTreeMap,
+    vec::Vec,
+};
+
+/// The fee withheld for an ongoing top-level execution.
+#[derive(Clone)]
+struct Withdrawal {
+    /// The account which paid the fee.
+    origin: Vec<u8>,
+    /// The gas the fee was withdrawn for.
+    gas_limit: u64,
+}
+
+/// The fees paid by the origins of top-level executions.
+#[derive(Clone, Default)]
+pub(crate) struct FeeAccounting {
+    /// Fees are only withdrawn if enabled.
+    enabled: bool,
+    /// The sum of the fees paid by every origin.
+    paid: BTreeMap<Vec<u8>, Balance>,
+    /// The withdrawal of the ongoing top-level execution, if any.
+    withdrawal: Option<Withdrawal>,
+    /// The gas consumed by the ongoing top-level execution, or since the last one
+    /// if there is none.
+    gas_consumed: u64,
+    /// The gas consumed when the active frames were entered, innermost last.
+    frame_gas: Vec<u64>,
+}
+
+impl FeeAccounting {
+    /// Disables fee accounting and forgets all paid fees.
+    pub(crate) fn reset(&mut self) {
+        *self = Default::default();
+    }
+}
+
+impl Engine {
+    /// Enables or disables the withdrawal of fees from the origin of top-level
+    /// executions.
+    ///
+    /// Fee accounting is disabled by default.
+    pub fn enable_fee_accounting(&mut self, enabled: bool) {
+        self.fee_accounting.enabled = enabled;
+    }
+
+    /// Returns the sum of the fees paid by `account_id`, net of refunds.
+    pub fn fees_paid(&self, account_id: &[u8]) -> Balance {
+        self.fee_accounting
+            .paid
+            .get(account_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Simulates the consumption of `gas` by the ongoing top-level execution.
+    ///
+    /// The fee for the consumed gas is not refunded when the execution is exited.
+    pub fn consume_gas(&mut self, gas: u64) {
+        self.fee_accounting.gas_consumed =
+            self.fee_accounting.gas_consumed.saturating_add(gas);
+    }
+
+    /// Returns the gas consumed by the ongoing top-level execution, or since the last
+    /// one if there is none.
+    ///
+    /// Includes the weights charged by host functions, see [`crate::weights`].
+    pub fn gas_consumed(&self) -> u64 {
+        self.fee_accounting.gas_consumed
+    }
+
+    /// Records the gas consumed when a frame is entered, see
+    /// [`Engine::leave_gas_frame`].
+    pub(crate) fn enter_gas_frame(&mut self) {
+        let consumed = self.fee_accounting.gas_consumed;
+        self.fee_accounting.frame_gas.push(consumed);
+    }
+
+    /// Returns the gas consumed since the current frame was entered, including the
+    /// frames nested in it.
+    pub(crate) fn leave_gas_frame(&mut self) -> u64 {
+        let entered = self.fee_accounting.frame_gas.pop().unwrap_or_default();
+        self.fee_accounting.gas_consumed.saturating_sub(entered)
+    }
+
+    /// Returns the gas of [`EngineConfig::gas_limit`] not yet consumed by the ongoing
+    /// top-level execution.
+    ///
+    /// The consumed gas is reset whenever a top-level execution is entered.
+    ///
+    /// [`EngineConfig::gas_limit`]: crate::ext::EngineConfig::gas_limit
+    pub fn gas_remaining(&self) -> u64 {
+        self.config
+            .gas_limit
+            .saturating_sub(self.fee_accounting.gas_consumed)
+    }
+
+    /// Withdraws the fee for a top-level execution from the balance of its caller.
+    ///
+    /// Resets the consumed gas. Does nothing else if fee accounting is disabled or
+    /// the caller is not set.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::FeeWithdrawalFailed`] if the balance of the caller does not cover the
+    ///   fee. No balance is changed in this case.
+    pub(crate) fn withdraw_fee(&mut self) -> Result<(), Error> {
+        self.fee_accounting.gas_consumed = 0;
+        if !self.fee_accounting.enabled {
+            return Ok(())
+        }
+        let Some(origin) = self
+            .exec_context
+            .caller()
+            .map(|caller| caller.as_bytes().to_vec())
+        else {
+            return Ok(())
+        };
+        let gas_limit = self.config.gas_limit;
+        let fee = self.chain_spec.gas_price.saturating_mul(gas_limit.into());
+        let balance = self.database.get_balance(&origin).unwrap_or_default();
+        if balance < fee {
+            return Err(Error::FeeWithdrawalFailed)
+        }
+
+        self.database.set_balance(&origin, balance - fee);
+        *self.fee_accounting.paid.entry(origin.clone()).or_default() += fee;
+        self.fee_accounting.withdrawal = Some(Withdrawal { origin, gas_limit });
+        Ok(())
+    }
+
+    /// Refunds the fee for the gas the exited top-level execution did not consume.
+    pub(crate) fn refund_fee(&mut self) {
+        let Some(withdrawal) = self.fee_accounting.withdrawal.take() else {
+            return
+        };
+        let unused = withdrawal
+            .gas_limit
+            .saturating_sub(self.fee_accounting.gas_consumed);
+        let refund = self.chain_spec.gas_price.saturating_mul(unused.into());
+        let balance = self
+            .database
+            .get_balance(&withdrawal.origin)
+            .unwrap_or_default();
+        self.database
+            .set_balance(&withdrawal.origin, balance + refund);
+        if let Some(paid) = self.fee_accounting.paid.get_mut(&withdrawal.origin) {
+            *paid -= refund;
+        }
+    }
+}
@@ -14,6 +14,48 @@
 
 //! Implementations of supported cryptographic hash functions.
 
+pub mod test_vectors;
+
+/// The supported cryptographic hash functions.
+///
+/// Allows to plug an alternative implementation, e.g. the one of `sp-core`, and
+/// compare its outputs with the ones of the engine via [`test_vectors::compare`].
+pub trait Hasher {
+    /// Conduct the BLAKE2 128-bit hash and place the result into `output`.
+    fn blake2b_128(&self, input: &[u8], output: &mut [u8; 16]);
+
+    /// Conduct the BLAKE2 256-bit hash and place the result into `output`.
+    fn blake2b_256(&self, input: &[u8], output: &mut [u8; 32]);
+
+    /// Conduct the SHA-2 256-bit hash and place the result into `output`.
+    fn sha2_256(&self, input: &[u8], output: &mut [u8; 32]);
+
+    /// Conduct the KECCAK 256-bit hash and place the result into `output`.
+    fn keccak_256(&self, input: &[u8], output: &mut [u8; 32]);
+}
+
+/// The hash functions used by the engine.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultHasher;
+
+impl Hasher for DefaultHasher {
+    fn blake2b_128(&self, input: &[u8], output: &mut [u8; 16]) {
+        blake2b_128(input, output)
+    }
+
+    fn blake2b_256(&self, input: &[u8], output: &mut [u8; 32]) {
+        blake2b_256(input, output)
+    }
+
+    fn sha2_256(&self, input: &[u8], output: &mut [u8; 32]) {
+        sha2_256(input, output)
+    }
+
+    fn keccak_256(&self, input: &[u8], output: &mut [u8; 32]) {
+        keccak_256(input, output)
+    }
+}
+
 /// Conduct the BLAKE2 256-bit hash and place the result into `output`.
 pub fn blake2b_256(input: &[u8], output: &mut [u8; 32]) {
     use ::blake2::digest::{
@@ -125,4 +167,65 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_vectors_match() {
+        assert_eq!(test_vectors::verify_all(), Ok(()));
+    }
+
+    /// Hashes like the engine, but flips the first output byte of SHA-2.
+    struct FaultySha2;
+
+    impl Hasher for FaultySha2 {
+        fn blake2b_128(&self, input: &[u8], output: &mut [u8; 16]) {
+            DefaultHasher.blake2b_128(input, output)
+        }
+
+        fn blake2b_256(&self, input: &[u8], output: &mut [u8; 32]) {
+            DefaultHasher.blake2b_256(input, output)
+        }
+
+        fn sha2_256(&self, input: &[u8], output: &mut [u8; 32]) {
+            DefaultHasher.sha2_256(input, output);
+            output[0] ^= 0xFF;
+        }
+
+        fn keccak_256(&self, input: &[u8], output: &mut [u8; 32]) {
+            DefaultHasher.keccak_256(input, output)
+        }
+    }
+
+    /// Returns pseudo-random inputs of increasing length.
+    fn random_inputs() -> impl Iterator<Item = Vec<u8>> {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        (0..64).map(move |len| {
+            (0..len)
+                .map(|_| {
+                    // xorshift64
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn alternative_hasher_is_compared_byte_for_byte() {
+        for input in random_inputs() {
+            assert_eq!(
+                test_vectors::compare(&DefaultHasher, &DefaultHasher, &input),
+                Ok(())
+            );
+            let mismatch = test_vectors::compare(&DefaultHasher, &FaultySha2, &input)
+                .expect_err("outputs of SHA-2 differ");
+            assert_eq!(mismatch.algorithm, test_vectors::Algorithm::Sha2_256);
+            assert_eq!(mismatch.input, input);
+            assert_eq!(mismatch.expected[1..], mismatch.actual[1..]);
+        }
+
+        let mismatch = test_vectors::verify(&FaultySha2).expect_err("SHA-2 is faulty");
+        assert_eq!(mismatch.algorithm, test_vectors::Algorithm::Sha2_256);
+    }
 }
@@ -0,0 +1,262 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Known input and output pairs of the supported hash functions.
+//!
+//! The outputs match the ones of `sp-core`, hence the table allows to cross-check
+//! the engine against the runtime, e.g. when diagnosing mismatching event topics.
+
+use super::{
+    DefaultHasher,
+    Hasher,
+};
+use ink_prelude::vec::Vec;
+
+/// A supported hash function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// BLAKE2 with a 128-bit output.
+    Blake2b128,
+    /// BLAKE2 with a 256-bit output.
+    Blake2b256,
+    /// SHA-2 with a 256-bit output.
+    Sha2_256,
+    /// KECCAK with a 256-bit output.
+    Keccak256,
+}
+
+impl Algorithm {
+    /// All supported hash functions.
+    pub const ALL: [Algorithm; 4] = [
+        Algorithm::Blake2b128,
+        Algorithm::Blake2b256,
+        Algorithm::Sha2_256,
+        Algorithm::Keccak256,
+    ];
+
+    /// Returns the output of `hasher` for `input` with this hash function.
+    pub fn hash(self, hasher: &dyn Hasher, input: &[u8]) -> Vec<u8> {
+        match self {
+            Algorithm::Blake2b128 => {
+                let mut output = [0; 16];
+                hasher.blake2b_128(input, &mut output);
+                output.to_vec()
+            }
+            Algorithm::Blake2b256 => {
+                let mut output = [0; 32];
+                hasher.blake2b_256(input, &mut output);
+                output.to_vec()
+            }
+            Algorithm::Sha2_256 => {
+                let mut output = [0; 32];
+                hasher.sha2_256(input, &mut output);
+                output.to_vec()
+            }
+            Algorithm::Keccak256 => {
+                let mut output = [0; 32];
+                hasher.keccak_256(input, &mut output);
+                output.to_vec()
+            }
+        }
+    }
+}
+
+/// A known output of a hash function for an input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestVector {
+    /// The hash function.
+    pub algorithm: Algorithm,
+    /// The hashed input.
+    pub input: &'static [u8],
+    /// The expected output.
+    pub output: &'static [u8],
+}
+
+/// The output of a hash function differs from the expected one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashMismatch {
+    /// The hash function.
+    pub algorithm: Algorithm,
+    /// The hashed input.
+    pub input: Vec<u8>,
+    /// The expected output.
+    pub expected: Vec<u8>,
+    /// The actual output.
+    pub actual: Vec<u8>,
+}
+
+/// The known outputs of the supported hash functions.
+pub const TEST_VECTORS: &[TestVector] = &[
+    TestVector {
+        algorithm: Algorithm::Blake2b128,
+        input: b"",
+        output: &[
+            0xca, 0xe6, 0x69, 0x41, 0xd9, 0xef, 0xbd, 0x40, 0x4e, 0x4d, 0x88, 0x75, 0x8e,
+            0xa6, 0x76, 0x70,
+        ],
+    },
+    TestVector {
+        algorithm: Algorithm::Blake2b128,
+        input: b"abc",
+        output: &[
+            0xcf, 0x4a, 0xb7, 0x91, 0xc6, 0x2b, 0x8d, 0x2b, 0x21, 0x09, 0xc9, 0x02, 0x75,
+            0x28, 0x78, 0x16,
+        ],
+    },
+    TestVector {
+        algorithm: Algorithm::Blake2b128,
+        input: b"DEAD_BEEF",
+        output: &[
+            0xb4, 0x9e, 0x30, 0x15, 0xab, 0xa3, 0xd9, 0xaf, 0x91, 0xa0, 0x19, 0x9f, 0xd5,
+            0x8e, 0x67, 0xf2,
+        ],
+    },
+    TestVector {
+        algorithm: Algorithm::Blake2b256,
+        input: b"",
+        output: &[
+            0x0e, 0x57, 0x51, 0xc0, 0x26, 0xe5, 0x43, 0xb2, 0xe8, 0xab, 0x2e, 0xb0, 0x60,
+            0x99, 0xda, 0xa1, 0xd1, 0xe5, 0xdf, 0x47, 0x77, 0x8f, 0x77, 0x87, 0xfa, 0xab,
+            0x45, 0xcd, 0xf1, 0x2f, 0xe3, 0xa8,
+        ],
+    },
+    TestVector {
+        algorithm: Algorithm::Blake2b256,
+        input: b"abc",
+        output: &[
+            0xbd, 0xdd, 0x81, 0x3c, 0x63, 0x42, 0x39, 0x72, 0x31, 0x71, 0xef, 0x3f, 0xee,
+            0x98, 0x57, 0x9b, 0x94, 0x96, 0x4e, 0x3b, 0xb1, 0xcb, 0x3e, 0x42, 0x72, 0x62,
+            0xc8, 0xc0, 0x68, 0xd5, 0x23, 0x19,
+        ],
+    },
+    TestVector {
+        algorithm: Algorithm::Blake2b256,
+        input: b"DEAD_BEEF",
+        output: &[
+            0xf4, 0xf7, 0xeb, 0xb6, 0xc2, 0xa1, 0x1c, 0x45, 0x22, 0x6a, 0xed, 0x07, 0x39,
+            0x57, 0xbe, 0x0c, 0x5c, 0xab, 0x5b, 0xb0, 0x87, 0x34, 0xf7, 0x5e, 0x08, 0x70,
+            0x5e, 0xb7, 0x8c, 0x65, 0xd0, 0x78,
+        ],
+    },
+    TestVector {
+        algorithm: Algorithm::Sha2_256,
+        input: b"",
+        output: &[
+            0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99,
+            0x6f, 0xb9, 0x24, 0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95,
+            0x99, 0x1b, 0x78, 0x52, 0xb8, 0x55,
+        ],
+    },
+    TestVector {
+        algorithm: Algorithm::Sha2_256,
+        input: b"abc",
+        output: &[
+            0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d,
+            0xae, 0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10,
+            0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+        ],
+    },
+    TestVector {
+        algorithm: Algorithm::Sha2_256,
+        input: b"DEAD_BEEF",
+        output: &[
+            0x88, 0x0f, 0x19, 0xda, 0x58, 0x36, 0x31, 0x98, 0x73, 0xa8, 0x93, 0xbd, 0xcf,
+            0xab, 0xf3, 0x81, 0xa1, 0x4c, 0x0f, 0x8d, 0xc5, 0x6a, 0x6f, 0xd5, 0x13, 0xc5,
+            0x85, 0xdb, 0xb5, 0xe9, 0xc3, 0x78,
+        ],
+    },
+    TestVector {
+        algorithm: Algorithm::Keccak256,
+        input: b"",
+        output: &[
+            0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc,
+            0xc7, 0x03, 0xc0, 0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa,
+            0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+        ],
+    },
+    TestVector {
+        algorithm: Algorithm::Keccak256,
+        input: b"abc",
+        output: &[
+            0x4e, 0x03, 0x65, 0x7a, 0xea, 0x45, 0xa9, 0x4f, 0xc7, 0xd4, 0x7b, 0xa8, 0x26,
+            0xc8, 0xd6, 0x67, 0xc0, 0xd1, 0xe6, 0xe3, 0x3a, 0x64, 0xa0, 0x36, 0xec, 0x44,
+            0xf5, 0x8f, 0xa1, 0x2d, 0x6c, 0x45,
+        ],
+    },
+    TestVector {
+        algorithm: Algorithm::Keccak256,
+        input: b"DEAD_BEEF",
+        output: &[
+            0x18, 0xe6, 0xd1, 0x3b, 0x7f, 0x1e, 0x9e, 0xf4, 0x3c, 0xb1, 0x84, 0x96, 0xa7,
+            0xf4, 0x40, 0x45, 0xb8, 0x7b, 0xb9, 0x2c, 0xd3, 0xc7, 0xd0, 0xb3, 0x0e, 0x40,
+            0x7e, 0x8c, 0xd9, 0x45, 0x24, 0xd8,
+        ],
+    },
+];
+
+/// Checks the outputs of the engine's hash functions against [`TEST_VECTORS`].
+///
+/// # Errors
+///
+/// For the first test vector the output differs from.
+pub fn verify_all() -> Result<(), HashMismatch> {
+    verify(&DefaultHasher)
+}
+
+/// Checks the outputs of `hasher` against [`TEST_VECTORS`].
+///
+/// # Errors
+///
+/// For the first test vector the output differs from.
+pub fn verify(hasher: &dyn Hasher) -> Result<(), HashMismatch> {
+    TEST_VECTORS.iter().try_for_each(|vector| {
+        let actual = vector.algorithm.hash(hasher, vector.input);
+        if actual != vector.output {
+            return Err(HashMismatch {
+                algorithm: vector.algorithm,
+                input: vector.input.to_vec(),
+                expected: vector.output.to_vec(),
+                actual,
+            })
+        }
+        Ok(())
+    })
+}
+
+/// Compares the outputs of `candidate` for `input` byte-for-byte with the ones of
+/// `reference` for all supported hash functions.
+///
+/// # Errors
+///
+/// For the first hash function the outputs differ for, the output of `reference`
+/// is the expected one.
+pub fn compare(
+    reference: &dyn Hasher,
+    candidate: &dyn Hasher,
+    input: &[u8],
+) -> Result<(), HashMismatch> {
+    Algorithm::ALL.iter().try_for_each(|&algorithm| {
+        let expected = algorithm.hash(reference, input);
+        let actual = algorithm.hash(candidate, input);
+        if actual != expected {
+            return Err(HashMismatch {
+                algorithm,
+                input: input.to_vec(),
+                expected,
+                actual,
+            })
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,155 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Callbacks invoked around the host functions of the engine, e.g. for telemetry.
+
+use crate::ext::Engine;
+use ink_prelude::{
+    boxed::Box,
+    rc::Rc,
+};
+
+/// Summary of the inputs a host function was called with.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HostCallInfo {
+    /// The total length of the byte inputs, e.g. storage key and value.
+    pub input_len: usize,
+}
+
+/// Invoked with the name of a host function and a summary of its inputs when it is
+/// called.
+pub type OnHostCall = Box<dyn Fn(&'static str, &HostCallInfo)>;
+
+/// Invoked with the name of a host function and whether it succeeded when it returns.
+pub type OnHostReturn = Box<dyn Fn(&'static str, bool)>;
+
+/// Callbacks invoked around every host function of the engine.
+///
+/// The callbacks have no access to the engine, hence they cannot change its state.
+/// Panics inside of the callbacks propagate to the caller of the host function.
+///
+/// The hashing functions do not invoke the hooks. Host functions which never
+/// return, like `terminate` and `return_value`, only invoke `on_host_call`.
+#[derive(Default)]
+pub struct EngineHooks {
+    /// Invoked when a host function is called.
+    pub on_host_call: Option<OnHostCall>,
+    /// Invoked when a host function returns.
+    pub on_host_return: Option<OnHostReturn>,
+}
+
+#[cfg(feature = "tracing")]
+impl EngineHooks {
+    /// Hooks which emit a `tracing` event at trace level with target `ink_engine`
+    /// whenever a host function is called or returns.
+    pub fn tracing() -> Self {
+        Self {
+            on_host_call: Some(Box::new(|name, info| {
+                tracing::trace!(
+                    target: "ink_engine",
+                    host_fn = name,
+                    input_len = info.input_len,
+                    "host function called"
+                )
+            })),
+            on_host_return: Some(Box::new(|name, ok| {
+                tracing::trace!(
+                    target: "ink_engine",
+                    host_fn = name,
+                    ok,
+                    "host function returned"
+                )
+            })),
+        }
+    }
+}
+
+/// The outcome of a host function as reported to [`EngineHooks::on_host_return`].
+pub(crate) trait HostCallOutcome {
+    /// Returns `true` if the host function succeeded.
+    fn is_ok(&self) -> bool;
+}
+
+impl HostCallOutcome for () {
+    fn is_ok(&self) -> bool {
+        true
+    }
+}
+
+impl HostCallOutcome for bool {
+    fn is_ok(&self) -> bool {
+        true
+    }
+}
+
+impl<T> HostCallOutcome for Option<T> {
+    fn is_ok(&self) -> bool {
+        true
+    }
+}
+
+impl<T, E> HostCallOutcome for Result<T, E> {
+    fn is_ok(&self) -> bool {
+        Result::is_ok(self)
+    }
+}
+
+impl Engine {
+    /// Sets the hooks invoked around every host function.
+    ///
+    /// Replaces previously set hooks.
+    pub fn set_hooks(&mut self, hooks: EngineHooks) {
+        self.hooks = Rc::new(hooks);
+    }
+
+    /// Invokes [`EngineHooks::on_host_call`] for the host function `name`.
+    pub(crate) fn on_host_call(&self, name: &'static str, info: HostCallInfo) {
+        if let Some(on_host_call) = &self.hooks.on_host_call {
+            on_host_call(name, &info);
+        }
+    }
+
+    /// Invokes [`EngineHooks::on_host_return`] for the host function `name`.
+    pub(crate) fn on_host_return(&self, name: &'static str, ok: bool) {
+        if let Some(on_host_return) = &self.hooks.on_host_return {
+            on_host_return(name, ok);
+        }
+    }
+
+    /// Invokes the hooks around `f`, which implements the host function `name`.
+    pub(crate) fn host_call<R: HostCallOutcome>(
+        &self,
+        name: &'static str,
+        info: HostCallInfo,
+        f: impl FnOnce() -> R,
+    ) -> R {
+        self.on_host_call(name, info);
+        let result = f();
+        self.on_host_return(name, result.is_ok());
+        result
+    }
+
+    /// Invokes the hooks around `f`, which implements the host function `name`.
+    pub(crate) fn host_call_mut<R: HostCallOutcome>(
+        &mut self,
+        name: &'static str,
+        info: HostCallInfo,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        self.on_host_call(name, info);
+        let result = f(self);
+        self.on_host_return(name, result.is_ok());
+        result
+    }
+}
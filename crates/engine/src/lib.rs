@@ -16,24 +16,92 @@
     html_logo_url = "https://use.ink/img/crate-docs/logo.png",
     html_favicon_url = "https://use.ink/crate-docs/favicon.png"
 )]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(not(any(feature = "std", feature = "alloc")))]
+compile_error!("`ink_engine` requires either the `std` or the `alloc` feature");
+
+pub mod activity;
+#[cfg(feature = "allowances")]
+pub mod allowances;
+pub mod api;
+pub mod block_history;
+pub mod capabilities;
+pub mod chain_params;
+pub mod contract_locks;
 pub mod ext;
+pub mod fee_accounting;
+pub mod hashing;
+pub mod hooks;
+pub mod nonce;
+pub mod read_cache;
+pub mod recorder;
+pub mod runtime_call;
+pub mod scenario;
+pub mod snapshot;
+pub mod state;
+pub mod storage_codec;
+pub mod storage_deposit;
+pub mod storage_overlay;
 pub mod test_api;
+pub mod test_chain;
+#[cfg(feature = "wasm-exec")]
+pub mod wasm_exec;
+pub mod weights;
 
 mod chain_extension;
 mod database;
+mod dispatch;
 mod exec_context;
-mod hashing;
 mod types;
 
 #[cfg(test)]
 mod tests;
 
-pub use chain_extension::ChainExtension;
-pub use types::AccountError;
+pub use capabilities::{
+    capabilities,
+    Capabilities,
+    ENGINE_API_VERSION,
+};
+pub use chain_extension::{
+    ChainExtension,
+    ChainExtensionCall,
+    ChainExtensionOutcome,
+    ChainExtensionTrapped,
+};
+pub use dispatch::{
+    DuplicateSelector,
+    EntryPoints,
+    ReturnValue,
+    UnknownSelector,
+};
+pub use exec_context::{
+    ExecContextSnapshot,
+    Origin,
+};
+pub use test_chain::TestChain;
+pub use types::{
+    AccountError,
+    AccountId,
+    AssetId,
+    Hash,
+    HexError,
+    DEFAULT_ACCOUNT_LENGTH,
+};
 
 use derive_more::From;
 
+#[cfg(not(feature = "std"))]
+use hashbrown::{
+    hash_map,
+    HashMap,
+};
+#[cfg(feature = "std")]
+use ink_prelude::collections::{
+    hash_map,
+    HashMap,
+};
+
 /// Errors which can happen when interacting with this crate.
 #[derive(Debug, From, PartialEq, Eq)]
 pub enum Error {
@@ -44,4 +112,16 @@ pub enum Error {
     UninitializedExecutionContext,
     #[from(ignore)]
     UnregisteredChainExtension,
+    /// A property of the execution context was read in strict mode without
+    /// having been set, carries the name of the property.
+    #[from(ignore)]
+    UninitializedProperty(&'static str),
+    /// The caller was read in an execution dispatched by root or without origin,
+    /// which has no caller.
+    #[from(ignore)]
+    UnsignedOrigin,
+    /// The output buffer is too small for the value to be written to it, carries
+    /// the needed size in bytes. Only returned under [`ext::MisusePolicy::Error`].
+    #[from(ignore)]
+    BufferTooSmall(usize),
 }
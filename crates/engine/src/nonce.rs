@@ -0,0 +1,136 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Nonces of the accounts of the engine.
+//!
+//! Mirrors the account nonce of the runtime: the nonce of an account is incremented
+//! for every top-level execution it initiates, i.e. for every transaction it
+//! submits. The nonce of the origin is part of the derivation of contract
+//! addresses, see [`Engine::derive_contract_address`].
+//!
+//! Chains deriving addresses differently can be mirrored via
+//! [`Engine::set_address_derivation`].
+
+use crate::ext::Engine;
+use ink_prelude::vec::Vec;
+use scale::KeyedVec;
+
+const NONCE_OF: &[u8] = b"nonce:";
+const CONTRACT_ADDRESS: &[u8] = b"contract-address:";
+
+/// The arguments of an instantiation an address is derived from, see
+/// [`Engine::set_address_derivation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressDerivationInput<'a> {
+    /// The contract instantiating the new one.
+    pub deployer: &'a [u8],
+    /// The hash of the code of the new contract.
+    pub code_hash: &'a [u8],
+    /// The salt of the instantiation.
+    pub salt: &'a [u8],
+    /// The input of the constructor.
+    pub input: &'a [u8],
+    /// The nonce of the origin of the current execution.
+    pub nonce: u64,
+}
+
+/// Returns the database key under which to find the nonce of account `who`.
+fn nonce_of_key(who: &[u8]) -> [u8; 32] {
+    let keyed = who.to_vec().to_keyed_vec(NONCE_OF);
+    let mut hashed_key: [u8; 32] = [0; 32];
+    super::hashing::blake2b_256(&keyed[..], &mut hashed_key);
+    hashed_key
+}
+
+impl Engine {
+    /// Returns the nonce of `account_id`, `0` if it never initiated an execution.
+    pub fn get_nonce(&self, account_id: &[u8]) -> u64 {
+        self.database
+            .get(&nonce_of_key(account_id))
+            .map(|encoded_nonce| {
+                scale::Decode::decode(&mut &encoded_nonce[..])
+                    .expect("unable to decode nonce from database")
+            })
+            .unwrap_or_default()
+    }
+
+    /// Increments the nonce of `account_id` by one.
+    pub fn increment_nonce(&mut self, account_id: &[u8]) {
+        let nonce = self.get_nonce(account_id).saturating_add(1);
+        self.database.insert(
+            nonce_of_key(account_id).to_vec(),
+            scale::Encode::encode(&nonce),
+        );
+    }
+
+    /// Returns the address of the contract the current contract would instantiate
+    /// from the code with `code_hash` with `input` and `salt`.
+    ///
+    /// Like on-chain, the address is derived from the deployer and the instantiation
+    /// arguments. Additionally the nonce of the origin of the current execution is
+    /// part of the derivation, hence repeated identical instantiations in separate
+    /// top-level executions yield distinct addresses.
+    ///
+    /// The function set via [`Engine::set_address_derivation`] replaces this scheme.
+    ///
+    /// # Panics
+    ///
+    /// - If no callee has been set.
+    /// - If the address derived by the function set via
+    ///   [`Engine::set_address_derivation`] does not have the account length of the
+    ///   chain.
+    pub fn derive_contract_address(
+        &self,
+        code_hash: &[u8],
+        input: &[u8],
+        salt: &[u8],
+    ) -> Vec<u8> {
+        let deployer = self.get_callee();
+        let nonce = self
+            .exec_context
+            .origin()
+            .map(|origin| self.get_nonce(origin.as_bytes()))
+            .unwrap_or_default();
+        if let Some(derive) = &self.address_derivation {
+            let address = derive(&AddressDerivationInput {
+                deployer: &deployer,
+                code_hash,
+                salt,
+                input,
+                nonce,
+            });
+            let account_length = self.chain_spec.account_length;
+            assert_eq!(
+                address.len(),
+                account_length,
+                "the address derivation returned an address of {} bytes, but accounts \
+                 of the chain have {account_length} bytes",
+                address.len(),
+            );
+            return address
+        }
+        let keyed = [
+            &deployer[..],
+            &scale::Encode::encode(&nonce),
+            code_hash,
+            input,
+            salt,
+        ]
+        .concat()
+        .to_keyed_vec(CONTRACT_ADDRESS);
+        let mut address = [0; 32];
+        super::hashing::blake2b_256(&keyed[..], &mut address);
+        address.to_vec()
+    }
+}
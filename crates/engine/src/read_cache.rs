@@ -0,0 +1,119 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Caching of contract storage reads per execution frame.
+//!
+//! Contracts with lazy collections read the same keys many times per message. The
+//! values read via [`Engine::get_storage`] and [`Engine::contains_storage`] are
+//! cached until the current execution frame is entered or left, see
+//! [`Engine::enter_frame`]. Writes and removals of a key invalidate its entry.
+//!
+//! A read served from the cache still counts as a read of the contract storage,
+//! unless [`crate::ext::EngineConfig::count_physical_reads_only`] is set. The
+//! hits and misses of the cache are part of the statistics of the contract, see
+//! [`Engine::contract_statistics`].
+
+use crate::{
+    ext::Engine,
+    types::AccountId,
+    HashMap,
+};
+use ink_prelude::{
+    rc::Rc,
+    vec::Vec,
+};
+
+/// The values read in the current frame by contract and key, `None` for missing
+/// keys.
+type Entries = HashMap<Vec<u8>, HashMap<Vec<u8>, Option<Rc<[u8]>>>>;
+
+/// The cached storage reads of the current frame.
+#[derive(Clone, Default)]
+pub(crate) struct ReadCache {
+    /// The cached values of the current frame.
+    entries: Entries,
+    /// The number of cache hits and misses by contract.
+    counters: HashMap<AccountId, (usize, usize)>,
+}
+
+impl ReadCache {
+    /// Drops the cached values, e.g. when the current frame changes.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Drops the cached values and the counters.
+    pub(crate) fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Drops the cached value at `key` of `account_id`, if any.
+    pub(crate) fn invalidate(&mut self, account_id: &[u8], key: &[u8]) {
+        if let Some(entries) = self.entries.get_mut(account_id) {
+            entries.remove(key);
+        }
+    }
+}
+
+impl Engine {
+    /// Returns the value at the contract storage `key` of `account_id`, if any, and
+    /// whether it was served from the read cache of the current frame.
+    pub(crate) fn cached_read(
+        &mut self,
+        account_id: &[u8],
+        key: &[u8],
+    ) -> (Option<Rc<[u8]>>, bool) {
+        let cached = self
+            .read_cache
+            .entries
+            .get(account_id)
+            .and_then(|entries| entries.get(key))
+            .cloned();
+        let (hits, misses) = self
+            .read_cache
+            .counters
+            .entry(AccountId::from_bytes(account_id))
+            .or_default();
+        if let Some(value) = cached {
+            *hits += 1;
+            return (value, true)
+        }
+        *misses += 1;
+        let value = self.read_storage(account_id, key).map(Rc::from);
+        self.read_cache
+            .entries
+            .entry(account_id.to_vec())
+            .or_default()
+            .insert(key.to_vec(), value.clone());
+        (value, false)
+    }
+
+    /// Returns the number of reads of the storage of `account_id` served from the
+    /// read cache.
+    pub fn cache_hits(&self, account_id: &[u8]) -> usize {
+        self.read_cache
+            .counters
+            .get(&AccountId::from_bytes(account_id))
+            .map_or(0, |(hits, _)| *hits)
+    }
+
+    /// Returns the number of reads of the storage of `account_id` which missed the
+    /// read cache.
+    pub fn cache_misses(&self, account_id: &[u8]) -> usize {
+        self.read_cache
+            .counters
+            .get(&AccountId::from_bytes(account_id))
+            .map_or(0, |(_, misses)| *misses)
+    }
+}
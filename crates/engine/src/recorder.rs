@@ -0,0 +1,451 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recording of the statistics of the interactions with the engine.
+//!
+//! The engine reports storage accesses, emitted events and debug messages to its
+//! [`Recorder`]. By default this is an [`InMemoryRecorder`], which backs the
+//! statistics accessors of the engine like [`Engine::count_reads`] and
+//! [`Engine::get_emitted_events`]. Other recorders, e.g. streaming the statistics
+//! to a file, are set via [`Engine::set_recorder`]. While one is set, the accessors
+//! report no statistics.
+//!
+//! The execution frames, warnings and transfers are recorded by the engine itself,
+//! independent of the recorder.
+//!
+//! The events and debug messages held by an [`InMemoryRecorder`] and the recorded
+//! calls grow without bound by default. Long running tests, e.g. fuzzing campaigns,
+//! can limit their retention via [`crate::ext::EngineConfig::max_recorded_events`],
+//! [`crate::ext::EngineConfig::max_recorded_printlns`] and
+//! [`crate::ext::EngineConfig::max_recorded_calls`]. The oldest entries are dropped
+//! first, the number of dropped entries is reported by [`Engine::dropped_records`].
+//!
+//! The events, debug messages and storage accesses recorded within the execution
+//! frames, see [`Engine::recorded_frames`], are limited likewise, the storage
+//! accesses via [`crate::ext::EngineConfig::max_recorded_storage_accesses`]. The
+//! number of items dropped from a frame is reported by
+//! [`crate::test_api::FrameRecord::dropped`].
+
+use crate::{
+    ext::Engine,
+    test_api::{
+        EmittedEvent,
+        FrameItem,
+        RecordedDebugMessages,
+    },
+    types::AccountId,
+    HashMap,
+};
+use ink_prelude::{
+    boxed::Box,
+    collections::VecDeque,
+    string::String,
+    vec::Vec,
+};
+use serde::Serialize;
+
+/// Receives the statistics of the interactions with the engine.
+pub trait Recorder {
+    /// Invoked when the storage of `account_id` is read.
+    fn inc_reads(&mut self, account_id: &AccountId);
+
+    /// Invoked when the storage of `account_id` is written to.
+    fn inc_writes(&mut self, account_id: &AccountId);
+
+    /// Invoked when a key of the storage of `account_id` is cleared which holds no
+    /// value. Such a clear is no write, see [`Recorder::inc_writes`].
+    fn inc_noop_clears(&mut self, _account_id: &AccountId) {}
+
+    /// Invoked after a write to a key of the storage of `account_id` which held no
+    /// value, i.e. a write creating a cell. Such a write is also counted by
+    /// [`Recorder::inc_writes`], overwrites are not counted here.
+    fn inc_new_cells(&mut self, _account_id: &AccountId) {}
+
+    /// Invoked once when `count` cells of the storage of `account_id` are seeded in
+    /// a batch, see [`Engine::seed_storage`]. Counts as `count` writes by default.
+    fn record_batch_write(&mut self, account_id: &AccountId, count: usize) {
+        for _ in 0..count {
+            self.inc_writes(account_id);
+        }
+    }
+
+    /// Invoked when `event` is emitted by the contract `emitter`, `None` outside of
+    /// a contract execution.
+    fn record_event(&mut self, emitter: Option<&AccountId>, event: &EmittedEvent);
+
+    /// Invoked when a debug message is printed.
+    fn record_println(&mut self, message: &str);
+
+    /// Invoked when the engine is reset, see [`Engine::initialize_or_reset`].
+    fn reset(&mut self) {}
+
+    /// Returns a copy of the recorder for a clone of the engine.
+    fn clone_box(&self) -> Box<dyn Recorder>;
+
+    /// Returns the recorder if it is the [`InMemoryRecorder`] backing the accessors
+    /// of the engine.
+    fn in_memory(&self) -> Option<&InMemoryRecorder> {
+        None
+    }
+
+    /// Returns the recorder mutably if it is an [`InMemoryRecorder`].
+    fn in_memory_mut(&mut self) -> Option<&mut InMemoryRecorder> {
+        None
+    }
+}
+
+impl Clone for Box<dyn Recorder> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// The default recorder, holding the statistics in memory.
+#[derive(Clone, Default)]
+pub struct InMemoryRecorder {
+    /// Emitted events recorder.
+    pub(crate) emitted_events: VecDeque<EmittedEvent>,
+    /// The number of events dropped to stay within
+    /// [`crate::ext::EngineConfig::max_recorded_events`].
+    pub(crate) dropped_events: usize,
+    /// The number of debug messages dropped to stay within
+    /// [`crate::ext::EngineConfig::max_recorded_printlns`].
+    pub(crate) dropped_printlns: usize,
+    /// Emitted print messages recorder.
+    pub(crate) emitted_debug_messages: RecordedDebugMessages,
+    /// The total number of reads to the storage.
+    pub(crate) count_reads: HashMap<AccountId, usize>,
+    /// The total number of writes to the storage.
+    pub(crate) count_writes: HashMap<AccountId, usize>,
+    /// The total number of clears of keys without a value.
+    pub(crate) count_noop_clears: HashMap<AccountId, usize>,
+    /// The total number of writes creating a cell.
+    pub(crate) count_new_cells: HashMap<AccountId, usize>,
+    /// The number of emitted events and their bytes by the emitting contract.
+    pub(crate) count_events: HashMap<AccountId, (u32, u64)>,
+}
+
+impl InMemoryRecorder {
+    /// Creates an empty recorder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of reads of the storage of `account_id`.
+    pub fn reads(&self, account_id: &[u8]) -> usize {
+        self.count_reads
+            .get(&AccountId::from_bytes(account_id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of writes to the storage of `account_id`.
+    pub fn writes(&self, account_id: &[u8]) -> usize {
+        self.count_writes
+            .get(&AccountId::from_bytes(account_id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of clears of keys without a value in the storage of
+    /// `account_id`.
+    pub fn noop_clears(&self, account_id: &[u8]) -> usize {
+        self.count_noop_clears
+            .get(&AccountId::from_bytes(account_id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of writes to the storage of `account_id` which created a
+    /// cell, i.e. did not overwrite a value.
+    pub fn new_cells(&self, account_id: &[u8]) -> usize {
+        self.count_new_cells
+            .get(&AccountId::from_bytes(account_id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the recorded events in the order they were emitted.
+    pub fn events(&self) -> &VecDeque<EmittedEvent> {
+        &self.emitted_events
+    }
+
+    /// Returns the number of events emitted so far, including the dropped ones.
+    pub(crate) fn total_events(&self) -> usize {
+        self.dropped_events + self.emitted_events.len()
+    }
+
+    /// Returns the recorded debug messages.
+    pub fn debug_messages(&self) -> &RecordedDebugMessages {
+        &self.emitted_debug_messages
+    }
+}
+
+impl Recorder for InMemoryRecorder {
+    fn inc_reads(&mut self, account_id: &AccountId) {
+        *self.count_reads.entry(account_id.clone()).or_default() += 1;
+    }
+
+    fn inc_writes(&mut self, account_id: &AccountId) {
+        *self.count_writes.entry(account_id.clone()).or_default() += 1;
+    }
+
+    fn inc_noop_clears(&mut self, account_id: &AccountId) {
+        *self
+            .count_noop_clears
+            .entry(account_id.clone())
+            .or_default() += 1;
+    }
+
+    fn inc_new_cells(&mut self, account_id: &AccountId) {
+        *self.count_new_cells.entry(account_id.clone()).or_default() += 1;
+    }
+
+    fn record_batch_write(&mut self, account_id: &AccountId, count: usize) {
+        *self.count_writes.entry(account_id.clone()).or_default() += count;
+    }
+
+    fn record_event(&mut self, emitter: Option<&AccountId>, event: &EmittedEvent) {
+        if let Some(emitter) = emitter {
+            let topic_bytes: usize = event.topics.iter().map(Vec::len).sum();
+            let bytes = (topic_bytes + event.data.len()) as u64;
+            let (events, event_bytes) =
+                self.count_events.entry(emitter.clone()).or_default();
+            *events += 1;
+            *event_bytes += bytes;
+        }
+        self.emitted_events.push_back(event.clone());
+    }
+
+    fn record_println(&mut self, message: &str) {
+        self.emitted_debug_messages.record(String::from(message));
+    }
+
+    /// Clears the statistics and releases the memory held by them.
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    fn clone_box(&self) -> Box<dyn Recorder> {
+        Box::new(self.clone())
+    }
+
+    fn in_memory(&self) -> Option<&InMemoryRecorder> {
+        Some(self)
+    }
+
+    fn in_memory_mut(&mut self) -> Option<&mut InMemoryRecorder> {
+        Some(self)
+    }
+}
+
+#[cfg(feature = "csv-recorder")]
+pub use self::csv::CsvRecorder;
+
+#[cfg(feature = "csv-recorder")]
+mod csv {
+    use super::Recorder;
+    use crate::{
+        test_api::EmittedEvent,
+        types::AccountId,
+    };
+    use std::{
+        cell::RefCell,
+        io::Write,
+        rc::Rc,
+    };
+
+    /// A recorder writing every statistic as a row `kind,account,detail` in CSV
+    /// format to a writer, e.g. a file, instead of holding it in memory.
+    ///
+    /// The rows are:
+    ///
+    ///   * `read,<account>,` and `write,<account>,` for storage accesses.
+    ///   * `new-cell,<account>,` following the `write` row of writes creating a cell.
+    ///   * `noop-clear,<account>,` for clears of keys without a value.
+    ///   * `batch-write,<account>,<count>` for cells seeded in a batch.
+    ///   * `event,<emitter>,<bytes>` for events, with the bytes of their topics and data.
+    ///     The emitter is empty outside of contract executions.
+    ///   * `println,,<message>` for debug messages, quoted.
+    ///
+    /// Accounts are hex encoded. Clones of the recorder write to the same writer.
+    ///
+    /// # Panics
+    ///
+    /// The recorder panics if writing a row fails.
+    pub struct CsvRecorder<W> {
+        writer: Rc<RefCell<W>>,
+    }
+
+    impl<W> CsvRecorder<W>
+    where
+        W: Write + 'static,
+    {
+        /// Creates a recorder writing the header `kind,account,detail` to `writer`.
+        pub fn new(writer: W) -> Self {
+            let recorder = Self {
+                writer: Rc::new(RefCell::new(writer)),
+            };
+            recorder.write_row("kind", "account", "detail");
+            recorder
+        }
+
+        /// Returns the writer, e.g. to read the rows back after the recorder has
+        /// been passed to the engine.
+        pub fn writer(&self) -> Rc<RefCell<W>> {
+            Rc::clone(&self.writer)
+        }
+
+        fn write_row(&self, kind: &str, account: &str, detail: &str) {
+            writeln!(self.writer.borrow_mut(), "{kind},{account},{detail}")
+                .unwrap_or_else(|error| panic!("writing the CSV row failed: {error}"));
+        }
+    }
+
+    impl<W> Recorder for CsvRecorder<W>
+    where
+        W: Write + 'static,
+    {
+        fn inc_reads(&mut self, account_id: &AccountId) {
+            self.write_row("read", &account_id.to_hex(), "");
+        }
+
+        fn inc_writes(&mut self, account_id: &AccountId) {
+            self.write_row("write", &account_id.to_hex(), "");
+        }
+
+        fn inc_noop_clears(&mut self, account_id: &AccountId) {
+            self.write_row("noop-clear", &account_id.to_hex(), "");
+        }
+
+        fn inc_new_cells(&mut self, account_id: &AccountId) {
+            self.write_row("new-cell", &account_id.to_hex(), "");
+        }
+
+        fn record_batch_write(&mut self, account_id: &AccountId, count: usize) {
+            self.write_row("batch-write", &account_id.to_hex(), &count.to_string());
+        }
+
+        fn record_event(&mut self, emitter: Option<&AccountId>, event: &EmittedEvent) {
+            let emitter = emitter.map(AccountId::to_hex).unwrap_or_default();
+            let topic_bytes: usize = event.topics.iter().map(Vec::len).sum();
+            let bytes = topic_bytes + event.data.len();
+            self.write_row("event", &emitter, &bytes.to_string());
+        }
+
+        fn record_println(&mut self, message: &str) {
+            let quoted = format!("\"{}\"", message.replace('"', "\"\""));
+            self.write_row("println", "", &quoted);
+        }
+
+        fn clone_box(&self) -> Box<dyn Recorder> {
+            Box::new(Self {
+                writer: Rc::clone(&self.writer),
+            })
+        }
+    }
+}
+
+/// The number of recorded entries dropped to stay within the retention limits of
+/// the engine, see [`Engine::dropped_records`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DroppedRecords {
+    /// The number of dropped events, see
+    /// [`crate::ext::EngineConfig::max_recorded_events`].
+    pub events: usize,
+    /// The number of dropped debug messages, see
+    /// [`crate::ext::EngineConfig::max_recorded_printlns`].
+    pub printlns: usize,
+    /// The number of dropped calls, see
+    /// [`crate::ext::EngineConfig::max_recorded_calls`].
+    pub calls: usize,
+}
+
+/// Drops the oldest `entries` beyond `limit`, if any, and returns their number.
+pub(crate) fn retain_last<T>(entries: &mut VecDeque<T>, limit: Option<usize>) -> usize {
+    let excess = limit.map_or(0, |limit| entries.len().saturating_sub(limit));
+    entries.drain(..excess);
+    excess
+}
+
+impl Engine {
+    /// Replaces the recorder of the engine by `recorder` and returns the previous
+    /// one, together with the statistics it holds.
+    ///
+    /// The statistics accessors of the engine, like [`Engine::count_reads`], only
+    /// report statistics while an [`InMemoryRecorder`] is set.
+    pub fn set_recorder(&mut self, recorder: Box<dyn Recorder>) -> Box<dyn Recorder> {
+        core::mem::replace(&mut self.recorder, recorder)
+    }
+
+    /// Returns the recorder of the engine.
+    pub fn recorder(&self) -> &dyn Recorder {
+        &*self.recorder
+    }
+
+    /// Records the emitted `event` within the current frame and reports it to the
+    /// recorder, attributed to the current callee.
+    pub(crate) fn record_event(&mut self, event: EmittedEvent) {
+        let emitter = self.exec_context.callee().cloned();
+        self.recorder.record_event(emitter.as_ref(), &event);
+        if let Some(recorder) = self.recorder.in_memory_mut() {
+            recorder.dropped_events += retain_last(
+                &mut recorder.emitted_events,
+                self.config.max_recorded_events,
+            );
+        }
+        self.debug_info
+            .record_event(event, self.config.max_recorded_events);
+    }
+
+    /// Records the printed debug `message` within the current frame and reports it
+    /// to the recorder.
+    pub(crate) fn record_println(&mut self, message: &str) {
+        self.recorder.record_println(message);
+        if let Some(recorder) = self.recorder.in_memory_mut() {
+            recorder.dropped_printlns += recorder
+                .emitted_debug_messages
+                .retain_last(self.config.max_recorded_printlns);
+        }
+        self.debug_info.record_debug_message(
+            String::from(message),
+            self.config.max_recorded_printlns,
+        );
+    }
+
+    /// Records the storage `access`, a [`FrameItem::StorageRead`] or
+    /// [`FrameItem::StorageWrite`], within the current frame.
+    pub(crate) fn record_storage_access(&mut self, access: FrameItem) {
+        self.debug_info.record_retained_frame_item(
+            access,
+            self.config.max_recorded_storage_accesses,
+        );
+    }
+
+    /// Returns the number of recorded entries dropped so far to stay within the
+    /// retention limits of the engine configuration.
+    ///
+    /// The dropped events and debug messages are only counted while an
+    /// [`InMemoryRecorder`] is set.
+    pub fn dropped_records(&self) -> DroppedRecords {
+        let (events, printlns) = self.recorder.in_memory().map_or((0, 0), |recorder| {
+            (recorder.dropped_events, recorder.dropped_printlns)
+        });
+        DroppedRecords {
+            events,
+            printlns,
+            calls: self.debug_info.dropped_calls,
+        }
+    }
+}
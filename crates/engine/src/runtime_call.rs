@@ -0,0 +1,90 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Simulation of the runtime calls dispatched by contracts via `call_runtime`.
+//!
+//! The engine has no runtime, the outcome of a runtime call is determined by the
+//! handler registered via [`Engine::register_runtime_call_handler`]. Without a
+//! handler every runtime call is rejected.
+
+use crate::ext::Engine;
+use core::cell::RefCell;
+use ink_prelude::{
+    boxed::Box,
+    rc::Rc,
+    vec::Vec,
+};
+
+/// Errors of runtime calls as reported by a runtime call handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeCallError {
+    /// The runtime does not allow contracts to dispatch the call.
+    Filtered,
+    /// The call was dispatched but returned an error.
+    DispatchFailed,
+}
+
+/// Determines the outcome of the SCALE encoded runtime call it is invoked with.
+pub type RuntimeCallHandler = Box<dyn FnMut(&[u8]) -> Result<(), RuntimeCallError>>;
+
+/// Record of a runtime call dispatched by a contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeCall {
+    /// The account of the contract which dispatched the call.
+    pub origin: Vec<u8>,
+    /// The SCALE encoded call.
+    pub call: Vec<u8>,
+    /// The outcome of the call.
+    pub result: Result<(), RuntimeCallError>,
+}
+
+impl Engine {
+    /// Registers the handler which determines the outcome of runtime calls.
+    ///
+    /// Replaces a previously registered handler. The handler has no access to the
+    /// engine, hence it cannot change its state.
+    pub fn register_runtime_call_handler<F>(&mut self, handler: F)
+    where
+        F: FnMut(&[u8]) -> Result<(), RuntimeCallError> + 'static,
+    {
+        let handler: RuntimeCallHandler = Box::new(handler);
+        self.runtime_call_handler = Some(Rc::new(RefCell::new(handler)));
+    }
+
+    /// Returns the runtime calls dispatched by contracts in order.
+    pub fn recorded_runtime_calls(&self) -> &[RuntimeCall] {
+        &self.runtime_calls
+    }
+
+    /// Dispatches the SCALE encoded `call` to the runtime call handler and records
+    /// it.
+    ///
+    /// Rejects the call with [`RuntimeCallError::Filtered`] if no handler has been
+    /// registered.
+    pub(crate) fn dispatch_runtime_call(
+        &mut self,
+        call: &[u8],
+    ) -> Result<(), RuntimeCallError> {
+        let result = match &self.runtime_call_handler {
+            Some(handler) => (handler.borrow_mut())(call),
+            None => Err(RuntimeCallError::Filtered),
+        };
+        self.runtime_calls.push(RuntimeCall {
+            origin: self.get_callee(),
+            call: call.to_vec(),
+            result,
+        });
+        result
+    }
+}
@@ -0,0 +1,421 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recording of the interactions with the engine and their replay.
+//!
+//! A recorded [`Scenario`] can be serialized, e.g. to attach it to a bug report,
+//! and replayed against a fresh engine via [`Engine::replay`]. A
+//! [`ScenarioReplayer`] replays it one operation at a time, pausing at breakpoints,
+//! so the state of the engine can be inspected in between.
+
+use crate::{
+    exec_context::Origin,
+    ext::{
+        Clock,
+        Engine,
+        WeightToFee,
+    },
+    types::{
+        Balance,
+        BlockNumber,
+        BlockTimestamp,
+    },
+};
+use core::cell::RefCell;
+use ink_prelude::{
+    boxed::Box,
+    rc::Rc,
+    string::String,
+    vec::Vec,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// An interaction with the engine together with its outcome.
+///
+/// New variants are appended, since the SCALE encoding of an operation is part of
+/// the format of [`crate::activity::ActivityLog`].
+#[derive(
+    Debug, Clone, PartialEq, Eq, Serialize, Deserialize, scale::Encode, scale::Decode,
+)]
+pub enum Operation {
+    /// The caller was set.
+    SetCaller(Vec<u8>),
+    /// The callee was set.
+    SetCallee(Vec<u8>),
+    /// The account was made a known contract.
+    SetContract(Vec<u8>),
+    /// The balance of the account was set.
+    SetBalance {
+        account_id: Vec<u8>,
+        balance: Balance,
+    },
+    /// The transferred value was set.
+    SetValueTransferred(Balance),
+    /// The block timestamp was set.
+    SetBlockTimestamp(BlockTimestamp),
+    /// The block number was set.
+    SetBlockNumber(BlockNumber),
+    /// The chain advanced by a block with the given timestamp.
+    AdvanceBlock { timestamp: BlockTimestamp },
+    /// The execution of a contract was entered.
+    EnterFrame { callee: Vec<u8>, input: Vec<u8> },
+    /// The execution of delegated code was entered, `input` is `None` if the input
+    /// was kept.
+    EnterDelegateFrame { input: Option<Vec<u8>> },
+    /// The execution of a contract was exited.
+    ExitFrame,
+    /// The execution of a contract reverted or trapped.
+    RevertFrame,
+    /// A synthetic execution called by the contract was entered.
+    PushCallerFrame(Vec<u8>),
+    /// A synthetic execution was exited.
+    PopCallerFrame,
+    /// Value was transferred, `error` is the code of the error it failed with.
+    Transfer {
+        account_id: Vec<u8>,
+        value: Vec<u8>,
+        error: Option<u32>,
+    },
+    /// An event was deposited.
+    DepositEvent { topics: Vec<u8>, data: Vec<u8> },
+    /// A debug message was emitted.
+    DebugMessage(String),
+    /// A storage cell was written, replacing a value of `previous_size` bytes.
+    SetStorage {
+        key: Vec<u8>,
+        value: Vec<u8>,
+        previous_size: Option<u32>,
+    },
+    /// A storage cell was read.
+    GetStorage {
+        key: Vec<u8>,
+        value: Option<Vec<u8>>,
+    },
+    /// A storage cell was read and removed.
+    TakeStorage {
+        key: Vec<u8>,
+        value: Option<Vec<u8>>,
+    },
+    /// The existence of a storage cell was checked.
+    ContainsStorage { key: Vec<u8>, size: Option<u32> },
+    /// A storage cell was removed.
+    ClearStorage { key: Vec<u8>, size: Option<u32> },
+    /// The fee for an amount of gas was computed.
+    WeightToFee { gas: u64, fee: Balance },
+    /// The origin was set.
+    SetOrigin(Origin),
+    /// The chain was reverted to the block.
+    RevertToBlock(BlockNumber),
+    /// The storage of the account was seeded with the entries.
+    SeedStorage {
+        account_id: Vec<u8>,
+        entries: Vec<(Vec<u8>, Vec<u8>)>,
+    },
+    /// The timestamp was advanced by the milliseconds within the current block.
+    AdvanceTime(u64),
+    /// Value was transferred between two accounts to set up a test, `error` is the
+    /// code of the error it failed with.
+    TransferBetween {
+        from: Vec<u8>,
+        to: Vec<u8>,
+        value: Balance,
+        error: Option<u32>,
+    },
+}
+
+/// The interactions with the engine in the order they happened.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scenario {
+    /// The recorded operations.
+    pub operations: Vec<Operation>,
+}
+
+/// The replay of a scenario diverged from its recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// The index of the first diverging operation.
+    pub index: usize,
+    /// The operation as it was recorded.
+    pub recorded: Operation,
+    /// The operation as it was replayed.
+    pub replayed: Operation,
+}
+
+impl Engine {
+    /// Starts recording the interactions with the engine.
+    ///
+    /// Discards the operations recorded by a previous recording which has not
+    /// been stopped.
+    pub fn start_recording_scenario(&mut self) {
+        self.scenario = Some(Scenario::default());
+    }
+
+    /// Stops recording the interactions with the engine and returns them.
+    ///
+    /// Returns an empty scenario if no recording was started.
+    pub fn stop_recording_scenario(&mut self) -> Scenario {
+        self.scenario.take().unwrap_or_default()
+    }
+
+    /// Records `operation` if a scenario is being recorded.
+    pub(crate) fn record_operation(&mut self, operation: Operation) {
+        if let Some(scenario) = self.scenario.as_mut() {
+            scenario.operations.push(operation);
+        }
+    }
+
+    /// Replays `scenario` against a fresh engine and returns the engine.
+    ///
+    /// Timestamps derived by a clock and fees computed by a fee function during the
+    /// recording are replayed as they were recorded.
+    ///
+    /// # Errors
+    ///
+    /// If the outcome of an operation differs from its recording.
+    pub fn replay(scenario: &Scenario) -> Result<Engine, Box<Divergence>> {
+        let mut replayer = ScenarioReplayer::new(scenario.clone());
+        while replayer.step()? {}
+        Ok(replayer.into_engine())
+    }
+
+    /// Applies the interaction of `operation` to the engine.
+    fn apply(&mut self, operation: &Operation) {
+        match operation.clone() {
+            Operation::SetCaller(caller) => self.set_caller(caller),
+            Operation::SetCallee(callee) => self.set_callee(callee),
+            Operation::SetContract(account_id) => self.set_contract(account_id),
+            Operation::SetBalance {
+                account_id,
+                balance,
+            } => self.set_balance(account_id, balance),
+            Operation::SetValueTransferred(value) => self.set_value_transferred(value),
+            Operation::SetBlockTimestamp(timestamp) => {
+                self.set_block_timestamp(timestamp)
+            }
+            Operation::SetBlockNumber(number) => self.set_block_number(number),
+            Operation::AdvanceBlock { timestamp } => {
+                let recorded: Clock = Box::new(move |_| timestamp);
+                let clock = self.clock.replace(Rc::new(RefCell::new(recorded)));
+                self.advance_block();
+                self.clock = clock;
+            }
+            Operation::RevertToBlock(number) => {
+                let _ = self.revert_to_block(number);
+            }
+            Operation::SeedStorage {
+                account_id,
+                entries,
+            } => {
+                self.seed_storage(&account_id, entries);
+            }
+            Operation::AdvanceTime(ms) => self.advance_time(ms),
+            Operation::TransferBetween {
+                from, to, value, ..
+            } => {
+                let _ = self.transfer_between(&from, &to, value);
+            }
+            Operation::EnterFrame { callee, input } => self.enter_frame(callee, input),
+            Operation::EnterDelegateFrame { input } => self.enter_delegate_frame(input),
+            Operation::ExitFrame => self.exit_frame(),
+            Operation::RevertFrame => self.revert_frame(Vec::new()),
+            Operation::PushCallerFrame(caller) => self.push_caller_frame(caller),
+            Operation::PopCallerFrame => self.pop_caller_frame(),
+            Operation::Transfer {
+                account_id, value, ..
+            } => {
+                let _ = self.transfer(&account_id, &value);
+            }
+            Operation::DepositEvent { topics, data } => {
+                self.deposit_event(&topics, &data)
+            }
+            Operation::DebugMessage(message) => self.debug_message(&message),
+            Operation::SetStorage { key, value, .. } => {
+                self.set_storage(&key, &value);
+            }
+            Operation::GetStorage { key, .. } => {
+                let _ = self.get_storage(&key);
+            }
+            Operation::TakeStorage { key, .. } => {
+                let _ = self.take_storage(&key);
+            }
+            Operation::ContainsStorage { key, .. } => {
+                let _ = self.try_contains_storage(&key);
+            }
+            Operation::ClearStorage { key, .. } => {
+                let _ = self.try_clear_storage(&key);
+            }
+            Operation::WeightToFee { gas, fee } => {
+                let recorded: WeightToFee = Box::new(move |_| fee);
+                let weight_to_fee = self.weight_to_fee_fn.replace(Rc::new(recorded));
+                self.weight_to_fee(gas, &mut &mut [0; 16][..]);
+                self.weight_to_fee_fn = weight_to_fee;
+            }
+            Operation::SetOrigin(origin) => self.set_origin(origin),
+        }
+    }
+}
+
+/// A condition pausing a [`ScenarioReplayer`] before an operation.
+#[derive(Clone)]
+enum Breakpoint {
+    /// Pauses before operations for which the predicate holds.
+    Operation(fn(&Operation) -> bool),
+    /// Pauses before operations writing or removing the storage cell at the key.
+    StorageWrite(Vec<u8>),
+}
+
+impl Breakpoint {
+    /// Returns `true` if the replay pauses before `operation`.
+    fn matches(&self, operation: &Operation) -> bool {
+        match self {
+            Self::Operation(predicate) => predicate(operation),
+            Self::StorageWrite(key) => {
+                match operation {
+                    Operation::SetStorage { key: written, .. }
+                    | Operation::TakeStorage { key: written, .. }
+                    | Operation::ClearStorage { key: written, .. } => written == key,
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+/// Replays a [`Scenario`] step-wise against its own fresh engine.
+///
+/// The state of the engine can be inspected between the steps via
+/// [`ScenarioReplayer::engine`]. Every replayed operation is checked against its
+/// recording like by [`Engine::replay`].
+pub struct ScenarioReplayer {
+    /// The engine the scenario is replayed against.
+    engine: Engine,
+    /// The recorded operations.
+    operations: Vec<Operation>,
+    /// The index of the next operation to replay.
+    position: usize,
+    /// The conditions pausing [`ScenarioReplayer::resume`].
+    breakpoints: Vec<Breakpoint>,
+    /// The index of the operation the replay is paused before, if any.
+    paused_at: Option<usize>,
+}
+
+impl ScenarioReplayer {
+    /// Creates a replayer of `scenario` positioned before its first operation.
+    pub fn new(scenario: Scenario) -> Self {
+        let mut engine = Engine::new();
+        engine.start_recording_scenario();
+        Self {
+            engine,
+            operations: scenario.operations,
+            position: 0,
+            breakpoints: Vec::new(),
+            paused_at: None,
+        }
+    }
+
+    /// Pauses [`ScenarioReplayer::resume`] before every operation for which
+    /// `predicate` holds, e.g. `|op| matches!(op, Operation::DepositEvent { .. })`.
+    pub fn break_on(&mut self, predicate: fn(&Operation) -> bool) {
+        self.breakpoints.push(Breakpoint::Operation(predicate));
+    }
+
+    /// Pauses [`ScenarioReplayer::resume`] before every operation writing or removing
+    /// the storage cell at `key`.
+    pub fn break_on_storage_write(&mut self, key: &[u8]) {
+        self.breakpoints
+            .push(Breakpoint::StorageWrite(key.to_vec()));
+    }
+
+    /// Returns the engine the scenario is replayed against.
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Returns the index of the next operation to replay.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the next operation to replay, `None` if the replay is finished.
+    pub fn next_operation(&self) -> Option<&Operation> {
+        self.operations.get(self.position)
+    }
+
+    /// Replays the next operation.
+    ///
+    /// Returns `false` if the replay was already finished.
+    ///
+    /// # Errors
+    ///
+    /// If the outcome of the operation differs from its recording. The operation is
+    /// applied to the engine nevertheless.
+    pub fn step(&mut self) -> Result<bool, Box<Divergence>> {
+        let Some(recorded) = self.operations.get(self.position) else {
+            return Ok(false)
+        };
+        let index = self.position;
+        self.position += 1;
+        self.paused_at = None;
+        self.engine.apply(recorded);
+        let replayed = self
+            .engine
+            .scenario
+            .as_mut()
+            .and_then(|scenario| scenario.operations.pop())
+            .expect("applying an operation records it");
+        if replayed != *recorded {
+            return Err(Box::new(Divergence {
+                index,
+                recorded: recorded.clone(),
+                replayed,
+            }))
+        }
+        Ok(true)
+    }
+
+    /// Replays operations until the next one matches a breakpoint.
+    ///
+    /// Returns the index of the operation the replay paused before, `None` if the
+    /// replay finished. If the replay is paused at a breakpoint, that operation is
+    /// replayed first.
+    ///
+    /// # Errors
+    ///
+    /// If the outcome of an operation differs from its recording.
+    pub fn resume(&mut self) -> Result<Option<usize>, Box<Divergence>> {
+        while let Some(operation) = self.next_operation() {
+            let paused = self.paused_at == Some(self.position);
+            if !paused
+                && self
+                    .breakpoints
+                    .iter()
+                    .any(|breakpoint| breakpoint.matches(operation))
+            {
+                self.paused_at = Some(self.position);
+                return Ok(self.paused_at)
+            }
+            self.step()?;
+        }
+        Ok(None)
+    }
+
+    /// Stops the replay and returns the engine.
+    pub fn into_engine(mut self) -> Engine {
+        self.engine.stop_recording_scenario();
+        self.engine
+    }
+}
@@ -0,0 +1,323 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Snapshots of the observable state of the engine and the differences between them.
+//!
+//! [`Engine::snapshot`] captures the balances, the contract storages and the number
+//! of events emitted by every contract. [`Snapshot::diff`] lists what changed
+//! between two snapshots, e.g. to assert that an operation is free of side effects
+//! via [`StateDiff::is_empty`].
+
+use crate::{
+    ext::Engine,
+    types::{
+        encode_hex,
+        Balance,
+    },
+};
+use core::fmt;
+use ink_prelude::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    format,
+    vec::Vec,
+};
+
+/// The observable state of the engine at some point, see [`Engine::snapshot`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    /// The balances by account id.
+    balances: BTreeMap<Vec<u8>, Balance>,
+    /// The storage values by key, by contract account id.
+    storage: BTreeMap<Vec<u8>, BTreeMap<Vec<u8>, Vec<u8>>>,
+    /// The number of events emitted by every contract which emitted any.
+    events: BTreeMap<Vec<u8>, u32>,
+}
+
+/// The change of a storage cell between two snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CellChange {
+    /// The cell has been written.
+    Added {
+        /// The written value.
+        value: Vec<u8>,
+    },
+    /// The cell has been removed.
+    Removed {
+        /// The value stored before.
+        value: Vec<u8>,
+    },
+    /// The value of the cell has been replaced.
+    Changed {
+        /// The value stored before.
+        old: Vec<u8>,
+        /// The value stored afterwards.
+        new: Vec<u8>,
+    },
+}
+
+/// A storage cell which differs between two snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellDiff {
+    /// The contract owning the cell.
+    pub account_id: Vec<u8>,
+    /// The key of the cell.
+    pub key: Vec<u8>,
+    /// How the cell changed.
+    pub change: CellChange,
+}
+
+/// A balance which differs between two snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceDiff {
+    /// The account owning the balance.
+    pub account_id: Vec<u8>,
+    /// The balance before, `0` for new accounts.
+    pub old: Balance,
+    /// The balance afterwards.
+    pub new: Balance,
+}
+
+/// A number of emitted events which differs between two snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventCountDiff {
+    /// The contract emitting the events.
+    pub account_id: Vec<u8>,
+    /// The number of events emitted before.
+    pub old: u32,
+    /// The number of events emitted afterwards.
+    pub new: u32,
+}
+
+/// The differences between two snapshots, see [`Snapshot::diff`].
+///
+/// All lists are ordered by account id and key bytes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    /// The storage cells added, removed or changed.
+    pub storage: Vec<CellDiff>,
+    /// The balances which changed.
+    pub balances: Vec<BalanceDiff>,
+    /// The accounts which are known afterwards but were not before.
+    pub new_accounts: Vec<Vec<u8>>,
+    /// The contracts which emitted events in between.
+    pub events: Vec<EventCountDiff>,
+}
+
+impl Snapshot {
+    /// Returns the differences from this snapshot to the `later` one.
+    pub fn diff(&self, later: &Snapshot) -> StateDiff {
+        let mut storage = Vec::new();
+        let contracts: BTreeSet<&Vec<u8>> =
+            self.storage.keys().chain(later.storage.keys()).collect();
+        for account_id in contracts {
+            let empty = BTreeMap::new();
+            let old = self.storage.get(account_id).unwrap_or(&empty);
+            let new = later.storage.get(account_id).unwrap_or(&empty);
+            let keys: BTreeSet<&Vec<u8>> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                let change = match (old.get(key), new.get(key)) {
+                    (None, Some(value)) => {
+                        CellChange::Added {
+                            value: value.clone(),
+                        }
+                    }
+                    (Some(value), None) => {
+                        CellChange::Removed {
+                            value: value.clone(),
+                        }
+                    }
+                    (Some(old), Some(new)) if old != new => {
+                        CellChange::Changed {
+                            old: old.clone(),
+                            new: new.clone(),
+                        }
+                    }
+                    _ => continue,
+                };
+                storage.push(CellDiff {
+                    account_id: account_id.clone(),
+                    key: key.clone(),
+                    change,
+                });
+            }
+        }
+        let balances = later
+            .balances
+            .iter()
+            .filter_map(|(account_id, &new)| {
+                let old = self.balances.get(account_id).copied().unwrap_or_default();
+                (old != new).then(|| {
+                    BalanceDiff {
+                        account_id: account_id.clone(),
+                        old,
+                        new,
+                    }
+                })
+            })
+            .collect();
+        let new_accounts = later
+            .accounts()
+            .difference(&self.accounts())
+            .map(|account_id| account_id.to_vec())
+            .collect();
+        let events = later
+            .events
+            .iter()
+            .filter_map(|(account_id, &new)| {
+                let old = self.events.get(account_id).copied().unwrap_or_default();
+                (old != new).then(|| {
+                    EventCountDiff {
+                        account_id: account_id.clone(),
+                        old,
+                        new,
+                    }
+                })
+            })
+            .collect();
+        StateDiff {
+            storage,
+            balances,
+            new_accounts,
+            events,
+        }
+    }
+
+    /// Returns the accounts with a balance or storage.
+    fn accounts(&self) -> BTreeSet<&[u8]> {
+        self.balances
+            .keys()
+            .chain(self.storage.keys())
+            .map(Vec::as_slice)
+            .collect()
+    }
+}
+
+impl StateDiff {
+    /// Returns `true` if the snapshots do not differ.
+    pub fn is_empty(&self) -> bool {
+        self.storage.is_empty()
+            && self.balances.is_empty()
+            && self.new_accounts.is_empty()
+            && self.events.is_empty()
+    }
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no changes")
+        }
+        let mut lines = Vec::new();
+        for account_id in &self.new_accounts {
+            lines.push(format!("new account {}", encode_hex(account_id)));
+        }
+        for balance in &self.balances {
+            let delta = if balance.new > balance.old {
+                format!("+{}", balance.new - balance.old)
+            } else {
+                format!("-{}", balance.old - balance.new)
+            };
+            lines.push(format!(
+                "balance of {}: {} -> {} ({delta})",
+                encode_hex(&balance.account_id),
+                balance.old,
+                balance.new,
+            ));
+        }
+        for cell in &self.storage {
+            let (account_id, key) = (encode_hex(&cell.account_id), encode_hex(&cell.key));
+            lines.push(match &cell.change {
+                CellChange::Added { value } => {
+                    format!(
+                        "storage of {account_id}: added {key} = {}",
+                        encode_hex(value)
+                    )
+                }
+                CellChange::Removed { value } => {
+                    format!(
+                        "storage of {account_id}: removed {key} = {}",
+                        encode_hex(value)
+                    )
+                }
+                CellChange::Changed { old, new } => {
+                    format!(
+                        "storage of {account_id}: changed {key} from {} to {}",
+                        encode_hex(old),
+                        encode_hex(new)
+                    )
+                }
+            });
+        }
+        for events in &self.events {
+            lines.push(format!(
+                "events of {}: {} -> {}",
+                encode_hex(&events.account_id),
+                events.old,
+                events.new,
+            ));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl Engine {
+    /// Returns a snapshot of the balances, the contract storages and the number of
+    /// events emitted by every contract.
+    ///
+    /// Compare snapshots via [`Snapshot::diff`].
+    pub fn snapshot(&self) -> Snapshot {
+        let balances = self
+            .database
+            .accounts()
+            .map(|account_id| {
+                let balance = self.database.get_balance(account_id).unwrap_or_default();
+                (account_id.to_vec(), balance)
+            })
+            .collect();
+        let storage = self
+            .debug_info
+            .cells_per_account
+            .keys()
+            .map(|account_id| {
+                let entries = self
+                    .storage_dump(account_id.as_bytes())
+                    .into_iter()
+                    .collect();
+                (account_id.as_bytes().to_vec(), entries)
+            })
+            .filter(|(_, entries): &(_, BTreeMap<_, _>)| !entries.is_empty())
+            .collect();
+        let events = self
+            .recorder
+            .in_memory()
+            .map(|recorder| {
+                recorder
+                    .count_events
+                    .iter()
+                    .map(|(account_id, (events, _))| {
+                        (account_id.as_bytes().to_vec(), *events)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Snapshot {
+            balances,
+            storage,
+            events,
+        }
+    }
+}
@@ -0,0 +1,226 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The state of the engine as exported by [`Engine::export_state_json`], and its
+//! import into an engine.
+//!
+//! States of version 1 kept the balances under hashed `balance:`-prefixed keys in
+//! the storage. They are migrated to the balances map on import.
+
+use crate::{
+    database::balance_of_key,
+    ext::Engine,
+    types::{
+        decode_hex_bytes,
+        encode_hex,
+        AccountId,
+        Balance,
+    },
+    HexError,
+};
+use ink_prelude::{
+    collections::BTreeMap,
+    string::{
+        String,
+        ToString,
+    },
+    vec::Vec,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// The version of the state exported by [`Engine::export_state_json`].
+pub const STATE_VERSION: u32 = 2;
+
+/// The balances and contract storages of an engine, as exported by
+/// [`Engine::export_state_json`].
+///
+/// Bytes are hex encoded with a `0x` prefix, balances are decimal strings. The
+/// weights of the export are not part of the state and ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EngineState {
+    /// The version of the format, `1` for states exported without one.
+    #[serde(default = "legacy_version")]
+    pub version: u32,
+    /// The balances by account id.
+    #[serde(default)]
+    pub balances: BTreeMap<String, String>,
+    /// The storage entries by key, by contract account id.
+    #[serde(default)]
+    pub storage: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+fn legacy_version() -> u32 {
+    1
+}
+
+/// Errors which can happen when migrating or importing an [`EngineState`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    /// The state was exported with a version this engine does not support.
+    UnsupportedVersion(u32),
+    /// An account id, storage key or value is not valid hex.
+    InvalidHex { value: String, error: HexError },
+    /// The balance of the account is not a valid balance.
+    InvalidBalance { account: String, balance: String },
+}
+
+impl EngineState {
+    /// Migrates a state of version 1 to version 2.
+    ///
+    /// Version 1 kept the balances as SCALE encoded storage entries under the
+    /// hashed `balance:`-prefixed keys of the accounts. For every account of the
+    /// state these keys are reconstructed, the entries are stripped from the
+    /// storage and their balances moved to [`EngineState::balances`], unless a
+    /// balance is listed there already. Contracts left without storage entries are
+    /// removed.
+    ///
+    /// Legacy entries of accounts which appear nowhere else in the state cannot be
+    /// detected and remain in the storage.
+    ///
+    /// States of version 2 are left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// - If the state has a version newer than [`STATE_VERSION`].
+    /// - If an account id or legacy entry is not valid hex, or a legacy entry not a valid
+    ///   balance.
+    pub fn migrate_v1_to_v2(&mut self) -> Result<(), StateError> {
+        match self.version {
+            1 => (),
+            STATE_VERSION => return Ok(()),
+            version => return Err(StateError::UnsupportedVersion(version)),
+        }
+        let mut accounts = Vec::new();
+        for account in self.balances.keys().chain(self.storage.keys()) {
+            if !accounts.contains(account) {
+                accounts.push(account.clone());
+            }
+        }
+        for account in accounts {
+            let legacy_key = encode_hex(&balance_of_key(&decode(&account)?));
+            let mut legacy_balance = None;
+            for entries in self.storage.values_mut() {
+                if let Some(encoded) = entries.remove(&legacy_key) {
+                    legacy_balance = Some(encoded);
+                }
+            }
+            if let Some(encoded) = legacy_balance {
+                let balance: Balance = scale::Decode::decode(&mut &decode(&encoded)?[..])
+                    .map_err(|_| {
+                        StateError::InvalidBalance {
+                            account: account.clone(),
+                            balance: encoded,
+                        }
+                    })?;
+                self.balances
+                    .entry(account)
+                    .or_insert_with(|| balance.to_string());
+            }
+        }
+        self.storage.retain(|_, entries| !entries.is_empty());
+        self.version = STATE_VERSION;
+        Ok(())
+    }
+}
+
+/// Returns the bytes of the hex encoded `value`.
+fn decode(value: &str) -> Result<Vec<u8>, StateError> {
+    decode_hex_bytes(value).map_err(|error| {
+        StateError::InvalidHex {
+            value: value.to_string(),
+            error,
+        }
+    })
+}
+
+impl Engine {
+    /// Returns the balances and contract storages of the engine, like
+    /// [`Engine::export_state_json`] without the weights.
+    pub fn export_state(&self) -> EngineState {
+        let balances = self
+            .database
+            .accounts()
+            .map(|account_id| {
+                let balance = self.database.get_balance(account_id).unwrap_or_default();
+                (encode_hex(account_id), balance.to_string())
+            })
+            .collect();
+        let storage = self
+            .debug_info
+            .cells_per_account
+            .keys()
+            .map(|account_id| {
+                let entries = self
+                    .storage_dump(account_id.as_bytes())
+                    .into_iter()
+                    .map(|(key, value)| (encode_hex(&key), encode_hex(&value)))
+                    .collect();
+                (encode_hex(account_id.as_bytes()), entries)
+            })
+            .collect();
+        EngineState {
+            version: STATE_VERSION,
+            balances,
+            storage,
+        }
+    }
+
+    /// Imports the balances and contract storages of `state` on top of the current
+    /// state of the engine.
+    ///
+    /// States of version 1 are migrated first, see
+    /// [`EngineState::migrate_v1_to_v2`]. Storage values are written as by the
+    /// contracts, i.e. encoded by the storage codec, but neither counted nor
+    /// charged for.
+    ///
+    /// # Errors
+    ///
+    /// If the state cannot be migrated or contains invalid hex or balances. The
+    /// engine is left unchanged then.
+    pub fn import_state(&mut self, mut state: EngineState) -> Result<(), StateError> {
+        state.migrate_v1_to_v2()?;
+        let balances = state
+            .balances
+            .iter()
+            .map(|(account, balance)| {
+                let balance = balance.parse::<Balance>().map_err(|_| {
+                    StateError::InvalidBalance {
+                        account: account.clone(),
+                        balance: balance.clone(),
+                    }
+                })?;
+                Ok((decode(account)?, balance))
+            })
+            .collect::<Result<Vec<_>, StateError>>()?;
+        let mut storage = Vec::new();
+        for (account, entries) in &state.storage {
+            let account = decode(account)?;
+            for (key, value) in entries {
+                storage.push((account.clone(), decode(key)?, decode(value)?));
+            }
+        }
+        for (account, balance) in balances {
+            self.database.set_balance(&account, balance);
+        }
+        for (account, key, value) in storage {
+            self.write_storage(&account, &key, &value);
+            self.debug_info
+                .record_cell_for_account(AccountId::from_bytes(&account), key);
+        }
+        Ok(())
+    }
+}
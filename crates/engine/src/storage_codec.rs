@@ -0,0 +1,176 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Codecs for the representation in which contract storage is stored.
+//!
+//! Contracts always observe their storage as they wrote it. The codec only
+//! determines the stored keys and values, which are inspected via
+//! [`Engine::raw_storage`], e.g. to test tools reading on-chain contract storage.
+
+use crate::ext::Engine;
+use ink_prelude::{
+    boxed::Box,
+    rc::Rc,
+    vec::Vec,
+};
+
+/// Determines the representation in which contract storage is stored.
+pub trait StorageCodec {
+    /// Returns the key under which the value at the contract storage `key` is
+    /// stored.
+    fn encode_key(&self, key: &[u8]) -> Vec<u8>;
+
+    /// Returns the representation in which `value` at the contract storage `key` is
+    /// stored.
+    ///
+    /// Defaults to `value` itself.
+    fn encode_value(&self, _key: &[u8], value: &[u8]) -> Vec<u8> {
+        value.to_vec()
+    }
+
+    /// Returns the value at the contract storage `key` from its stored
+    /// representation.
+    ///
+    /// Defaults to `stored` itself.
+    fn decode_value(&self, _key: &[u8], stored: &[u8]) -> Vec<u8> {
+        stored.to_vec()
+    }
+}
+
+/// Stores contract storage as written by the contract.
+///
+/// This is the default codec of the engine.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IdentityCodec;
+
+impl StorageCodec for IdentityCodec {
+    fn encode_key(&self, key: &[u8]) -> Vec<u8> {
+        key.to_vec()
+    }
+}
+
+/// Stores contract storage the way `pallet-contracts` stores it in the child trie
+/// of a contract: keys are hashed with `blake2_128_concat`, values are unchanged.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PalletContractsCodec;
+
+impl StorageCodec for PalletContractsCodec {
+    fn encode_key(&self, key: &[u8]) -> Vec<u8> {
+        let mut hash = [0; 16];
+        crate::hashing::blake2b_128(key, &mut hash);
+        let mut encoded = Vec::with_capacity(hash.len() + key.len());
+        encoded.extend_from_slice(&hash);
+        encoded.extend_from_slice(key);
+        encoded
+    }
+}
+
+impl Engine {
+    /// Sets the codec which determines the representation in which contract
+    /// storage is stored.
+    ///
+    /// Storage written with the previous codec is not converted, hence the codec
+    /// should be set before any contract storage is written.
+    pub fn set_storage_codec(&mut self, codec: Box<dyn StorageCodec>) {
+        self.storage_codec = Rc::from(codec);
+        self.read_cache.clear();
+    }
+
+    /// Returns the contract storage of `account_id` as stored, i.e. as encoded by the
+    /// storage codec, as key-value pairs.
+    ///
+    /// The entries are ordered by the key bytes the contract wrote them under.
+    pub fn raw_storage(&self, account_id: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.storage_keys(account_id)
+            .filter_map(|key| {
+                let stored_key = self.storage_codec.encode_key(key);
+                self.stored_value(account_id, &stored_key)
+                    .map(|stored| (stored_key, stored))
+            })
+            .collect()
+    }
+
+    /// Returns the value at the contract storage `key` of `account_id`, if any.
+    pub(crate) fn read_storage(&self, account_id: &[u8], key: &[u8]) -> Option<Vec<u8>> {
+        let stored_key = self.storage_codec.encode_key(key);
+        self.stored_value(account_id, &stored_key)
+            .map(|stored| self.storage_codec.decode_value(key, &stored))
+    }
+
+    /// Writes `value` to the contract storage `key` of `account_id`, returning the
+    /// previous value, if any.
+    pub(crate) fn write_storage(
+        &mut self,
+        account_id: &[u8],
+        key: &[u8],
+        value: &[u8],
+    ) -> Option<Vec<u8>> {
+        self.read_cache.invalidate(account_id, key);
+        let stored_key = self.storage_codec.encode_key(key);
+        let stored_value = self.storage_codec.encode_value(key, value);
+        let previous = self
+            .stored_value(account_id, &stored_key)
+            .map(|stored| self.storage_codec.decode_value(key, &stored));
+        if !self.buffer_write(account_id, &stored_key, Some(stored_value.clone())) {
+            self.database.insert_into_contract_storage(
+                account_id,
+                &stored_key,
+                stored_value,
+            );
+        }
+        self.debug_info.record_storage_change(
+            account_id,
+            key,
+            previous.as_deref(),
+            Some(value),
+        );
+        self.debug_info.record_written_key(account_id, key);
+        previous
+    }
+
+    /// Returns the estimated memory usage of the database after writing `value` to
+    /// the contract storage `key` of `account_id`.
+    pub(crate) fn memory_usage_after_write(
+        &self,
+        account_id: &[u8],
+        key: &[u8],
+        value: &[u8],
+    ) -> usize {
+        let stored_key = self.storage_codec.encode_key(key);
+        let stored_len = self.storage_codec.encode_value(key, value).len();
+        self.database
+            .memory_usage_after_insert(account_id, &stored_key, stored_len)
+    }
+
+    /// Removes the contract storage `key` of `account_id`, returning the previous
+    /// value, if any.
+    pub(crate) fn remove_storage(
+        &mut self,
+        account_id: &[u8],
+        key: &[u8],
+    ) -> Option<Vec<u8>> {
+        self.read_cache.invalidate(account_id, key);
+        let stored_key = self.storage_codec.encode_key(key);
+        let previous = self
+            .stored_value(account_id, &stored_key)
+            .map(|stored| self.storage_codec.decode_value(key, &stored));
+        if !self.buffer_write(account_id, &stored_key, None) {
+            self.database
+                .remove_contract_storage(account_id, &stored_key);
+        }
+        self.debug_info
+            .record_storage_change(account_id, key, previous.as_deref(), None);
+        previous
+    }
+}
@@ -0,0 +1,257 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storage deposits reserved for the contract storage.
+//!
+//! Mirrors the storage deposit of `pallet-contracts`: storing a cell reserves
+//! [`EngineConfig::storage_deposit_per_cell`] plus
+//! [`EngineConfig::storage_deposit_per_byte`] for every byte of its value from the
+//! balance of the depositor. Removing the cell refunds the deposit to the account
+//! which paid it. The [`STORAGE_DEPOSIT_LIMIT`] chain parameter caps the sum of
+//! the deposits reserved from a single depositor.
+//!
+//! Like the storage deposit limit a caller passes on-chain, the net deposit charged
+//! by an execution and the ones nested in it can be capped as well, see
+//! [`Engine::set_storage_deposit_limit`] and [`Engine::limit_frame_storage_deposit`].
+//! Refunds within the execution raise the remaining limit again.
+//!
+//! [`EngineConfig::storage_deposit_per_cell`]: crate::ext::EngineConfig::storage_deposit_per_cell
+//! [`EngineConfig::storage_deposit_per_byte`]: crate::ext::EngineConfig::storage_deposit_per_byte
+
+use crate::{
+    chain_params::STORAGE_DEPOSIT_LIMIT,
+    ext::{
+        Engine,
+        Error,
+    },
+    types::Balance,
+};
+use ink_prelude::{
+    collections::BTreeMap,
+    vec::Vec,
+};
+
+/// The deposit reserved for a stored cell.
+#[derive(Clone)]
+struct Deposit {
+    /// The account which paid the deposit.
+    depositor: Vec<u8>,
+    /// The reserved amount.
+    amount: Balance,
+}
+
+/// A cap on the net deposit charged by an execution and the ones nested in it.
+#[derive(Clone)]
+struct DepositLimit {
+    /// The depth of the capped execution.
+    depth: usize,
+    /// The deposit which can still be charged.
+    remaining: Balance,
+}
+
+/// The storage deposits reserved for the contract storage.
+#[derive(Clone, Default)]
+pub(crate) struct StorageDeposits {
+    /// The account paying the deposits, `None` for the caller.
+    depositor: Option<Vec<u8>>,
+    /// The deposit of every stored cell, by contract and key.
+    cells: BTreeMap<(Vec<u8>, Vec<u8>), Deposit>,
+    /// The limit of the next top-level execution, if any.
+    next_limit: Option<Balance>,
+    /// The limits of the entered executions, innermost last.
+    limits: Vec<DepositLimit>,
+}
+
+impl StorageDeposits {
+    /// Removes all deposits and restores the caller as depositor.
+    pub(crate) fn reset(&mut self) {
+        *self = Default::default();
+    }
+
+    /// Returns the sum of the deposits reserved from the balance of `account_id`.
+    pub(crate) fn reserved_by(&self, account_id: &[u8]) -> Balance {
+        self.cells
+            .values()
+            .filter(|deposit| deposit.depositor == account_id)
+            .map(|deposit| deposit.amount)
+            .sum()
+    }
+}
+
+impl Engine {
+    /// Sets the account paying the storage deposits, `None` for the caller.
+    ///
+    /// The caller is the depositor by default.
+    pub fn set_storage_depositor(&mut self, depositor: Option<Vec<u8>>) {
+        self.storage_deposits.depositor = depositor;
+    }
+
+    /// Caps the net storage deposit charged by the next top-level execution and the
+    /// ones nested in it, `None` for no cap.
+    ///
+    /// Storage writes exceeding the cap fail with
+    /// [`Error::StorageDepositLimitExhausted`], which makes the contract trap and
+    /// thereby revert its frame. The limit applies to the next execution entered via
+    /// [`Engine::enter_frame`] at the top level only, it is consumed by it.
+    pub fn set_storage_deposit_limit(&mut self, limit: Option<Balance>) {
+        self.storage_deposits.next_limit = limit;
+    }
+
+    /// Caps the net storage deposit charged by the current execution and the ones
+    /// nested in it, like the storage deposit limit of a call on-chain.
+    ///
+    /// The limits of the enclosing executions still apply. The cap is lifted once
+    /// the execution exits.
+    pub fn limit_frame_storage_deposit(&mut self, limit: Balance) {
+        let depth = self.exec_context.depth();
+        self.storage_deposits.limits.push(DepositLimit {
+            depth,
+            remaining: limit,
+        });
+    }
+
+    /// Returns the storage deposit the current execution can still charge, `None` if
+    /// it is not capped.
+    pub fn remaining_storage_deposit(&self) -> Option<Balance> {
+        self.storage_deposits
+            .limits
+            .iter()
+            .map(|limit| limit.remaining)
+            .min()
+    }
+
+    /// Applies the limit set via [`Engine::set_storage_deposit_limit`] to the entered
+    /// top-level execution.
+    pub(crate) fn enter_storage_deposit_frame(&mut self) {
+        if let Some(limit) = self.storage_deposits.next_limit.take() {
+            self.limit_frame_storage_deposit(limit);
+        }
+    }
+
+    /// Lifts the limits of the left execution.
+    pub(crate) fn leave_storage_deposit_frame(&mut self) {
+        let depth = self.exec_context.depth();
+        self.storage_deposits
+            .limits
+            .retain(|limit| limit.depth <= depth);
+    }
+
+    /// Returns the sum of the storage deposits reserved for all stored cells.
+    pub fn total_storage_deposit(&self) -> Balance {
+        self.storage_deposits
+            .cells
+            .values()
+            .map(|deposit| deposit.amount)
+            .sum()
+    }
+
+    /// Reserves the deposit for storing `len` bytes at the storage `key` of
+    /// `contract`, refunding the deposit of the value stored there before.
+    ///
+    /// # Errors
+    ///
+    /// If the balance of the depositor does not cover the deposit, or the deposit
+    /// exceeds a limit. No balance is changed in these cases.
+    pub(crate) fn charge_storage_deposit(
+        &mut self,
+        contract: &[u8],
+        key: &[u8],
+        len: usize,
+    ) -> Result<(), Error> {
+        let deposit = self
+            .config
+            .storage_deposit_per_byte
+            .unwrap_or_default()
+            .saturating_mul(len as Balance)
+            .saturating_add(self.config.storage_deposit_per_cell.unwrap_or_default());
+        let depositor = self.storage_deposits.depositor.clone().or_else(|| {
+            self.exec_context
+                .caller()
+                .map(|caller| caller.as_bytes().to_vec())
+        });
+        let cell = (contract.to_vec(), key.to_vec());
+        let refund = match (&depositor, self.storage_deposits.cells.get(&cell)) {
+            (Some(depositor), Some(previous)) if previous.depositor == *depositor => {
+                previous.amount
+            }
+            _ => 0,
+        };
+        let available = depositor
+            .as_ref()
+            .and_then(|depositor| self.database.get_balance(depositor))
+            .unwrap_or_default()
+            .saturating_add(refund);
+        if available < deposit {
+            return Err(Error::StorageDepositLimitExhausted)
+        }
+        if let (Some(limit), Some(depositor)) = (
+            self.recognized_chain_param::<Balance>(STORAGE_DEPOSIT_LIMIT),
+            &depositor,
+        ) {
+            let reserved = self
+                .storage_deposits
+                .reserved_by(depositor)
+                .saturating_sub(refund)
+                .saturating_add(deposit);
+            if reserved > limit {
+                return Err(Error::StorageDepositLimitExhausted)
+            }
+        }
+        let charge = deposit.saturating_sub(refund);
+        if depositor.is_some()
+            && self
+                .remaining_storage_deposit()
+                .is_some_and(|remaining| remaining < charge)
+        {
+            return Err(Error::StorageDepositLimitExhausted)
+        }
+
+        self.refund_storage_deposit(contract, key);
+        if let Some(depositor) = depositor.filter(|_| deposit > 0) {
+            let balance = self.database.get_balance(&depositor).unwrap_or_default();
+            self.database.set_balance(&depositor, balance - deposit);
+            for limit in &mut self.storage_deposits.limits {
+                limit.remaining = limit.remaining.saturating_sub(deposit);
+            }
+            self.storage_deposits.cells.insert(
+                cell,
+                Deposit {
+                    depositor,
+                    amount: deposit,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Refunds the deposit for the storage `key` of `contract` to the account which
+    /// paid it, if any, raising the remaining limits.
+    pub(crate) fn refund_storage_deposit(&mut self, contract: &[u8], key: &[u8]) {
+        if let Some(deposit) = self
+            .storage_deposits
+            .cells
+            .remove(&(contract.to_vec(), key.to_vec()))
+        {
+            let balance = self
+                .database
+                .get_balance(&deposit.depositor)
+                .unwrap_or_default();
+            self.database
+                .set_balance(&deposit.depositor, balance + deposit.amount);
+            for limit in &mut self.storage_deposits.limits {
+                limit.remaining = limit.remaining.saturating_add(deposit.amount);
+            }
+        }
+    }
+}
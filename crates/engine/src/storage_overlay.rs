@@ -0,0 +1,150 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Buffering of contract storage writes per execution frame.
+//!
+//! On-chain the storage writes of a contract execution are committed once it
+//! succeeds and discarded if it reverts or traps. By default the engine applies
+//! writes immediately, see [`CommitMode::Immediate`].
+//!
+//! With [`CommitMode::OnFrameSuccess`] the writes of an execution entered via
+//! [`Engine::enter_frame`] go to an overlay of its frame. The overlay is committed
+//! to the enclosing frame, or to the database for a top-level execution, when the
+//! execution exits via [`Engine::exit_frame`] and discarded when it exits via
+//! [`Engine::revert_frame`]. Reads resolve through the overlays of all entered
+//! frames, innermost first.
+//!
+//! Only contract storage is buffered: transfers, storage deposits and events of a
+//! reverted execution remain.
+
+use crate::ext::Engine;
+use ink_prelude::{
+    collections::BTreeMap,
+    vec::Vec,
+};
+
+/// Determines when the storage writes of a contract execution are applied.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CommitMode {
+    /// Writes are applied immediately, also if the execution reverts.
+    #[default]
+    Immediate,
+    /// Writes are applied once the execution succeeds and discarded if it reverts.
+    OnFrameSuccess,
+}
+
+/// The buffered writes of a frame by contract and stored key, `None` for removals.
+type Writes = BTreeMap<(Vec<u8>, Vec<u8>), Option<Vec<u8>>>;
+
+/// The buffered storage writes of the entered frames.
+#[derive(Clone, Default)]
+pub(crate) struct StorageOverlay {
+    /// The writes of every entered frame, innermost last.
+    frames: Vec<Writes>,
+}
+
+impl StorageOverlay {
+    /// Discards all buffered writes.
+    pub(crate) fn reset(&mut self) {
+        self.frames.clear();
+    }
+}
+
+impl Engine {
+    /// Returns the value stored at `stored_key` of `account_id`, resolved through the
+    /// overlays of the entered frames.
+    pub(crate) fn stored_value(
+        &self,
+        account_id: &[u8],
+        stored_key: &[u8],
+    ) -> Option<Vec<u8>> {
+        let key = (account_id.to_vec(), stored_key.to_vec());
+        self.storage_overlay
+            .frames
+            .iter()
+            .rev()
+            .find_map(|writes| writes.get(&key))
+            .cloned()
+            .unwrap_or_else(|| {
+                self.database
+                    .get_from_contract_storage(account_id, stored_key)
+                    .cloned()
+            })
+    }
+
+    /// Returns `true` if writes are buffered in the overlay of the current frame.
+    pub(crate) fn buffers_writes(&self) -> bool {
+        self.config.commit_mode != CommitMode::Immediate
+            && !self.storage_overlay.frames.is_empty()
+    }
+
+    /// Buffers the write of `value` at `stored_key` of `account_id` in the overlay of
+    /// the current frame, `None` for a removal.
+    ///
+    /// Returns `false` if the write is to be applied immediately instead.
+    pub(crate) fn buffer_write(
+        &mut self,
+        account_id: &[u8],
+        stored_key: &[u8],
+        value: Option<Vec<u8>>,
+    ) -> bool {
+        if self.config.commit_mode == CommitMode::Immediate {
+            return false
+        }
+        match self.storage_overlay.frames.last_mut() {
+            Some(writes) => {
+                writes.insert((account_id.to_vec(), stored_key.to_vec()), value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Enters the overlay of a new frame.
+    pub(crate) fn enter_overlay_frame(&mut self) {
+        self.storage_overlay.frames.push(Writes::new());
+    }
+
+    /// Commits the writes of the current frame to the enclosing frame, or to the
+    /// database if there is none.
+    pub(crate) fn commit_overlay_frame(&mut self) {
+        let Some(writes) = self.storage_overlay.frames.pop() else {
+            return
+        };
+        if let Some(parent) = self.storage_overlay.frames.last_mut() {
+            parent.extend(writes);
+            return
+        }
+        for ((account_id, stored_key), value) in writes {
+            match value {
+                Some(value) => {
+                    self.database.insert_into_contract_storage(
+                        &account_id,
+                        &stored_key,
+                        value,
+                    );
+                }
+                None => {
+                    self.database
+                        .remove_contract_storage(&account_id, &stored_key);
+                }
+            }
+        }
+    }
+
+    /// Discards the writes of the current frame.
+    pub(crate) fn discard_overlay_frame(&mut self) {
+        self.storage_overlay.frames.pop();
+    }
+}
@@ -13,20 +13,56 @@
 // limitations under the License.
 
 use crate::{
-    ext::Engine,
+    exec_context::Origin,
+    ext::{
+        self,
+        AddressDerivation,
+        BlockCallback,
+        Clock,
+        Engine,
+        WeightToFee,
+    },
+    nonce::AddressDerivationInput,
+    recorder::retain_last,
+    scenario::Operation,
+    storage_codec::IdentityCodec,
     types::{
+        encode_hex,
         AccountId,
+        AssetId,
         Balance,
         BlockNumber,
         BlockTimestamp,
+        Hash,
+        HexError,
     },
     AccountError,
+    DuplicateSelector,
+    EntryPoints,
     Error,
+    ExecContextSnapshot,
+    UnknownSelector,
+};
+use core::{
+    cell::RefCell,
+    fmt::Write,
+};
+use ink_prelude::{
+    boxed::Box,
+    collections::{
+        vec_deque,
+        BTreeMap,
+        BTreeSet,
+        VecDeque,
+    },
+    rc::Rc,
+    string::String,
+    vec,
+    vec::Vec,
 };
-use std::collections::HashMap;
 
 /// Record for an emitted event.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct EmittedEvent {
     /// Recorded topics of the emitted event.
     pub topics: Vec<Vec<u8>>,
@@ -34,28 +70,438 @@ pub struct EmittedEvent {
     pub data: Vec<u8>,
 }
 
+/// Returns the topics the ink! codegen emits for an event, to be compared with
+/// [`EmittedEvent::topics`].
+///
+/// The `signature` is the name of the event followed by the types of its fields
+/// as written in its definition without whitespace, e.g. `"Transferred(u8,Balance)"`,
+/// `None` for anonymous events. The `indexed_fields` are the SCALE encoded values of
+/// the `#[ink(topic)]` fields in declaration order, with `0u8` for an `Option` which
+/// is `None`.
+///
+/// Values encoded into up to 32 bytes are padded with zeros, longer ones are hashed
+/// via BLAKE2 256-bit.
+pub fn event_topics(signature: Option<&str>, indexed_fields: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    fn topic(encoded: &[u8]) -> Vec<u8> {
+        let mut topic = [0; 32];
+        if encoded.len() <= topic.len() {
+            topic[..encoded.len()].copy_from_slice(encoded);
+        } else {
+            crate::hashing::blake2b_256(encoded, &mut topic);
+        }
+        topic.to_vec()
+    }
+
+    let signature_topic = signature.map(|signature| {
+        let mut signature_topic = [0; 32];
+        crate::hashing::blake2b_256(signature.as_bytes(), &mut signature_topic);
+        topic(&signature_topic)
+    });
+    signature_topic
+        .into_iter()
+        .chain(indexed_fields.iter().map(|encoded| topic(encoded)))
+        .collect()
+}
+
+/// An item recorded within an execution frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameItem {
+    /// An event was emitted.
+    Event(EmittedEvent),
+    /// A debug message was emitted.
+    DebugMessage(String),
+    /// A call was dispatched, identified by the id of its frame.
+    Call(usize),
+    /// The storage cell at the key was read.
+    StorageRead(Vec<u8>),
+    /// The storage cell at the key was written or removed.
+    StorageWrite(Vec<u8>),
+}
+
+impl FrameItem {
+    /// Returns the items sharing a retention limit with the item, `None` for calls,
+    /// which are retained without limit.
+    fn retained_as(&self) -> Option<RetainedItems> {
+        match self {
+            FrameItem::Event(_) => Some(RetainedItems::Events),
+            FrameItem::DebugMessage(_) => Some(RetainedItems::Printlns),
+            FrameItem::Call(_) => None,
+            FrameItem::StorageRead(_) | FrameItem::StorageWrite(_) => {
+                Some(RetainedItems::StorageAccesses)
+            }
+        }
+    }
+}
+
+/// The items of the execution frames sharing a retention limit of the engine
+/// configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RetainedItems {
+    /// See [`crate::ext::EngineConfig::max_recorded_events`].
+    Events,
+    /// See [`crate::ext::EngineConfig::max_recorded_printlns`].
+    Printlns,
+    /// See [`crate::ext::EngineConfig::max_recorded_storage_accesses`].
+    StorageAccesses,
+}
+
+/// A change of a watched storage cell, see [`Engine::watch_storage_key`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageChange {
+    /// The id of the execution frame which changed the cell, see [`FrameRecord`].
+    pub frame: usize,
+    /// The value before the change, `None` if the cell was empty.
+    pub old_value: Option<Vec<u8>>,
+    /// The value after the change, `None` if the cell was cleared.
+    pub new_value: Option<Vec<u8>>,
+}
+
+/// A transfer of value, see [`Engine::recorded_transfers`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferRecord {
+    /// The account the value was transferred from.
+    pub from: Vec<u8>,
+    /// The account the value was transferred to.
+    pub to: Vec<u8>,
+    /// The transferred value.
+    pub value: Balance,
+    /// The id of the execution frame which transferred, see [`FrameRecord`].
+    pub frame: usize,
+}
+
+/// The interactions of a contract recorded by the engine, see
+/// [`Engine::contract_statistics`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContractStatistics {
+    /// The number of reads of the storage of the contract.
+    pub storage_reads: usize,
+    /// The number of writes to the storage of the contract.
+    pub storage_writes: usize,
+    /// The number of events emitted by the contract.
+    pub events_emitted: u32,
+    /// The number of bytes of the topics and data of the events emitted by the
+    /// contract.
+    pub event_bytes_emitted: u64,
+    /// The number of reads of the storage of the contract served from the read
+    /// cache, see [`crate::read_cache`].
+    pub cache_hits: usize,
+    /// The number of reads of the storage of the contract which missed the read
+    /// cache.
+    pub cache_misses: usize,
+}
+
+/// An account known to the engine, see [`Engine::accounts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountInfo {
+    /// The id of the account.
+    pub id: Vec<u8>,
+    /// The balance of the account, `0` if it has never been assigned one.
+    pub balance: Balance,
+    /// Whether the account is a known contract.
+    pub is_contract: bool,
+    /// The nonce of the account, see [`Engine::get_nonce`].
+    pub nonce: u64,
+    /// The number of storage cells holding a value.
+    pub storage_cells: usize,
+}
+
+/// The kind of an account, see [`Engine::account_kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountKind {
+    /// A plain account, which is not callable.
+    User,
+    /// A known contract.
+    Contract,
+    /// A contract which terminated itself, see [`Engine::terminate`]. It is no longer
+    /// callable.
+    Tombstone,
+}
+
+/// Code to upload via [`Engine::upload_code`].
+#[derive(Debug, Clone)]
+pub enum Code {
+    /// The bytes of compiled code, e.g. a Wasm blob.
+    ///
+    /// Contracts instantiated from it have no entry points. Under the `wasm-exec`
+    /// feature, they execute a Wasm blob instead.
+    Bytes(Vec<u8>),
+    /// Mocked code dispatching to the given entry points.
+    Mock(EntryPoints),
+}
+
+/// Record of an execution frame.
+///
+/// The root frame with id `0` and depth `0` records everything which happens
+/// outside of dispatched calls, e.g. in the body of a test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameRecord {
+    /// The id of the frame, ids are increasing in the order frames are entered.
+    pub id: usize,
+    /// The number of frames the frame is nested in.
+    pub depth: usize,
+    /// The caller of the frame, `None` for the root frame.
+    pub caller: Option<Vec<u8>>,
+    /// The callee of the frame, `None` for the root frame.
+    pub callee: Option<Vec<u8>>,
+    /// The items recorded within the frame in order.
+    pub items: Vec<FrameItem>,
+    /// The number of items dropped from `items` to stay within the retention limits
+    /// of the engine configuration.
+    pub dropped: DroppedFrameItems,
+    /// Whether the frame reverted or trapped.
+    pub reverted: bool,
+}
+
+/// The number of items dropped from the record of an execution frame, see
+/// [`FrameRecord::dropped`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DroppedFrameItems {
+    /// The number of dropped events, see
+    /// [`crate::ext::EngineConfig::max_recorded_events`].
+    pub events: usize,
+    /// The number of dropped debug messages, see
+    /// [`crate::ext::EngineConfig::max_recorded_printlns`].
+    pub printlns: usize,
+    /// The number of dropped storage reads, see
+    /// [`crate::ext::EngineConfig::max_recorded_storage_accesses`].
+    pub storage_reads: usize,
+    /// The number of dropped storage writes, see
+    /// [`crate::ext::EngineConfig::max_recorded_storage_accesses`].
+    pub storage_writes: usize,
+}
+
+impl FrameRecord {
+    /// Creates the record of the root frame.
+    fn root() -> Self {
+        Self {
+            id: 0,
+            depth: 0,
+            caller: None,
+            callee: None,
+            items: Vec::new(),
+            dropped: DroppedFrameItems::default(),
+            reverted: false,
+        }
+    }
+
+    /// Returns the events emitted within the frame in order.
+    pub fn events(&self) -> impl Iterator<Item = &EmittedEvent> {
+        self.items.iter().filter_map(|item| {
+            match item {
+                FrameItem::Event(event) => Some(event),
+                _ => None,
+            }
+        })
+    }
+}
+
+/// The input of a dispatched call, see [`Engine::recorded_calls`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedCall {
+    /// The id of the execution frame of the call, see [`FrameRecord`].
+    pub frame: usize,
+    /// The caller of the call, if any.
+    pub caller: Option<Vec<u8>>,
+    /// The called contract.
+    pub callee: Vec<u8>,
+    /// The raw input bytes of the call.
+    pub input: Vec<u8>,
+    /// The selector decoded from the first four bytes of the input, `None` if the
+    /// input is shorter.
+    pub selector: Option<[u8; 4]>,
+}
+
+/// The outcome of a completed call, including the calls nested in it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallOutcome {
+    /// The data the callee returned.
+    pub data: Vec<u8>,
+    /// The number of storage reads.
+    pub storage_reads: usize,
+    /// The number of storage writes, including removals.
+    pub storage_writes: usize,
+    /// The number of emitted events.
+    pub events_emitted: usize,
+    /// The events emitted by the call in order, empty if it reverted.
+    ///
+    /// Lacks the events dropped to stay within
+    /// [`crate::ext::EngineConfig::max_recorded_events`].
+    ///
+    /// The events are also part of [`Engine::get_emitted_events`], they are not
+    /// recorded twice.
+    pub events: Vec<EmittedEvent>,
+    /// The gas consumed by the call, see [`Engine::gas_consumed`].
+    pub gas_consumed: u64,
+}
+
+/// A warning about suspicious behavior recorded by the engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// The clock set via [`Engine::set_clock`] derived a block timestamp which is
+    /// earlier than the one of the previous block.
+    ClockMovedBackwards {
+        /// The number of the block whose timestamp moved backwards.
+        block_number: BlockNumber,
+        /// The timestamp of the previous block.
+        previous: BlockTimestamp,
+        /// The timestamp derived for the block.
+        timestamp: BlockTimestamp,
+    },
+    /// An event was deposited with its topics in the deprecated format of
+    /// concatenated topics of equal length, see [`Engine::deposit_event`].
+    DeprecatedTopicsFormat {
+        /// The number of topics of the event.
+        topics_count: usize,
+    },
+    /// Advancing the timestamp via [`Engine::advance_time`] overflowed, the
+    /// timestamp saturated at `u64::MAX`.
+    TimestampSaturated {
+        /// The timestamp before the advancement.
+        previous: BlockTimestamp,
+        /// The requested advancement in milliseconds.
+        ms: u64,
+    },
+    /// A storage write made the estimated memory usage of the database exceed the
+    /// configured [`crate::ext::EngineConfig::memory_soft_cap`].
+    ///
+    /// Recorded once per crossing of the cap.
+    MemorySoftCapExceeded {
+        /// The estimated memory usage after the write, in bytes.
+        usage: usize,
+        /// The configured soft cap, in bytes.
+        cap: usize,
+    },
+    /// A debug message was emitted while disabled via
+    /// [`crate::ext::EngineConfig::println_enabled`], like on a production chain.
+    PrintlnDenied {
+        /// The denied message.
+        message: String,
+    },
+    /// A contract executed from a Wasm blob trapped, see [`crate::ext::Engine::call`].
+    ContractTrapped {
+        /// The contract which trapped.
+        account_id: Vec<u8>,
+        /// Why the contract trapped, e.g. the name of an unsupported import it called.
+        message: String,
+    },
+}
+
+/// A pattern which likely hints at a bug, recorded while diagnostics are enabled
+/// via [`Engine::enable_diagnostics`].
+///
+/// Each diagnostic names the id of the execution frame it was recorded in, see
+/// [`FrameRecord`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// A storage cell holding a value was overwritten without having been read in
+    /// the same execution frame.
+    OverwriteWithoutRead {
+        /// The id of the frame.
+        frame: usize,
+        /// The contract owning the cell.
+        account_id: Vec<u8>,
+        /// The key of the cell.
+        key: Vec<u8>,
+    },
+    /// A storage cell holding no value was cleared.
+    ClearOfMissingKey {
+        /// The id of the frame.
+        frame: usize,
+        /// The contract owning the cell.
+        account_id: Vec<u8>,
+        /// The key of the cell.
+        key: Vec<u8>,
+    },
+    /// A value of zero was transferred.
+    ZeroValueTransfer {
+        /// The id of the frame.
+        frame: usize,
+        /// The account transferring the value.
+        from: Vec<u8>,
+        /// The destination of the transfer.
+        to: Vec<u8>,
+    },
+    /// An event without data was emitted.
+    EventWithEmptyData {
+        /// The id of the frame.
+        frame: usize,
+        /// The emitting contract, `None` outside of contract executions.
+        emitter: Option<Vec<u8>>,
+        /// The topics of the event.
+        topics: Vec<Vec<u8>>,
+    },
+    /// A storage cell which has never been written was read, see
+    /// [`Engine::uninitialized_reads`].
+    UninitializedRead {
+        /// The id of the frame.
+        frame: usize,
+        /// The contract owning the cell.
+        account_id: Vec<u8>,
+        /// The key of the cell.
+        key: Vec<u8>,
+    },
+}
+
+/// A fault which can be injected into the engine.
+///
+/// Injected faults are consumed when they are triggered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// The `n`-th storage write after injecting the fault traps.
+    ///
+    /// The failing write is not applied to the storage.
+    FailNthStorageWrite(u32),
+    /// The next transfer fails with the given error.
+    FailNextTransfer(ext::Error),
+    /// The next call to the chain extension method `func_id` returns `status`
+    /// without invoking the registered chain extension.
+    ///
+    /// `func_id` is the full id as passed to `call_chain_extension`.
+    ChainExtensionStatus { func_id: u32, status: u32 },
+}
+
 #[derive(Clone)]
 pub struct RecordedDebugMessages {
-    debug_messages: Vec<String>,
+    debug_messages: VecDeque<String>,
 }
 
 impl RecordedDebugMessages {
     // Creates a new `Engine instance.
     pub fn new() -> Self {
         Self {
-            debug_messages: Vec::new(),
+            debug_messages: VecDeque::new(),
         }
     }
 
     // Records a new debug message.
     pub fn record(&mut self, message: String) {
-        self.debug_messages.push(message);
+        self.debug_messages.push_back(message);
+    }
+
+    /// Drops the oldest debug messages beyond `limit`, if any, and returns their
+    /// number.
+    pub(crate) fn retain_last(&mut self, limit: Option<usize>) -> usize {
+        retain_last(&mut self.debug_messages, limit)
     }
 
     // Clears all recorded debug messages.
     pub fn clear(&mut self) {
         self.debug_messages.clear();
     }
+
+    /// Returns an iterator over the recorded debug messages in order.
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.debug_messages.iter()
+    }
+
+    /// Returns all recorded debug messages which contain `pattern`.
+    pub fn containing(&self, pattern: &str) -> Vec<String> {
+        self.debug_messages
+            .iter()
+            .filter(|message| message.contains(pattern))
+            .cloned()
+            .collect()
+    }
 }
 
 impl Default for RecordedDebugMessages {
@@ -66,7 +512,7 @@ impl Default for RecordedDebugMessages {
 
 impl IntoIterator for RecordedDebugMessages {
     type Item = String;
-    type IntoIter = std::vec::IntoIter<Self::Item>;
+    type IntoIter = vec_deque::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.debug_messages.into_iter()
@@ -74,17 +520,48 @@ impl IntoIterator for RecordedDebugMessages {
 }
 
 /// Recorder for relevant interactions with this crate.
+#[derive(Clone)]
 pub struct DebugInfo {
-    /// Emitted events recorder.
-    emitted_events: Vec<EmittedEvent>,
-    /// Emitted print messages recorder.
-    emitted_debug_messages: RecordedDebugMessages,
-    /// The total number of reads to the storage.
-    count_reads: HashMap<AccountId, usize>,
-    /// The total number of writes to the storage.
-    count_writes: HashMap<AccountId, usize>,
     /// The number of storage cells used by each account id.
-    cells_per_account: HashMap<AccountId, HashMap<Vec<u8>, bool>>,
+    pub(crate) cells_per_account: BTreeMap<AccountId, BTreeMap<Vec<u8>, bool>>,
+    /// The records of all execution frames, indexed by their id.
+    frames: Vec<FrameRecord>,
+    /// The ids of the currently active execution frames, innermost last.
+    active_frames: Vec<usize>,
+    /// The ids of the frames holding the retained events, oldest first.
+    retained_events: VecDeque<usize>,
+    /// The ids of the frames holding the retained debug messages, oldest first.
+    retained_printlns: VecDeque<usize>,
+    /// The ids of the frames holding the retained storage accesses, oldest first.
+    retained_storage_accesses: VecDeque<usize>,
+    /// Warnings recorder.
+    warnings: Vec<Warning>,
+    /// Diagnostics recorder, `None` while diagnostics are disabled.
+    diagnostics: Option<Vec<Diagnostic>>,
+    /// The storage keys written by contract while diagnostics are enabled.
+    written_keys: BTreeMap<Vec<u8>, BTreeSet<Vec<u8>>>,
+    /// The outcome of the last completed call.
+    last_call_outcome: Option<CallOutcome>,
+    /// The changes of the watched storage cells, by contract and key.
+    storage_changes: BTreeMap<(Vec<u8>, Vec<u8>), Vec<StorageChange>>,
+    /// The keys written to the storage of a contract since a log was started, by
+    /// the id of the log, see [`Engine::start_storage_write_log`].
+    write_logs: BTreeMap<usize, (Vec<u8>, Vec<Vec<u8>>)>,
+    /// The id of the next write log, not reset to keep ids unique.
+    next_write_log: usize,
+    /// The successful transfers in order.
+    transfers: Vec<TransferRecord>,
+    /// The transfers rejected because they would have created their destination.
+    rejected_transfers: Vec<TransferRecord>,
+    /// The successful fixture transfers in order, see [`Engine::transfer_between`].
+    fixture_transfers: Vec<TransferRecord>,
+    /// The inputs of the dispatched calls in order.
+    calls: VecDeque<RecordedCall>,
+    /// The number of calls dropped to stay within
+    /// [`crate::ext::EngineConfig::max_recorded_calls`].
+    pub(crate) dropped_calls: usize,
+    /// The input of the last dispatch, also if no function was found for it.
+    last_dispatch_input: Option<Vec<u8>>,
 }
 
 impl Default for DebugInfo {
@@ -97,37 +574,50 @@ impl DebugInfo {
     // Creates a new `RecInstance instance.
     pub fn new() -> Self {
         Self {
-            emitted_events: Vec::new(),
-            emitted_debug_messages: RecordedDebugMessages::new(),
-            count_reads: HashMap::new(),
-            count_writes: HashMap::new(),
-            cells_per_account: HashMap::new(),
+            cells_per_account: BTreeMap::new(),
+            frames: vec![FrameRecord::root()],
+            active_frames: vec![0],
+            retained_events: VecDeque::new(),
+            retained_printlns: VecDeque::new(),
+            retained_storage_accesses: VecDeque::new(),
+            warnings: Vec::new(),
+            diagnostics: None,
+            written_keys: BTreeMap::new(),
+            last_call_outcome: None,
+            storage_changes: BTreeMap::new(),
+            write_logs: BTreeMap::new(),
+            next_write_log: 0,
+            transfers: Vec::new(),
+            rejected_transfers: Vec::new(),
+            fixture_transfers: Vec::new(),
+            calls: VecDeque::new(),
+            dropped_calls: 0,
+            last_dispatch_input: None,
         }
     }
 
     /// Resets the recorder.
+    ///
+    /// The memory held by the recorder is released.
     pub fn reset(&mut self) {
-        self.count_reads.clear();
-        self.count_writes.clear();
-        self.emitted_events.clear();
-        self.emitted_debug_messages.clear();
         self.cells_per_account.clear();
-    }
-
-    /// Increases the number of storage writes for the supplied account by one.
-    pub fn inc_writes(&mut self, account_id: AccountId) {
-        self.count_writes
-            .entry(account_id)
-            .and_modify(|v| *v += 1)
-            .or_insert(1);
-    }
-
-    /// Increases the number of storage reads for the supplied account by one.
-    pub fn inc_reads(&mut self, account_id: AccountId) {
-        self.count_reads
-            .entry(account_id)
-            .and_modify(|v| *v += 1)
-            .or_insert(1);
+        self.frames = vec![FrameRecord::root()];
+        self.active_frames = vec![0];
+        self.retained_events = VecDeque::new();
+        self.retained_printlns = VecDeque::new();
+        self.retained_storage_accesses = VecDeque::new();
+        self.warnings = Vec::new();
+        self.diagnostics = None;
+        self.written_keys.clear();
+        self.last_call_outcome = None;
+        self.storage_changes.clear();
+        self.write_logs.clear();
+        self.transfers = Vec::new();
+        self.rejected_transfers = Vec::new();
+        self.fixture_transfers = Vec::new();
+        self.calls = VecDeque::new();
+        self.dropped_calls = 0;
+        self.last_dispatch_input = None;
     }
 
     /// Records that a cell exists for an account under `key`.
@@ -141,12 +631,18 @@ impl DebugInfo {
                 let _ = hm.insert(key.clone(), true);
             })
             .or_insert({
-                let mut hm = HashMap::new();
+                let mut hm = BTreeMap::new();
                 hm.insert(key, true);
                 hm
             });
     }
 
+    /// Records an empty storage for the supplied account, unless there already
+    /// are cells recorded for it.
+    pub fn register_account(&mut self, account_id: AccountId) {
+        self.cells_per_account.entry(account_id).or_default();
+    }
+
     /// Removes the cell under `key` for the supplied account.
     ///
     /// Returns the removed cell, if there was one.
@@ -161,56 +657,1044 @@ impl DebugInfo {
             .unwrap_or(None)
     }
 
-    /// Records a debug message.
-    pub fn record_debug_message(&mut self, message: String) {
-        self.emitted_debug_messages.record(message);
+    /// Records a debug message within the current execution frame, retaining at
+    /// most `limit` debug messages across all frames.
+    ///
+    /// The statistics are reported to the recorder of the engine, see
+    /// [`crate::recorder`].
+    pub fn record_debug_message(&mut self, message: String, limit: Option<usize>) {
+        self.record_retained_frame_item(FrameItem::DebugMessage(message), limit);
+    }
+
+    /// Records an event within the current execution frame, retaining at most
+    /// `limit` events across all frames.
+    pub fn record_event(&mut self, event: EmittedEvent, limit: Option<usize>) {
+        self.record_retained_frame_item(FrameItem::Event(event), limit);
+    }
+
+    /// Records a warning.
+    pub fn record_warning(&mut self, warning: Warning) {
+        self.warnings.push(warning);
+    }
+
+    /// Returns whether diagnostics are recorded.
+    pub(crate) fn diagnostics_enabled(&self) -> bool {
+        self.diagnostics.is_some()
+    }
+
+    /// Records the diagnostic built from the id of the current execution frame, if
+    /// diagnostics are enabled.
+    pub(crate) fn record_diagnostic(
+        &mut self,
+        diagnostic: impl FnOnce(usize) -> Diagnostic,
+    ) {
+        let frame = self.current_frame();
+        if let Some(diagnostics) = &mut self.diagnostics {
+            diagnostics.push(diagnostic(frame));
+        }
+    }
+
+    /// Records that the storage `key` of `account_id` has been written, if
+    /// diagnostics are enabled.
+    pub(crate) fn record_written_key(&mut self, account_id: &[u8], key: &[u8]) {
+        if self.diagnostics_enabled() {
+            self.written_keys
+                .entry(account_id.to_vec())
+                .or_default()
+                .insert(key.to_vec());
+        }
+    }
+
+    /// Records an [`Diagnostic::UninitializedRead`] if the storage `key` of
+    /// `account_id` has never been written.
+    pub(crate) fn check_uninitialized_read(&mut self, account_id: &[u8], key: &[u8]) {
+        let written = self
+            .written_keys
+            .get(account_id)
+            .is_some_and(|keys| keys.contains(key));
+        if !written {
+            self.record_diagnostic(|frame| {
+                Diagnostic::UninitializedRead {
+                    frame,
+                    account_id: account_id.to_vec(),
+                    key: key.to_vec(),
+                }
+            });
+        }
+    }
+
+    /// Returns whether the storage `key` has been read within the current execution
+    /// frame.
+    pub(crate) fn read_in_current_frame(&self, key: &[u8]) -> bool {
+        self.frames[self.current_frame()].items.iter().any(
+            |item| matches!(item, FrameItem::StorageRead(read) if read.as_slice() == key),
+        )
+    }
+
+    /// Records an item within the current execution frame.
+    pub fn record_frame_item(&mut self, item: FrameItem) {
+        let current = self.current_frame();
+        self.frames[current].items.push(item);
+    }
+
+    /// Records an item within the current execution frame, retaining at most
+    /// `limit` items sharing its retention limit across all frames.
+    ///
+    /// The oldest items beyond the limit are dropped, see [`FrameRecord::dropped`].
+    ///
+    /// # Panics
+    ///
+    /// If the item is a call, which is retained without limit.
+    pub(crate) fn record_retained_frame_item(
+        &mut self,
+        item: FrameItem,
+        limit: Option<usize>,
+    ) {
+        let retained = item
+            .retained_as()
+            .expect("calls are retained without limit");
+        let current = self.current_frame();
+        self.frames[current].items.push(item);
+        self.retained_frames(retained).push_back(current);
+        let excess = limit.map_or(0, |limit| {
+            self.retained_frames(retained).len().saturating_sub(limit)
+        });
+        for _ in 0..excess {
+            let frame = self
+                .retained_frames(retained)
+                .pop_front()
+                .expect("the excess items are retained");
+            self.drop_oldest_item(frame, retained);
+        }
+    }
+
+    /// Returns the ids of the frames holding the `retained` items, oldest first.
+    fn retained_frames(&mut self, retained: RetainedItems) -> &mut VecDeque<usize> {
+        match retained {
+            RetainedItems::Events => &mut self.retained_events,
+            RetainedItems::Printlns => &mut self.retained_printlns,
+            RetainedItems::StorageAccesses => &mut self.retained_storage_accesses,
+        }
+    }
+
+    /// Drops the oldest of the `retained` items of the frame with `id` and counts it
+    /// in [`FrameRecord::dropped`].
+    fn drop_oldest_item(&mut self, id: usize, retained: RetainedItems) {
+        let frame = &mut self.frames[id];
+        let index = frame
+            .items
+            .iter()
+            .position(|item| item.retained_as() == Some(retained))
+            .expect("the retained items are recorded within their frame");
+        let dropped = &mut frame.dropped;
+        match frame.items.remove(index) {
+            FrameItem::Event(_) => dropped.events += 1,
+            FrameItem::DebugMessage(_) => dropped.printlns += 1,
+            FrameItem::StorageRead(_) => dropped.storage_reads += 1,
+            FrameItem::StorageWrite(_) => dropped.storage_writes += 1,
+            FrameItem::Call(_) => unreachable!("calls are retained without limit"),
+        }
+    }
+
+    /// Returns the id of the current execution frame.
+    fn current_frame(&self) -> usize {
+        *self
+            .active_frames
+            .last()
+            .expect("the root frame is always active")
+    }
+
+    /// Records the change of the storage cell at `key` of `account_id` if the cell
+    /// is watched, and the key in the write logs of `account_id`.
+    pub(crate) fn record_storage_change(
+        &mut self,
+        account_id: &[u8],
+        key: &[u8],
+        old_value: Option<&[u8]>,
+        new_value: Option<&[u8]>,
+    ) {
+        for (account, keys) in self.write_logs.values_mut() {
+            if account == account_id && !keys.iter().any(|written| written == key) {
+                keys.push(key.to_vec());
+            }
+        }
+        let frame = self.current_frame();
+        if let Some(changes) = self
+            .storage_changes
+            .get_mut(&(account_id.to_vec(), key.to_vec()))
+        {
+            changes.push(StorageChange {
+                frame,
+                old_value: old_value.map(<[u8]>::to_vec),
+                new_value: new_value.map(<[u8]>::to_vec),
+            });
+        }
+    }
+
+    /// Returns `true` if changes of any key of `account_id` are watched or logged.
+    pub(crate) fn watches_storage_of(&self, account_id: &[u8]) -> bool {
+        self.storage_changes
+            .keys()
+            .any(|(account, _)| account == account_id)
+            || self
+                .write_logs
+                .values()
+                .any(|(account, _)| account == account_id)
+    }
+
+    /// Records a transfer within the current execution frame, `rejected` if it was
+    /// rejected because it would have created its destination.
+    pub(crate) fn record_transfer(
+        &mut self,
+        from: Vec<u8>,
+        to: Vec<u8>,
+        value: Balance,
+        rejected: bool,
+    ) {
+        let record = TransferRecord {
+            from,
+            to,
+            value,
+            frame: self.current_frame(),
+        };
+        if rejected {
+            self.rejected_transfers.push(record);
+        } else {
+            self.transfers.push(record);
+        }
+    }
+
+    /// Records a successful fixture transfer, see [`Engine::transfer_between`].
+    fn record_fixture_transfer(&mut self, from: Vec<u8>, to: Vec<u8>, value: Balance) {
+        let record = TransferRecord {
+            from,
+            to,
+            value,
+            frame: self.current_frame(),
+        };
+        self.fixture_transfers.push(record);
+    }
+
+    /// Enters a new execution frame nested in the current one.
+    ///
+    /// Returns the id of the new frame.
+    pub fn enter_frame(&mut self, caller: Option<Vec<u8>>, callee: Vec<u8>) -> usize {
+        let id = self.frames.len();
+        self.record_frame_item(FrameItem::Call(id));
+        self.frames.push(FrameRecord {
+            id,
+            depth: self.active_frames.len(),
+            caller,
+            callee: Some(callee),
+            items: Vec::new(),
+            dropped: DroppedFrameItems::default(),
+            reverted: false,
+        });
+        self.active_frames.push(id);
+        id
     }
 
-    /// Records an event.
-    pub fn record_event(&mut self, event: EmittedEvent) {
-        self.emitted_events.push(event);
+    /// Exits the current execution frame.
+    ///
+    /// # Panics
+    ///
+    /// If the current frame is the root frame.
+    pub fn exit_frame(&mut self) {
+        assert!(self.active_frames.len() > 1, "cannot exit the root frame");
+        let id = self.active_frames.pop().expect("checked above");
+        self.last_call_outcome = Some(self.outcome_of(id));
+    }
+
+    /// Marks the current execution frame as reverted, see [`FrameRecord::reverted`].
+    fn mark_current_frame_reverted(&mut self) {
+        let id = self.current_frame();
+        self.frames[id].reverted = true;
+    }
+
+    /// Returns the outcome of the frame with `id`, including its nested frames.
+    ///
+    /// The returned data is left empty. The events of a reverted frame, including
+    /// the ones of its nested frames, are discarded. The counts include the items
+    /// dropped from the frames, see [`FrameRecord::dropped`].
+    fn outcome_of(&self, id: usize) -> CallOutcome {
+        let dropped = &self.frames[id].dropped;
+        let mut outcome = CallOutcome {
+            storage_reads: dropped.storage_reads,
+            storage_writes: dropped.storage_writes,
+            events_emitted: dropped.events,
+            ..CallOutcome::default()
+        };
+        for item in &self.frames[id].items {
+            match item {
+                FrameItem::Event(event) => {
+                    outcome.events_emitted += 1;
+                    outcome.events.push(event.clone());
+                }
+                FrameItem::StorageRead(_) => outcome.storage_reads += 1,
+                FrameItem::StorageWrite(_) => outcome.storage_writes += 1,
+                FrameItem::Call(nested) => {
+                    let nested = self.outcome_of(*nested);
+                    outcome.events_emitted += nested.events_emitted;
+                    outcome.events.extend(nested.events);
+                    outcome.storage_reads += nested.storage_reads;
+                    outcome.storage_writes += nested.storage_writes;
+                }
+                FrameItem::DebugMessage(_) => (),
+            }
+        }
+        if self.frames[id].reverted {
+            outcome.events.clear();
+        }
+        outcome
     }
 }
 
 impl Engine {
     /// Resets the environment.
+    ///
+    /// The memory held by the database and the recorders is released, see
+    /// [`Engine::approximate_memory_usage`].
     pub fn initialize_or_reset(&mut self) {
         self.exec_context.reset();
         self.database.clear();
         self.debug_info.reset();
+        self.recorder.reset();
+        self.faults.clear();
+        self.dispatch_handler.reset();
+        self.clock = None;
+        self.weight_to_fee_fn = None;
+        self.address_derivation = None;
+        self.block_callbacks.clear();
+        self.scenario = None;
+        self.storage_codec = Rc::new(IdentityCodec);
+        self.storage_deposits.reset();
+        self.storage_overlay.reset();
+        self.contract_locks.reset();
+        self.chain_params.reset();
+        self.fee_accounting.reset();
+        self.runtime_call_handler = None;
+        self.runtime_calls = Vec::new();
+        self.storage_buffer = Rc::from(Vec::new());
+        self.read_cache.reset();
+        self.block_history.reset();
+        self.tombstones.clear();
+        self.memory_soft_cap_exceeded = false;
+        #[cfg(feature = "wasm-exec")]
+        self.wasm_code.reset();
+    }
+
+    /// Returns the estimated number of bytes used by the database.
+    ///
+    /// The estimate is the sum of the lengths of all stored keys and values plus a
+    /// fixed overhead per entry. It is the figure the
+    /// [`crate::ext::EngineConfig::memory_soft_cap`] and
+    /// [`crate::ext::EngineConfig::memory_hard_cap`] apply to.
+    pub fn approximate_memory_usage(&self) -> usize {
+        self.database.approximate_memory_usage()
+    }
+
+    /// Records a [`Warning::MemorySoftCapExceeded`] if the last write made the
+    /// memory usage cross the configured soft cap.
+    pub(crate) fn check_memory_soft_cap(&mut self) {
+        let Some(cap) = self.config.memory_soft_cap else {
+            return
+        };
+        let usage = self.approximate_memory_usage();
+        if usage <= cap {
+            self.memory_soft_cap_exceeded = false;
+        } else if !self.memory_soft_cap_exceeded {
+            self.memory_soft_cap_exceeded = true;
+            self.debug_info
+                .record_warning(Warning::MemorySoftCapExceeded { usage, cap });
+        }
+    }
+
+    /// Injects a fault which is triggered by the corresponding engine operation.
+    pub fn inject_fault(&mut self, fault: Fault) {
+        self.faults.push(fault);
+    }
+
+    /// Advances all pending storage write faults by one write.
+    ///
+    /// Returns `true` if one of them was triggered by the current write.
+    pub(crate) fn take_storage_write_fault(&mut self) -> bool {
+        let mut triggered = false;
+        self.faults.retain_mut(|fault| {
+            match fault {
+                Fault::FailNthStorageWrite(n) if *n <= 1 => {
+                    triggered = true;
+                    false
+                }
+                Fault::FailNthStorageWrite(n) => {
+                    *n -= 1;
+                    true
+                }
+                _ => true,
+            }
+        });
+        triggered
+    }
+
+    /// Returns the error of the first pending transfer fault, if any.
+    pub(crate) fn take_transfer_fault(&mut self) -> Option<ext::Error> {
+        let pos = self
+            .faults
+            .iter()
+            .position(|fault| matches!(fault, Fault::FailNextTransfer(_)))?;
+        match self.faults.remove(pos) {
+            Fault::FailNextTransfer(error) => Some(error),
+            _ => unreachable!("the fault at `pos` is a transfer fault"),
+        }
+    }
+
+    /// Returns the status of the first pending chain extension fault for `id`, if any.
+    pub(crate) fn take_chain_extension_fault(&mut self, id: u32) -> Option<u32> {
+        let pos = self.faults.iter().position(|fault| {
+            matches!(fault, Fault::ChainExtensionStatus { func_id, .. } if *func_id == id)
+        })?;
+        match self.faults.remove(pos) {
+            Fault::ChainExtensionStatus { status, .. } => Some(status),
+            _ => unreachable!("the fault at `pos` is a chain extension fault"),
+        }
     }
 
     /// Returns the total number of reads and writes of the contract's storage.
+    ///
+    /// Like all statistics accessors, this reports the statistics of the
+    /// [`InMemoryRecorder`] of the engine, none if another recorder is set.
     pub fn get_contract_storage_rw(&self, account_id: Vec<u8>) -> (usize, usize) {
-        let account_id = AccountId::from(account_id);
-        let reads = self.debug_info.count_reads.get(&account_id).unwrap_or(&0);
-        let writes = self.debug_info.count_writes.get(&account_id).unwrap_or(&0);
-        (*reads, *writes)
+        self.recorder.in_memory().map_or((0, 0), |recorder| {
+            (recorder.reads(&account_id), recorder.writes(&account_id))
+        })
+    }
+
+    /// Returns the number of events emitted by the contract `account_id`.
+    ///
+    /// Events are attributed to the callee of the execution emitting them.
+    pub fn events_emitted(&self, account_id: &[u8]) -> u32 {
+        self.recorder
+            .in_memory()
+            .and_then(|recorder| {
+                recorder
+                    .count_events
+                    .get(&AccountId::from_bytes(account_id))
+            })
+            .map_or(0, |(events, _)| *events)
+    }
+
+    /// Returns the number of bytes of the topics and data of the events emitted by
+    /// the contract `account_id`.
+    ///
+    /// Events are attributed to the callee of the execution emitting them.
+    pub fn event_bytes_emitted(&self, account_id: &[u8]) -> u64 {
+        self.recorder
+            .in_memory()
+            .and_then(|recorder| {
+                recorder
+                    .count_events
+                    .get(&AccountId::from_bytes(account_id))
+            })
+            .map_or(0, |(_, bytes)| *bytes)
+    }
+
+    /// Returns the recorded interactions of the contract `account_id`.
+    pub fn contract_statistics(&self, account_id: &[u8]) -> ContractStatistics {
+        let (storage_reads, storage_writes) =
+            self.get_contract_storage_rw(account_id.to_vec());
+        ContractStatistics {
+            storage_reads,
+            storage_writes,
+            events_emitted: self.events_emitted(account_id),
+            event_bytes_emitted: self.event_bytes_emitted(account_id),
+            cache_hits: self.cache_hits(account_id),
+            cache_misses: self.cache_misses(account_id),
+        }
     }
 
     /// Returns the total number of reads executed.
     pub fn count_reads(&self) -> usize {
-        self.debug_info.count_reads.values().sum()
+        self.recorder
+            .in_memory()
+            .map_or(0, |recorder| recorder.count_reads.values().sum())
     }
 
     /// Returns the total number of writes executed.
     pub fn count_writes(&self) -> usize {
-        self.debug_info.count_writes.values().sum()
+        self.recorder
+            .in_memory()
+            .map_or(0, |recorder| recorder.count_writes.values().sum())
+    }
+
+    /// Returns the number of clears of keys without a value in the storage of
+    /// `account_id`, which are not counted as writes.
+    pub fn count_noop_clears(&self, account_id: &[u8]) -> usize {
+        self.recorder
+            .in_memory()
+            .map_or(0, |recorder| recorder.noop_clears(account_id))
+    }
+
+    /// Returns the number of writes to the storage of `account_id` which created a
+    /// cell. Writes overwriting a value are not counted.
+    pub fn count_new_cells(&self, account_id: &[u8]) -> usize {
+        self.recorder
+            .in_memory()
+            .map_or(0, |recorder| recorder.new_cells(account_id))
     }
 
     /// Sets a caller for the next call.
+    ///
+    /// # Panics
+    ///
+    /// If the caller is forbidden, see [`Engine::try_set_caller`].
     pub fn set_caller(&mut self, caller: Vec<u8>) {
-        self.exec_context.caller = Some(caller.into());
+        self.try_set_caller(caller)
+            .unwrap_or_else(|error| panic!("setting the caller failed: {error:?}"))
+    }
+
+    /// Sets the caller, like [`Engine::set_caller`].
+    ///
+    /// # Errors
+    ///
+    /// [`AccountError::ZeroAddressForbidden`] if the caller is the all-zero account
+    /// while forbidden, see [`crate::ext::EngineConfig::allow_zero_address`].
+    pub fn try_set_caller(&mut self, caller: Vec<u8>) -> Result<(), Error> {
+        self.ensure_not_zero_address(&caller)?;
+        self.record_operation(Operation::SetCaller(caller.clone()));
+        self.exec_context.set_caller(Some(caller.into()));
+        Ok(())
     }
 
     /// Sets a known contract by adding it to a vector of known contracts accounts
+    ///
+    /// A tombstone at the account is replaced by the contract.
     pub fn set_contract(&mut self, caller: Vec<u8>) {
-        self.exec_context.contracts.push(caller);
+        self.record_operation(Operation::SetContract(caller.clone()));
+        self.tombstones.remove(&caller);
+        self.exec_context.add_contract(caller);
+    }
+
+    /// Returns the kind of the account `account_id`.
+    ///
+    /// Accounts become contracts via [`Engine::set_contract`], by registering their
+    /// entry points or handlers, or by being instantiated. A contract becomes a
+    /// tombstone once it terminates itself. Every other account is a user account.
+    pub fn account_kind(&self, account_id: &[u8]) -> AccountKind {
+        if self.tombstones.contains(account_id) {
+            AccountKind::Tombstone
+        } else if self.is_contract(account_id.to_vec()) {
+            AccountKind::Contract
+        } else {
+            AccountKind::User
+        }
+    }
+
+    /// Turns the contract `account_id` into a tombstone, see [`AccountKind::Tombstone`].
+    pub(crate) fn entomb(&mut self, account_id: &[u8]) {
+        self.exec_context.remove_contract(account_id);
+        self.tombstones.insert(account_id.to_vec());
+    }
+
+    /// Sets the origin of the next top-level execution.
+    ///
+    /// A signed origin is the caller of the top-level execution, like set via
+    /// [`Engine::set_caller`]. Executions dispatched by root or without origin have
+    /// no caller, reading it fails with [`Error::UnsignedOrigin`].
+    pub fn set_origin(&mut self, origin: Origin) {
+        self.record_operation(Operation::SetOrigin(origin.clone()));
+        self.exec_context.set_origin(origin);
+    }
+
+    /// Returns the origin of the top-level execution, `None` if it is signed but no
+    /// caller has been set.
+    ///
+    /// Chain extensions can query the origin via
+    /// [`ChainExtension::call_with_engine`](crate::ChainExtension::call_with_engine).
+    pub fn origin(&self) -> Option<Origin> {
+        self.exec_context.runtime_origin()
+    }
+
+    /// Runs `f` with `origin` as the origin of the top-level execution, see
+    /// [`Engine::set_origin`], and restores the previous origin afterwards.
+    pub fn call_with_origin<F, R>(&mut self, origin: Origin, f: F) -> R
+    where
+        F: FnOnce(&mut Engine) -> R,
+    {
+        let previous = self.origin();
+        self.set_origin(origin);
+        let result = f(self);
+        match previous {
+            Some(previous) => self.set_origin(previous),
+            None => self.exec_context.set_caller(None),
+        }
+        result
     }
 
     /// Sets the callee for the next call.
+    ///
+    /// # Panics
+    ///
+    /// If the callee is forbidden, see [`Engine::try_set_callee`].
     pub fn set_callee(&mut self, callee: Vec<u8>) {
-        self.exec_context.callee = Some(callee.into());
+        self.try_set_callee(callee)
+            .unwrap_or_else(|error| panic!("setting the callee failed: {error:?}"))
+    }
+
+    /// Sets the callee, like [`Engine::set_callee`].
+    ///
+    /// # Errors
+    ///
+    /// [`AccountError::ZeroAddressForbidden`] if the callee is the all-zero account
+    /// while forbidden, see [`crate::ext::EngineConfig::allow_zero_address`].
+    pub fn try_set_callee(&mut self, callee: Vec<u8>) -> Result<(), Error> {
+        self.ensure_not_zero_address(&callee)?;
+        self.record_operation(Operation::SetCallee(callee.clone()));
+        self.exec_context.set_callee(Some(callee.into()));
+        Ok(())
+    }
+
+    /// Deploys a contract for testing under `account_id`.
+    ///
+    /// The contract is funded with `initial_balance`, starts out with an empty
+    /// storage and is set as the callee for the next call.
+    pub fn deploy_test_contract(
+        &mut self,
+        account_id: Vec<u8>,
+        initial_balance: Balance,
+    ) {
+        self.set_balance(account_id.clone(), initial_balance);
+        self.set_contract(account_id.clone());
+        self.debug_info
+            .register_account(AccountId::from_bytes(&account_id[..]));
+        self.set_callee(account_id);
+    }
+
+    /// Registers the entry points of the contract at `account_id`.
+    ///
+    /// The account becomes a known contract, calls to it are dispatched to its
+    /// `entry_points`.
+    pub fn register_contract(&mut self, account_id: Vec<u8>, entry_points: EntryPoints) {
+        self.register_callable_contract(&account_id);
+        self.dispatch_handler.register(account_id, entry_points);
+    }
+
+    /// Returns the entry points of the contract registered at `account_id`, if any.
+    pub fn get_entry_points(&self, account_id: &[u8]) -> Option<EntryPoints> {
+        self.dispatch_handler.entry_points(account_id)
+    }
+
+    /// Registers the entry points of the code with `code_hash`.
+    ///
+    /// Delegate calls of the code are dispatched to its `entry_points`, see
+    /// [`Engine::delegate_call`].
+    pub fn register_code(&mut self, code_hash: Vec<u8>, entry_points: EntryPoints) {
+        self.dispatch_handler.register_code(code_hash, entry_points);
+    }
+
+    /// Uploads `code` and returns its code hash, under which contracts are
+    /// instantiated from it, see [`Engine::instantiate`].
+    ///
+    /// The hash of bytes is their BLAKE2 256-bit hash. Under the `wasm-exec` feature,
+    /// contracts instantiated from a Wasm blob execute it, see the `wasm_exec` module.
+    /// Mocked code is identified by a synthetic hash of its entry points, which are
+    /// registered like via [`Engine::register_code`]. Uploading identical code again
+    /// yields the same hash.
+    ///
+    /// # Errors
+    ///
+    /// [`crate::ext::Error::CodeTooLarge`] if the bytes exceed
+    /// [`crate::ext::EngineConfig::max_code_len`].
+    pub fn upload_code(&mut self, code: Code) -> Result<Hash, ext::Error> {
+        let mut code_hash = [0; 32];
+        match code {
+            Code::Bytes(bytes) => {
+                if bytes.len() > self.config.max_code_len {
+                    return Err(ext::Error::CodeTooLarge)
+                }
+                crate::hashing::blake2b_256(&bytes, &mut code_hash);
+                self.dispatch_handler.register_code_hash(code_hash.to_vec());
+                #[cfg(feature = "wasm-exec")]
+                self.register_wasm_code(&code_hash, &bytes);
+            }
+            Code::Mock(entry_points) => {
+                let id = [
+                    &b"mock-code:"[..],
+                    &(entry_points.deploy as usize).to_le_bytes(),
+                    &(entry_points.call as usize).to_le_bytes(),
+                ]
+                .concat();
+                crate::hashing::blake2b_256(&id, &mut code_hash);
+                self.register_code(code_hash.to_vec(), entry_points);
+            }
+        }
+        Ok(Hash::from(code_hash))
+    }
+
+    /// Returns `true` if code has been uploaded or registered under `code_hash`.
+    pub fn code_exists(&self, code_hash: &[u8]) -> bool {
+        self.dispatch_handler.code_exists(code_hash)
+    }
+
+    /// Registers `handler` for the message with `selector` of the mocked contract at
+    /// `account_id`.
+    ///
+    /// The account becomes a known contract. Handlers read their input via `input`
+    /// and terminate via `return_value`, like entry points.
+    ///
+    /// # Errors
+    ///
+    /// If a handler was already registered for `selector` of the contract. The
+    /// registered handler is kept in this case.
+    pub fn register_contract_message(
+        &mut self,
+        account_id: Vec<u8>,
+        selector: [u8; 4],
+        handler: fn(),
+    ) -> Result<(), DuplicateSelector> {
+        self.register_callable_contract(&account_id);
+        self.dispatch_handler
+            .register_message(account_id, selector, handler)
+    }
+
+    /// Registers `handler` for all messages of the mocked contract at `account_id`
+    /// without a handler registered for their selector.
+    ///
+    /// The account becomes a known contract. Replaces a fallback handler previously
+    /// registered there.
+    pub fn register_fallback_message(&mut self, account_id: Vec<u8>, handler: fn()) {
+        self.register_callable_contract(&account_id);
+        self.dispatch_handler
+            .register_fallback_message(account_id, handler);
+    }
+
+    /// Returns the handler for the message with `selector` of the mocked contract at
+    /// `account_id`, falling back to its fallback handler, if any.
+    pub fn get_message_handler(
+        &self,
+        account_id: &[u8],
+        selector: [u8; 4],
+    ) -> Option<fn()> {
+        self.dispatch_handler.message_handler(account_id, selector)
+    }
+
+    /// Returns the selectors with a registered handler of the mocked contract at
+    /// `account_id` in ascending order.
+    ///
+    /// The fallback handler has no selector, hence it is not included.
+    pub fn registered_selectors(&self, account_id: &[u8]) -> Vec<[u8; 4]> {
+        self.dispatch_handler.registered_selectors(account_id)
+    }
+
+    /// Returns the error for a call of `selector` to the mocked contract at
+    /// `account_id` which found no function to dispatch to, `None` if no message
+    /// handlers are registered for the contract.
+    pub fn unknown_selector(
+        &self,
+        account_id: &[u8],
+        selector: [u8; 4],
+    ) -> Option<UnknownSelector> {
+        let registered = self.registered_selectors(account_id);
+        (!registered.is_empty()).then(|| {
+            UnknownSelector {
+                account_id: account_id.to_vec(),
+                selector,
+                registered,
+            }
+        })
+    }
+
+    /// Makes `account_id` a known contract for which calls can be dispatched.
+    pub(crate) fn register_callable_contract(&mut self, account_id: &[u8]) {
+        if !self.is_contract(account_id.to_vec()) {
+            self.set_contract(account_id.to_vec());
+        }
+        self.debug_info
+            .register_account(AccountId::from_bytes(account_id));
+    }
+
+    /// Sets the input for the next contract execution.
+    pub fn set_input(&mut self, input: Vec<u8>) {
+        self.exec_context.set_input(input);
+    }
+
+    /// Returns the value at the storage `key` of the contract at `account_id`, if
+    /// any.
+    ///
+    /// Unlike [`Engine::get_storage`] the read is not counted and not recorded.
+    pub fn get_storage_raw(&self, account_id: &[u8], key: &[u8]) -> Option<Vec<u8>> {
+        self.read_storage(account_id, key)
+    }
+
+    /// Writes `entries` of keys and encoded values into the storage of the contract
+    /// at `account_id` and returns the number of written entries.
+    ///
+    /// Meant to set up large storages for tests. The values are written as by the
+    /// contract, i.e. encoded by the storage codec, but the writes are neither
+    /// charged for, nor reserve storage deposits, nor are they checked against the
+    /// memory hard cap. They are reported to the recorder as a single batch, see
+    /// [`Recorder::record_batch_write`], and not recorded per frame.
+    ///
+    /// Unless the writes are buffered in the overlay of an entered frame or changes
+    /// of the storage are watched, the entries are merged into the database at once,
+    /// see [`Database::extend_contract_storage`].
+    ///
+    /// [`Recorder::record_batch_write`]: crate::recorder::Recorder::record_batch_write
+    /// [`Database::extend_contract_storage`]: crate::database::Database::extend_contract_storage
+    pub fn seed_storage<I>(&mut self, account_id: &[u8], entries: I) -> usize
+    where
+        I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    {
+        let entries = entries.into_iter();
+        let mut recorded = self
+            .scenario
+            .is_some()
+            .then(|| Vec::with_capacity(entries.size_hint().0));
+        let account = AccountId::from_bytes(account_id);
+        // Buffered or watched writes go through the regular write path, all others
+        // are merged into the database at once.
+        let merge =
+            !self.buffers_writes() && !self.debug_info.watches_storage_of(account_id);
+        let codec = Rc::clone(&self.storage_codec);
+        let mut stored =
+            Vec::with_capacity(if merge { entries.size_hint().0 } else { 0 });
+        let mut keys = Vec::with_capacity(entries.size_hint().0);
+        for (key, value) in entries {
+            if merge {
+                stored.push((codec.encode_key(&key), codec.encode_value(&key, &value)));
+            } else {
+                self.write_storage(account_id, &key, &value);
+            }
+            if let Some(recorded) = recorded.as_mut() {
+                recorded.push((key.clone(), value));
+            }
+            self.debug_info.record_written_key(account_id, &key);
+            keys.push(key);
+        }
+        if merge {
+            self.database.extend_contract_storage(account_id, stored);
+            self.read_cache.clear();
+        }
+        let count = keys.len();
+        let mut cells = keys.into_iter().map(|key| (key, true)).collect();
+        self.debug_info
+            .cells_per_account
+            .entry(account.clone())
+            .or_default()
+            .append(&mut cells);
+        self.recorder.record_batch_write(&account, count);
+        self.check_memory_soft_cap();
+        if let Some(entries) = recorded {
+            self.record_operation(Operation::SeedStorage {
+                account_id: account_id.to_vec(),
+                entries,
+            });
+        }
+        count
+    }
+
+    /// Writes `entries` of keys and encoded values into the storage of the callee,
+    /// see [`Engine::seed_storage`].
+    pub fn set_storage_batch<I>(&mut self, entries: I) -> usize
+    where
+        I: IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+    {
+        let callee = self.get_callee();
+        self.seed_storage(&callee, entries)
+    }
+
+    /// Returns the storage of the contract at `account_id` as key-value pairs.
+    ///
+    /// The entries are ordered by their key bytes.
+    pub fn storage_dump(&self, account_id: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.storage_keys(account_id)
+            .filter_map(|key| {
+                self.read_storage(account_id, key)
+                    .map(|value| (key.to_vec(), value))
+            })
+            .collect()
+    }
+
+    /// Returns the keys the contract at `account_id` has written to, ordered by
+    /// their bytes.
+    ///
+    /// Includes keys which have been removed by `take_storage`.
+    pub(crate) fn storage_keys(&self, account_id: &[u8]) -> impl Iterator<Item = &[u8]> {
+        self.debug_info
+            .cells_per_account
+            .get(&AccountId::from_bytes(account_id))
+            .into_iter()
+            .flat_map(|cells| cells.keys().map(Vec::as_slice))
+    }
+
+    /// Returns the accounts known to the engine ordered by their id bytes.
+    ///
+    /// An account is known if it has been assigned a balance, is a known contract or
+    /// has written to its storage.
+    pub fn accounts(&self) -> impl Iterator<Item = AccountInfo> + '_ {
+        let ids: BTreeSet<&[u8]> = self
+            .database
+            .accounts()
+            .chain(self.exec_context.contracts().iter().map(Vec::as_slice))
+            .chain(
+                self.debug_info
+                    .cells_per_account
+                    .keys()
+                    .map(AccountId::as_bytes),
+            )
+            .collect();
+        ids.into_iter().map(|id| {
+            AccountInfo {
+                id: id.to_vec(),
+                balance: self.database.get_balance(id).unwrap_or_default(),
+                is_contract: self.exec_context.contracts().iter().any(|c| c == id),
+                nonce: self.get_nonce(id),
+                storage_cells: self
+                    .storage_keys(id)
+                    .filter(|key| self.read_storage(id, key).is_some())
+                    .count(),
+            }
+        })
+    }
+
+    /// Returns the sum of the balances of all accounts, `None` if it overflows.
+    pub fn total_issuance(&self) -> Option<Balance> {
+        self.database.accounts().try_fold(0, |total: Balance, id| {
+            total.checked_add(self.database.get_balance(id).unwrap_or_default())
+        })
+    }
+
+    /// Returns the root hash of the storage of the contract at `account_id`.
+    ///
+    /// The root is the BLAKE2b 256-bit hash over the entries of [`Engine::storage_dump`]
+    /// in key order, each encoded as
+    ///
+    /// ```text
+    /// len(key) as u32 little endian ++ key ++ len(value) as u32 little endian ++ value
+    /// ```
+    ///
+    /// Only the contract storage is hashed, the bookkeeping of the engine is not.
+    /// The encoding is stable: equal storages have equal roots across releases.
+    pub fn storage_root(&self, account_id: &[u8]) -> [u8; 32] {
+        let mut encoded = Vec::new();
+        for (key, value) in self.storage_dump(account_id) {
+            for bytes in [key, value] {
+                let len =
+                    u32::try_from(bytes.len()).expect("usize to u32 conversion failed");
+                encoded.extend_from_slice(&len.to_le_bytes());
+                encoded.extend_from_slice(&bytes);
+            }
+        }
+        let mut root = [0; 32];
+        crate::hashing::blake2b_256(&encoded, &mut root);
+        root
+    }
+
+    /// Exports the balances and contract storages as JSON.
+    ///
+    /// Balances are ordered by account id bytes, contracts by their account id bytes
+    /// and storage entries by their key bytes. Hence equal states export to
+    /// byte-identical JSON, regardless of the order in which they were built up.
+    ///
+    /// Bytes are hex encoded with a `0x` prefix, balances are decimal strings. The
+    /// weights charged by host functions are listed in the order of declaration of
+    /// [`crate::weights::WeightTable`]. The version is
+    /// [`crate::state::STATE_VERSION`]:
+    ///
+    /// ```json
+    /// {"version":2,"balances":{"0x0101..":"1000"},
+    ///  "storage":{"0x0101..":{"0x2a":"0x01"}},
+    ///  "weights":{"storage_write_base":250000000,..}}
+    /// ```
+    ///
+    /// The export is imported via [`Engine::import_state`], after deserializing it
+    /// into a [`crate::state::EngineState`].
+    pub fn export_state_json(&self) -> String {
+        let mut json = String::new();
+        write!(
+            json,
+            "{{\"version\":{},\"balances\":{{",
+            crate::state::STATE_VERSION
+        )
+        .expect("writing to a string cannot fail");
+        for (n, account_id) in self.database.accounts().enumerate() {
+            let balance = self.database.get_balance(account_id).unwrap_or_default();
+            let separator = if n == 0 { "" } else { "," };
+            write!(
+                json,
+                "{separator}\"{}\":\"{balance}\"",
+                encode_hex(account_id)
+            )
+            .expect("writing to a string cannot fail");
+        }
+        json.push_str("},\"storage\":{");
+        for (n, account_id) in self.debug_info.cells_per_account.keys().enumerate() {
+            let separator = if n == 0 { "" } else { "," };
+            write!(
+                json,
+                "{separator}\"{}\":{{",
+                encode_hex(account_id.as_bytes())
+            )
+            .expect("writing to a string cannot fail");
+            let storage = self.storage_dump(account_id.as_bytes());
+            for (n, (key, value)) in storage.iter().enumerate() {
+                let separator = if n == 0 { "" } else { "," };
+                write!(
+                    json,
+                    "{separator}\"{}\":\"{}\"",
+                    encode_hex(key),
+                    encode_hex(value)
+                )
+                .expect("writing to a string cannot fail");
+            }
+            json.push('}');
+        }
+        json.push_str("},\"weights\":{");
+        for (n, (name, weight)) in self.config.weights.entries().iter().enumerate() {
+            let separator = if n == 0 { "" } else { "," };
+            write!(json, "{separator}\"{name}\":{weight}")
+                .expect("writing to a string cannot fail");
+        }
+        json.push_str("}}");
+        json
+    }
+
+    /// Returns a human readable dump of the engine state for diagnostics.
+    ///
+    /// Lists the execution context, the number of emitted events, the recorded debug
+    /// messages and warnings, and the state as exported by
+    /// [`Engine::export_state_json`]. The format is not stable.
+    pub fn debug_dump(&self) -> String {
+        let context = self.exec_context.snapshot();
+        let account = |account_id: &Option<Vec<u8>>| {
+            account_id
+                .as_deref()
+                .map_or_else(|| String::from("<unset>"), encode_hex)
+        };
+        let mut dump = String::from("ink_engine state:\n");
+        writeln!(dump, "  caller: {}", account(&context.caller))
+            .expect("writing to a string cannot fail");
+        writeln!(dump, "  callee: {}", account(&context.callee))
+            .expect("writing to a string cannot fail");
+        writeln!(
+            dump,
+            "  block: number {}, timestamp {}",
+            context.block_number, context.block_timestamp
+        )
+        .expect("writing to a string cannot fail");
+        writeln!(dump, "  call depth: {}", context.depth)
+            .expect("writing to a string cannot fail");
+        writeln!(dump, "  events emitted: {}", self.emitted_event_count())
+            .expect("writing to a string cannot fail");
+        for message in self.get_emitted_debug_messages() {
+            writeln!(dump, "  debug message: {message}")
+                .expect("writing to a string cannot fail");
+        }
+        for warning in &self.debug_info.warnings {
+            writeln!(dump, "  warning: {warning:?}")
+                .expect("writing to a string cannot fail");
+        }
+        writeln!(dump, "  state: {}", self.export_state_json())
+            .expect("writing to a string cannot fail");
+        dump
     }
 
     /// Returns the amount of storage cells used by the account `account_id`.
@@ -220,7 +1704,7 @@ impl Engine {
         let cells = self
             .debug_info
             .cells_per_account
-            .get(&account_id.to_owned().into())
+            .get(&AccountId::from_bytes(account_id))
             .ok_or_else(|| {
                 Error::Account(AccountError::NoAccountForId(account_id.to_vec()))
             })?;
@@ -228,29 +1712,584 @@ impl Engine {
     }
 
     /// Advances the chain by a single block.
+    ///
+    /// The timestamp of the new block is derived by the clock set via
+    /// [`Engine::set_clock`]. Without a clock it advances by the block time of the
+    /// chain specification.
+    ///
+    /// The callbacks registered via [`Engine::on_block_advance`] are invoked once the
+    /// block number and timestamp are updated. The state at the end of the finished
+    /// block is retained to revert to, see [`Engine::revert_to_block`].
     pub fn advance_block(&mut self) {
-        self.exec_context.block_number += 1;
-        self.exec_context.block_timestamp += self.chain_spec.block_time;
+        self.retain_block();
+        let block_number = self.exec_context.block_number() + 1;
+        let previous = self.exec_context.timestamp();
+        let timestamp = match &self.clock {
+            Some(clock) => (clock.borrow_mut())(block_number),
+            None => previous + self.chain_spec.block_time,
+        };
+        if timestamp < previous {
+            self.debug_info
+                .record_warning(Warning::ClockMovedBackwards {
+                    block_number,
+                    previous,
+                    timestamp,
+                });
+        }
+        self.exec_context.set_block_number(block_number);
+        self.exec_context.set_block_timestamp(timestamp);
+        self.record_operation(Operation::AdvanceBlock { timestamp });
+
+        let mut callbacks = core::mem::take(&mut self.block_callbacks);
+        for callback in &callbacks {
+            (callback.borrow_mut())(self, block_number);
+        }
+        callbacks.append(&mut self.block_callbacks);
+        self.block_callbacks = callbacks;
+    }
+
+    /// Advances the timestamp by `ms` milliseconds without advancing the block
+    /// number.
+    ///
+    /// The advanced timestamp stays in effect until the next
+    /// [`Engine::advance_block`]. The clock set via [`Engine::set_clock`] then derives
+    /// the timestamp of the new block as usual, without a clock the block time is
+    /// added to the advanced timestamp. An advancement beyond `u64::MAX` saturates,
+    /// which is recorded as [`Warning::TimestampSaturated`].
+    pub fn advance_time(&mut self, ms: u64) {
+        let previous = self.exec_context.timestamp();
+        let timestamp = previous.checked_add(ms).unwrap_or_else(|| {
+            self.debug_info
+                .record_warning(Warning::TimestampSaturated { previous, ms });
+            BlockTimestamp::MAX
+        });
+        self.exec_context.set_block_timestamp(timestamp);
+        self.record_operation(Operation::AdvanceTime(ms));
+    }
+
+    /// Registers `callback` to be invoked with the engine and the number of the new
+    /// block whenever the chain advances by a block.
+    ///
+    /// Callbacks are invoked in the order they were registered. Panics inside of a
+    /// callback propagate to the caller of [`Engine::advance_block`], the registered
+    /// callbacks are dropped in this case.
+    pub fn on_block_advance<F>(&mut self, callback: F)
+    where
+        F: FnMut(&mut Engine, BlockNumber) + 'static,
+    {
+        let callback: BlockCallback = Box::new(callback);
+        self.block_callbacks.push(Rc::new(RefCell::new(callback)));
+    }
+
+    /// Sets the clock which derives the timestamp of a block from its number
+    /// whenever the chain advances by a block.
+    ///
+    /// Replaces a previously set clock. A clock may move backwards, which is
+    /// recorded as [`Warning::ClockMovedBackwards`].
+    pub fn set_clock<F>(&mut self, clock: F)
+    where
+        F: FnMut(BlockNumber) -> BlockTimestamp + 'static,
+    {
+        let clock: Clock = Box::new(clock);
+        self.clock = Some(Rc::new(RefCell::new(clock)));
+    }
+
+    /// Sets the function computing the fee for an amount of gas, which is used by
+    /// [`Engine::weight_to_fee`] instead of the gas price of the chain.
+    ///
+    /// Replaces a previously set function. Scenario recordings capture the
+    /// computed fees, not the function.
+    pub fn set_weight_to_fee_fn<F>(&mut self, weight_to_fee: F)
+    where
+        F: Fn(u64) -> Balance + 'static,
+    {
+        let weight_to_fee: WeightToFee = Box::new(weight_to_fee);
+        self.weight_to_fee_fn = Some(Rc::new(weight_to_fee));
+    }
+
+    /// Sets the function deriving the addresses of instantiated contracts, which is
+    /// used by [`Engine::derive_contract_address`] instead of the scheme of
+    /// `pallet-contracts`.
+    ///
+    /// Replaces a previously set function. The derived addresses must have the
+    /// account length of the chain, see [`crate::ext::ChainSpec::account_length`].
+    pub fn set_address_derivation<F>(&mut self, derive: F)
+    where
+        F: Fn(&AddressDerivationInput) -> Vec<u8> + 'static,
+    {
+        let derive: AddressDerivation = Box::new(derive);
+        self.address_derivation = Some(Rc::new(derive));
+    }
+
+    /// Returns the recorded warnings in order.
+    pub fn get_recorded_warnings(&self) -> Vec<Warning> {
+        self.debug_info.warnings.clone()
+    }
+
+    /// Enables recording [`Diagnostic`]s for suspicious patterns, until the engine is
+    /// reset.
+    ///
+    /// From now on the written storage keys are tracked to detect reads of keys which
+    /// have never been written, see [`Engine::uninitialized_reads`]. The keys holding
+    /// a value count as written.
+    pub fn enable_diagnostics(&mut self) {
+        if self.debug_info.diagnostics_enabled() {
+            return
+        }
+        self.debug_info.diagnostics = Some(Vec::new());
+        let debug_info = &mut self.debug_info;
+        for (account_id, cells) in &debug_info.cells_per_account {
+            debug_info
+                .written_keys
+                .entry(account_id.as_bytes().to_vec())
+                .or_default()
+                .extend(cells.keys().cloned());
+        }
+    }
+
+    /// Returns the diagnostics recorded since they were enabled, in order.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.debug_info.diagnostics.clone().unwrap_or_default()
+    }
+
+    /// Returns the keys of the storage of `account_id` which were read without ever
+    /// having been written, in the order of the reads.
+    ///
+    /// Such reads are only detected while diagnostics are enabled, see
+    /// [`Engine::enable_diagnostics`], and recorded as
+    /// [`Diagnostic::UninitializedRead`]. The read itself still fails with
+    /// [`ext::Error::KeyNotFound`].
+    pub fn uninitialized_reads(&self, account_id: &[u8]) -> Vec<Vec<u8>> {
+        self.debug_info
+            .diagnostics
+            .iter()
+            .flatten()
+            .filter_map(|diagnostic| {
+                match diagnostic {
+                    Diagnostic::UninitializedRead {
+                        account_id: reader,
+                        key,
+                        ..
+                    } if reader == account_id => Some(key.clone()),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Asserts that no diagnostic has been recorded.
+    ///
+    /// # Panics
+    ///
+    /// If a diagnostic has been recorded. The panic message lists all of them.
+    pub fn assert_no_diagnostics(&self) {
+        let diagnostics = self.diagnostics();
+        assert!(
+            diagnostics.is_empty(),
+            "expected no diagnostics, recorded diagnostics: {diagnostics:?}"
+        );
+    }
+
+    /// Returns the successful transfers in order.
+    pub fn recorded_transfers(&self) -> &[TransferRecord] {
+        &self.debug_info.transfers
+    }
+
+    /// Returns the transfers rejected because implicit account creation is disabled,
+    /// see [`EngineConfig::allow_implicit_account_creation`].
+    ///
+    /// [`EngineConfig::allow_implicit_account_creation`]: crate::ext::EngineConfig::allow_implicit_account_creation
+    pub fn rejected_transfers(&self) -> &[TransferRecord] {
+        &self.debug_info.rejected_transfers
+    }
+
+    /// Returns the successful fixture transfers in order, see
+    /// [`Engine::transfer_between`].
+    pub fn fixture_transfers(&self) -> &[TransferRecord] {
+        &self.debug_info.fixture_transfers
+    }
+
+    /// Returns the current state of the execution context.
+    pub fn exec_context_snapshot(&self) -> ExecContextSnapshot {
+        self.exec_context.snapshot()
     }
 
     /// Returns the callee, i.e. the currently executing contract.
+    ///
+    /// # Panics
+    ///
+    /// If no callee has been set, see [`Engine::try_get_callee`].
     pub fn get_callee(&self) -> Vec<u8> {
-        self.exec_context.callee()
+        self.try_get_callee().unwrap_or_else(|_| {
+            panic!("no callee has been set, see `Engine::set_callee`")
+        })
+    }
+
+    /// Returns the callee, i.e. the currently executing contract.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::UninitializedExecutionContext`] if no callee has been set.
+    pub fn try_get_callee(&self) -> Result<Vec<u8>, Error> {
+        self.exec_context
+            .callee()
+            .map(|callee| callee.as_bytes().to_vec())
+            .ok_or(Error::UninitializedExecutionContext)
     }
 
     /// Returns boolean value indicating whether the account is a contract
     pub fn is_contract(&self, account_id: Vec<u8>) -> bool {
-        self.exec_context.contracts.contains(&account_id)
+        self.exec_context.contracts().contains(&account_id)
     }
 
     /// Returns the contents of the past performed environmental `debug_message` in order.
     pub fn get_emitted_debug_messages(&self) -> RecordedDebugMessages {
-        self.debug_info.emitted_debug_messages.clone()
+        self.recorder
+            .in_memory()
+            .map(|recorder| recorder.emitted_debug_messages.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the contents of the past performed environmental `debug_message` in order
+    /// and clears them from the recorder.
+    ///
+    /// Other recorded interactions, such as emitted events, are left untouched.
+    pub fn take_emitted_debug_messages(&mut self) -> RecordedDebugMessages {
+        self.recorder
+            .in_memory_mut()
+            .map(|recorder| core::mem::take(&mut recorder.emitted_debug_messages))
+            .unwrap_or_default()
     }
 
     /// Returns the recorded emitted events in order.
+    ///
+    /// All recorded events are cloned, use [`Engine::emitted_events`] to iterate
+    /// them without cloning.
     pub fn get_emitted_events(&self) -> impl Iterator<Item = EmittedEvent> {
-        self.debug_info.emitted_events.clone().into_iter()
+        self.recorder
+            .in_memory()
+            .map(|recorder| recorder.emitted_events.clone())
+            .unwrap_or_default()
+            .into_iter()
+    }
+
+    /// Returns references to the recorded emitted events in order.
+    pub fn emitted_events(&self) -> impl Iterator<Item = &EmittedEvent> {
+        self.recorder
+            .in_memory()
+            .into_iter()
+            .flat_map(|recorder| recorder.events())
+    }
+
+    /// Returns the number of recorded emitted events, like
+    /// `get_emitted_events().count()` without cloning them.
+    pub fn emitted_event_count(&self) -> usize {
+        self.recorder
+            .in_memory()
+            .map_or(0, |recorder| recorder.events().len())
+    }
+
+    /// Calls `f` with each recorded emitted event in order.
+    pub fn for_each_emitted_event(&self, f: impl FnMut(&EmittedEvent)) {
+        self.emitted_events().for_each(f)
+    }
+
+    /// Returns the records of all execution frames in the order they were entered.
+    ///
+    /// The first record is the root frame.
+    pub fn recorded_frames(&self) -> Vec<FrameRecord> {
+        self.debug_info.frames.clone()
+    }
+
+    /// Records every change of the storage cell at `key` of the contract
+    /// `account_id` from now on, see [`Engine::storage_changes`].
+    ///
+    /// Watching a cell does not count as a storage read.
+    pub fn watch_storage_key(&mut self, account_id: &[u8], key: &[u8]) {
+        self.debug_info
+            .storage_changes
+            .entry((account_id.to_vec(), key.to_vec()))
+            .or_default();
+    }
+
+    /// Returns the changes of the watched storage cell at `key` of the contract
+    /// `account_id` in the order they happened.
+    ///
+    /// Every write and removal is a change, even if it leaves the value unchanged.
+    pub fn storage_changes(&self, account_id: &[u8], key: &[u8]) -> Vec<StorageChange> {
+        self.debug_info
+            .storage_changes
+            .get(&(account_id.to_vec(), key.to_vec()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Starts logging the keys written to or removed from the storage of the contract
+    /// `account_id` and returns the id of the log, see
+    /// [`Engine::end_storage_write_log`].
+    ///
+    /// Several logs can be active at once, also for the same contract. Like watching
+    /// a cell, logging does not count as a storage read.
+    pub fn start_storage_write_log(&mut self, account_id: &[u8]) -> usize {
+        let id = self.debug_info.next_write_log;
+        self.debug_info.next_write_log += 1;
+        self.debug_info
+            .write_logs
+            .insert(id, (account_id.to_vec(), Vec::new()));
+        id
+    }
+
+    /// Returns the keys written to or removed from the storage since the write log
+    /// `id` was started, in the order they were first written.
+    ///
+    /// Empty if the log has ended or the engine has been reset since.
+    pub fn storage_write_log(&self, id: usize) -> Vec<Vec<u8>> {
+        self.debug_info
+            .write_logs
+            .get(&id)
+            .map(|(_, keys)| keys.clone())
+            .unwrap_or_default()
+    }
+
+    /// Ends the write log `id` and returns its keys, see
+    /// [`Engine::storage_write_log`].
+    pub fn end_storage_write_log(&mut self, id: usize) -> Vec<Vec<u8>> {
+        self.debug_info
+            .write_logs
+            .remove(&id)
+            .map(|(_, keys)| keys)
+            .unwrap_or_default()
+    }
+
+    /// Enters the execution of the contract at `callee` with `input`.
+    ///
+    /// The callee becomes the caller of nested executions. Each execution is
+    /// recorded as a separate frame, see [`Engine::recorded_frames`].
+    ///
+    /// # Panics
+    ///
+    /// If the fee of a top-level execution cannot be paid. Use
+    /// [`Engine::try_enter_frame`] to handle this case.
+    pub fn enter_frame(&mut self, callee: Vec<u8>, input: Vec<u8>) {
+        self.try_enter_frame(callee, input)
+            .unwrap_or_else(|error| panic!("fee withdrawal failed: {error:?}"))
+    }
+
+    /// Enters the execution of `callee` with `input`, like
+    /// [`Engine::enter_frame`].
+    ///
+    /// # Errors
+    ///
+    /// - [`ext::Error::FeeWithdrawalFailed`] if fee accounting is enabled and the caller
+    ///   of a top-level execution cannot pay its fee, see [`crate::fee_accounting`]. The
+    ///   execution is not entered in this case.
+    pub fn try_enter_frame(
+        &mut self,
+        callee: Vec<u8>,
+        input: Vec<u8>,
+    ) -> Result<(), ext::Error> {
+        if self.exec_context.depth() == 0 {
+            self.withdraw_fee()?;
+            if let Some(origin) = self.exec_context.caller() {
+                let origin = origin.as_bytes().to_vec();
+                self.increment_nonce(&origin);
+            }
+        }
+        self.record_operation(Operation::EnterFrame {
+            callee: callee.clone(),
+            input: input.clone(),
+        });
+        self.exec_context
+            .push_frame(AccountId::from_bytes(&callee[..]), input);
+        if self.exec_context.depth() == 1 {
+            self.enter_storage_deposit_frame();
+        }
+        self.enter_gas_frame();
+        self.enter_overlay_frame();
+        self.read_cache.clear();
+        let caller = self
+            .exec_context
+            .caller()
+            .map(|caller| caller.as_bytes().to_vec());
+        let frame = self.debug_info.enter_frame(caller.clone(), callee.clone());
+        let input = self.exec_context.input().to_vec();
+        self.record_call(frame, caller, callee, input);
+        Ok(())
+    }
+
+    /// Enters a synthetic execution of the current callee called by the contract
+    /// `caller`, as if the current caller had called `caller` which in turn called
+    /// the callee.
+    ///
+    /// The current caller thereby becomes the origin of nested executions, unless an
+    /// execution has already been entered. Unlike [`Engine::enter_frame`] nothing is
+    /// recorded and no fee is withdrawn, `caller` need not be a registered contract.
+    pub fn push_caller_frame(&mut self, caller: Vec<u8>) {
+        self.record_operation(Operation::PushCallerFrame(caller.clone()));
+        self.exec_context
+            .push_caller_frame(AccountId::from_bytes(&caller[..]));
+    }
+
+    /// Exits the synthetic execution entered via [`Engine::push_caller_frame`].
+    ///
+    /// # Panics
+    ///
+    /// If the current execution was not entered via [`Engine::push_caller_frame`].
+    pub fn pop_caller_frame(&mut self) {
+        self.exec_context.pop_caller_frame();
+        self.record_operation(Operation::PopCallerFrame);
+    }
+
+    /// Enters the execution of delegated code with `input` in the context of the
+    /// current contract execution.
+    ///
+    /// Keeps the current input if `input` is `None`.
+    pub(crate) fn enter_delegate_frame(&mut self, input: Option<Vec<u8>>) {
+        self.record_operation(Operation::EnterDelegateFrame {
+            input: input.clone(),
+        });
+        self.exec_context.push_delegate_frame(input);
+        self.enter_gas_frame();
+        self.enter_overlay_frame();
+        self.read_cache.clear();
+        let caller = self
+            .exec_context
+            .caller()
+            .map(|caller| caller.as_bytes().to_vec());
+        let callee = self.get_callee();
+        let frame = self.debug_info.enter_frame(caller.clone(), callee.clone());
+        let input = self.exec_context.input().to_vec();
+        self.record_call(frame, caller, callee, input);
+    }
+
+    /// Records the call of `callee` with `input` executing in `frame`.
+    fn record_call(
+        &mut self,
+        frame: usize,
+        caller: Option<Vec<u8>>,
+        callee: Vec<u8>,
+        input: Vec<u8>,
+    ) {
+        self.record_dispatch_input(&input);
+        let selector = input
+            .get(..4)
+            .and_then(|selector| <[u8; 4]>::try_from(selector).ok());
+        self.debug_info.calls.push_back(RecordedCall {
+            frame,
+            caller,
+            callee,
+            input,
+            selector,
+        });
+        self.debug_info.dropped_calls +=
+            retain_last(&mut self.debug_info.calls, self.config.max_recorded_calls);
+    }
+
+    /// Records `input` as the input of the last dispatch, see
+    /// [`Engine::last_dispatch_input`].
+    ///
+    /// Environments resolving the function to dispatch a call to themselves record
+    /// the input before, so that it is available if none is found.
+    pub fn record_dispatch_input(&mut self, input: &[u8]) {
+        self.debug_info.last_dispatch_input = Some(input.to_vec());
+    }
+
+    /// Returns the raw input bytes of the last top-level dispatch or nested call,
+    /// also if no function was found to dispatch it to, e.g. for an unknown
+    /// selector.
+    pub fn last_dispatch_input(&self) -> Option<Vec<u8>> {
+        self.debug_info.last_dispatch_input.clone()
+    }
+
+    /// Returns the inputs of all dispatched calls in the order they were entered.
+    ///
+    /// Only the last [`crate::ext::EngineConfig::max_recorded_calls`] are retained,
+    /// see [`Engine::dropped_records`].
+    pub fn recorded_calls(&self) -> Vec<RecordedCall> {
+        self.debug_info.calls.iter().cloned().collect()
+    }
+
+    /// Exits the current contract execution, restoring the execution context of
+    /// the enclosing one.
+    ///
+    /// Commits the buffered storage writes of the execution, see
+    /// [`crate::storage_overlay`].
+    ///
+    /// # Panics
+    ///
+    /// If no contract execution has been entered.
+    pub fn exit_frame(&mut self) {
+        self.commit_overlay_frame();
+        self.record_operation(Operation::ExitFrame);
+        self.leave_frame();
+    }
+
+    /// Exits the current contract execution, which reverted or trapped, restoring
+    /// the execution context of the enclosing one.
+    ///
+    /// Discards the buffered storage writes of the execution, see
+    /// [`crate::storage_overlay`]. The outcome of the execution with the `data` it
+    /// reverted with is available via [`Engine::last_call_outcome`], without the
+    /// events it emitted.
+    ///
+    /// # Panics
+    ///
+    /// If no contract execution has been entered.
+    pub fn revert_frame(&mut self, data: Vec<u8>) {
+        self.discard_overlay_frame();
+        self.record_operation(Operation::RevertFrame);
+        self.debug_info.mark_current_frame_reverted();
+        self.leave_frame();
+        if let Some(outcome) = self.debug_info.last_call_outcome.as_mut() {
+            outcome.data = data;
+        }
+    }
+
+    /// Restores the execution context of the execution enclosing the current one.
+    fn leave_frame(&mut self) {
+        self.exec_context.pop_frame();
+        self.leave_storage_deposit_frame();
+        let gas_consumed = self.leave_gas_frame();
+        self.debug_info.exit_frame();
+        if let Some(outcome) = self.debug_info.last_call_outcome.as_mut() {
+            outcome.gas_consumed = gas_consumed;
+        }
+        self.read_cache.clear();
+        if self.exec_context.depth() == 0 {
+            self.refund_fee();
+        }
+    }
+
+    /// Exits the current contract execution, which returned `data`.
+    ///
+    /// The outcome of the execution is available via [`Engine::last_call_outcome`].
+    ///
+    /// # Panics
+    ///
+    /// If no contract execution has been entered.
+    pub fn return_from_frame(&mut self, data: Vec<u8>) {
+        self.exit_frame();
+        if let Some(outcome) = self.debug_info.last_call_outcome.as_mut() {
+            outcome.data = data;
+        }
+    }
+
+    /// Returns the outcome of the last completed contract execution, if any.
+    ///
+    /// The counts of an outcome include the ones of the executions nested in it.
+    pub fn last_call_outcome(&self) -> Option<CallOutcome> {
+        self.debug_info.last_call_outcome.clone()
+    }
+
+    /// Parses a hex encoded account id of the length of the chain specification, with
+    /// or without a `0x` prefix.
+    ///
+    /// # Errors
+    ///
+    /// If `hex` is not hex encoded or decodes to a number of bytes other than
+    /// [`crate::ext::ChainSpec::account_length`].
+    pub fn account_id_from_hex(&self, hex: &str) -> Result<AccountId, HexError> {
+        AccountId::from_hex_with_length(hex, self.chain_spec.account_length)
     }
 
     /// Returns the current balance of `account_id`.
@@ -260,24 +2299,118 @@ impl Engine {
             .ok_or(Error::Account(AccountError::NoAccountForId(account_id)))
     }
 
+    /// Returns the sum of the storage deposits reserved from the balance of
+    /// `account_id`.
+    pub fn get_storage_deposit(&self, account_id: &[u8]) -> Balance {
+        self.storage_deposits.reserved_by(account_id)
+    }
+
     /// Sets the balance of `account_id` to `new_balance`.
+    ///
+    /// # Panics
+    ///
+    /// If the account is forbidden, see [`Engine::try_set_balance`].
     pub fn set_balance(&mut self, account_id: Vec<u8>, new_balance: Balance) {
+        self.try_set_balance(account_id, new_balance)
+            .unwrap_or_else(|error| panic!("setting the balance failed: {error:?}"))
+    }
+
+    /// Sets the balance of `account_id` to `new_balance`, like
+    /// [`Engine::set_balance`].
+    ///
+    /// # Errors
+    ///
+    /// [`AccountError::ZeroAddressForbidden`] if `account_id` is the all-zero
+    /// account while forbidden, see
+    /// [`crate::ext::EngineConfig::allow_zero_address`].
+    pub fn try_set_balance(
+        &mut self,
+        account_id: Vec<u8>,
+        new_balance: Balance,
+    ) -> Result<(), Error> {
+        self.ensure_not_zero_address(&account_id)?;
+        self.record_operation(Operation::SetBalance {
+            account_id: account_id.clone(),
+            balance: new_balance,
+        });
         self.database.set_balance(&account_id, new_balance);
+        Ok(())
+    }
+
+    /// Transfers `value` from `from` to `to` to set up a test, e.g. to fund bob from
+    /// the balance of alice without making alice the callee.
+    ///
+    /// The transfer is subject to the same checks as transfers of contracts, see
+    /// [`Engine::transfer`], but neither counts as activity of a contract nor is part
+    /// of [`Engine::recorded_transfers`]. It is recorded in
+    /// [`Engine::fixture_transfers`] instead. Contracts still cannot move the funds
+    /// of other accounts, [`Engine::transfer`] always debits the callee.
+    ///
+    /// # Errors
+    ///
+    /// [`ext::Error::TransferFailed`] if the balance of `from` does not cover
+    /// `value`, `to` does not exist while
+    /// [`crate::ext::EngineConfig::allow_implicit_account_creation`] is disabled,
+    /// the balance of `to` would overflow or either account is the forbidden
+    /// all-zero account. No balance is changed in this case.
+    pub fn transfer_between(
+        &mut self,
+        from: &[u8],
+        to: &[u8],
+        value: Balance,
+    ) -> Result<(), ext::Error> {
+        let result = self
+            .move_balance(from, to, value)
+            .map_err(|_| ext::Error::TransferFailed);
+        if result.is_ok() {
+            self.debug_info
+                .record_fixture_transfer(from.to_vec(), to.to_vec(), value);
+        }
+        self.record_operation(Operation::TransferBetween {
+            from: from.to_vec(),
+            to: to.to_vec(),
+            value,
+            error: result.as_ref().err().map(ext::Error::code),
+        });
+        result
+    }
+
+    /// Returns the balance of `asset` held by `account_id`.
+    ///
+    /// Accounts which have never held the asset have a balance of `0`.
+    pub fn get_asset_balance(&self, account_id: &[u8], asset: AssetId) -> Balance {
+        self.database
+            .get_asset_balance(account_id, asset)
+            .unwrap_or_default()
+    }
+
+    /// Sets the balance of `asset` held by `account_id` to `new_balance`.
+    pub fn set_asset_balance(
+        &mut self,
+        account_id: &[u8],
+        asset: AssetId,
+        new_balance: Balance,
+    ) {
+        self.database
+            .set_asset_balance(account_id, asset, new_balance);
     }
 
     /// Sets the value transferred from the caller to the callee as part of the call.
     pub fn set_value_transferred(&mut self, value: Balance) {
-        self.exec_context.value_transferred = value;
+        self.exec_context.set_value_transferred(value);
+        self.record_operation(Operation::SetValueTransferred(value));
     }
 
     /// Set the block timestamp for the execution context.
     pub fn set_block_timestamp(&mut self, new_block_timestamp: BlockTimestamp) {
-        self.exec_context.block_timestamp = new_block_timestamp;
+        self.exec_context.set_block_timestamp(new_block_timestamp);
+        self.record_operation(Operation::SetBlockTimestamp(new_block_timestamp));
     }
 
     /// Set the block number for the execution context.
     pub fn set_block_number(&mut self, new_block_number: BlockNumber) {
-        self.exec_context.block_number = new_block_number;
+        self.exec_context.set_block_number(new_block_number);
+        self.record_operation(Operation::SetBlockNumber(new_block_number));
     }
 }
 
@@ -293,6 +2426,22 @@ mod tests {
         assert_eq!(engine.get_callee(), account_id);
     }
 
+    #[test]
+    fn deployed_test_contract_is_funded_callee() {
+        // given
+        let mut engine = Engine::new();
+        let account_id = vec![1; 32];
+
+        // when
+        engine.deploy_test_contract(account_id.clone(), 1337);
+
+        // then
+        assert_eq!(engine.get_callee(), account_id);
+        assert!(engine.is_contract(account_id.clone()));
+        assert_eq!(engine.get_balance(account_id.clone()), Ok(1337));
+        assert_eq!(engine.count_used_storage_cells(&account_id), Ok(0));
+    }
+
     #[test]
     fn count_cells_per_account_must_stay_the_same() {
         // given
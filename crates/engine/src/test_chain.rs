@@ -0,0 +1,200 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A facade over the engine for integration-style tests of multiple contracts.
+//!
+//! A [`TestChain`] owns its [`Engine`], hence it is used via an explicit handle
+//! instead of the thread-local environment of `ink_env`, and several chains can
+//! exist side by side. Contracts are plain functions with access to the chain, see
+//! [`ContractCode`]. The chain enters and exits the execution frames of calls, so
+//! contracts read and write their storage via [`TestChain::engine_mut`] and call
+//! other contracts via [`TestChain::call`].
+
+use crate::{
+    ext::{
+        Engine,
+        Error,
+    },
+    hashing,
+    test_api::EmittedEvent,
+    types::{
+        AccountId,
+        Balance,
+    },
+};
+use ink_prelude::{
+    collections::BTreeMap,
+    vec::Vec,
+};
+
+/// The code of a contract deployed on a [`TestChain`].
+///
+/// Invoked with the chain, the selector of the called message and its SCALE
+/// encoded arguments. Returns the SCALE encoded output, or the data the contract
+/// reverted with as error.
+pub type ContractCode = fn(&mut TestChain, [u8; 4], &[u8]) -> Result<Vec<u8>, Vec<u8>>;
+
+/// The outcome of a call via [`TestChain::call`], the SCALE encoded output on
+/// success.
+///
+/// - [`Error::CalleeReverted`] carries the data the contract reverted with. Its storage
+///   writes are discarded if the engine is configured to, see [`crate::storage_overlay`].
+/// - [`Error::NotCallable`] if no contract is deployed at the called account.
+/// - [`Error::FeeWithdrawalFailed`] if the caller cannot pay the fee of the call, see
+///   [`crate::fee_accounting`].
+pub type CallResult = Result<Vec<u8>, Error>;
+
+/// A chain of contracts deployed on an owned engine.
+pub struct TestChain {
+    /// The engine holding the state of the chain.
+    engine: Engine,
+    /// The code of the deployed contracts by their account id.
+    contracts: BTreeMap<Vec<u8>, ContractCode>,
+}
+
+impl Default for TestChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestChain {
+    /// Creates a chain without any contracts on a new engine.
+    pub fn new() -> Self {
+        Self {
+            engine: Engine::new(),
+            contracts: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the engine holding the state of the chain.
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Returns the engine holding the state of the chain, e.g. for contracts to
+    /// access their storage.
+    pub fn engine_mut(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
+    /// Deploys `code` under an account id derived from `salt` and funds the
+    /// contract with `endowment`.
+    ///
+    /// The same `salt` always results in the same account id, like for
+    /// `ink_env::test::deploy_test_contract_with_salt`.
+    ///
+    /// # Panics
+    ///
+    /// If a contract has already been deployed with `salt`.
+    pub fn deploy(
+        &mut self,
+        code: ContractCode,
+        salt: &[u8],
+        endowment: Balance,
+    ) -> AccountId {
+        let mut account_id = [0; 32];
+        let input = [b"test-contract:", salt].concat();
+        hashing::blake2b_256(&input[..], &mut account_id);
+        assert!(
+            !self.contracts.contains_key(&account_id[..]),
+            "a contract has already been deployed with the salt {salt:?}"
+        );
+        self.contracts.insert(account_id.to_vec(), code);
+        self.engine
+            .deploy_test_contract(account_id.to_vec(), endowment);
+        AccountId::from_bytes(&account_id)
+    }
+
+    /// Calls the message with `selector` of `contract` with the SCALE encoded
+    /// `args`.
+    ///
+    /// Outside of any call, `caller` becomes the caller and origin of the call.
+    /// Calls made by contracts are nested in the current call, their caller is the
+    /// calling contract. Panics of the contract propagate to the caller.
+    ///
+    /// # Panics
+    ///
+    /// If a nested call is made with a `caller` other than the calling contract.
+    pub fn call(
+        &mut self,
+        caller: &AccountId,
+        contract: &AccountId,
+        selector: [u8; 4],
+        args: &[u8],
+    ) -> CallResult {
+        let code = *self
+            .contracts
+            .get(contract.as_bytes())
+            .ok_or(Error::NotCallable)?;
+        if self.engine.exec_context.depth() == 0 {
+            self.engine.set_caller(caller.as_bytes().to_vec());
+        } else {
+            assert_eq!(
+                self.engine.get_callee(),
+                caller.as_bytes(),
+                "the caller of a nested call must be the calling contract"
+            );
+        }
+        let input = [&selector[..], args].concat();
+        self.engine
+            .try_enter_frame(contract.as_bytes().to_vec(), input)?;
+        match code(self, selector, args) {
+            Ok(output) => {
+                self.engine.return_from_frame(output.clone());
+                Ok(output)
+            }
+            Err(data) => {
+                self.engine.revert_frame(data.clone());
+                Err(Error::CalleeReverted(data))
+            }
+        }
+    }
+
+    /// Returns the account id of the contract currently executing.
+    ///
+    /// # Panics
+    ///
+    /// Outside of any call.
+    pub fn address(&self) -> AccountId {
+        assert!(
+            self.engine.exec_context.depth() > 0,
+            "no contract is executing outside of calls"
+        );
+        AccountId::from_bytes(&self.engine.get_callee())
+    }
+
+    /// Advances the chain by a single block, see [`Engine::advance_block`].
+    pub fn advance_block(&mut self) {
+        self.engine.advance_block();
+    }
+
+    /// Returns the balance of `account_id`, zero for unknown accounts.
+    pub fn balance(&self, account_id: &AccountId) -> Balance {
+        self.engine
+            .get_balance(account_id.as_bytes().to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Sets the balance of `account_id`.
+    pub fn set_balance(&mut self, account_id: &AccountId, balance: Balance) {
+        self.engine
+            .set_balance(account_id.as_bytes().to_vec(), balance);
+    }
+
+    /// Returns the events emitted by the contracts in the order they were emitted.
+    pub fn events(&self) -> Vec<EmittedEvent> {
+        self.engine.get_emitted_events().collect()
+    }
+}
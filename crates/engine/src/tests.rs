@@ -12,9 +12,60 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::ext::{
-    Engine,
-    Error,
+use crate::{
+    activity::{
+        ActivityLog,
+        ActivityLogError,
+        ACTIVITY_LOG_VERSION,
+    },
+    ext::{
+        Engine,
+        Error,
+        EventLimit,
+        EventLimitViolation,
+        MisusePolicy,
+        TopicsFormat,
+    },
+    recorder::{
+        DroppedRecords,
+        InMemoryRecorder,
+    },
+    scenario::{
+        Operation,
+        Scenario,
+        ScenarioReplayer,
+    },
+    state::{
+        EngineState,
+        StateError,
+    },
+    storage_overlay::CommitMode,
+    test_api::{
+        event_topics,
+        AccountInfo,
+        AccountKind,
+        Code,
+        ContractStatistics,
+        Diagnostic,
+        DroppedFrameItems,
+        Fault,
+        FrameItem,
+        FrameRecord,
+        TransferRecord,
+        Warning,
+    },
+    weights::WeightTable,
+    AccountId,
+    ChainExtension,
+    ChainExtensionCall,
+    ChainExtensionOutcome,
+    ChainExtensionTrapped,
+    EntryPoints,
+    Hash,
+    HexError,
+    Origin,
+    ReturnValue,
+    TestChain,
 };
 use secp256k1::{
     ecdsa::RecoverableSignature,
@@ -41,7 +92,7 @@ fn store_load_clear() {
     engine.set_callee(vec![1; 32]);
     let key: &[u8; 32] = &[0x42; 32];
     let res = engine.get_storage(key);
-    assert_eq!(res, Err(Error::KeyNotFound));
+    assert_eq!(res, Err(Error::KeyNotFound(key.to_vec())));
 
     engine.set_storage(key, &[0x05_u8; 5]);
     let res = engine.get_storage(key);
@@ -50,7 +101,28 @@ fn store_load_clear() {
 
     engine.clear_storage(key);
     let res = engine.get_storage(key);
-    assert_eq!(res, Err(Error::KeyNotFound));
+    assert_eq!(res, Err(Error::KeyNotFound(key.to_vec())));
+}
+
+#[test]
+fn error_codes_map_to_errors() {
+    use crate::ext::ReturnCode;
+
+    assert_eq!(Result::<(), Error>::from(ReturnCode(0)), Ok(()));
+    assert_eq!(
+        Result::<(), Error>::from(ReturnCode(5)),
+        Err(Error::TransferFailed)
+    );
+    assert_eq!(
+        Result::<(), Error>::from(ReturnCode(3)),
+        Err(Error::KeyNotFound(Vec::new()))
+    );
+    assert_eq!(
+        Result::<(), Error>::from(ReturnCode(77)),
+        Err(Error::UnknownError(77))
+    );
+    assert_eq!(Error::KeyNotFound(vec![1, 2, 3]).code(), 3);
+    assert_eq!(Error::UnknownError(77).code(), 77);
 }
 
 #[test]
@@ -64,7 +136,9 @@ fn setting_getting_balance() {
 
     // when
     let mut output = get_buffer();
-    engine.balance(&mut &mut output[..]);
+    engine
+        .balance(&mut &mut output[..])
+        .expect("reading balance failed");
 
     // then
     let output = <u128 as scale::Decode>::decode(&mut &output[..16])
@@ -83,10 +157,67 @@ fn setting_getting_caller() {
 
     // then
     let mut output = get_buffer();
-    engine.caller(&mut &mut output[..]);
+    engine
+        .caller(&mut &mut output[..])
+        .expect("reading caller failed");
     assert_eq!(&output[..account_id.len()], &account_id);
 }
 
+#[test]
+fn reading_unset_caller_fails() {
+    // given
+    let engine = Engine::new();
+
+    // when
+    let mut output = get_buffer();
+    let res = engine.caller(&mut &mut output[..]);
+
+    // then
+    assert_eq!(res, Err(crate::Error::UninitializedExecutionContext));
+}
+
+#[test]
+fn signed_origin_is_caller_of_top_level_execution() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_origin(Origin::Signed(vec![1; 32]));
+
+    // when
+    engine.enter_frame(vec![2; 32], Vec::new());
+    let mut output = get_buffer();
+    let res = engine.caller(&mut &mut output[..]);
+
+    // then
+    assert_eq!(res, Ok(32));
+    assert_eq!(&output[..32], &[1; 32]);
+    assert_eq!(engine.origin(), Some(Origin::Signed(vec![1; 32])));
+}
+
+#[test]
+fn root_origin_has_no_caller() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_caller(vec![1; 32]);
+    let mut output = get_buffer();
+
+    // when
+    let (top_level, nested) = engine.call_with_origin(Origin::Root, |engine| {
+        engine.enter_frame(vec![2; 32], Vec::new());
+        let top_level = engine.caller(&mut &mut output[..]);
+        engine.enter_frame(vec![3; 32], Vec::new());
+        let nested = engine.caller(&mut &mut output[..]);
+        engine.exit_frame();
+        engine.exit_frame();
+        (top_level, nested)
+    });
+
+    // then
+    assert_eq!(top_level, Err(crate::Error::UnsignedOrigin));
+    assert_eq!(nested, Ok(32));
+    assert_eq!(&output[..32], &[2; 32]);
+    assert_eq!(engine.origin(), Some(Origin::Signed(vec![1; 32])));
+}
+
 #[test]
 fn address() {
     // given
@@ -96,7 +227,9 @@ fn address() {
 
     // when
     let mut output = get_buffer();
-    engine.address(&mut &mut output[..]);
+    engine
+        .address(&mut &mut output[..])
+        .expect("reading address failed");
 
     // then
     assert_eq!(&output[..account_id.len()], &account_id);
@@ -129,21 +262,38 @@ fn debug_messages() {
     assert_eq!(recorded.next(), None);
 }
 
+#[test]
+fn taking_debug_messages_drains_only_debug_messages() {
+    // given
+    let mut engine = Engine::new();
+    let enc_topics_count = scale::Encode::encode(&scale::Compact(0u32));
+    engine.debug_message("foo");
+    engine.deposit_event(&enc_topics_count, &[1]);
+    engine.debug_message("bar");
+    engine.deposit_event(&enc_topics_count, &[2]);
+
+    // when
+    let taken: Vec<String> = engine.take_emitted_debug_messages().into_iter().collect();
+    engine.debug_message("foobar");
+
+    // then
+    assert_eq!(taken, vec!["foo", "bar"]);
+    let recorded = engine.get_emitted_debug_messages();
+    assert_eq!(recorded.containing("foo"), vec!["foobar"]);
+    assert_eq!(recorded.containing("baz"), Vec::<String>::new());
+    assert_eq!(engine.get_emitted_events().count(), 2);
+}
+
 #[test]
 fn events() {
     // given
     let mut engine = Engine::new();
-    let topics_count: scale::Compact<u32> = scale::Compact(2u32);
-    let mut enc_topics_count = scale::Encode::encode(&topics_count);
     let topic1 = vec![12u8, 13];
     let topic2 = vec![14u8, 15];
     let data = &vec![21, 22, 23];
 
     // when
-    let mut enc_topics_info: Vec<u8> = Vec::new();
-    enc_topics_info.append(&mut enc_topics_count);
-    enc_topics_info.append(&mut topic1.clone());
-    enc_topics_info.append(&mut topic2.clone());
+    let enc_topics_info = scale::Encode::encode(&vec![topic1.clone(), topic2.clone()]);
     engine.deposit_event(&enc_topics_info, data);
 
     // then
@@ -162,6 +312,90 @@ fn events() {
     assert!(events.next().is_none());
 }
 
+#[test]
+fn topics_of_mixed_lengths_are_split_by_their_length_prefixes() {
+    // given
+    let mut engine = Engine::new();
+    let topics = vec![vec![1u8; 32], vec![2; 4], Vec::new(), vec![3; 20]];
+
+    // when
+    engine.deposit_event(&scale::Encode::encode(&topics), &[1]);
+
+    // then
+    let event = engine
+        .get_emitted_events()
+        .next()
+        .expect("event must exist");
+    assert_eq!(event.topics, topics);
+    assert!(engine.get_recorded_warnings().is_empty());
+}
+
+#[test]
+fn concatenated_topics_are_split_evenly_with_a_deprecation_warning() {
+    // given
+    let mut engine = Engine::new();
+    let mut topics = scale::Encode::encode(&scale::Compact(2u32));
+    topics.extend([7; 32]);
+    topics.extend([8; 32]);
+
+    // when
+    engine
+        .try_deposit_event_with_format(&topics, TopicsFormat::Concatenated, &[1])
+        .expect("event within limits");
+
+    // then
+    let event = engine
+        .get_emitted_events()
+        .next()
+        .expect("event must exist");
+    assert_eq!(event.topics, vec![vec![7; 32], vec![8; 32]]);
+    assert_eq!(
+        engine.get_recorded_warnings(),
+        [Warning::DeprecatedTopicsFormat { topics_count: 2 }]
+    );
+}
+
+#[test]
+fn concatenated_topics_are_not_decoded_as_framed_topics() {
+    // given
+    let mut engine = Engine::new();
+    let mut topics = scale::Encode::encode(&scale::Compact(1u32));
+    // also decodes as a single framed topic of 31 bytes
+    topics.extend([0x7C; 32]);
+
+    // when
+    engine
+        .try_deposit_event_with_format(&topics, TopicsFormat::Concatenated, &[1])
+        .expect("event within limits");
+
+    // then
+    let event = engine
+        .get_emitted_events()
+        .next()
+        .expect("event must exist");
+    assert_eq!(event.topics, vec![vec![0x7C; 32]]);
+}
+
+#[test]
+fn concatenated_topics_without_topics_decode_to_no_topics() {
+    // given
+    let mut engine = Engine::new();
+    let no_topics = scale::Encode::encode(&scale::Compact(0u32));
+
+    // when
+    engine
+        .try_deposit_event_with_format(&no_topics, TopicsFormat::Concatenated, &[1])
+        .expect("event within limits");
+
+    // then
+    let event = engine
+        .get_emitted_events()
+        .next()
+        .expect("event must exist");
+    assert!(event.topics.is_empty());
+    assert_eq!(event.data, [1]);
+}
+
 #[test]
 fn value_transferred() {
     // given
@@ -171,7 +405,9 @@ fn value_transferred() {
 
     // when
     let output = &mut &mut get_buffer()[..];
-    engine.value_transferred(output);
+    engine
+        .value_transferred(output)
+        .expect("reading value transferred failed");
 
     // then
     let output = <u128 as scale::Decode>::decode(&mut &output[..16])
@@ -262,7 +498,9 @@ fn setting_getting_block_timestamp() {
     // when
     engine.advance_block();
     engine.set_block_timestamp(new_block_timestamp);
-    engine.block_timestamp(output);
+    engine
+        .block_timestamp(output)
+        .expect("reading block timestamp failed");
 
     // then
     let output = <u64 as scale::Decode>::decode(&mut &output[..16])
@@ -280,10 +518,4567 @@ fn setting_getting_block_number() {
     // when
     engine.advance_block();
     engine.set_block_number(new_block_number);
-    engine.block_number(output);
+    engine
+        .block_number(output)
+        .expect("reading block number failed");
 
     // then
     let output = <u32 as scale::Decode>::decode(&mut &output[..16])
         .expect("decoding value transferred failed");
     assert_eq!(output, new_block_number);
 }
+
+#[test]
+fn injected_storage_write_fault_traps_nth_write() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    engine.inject_fault(Fault::FailNthStorageWrite(2));
+
+    // when
+    engine.set_storage(&[0x01; 32], &[0x01]);
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        engine.set_storage(&[0x02; 32], &[0x02]);
+    }));
+
+    // then
+    assert!(res.is_err());
+    assert_eq!(engine.get_storage(&[0x01; 32]), Ok(&[0x01][..]));
+    assert_eq!(
+        engine.get_storage(&[0x02; 32]),
+        Err(Error::KeyNotFound(vec![0x02; 32]))
+    );
+
+    // the fault is consumed
+    engine.set_storage(&[0x02; 32], &[0x02]);
+    assert_eq!(engine.get_storage(&[0x02; 32]), Ok(&[0x02][..]));
+}
+
+#[test]
+fn injected_transfer_fault_fails_next_transfer_only() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let bob = vec![2; 32];
+    engine.set_callee(alice.clone());
+    engine.set_balance(alice.clone(), 1337);
+    engine.inject_fault(Fault::FailNextTransfer(Error::TransferFailed));
+    let val = scale::Encode::encode(&337u128);
+
+    // when
+    assert_eq!(engine.transfer(&bob, &val), Err(Error::TransferFailed));
+
+    // then
+    assert_eq!(engine.get_balance(alice.clone()), Ok(1337));
+    assert_eq!(engine.transfer(&bob, &val), Ok(()));
+    assert_eq!(engine.get_balance(alice), Ok(1000));
+}
+
+#[test]
+fn injected_chain_extension_fault_overrides_status_once() {
+    struct MockExtension;
+    impl ChainExtension for MockExtension {
+        fn ext_id(&self) -> u16 {
+            0
+        }
+
+        fn call(&mut self, _func_id: u16, _input: &[u8], output: &mut Vec<u8>) -> u32 {
+            output.push(42);
+            0
+        }
+    }
+
+    // given
+    let mut engine = Engine::new();
+    engine
+        .chain_extension_handler
+        .register(Box::new(MockExtension));
+    engine.inject_fault(Fault::ChainExtensionStatus {
+        func_id: 1,
+        status: 7,
+    });
+    let call = |engine: &mut Engine| {
+        let mut output = get_buffer();
+        engine.call_chain_extension(1, &[], &mut &mut output[..]);
+        <(u32, Vec<u8>) as scale::Decode>::decode(&mut &output[..])
+            .expect("decoding chain extension output failed")
+    };
+
+    // then
+    assert_eq!(call(&mut engine), (7, vec![]));
+    assert_eq!(call(&mut engine), (0, vec![42]));
+}
+
+#[test]
+fn chain_extension_observes_origin() {
+    struct OriginExtension;
+    impl ChainExtension for OriginExtension {
+        fn ext_id(&self) -> u16 {
+            0
+        }
+
+        fn call(&mut self, _func_id: u16, _input: &[u8], _output: &mut Vec<u8>) -> u32 {
+            unreachable!("the extension is called with the engine")
+        }
+
+        fn call_with_engine(
+            &mut self,
+            engine: &mut Engine,
+            _func_id: u16,
+            _input: &[u8],
+            output: &mut Vec<u8>,
+        ) -> u32 {
+            output.push(u8::from(engine.origin() == Some(Origin::Root)));
+            0
+        }
+    }
+
+    // given
+    let mut engine = Engine::new();
+    engine
+        .chain_extension_handler
+        .register(Box::new(OriginExtension));
+    let call = |engine: &mut Engine| {
+        let mut output = get_buffer();
+        engine.call_chain_extension(1, &[], &mut &mut output[..]);
+        <(u32, Vec<u8>) as scale::Decode>::decode(&mut &output[..])
+            .expect("decoding chain extension output failed")
+    };
+
+    // when
+    engine.set_origin(Origin::Signed(vec![1; 32]));
+    let signed = call(&mut engine);
+    engine.set_origin(Origin::Root);
+    let root = call(&mut engine);
+
+    // then
+    assert_eq!(signed, (0, vec![0]));
+    assert_eq!(root, (0, vec![1]));
+}
+
+#[test]
+fn chain_extension_output_exceeding_the_buffer_is_rejected() {
+    struct MockExtension;
+    impl ChainExtension for MockExtension {
+        fn ext_id(&self) -> u16 {
+            0
+        }
+
+        fn call(&mut self, func_id: u16, _input: &[u8], output: &mut Vec<u8>) -> u32 {
+            output.resize(usize::from(func_id), 7);
+            0
+        }
+    }
+
+    // given
+    let mut engine = Engine::new();
+    engine
+        .chain_extension_handler
+        .register(Box::new(MockExtension));
+    let mut output = get_buffer();
+
+    // when
+    let empty = engine.try_call_chain_extension(0, &[], &mut &mut output[..]);
+    let fitting = engine.try_call_chain_extension(1000, &[], &mut &mut output[..]);
+    let exceeding = engine.try_call_chain_extension(1020, &[], &mut &mut output[..]);
+
+    // then
+    assert_eq!(empty, Ok(5));
+    assert_eq!(fitting, Ok(1006));
+    assert_eq!(exceeding, Err(Error::BufferTooSmall(1026)));
+    assert_eq!(engine.chain_extension_handler.last_status(1020), Some(0));
+}
+
+#[test]
+fn weighted_chain_extension_is_charged_before_invocation() {
+    struct MockExtension;
+    impl ChainExtension for MockExtension {
+        fn ext_id(&self) -> u16 {
+            0
+        }
+
+        fn call(&mut self, _func_id: u16, _input: &[u8], output: &mut Vec<u8>) -> u32 {
+            output.push(42);
+            0
+        }
+    }
+
+    // given
+    let mut engine = Engine::new();
+    engine.config.gas_limit = 1_000;
+    engine
+        .chain_extension_handler
+        .register_with_weight(1, 600, Box::new(MockExtension));
+    let mut output = get_buffer();
+
+    // when
+    let result = engine.try_call_chain_extension(1, &[7], &mut &mut output[..]);
+
+    // then
+    assert_eq!(result, Ok(6));
+    assert_eq!(
+        <(u32, Vec<u8>) as scale::Decode>::decode(&mut &output[..]),
+        Ok((0, vec![42]))
+    );
+    assert_eq!(engine.gas_remaining(), 400);
+    assert_eq!(
+        engine.chain_extension_handler.calls(),
+        &[ChainExtensionCall {
+            func_id: 1,
+            input: vec![7],
+            weight: 600,
+            outcome: ChainExtensionOutcome::Returned {
+                status: 0,
+                output: vec![42],
+            },
+        }]
+    );
+    assert!(engine.chain_extension_handler.out_of_gas_calls().is_empty());
+}
+
+#[test]
+fn weighted_chain_extension_runs_out_of_gas_without_invocation() {
+    struct MockExtension;
+    impl ChainExtension for MockExtension {
+        fn ext_id(&self) -> u16 {
+            0
+        }
+
+        fn call(&mut self, _func_id: u16, _input: &[u8], _output: &mut Vec<u8>) -> u32 {
+            panic!("the chain extension must not be invoked")
+        }
+    }
+
+    // given
+    let mut engine = Engine::new();
+    engine.config.gas_limit = 1_000;
+    engine.consume_gas(500);
+    engine
+        .chain_extension_handler
+        .register_with_weight(1, 600, Box::new(MockExtension));
+    let mut output = get_buffer();
+
+    // when
+    let result = engine.try_call_chain_extension(1, &[7], &mut &mut output[..]);
+
+    // then
+    assert_eq!(result, Err(Error::OutOfGas));
+    assert_eq!(engine.gas_remaining(), 500);
+    assert!(engine.chain_extension_handler.calls().is_empty());
+    assert_eq!(
+        engine.chain_extension_handler.out_of_gas_calls(),
+        &[ChainExtensionCall {
+            func_id: 1,
+            input: vec![7],
+            weight: 600,
+            outcome: ChainExtensionOutcome::NotInvoked,
+        }]
+    );
+}
+
+#[test]
+fn returning_value_unwinds_with_flags_and_data() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_input(vec![1, 2, 3]);
+    let mut input = get_buffer();
+    let written = engine
+        .input(&mut &mut input[..])
+        .expect("reading input failed");
+    assert_eq!(&input[..written], &[1, 2, 3]);
+
+    // when
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        engine.return_value(1, &input[..written])
+    }));
+
+    // then
+    let returned = result
+        .expect_err("`return_value` must not return")
+        .downcast::<ReturnValue>()
+        .expect("payload must be the returned value");
+    assert_eq!(
+        *returned,
+        ReturnValue {
+            flags: 1,
+            data: vec![1, 2, 3],
+        }
+    );
+}
+
+#[test]
+fn registered_contract_has_entry_points() {
+    fn deploy() {}
+    fn call() {}
+
+    // given
+    let mut engine = Engine::new();
+    let account_id = vec![1; 32];
+
+    // when
+    engine.register_contract(account_id.clone(), EntryPoints { deploy, call });
+
+    // then
+    assert!(engine.is_contract(account_id.clone()));
+    assert!(engine.get_entry_points(&account_id).is_some());
+    assert!(engine.get_entry_points(&[2; 32]).is_none());
+}
+
+#[test]
+fn nested_frames_record_their_own_items() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let bob = vec![2; 32];
+    let charlie = vec![3; 32];
+    engine.set_caller(alice.clone());
+
+    // when
+    engine.deposit_event(&[0u8], &[0]);
+    engine.enter_frame(bob.clone(), vec![]);
+    engine.deposit_event(&[0u8], &[1]);
+    engine.enter_frame(charlie.clone(), vec![]);
+    engine.set_storage(&[42], &[1]);
+    engine.deposit_event(&[0u8], &[2]);
+    engine.exit_frame();
+    engine.debug_message("back in bob");
+    engine.exit_frame();
+
+    // then
+    let frames = engine.recorded_frames();
+    assert_eq!(frames.len(), 3);
+    let (root, outer, inner) = (&frames[0], &frames[1], &frames[2]);
+    assert_eq!((root.id, root.depth, root.callee.clone()), (0, 0, None));
+    assert_eq!(
+        root.events().map(|e| e.data.clone()).collect::<Vec<_>>(),
+        [[0]]
+    );
+    assert_eq!(root.items.last(), Some(&FrameItem::Call(1)));
+
+    assert_eq!((outer.id, outer.depth), (1, 1));
+    assert_eq!(outer.caller, Some(alice.clone()));
+    assert_eq!(outer.callee, Some(bob.clone()));
+    assert_eq!(
+        outer.events().map(|e| e.data.clone()).collect::<Vec<_>>(),
+        [[1]]
+    );
+    assert_eq!(
+        outer.items[1..],
+        [
+            FrameItem::Call(2),
+            FrameItem::DebugMessage("back in bob".into())
+        ]
+    );
+
+    assert_eq!((inner.id, inner.depth), (2, 2));
+    assert_eq!(inner.caller, Some(bob));
+    assert_eq!(inner.callee, Some(charlie));
+    assert_eq!(inner.items[0], FrameItem::StorageWrite(vec![42]));
+    assert_eq!(
+        inner.events().map(|e| e.data.clone()).collect::<Vec<_>>(),
+        [[2]]
+    );
+
+    // the flat recorders still contain everything
+    assert_eq!(engine.get_emitted_events().count(), 3);
+    assert_eq!(engine.exec_context.caller(), Some(&alice.into()));
+    assert_eq!(engine.exec_context.callee(), None);
+}
+
+#[test]
+fn exported_state_does_not_depend_on_insertion_order() {
+    // given
+    let bob = vec![2; 2];
+    let build = |forward: bool| {
+        let mut engine = Engine::new();
+        let mut steps: Vec<fn(&mut Engine)> = vec![
+            |engine| engine.set_balance(vec![1; 2], 10),
+            |engine| engine.set_balance(vec![2; 2], 20),
+            |engine| {
+                engine.set_callee(vec![2; 2]);
+                engine.set_storage(&[2], &[22]);
+            },
+            |engine| {
+                engine.set_callee(vec![2; 2]);
+                engine.set_storage(&[1], &[11]);
+            },
+        ];
+        if !forward {
+            steps.reverse();
+        }
+        for step in steps {
+            step(&mut engine);
+        }
+        engine
+    };
+
+    // when
+    let forward = build(true);
+    let backward = build(false);
+
+    // then
+    assert_eq!(forward.export_state_json(), backward.export_state_json());
+    assert_eq!(
+        forward.export_state_json(),
+        "{\"version\":2,\"balances\":{\"0x0101\":\"10\",\"0x0202\":\"20\"},\
+        \"storage\":{\"0x0202\":{\"0x01\":\"0x0b\",\"0x02\":\"0x16\"}},\
+        \"weights\":{\"storage_write_base\":250000000,\"storage_write_per_byte\":300000,\
+        \"storage_read_base\":150000000,\"storage_read_per_byte\":50000,\
+        \"event_base\":100000000,\"event_per_topic\":120000000,\"event_per_byte\":10000,\
+        \"hash_base\":10000000,\"hash_per_byte\":3000,\"call_base\":400000000,\
+        \"transfer_base\":200000000,\"gas_left_base\":5000000}}"
+    );
+    assert_eq!(
+        backward.storage_dump(&bob),
+        vec![(vec![1], vec![11]), (vec![2], vec![22])]
+    );
+}
+
+#[test]
+fn snapshot_diff_lists_the_changes_in_order() {
+    use crate::snapshot::{
+        BalanceDiff,
+        CellChange,
+        CellDiff,
+        EventCountDiff,
+    };
+
+    // given
+    let (alice, bob, contract) = (vec![1; 2], vec![2; 2], vec![3; 2]);
+    let mut engine = Engine::new();
+    engine.set_balance(alice.clone(), 100);
+    engine.set_callee(contract.clone());
+    engine.set_storage(&[1], &[11]);
+    engine.set_storage(&[2], &[22]);
+    let before = engine.snapshot();
+
+    // when
+    engine.set_balance(alice.clone(), 90);
+    engine.set_balance(bob.clone(), 10);
+    engine.set_storage(&[3], &[33]);
+    engine.set_storage(&[1], &[12]);
+    assert_eq!(engine.take_storage(&[2]), Ok(vec![22]));
+    engine.deposit_event(&scale::Encode::encode(&scale::Compact(0u32)), &[1]);
+    let diff = before.diff(&engine.snapshot());
+
+    // then
+    assert_eq!(
+        diff.storage,
+        [
+            CellDiff {
+                account_id: contract.clone(),
+                key: vec![1],
+                change: CellChange::Changed {
+                    old: vec![11],
+                    new: vec![12],
+                },
+            },
+            CellDiff {
+                account_id: contract.clone(),
+                key: vec![2],
+                change: CellChange::Removed { value: vec![22] },
+            },
+            CellDiff {
+                account_id: contract.clone(),
+                key: vec![3],
+                change: CellChange::Added { value: vec![33] },
+            },
+        ]
+    );
+    assert_eq!(
+        diff.balances,
+        [
+            BalanceDiff {
+                account_id: alice,
+                old: 100,
+                new: 90,
+            },
+            BalanceDiff {
+                account_id: bob.clone(),
+                old: 0,
+                new: 10,
+            },
+        ]
+    );
+    assert_eq!(diff.new_accounts, [bob]);
+    assert_eq!(
+        diff.events,
+        [EventCountDiff {
+            account_id: contract,
+            old: 0,
+            new: 1,
+        }]
+    );
+    assert_eq!(
+        diff.to_string(),
+        "new account 0x0202\n\
+         balance of 0x0101: 100 -> 90 (-10)\n\
+         balance of 0x0202: 0 -> 10 (+10)\n\
+         storage of 0x0303: changed 0x01 from 0x0b to 0x0c\n\
+         storage of 0x0303: removed 0x02 = 0x16\n\
+         storage of 0x0303: added 0x03 = 0x21\n\
+         events of 0x0303: 0 -> 1"
+    );
+}
+
+#[test]
+fn snapshot_diff_of_read_only_operations_is_empty() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_balance(vec![1; 2], 100);
+    engine.set_callee(vec![3; 2]);
+    engine.set_storage(&[1], &[11]);
+    let before = engine.snapshot();
+
+    // when
+    assert_eq!(engine.get_storage(&[1]), Ok(&[11][..]));
+    assert!(engine.get_storage(&[2]).is_err());
+    assert_eq!(engine.get_balance(vec![1; 2]), Ok(100));
+    let diff = before.diff(&engine.snapshot());
+
+    // then
+    assert!(diff.is_empty(), "{diff}");
+    assert_eq!(diff.to_string(), "no changes");
+}
+
+#[test]
+fn clock_derives_block_timestamps() {
+    // given
+    let mut engine = Engine::new();
+    let timestamp = |engine: &Engine| {
+        let mut output = get_buffer();
+        let written = engine
+            .block_timestamp(&mut &mut output[..])
+            .expect("reading block timestamp failed");
+        <u64 as scale::Decode>::decode(&mut &output[..written])
+            .expect("decoding block timestamp failed")
+    };
+    engine.set_clock(|block_number| {
+        match block_number {
+            3 => 1_000,
+            4 => 500,
+            n => u64::from(n) * 10,
+        }
+    });
+
+    // when
+    let timestamps: Vec<u64> = (0..5)
+        .map(|_| {
+            engine.advance_block();
+            timestamp(&engine)
+        })
+        .collect();
+
+    // then
+    assert_eq!(timestamps, [10, 20, 1_000, 500, 50]);
+    assert_eq!(
+        engine.get_recorded_warnings(),
+        [
+            Warning::ClockMovedBackwards {
+                block_number: 4,
+                previous: 1_000,
+                timestamp: 500,
+            },
+            Warning::ClockMovedBackwards {
+                block_number: 5,
+                previous: 500,
+                timestamp: 50,
+            }
+        ]
+    );
+}
+
+#[test]
+fn advancing_time_leaves_the_block_number_unchanged() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_clock(|block_number| u64::from(block_number) * 1_000);
+    engine.advance_block();
+
+    // when
+    engine.advance_time(300);
+    let advanced = (
+        engine.exec_context.block_number(),
+        engine.exec_context.timestamp(),
+    );
+    engine.advance_block();
+    let next_block = (
+        engine.exec_context.block_number(),
+        engine.exec_context.timestamp(),
+    );
+
+    // then
+    assert_eq!(advanced, (1, 1_300));
+    assert_eq!(next_block, (2, 2_000));
+    assert!(engine.get_recorded_warnings().is_empty());
+}
+
+#[test]
+fn advancing_time_beyond_the_maximum_saturates() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_block_timestamp(10);
+
+    // when
+    engine.advance_time(u64::MAX);
+
+    // then
+    assert_eq!(engine.exec_context.timestamp(), u64::MAX);
+    assert_eq!(engine.exec_context.block_number(), 0);
+    assert_eq!(
+        engine.get_recorded_warnings(),
+        [Warning::TimestampSaturated {
+            previous: 10,
+            ms: u64::MAX,
+        }]
+    );
+}
+
+/// Records a scenario with storage operations, a transfer and an event.
+fn record_scenario() -> (Engine, Scenario) {
+    let mut engine = Engine::new();
+    let (alice, bob) = (vec![1; 32], vec![2; 32]);
+    engine.start_recording_scenario();
+    engine.set_caller(alice.clone());
+    engine.deploy_test_contract(bob.clone(), 1_000);
+    engine.set_clock(|block_number| u64::from(block_number) * 100);
+    engine.advance_block();
+    engine.set_storage(&[1], &[11]);
+    engine.set_storage(&[2], &[22]);
+    let _ = engine.take_storage(&[1]);
+    assert!(engine.contains_storage(&[2]).is_some());
+    engine
+        .transfer(&alice, &scale::Encode::encode(&100u128))
+        .expect("transfer failed");
+    engine.deposit_event(&[0x04, 0x04, 0x01], &[0x05]);
+    let scenario = engine.stop_recording_scenario();
+    (engine, scenario)
+}
+
+#[test]
+fn recorded_scenario_round_trips_through_replay() {
+    // given
+    let (recorded, scenario) = record_scenario();
+
+    // when
+    let json = serde_json::to_string(&scenario).expect("serializing scenario failed");
+    let scenario: Scenario =
+        serde_json::from_str(&json).expect("deserializing scenario failed");
+    let replayed = Engine::replay(&scenario)
+        .unwrap_or_else(|divergence| panic!("replay diverged: {divergence:?}"));
+
+    // then
+    assert_eq!(replayed.export_state_json(), recorded.export_state_json());
+    assert_eq!(
+        replayed.get_emitted_events().collect::<Vec<_>>(),
+        recorded.get_emitted_events().collect::<Vec<_>>()
+    );
+    assert!(scenario
+        .operations
+        .contains(&Operation::AdvanceBlock { timestamp: 100 }));
+}
+
+#[test]
+fn replay_reports_first_diverging_operation() {
+    // given
+    let (_, mut scenario) = record_scenario();
+    let index = scenario
+        .operations
+        .iter()
+        .position(|operation| matches!(operation, Operation::ContainsStorage { .. }))
+        .expect("scenario contains no storage check");
+    scenario.operations[index] = Operation::ContainsStorage {
+        key: vec![2],
+        size: None,
+    };
+
+    // when
+    let divergence = Engine::replay(&scenario)
+        .err()
+        .expect("replay did not diverge");
+
+    // then
+    assert_eq!(divergence.index, index);
+    assert_eq!(
+        divergence.replayed,
+        Operation::ContainsStorage {
+            key: vec![2],
+            size: Some(1),
+        }
+    );
+}
+
+#[test]
+fn call_with_undecodable_value_fails() {
+    let mut engine = Engine::new();
+    let res = engine.call(&[1; 32], 0, &[1, 2, 3], &[], &mut &mut get_buffer()[..]);
+    assert_eq!(res, Err(Error::InvalidValue));
+    assert_eq!(Error::InvalidValue.code(), 15);
+}
+
+#[test]
+fn call_with_short_callee_fails() {
+    let mut engine = Engine::new();
+    let value = scale::Encode::encode(&0u128);
+    let res = engine.call(&[1; 20], 0, &value, &[], &mut &mut get_buffer()[..]);
+    assert_eq!(res, Err(Error::InvalidCallee(vec![1; 20])));
+    assert_eq!(Error::InvalidCallee(Vec::new()).code(), 16);
+}
+
+#[test]
+fn call_with_zero_gas_limit_inherits_gas() {
+    let engine = Engine::new();
+    let value = scale::Encode::encode(&10u128);
+
+    let inherited = engine
+        .validate_call(&[1; 32], 0, &value)
+        .expect("validating call failed");
+    let limited = engine
+        .validate_call(&[1; 32], 1_000, &value)
+        .expect("validating call failed");
+
+    assert_eq!(inherited.gas_limit, None);
+    assert_eq!(limited.gas_limit, Some(1_000));
+    assert_eq!(limited.value, 10);
+    assert_eq!(limited.callee, vec![1; 32]);
+}
+
+#[cfg(feature = "allowances")]
+#[test]
+fn transfer_from_exhausts_allowance() {
+    use crate::allowances::AllowanceError;
+
+    // given
+    let mut engine = Engine::new();
+    let (owner, spender, dest) = ([1; 32], [2; 32], [3; 32]);
+    engine.set_balance(owner.to_vec(), 100);
+    engine.set_allowance(&owner, &spender, 30);
+
+    // when
+    let first = engine.transfer_from(&spender, &owner, &dest, 20);
+    let second = engine.transfer_from(&spender, &owner, &dest, 20);
+
+    // then
+    assert_eq!(first, Ok(()));
+    assert_eq!(
+        second,
+        Err(AllowanceError::InsufficientAllowance {
+            allowance: 10,
+            amount: 20,
+        })
+    );
+    assert_eq!(engine.allowance(&owner, &spender), 10);
+    assert_eq!(engine.get_balance(owner.to_vec()), Ok(80));
+    assert_eq!(engine.get_balance(dest.to_vec()), Ok(20));
+}
+
+#[cfg(feature = "allowances")]
+#[test]
+fn transfer_from_spends_exact_allowance() {
+    use crate::allowances::AllowanceError;
+
+    // given
+    let mut engine = Engine::new();
+    let (owner, spender, dest) = ([1; 32], [2; 32], [3; 32]);
+    engine.set_balance(owner.to_vec(), 50);
+    engine.set_allowance(&owner, &spender, 50);
+
+    // when
+    let spent = engine.transfer_from(&spender, &owner, &dest, 50);
+
+    // then
+    assert_eq!(spent, Ok(()));
+    assert_eq!(engine.allowance(&owner, &spender), 0);
+    assert_eq!(engine.get_balance(owner.to_vec()), Ok(0));
+    assert_eq!(engine.get_balance(dest.to_vec()), Ok(50));
+
+    // when
+    engine.set_allowance(&owner, &spender, 10);
+    let overdrawn = engine.transfer_from(&spender, &owner, &dest, 10);
+
+    // then
+    assert_eq!(
+        overdrawn,
+        Err(AllowanceError::InsufficientBalance {
+            balance: 0,
+            amount: 10,
+        })
+    );
+    assert_eq!(engine.allowance(&owner, &spender), 10);
+}
+
+#[test]
+fn storage_root_depends_only_on_storage_contents() {
+    // given
+    let contract = vec![1; 32];
+    let build = |entries: &[(&[u8], &[u8])]| {
+        let mut engine = Engine::new();
+        engine.set_callee(contract.clone());
+        for (key, value) in entries {
+            engine.set_storage(key, value);
+        }
+        engine
+    };
+
+    // when
+    let forward = build(&[(&[1], &[11]), (&[2], &[22, 23])]);
+    let backward = build(&[(&[2], &[22, 23]), (&[1], &[11])]);
+    let changed = build(&[(&[1], &[11]), (&[2], &[22, 24])]);
+
+    // then
+    assert_eq!(
+        forward.storage_root(&contract),
+        backward.storage_root(&contract)
+    );
+    assert_ne!(
+        forward.storage_root(&contract),
+        changed.storage_root(&contract)
+    );
+    // The encoding of the root is stable across releases.
+    assert_eq!(
+        forward.storage_root(&contract),
+        [
+            0x1d, 0x76, 0x21, 0x75, 0xfd, 0x5f, 0x2f, 0xba, 0xfc, 0xe0, 0x88, 0x56, 0xdb,
+            0x4f, 0x79, 0x16, 0x82, 0x46, 0x6b, 0xbe, 0x56, 0x97, 0x36, 0xdc, 0xc7, 0x12,
+            0xc2, 0x3b, 0xeb, 0x83, 0x56, 0xa5,
+        ]
+    );
+}
+
+#[test]
+fn caller_in_non_strict_mode() {
+    let mut engine = Engine::new();
+    let mut output = get_buffer();
+    assert_eq!(
+        engine.caller(&mut &mut output[..]),
+        Err(crate::Error::UninitializedExecutionContext)
+    );
+    assert_eq!(engine.block_timestamp(&mut &mut output[..]), Ok(8));
+
+    engine.set_caller(vec![1; 32]);
+    assert_eq!(engine.caller(&mut &mut output[..]), Ok(32));
+}
+
+#[test]
+fn caller_in_strict_mode() {
+    let mut engine = Engine::new();
+    engine.config.strict = true;
+    let mut output = get_buffer();
+    assert_eq!(
+        engine.caller(&mut &mut output[..]),
+        Err(crate::Error::UninitializedProperty("caller"))
+    );
+    assert_eq!(
+        engine.block_timestamp(&mut &mut output[..]),
+        Err(crate::Error::UninitializedProperty("block_timestamp"))
+    );
+
+    engine.set_caller(vec![1; 32]);
+    engine.advance_block();
+    assert_eq!(engine.caller(&mut &mut output[..]), Ok(32));
+    assert_eq!(engine.block_timestamp(&mut &mut output[..]), Ok(8));
+}
+
+#[test]
+fn chain_extension_moves_asset_through_engine() {
+    const ASSET: u32 = 7;
+
+    struct AssetExtension;
+    impl ChainExtension for AssetExtension {
+        fn ext_id(&self) -> u16 {
+            0
+        }
+
+        fn call(&mut self, _func_id: u16, _input: &[u8], _output: &mut Vec<u8>) -> u32 {
+            unreachable!("asset transfers require access to the engine")
+        }
+
+        fn call_with_engine(
+            &mut self,
+            engine: &mut Engine,
+            _func_id: u16,
+            mut input: &[u8],
+            _output: &mut Vec<u8>,
+        ) -> u32 {
+            // The engine passes the input SCALE encoded.
+            let input = <Vec<u8> as scale::Decode>::decode(&mut input)
+                .expect("decoding input failed");
+            let (dest, value) =
+                <(Vec<u8>, u128) as scale::Decode>::decode(&mut &input[..])
+                    .expect("decoding asset transfer failed");
+            match engine.asset_transfer(ASSET, &dest, value) {
+                Ok(()) => 0,
+                Err(error) => error.code(),
+            }
+        }
+    }
+
+    // given
+    let mut engine = Engine::new();
+    let (contract, dest) = (vec![1; 32], vec![2; 32]);
+    engine.set_callee(contract.clone());
+    engine.set_balance(contract.clone(), 1_000);
+    engine.set_asset_balance(&contract, ASSET, 100);
+    engine
+        .chain_extension_handler
+        .register(Box::new(AssetExtension));
+    let transfer = |engine: &mut Engine, value: u128| {
+        let mut output = get_buffer();
+        let input = scale::Encode::encode(&(dest.clone(), value));
+        engine.call_chain_extension(1, &input, &mut &mut output[..]);
+        <(u32, Vec<u8>) as scale::Decode>::decode(&mut &output[..])
+            .expect("decoding chain extension output failed")
+            .0
+    };
+
+    // when
+    let moved = transfer(&mut engine, 60);
+    let overdrawn = transfer(&mut engine, 60);
+
+    // then
+    assert_eq!(moved, 0);
+    assert_eq!(overdrawn, Error::TransferFailed.code());
+    assert_eq!(engine.get_asset_balance(&contract, ASSET), 40);
+    assert_eq!(engine.get_asset_balance(&dest, ASSET), 60);
+    assert_eq!(engine.get_asset_balance(&dest, ASSET + 1), 0);
+    assert_eq!(engine.get_balance(contract), Ok(1_000));
+}
+
+#[test]
+fn asset_transfer_overflowing_the_destination_fails() {
+    const ASSET: u32 = 7;
+
+    // given
+    let mut engine = Engine::new();
+    let (contract, dest) = (vec![1; 32], vec![2; 32]);
+    engine.set_callee(contract.clone());
+    engine.set_asset_balance(&contract, ASSET, 100);
+    engine.set_asset_balance(&dest, ASSET, u128::MAX - 10);
+
+    // when
+    let res = engine.asset_transfer(ASSET, &dest, 11);
+
+    // then
+    assert_eq!(res, Err(Error::TransferFailed));
+    assert_eq!(engine.get_asset_balance(&contract, ASSET), 100);
+    assert_eq!(engine.get_asset_balance(&dest, ASSET), u128::MAX - 10);
+}
+
+#[test]
+fn hooks_are_invoked_around_host_functions() {
+    use crate::hooks::EngineHooks;
+    use std::{
+        cell::RefCell,
+        rc::Rc,
+    };
+
+    // given
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let returns = Rc::new(RefCell::new(Vec::new()));
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    engine.set_hooks(EngineHooks {
+        on_host_call: Some(Box::new({
+            let calls = calls.clone();
+            move |name, info| calls.borrow_mut().push((name, info.input_len))
+        })),
+        on_host_return: Some(Box::new({
+            let returns = returns.clone();
+            move |name, ok| returns.borrow_mut().push((name, ok))
+        })),
+    });
+
+    // when
+    engine.set_storage(&[1], &[2, 3]);
+    let _ = engine.get_storage(&[1]);
+    let _ = engine.take_storage(&[4]);
+    let mut output = get_buffer();
+    let _ = engine.address(&mut &mut output[..]);
+    Engine::hash_blake2_256(&[], &mut [0; 32]);
+
+    // then
+    assert_eq!(
+        *calls.borrow(),
+        [
+            ("set_storage", 3),
+            ("get_storage", 1),
+            ("take_storage", 1),
+            ("address", 0)
+        ]
+    );
+    assert_eq!(
+        *returns.borrow(),
+        [
+            ("set_storage", true),
+            ("get_storage", true),
+            ("take_storage", false),
+            ("address", true)
+        ]
+    );
+}
+
+#[test]
+#[should_panic(expected = "panic in hook")]
+fn panics_in_hooks_propagate() {
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    engine.set_hooks(crate::hooks::EngineHooks {
+        on_host_call: Some(Box::new(|_, _| panic!("panic in hook"))),
+        on_host_return: None,
+    });
+    engine.set_storage(&[1], &[2]);
+}
+
+#[test]
+fn pallet_contracts_codec_hashes_keys_with_blake2_128_concat() {
+    use crate::storage_codec::{
+        PalletContractsCodec,
+        StorageCodec,
+    };
+
+    // The stored keys of the root and a lazy storage cell of an ink! contract.
+    let root_key = 0u32.to_le_bytes();
+    let lazy_key = 42u32.to_le_bytes();
+    assert_eq!(
+        PalletContractsCodec.encode_key(&root_key),
+        [
+            0x11, 0xd2, 0xdf, 0x4e, 0x97, 0x9a, 0xa1, 0x05, 0xcf, 0x55, 0x2e, 0x95, 0x44,
+            0xeb, 0xd2, 0xb5, 0x00, 0x00, 0x00, 0x00,
+        ]
+    );
+    assert_eq!(
+        PalletContractsCodec.encode_key(&lazy_key),
+        [
+            0x90, 0x17, 0xd1, 0xc7, 0x33, 0xec, 0x2a, 0xea, 0xa6, 0xfd, 0x02, 0x30, 0x61,
+            0x4e, 0x39, 0x7e, 0x2a, 0x00, 0x00, 0x00,
+        ]
+    );
+    assert_eq!(
+        PalletContractsCodec.decode_value(&root_key, &[1, 2]),
+        [1, 2]
+    );
+}
+
+#[test]
+fn storage_codec_determines_stored_keys_only() {
+    use crate::storage_codec::{
+        PalletContractsCodec,
+        StorageCodec,
+    };
+
+    // given
+    let contract = vec![1; 32];
+    let key = 0u32.to_le_bytes();
+    let mut engine = Engine::new();
+    engine.set_storage_codec(Box::new(PalletContractsCodec));
+    engine.set_callee(contract.clone());
+
+    // when
+    assert_eq!(engine.set_storage(&key, &[5, 6]), None);
+    assert_eq!(engine.set_storage(&key, &[7]), Some(2));
+
+    // then
+    assert_eq!(engine.get_storage(&key), Ok(&[7][..]));
+    assert_eq!(engine.contains_storage(&key), Some(1));
+    assert_eq!(engine.storage_dump(&contract), [(key.to_vec(), vec![7])]);
+    assert_eq!(
+        engine.raw_storage(&contract),
+        [(PalletContractsCodec.encode_key(&key), vec![7])]
+    );
+    assert_eq!(engine.clear_storage(&key), Some(1));
+    assert!(engine.raw_storage(&contract).is_empty());
+}
+
+/// Returns which of the handlers below was invoked last.
+fn invoked_handler() -> u8 {
+    INVOKED_HANDLER.with(|invoked| invoked.get())
+}
+
+thread_local! {
+    static INVOKED_HANDLER: core::cell::Cell<u8> = const { core::cell::Cell::new(0) };
+}
+
+fn first_handler() {
+    INVOKED_HANDLER.with(|invoked| invoked.set(1));
+}
+
+fn second_handler() {
+    INVOKED_HANDLER.with(|invoked| invoked.set(2));
+}
+
+#[test]
+fn duplicate_message_registration_is_rejected() {
+    use crate::DuplicateSelector;
+
+    // given
+    let contract = vec![1; 32];
+    let mut engine = Engine::new();
+    assert_eq!(
+        engine.register_contract_message(contract.clone(), [0, 0, 0, 2], first_handler),
+        Ok(())
+    );
+    assert_eq!(
+        engine.register_contract_message(contract.clone(), [0, 0, 0, 1], first_handler),
+        Ok(())
+    );
+
+    // when
+    let result =
+        engine.register_contract_message(contract.clone(), [0, 0, 0, 1], second_handler);
+
+    // then
+    assert_eq!(
+        result,
+        Err(DuplicateSelector {
+            account_id: contract.clone(),
+            selector: [0, 0, 0, 1],
+        })
+    );
+    engine
+        .get_message_handler(&contract, [0, 0, 0, 1])
+        .expect("handler is registered")();
+    assert_eq!(invoked_handler(), 1);
+    assert_eq!(
+        engine.registered_selectors(&contract),
+        [[0, 0, 0, 1], [0, 0, 0, 2]]
+    );
+    assert!(engine.is_contract(contract));
+}
+
+#[test]
+fn fallback_message_handles_unregistered_selectors() {
+    // given
+    let contract = vec![1; 32];
+    let mut engine = Engine::new();
+    engine
+        .register_contract_message(contract.clone(), [0, 0, 0, 1], first_handler)
+        .expect("selector is not registered yet");
+    assert!(engine
+        .get_message_handler(&contract, [0, 0, 0, 2])
+        .is_none());
+
+    // when
+    engine.register_fallback_message(contract.clone(), second_handler);
+
+    // then
+    engine
+        .get_message_handler(&contract, [0, 0, 0, 1])
+        .expect("handler is registered")();
+    assert_eq!(invoked_handler(), 1);
+    engine
+        .get_message_handler(&contract, [0, 0, 0, 2])
+        .expect("fallback is registered")();
+    assert_eq!(invoked_handler(), 2);
+    assert_eq!(engine.registered_selectors(&contract), [[0, 0, 0, 1]]);
+    assert!(engine.get_message_handler(&[2; 32], [0, 0, 0, 1]).is_none());
+}
+
+#[test]
+fn unknown_selector_reports_received_and_registered_selectors() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![2; 32];
+    engine.set_callee(vec![1; 32]);
+    engine
+        .register_contract_message(contract.clone(), [0, 0, 0, 1], first_handler)
+        .expect("selector is not registered yet");
+    engine
+        .register_contract_message(contract.clone(), [0, 0, 0, 2], second_handler)
+        .expect("selector is not registered yet");
+    let value = scale::Encode::encode(&0u128);
+
+    // when
+    let unknown = engine.enter_call(&contract, &value, Some(vec![0, 0, 0, 3, 7]));
+    let last_input = engine.last_dispatch_input();
+    let entered = engine.enter_call(&contract, &value, Some(vec![0, 0, 0, 1, 8]));
+
+    // then
+    let Err(Error::UnknownSelector(unknown)) = unknown else {
+        panic!("expected an unknown selector, got {unknown:?}")
+    };
+    assert_eq!(unknown.selector, [0, 0, 0, 3]);
+    let message = unknown.to_string();
+    assert!(message.contains("0x00000003"), "{message}");
+    assert!(message.contains("0x00000001, 0x00000002"), "{message}");
+    assert_eq!(last_input, Some(vec![0, 0, 0, 3, 7]));
+    assert!(entered.is_ok());
+    let calls = engine.recorded_calls();
+    let call = calls.last().expect("the call is recorded");
+    assert_eq!(call.callee, contract);
+    assert_eq!(call.input, [0, 0, 0, 1, 8]);
+    assert_eq!(call.selector, Some([0, 0, 0, 1]));
+}
+
+/// Returns an engine reserving a deposit of 10 per cell and 2 per byte from the
+/// caller, which has a balance of 100.
+fn engine_with_storage_deposit() -> Engine {
+    let mut engine = Engine::new();
+    engine.config.storage_deposit_per_cell = Some(10);
+    engine.config.storage_deposit_per_byte = Some(2);
+    engine.set_caller(vec![1; 32]);
+    engine.set_balance(vec![1; 32], 100);
+    engine.set_callee(vec![2; 32]);
+    engine
+}
+
+#[test]
+fn storage_deposit_is_charged_and_refunded() {
+    // given
+    let caller = vec![1; 32];
+    let depositor = vec![3; 32];
+    let mut engine = engine_with_storage_deposit();
+
+    // when
+    engine.set_storage(&[1], &[0; 5]);
+    engine.set_storage(&[2], &[0; 1]);
+
+    // then
+    assert_eq!(engine.get_storage_deposit(&caller), 20 + 12);
+    assert_eq!(engine.get_balance(caller.clone()), Ok(100 - 20 - 12));
+
+    // when
+    engine.set_storage(&[1], &[0; 2]);
+
+    // then
+    assert_eq!(engine.get_storage_deposit(&caller), 14 + 12);
+    assert_eq!(engine.get_balance(caller.clone()), Ok(100 - 14 - 12));
+
+    // when
+    engine.set_balance(depositor.clone(), 50);
+    engine.set_storage_depositor(Some(depositor.clone()));
+    engine.set_storage(&[2], &[0; 3]);
+
+    // then
+    assert_eq!(engine.get_storage_deposit(&caller), 14);
+    assert_eq!(engine.get_storage_deposit(&depositor), 16);
+    assert_eq!(engine.get_balance(caller.clone()), Ok(100 - 14));
+    assert_eq!(engine.get_balance(depositor.clone()), Ok(50 - 16));
+
+    // when
+    assert_eq!(engine.clear_storage(&[1]), Some(2));
+    assert_eq!(engine.take_storage(&[2]), Ok(vec![0; 3]));
+
+    // then
+    assert_eq!(engine.get_storage_deposit(&caller), 0);
+    assert_eq!(engine.get_storage_deposit(&depositor), 0);
+    assert_eq!(engine.get_balance(caller), Ok(100));
+    assert_eq!(engine.get_balance(depositor), Ok(50));
+}
+
+#[test]
+fn exhausted_storage_deposit_leaves_storage_unchanged() {
+    // given
+    let caller = vec![1; 32];
+    let mut engine = engine_with_storage_deposit();
+    engine.set_storage(&[1], &[0; 41]);
+    assert_eq!(engine.get_balance(caller.clone()), Ok(8));
+
+    // when
+    let grown = engine.try_set_storage(&[1], &[0; 46]);
+    let added = engine.try_set_storage(&[2], &[]);
+
+    // then
+    assert_eq!(grown, Err(Error::StorageDepositLimitExhausted));
+    assert_eq!(added, Err(Error::StorageDepositLimitExhausted));
+    assert_eq!(engine.get_storage(&[1]), Ok(&[0; 41][..]));
+    assert_eq!(engine.contains_storage(&[2]), None);
+    assert_eq!(engine.get_storage_deposit(&caller), 92);
+    assert_eq!(engine.get_balance(caller.clone()), Ok(8));
+
+    // when
+    let shrunk = engine.try_set_storage(&[1], &[0; 20]);
+
+    // then
+    assert_eq!(shrunk, Ok(Some(41)));
+    assert_eq!(engine.get_balance(caller), Ok(50));
+}
+
+#[test]
+#[should_panic(expected = "contract trapped: StorageDepositLimitExhausted")]
+fn set_storage_traps_on_exhausted_storage_deposit() {
+    let mut engine = engine_with_storage_deposit();
+    engine.set_storage(&[1], &[0; 46]);
+}
+
+#[test]
+fn storage_deposit_limit_chain_param_caps_the_reserved_deposits() {
+    use crate::chain_params::STORAGE_DEPOSIT_LIMIT;
+    use scale::Encode;
+
+    // given
+    let caller = vec![1; 32];
+    let mut engine = engine_with_storage_deposit();
+    engine.set_chain_param(STORAGE_DEPOSIT_LIMIT, 30u128.encode());
+    engine.set_storage(&[1], &[0; 5]);
+
+    // when
+    let exceeding = engine.try_set_storage(&[2], &[0; 1]);
+    let replacing = engine.try_set_storage(&[1], &[0; 10]);
+
+    // then
+    assert_eq!(exceeding, Err(Error::StorageDepositLimitExhausted));
+    assert_eq!(replacing, Ok(Some(5)));
+    assert_eq!(engine.get_storage_deposit(&caller), 30);
+}
+
+#[test]
+fn storage_deposit_limit_caps_the_next_top_level_execution() {
+    // given
+    let contract = vec![3; 32];
+    let mut engine = engine_with_storage_deposit();
+    engine.set_balance(vec![2; 32], 100);
+    engine.set_storage_deposit_limit(Some(12));
+
+    // when
+    engine.enter_frame(contract.clone(), Vec::new());
+    let first = engine.try_set_storage(&[1], &[0; 1]);
+    let second = engine.try_set_storage(&[2], &[0; 1]);
+    let cleared = engine.clear_storage(&[1]);
+    let third = engine.try_set_storage(&[2], &[0; 1]);
+    engine.exit_frame();
+    engine.enter_frame(contract, Vec::new());
+    let unlimited = engine.remaining_storage_deposit();
+
+    // then
+    assert_eq!(first, Ok(None));
+    assert_eq!(second, Err(Error::StorageDepositLimitExhausted));
+    assert_eq!(cleared, Some(1));
+    assert_eq!(third, Ok(None));
+    assert_eq!(unlimited, None);
+}
+
+#[test]
+fn storage_deposit_limits_of_nested_executions_apply_together() {
+    // given
+    let mut engine = engine_with_storage_deposit();
+    engine.set_balance(vec![2; 32], 100);
+    engine.set_balance(vec![3; 32], 100);
+    engine.set_storage_deposit_limit(Some(30));
+    engine.enter_frame(vec![3; 32], Vec::new());
+    engine.enter_frame(vec![4; 32], Vec::new());
+    engine.limit_frame_storage_deposit(50);
+
+    // when
+    let nested = engine.try_set_storage(&[1], &[0; 10]);
+    let remaining_nested = engine.remaining_storage_deposit();
+    let exceeding = engine.try_set_storage(&[2], &[0; 1]);
+    engine.exit_frame();
+    let remaining = engine.remaining_storage_deposit();
+
+    // then
+    assert_eq!(nested, Ok(None));
+    assert_eq!(remaining_nested, Some(0));
+    assert_eq!(exceeding, Err(Error::StorageDepositLimitExhausted));
+    assert_eq!(remaining, Some(0));
+}
+
+#[test]
+fn chain_extension_reads_custom_chain_param() {
+    use crate::chain_params::ChainParamError;
+    use scale::Encode;
+
+    struct ParamExtension;
+    impl ChainExtension for ParamExtension {
+        fn ext_id(&self) -> u16 {
+            0
+        }
+
+        fn call(&mut self, _func_id: u16, _input: &[u8], _output: &mut Vec<u8>) -> u32 {
+            unreachable!("the extension is called with the engine")
+        }
+
+        fn call_with_engine(
+            &mut self,
+            engine: &mut Engine,
+            _func_id: u16,
+            _input: &[u8],
+            output: &mut Vec<u8>,
+        ) -> u32 {
+            match engine.decode_chain_param::<u32>("max_proposals") {
+                Ok(max_proposals) => {
+                    output.extend(max_proposals.encode());
+                    0
+                }
+                Err(_) => 1,
+            }
+        }
+    }
+
+    // given
+    let mut engine = Engine::new();
+    engine
+        .chain_extension_handler
+        .register(Box::new(ParamExtension));
+    let call = |engine: &mut Engine| {
+        let mut output = get_buffer();
+        engine.call_chain_extension(1, &[], &mut &mut output[..]);
+        <(u32, Vec<u8>) as scale::Decode>::decode(&mut &output[..])
+            .expect("decoding chain extension output failed")
+    };
+    let unset = call(&mut engine);
+
+    // when
+    engine.set_chain_param("max_proposals", 7u32.encode());
+
+    // then
+    assert_eq!(unset, (1, vec![]));
+    assert_eq!(call(&mut engine), (0, 7u32.encode()));
+    assert_eq!(
+        engine.get_chain_param("max_proposals"),
+        Ok(&[7, 0, 0, 0][..])
+    );
+    assert_eq!(
+        engine.decode_chain_param::<u128>("max_proposals"),
+        Err(ChainParamError::Decoding {
+            name: "max_proposals".into()
+        })
+    );
+    let unknown = engine.get_chain_param("max_votes").unwrap_err();
+    assert_eq!(
+        unknown.to_string(),
+        "chain parameter `max_votes` has not been set, see `Engine::set_chain_param`, \
+         the engine recognizes: storage_deposit_limit"
+    );
+}
+
+#[test]
+fn delegate_call_keeps_caller_and_callee() {
+    fn deploy() {}
+    fn call() {}
+
+    // given
+    let mut engine = Engine::new();
+    engine.set_caller(vec![1; 32]);
+    engine.set_callee(vec![2; 32]);
+    engine.set_input(vec![0; 4]);
+    assert_eq!(
+        engine.delegate_call(&[7; 32], Some(vec![1; 4])).err(),
+        Some(Error::CodeNotFound)
+    );
+    engine.register_code(vec![7; 32], EntryPoints { deploy, call });
+
+    // when
+    engine
+        .delegate_call(&[7; 32], Some(vec![1; 4]))
+        .expect("code is registered");
+
+    // then
+    let mut output = get_buffer();
+    engine.caller(&mut &mut output[..]).expect("caller is set");
+    assert_eq!(&output[..32], &[1; 32]);
+    assert_eq!(engine.get_callee(), vec![2; 32]);
+    engine.input(&mut &mut output[..]).expect("input is set");
+    assert_eq!(&output[..4], &[1; 4]);
+
+    // when
+    engine.return_from_frame(Vec::new());
+
+    // then
+    engine.input(&mut &mut output[..]).expect("input is set");
+    assert_eq!(&output[..4], &[0; 4]);
+    assert_eq!(engine.recorded_frames().len(), 2);
+}
+
+#[test]
+fn exec_context_snapshot_is_restored_by_reset() {
+    // given
+    let mut engine = Engine::new();
+    let initial = engine.exec_context_snapshot();
+    engine.set_caller(vec![1; 32]);
+    engine.set_callee(vec![2; 32]);
+    engine.set_value_transferred(3);
+    engine.advance_block();
+    let changed = engine.exec_context_snapshot();
+    assert_eq!(changed.caller, Some(vec![1; 32]));
+    assert_eq!(changed.value_transferred, 3);
+    assert_eq!(changed.block_number, 1);
+    assert_ne!(changed, initial);
+
+    // when
+    engine.initialize_or_reset();
+
+    // then
+    assert_eq!(engine.exec_context_snapshot(), initial);
+}
+
+#[test]
+fn exec_context_snapshot_reflects_the_current_frame() {
+    // given
+    let mut engine = Engine::new();
+    let (alice, bob, contract) = (vec![1; 32], vec![2; 32], vec![3; 32]);
+    engine.set_caller(alice.clone());
+    engine.set_callee(bob.clone());
+
+    // when
+    let outside = engine.exec_context_snapshot();
+    engine.enter_frame(contract.clone(), Vec::new());
+    engine.enter_frame(bob.clone(), Vec::new());
+    let inside = engine.exec_context_snapshot();
+    engine.exit_frame();
+    engine.exit_frame();
+
+    // then
+    assert_eq!(
+        (outside.caller, outside.callee, outside.depth),
+        (Some(alice.clone()), Some(bob.clone()), 0)
+    );
+    assert_eq!(
+        (inside.caller, inside.callee, inside.depth),
+        (Some(contract), Some(bob), 2)
+    );
+    assert_eq!(inside.origin, Some(Origin::Signed(alice.clone())));
+    assert_eq!(outside.origin, inside.origin);
+    assert_eq!(
+        engine.exec_context_snapshot().origin,
+        Some(Origin::Signed(alice))
+    );
+}
+
+#[test]
+fn runtime_calls_are_rejected_without_handler() {
+    use crate::runtime_call::RuntimeCallError;
+
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+
+    // when
+    let rejected = engine.call_runtime(&[0, 1]);
+    engine.register_runtime_call_handler(|call| {
+        match call {
+            [0, ..] => Ok(()),
+            _ => Err(RuntimeCallError::DispatchFailed),
+        }
+    });
+    let accepted = engine.call_runtime(&[0, 2]);
+    let failed = engine.call_runtime(&[1]);
+
+    // then
+    assert_eq!(rejected, Err(Error::CallRuntimeFailed));
+    assert_eq!(accepted, Ok(()));
+    assert_eq!(failed, Err(Error::CallRuntimeFailed));
+    let results = engine
+        .recorded_runtime_calls()
+        .iter()
+        .map(|call| call.result)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        results,
+        [
+            Err(RuntimeCallError::Filtered),
+            Ok(()),
+            Err(RuntimeCallError::DispatchFailed)
+        ]
+    );
+
+    // when
+    engine.initialize_or_reset();
+
+    // then
+    assert!(engine.recorded_runtime_calls().is_empty());
+}
+
+#[test]
+fn locked_contract_termination_is_denied() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    let beneficiary = vec![2; 32];
+    engine.set_callee(contract.clone());
+    engine.set_balance(contract.clone(), 100);
+    engine.set_balance(beneficiary.clone(), 10);
+    engine.lock_contract(&contract, "code in use");
+    engine.lock_contract(&contract, "deposit held");
+
+    // when
+    let denied = engine.terminate(&beneficiary);
+
+    // then
+    assert_eq!(denied, Err(Error::TerminationDenied));
+    assert_eq!(engine.get_balance(contract.clone()), Ok(100));
+    assert_eq!(engine.get_balance(beneficiary.clone()), Ok(10));
+
+    // when
+    let unlocked = engine.unlock_contract(&contract, "code in use");
+    let unlocked_twice = engine.unlock_contract(&contract, "code in use");
+
+    // then
+    assert!(unlocked);
+    assert!(!unlocked_twice);
+    assert_eq!(engine.contract_locks(&contract), ["deposit held"]);
+    assert_eq!(
+        engine.terminate(&beneficiary),
+        Err(Error::TerminationDenied)
+    );
+
+    // when
+    engine.unlock_contract(&contract, "deposit held");
+    let terminated = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let _ = engine.terminate(&beneficiary);
+    }))
+    .expect_err("unlocked contract did not terminate");
+
+    // then
+    assert_eq!(
+        terminated.downcast_ref::<Vec<u8>>(),
+        Some(&scale::Encode::encode(&(100u128, beneficiary.clone())))
+    );
+    assert_eq!(engine.get_balance(contract), Ok(0));
+    assert_eq!(engine.get_balance(beneficiary), Ok(110));
+}
+
+#[test]
+fn only_contracts_are_callable() {
+    fn deploy() {}
+    fn call() {}
+
+    // given
+    let mut engine = Engine::new();
+    let caller = vec![1; 32];
+    let user = vec![2; 32];
+    let contract = vec![3; 32];
+    let value = scale::Encode::encode(&0u128);
+    engine.set_callee(caller.clone());
+    engine.set_balance(user.clone(), 10);
+    engine.register_contract(contract.clone(), EntryPoints { deploy, call });
+    engine.set_balance(contract.clone(), 10);
+
+    // when
+    let user_call = engine.enter_call(&user, &value, Some(Vec::new()));
+    let contract_call = engine.enter_call(&contract, &value, Some(Vec::new()));
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let _ = engine.terminate(&caller);
+    }))
+    .expect_err("the contract did not terminate");
+    engine.exit_frame();
+    let tombstone_call = engine.enter_call(&contract, &value, Some(Vec::new()));
+
+    // then
+    assert_eq!(user_call.err(), Some(Error::NotCallable));
+    assert!(contract_call.is_ok());
+    assert_eq!(tombstone_call.err(), Some(Error::NotCallable));
+    assert_eq!(engine.account_kind(&user), AccountKind::User);
+    assert_eq!(engine.account_kind(&caller), AccountKind::User);
+    assert_eq!(engine.account_kind(&contract), AccountKind::Tombstone);
+    assert!(!engine.is_contract(contract.clone()));
+    assert_eq!(
+        engine.call(&user, 0, &value, &[], &mut &mut [0; 4][..]),
+        Err(Error::NotCallable)
+    );
+
+    // when
+    engine.set_contract(contract.clone());
+
+    // then
+    assert_eq!(engine.account_kind(&contract), AccountKind::Contract);
+}
+
+#[test]
+fn fee_is_withdrawn_and_unused_part_refunded() {
+    // given
+    let mut engine = Engine::new();
+    let origin = vec![1; 32];
+    let contract = vec![2; 32];
+    engine.config.gas_limit = 1_000;
+    engine.chain_spec.gas_price = 10;
+    engine.set_caller(origin.clone());
+    engine.set_balance(origin.clone(), 50_000);
+    engine.enable_fee_accounting(true);
+
+    // when
+    engine.enter_frame(contract.clone(), Vec::new());
+
+    // then
+    assert_eq!(engine.get_balance(origin.clone()), Ok(40_000));
+    assert_eq!(engine.fees_paid(&origin), 10_000);
+
+    // when
+    engine.consume_gas(100);
+    engine.enter_frame(vec![3; 32], Vec::new());
+    engine.consume_gas(150);
+    engine.exit_frame();
+
+    // then
+    assert_eq!(engine.get_balance(origin.clone()), Ok(40_000));
+
+    // when
+    engine.exit_frame();
+
+    // then
+    assert_eq!(engine.get_balance(origin.clone()), Ok(47_500));
+    assert_eq!(engine.fees_paid(&origin), 2_500);
+}
+
+#[test]
+fn fee_withdrawal_fails_for_insufficient_balance() {
+    // given
+    let mut engine = Engine::new();
+    let origin = vec![1; 32];
+    engine.config.gas_limit = 1_000;
+    engine.chain_spec.gas_price = 10;
+    engine.set_caller(origin.clone());
+    engine.set_balance(origin.clone(), 9_999);
+    engine.enable_fee_accounting(true);
+
+    // when
+    let result = engine.try_enter_frame(vec![2; 32], Vec::new());
+
+    // then
+    assert_eq!(result, Err(Error::FeeWithdrawalFailed));
+    assert_eq!(engine.get_balance(origin.clone()), Ok(9_999));
+    assert_eq!(engine.fees_paid(&origin), 0);
+    assert_eq!(engine.exec_context_snapshot().depth, 0);
+
+    // when
+    engine.enable_fee_accounting(false);
+    let result = engine.try_enter_frame(vec![2; 32], Vec::new());
+
+    // then
+    assert_eq!(result, Ok(()));
+    assert_eq!(engine.get_balance(origin), Ok(9_999));
+}
+
+#[test]
+fn watched_storage_key_records_changes_per_frame() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    engine.set_callee(contract.clone());
+    engine.watch_storage_key(&contract, &[7]);
+
+    // when
+    engine.set_storage(&[7], &[1]);
+    engine.enter_frame(contract.clone(), Vec::new());
+    engine.set_storage(&[7], &[2]);
+    engine.exit_frame();
+    engine.clear_storage(&[7]);
+
+    // then
+    let changes = engine
+        .storage_changes(&contract, &[7])
+        .into_iter()
+        .map(|change| (change.frame, change.old_value, change.new_value))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        [
+            (0, None, Some(vec![1])),
+            (1, Some(vec![1]), Some(vec![2])),
+            (0, Some(vec![2]), None),
+        ]
+    );
+    assert_eq!(engine.get_contract_storage_rw(contract), (0, 3));
+}
+
+#[test]
+fn storage_write_log_lists_the_written_keys_of_the_contract() {
+    // given
+    let (contract, other) = (vec![1; 32], vec![2; 32]);
+    let mut engine = Engine::new();
+    engine.set_callee(contract.clone());
+    engine.set_storage(&[1], &[11]);
+    let log = engine.start_storage_write_log(&contract);
+
+    // when
+    let _ = engine.get_storage(&[1]);
+    let read_only = engine.storage_write_log(log);
+    engine.set_storage(&[2], &[22]);
+    engine.clear_storage(&[1]);
+    engine.set_storage(&[2], &[23]);
+    engine.set_callee(other);
+    engine.set_storage(&[3], &[33]);
+
+    // then
+    assert!(read_only.is_empty());
+    assert_eq!(engine.end_storage_write_log(log), [vec![2], vec![1]]);
+    assert!(engine.storage_write_log(log).is_empty());
+}
+
+#[test]
+fn nonce_is_incremented_for_top_level_executions() {
+    // given
+    let mut engine = Engine::new();
+    let origin = vec![1; 32];
+    let contract = vec![2; 32];
+    engine.set_caller(origin.clone());
+    let nonce = |engine: &Engine, account_id: &[u8]| {
+        let mut output = [0; 8];
+        let written = engine
+            .account_nonce(account_id, &mut &mut output[..])
+            .expect("nonce must be readable");
+        <u64 as scale::Decode>::decode(&mut &output[..written])
+            .expect("nonce must be decodable")
+    };
+
+    // when
+    let mut addresses = Vec::new();
+    for _ in 0..3 {
+        engine.enter_frame(contract.clone(), Vec::new());
+        engine.enter_frame(vec![3; 32], Vec::new());
+        engine.exit_frame();
+        addresses.push(engine.derive_contract_address(&[0; 32], &[], &[]));
+        engine.exit_frame();
+    }
+
+    // then
+    assert_eq!(nonce(&engine, &origin), 3);
+    assert_eq!(nonce(&engine, &contract), 0);
+    assert_ne!(addresses[0], addresses[1]);
+    assert_ne!(addresses[1], addresses[2]);
+
+    // when
+    engine.increment_nonce(&contract);
+
+    // then
+    assert_eq!(nonce(&engine, &contract), 1);
+}
+
+#[test]
+fn get_storage_into_fails_for_too_small_buffer() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.misuse_policy = MisusePolicy::Error;
+    engine.set_callee(vec![1; 32]);
+    engine.set_storage(&[1], &[7; 12 * 1024]);
+    let mut output = [0; 9600];
+
+    // when
+    let result = engine.get_storage_into(&[1], &mut &mut output[..]);
+
+    // then
+    assert_eq!(result, Err(Error::BufferTooSmall(12 * 1024)));
+    assert_eq!(output, [0; 9600]);
+    assert_eq!(
+        engine.get_storage_into(&[2], &mut &mut output[..]),
+        Err(Error::KeyNotFound(vec![2]))
+    );
+}
+
+#[test]
+#[should_panic(
+    expected = "the output buffer of `get_storage` is too small: the value \
+    is of size 12288 bytes, but the output buffer has only room for 9600"
+)]
+fn get_storage_into_panics_for_too_small_buffer_under_panic_policy() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    engine.set_storage(&[1], &[7; 12 * 1024]);
+    let mut output = [0; 9600];
+
+    // when
+    let _ = engine.get_storage_into(&[1], &mut &mut output[..]);
+}
+
+#[test]
+fn block_advance_callbacks_run_in_registration_order() {
+    // given
+    let mut engine = Engine::new();
+    let account = vec![1; 32];
+    engine.set_balance(account.clone(), 100);
+    let drip_account = account.clone();
+    engine.on_block_advance(move |engine, _| {
+        let balance = engine.get_balance(drip_account.clone()).unwrap_or_default();
+        engine.set_balance(drip_account.clone(), balance + 10);
+    });
+    let observed = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let recorded = observed.clone();
+    let observed_account = account.clone();
+    engine.on_block_advance(move |engine, block_number| {
+        let balance = engine.get_balance(observed_account.clone()).unwrap();
+        recorded.borrow_mut().push((
+            block_number,
+            engine.exec_context.block_number(),
+            balance,
+        ));
+    });
+
+    // when
+    for _ in 0..5 {
+        engine.advance_block();
+    }
+
+    // then
+    assert_eq!(engine.get_balance(account), Ok(150));
+    assert_eq!(
+        *observed.borrow(),
+        [
+            (1, 1, 110),
+            (2, 2, 120),
+            (3, 3, 130),
+            (4, 4, 140),
+            (5, 5, 150)
+        ]
+    );
+}
+
+#[test]
+fn enter_call_resolves_entry_point_and_transfers_value() {
+    fn deploy() {}
+    fn call() {}
+
+    // given
+    let mut engine = Engine::new();
+    let caller = vec![1; 32];
+    let callee = vec![2; 32];
+    engine.set_callee(caller.clone());
+    engine.set_balance(caller.clone(), 100);
+    engine.register_contract(callee.clone(), EntryPoints { deploy, call });
+
+    // when
+    let unknown = engine.enter_call(&[3; 32], &scale::Encode::encode(&0u128), None);
+    let too_much =
+        engine.enter_call(&callee, &scale::Encode::encode(&101u128), Some(vec![1]));
+    let entered =
+        engine.enter_call(&callee, &scale::Encode::encode(&40u128), Some(vec![1]));
+
+    // then
+    assert_eq!(unknown, Err(Error::NotCallable));
+    assert_eq!(too_much, Err(Error::TransferFailed));
+    assert!(entered.is_ok());
+    assert_eq!(engine.get_callee(), callee);
+    assert_eq!(engine.exec_context.input(), [1]);
+    assert_eq!(engine.get_balance(caller), Ok(60));
+    assert_eq!(engine.get_balance(callee), Ok(40));
+}
+
+#[test]
+fn capabilities_name_missing_host_functions() {
+    use crate::Capabilities;
+
+    // given
+    let required = Capabilities::CALL | Capabilities::GAS_LEFT | Capabilities::STORAGE;
+
+    let capabilities = crate::capabilities().without(Capabilities::GAS_LEFT);
+
+    // when
+    let missing = capabilities.missing(required).collect::<Vec<_>>();
+
+    // then
+    assert!(capabilities.contains(Capabilities::STORAGE));
+    assert!(!capabilities.contains(required));
+    assert!(crate::capabilities().contains(required));
+    assert_eq!(missing, ["gas_left"]);
+    assert_eq!(
+        capabilities
+            .without(Capabilities::STORAGE)
+            .missing(required)
+            .collect::<Vec<_>>(),
+        ["storage", "gas_left"]
+    );
+}
+
+#[test]
+fn memory_usage_grows_and_shrinks_across_insert_clear_and_reset() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    let initial = engine.approximate_memory_usage();
+
+    // when
+    engine.set_storage(&[1], &[0; 100]);
+    let after_insert = engine.approximate_memory_usage();
+    engine.set_storage(&[1], &[0; 10]);
+    let after_overwrite = engine.approximate_memory_usage();
+    engine.clear_storage(&[1]);
+    let after_clear = engine.approximate_memory_usage();
+
+    // then
+    assert!(after_insert > initial + 100);
+    assert_eq!(after_insert - after_overwrite, 90);
+    assert_eq!(after_clear, initial);
+
+    // when
+    engine.set_balance(vec![2; 32], 1_000);
+    engine.set_storage(&[2], &[0; 100]);
+    assert!(engine.approximate_memory_usage() > initial);
+    engine.initialize_or_reset();
+
+    // then
+    assert_eq!(engine.approximate_memory_usage(), 0);
+}
+
+#[test]
+fn memory_soft_cap_records_warning_once_per_crossing() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    let cap = engine.approximate_memory_usage() + 250;
+    engine.config.memory_soft_cap = Some(cap);
+
+    // when
+    engine.set_storage(&[1], &[0; 100]);
+    engine.set_storage(&[2], &[0; 100]);
+    let first = engine.approximate_memory_usage();
+    engine.set_storage(&[3], &[0; 100]);
+    engine.clear_storage(&[3]);
+    engine.clear_storage(&[2]);
+    engine.set_storage(&[1], &[0; 10]);
+    engine.set_storage(&[2], &[0; 100]);
+    let second = engine.approximate_memory_usage();
+
+    // then
+    assert_eq!(
+        engine.get_recorded_warnings(),
+        [
+            Warning::MemorySoftCapExceeded { usage: first, cap },
+            Warning::MemorySoftCapExceeded { usage: second, cap },
+        ]
+    );
+}
+
+#[test]
+fn memory_hard_cap_rejects_write() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    engine.config.memory_hard_cap = Some(engine.approximate_memory_usage() + 250);
+    engine.set_storage(&[1], &[0; 100]);
+    let usage = engine.approximate_memory_usage();
+
+    // when
+    let rejected = engine.try_set_storage(&[2], &[0; 100]);
+    let shrunk = engine.try_set_storage(&[1], &[0; 10]);
+
+    // then
+    assert_eq!(rejected, Err(Error::MemoryLimitExceeded));
+    assert_eq!(shrunk, Ok(Some(100)));
+    assert_eq!(engine.approximate_memory_usage(), usage - 90);
+    assert_eq!(engine.get_storage(&[2]), Err(Error::KeyNotFound(vec![2])));
+}
+
+#[test]
+fn weight_to_fee_curve_is_replayed_by_value() {
+    // given
+    let mut engine = Engine::new();
+    let fee = |engine: &mut Engine, gas| {
+        let mut output = get_buffer();
+        engine.weight_to_fee(gas, &mut &mut output[..]);
+        <u128 as scale::Decode>::decode(&mut &output[..]).expect("decoding fee failed")
+    };
+    engine.start_recording_scenario();
+    let linear = fee(&mut engine, 3);
+    engine.set_weight_to_fee_fn(|gas| u128::from(gas).pow(2));
+    let quadratic = fee(&mut engine, 3);
+
+    // when
+    let scenario = engine.stop_recording_scenario();
+    let replayed = Engine::replay(&scenario);
+
+    // then
+    assert_eq!((linear, quadratic), (300, 9));
+    assert!(replayed.is_ok());
+    assert_eq!(
+        scenario.operations,
+        [
+            Operation::WeightToFee { gas: 3, fee: 300 },
+            Operation::WeightToFee { gas: 3, fee: 9 },
+        ]
+    );
+}
+
+#[test]
+fn debug_dump_lists_context_messages_and_state() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_caller(vec![1; 2]);
+    engine.set_block_number(7);
+    engine.set_balance(vec![1; 2], 10);
+    engine.debug_message("checkpoint");
+
+    // when
+    let dump = engine.debug_dump();
+
+    // then
+    assert_eq!(
+        dump,
+        "ink_engine state:\n  \
+        caller: 0x0101\n  \
+        callee: <unset>\n  \
+        block: number 7, timestamp 0\n  \
+        call depth: 0\n  \
+        events emitted: 0\n  \
+        debug message: checkpoint\n  \
+        state: {\"version\":2,\"balances\":{\"0x0101\":\"10\"},\"storage\":{},\
+        \"weights\":{\"storage_write_base\":250000000,\"storage_write_per_byte\":300000,\
+        \"storage_read_base\":150000000,\"storage_read_per_byte\":50000,\
+        \"event_base\":100000000,\"event_per_topic\":120000000,\"event_per_byte\":10000,\
+        \"hash_base\":10000000,\"hash_per_byte\":3000,\"call_base\":400000000,\
+        \"transfer_base\":200000000,\"gas_left_base\":5000000}}\n"
+    );
+}
+
+#[test]
+fn event_limits_reject_oversized_events() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.max_event_data_len = 4;
+    engine.config.max_topic_count = 1;
+    // one and two topics of one byte each, encoded as `Vec<Vec<u8>>`
+    let (one_topic, two_topics) = ([0x04, 0x04, 0x01], [0x08, 0x04, 0x01, 0x04, 0x02]);
+
+    // when
+    let at_limit = engine.try_deposit_event(&one_topic, &[0; 4]);
+    let data_over = engine.try_deposit_event(&one_topic, &[0; 5]);
+    let topics_over = engine.try_deposit_event(&two_topics, &[0; 4]);
+
+    // then
+    assert_eq!(at_limit, Ok(()));
+    assert_eq!(
+        data_over,
+        Err(Error::EventLimitExceeded(EventLimitViolation {
+            limit: EventLimit::DataLen,
+            actual: 5,
+            allowed: 4,
+        }))
+    );
+    assert_eq!(
+        topics_over,
+        Err(Error::EventLimitExceeded(EventLimitViolation {
+            limit: EventLimit::TopicCount,
+            actual: 2,
+            allowed: 1,
+        }))
+    );
+    assert_eq!(engine.get_emitted_events().count(), 1);
+    assert_eq!(
+        EventLimitViolation {
+            limit: EventLimit::DataLen,
+            actual: 5,
+            allowed: 4,
+        }
+        .to_string(),
+        "event data of 5 bytes exceeds the maximum of 4 bytes"
+    );
+}
+
+#[test]
+fn reverted_frame_discards_storage_writes_on_frame_success() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.commit_mode = CommitMode::OnFrameSuccess;
+    let contract = vec![1; 32];
+    engine.set_callee(contract.clone());
+    engine.set_storage(&[1], &[10]);
+    engine.enter_frame(contract.clone(), Vec::new());
+
+    // when
+    engine.enter_frame(contract.clone(), Vec::new());
+    engine.set_storage(&[1], &[20]);
+    let inner = engine.get_storage(&[1]).map(<[u8]>::to_vec);
+    engine.revert_frame(Vec::new());
+    let after_revert = engine.get_storage(&[1]).map(<[u8]>::to_vec);
+    engine.enter_frame(contract.clone(), Vec::new());
+    engine.set_storage(&[2], &[30]);
+    engine.exit_frame();
+    let in_database_before_outer_exit = engine
+        .database
+        .get_from_contract_storage(&contract, &[2])
+        .cloned();
+    engine.exit_frame();
+
+    // then
+    assert_eq!(inner, Ok(vec![20]));
+    assert_eq!(after_revert, Ok(vec![10]));
+    assert_eq!(in_database_before_outer_exit, None);
+    assert_eq!(
+        engine.storage_dump(&contract),
+        [(vec![1], vec![10]), (vec![2], vec![30])]
+    );
+}
+
+#[test]
+fn reverted_frame_keeps_storage_writes_when_immediate() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    engine.set_callee(contract.clone());
+    engine.set_storage(&[1], &[10]);
+
+    // when
+    engine.enter_frame(contract.clone(), Vec::new());
+    engine.set_storage(&[1], &[20]);
+    engine.revert_frame(Vec::new());
+
+    // then
+    assert_eq!(engine.get_storage(&[1]), Ok(&[20][..]));
+}
+
+#[test]
+fn caller_frame_makes_caller_differ_from_origin() {
+    // given
+    let mut engine = Engine::new();
+    let (origin, caller, callee) = (vec![1; 32], vec![2; 32], vec![3; 32]);
+    engine.set_caller(origin.clone());
+    engine.set_callee(callee.clone());
+
+    // when
+    engine.push_caller_frame(caller.clone());
+    let caller_of = |engine: &Engine| engine.exec_context_snapshot().caller;
+    let in_caller_frame = (caller_of(&engine), engine.caller_is_origin());
+    engine.enter_frame(vec![4; 32], Vec::new());
+    let nested = (caller_of(&engine), engine.caller_is_origin());
+    engine.exit_frame();
+    engine.pop_caller_frame();
+
+    // then
+    assert_eq!(in_caller_frame, (Some(caller), false));
+    assert_eq!(nested, (Some(callee), false));
+    assert_eq!(caller_of(&engine), Some(origin));
+    assert!(engine.caller_is_origin());
+}
+
+#[test]
+#[should_panic(expected = "no caller frame has been pushed")]
+fn pop_caller_frame_rejects_entered_frame() {
+    let mut engine = Engine::new();
+    engine.enter_frame(vec![1; 32], Vec::new());
+    engine.pop_caller_frame();
+}
+
+#[test]
+fn activity_logs_round_trip_and_diff() {
+    // given
+    let record = |value: u8| {
+        let mut engine = Engine::new();
+        engine.start_recording_scenario();
+        engine.set_callee(vec![1; 32]);
+        engine.set_storage(&[1], &[value]);
+        engine.deposit_event(&[0x04, 0x04, 0x01], &[0x05]);
+        engine.export_activity()
+    };
+    let (ours, theirs) = (record(10), record(11));
+
+    // when
+    let ours = ActivityLog::decode(&ours).expect("decoding activity log failed");
+    let theirs = ActivityLog::decode(&theirs).expect("decoding activity log failed");
+    let diff = ours.diff(&theirs).expect("logs do not differ");
+
+    // then
+    assert_eq!(ours.operations.len(), 3);
+    assert_eq!(ours.diff(&ours), None);
+    assert_eq!(diff.index, 1);
+    assert_eq!(
+        diff.description,
+        "operation 1 differs: SetStorage { key: [1], value: [10], previous_size: None } \
+        != SetStorage { key: [1], value: [11], previous_size: None }"
+    );
+    let mut truncated = ours.clone();
+    truncated.operations.pop();
+    assert_eq!(truncated.diff(&ours).map(|diff| diff.index), Some(2));
+}
+
+#[test]
+fn activity_log_rejects_unsupported_version() {
+    let mut encoded = ActivityLog::default().encode();
+    assert_eq!(encoded[0], ACTIVITY_LOG_VERSION);
+    encoded[0] = ACTIVITY_LOG_VERSION + 1;
+
+    assert_eq!(
+        ActivityLog::decode(&encoded),
+        Err(ActivityLogError::UnsupportedVersion(
+            ACTIVITY_LOG_VERSION + 1
+        ))
+    );
+    assert_eq!(
+        ActivityLog::decode(&[]),
+        Err(ActivityLogError::MissingVersion)
+    );
+}
+
+#[test]
+fn account_ids_and_hashes_round_trip_through_hex() {
+    // given
+    let account_id = AccountId::from_seed(0xab);
+    let hash = Hash::from_seed(0x01);
+
+    // when
+    let account_hex = account_id.to_hex();
+    let hash_hex = hash.to_hex();
+
+    // then
+    assert_eq!(account_hex, format!("0x{}", "ab".repeat(32)));
+    assert_eq!(AccountId::from_hex(&account_hex), Ok(account_id.clone()));
+    assert_eq!(
+        AccountId::from_hex(&account_hex.to_uppercase()[2..]),
+        Ok(account_id)
+    );
+    assert_eq!(Hash::from_hex(&hash_hex), Ok(hash));
+    assert_eq!(hash.as_bytes(), [0x01; 32]);
+}
+
+#[test]
+fn seeded_account_ids_are_deterministic() {
+    assert_eq!(AccountId::from_seed(1), AccountId::from_seed(1));
+    assert_eq!(AccountId::from_seed(1).as_bytes(), [1; 32]);
+    assert_ne!(AccountId::from_seed(1), AccountId::from_seed(2));
+    assert_eq!(Hash::from_seed(7), Hash::from([7; 32]));
+}
+
+#[test]
+fn hex_parsing_rejects_malformed_input() {
+    assert_eq!(
+        AccountId::from_hex("0x0101"),
+        Err(HexError::InvalidLength {
+            expected: 32,
+            actual: 2,
+        })
+    );
+    assert_eq!(AccountId::from_hex("0x0"), Err(HexError::OddLength));
+    assert_eq!(Hash::from_hex("0xzz"), Err(HexError::InvalidCharacter('z')));
+
+    let mut engine = Engine::new();
+    engine.chain_spec.account_length = 20;
+    let twenty_bytes = format!("0x{}", "01".repeat(20));
+    assert_eq!(
+        engine.account_id_from_hex(&twenty_bytes),
+        Ok(AccountId::from_bytes(&[1; 20]))
+    );
+    assert_eq!(
+        engine.account_id_from_hex(&AccountId::from_seed(1).to_hex()),
+        Err(HexError::InvalidLength {
+            expected: 20,
+            actual: 32,
+        })
+    );
+}
+
+#[test]
+fn cloned_engine_is_independent_of_original() {
+    // given
+    let mut engine = Engine::new();
+    let enc_topics_count = scale::Encode::encode(&scale::Compact(0u32));
+    engine.set_callee(vec![1; 32]);
+    engine.set_storage(&[1], &[11]);
+    engine.set_clock(|block_number| u64::from(block_number) * 10);
+
+    // when
+    let mut fork = engine.clone();
+    fork.set_storage(&[1], &[22]);
+    fork.set_storage(&[2], &[33]);
+    fork.deposit_event(&enc_topics_count, &[1]);
+    fork.advance_block();
+
+    // then
+    assert_eq!(engine.get_storage(&[1]), Ok(&[11][..]));
+    assert_eq!(engine.get_storage(&[2]), Err(Error::KeyNotFound(vec![2])));
+    assert_eq!(engine.get_emitted_events().count(), 0);
+    assert_eq!(fork.get_storage(&[1]), Ok(&[22][..]));
+    assert_eq!(fork.get_emitted_events().count(), 1);
+    assert_eq!(fork.exec_context_snapshot().block_timestamp, 10);
+    assert_eq!(engine.exec_context_snapshot().block_number, 0);
+}
+
+#[test]
+fn test_chain_contract_calls_other_contract() {
+    const INCREMENT: [u8; 4] = [0, 0, 0, 1];
+    const FORWARD: [u8; 4] = [0, 0, 0, 2];
+
+    fn counter(
+        chain: &mut TestChain,
+        selector: [u8; 4],
+        args: &[u8],
+    ) -> Result<Vec<u8>, Vec<u8>> {
+        if selector != INCREMENT {
+            return Err(b"unknown selector".to_vec())
+        }
+        let by = <u32 as scale::Decode>::decode(&mut &args[..])
+            .map_err(|_| b"invalid arguments".to_vec())?;
+        let engine = chain.engine_mut();
+        let count = engine
+            .get_storage(&[0])
+            .map(|mut value| <u32 as scale::Decode>::decode(&mut value).unwrap())
+            .unwrap_or_default()
+            + by;
+        engine.set_storage(&[0], &scale::Encode::encode(&count));
+        engine.deposit_event(&scale::Encode::encode(&scale::Compact(0u32)), &[1]);
+        Ok(scale::Encode::encode(&count))
+    }
+
+    fn proxy(
+        chain: &mut TestChain,
+        selector: [u8; 4],
+        args: &[u8],
+    ) -> Result<Vec<u8>, Vec<u8>> {
+        if selector != FORWARD {
+            return Err(b"unknown selector".to_vec())
+        }
+        let target = AccountId::from_bytes(&args[..32]);
+        let proxy = chain.address();
+        chain
+            .call(&proxy, &target, INCREMENT, &args[32..])
+            .map_err(|error| scale::Encode::encode(&error.code()))
+    }
+
+    // given
+    let mut chain = TestChain::new();
+    let alice = AccountId::from_seed(1);
+    let counter = chain.deploy(counter, b"counter", 100);
+    let proxy = chain.deploy(proxy, b"proxy", 50);
+    let args = [counter.as_bytes(), &scale::Encode::encode(&5u32)[..]].concat();
+
+    // when
+    let direct = chain.call(&alice, &counter, INCREMENT, &scale::Encode::encode(&2u32));
+    chain.advance_block();
+    let forwarded = chain.call(&alice, &proxy, FORWARD, &args);
+    let unknown = chain.call(&alice, &counter, FORWARD, &[]);
+
+    // then
+    assert_eq!(direct, Ok(scale::Encode::encode(&2u32)));
+    assert_eq!(forwarded, Ok(scale::Encode::encode(&7u32)));
+    assert_eq!(
+        unknown,
+        Err(Error::CalleeReverted(b"unknown selector".to_vec()))
+    );
+    assert_eq!(
+        chain.call(&alice, &alice, INCREMENT, &[]),
+        Err(Error::NotCallable)
+    );
+    assert_eq!(chain.events().len(), 2);
+    assert_eq!(chain.balance(&counter), 100);
+    assert_eq!(chain.balance(&proxy), 50);
+    assert_eq!(chain.engine().exec_context_snapshot().block_number, 1);
+    let outcome = chain.engine().last_call_outcome().expect("calls were made");
+    assert_eq!(outcome.data, b"unknown selector".to_vec());
+}
+
+/// Runs the host functions of a typical message against `engine`.
+fn run_weighted_message(engine: &mut Engine) {
+    engine.set_callee(vec![1; 32]);
+    engine.set_balance(vec![1; 32], 1_000);
+    engine.set_storage(&[1], &[0; 10]);
+    engine.get_storage(&[1]).expect("the value was just stored");
+    engine.deposit_event(&[4, 8, 9, 9], &[0; 20]);
+    engine
+        .transfer(&[2; 32], &scale::Encode::encode(&10u128))
+        .expect("transfer failed");
+    engine.charge_hashing(32);
+}
+
+#[test]
+fn weight_tables_scale_gas_consumed() {
+    // given
+    let table = WeightTable {
+        storage_write_base: 100,
+        storage_write_per_byte: 1,
+        storage_read_base: 50,
+        storage_read_per_byte: 2,
+        event_base: 30,
+        event_per_topic: 10,
+        event_per_byte: 3,
+        hash_base: 5,
+        hash_per_byte: 1,
+        call_base: 0,
+        transfer_base: 70,
+        gas_left_base: 0,
+    };
+    let doubled = WeightTable {
+        storage_write_base: 200,
+        storage_write_per_byte: 2,
+        storage_read_base: 100,
+        storage_read_per_byte: 4,
+        event_base: 60,
+        event_per_topic: 20,
+        event_per_byte: 6,
+        hash_base: 10,
+        hash_per_byte: 2,
+        call_base: 0,
+        transfer_base: 140,
+        gas_left_base: 0,
+    };
+    let mut engine = Engine::new();
+    engine.config.weights = table;
+    let mut doubled_engine = Engine::new();
+    doubled_engine.config.weights = doubled;
+
+    // when
+    run_weighted_message(&mut engine);
+    run_weighted_message(&mut doubled_engine);
+
+    // then
+    assert_eq!(engine.gas_consumed(), 110 + 70 + 100 + 70 + 37);
+    assert_eq!(doubled_engine.gas_consumed(), 2 * engine.gas_consumed());
+}
+
+#[test]
+fn weight_table_change_affects_subsequent_operations_only() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.weights = WeightTable {
+        storage_write_base: 100,
+        ..WeightTable::zero()
+    };
+    engine.set_callee(vec![1; 32]);
+    engine.set_storage(&[1], &[1]);
+
+    // when
+    engine.config.weights = WeightTable {
+        storage_write_base: 1_000,
+        ..WeightTable::zero()
+    };
+    engine.set_storage(&[2], &[2]);
+
+    // then
+    assert_eq!(engine.gas_consumed(), 1_100);
+}
+
+#[test]
+fn hashing_charges_gas_proportional_to_input_length() {
+    // given
+    let mut engine = Engine::new();
+    let kib = vec![7; 1024];
+    let mib = vec![7; 1024 * 1024];
+    let mut output = [0; 32];
+
+    // when
+    engine
+        .try_hash(&kib, &mut output, Engine::hash_blake2_256)
+        .unwrap();
+    let kib_gas = engine.gas_consumed();
+    engine
+        .try_hash(&mib, &mut output, Engine::hash_blake2_256)
+        .unwrap();
+    let mib_gas = engine.gas_consumed() - kib_gas;
+
+    // then
+    let weights = WeightTable::default();
+    assert_eq!(kib_gas, weights.hash_base + weights.hash_per_byte * 1024);
+    assert_eq!(
+        mib_gas,
+        weights.hash_base + weights.hash_per_byte * 1024 * 1024
+    );
+    assert_eq!(
+        mib_gas - weights.hash_base,
+        1024 * (kib_gas - weights.hash_base)
+    );
+    let mut expected = [0; 32];
+    Engine::hash_blake2_256(&mib, &mut expected);
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn metered_hashing_fails_if_out_of_gas() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.gas_metering = true;
+    engine.config.gas_limit = WeightTable::default().hash_base;
+    let mut output = [0; 32];
+
+    // when
+    let empty = engine.try_hash(&[], &mut output, Engine::hash_sha2_256);
+    let kib = engine.try_hash(&[7; 1024], &mut output, Engine::hash_sha2_256);
+
+    // then
+    assert_eq!(empty, Ok(()));
+    assert_eq!(kib, Err(Error::OutOfGas));
+    assert_eq!(engine.gas_remaining(), 0);
+    let mut expected = [0; 32];
+    Engine::hash_sha2_256(&[], &mut expected);
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn transfers_and_termination_conserve_total_issuance() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let contract = vec![2; 32];
+    let bob = vec![3; 32];
+    engine.set_balance(alice.clone(), 1_000);
+    engine.set_balance(contract.clone(), 500);
+    engine.set_contract(contract.clone());
+    engine.set_callee(contract.clone());
+    engine.set_storage(&[1], &[1, 2]);
+    let issuance = engine.total_issuance();
+
+    // when
+    engine
+        .transfer(&bob, &scale::Encode::encode(&200u128))
+        .expect("transfer failed");
+    engine
+        .transfer(&alice, &scale::Encode::encode(&100u128))
+        .expect("transfer failed");
+    let accounts: Vec<AccountInfo> = engine.accounts().collect();
+    let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let _ = engine.terminate(&bob);
+    }))
+    .expect_err("contract did not terminate");
+
+    // then
+    assert_eq!(issuance, Some(1_500));
+    assert_eq!(engine.total_issuance(), issuance);
+    assert_eq!(
+        accounts,
+        [
+            AccountInfo {
+                id: alice,
+                balance: 1_100,
+                is_contract: false,
+                nonce: 0,
+                storage_cells: 0,
+            },
+            AccountInfo {
+                id: contract,
+                balance: 200,
+                is_contract: true,
+                nonce: 0,
+                storage_cells: 1,
+            },
+            AccountInfo {
+                id: bob.clone(),
+                balance: 200,
+                is_contract: false,
+                nonce: 0,
+                storage_cells: 0,
+            },
+        ]
+    );
+    assert_eq!(engine.get_balance(bob), Ok(400));
+}
+
+#[test]
+fn total_issuance_detects_overflow() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_balance(vec![1; 32], u128::MAX);
+    engine.set_balance(vec![2; 32], 1);
+
+    // when
+    let issuance = engine.total_issuance();
+
+    // then
+    assert_eq!(issuance, None);
+}
+
+#[test]
+fn transfers_are_recorded_with_their_frame() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    let known = vec![2; 32];
+    let unknown = vec![3; 32];
+    engine.set_callee(contract.clone());
+    engine.set_balance(contract.clone(), 100);
+    engine.set_balance(known.clone(), 0);
+
+    // when
+    engine
+        .transfer(&known, &scale::Encode::encode(&10u128))
+        .expect("transfer failed");
+    engine.enter_frame(contract.clone(), Vec::new());
+    engine
+        .transfer(&unknown, &scale::Encode::encode(&20u128))
+        .expect("transfer failed");
+    engine.exit_frame();
+
+    // then
+    assert_eq!(
+        engine.recorded_transfers(),
+        [
+            TransferRecord {
+                from: contract.clone(),
+                to: known,
+                value: 10,
+                frame: 0,
+            },
+            TransferRecord {
+                from: contract,
+                to: unknown.clone(),
+                value: 20,
+                frame: 1,
+            },
+        ]
+    );
+    assert!(engine.rejected_transfers().is_empty());
+    assert_eq!(engine.get_balance(unknown), Ok(20));
+}
+
+#[test]
+fn transfers_to_unknown_accounts_fail_without_implicit_account_creation() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    let known = vec![2; 32];
+    let unknown = vec![3; 32];
+    engine.config.allow_implicit_account_creation = false;
+    engine.set_callee(contract.clone());
+    engine.set_balance(contract.clone(), 100);
+    engine.set_balance(known.clone(), 0);
+
+    // when
+    let rejected = engine.transfer(&unknown, &scale::Encode::encode(&20u128));
+    let accepted = engine.transfer(&known, &scale::Encode::encode(&10u128));
+
+    // then
+    assert_eq!(rejected, Err(Error::TransferFailed));
+    assert_eq!(accepted, Ok(()));
+    assert_eq!(
+        engine.rejected_transfers(),
+        [TransferRecord {
+            from: contract.clone(),
+            to: unknown.clone(),
+            value: 20,
+            frame: 0,
+        }]
+    );
+    assert_eq!(engine.recorded_transfers().len(), 1);
+    assert_eq!(engine.get_balance(contract), Ok(90));
+    assert!(engine.get_balance(unknown).is_err());
+}
+
+#[test]
+fn replayer_pauses_at_storage_write_breakpoint() {
+    // given
+    let contract = vec![1; 32];
+    let mut recorded = Engine::new();
+    recorded.start_recording_scenario();
+    recorded.set_callee(contract.clone());
+    recorded.set_storage(&[1], &[10]);
+    recorded.set_storage(&[2], &[20]);
+    recorded.debug_message("written");
+    recorded.set_storage(&[1], &[11]);
+    let scenario = recorded.stop_recording_scenario();
+    assert_eq!(scenario.operations.len(), 5);
+    let mut replayer = ScenarioReplayer::new(scenario);
+    replayer.break_on_storage_write(&[2]);
+    replayer.break_on(|operation| matches!(operation, Operation::DebugMessage(_)));
+
+    // when
+    let paused = replayer.resume();
+
+    // then
+    assert_eq!(paused, Ok(Some(2)));
+    assert_eq!(replayer.position(), 2);
+    assert_eq!(
+        replayer.engine().get_storage_raw(&contract, &[1]),
+        Some(vec![10])
+    );
+    assert_eq!(replayer.engine().get_storage_raw(&contract, &[2]), None);
+
+    // when
+    let stepped = replayer.step();
+
+    // then
+    assert_eq!(stepped, Ok(true));
+    assert_eq!(
+        replayer.engine().get_storage_raw(&contract, &[2]),
+        Some(vec![20])
+    );
+    assert_eq!(replayer.resume(), Ok(Some(3)));
+    assert_eq!(replayer.resume(), Ok(None));
+    assert_eq!(replayer.step(), Ok(false));
+    let replayed = replayer.into_engine();
+    assert_eq!(replayed.export_state_json(), recorded.export_state_json());
+}
+
+#[test]
+fn chain_extension_statuses_and_traps_are_recorded() {
+    /// Returns success for method `1`, the domain error `1000` for method `2` and
+    /// traps for method `3`.
+    struct PriceOracle;
+    impl ChainExtension for PriceOracle {
+        fn ext_id(&self) -> u16 {
+            0
+        }
+
+        fn call(&mut self, _func_id: u16, _input: &[u8], _output: &mut Vec<u8>) -> u32 {
+            unreachable!("the oracle is called via `try_call_with_engine`")
+        }
+
+        fn try_call_with_engine(
+            &mut self,
+            _engine: &mut Engine,
+            func_id: u16,
+            _input: &[u8],
+            output: &mut Vec<u8>,
+        ) -> Result<u32, ChainExtensionTrapped> {
+            match func_id {
+                1 => {
+                    output.push(42);
+                    Ok(0)
+                }
+                2 => Ok(1000),
+                _ => Err(ChainExtensionTrapped),
+            }
+        }
+    }
+
+    // given
+    let mut engine = Engine::new();
+    engine
+        .chain_extension_handler
+        .register(Box::new(PriceOracle));
+    let mut output = get_buffer();
+
+    // when
+    let success = engine.try_call_chain_extension(1, &[], &mut &mut output[..]);
+    let domain_error = engine.try_call_chain_extension(2, &[], &mut &mut output[..]);
+    let trap = engine.try_call_chain_extension(3, &[], &mut &mut output[..]);
+
+    // then
+    assert_eq!((success, domain_error), (Ok(6), Ok(5)));
+    assert_eq!(trap, Err(Error::ChainExtensionTrapped));
+    let handler = &engine.chain_extension_handler;
+    assert_eq!(handler.last_status(1), Some(0));
+    assert_eq!(handler.last_status(2), Some(1000));
+    assert_eq!(handler.last_status(3), None);
+    assert_eq!(
+        handler
+            .calls()
+            .iter()
+            .map(|call| call.outcome.clone())
+            .collect::<Vec<_>>(),
+        [
+            ChainExtensionOutcome::Returned {
+                status: 0,
+                output: vec![42],
+            },
+            ChainExtensionOutcome::Returned {
+                status: 1000,
+                output: Vec::new(),
+            },
+            ChainExtensionOutcome::Trapped,
+        ]
+    );
+}
+
+#[test]
+fn denied_debug_messages_are_dropped_with_warning() {
+    // given
+    let mut engine = Engine::new();
+    engine.debug_message("allowed");
+
+    // when
+    engine.config.println_enabled = false;
+    engine.debug_message("denied");
+
+    // then
+    assert_eq!(
+        engine
+            .get_emitted_debug_messages()
+            .into_iter()
+            .collect::<Vec<_>>(),
+        ["allowed"]
+    );
+    assert_eq!(
+        engine.get_recorded_warnings(),
+        [Warning::PrintlnDenied {
+            message: String::from("denied"),
+        }]
+    );
+}
+
+#[test]
+fn emitted_events_are_read_without_cloning() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    let no_topics = scale::Encode::encode(&Vec::<Vec<u8>>::new());
+    engine.deposit_event(&no_topics, &[1]);
+    engine.deposit_event(&no_topics, &[2, 2]);
+
+    // when
+    let count = engine.emitted_event_count();
+    let borrowed: Vec<&[u8]> = engine
+        .emitted_events()
+        .map(|event| &event.data[..])
+        .collect();
+    let mut visited = Vec::new();
+    engine.for_each_emitted_event(|event| visited.push(event.data.clone()));
+
+    // then
+    assert_eq!(count, engine.get_emitted_events().count());
+    assert_eq!(count, 2);
+    assert_eq!(borrowed, [&[1][..], &[2, 2][..]]);
+    assert_eq!(visited, [vec![1], vec![2, 2]]);
+}
+
+#[test]
+fn events_are_counted_per_emitting_contract() {
+    const EMIT: [u8; 4] = [0, 0, 0, 1];
+
+    fn topics(topics: &[[u8; 32]]) -> Vec<u8> {
+        let topics: Vec<&[u8]> = topics.iter().map(<[u8; 32]>::as_slice).collect();
+        scale::Encode::encode(&topics)
+    }
+
+    fn small(
+        chain: &mut TestChain,
+        _selector: [u8; 4],
+        _args: &[u8],
+    ) -> Result<Vec<u8>, Vec<u8>> {
+        chain
+            .engine_mut()
+            .deposit_event(&topics(&[[1; 32]]), &[1, 2]);
+        Ok(Vec::new())
+    }
+
+    fn large(
+        chain: &mut TestChain,
+        _selector: [u8; 4],
+        args: &[u8],
+    ) -> Result<Vec<u8>, Vec<u8>> {
+        chain
+            .engine_mut()
+            .deposit_event(&topics(&[[2; 32], [3; 32]]), &[0; 10]);
+        let target = AccountId::from_bytes(args);
+        let large = chain.address();
+        chain
+            .call(&large, &target, EMIT, &[])
+            .map_err(|error| scale::Encode::encode(&error.code()))?;
+        chain.engine_mut().deposit_event(&topics(&[]), &[0; 4]);
+        Ok(Vec::new())
+    }
+
+    // given
+    let mut chain = TestChain::new();
+    let alice = AccountId::from_seed(1);
+    let small = chain.deploy(small, b"small", 0);
+    let large = chain.deploy(large, b"large", 0);
+
+    // when
+    chain.call(&alice, &small, EMIT, &[]).unwrap();
+    chain.call(&alice, &large, EMIT, small.as_bytes()).unwrap();
+
+    // then
+    let engine = chain.engine();
+    assert_eq!(engine.events_emitted(small.as_bytes()), 2);
+    assert_eq!(engine.event_bytes_emitted(small.as_bytes()), 2 * (32 + 2));
+    assert_eq!(engine.events_emitted(large.as_bytes()), 2);
+    assert_eq!(engine.event_bytes_emitted(large.as_bytes()), (64 + 10) + 4);
+    assert_eq!(engine.events_emitted(alice.as_bytes()), 0);
+    assert_eq!(
+        engine.contract_statistics(large.as_bytes()),
+        ContractStatistics {
+            storage_reads: 0,
+            storage_writes: 0,
+            events_emitted: 2,
+            event_bytes_emitted: 78,
+            cache_hits: 0,
+            cache_misses: 0,
+        }
+    );
+}
+
+#[test]
+fn event_topics_pad_short_and_hash_long_values() {
+    // given
+    let mut signature_topic = [0; 32];
+    Engine::hash_blake2_256(b"Transferred(u8,[u8;40])", &mut signature_topic);
+    let mut memo_topic = [0; 32];
+    Engine::hash_blake2_256(&[7; 40], &mut memo_topic);
+    let mut amount_topic = vec![0; 32];
+    amount_topic[0] = 13;
+
+    // when
+    let topics = event_topics(Some("Transferred(u8,[u8;40])"), &[vec![13], vec![7; 40]]);
+    let anonymous = event_topics(None, &[vec![13]]);
+
+    // then
+    assert_eq!(
+        topics,
+        vec![
+            signature_topic.to_vec(),
+            amount_topic.clone(),
+            memo_topic.to_vec()
+        ]
+    );
+    assert_eq!(anonymous, vec![amount_topic]);
+}
+
+#[test]
+fn swapping_the_recorder_keeps_earlier_statistics_with_the_old_one() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let key: &[u8; 32] = &[0x42; 32];
+    let no_topics = scale::Encode::encode(&scale::Compact(0u32));
+    engine.set_callee(alice.clone());
+    engine.set_storage(key, &[1]);
+    let _ = engine.get_storage(key);
+    engine.deposit_event(&no_topics, &[1]);
+    engine.debug_message("before");
+
+    // when
+    let old = engine.set_recorder(Box::new(InMemoryRecorder::new()));
+    engine.set_storage(key, &[2]);
+    engine.set_storage(key, &[3]);
+    engine.deposit_event(&no_topics, &[2, 2]);
+    engine.debug_message("after");
+
+    // then
+    let old = old.in_memory().expect("the default recorder is in memory");
+    assert_eq!((old.reads(&alice), old.writes(&alice)), (1, 1));
+    assert_eq!(old.events().len(), 1);
+    assert_eq!(old.events()[0].data, vec![1]);
+    assert_eq!(
+        old.debug_messages().clone().into_iter().collect::<Vec<_>>(),
+        vec!["before"]
+    );
+    let (reads, writes) = engine.get_contract_storage_rw(alice.clone());
+    assert_eq!((reads, writes), (0, 2));
+    assert_eq!(engine.events_emitted(&alice), 1);
+    let events: Vec<_> = engine
+        .get_emitted_events()
+        .map(|event| event.data)
+        .collect();
+    assert_eq!(events, vec![vec![2, 2]]);
+    let messages: Vec<String> = engine.get_emitted_debug_messages().into_iter().collect();
+    assert_eq!(messages, vec!["after"]);
+}
+
+#[cfg(feature = "csv-recorder")]
+#[test]
+fn csv_recorder_writes_a_row_per_statistic() {
+    // given
+    let mut engine = Engine::new();
+    let recorder = crate::recorder::CsvRecorder::new(Vec::new());
+    let rows = recorder.writer();
+    engine.set_recorder(Box::new(recorder));
+    engine.set_callee(vec![1; 32]);
+
+    // when
+    engine.set_storage(&[0x42; 32], &[1]);
+    let _ = engine.get_storage(&[0x42; 32]);
+    engine.deposit_event(&scale::Encode::encode(&scale::Compact(0u32)), &[1, 2, 3]);
+    engine.debug_message("say \"hi\"");
+    engine.clear_storage(&[0x43; 32]);
+    engine.set_storage_batch([(vec![1], vec![1]), (vec![2], vec![2])]);
+
+    // then
+    let alice = AccountId::from_bytes(&[1; 32]).to_hex();
+    let csv = String::from_utf8(rows.borrow().clone()).expect("rows are utf-8");
+    assert_eq!(
+        csv,
+        format!(
+            "kind,account,detail\nwrite,{alice},\nnew-cell,{alice},\nread,{alice},\nevent,{alice},3\nprintln,,\"say \"\"hi\"\"\"\nnoop-clear,{alice},\nbatch-write,{alice},2\n"
+        )
+    );
+    assert_eq!(engine.get_contract_storage_rw(vec![1; 32]), (0, 0));
+}
+
+#[test]
+fn importing_a_v1_state_moves_legacy_balances_to_the_balances_map() {
+    fn hex(bytes: &[u8]) -> String {
+        crate::types::encode_hex(bytes)
+    }
+
+    // given
+    let alice = vec![1; 32];
+    let contract = vec![2; 32];
+    let legacy_key = |account: &[u8]| hex(&crate::database::balance_of_key(account));
+    let json = format!(
+        "{{\"balances\":{{\"{alice}\":\"10\"}},\"storage\":{{\"{contract}\":{{\
+        \"0x2a\":\"0x01\",\"{alice_key}\":\"{ten}\",\"{contract_key}\":\"{twenty}\"}}}}}}",
+        alice = hex(&alice),
+        contract = hex(&contract),
+        alice_key = legacy_key(&alice),
+        contract_key = legacy_key(&contract),
+        ten = hex(&scale::Encode::encode(&10u128)),
+        twenty = hex(&scale::Encode::encode(&20u128)),
+    );
+    let state: EngineState = serde_json::from_str(&json).expect("invalid state");
+    assert_eq!(state.version, 1);
+
+    // when
+    let mut engine = Engine::new();
+    engine
+        .import_state(state)
+        .expect("importing the state failed");
+
+    // then
+    assert_eq!(engine.get_balance(alice.clone()), Ok(10));
+    assert_eq!(engine.get_balance(contract.clone()), Ok(20));
+    assert_eq!(
+        engine.storage_dump(&contract),
+        vec![(vec![0x2a], vec![0x01])]
+    );
+    assert!(engine.storage_dump(&alice).is_empty());
+    let exported: EngineState =
+        serde_json::from_str(&engine.export_state_json()).expect("invalid export");
+    assert_eq!(exported.version, crate::state::STATE_VERSION);
+    assert_eq!(exported.balances.len(), 2);
+}
+
+#[test]
+fn exported_states_import_unchanged() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_balance(vec![1; 32], 1000);
+    engine.set_callee(vec![2; 32]);
+    engine.set_storage(&[0x2a], &[1, 2, 3]);
+    let json = engine.export_state_json();
+
+    // when
+    let mut imported = Engine::new();
+    let state: EngineState = serde_json::from_str(&json).expect("invalid export");
+    imported
+        .import_state(state)
+        .expect("importing the state failed");
+
+    // then
+    assert_eq!(imported.export_state_json(), json);
+}
+
+#[test]
+fn importing_a_state_of_an_unknown_version_fails() {
+    // given
+    let mut engine = Engine::new();
+    let state: EngineState = serde_json::from_str(
+        "{\"version\":3,\"balances\":{\"0x0101\":\"10\"},\"storage\":{}}",
+    )
+    .expect("invalid state");
+
+    // when
+    let res = engine.import_state(state);
+
+    // then
+    assert_eq!(res, Err(StateError::UnsupportedVersion(3)));
+    assert!(engine.accounts().next().is_none());
+}
+
+#[test]
+fn overwriting_unread_cells_is_diagnosed() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    engine.set_callee(contract.clone());
+    engine.enable_diagnostics();
+    engine.set_storage(&[1], &[1]);
+    engine.assert_no_diagnostics();
+
+    // when
+    engine.enter_frame(contract.clone(), Vec::new());
+    engine.set_storage(&[1], &[2]);
+    let _ = engine.get_storage(&[1]);
+    engine.set_storage(&[1], &[3]);
+    engine.exit_frame();
+
+    // then
+    assert_eq!(
+        engine.diagnostics(),
+        vec![Diagnostic::OverwriteWithoutRead {
+            frame: 1,
+            account_id: contract,
+            key: vec![1],
+        }]
+    );
+}
+
+#[test]
+fn clearing_reports_the_previous_length_and_counts_noop_clears() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    engine.set_callee(contract.clone());
+    engine.set_storage(&[1], &[7; 7]);
+    engine.set_storage(&[2], &[2]);
+
+    // when
+    let existing = engine.clear_storage(&[1]);
+    let absent = engine.clear_storage(&[3]);
+
+    // then
+    assert_eq!(existing, Some(7));
+    assert_eq!(absent, None);
+    assert_eq!(engine.count_used_storage_cells(&contract), Ok(1));
+    assert_eq!(engine.get_contract_storage_rw(contract.clone()), (0, 3));
+    assert_eq!(engine.count_noop_clears(&contract), 1);
+}
+
+#[test]
+fn set_storage_reports_the_previous_length_and_counts_new_cells() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    engine.set_callee(contract.clone());
+
+    // when
+    let first = engine.set_storage(&[1], &[7; 7]);
+    let overwrite = engine.set_storage(&[1], &[2]);
+
+    // then
+    assert_eq!(first, None);
+    assert_eq!(overwrite, Some(7));
+    assert_eq!(engine.count_used_storage_cells(&contract), Ok(1));
+    assert_eq!(engine.get_contract_storage_rw(contract.clone()), (0, 2));
+    assert_eq!(engine.count_new_cells(&contract), 1);
+}
+
+#[test]
+fn seeded_storage_is_readable_and_counted_as_one_batch() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    engine.set_callee(contract.clone());
+    engine.set_storage(&[0, 0, 0, 0], &[9]);
+    let entries = (0..1000u32).map(|n| (n.to_le_bytes().to_vec(), vec![n as u8; 4]));
+
+    // when
+    let seeded = engine.seed_storage(&contract, entries);
+    let batched = engine.set_storage_batch([(vec![1], vec![1]), (vec![1], vec![2])]);
+
+    // then
+    assert_eq!(seeded, 1000);
+    assert_eq!(batched, 2);
+    for n in [0u32, 1, 255, 999] {
+        assert_eq!(
+            engine.get_storage_raw(&contract, &n.to_le_bytes()),
+            Some(vec![n as u8; 4])
+        );
+    }
+    assert_eq!(engine.get_storage_raw(&contract, &[1]), Some(vec![2]));
+    assert_eq!(engine.count_used_storage_cells(&contract), Ok(1001));
+    assert_eq!(engine.get_contract_storage_rw(contract), (0, 1003));
+}
+
+#[test]
+fn seeding_within_a_frame_is_reverted_with_it() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.commit_mode = CommitMode::OnFrameSuccess;
+    let contract = vec![1; 32];
+    engine.set_callee(contract.clone());
+    engine.watch_storage_key(&contract, &[1]);
+
+    // when
+    engine.enter_frame(contract.clone(), Vec::new());
+    engine.seed_storage(&contract, [(vec![1], vec![1]), (vec![2], vec![2])]);
+    engine.revert_frame(Vec::new());
+
+    // then
+    assert_eq!(engine.get_storage_raw(&contract, &[1]), None);
+    assert_eq!(engine.get_storage_raw(&contract, &[2]), None);
+    assert_eq!(engine.storage_changes(&contract, &[1]).len(), 1);
+}
+
+#[test]
+fn clearing_missing_keys_is_diagnosed() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    engine.set_callee(contract.clone());
+    engine.enable_diagnostics();
+    engine.set_storage(&[1], &[1]);
+
+    // when
+    engine.clear_storage(&[1]);
+    engine.clear_storage(&[1]);
+
+    // then
+    assert_eq!(
+        engine.diagnostics(),
+        vec![Diagnostic::ClearOfMissingKey {
+            frame: 0,
+            account_id: contract,
+            key: vec![1],
+        }]
+    );
+}
+
+#[test]
+fn zero_value_transfers_are_diagnosed() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    let bob = vec![2; 32];
+    engine.set_callee(contract.clone());
+    engine.set_balance(contract.clone(), 100);
+    engine.enable_diagnostics();
+
+    // when
+    engine
+        .transfer(&bob, &scale::Encode::encode(&0u128))
+        .expect("transfer failed");
+    engine
+        .transfer(&bob, &scale::Encode::encode(&1u128))
+        .expect("transfer failed");
+
+    // then
+    assert_eq!(
+        engine.diagnostics(),
+        vec![Diagnostic::ZeroValueTransfer {
+            frame: 0,
+            from: contract,
+            to: bob,
+        }]
+    );
+}
+
+#[test]
+fn events_without_data_are_diagnosed() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    let topics = scale::Encode::encode(&vec![vec![7u8; 32]]);
+    engine.set_callee(contract.clone());
+    engine.enable_diagnostics();
+
+    // when
+    engine.deposit_event(&topics, &[]);
+    engine.deposit_event(&topics, &[1]);
+
+    // then
+    assert_eq!(
+        engine.diagnostics(),
+        vec![Diagnostic::EventWithEmptyData {
+            frame: 0,
+            emitter: Some(contract),
+            topics: vec![vec![7; 32]],
+        }]
+    );
+}
+
+#[test]
+fn diagnostics_are_recorded_only_while_enabled() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    engine.clear_storage(&[1]);
+    engine.assert_no_diagnostics();
+
+    // when
+    engine.enable_diagnostics();
+    engine.clear_storage(&[2]);
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        engine.assert_no_diagnostics()
+    }));
+    engine.initialize_or_reset();
+    engine.set_callee(vec![1; 32]);
+    engine.clear_storage(&[3]);
+
+    // then
+    let message = res
+        .expect_err("recorded diagnostics must fail the assertion")
+        .downcast::<String>()
+        .expect("panic message must be a string");
+    assert!(message.contains("ClearOfMissingKey"));
+    assert!(engine.diagnostics().is_empty());
+}
+
+#[test]
+fn zero_address_is_rejected_by_default() {
+    // given
+    let mut engine = Engine::new();
+    let zero = vec![0; 32];
+    engine.set_callee(vec![1; 32]);
+    engine.set_balance(vec![1; 32], 100);
+
+    // when
+    let set_balance = engine.try_set_balance(zero.clone(), 10);
+    let set_caller = engine.try_set_caller(zero.clone());
+    let set_callee = engine.try_set_callee(zero.clone());
+    let transfer = engine.transfer(&zero, &scale::Encode::encode(&10u128));
+
+    // then
+    for res in [set_balance, set_caller, set_callee] {
+        assert_eq!(
+            res,
+            Err(crate::Error::Account(
+                crate::AccountError::ZeroAddressForbidden
+            ))
+        );
+    }
+    assert_eq!(transfer, Err(Error::TransferFailed));
+    assert_eq!(engine.get_balance(vec![1; 32]), Ok(100));
+    assert!(engine.get_balance(zero.clone()).is_err());
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        engine.set_balance(zero, 10)
+    }));
+    let message = res
+        .expect_err("funding the zero address must panic")
+        .downcast::<String>()
+        .expect("panic message must be a string");
+    assert!(message.contains("ZeroAddressForbidden"));
+}
+
+#[test]
+fn zero_address_is_accepted_if_allowed() {
+    // given
+    let mut engine = Engine::new();
+    let zero = vec![0; 32];
+    engine.config.allow_zero_address = true;
+
+    // when
+    engine.set_balance(zero.clone(), 100);
+    engine.set_caller(zero.clone());
+    engine.set_callee(zero.clone());
+    let transfer = engine.transfer(&[1; 32], &scale::Encode::encode(&10u128));
+
+    // then
+    assert_eq!(transfer, Ok(()));
+    assert_eq!(engine.get_balance(zero), Ok(90));
+    let mut output = get_buffer();
+    assert_eq!(engine.caller(&mut &mut output[..]), Ok(32));
+}
+
+#[test]
+fn strict_mode_forbids_zero_address_even_if_allowed() {
+    // given
+    let mut engine = Engine::new();
+    let zero = vec![0; 32];
+    engine.config.allow_zero_address = true;
+    engine.set_caller(zero.clone());
+    engine.set_callee(zero.clone());
+
+    // when
+    engine.config.strict = true;
+
+    // then
+    let forbidden = || crate::Error::Account(crate::AccountError::ZeroAddressForbidden);
+    let mut output = get_buffer();
+    assert_eq!(engine.caller(&mut &mut output[..]), Err(forbidden()));
+    assert_eq!(engine.address(&mut &mut output[..]), Err(forbidden()));
+    assert_eq!(engine.try_set_balance(zero, 10), Err(forbidden()));
+}
+
+#[test]
+fn uploading_identical_code_yields_the_same_hash() {
+    fn deploy() {}
+    fn call() {}
+    fn other_call() {}
+
+    // given
+    let mut engine = Engine::new();
+    let bytes = vec![0, 97, 115, 109];
+    let mut expected = [0; 32];
+    crate::hashing::blake2b_256(&bytes, &mut expected);
+
+    // when
+    let uploaded = engine.upload_code(Code::Bytes(bytes.clone()));
+    let reuploaded = engine.upload_code(Code::Bytes(bytes));
+    let mock = engine.upload_code(Code::Mock(EntryPoints { deploy, call }));
+    let remock = engine.upload_code(Code::Mock(EntryPoints { deploy, call }));
+    let other = engine.upload_code(Code::Mock(EntryPoints {
+        deploy,
+        call: other_call,
+    }));
+
+    // then
+    let uploaded = uploaded.expect("uploading failed");
+    assert_eq!(uploaded.as_bytes(), &expected);
+    assert_eq!(reuploaded, Ok(uploaded));
+    let mock = mock.expect("uploading failed");
+    assert_eq!(remock, Ok(mock));
+    assert_ne!(other.expect("uploading failed"), mock);
+    assert!(engine.code_exists(uploaded.as_bytes()));
+    assert!(engine.code_exists(mock.as_bytes()));
+    assert!(!engine.code_exists(&[7; 32]));
+    engine.set_callee(vec![1; 32]);
+    assert!(engine.delegate_call(mock.as_bytes(), None).is_ok());
+}
+
+#[test]
+fn oversized_code_is_rejected() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.max_code_len = 4;
+
+    // when
+    let oversized = engine.upload_code(Code::Bytes(vec![1; 5]));
+    let fitting = engine.upload_code(Code::Bytes(vec![1; 4]));
+
+    // then
+    assert_eq!(oversized, Err(Error::CodeTooLarge));
+    assert!(fitting.is_ok());
+    let mut oversized_hash = [0; 32];
+    crate::hashing::blake2b_256(&[1; 5], &mut oversized_hash);
+    assert!(!engine.code_exists(&oversized_hash));
+}
+
+#[test]
+fn instantiating_requires_uploaded_code() {
+    // given
+    let mut engine = Engine::new();
+    let deployer = vec![1; 32];
+    engine.set_callee(deployer.clone());
+    engine.set_balance(deployer.clone(), 100);
+    let endowment = scale::Encode::encode(&10u128);
+    let code_hash = {
+        let mut hash = [0; 32];
+        crate::hashing::blake2b_256(&[1, 2, 3], &mut hash);
+        hash
+    };
+    let mut address = get_buffer();
+    let mut return_value = get_buffer();
+
+    // when
+    let missing = engine.instantiate(
+        &code_hash,
+        0,
+        &endowment,
+        &[],
+        &mut &mut address[..],
+        &mut &mut return_value[..],
+        b"salt",
+    );
+    engine
+        .upload_code(Code::Bytes(vec![1, 2, 3]))
+        .expect("uploading failed");
+    let uploaded = engine.instantiate(
+        &code_hash,
+        0,
+        &endowment,
+        &[],
+        &mut &mut address[..],
+        &mut &mut return_value[..],
+        b"salt",
+    );
+
+    // then
+    assert_eq!(missing, Err(Error::CodeNotFound));
+    assert_eq!(uploaded, Ok(()));
+    let contract = engine.derive_contract_address(&code_hash, &[], b"salt");
+    assert_eq!(&address[..32], &contract[..]);
+    assert!(engine.is_contract(contract.clone()));
+    assert_eq!(engine.get_balance(contract), Ok(10));
+    assert_eq!(engine.get_balance(deployer), Ok(90));
+}
+
+#[test]
+fn instantiate_uses_the_custom_address_derivation() {
+    // given
+    let mut engine = Engine::new();
+    engine.chain_spec.account_length = 20;
+    let deployer = vec![1; 20];
+    engine.set_callee(deployer.clone());
+    engine.set_origin(Origin::Signed(vec![9; 20]));
+    engine.increment_nonce(&[9; 20]);
+    engine.set_address_derivation(|input| {
+        let mut hash = [0; 32];
+        Engine::hash_keccak_256(
+            &[input.deployer, input.salt, &[input.nonce as u8]].concat(),
+            &mut hash,
+        );
+        hash[12..].to_vec()
+    });
+    let code_hash = engine
+        .upload_code(Code::Bytes(vec![1, 2, 3]))
+        .expect("uploading failed");
+    let mut address = get_buffer();
+    let mut return_value = get_buffer();
+
+    // when
+    let res = engine.instantiate(
+        code_hash.as_bytes(),
+        0,
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut address[..],
+        &mut &mut return_value[..],
+        b"salt",
+    );
+
+    // then
+    assert_eq!(res, Ok(()));
+    let mut expected = [0; 32];
+    Engine::hash_keccak_256(&[&deployer[..], b"salt", &[1]].concat(), &mut expected);
+    assert_eq!(&address[..20], &expected[12..]);
+    assert!(engine.is_contract(expected[12..].to_vec()));
+}
+
+#[test]
+#[should_panic(
+    expected = "the address derivation returned an address of 32 bytes, but accounts \
+                of the chain have 20 bytes"
+)]
+fn derived_addresses_must_have_the_account_length() {
+    let mut engine = Engine::new();
+    engine.chain_spec.account_length = 20;
+    engine.set_callee(vec![1; 20]);
+    engine.set_address_derivation(|_| vec![7; 32]);
+    engine.derive_contract_address(&[0; 32], &[], &[]);
+}
+
+#[test]
+fn repeated_reads_are_served_from_the_read_cache() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    engine.set_callee(contract.clone());
+    engine.set_storage(&[1; 32], &[7, 8]);
+
+    // when
+    engine.get_storage(&[1; 32]).expect("reading failed");
+    engine.get_storage(&[1; 32]).expect("reading failed");
+    let size = engine.contains_storage(&[1; 32]);
+    let missing = engine.get_storage(&[2; 32]).is_err();
+    let still_missing = engine.contains_storage(&[2; 32]);
+
+    // then
+    assert_eq!(size, Some(2));
+    assert!(missing);
+    assert_eq!(still_missing, None);
+    let statistics = engine.contract_statistics(&contract);
+    assert_eq!(statistics.cache_hits, 3);
+    assert_eq!(statistics.cache_misses, 2);
+    assert_eq!(statistics.storage_reads, 5);
+}
+
+#[test]
+fn writes_and_clears_invalidate_the_read_cache() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    engine.set_callee(contract.clone());
+    engine.set_storage(&[1; 32], &[1]);
+    engine.get_storage(&[1; 32]).expect("reading failed");
+
+    // when
+    engine.set_storage(&[1; 32], &[2, 2]);
+    let written = engine.get_storage(&[1; 32]).map(<[u8]>::to_vec);
+    engine.clear_storage(&[1; 32]);
+    let cleared = engine.contains_storage(&[1; 32]);
+    engine.set_storage(&[1; 32], &[3]);
+    let taken = engine.take_storage(&[1; 32]);
+    let after_take = engine.get_storage(&[1; 32]).map(<[u8]>::to_vec);
+
+    // then
+    assert_eq!(written, Ok(vec![2, 2]));
+    assert_eq!(cleared, None);
+    assert_eq!(taken, Ok(vec![3]));
+    assert_eq!(after_take, Err(Error::KeyNotFound(vec![1; 32])));
+    assert_eq!(engine.cache_hits(&contract), 0);
+    assert_eq!(engine.cache_misses(&contract), 4);
+}
+
+#[test]
+fn read_cache_is_dropped_when_frames_change() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    engine.set_caller(vec![2; 32]);
+    engine.set_callee(contract.clone());
+    engine.set_storage(&[1; 32], &[1]);
+    engine.get_storage(&[1; 32]).expect("reading failed");
+
+    // when
+    engine.enter_frame(contract.clone(), Vec::new());
+    engine.get_storage(&[1; 32]).expect("reading failed");
+    engine.get_storage(&[1; 32]).expect("reading failed");
+    engine.exit_frame();
+    engine.get_storage(&[1; 32]).expect("reading failed");
+
+    // then
+    assert_eq!(engine.cache_hits(&contract), 1);
+    assert_eq!(engine.cache_misses(&contract), 3);
+}
+
+#[test]
+fn cached_reads_are_not_counted_when_counting_physical_reads_only() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.count_physical_reads_only = true;
+    let contract = vec![1; 32];
+    engine.set_callee(contract.clone());
+    engine.set_storage(&[1; 32], &[1]);
+
+    // when
+    for _ in 0..3 {
+        engine.get_storage(&[1; 32]).expect("reading failed");
+    }
+    engine.contains_storage(&[1; 32]);
+
+    // then
+    assert_eq!(engine.get_contract_storage_rw(contract.clone()), (1, 1));
+    assert_eq!(engine.cache_hits(&contract), 3);
+    engine.initialize_or_reset();
+    assert_eq!(engine.cache_hits(&contract), 0);
+}
+
+#[test]
+fn api_records_nested_calls_and_events_in_order() {
+    use crate::api::{
+        ApiError,
+        Config,
+        Simulator,
+    };
+
+    const OUTER: [u8; 4] = [1; 4];
+    const INNER: [u8; 4] = [2; 4];
+
+    fn contract(
+        sim: &mut Simulator,
+        selector: [u8; 4],
+        args: &[u8],
+    ) -> Result<Vec<u8>, Vec<u8>> {
+        sim.set_storage(&selector, &[1]).map_err(|_| Vec::new())?;
+        sim.emit_event(vec![[selector[0]; 32]], b"before")
+            .map_err(|_| Vec::new())?;
+        if selector == OUTER {
+            let inner = crate::api::AccountId::try_from(args).expect("invalid account");
+            let reverted = sim.call(sim.address(), inner, INNER, &[]);
+            assert_eq!(reverted, Err(ApiError::Reverted(b"inner".to_vec())));
+            sim.emit_event(Vec::new(), b"after")
+                .map_err(|_| Vec::new())?;
+            return Ok(sim.get_storage(&INNER).unwrap_or_default())
+        }
+        Err(b"inner".to_vec())
+    }
+
+    // given
+    let mut sim = Simulator::new(Config::default());
+    let outer = sim.deploy(contract, b"outer", 0);
+    let inner = sim.deploy(contract, b"inner", 0);
+
+    // when
+    let output = sim.call([9; 32], outer, OUTER, &inner);
+    let missing = sim.call([9; 32], [7; 32], OUTER, &[]);
+
+    // then
+    assert_eq!(output, Ok(Vec::new()));
+    assert_eq!(missing, Err(ApiError::NotCallable));
+    let calls = sim.calls();
+    assert_eq!(calls.len(), 2);
+    assert_eq!((calls[1].depth, &calls[1].caller[..]), (2, &outer[..]));
+    let events: Vec<_> = sim
+        .events()
+        .into_iter()
+        .map(|event| (event.call, event.data))
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            (1, b"before".to_vec()),
+            (2, b"before".to_vec()),
+            (1, b"after".to_vec()),
+        ]
+    );
+    assert_eq!(sim.statistics(inner).storage_writes, 1);
+    let report = sim.activity_report();
+    assert_eq!(report.contracts.len(), 2);
+    assert_eq!(report.events.len(), 3);
+    assert_eq!(report.dropped, DroppedRecords::default());
+}
+
+#[test]
+fn api_state_round_trips() {
+    use crate::api::{
+        Config,
+        Simulator,
+    };
+
+    fn store(
+        sim: &mut Simulator,
+        _selector: [u8; 4],
+        args: &[u8],
+    ) -> Result<Vec<u8>, Vec<u8>> {
+        sim.set_storage(b"key", args).map_err(|_| Vec::new())?;
+        Ok(Vec::new())
+    }
+
+    // given
+    let mut sim = Simulator::new(Config::default());
+    let contract = sim.deploy(store, b"store", 10);
+    sim.call([1; 32], contract, [0; 4], &[4, 2])
+        .expect("calling failed");
+
+    // when
+    let state = sim.export_state();
+    let mut imported = Simulator::new(Config::default());
+    imported
+        .import_state(state.clone())
+        .expect("importing failed");
+
+    // then
+    let from_json: EngineState =
+        serde_json::from_str(&sim.export_state_json()).expect("invalid JSON");
+    assert_eq!(state, from_json);
+    assert_eq!(imported.export_state(), state);
+    assert_eq!(imported.balance(contract), 10);
+}
+
+#[test]
+fn api_signatures_are_stable() {
+    use crate::api::*;
+
+    // Changing any of these signatures is a breaking change of the `api` module.
+    type Done = Result<(), ApiError>;
+    type Call = Result<Vec<u8>, ApiError>;
+    let _: fn(Config) -> Simulator = Simulator::new;
+    let _: fn(&mut Simulator, EngineState) -> Done = Simulator::import_state;
+    let _: fn(&Simulator) -> EngineState = Simulator::export_state;
+    let _: fn(&Simulator) -> String = Simulator::export_state_json;
+    let _: fn(&mut Simulator, Contract, &[u8], Balance) -> AccountId = Simulator::deploy;
+    let _: fn(&mut Simulator, AccountId, AccountId, [u8; 4], &[u8]) -> Call =
+        Simulator::call;
+    let _: fn(&Simulator, AccountId) -> Balance = Simulator::balance;
+    let _: fn(&mut Simulator, AccountId, Balance) -> Done = Simulator::set_balance;
+    let _: fn(&Simulator) -> AccountId = Simulator::address;
+    let _: fn(&Simulator) -> AccountId = Simulator::caller;
+    let _: fn(&mut Simulator, &[u8]) -> Option<Vec<u8>> = Simulator::get_storage;
+    let _: fn(&mut Simulator, &[u8], &[u8]) -> Done = Simulator::set_storage;
+    let _: fn(&mut Simulator, &[u8]) -> bool = Simulator::clear_storage;
+    let _: fn(&mut Simulator, Vec<[u8; 32]>, &[u8]) -> Done = Simulator::emit_event;
+    let _: fn(&mut Simulator, &str) = Simulator::debug_message;
+    let _: fn(&mut Simulator, AccountId, Balance) -> Done = Simulator::transfer;
+    let _: fn(&Simulator) -> Vec<CallRecord> = Simulator::calls;
+    let _: fn(&Simulator) -> Vec<EventRecord> = Simulator::events;
+    let _: fn(&Simulator) -> Vec<TransferRecord> = Simulator::transfers;
+    let _: fn(&Simulator) -> Vec<String> = Simulator::debug_messages;
+    let _: fn(&Simulator, AccountId) -> Statistics = Simulator::statistics;
+    let _: fn(&Simulator) -> ActivityReport = Simulator::activity_report;
+}
+
+#[test]
+fn gas_left_decreases_by_the_weights_charged_in_between() {
+    fn gas_left(engine: &mut Engine) -> u64 {
+        let mut output = get_buffer();
+        let written = engine
+            .gas_left(&mut &mut output[..])
+            .expect("reading the gas left failed");
+        scale::Decode::decode(&mut &output[..written]).expect("decoding failed")
+    }
+
+    // given
+    let mut engine = Engine::new();
+    engine.config.gas_limit = 1_000_000_000_000;
+    let weights = WeightTable::default();
+    let contract = vec![1; 32];
+    engine.set_caller(vec![2; 32]);
+    engine.enter_frame(contract, Vec::new());
+
+    // when
+    let before = gas_left(&mut engine);
+    for n in 0..10u8 {
+        engine.set_storage(&[n], &[n; 4]);
+    }
+    let after = gas_left(&mut engine);
+
+    // then
+    let write = weights.storage_write_base + 4 * weights.storage_write_per_byte;
+    assert_eq!(before, engine.config.gas_limit - weights.gas_left_base);
+    assert_eq!(before - after, 10 * write + weights.gas_left_base);
+    engine.exit_frame();
+}
+
+#[test]
+fn gas_left_is_charged_even_without_other_operations() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.gas_limit = 1_000_000_000_000;
+    let mut output = get_buffer();
+
+    // when
+    engine.gas_left(&mut &mut output[..]).unwrap();
+    let first: u64 = scale::Decode::decode(&mut &output[..]).unwrap();
+    engine.gas_left(&mut &mut output[..]).unwrap();
+    let second: u64 = scale::Decode::decode(&mut &output[..]).unwrap();
+
+    // then
+    assert!(second < first);
+    assert_eq!(
+        engine.gas_consumed(),
+        2 * WeightTable::default().gas_left_base
+    );
+}
+
+#[test]
+fn reverting_to_a_block_restores_its_state() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    let no_topics = scale::Encode::encode(&scale::Compact(0u32));
+    engine.set_callee(contract.clone());
+    for block in 1..=3u8 {
+        engine.advance_block();
+        engine.set_storage(&[block], &[block]);
+        engine.set_balance(contract.clone(), u128::from(block));
+        engine.deposit_event(&no_topics, &[block]);
+    }
+    let timestamp = engine.exec_context.timestamp();
+
+    // when
+    let reverted = engine.revert_to_block(1);
+
+    // then
+    assert_eq!(reverted, Ok(()));
+    assert_eq!(engine.exec_context.block_number(), 1);
+    assert!(engine.exec_context.timestamp() < timestamp);
+    assert_eq!(engine.get_storage_raw(&contract, &[1]), Some(vec![1]));
+    assert_eq!(engine.get_storage_raw(&contract, &[2]), None);
+    assert_eq!(engine.get_storage_raw(&contract, &[3]), None);
+    assert_eq!(engine.get_balance(contract.clone()), Ok(1));
+    let events: Vec<_> = engine
+        .get_emitted_events()
+        .map(|event| event.data)
+        .collect();
+    assert_eq!(events, [vec![1]]);
+    assert_eq!(engine.retained_blocks(), [0]);
+}
+
+#[test]
+fn only_the_configured_number_of_blocks_is_retained() {
+    use crate::block_history::BlockNotRetained;
+
+    // given
+    let mut engine = Engine::new();
+    engine.config.block_history_len = 2;
+    for _ in 0..4 {
+        engine.advance_block();
+    }
+
+    // when
+    let pruned = engine.revert_to_block(1);
+    let future = engine.revert_to_block(5);
+
+    // then
+    assert_eq!(engine.retained_blocks(), [2, 3]);
+    assert_eq!(
+        pruned,
+        Err(BlockNotRetained {
+            block_number: 1,
+            retained: vec![2, 3],
+        })
+    );
+    assert_eq!(
+        pruned.unwrap_err().to_string(),
+        "block 1 is not retained, retained blocks: 2 to 3"
+    );
+    assert!(future.is_err());
+    assert_eq!(engine.revert_to_block(4), Ok(()));
+    assert_eq!(engine.revert_to_block(2), Ok(()));
+    assert_eq!(engine.exec_context.block_number(), 2);
+}
+
+#[test]
+fn reentrance_count_counts_enclosing_calls_of_the_callee() {
+    fn reentrance_count(engine: &Engine) -> u32 {
+        let mut output = get_buffer();
+        let written = engine
+            .reentrance_count(&mut &mut output[..])
+            .expect("reading the reentrance count failed");
+        scale::Decode::decode(&mut &output[..written]).expect("decoding failed")
+    }
+
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    let other = vec![2; 32];
+    engine.set_caller(vec![3; 32]);
+
+    // when
+    let mut counts = Vec::new();
+    engine.enter_frame(contract.clone(), Vec::new());
+    counts.push(reentrance_count(&engine));
+    engine.enter_frame(contract.clone(), Vec::new());
+    counts.push(reentrance_count(&engine));
+    engine.enter_delegate_frame(None);
+    counts.push(reentrance_count(&engine));
+    engine.enter_frame(contract.clone(), Vec::new());
+    counts.push(reentrance_count(&engine));
+    engine.enter_frame(other, Vec::new());
+    counts.push(reentrance_count(&engine));
+
+    // then
+    assert_eq!(counts, [0, 1, 1, 2, 0]);
+    for _ in 0..5 {
+        engine.exit_frame();
+    }
+    assert_eq!(reentrance_count(&engine), 0);
+}
+
+#[test]
+#[should_panic(expected = "the output buffer of `caller` is too small: the value is \
+    of size 32 bytes, but the output buffer has only room for 4")]
+fn too_small_output_buffer_panics_under_panic_policy() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_caller(vec![1; 32]);
+
+    // when
+    let mut output = [0u8; 4];
+    let _ = engine.caller(&mut &mut output[..]);
+}
+
+#[test]
+fn too_small_output_buffer_errors_under_error_policy() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.misuse_policy = MisusePolicy::Error;
+    engine.set_caller(vec![1; 32]);
+
+    // when
+    let mut output = [0u8; 4];
+    let result = engine.caller(&mut &mut output[..]);
+
+    // then
+    assert_eq!(result, Err(crate::Error::BufferTooSmall(32)));
+    assert_eq!(output, [0u8; 4]);
+}
+
+#[test]
+#[should_panic(expected = "`set_storage` requires a callee, but none has been set")]
+fn storage_write_without_callee_panics_under_panic_policy() {
+    // given
+    let mut engine = Engine::new();
+
+    // when
+    let _ = engine.try_set_storage(&[1; 32], &[2]);
+}
+
+#[test]
+#[should_panic(expected = "`contains_storage` requires a callee, but none has been set")]
+fn storage_contains_without_callee_panics_under_panic_policy() {
+    // given
+    let mut engine = Engine::new();
+
+    // when
+    let _ = engine.try_contains_storage(&[1; 32]);
+}
+
+#[test]
+#[should_panic(expected = "`clear_storage` requires a callee, but none has been set")]
+fn storage_clear_without_callee_panics_under_panic_policy() {
+    // given
+    let mut engine = Engine::new();
+
+    // when
+    let _ = engine.try_clear_storage(&[1; 32]);
+}
+
+#[test]
+fn storage_access_without_callee_errors_under_error_policy() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.misuse_policy = MisusePolicy::Error;
+
+    // when
+    let written = engine.try_set_storage(&[1; 32], &[2]);
+    let read = engine.get_storage(&[1; 32]).map(<[u8]>::to_vec);
+    let taken = engine.take_storage(&[1; 32]);
+    let contained = engine.try_contains_storage(&[1; 32]);
+    let cleared = engine.try_clear_storage(&[1; 32]);
+
+    // then
+    assert_eq!(written, Err(Error::NoCallee));
+    assert_eq!(read, Err(Error::NoCallee));
+    assert_eq!(taken, Err(Error::NoCallee));
+    assert_eq!(contained, Err(Error::NoCallee));
+    assert_eq!(cleared, Err(Error::NoCallee));
+}
+
+#[test]
+#[should_panic(expected = "`terminate` requires a balance of the contract")]
+fn terminate_without_balance_panics_under_panic_policy() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+
+    // when
+    let _ = engine.terminate(&[2; 32]);
+}
+
+#[test]
+fn terminate_without_callee_or_balance_errors_under_error_policy() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.misuse_policy = MisusePolicy::Error;
+
+    // when
+    let without_callee = engine.terminate(&[2; 32]);
+    engine.set_callee(vec![1; 32]);
+    let without_balance = engine.terminate(&[2; 32]);
+
+    // then
+    assert_eq!(without_callee, Err(Error::NoCallee));
+    assert_eq!(without_balance, Err(Error::NoBalance(vec![1; 32])));
+    assert_ne!(engine.account_kind(&[1; 32]), AccountKind::Tombstone);
+}
+
+#[test]
+fn transfer_between_moves_value_without_a_callee() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let bob = vec![2; 32];
+    engine.set_balance(alice.clone(), 1_000);
+
+    // when
+    let result = engine.transfer_between(&alice, &bob, 300);
+
+    // then
+    assert_eq!(result, Ok(()));
+    assert_eq!(engine.get_balance(alice.clone()), Ok(700));
+    assert_eq!(engine.get_balance(bob.clone()), Ok(300));
+    assert_eq!(
+        engine.fixture_transfers(),
+        [TransferRecord {
+            from: alice,
+            to: bob,
+            value: 300,
+            frame: 0,
+        }]
+    );
+}
+
+#[test]
+fn transfer_between_fails_without_sufficient_funds() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let bob = vec![2; 32];
+    engine.set_balance(alice.clone(), 100);
+    engine.set_balance(bob.clone(), u128::MAX);
+
+    // when
+    let insufficient = engine.transfer_between(&alice, &bob, 101);
+    let overflowing = engine.transfer_between(&alice, &bob, 1);
+    engine.config.allow_implicit_account_creation = false;
+    let creating = engine.transfer_between(&alice, &[3; 32], 1);
+
+    // then
+    assert_eq!(insufficient, Err(Error::TransferFailed));
+    assert_eq!(overflowing, Err(Error::TransferFailed));
+    assert_eq!(creating, Err(Error::TransferFailed));
+    assert_eq!(engine.get_balance(alice), Ok(100));
+    assert_eq!(engine.get_balance(bob), Ok(u128::MAX));
+    assert!(engine.fixture_transfers().is_empty());
+    assert!(engine.rejected_transfers().is_empty());
+}
+
+#[test]
+fn recorded_transfers_exclude_fixture_transfers() {
+    // given
+    let mut engine = Engine::new();
+    let alice = vec![1; 32];
+    let bob = vec![2; 32];
+    let contract = vec![3; 32];
+    engine.set_balance(alice.clone(), 1_000);
+    engine.set_balance(contract.clone(), 1_000);
+    engine.set_callee(contract.clone());
+
+    // when
+    engine
+        .transfer_between(&alice, &bob, 10)
+        .expect("fixture transfer failed");
+    engine
+        .transfer(&bob, &scale::Encode::encode(&20u128))
+        .expect("contract transfer failed");
+
+    // then
+    let from = |records: &[TransferRecord]| -> Vec<Vec<u8>> {
+        records.iter().map(|record| record.from.clone()).collect()
+    };
+    assert_eq!(from(engine.recorded_transfers()), [contract]);
+    assert_eq!(from(engine.fixture_transfers()), [alice]);
+    assert_eq!(engine.get_balance(bob), Ok(30));
+}
+
+#[test]
+fn call_outcome_carries_the_events_of_the_callee() {
+    // given
+    let mut engine = Engine::new();
+    let no_topics = scale::Encode::encode(&scale::Compact(0u32));
+    engine.set_caller(vec![1; 32]);
+    engine.enter_frame(vec![2; 32], Vec::new());
+
+    // when
+    engine.enter_frame(vec![3; 32], Vec::new());
+    engine.deposit_event(&no_topics, &[1]);
+    engine.deposit_event(&no_topics, &[2]);
+    engine.return_from_frame(Vec::new());
+    let inner = engine
+        .last_call_outcome()
+        .expect("the inner call completed");
+
+    // then
+    let data: Vec<Vec<u8>> = inner.events.iter().map(|e| e.data.clone()).collect();
+    assert_eq!(data, [vec![1], vec![2]]);
+    assert_eq!(inner.events_emitted, 2);
+    assert_eq!(engine.get_emitted_events().count(), 2);
+    engine.exit_frame();
+    let outer = engine
+        .last_call_outcome()
+        .expect("the outer call completed");
+    assert_eq!(outer.events, inner.events);
+    assert_eq!(engine.get_emitted_events().count(), 2);
+}
+
+#[test]
+fn call_outcome_carries_the_gas_consumed_by_the_call() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_caller(vec![1; 32]);
+    engine.enter_frame(vec![2; 32], Vec::new());
+    engine.consume_gas(5);
+
+    // when
+    engine.enter_frame(vec![3; 32], Vec::new());
+    engine.consume_gas(3);
+    engine.return_from_frame(Vec::new());
+    let inner = engine
+        .last_call_outcome()
+        .expect("the inner call completed");
+    engine.exit_frame();
+    let outer = engine
+        .last_call_outcome()
+        .expect("the outer call completed");
+
+    // then
+    assert_eq!(inner.gas_consumed, 3);
+    assert_eq!(outer.gas_consumed, 8);
+    assert_eq!(engine.gas_consumed(), 8);
+}
+
+#[test]
+fn call_outcome_of_a_reverted_call_has_no_events() {
+    // given
+    let mut engine = Engine::new();
+    let no_topics = scale::Encode::encode(&scale::Compact(0u32));
+    engine.set_caller(vec![1; 32]);
+    engine.enter_frame(vec![2; 32], Vec::new());
+
+    // when
+    engine.deposit_event(&no_topics, &[1]);
+    engine.revert_frame(vec![9]);
+
+    // then
+    let outcome = engine.last_call_outcome().expect("the call completed");
+    assert_eq!(outcome.data, [9]);
+    assert!(outcome.events.is_empty());
+}
+
+#[test]
+fn call_outcome_omits_the_events_of_reverted_nested_calls() {
+    // given
+    let mut engine = Engine::new();
+    let no_topics = scale::Encode::encode(&scale::Compact(0u32));
+    engine.set_caller(vec![1; 32]);
+    engine.enter_frame(vec![2; 32], Vec::new());
+    engine.deposit_event(&no_topics, &[1]);
+
+    // when
+    engine.enter_frame(vec![3; 32], Vec::new());
+    engine.deposit_event(&no_topics, &[2]);
+    engine.revert_frame(Vec::new());
+    engine.deposit_event(&no_topics, &[3]);
+    engine.return_from_frame(Vec::new());
+
+    // then
+    let outer = engine
+        .last_call_outcome()
+        .expect("the outer call completed");
+    let data: Vec<Vec<u8>> = outer.events.iter().map(|e| e.data.clone()).collect();
+    assert_eq!(data, [vec![1], vec![3]]);
+    let frames = engine.recorded_frames();
+    assert!(!frames[1].reverted);
+    assert!(frames[2].reverted);
+}
+
+#[test]
+fn recorded_events_are_bounded_by_the_retention_limit() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.max_recorded_events = Some(10);
+    let no_topics = scale::Encode::encode(&scale::Compact(0u32));
+
+    // when
+    for index in 0..15u8 {
+        engine.deposit_event(&no_topics, &[index]);
+    }
+
+    // then
+    let data: Vec<u8> = engine.get_emitted_events().map(|e| e.data[0]).collect();
+    assert_eq!(data, (5..15).collect::<Vec<u8>>());
+    assert_eq!(engine.dropped_records().events, 5);
+}
+
+#[test]
+fn frame_items_are_bounded_by_the_retention_limits() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.max_recorded_events = Some(10);
+    engine.config.max_recorded_printlns = Some(10);
+    engine.config.max_recorded_storage_accesses = Some(10);
+    let no_topics = scale::Encode::encode(&scale::Compact(0u32));
+    engine.set_caller(vec![1; 32]);
+
+    // when
+    for index in 0..15u8 {
+        engine.enter_frame(vec![2; 32], Vec::new());
+        engine.deposit_event(&no_topics, &[index]);
+        engine.debug_message("message");
+        engine.set_storage(&[index], &[index]);
+        let _ = engine.get_storage(&[index]);
+        engine.exit_frame();
+    }
+
+    // then
+    let frames = engine.recorded_frames();
+    let retained = |frame: &FrameRecord| {
+        frame
+            .items
+            .iter()
+            .filter(|item| !matches!(item, FrameItem::Call(_)))
+            .count()
+    };
+    assert_eq!(frames.iter().map(retained).sum::<usize>(), 30);
+    let data: Vec<u8> = frames
+        .iter()
+        .flat_map(FrameRecord::events)
+        .map(|event| event.data[0])
+        .collect();
+    assert_eq!(data, (5..15).collect::<Vec<u8>>());
+    assert_eq!(
+        frames[1].dropped,
+        DroppedFrameItems {
+            events: 1,
+            printlns: 1,
+            storage_reads: 1,
+            storage_writes: 1,
+        }
+    );
+    assert_eq!(frames[10].dropped.events, 0);
+    assert_eq!(frames[11].dropped.storage_writes, 0);
+    assert_eq!(frames[15].dropped, DroppedFrameItems::default());
+    let outcome = engine.last_call_outcome().expect("the call completed");
+    assert_eq!(outcome.events_emitted, 1);
+    assert_eq!(outcome.storage_writes, 1);
+}
+
+#[test]
+fn recorded_printlns_and_calls_are_bounded_by_the_retention_limits() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.max_recorded_printlns = Some(2);
+    engine.config.max_recorded_calls = Some(1);
+    engine.set_caller(vec![1; 32]);
+
+    // when
+    for message in ["a", "b", "c"] {
+        engine.debug_message(message);
+    }
+    for callee in [2, 3, 4] {
+        engine.enter_frame(vec![callee; 32], Vec::new());
+        engine.exit_frame();
+    }
+
+    // then
+    let messages: Vec<String> = engine.get_emitted_debug_messages().into_iter().collect();
+    assert_eq!(messages, ["b", "c"]);
+    let calls = engine.recorded_calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].callee, vec![4; 32]);
+    assert_eq!(
+        engine.dropped_records(),
+        DroppedRecords {
+            events: 0,
+            printlns: 1,
+            calls: 2,
+        }
+    );
+}
+
+#[test]
+fn exported_activity_includes_the_dropped_records() {
+    // given
+    let mut engine = Engine::new();
+    engine.config.max_recorded_printlns = Some(1);
+    engine.start_recording_scenario();
+
+    // when
+    engine.debug_message("dropped");
+    engine.debug_message("retained");
+    let log = ActivityLog::decode(&engine.export_activity())
+        .expect("decoding activity log failed");
+
+    // then
+    assert_eq!(log.dropped.printlns, 1);
+    assert_eq!(log.operations.len(), 2);
+}
+
+#[test]
+fn activity_logs_of_version_1_are_decoded_without_dropped_records() {
+    // given
+    let mut encoded = vec![1];
+    scale::Encode::encode_to(&vec![Operation::AdvanceTime(5)], &mut encoded);
+
+    // when
+    let log = ActivityLog::decode(&encoded).expect("decoding activity log failed");
+
+    // then
+    assert_eq!(log.operations, [Operation::AdvanceTime(5)]);
+    assert_eq!(log.dropped, DroppedRecords::default());
+}
+
+#[test]
+fn reads_of_never_written_keys_are_diagnosed() {
+    // given
+    let mut engine = Engine::new();
+    let contract = vec![1; 32];
+    engine.set_callee(contract.clone());
+    engine.set_storage(&[1], &[10]);
+    engine.enable_diagnostics();
+    engine.set_storage(&[2], &[20]);
+    engine.clear_storage(&[2]);
+
+    // when
+    let existing = engine.get_storage(&[1]).map(<[u8]>::to_vec);
+    let cleared = engine.get_storage(&[2]).map(<[u8]>::to_vec);
+    let missing = engine.get_storage(&[3]).map(<[u8]>::to_vec);
+
+    // then
+    assert_eq!(existing, Ok(vec![10]));
+    assert_eq!(cleared, Err(Error::KeyNotFound(vec![2])));
+    assert_eq!(missing, Err(Error::KeyNotFound(vec![3])));
+    assert_eq!(engine.uninitialized_reads(&contract), [vec![3]]);
+    let uninitialized: Vec<_> = engine
+        .diagnostics()
+        .into_iter()
+        .filter(|diagnostic| matches!(diagnostic, Diagnostic::UninitializedRead { .. }))
+        .collect();
+    assert_eq!(
+        uninitialized,
+        [Diagnostic::UninitializedRead {
+            frame: 0,
+            account_id: contract,
+            key: vec![3],
+        }]
+    );
+}
+
+#[cfg(feature = "wasm-exec")]
+#[test]
+fn wasm_flipper_is_executed_through_call() {
+    const NEW: [u8; 4] = [0x9b, 0xae, 0x9d, 0x5e];
+    const FLIP: [u8; 4] = [0x63, 0x3a, 0xa5, 0x51];
+    const GET: [u8; 4] = [0x2f, 0x86, 0x5b, 0xd9];
+
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    let code_hash = engine
+        .upload_code(Code::Bytes(
+            include_bytes!("../tests/fixtures/flipper.wasm").to_vec(),
+        ))
+        .expect("uploading failed");
+    let mut address = get_buffer();
+    let mut return_value = get_buffer();
+    engine
+        .instantiate(
+            code_hash.as_bytes(),
+            0,
+            &scale::Encode::encode(&0u128),
+            &[&NEW[..], &[0]].concat(),
+            &mut &mut address[..],
+            &mut &mut return_value[..],
+            b"salt",
+        )
+        .expect("instantiating failed");
+    let flipper = address[..32].to_vec();
+    let value = scale::Encode::encode(&0u128);
+    let get = |engine: &mut Engine| {
+        let mut output = get_buffer();
+        let mut output = &mut output[..];
+        engine
+            .call(&flipper, 0, &value, &GET, &mut output)
+            .expect("calling `get` failed");
+        output.to_vec()
+    };
+    let before = get(&mut engine);
+
+    // when
+    let mut output = get_buffer();
+    let flipped = engine.call(&flipper, 0, &value, &FLIP, &mut &mut output[..]);
+
+    // then
+    assert_eq!(flipped, Ok(()));
+    assert_eq!(before, [0, 0]);
+    assert_eq!(get(&mut engine), [0, 1]);
+    assert_eq!(engine.get_recorded_warnings(), []);
+}
+
+#[cfg(feature = "wasm-exec")]
+#[test]
+fn wasm_contracts_trap_on_unsupported_imports() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    let code_hash = engine
+        .upload_code(Code::Bytes(
+            include_bytes!("../tests/fixtures/unimplemented_import.wasm").to_vec(),
+        ))
+        .expect("uploading failed");
+    let mut address = get_buffer();
+    let mut return_value = get_buffer();
+    engine
+        .instantiate(
+            code_hash.as_bytes(),
+            0,
+            &scale::Encode::encode(&0u128),
+            &[],
+            &mut &mut address[..],
+            &mut &mut return_value[..],
+            b"salt",
+        )
+        .expect("instantiating failed");
+    let contract = address[..32].to_vec();
+
+    // when
+    let res = engine.call(
+        &contract,
+        0,
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut get_buffer()[..],
+    );
+
+    // then
+    assert_eq!(res, Err(Error::CalleeTrapped));
+    assert_eq!(
+        engine.get_recorded_warnings(),
+        [Warning::ContractTrapped {
+            account_id: contract,
+            message: String::from(
+                "the contract called the import `seal0::weight_to_fee`, which is not \
+                 supported by the engine"
+            ),
+        }]
+    );
+}
+
+#[cfg(feature = "wasm-exec")]
+#[test]
+fn wasm_contracts_deposit_events_without_deprecation_warnings() {
+    // given
+    let mut engine = Engine::new();
+    engine.set_callee(vec![1; 32]);
+    let code_hash = engine
+        .upload_code(Code::Bytes(
+            include_bytes!("../tests/fixtures/events.wasm").to_vec(),
+        ))
+        .expect("uploading failed");
+    let mut address = get_buffer();
+    let mut return_value = get_buffer();
+    engine
+        .instantiate(
+            code_hash.as_bytes(),
+            0,
+            &scale::Encode::encode(&0u128),
+            &[],
+            &mut &mut address[..],
+            &mut &mut return_value[..],
+            b"salt",
+        )
+        .expect("instantiating failed");
+    let contract = address[..32].to_vec();
+
+    // when
+    let res = engine.call(
+        &contract,
+        0,
+        &scale::Encode::encode(&0u128),
+        &[],
+        &mut &mut get_buffer()[..],
+    );
+
+    // then
+    assert_eq!(res, Ok(()));
+    let topics: Vec<Vec<Vec<u8>>> = engine
+        .get_emitted_events()
+        .map(|event| event.topics)
+        .collect();
+    assert_eq!(topics, [vec![], vec![vec![7; 32]]]);
+    assert_eq!(engine.get_recorded_warnings(), []);
+}
@@ -15,7 +15,12 @@
 //! Right now the `engine` crate can only be used with the `ink_env::DefaultEnvironment`.
 //! This is a known limitation that we want to address in the future.
 
+use core::fmt::Write;
 use derive_more::From;
+use ink_prelude::{
+    string::String,
+    vec::Vec,
+};
 
 /// Same type as the `DefaultEnvironment::BlockNumber` type.
 pub type BlockNumber = u32;
@@ -26,6 +31,13 @@ pub type BlockTimestamp = u64;
 /// Same type as the `DefaultEnvironment::Balance` type.
 pub type Balance = u128;
 
+/// The identifier of an asset on multi-asset chains.
+pub type AssetId = u32;
+
+/// The length of account ids in bytes, unless configured otherwise via
+/// [`crate::ext::ChainSpec::account_length`].
+pub const DEFAULT_ACCOUNT_LENGTH: usize = 32;
+
 /// The Account Id type used by this crate.
 #[derive(Debug, From, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(transparent)]
@@ -41,6 +53,126 @@ impl AccountId {
     pub fn as_bytes(&self) -> &[u8] {
         &self.0[..]
     }
+
+    /// Creates an `AccountId` of [`DEFAULT_ACCOUNT_LENGTH`] bytes which all equal
+    /// `seed`.
+    ///
+    /// E.g. the seed `1` gives the account id of Alice of the default accounts of
+    /// `ink_env`.
+    pub fn from_seed(seed: u8) -> Self {
+        Self(ink_prelude::vec![seed; DEFAULT_ACCOUNT_LENGTH])
+    }
+
+    /// Parses a hex encoded `AccountId` of [`DEFAULT_ACCOUNT_LENGTH`] bytes, with or
+    /// without a `0x` prefix.
+    ///
+    /// Use [`crate::ext::Engine::account_id_from_hex`] for the account length of the
+    /// chain specification.
+    ///
+    /// # Errors
+    ///
+    /// If `hex` is not hex encoded or decodes to a different number of bytes.
+    pub fn from_hex(hex: &str) -> Result<Self, HexError> {
+        Self::from_hex_with_length(hex, DEFAULT_ACCOUNT_LENGTH)
+    }
+
+    /// Parses a hex encoded `AccountId` of `length` bytes, with or without a `0x`
+    /// prefix.
+    ///
+    /// # Errors
+    ///
+    /// If `hex` is not hex encoded or decodes to a different number of bytes.
+    pub fn from_hex_with_length(hex: &str, length: usize) -> Result<Self, HexError> {
+        decode_hex(hex, length).map(Self)
+    }
+
+    /// Returns the `AccountId` hex encoded with a `0x` prefix.
+    pub fn to_hex(&self) -> String {
+        encode_hex(&self.0)
+    }
+}
+
+/// Same type as the `DefaultEnvironment::Hash` type.
+#[derive(Debug, Default, From, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Hash([u8; 32]);
+
+impl Hash {
+    /// Creates a `Hash` whose bytes all equal `seed`.
+    pub fn from_seed(seed: u8) -> Self {
+        Self([seed; 32])
+    }
+
+    /// Parses a hex encoded `Hash`, with or without a `0x` prefix.
+    ///
+    /// # Errors
+    ///
+    /// If `hex` is not hex encoded or decodes to a number of bytes other than 32.
+    pub fn from_hex(hex: &str) -> Result<Self, HexError> {
+        let bytes = decode_hex(hex, 32)?;
+        let mut hash = [0; 32];
+        hash.copy_from_slice(&bytes);
+        Ok(Self(hash))
+    }
+
+    /// Returns the `Hash` as bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0[..]
+    }
+
+    /// Returns the `Hash` hex encoded with a `0x` prefix.
+    pub fn to_hex(&self) -> String {
+        encode_hex(&self.0)
+    }
+}
+
+/// Errors encountered upon parsing hex encoded account ids and hashes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HexError {
+    /// The string contains a character which is no hex digit.
+    InvalidCharacter(char),
+    /// The string has an odd number of hex digits.
+    OddLength,
+    /// The string decodes to `actual` bytes instead of the `expected` ones.
+    InvalidLength { expected: usize, actual: usize },
+}
+
+/// Returns `bytes` hex encoded with a `0x` prefix.
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::from("0x"), |mut hex, byte| {
+        write!(hex, "{byte:02x}").expect("writing to a string cannot fail");
+        hex
+    })
+}
+
+/// Decodes the hex encoded `hex` of `length` bytes, with or without a `0x` prefix.
+fn decode_hex(hex: &str, length: usize) -> Result<Vec<u8>, HexError> {
+    let bytes = decode_hex_bytes(hex)?;
+    if bytes.len() != length {
+        return Err(HexError::InvalidLength {
+            expected: length,
+            actual: bytes.len(),
+        })
+    }
+    Ok(bytes)
+}
+
+/// Decodes the hex encoded `hex` of any length, with or without a `0x` prefix.
+pub(crate) fn decode_hex_bytes(hex: &str) -> Result<Vec<u8>, HexError> {
+    let digits = hex.strip_prefix("0x").unwrap_or(hex);
+    if let Some(invalid) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(HexError::InvalidCharacter(invalid))
+    }
+    if digits.len() % 2 == 1 {
+        return Err(HexError::OddLength)
+    }
+    Ok((0..digits.len())
+        .step_by(2)
+        .map(|n| {
+            u8::from_str_radix(&digits[n..n + 2], 16)
+                .expect("the digits are valid hex digits")
+        })
+        .collect())
 }
 
 /// Key into the database.
@@ -66,4 +198,8 @@ pub enum AccountError {
     UnexpectedUserAccount,
     #[from(ignore)]
     NoAccountForId(Vec<u8>),
+    /// The all-zero account, which is what unset accounts decode to, was used
+    /// while forbidden, see [`crate::ext::EngineConfig::allow_zero_address`].
+    #[from(ignore)]
+    ZeroAddressForbidden,
 }
@@ -0,0 +1,688 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Execution of contract Wasm blobs with `wasmi`.
+//!
+//! Wasm blobs uploaded via [`Engine::upload_code`] are executed when contracts
+//! instantiated from them are instantiated or called: [`Engine::instantiate`] runs
+//! the `deploy` export and [`Engine::call`] the `call` export of the blob, each in a
+//! new execution frame.
+//!
+//! The `seal_*` imports of the blob are backed by the host functions of the engine.
+//! Only the imports in [`SUPPORTED_IMPORTS`] are provided, calling any other import
+//! traps with a message naming it. A trapping contract fails with
+//! [`Error::CalleeTrapped`] and records a [`Warning::ContractTrapped`] holding the
+//! message.
+
+use crate::{
+    ext::{
+        Engine,
+        Error,
+        TopicsFormat,
+    },
+    test_api::Warning,
+};
+use ink_prelude::collections::BTreeMap;
+use std::{
+    fmt,
+    panic::{
+        self,
+        AssertUnwindSafe,
+    },
+    rc::Rc,
+};
+use wasmi::{
+    core::{
+        HostError,
+        Trap,
+    },
+    Caller,
+    ExternType,
+    Linker,
+    Memory,
+    Module,
+    Store,
+};
+
+/// The imports provided to contract Wasm blobs, as pairs of module and name.
+pub const SUPPORTED_IMPORTS: &[(&str, &str)] = &[
+    ("seal0", "input"),
+    ("seal0", "seal_return"),
+    ("seal0", "caller"),
+    ("seal0", "address"),
+    ("seal0", "balance"),
+    ("seal0", "value_transferred"),
+    ("seal0", "block_number"),
+    ("seal0", "now"),
+    ("seal0", "minimum_balance"),
+    ("seal0", "caller_is_origin"),
+    ("seal0", "deposit_event"),
+    ("seal0", "debug_message"),
+    ("seal0", "hash_blake2_128"),
+    ("seal0", "hash_blake2_256"),
+    ("seal0", "hash_sha2_256"),
+    ("seal0", "hash_keccak_256"),
+    ("seal0", "transfer"),
+    ("seal0", "take_storage"),
+    ("seal1", "get_storage"),
+    ("seal1", "contains_storage"),
+    ("seal1", "clear_storage"),
+    ("seal2", "set_storage"),
+];
+
+/// The flag of `seal_return` reverting the state changes of the execution.
+const REVERT_FLAG: u32 = 1;
+
+/// The return code of host functions for absent storage cells.
+const SENTINEL: u32 = u32::MAX;
+
+/// The uploaded Wasm blobs and the contracts instantiated from them.
+#[derive(Clone, Default)]
+pub(crate) struct WasmCode {
+    /// The blobs by their code hash.
+    blobs: BTreeMap<Vec<u8>, Rc<[u8]>>,
+    /// The code hashes of the contracts by their account.
+    contracts: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl WasmCode {
+    /// Forgets all blobs and contracts.
+    pub(crate) fn reset(&mut self) {
+        self.blobs.clear();
+        self.contracts.clear();
+    }
+}
+
+/// The state of a Wasm execution: the engine and the memory of the contract.
+struct WasmHost {
+    engine: Engine,
+    memory: Option<Memory>,
+}
+
+/// Ends the execution via `seal_return`.
+#[derive(Debug)]
+struct ReturnData {
+    flags: u32,
+    data: Vec<u8>,
+}
+
+impl fmt::Display for ReturnData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "returned with flags {}", self.flags)
+    }
+}
+
+impl HostError for ReturnData {}
+
+/// How a Wasm execution ended.
+enum WasmOutcome {
+    Returned(Vec<u8>),
+    Reverted(Vec<u8>),
+    Trapped(String),
+}
+
+impl Engine {
+    /// Keeps `bytes` for execution under `code_hash` if they are a Wasm blob.
+    pub(crate) fn register_wasm_code(&mut self, code_hash: &[u8], bytes: &[u8]) {
+        if bytes.starts_with(b"\0asm") {
+            self.wasm_code
+                .blobs
+                .insert(code_hash.to_vec(), Rc::from(bytes));
+        }
+    }
+
+    /// Returns the Wasm blob uploaded under `code_hash`, if any.
+    pub(crate) fn wasm_code(&self, code_hash: &[u8]) -> Option<Rc<[u8]>> {
+        self.wasm_code.blobs.get(code_hash).cloned()
+    }
+
+    /// Returns the Wasm blob of the contract at `account_id`, if any.
+    pub(crate) fn wasm_code_of_contract(&self, account_id: &[u8]) -> Option<Rc<[u8]>> {
+        let code_hash = self.wasm_code.contracts.get(account_id)?;
+        self.wasm_code(code_hash)
+    }
+
+    /// Executes the contract at `account_id` from the Wasm blob with `code_hash`.
+    pub(crate) fn register_wasm_contract(&mut self, account_id: &[u8], code_hash: &[u8]) {
+        self.wasm_code
+            .contracts
+            .insert(account_id.to_vec(), code_hash.to_vec());
+    }
+
+    /// Deposits an event emitted by a Wasm contract, which encodes its topics as
+    /// `Vec<[u8; 32]>`, i.e. in the [`TopicsFormat::Concatenated`] format.
+    ///
+    /// Unlike [`Engine::try_deposit_event_with_format`] no
+    /// [`Warning::DeprecatedTopicsFormat`] is recorded, the format is native to
+    /// contracts.
+    pub(crate) fn deposit_contract_event(
+        &mut self,
+        topics: &[u8],
+        data: &[u8],
+    ) -> Result<(), Error> {
+        self.deposit_event_in_format(topics, TopicsFormat::Concatenated, data, false)
+    }
+
+    /// Runs the `export` of `code` in the entered execution frame and exits the frame
+    /// with the outcome.
+    ///
+    /// Returns the data the contract returned with.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::CalleeReverted`] if the contract reverted, carrying the data it
+    ///   returned with.
+    /// - [`Error::CalleeTrapped`] if the contract trapped.
+    ///
+    /// The state changes of the execution are discarded in these cases.
+    pub(crate) fn execute_wasm(
+        &mut self,
+        code: &[u8],
+        export: &str,
+    ) -> Result<Vec<u8>, Error> {
+        let account_id = self
+            .exec_context
+            .callee()
+            .map(|callee| callee.as_bytes().to_vec())
+            .unwrap_or_default();
+        let wasm_engine = wasmi::Engine::default();
+        let host = WasmHost {
+            engine: core::mem::take(self),
+            memory: None,
+        };
+        let mut store = Store::new(&wasm_engine, host);
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+            run_export(&mut store, code, export)
+        }));
+        *self = store.into_data().engine;
+        let outcome = outcome.unwrap_or_else(|payload| panic::resume_unwind(payload));
+        match outcome {
+            WasmOutcome::Returned(data) => {
+                self.return_from_frame(data.clone());
+                Ok(data)
+            }
+            WasmOutcome::Reverted(data) => {
+                self.revert_frame(data.clone());
+                Err(Error::CalleeReverted(data))
+            }
+            WasmOutcome::Trapped(message) => {
+                self.revert_frame(Vec::new());
+                self.debug_info.record_warning(Warning::ContractTrapped {
+                    account_id,
+                    message,
+                });
+                Err(Error::CalleeTrapped)
+            }
+        }
+    }
+}
+
+/// Instantiates `code` in `store` and runs its `export`.
+fn run_export(store: &mut Store<WasmHost>, code: &[u8], export: &str) -> WasmOutcome {
+    let trap = match try_run_export(store, code, export) {
+        Ok(()) => return WasmOutcome::Returned(Vec::new()),
+        Err(trap) => trap,
+    };
+    match trap.downcast_ref::<ReturnData>() {
+        Some(ReturnData { flags, data }) if flags & REVERT_FLAG != 0 => {
+            WasmOutcome::Reverted(data.clone())
+        }
+        Some(ReturnData { data, .. }) => WasmOutcome::Returned(data.clone()),
+        None => WasmOutcome::Trapped(trap.to_string()),
+    }
+}
+
+/// Instantiates `code` in `store` and runs its `export`, failing with the trap
+/// ending the execution.
+fn try_run_export(
+    store: &mut Store<WasmHost>,
+    code: &[u8],
+    export: &str,
+) -> Result<(), Trap> {
+    let module = Module::new(store.engine(), code)
+        .map_err(|error| Trap::new(format!("invalid Wasm blob: {error}")))?;
+    let mut linker = Linker::new(store.engine());
+    link(store, &mut linker, &module)?;
+    let instance = linker
+        .instantiate(&mut *store, &module)
+        .and_then(|instance| instance.start(&mut *store))
+        .map_err(|error| Trap::new(error.to_string()))?;
+    let func = instance
+        .get_typed_func::<(), ()>(&*store, export)
+        .map_err(|_| Trap::new(format!("the contract has no `{export}` export")))?;
+    func.call(&mut *store, ())
+}
+
+/// Defines the imports of `module` in `linker`.
+///
+/// The imported memory is created in `store`. Imported functions which are not
+/// supported trap when called.
+fn link(
+    store: &mut Store<WasmHost>,
+    linker: &mut Linker<WasmHost>,
+    module: &Module,
+) -> Result<(), Trap> {
+    define_host_functions(linker).map_err(|error| Trap::new(error.to_string()))?;
+    for import in module.imports() {
+        let (module_name, name) = (import.module(), import.name());
+        let defined = match import.ty() {
+            ExternType::Memory(ty) => {
+                let memory = Memory::new(&mut *store, *ty)
+                    .map_err(|error| Trap::new(error.to_string()))?;
+                store.data_mut().memory = Some(memory);
+                linker.define(module_name, name, memory)
+            }
+            ExternType::Func(ty) if !SUPPORTED_IMPORTS.contains(&(module_name, name)) => {
+                let message = format!(
+                    "the contract called the import `{module_name}::{name}`, which is \
+                    not supported by the engine"
+                );
+                linker.func_new(module_name, name, ty.clone(), move |_, _, _| {
+                    Err(Trap::new(message.clone()))
+                })
+            }
+            _ => continue,
+        };
+        defined.map_err(|error| Trap::new(error.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Defines the [`SUPPORTED_IMPORTS`] in `linker`.
+fn define_host_functions(
+    linker: &mut Linker<WasmHost>,
+) -> Result<(), wasmi::errors::LinkerError> {
+    linker
+        .func_wrap(
+            "seal0",
+            "input",
+            |mut caller: Caller<WasmHost>, out_ptr: u32, out_len_ptr: u32| {
+                engine_output(
+                    &mut caller,
+                    out_ptr,
+                    out_len_ptr,
+                    "input",
+                    |engine, output| engine.input(output),
+                )
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "seal_return",
+            |caller: Caller<WasmHost>, flags: u32, data_ptr: u32, data_len: u32| {
+                let data = read(&caller, data_ptr, data_len)?;
+                Err::<(), _>(Trap::from(ReturnData { flags, data }))
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "caller",
+            |mut caller: Caller<WasmHost>, out_ptr: u32, out_len_ptr: u32| {
+                engine_output(
+                    &mut caller,
+                    out_ptr,
+                    out_len_ptr,
+                    "caller",
+                    |engine, output| engine.caller(output),
+                )
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "address",
+            |mut caller: Caller<WasmHost>, out_ptr: u32, out_len_ptr: u32| {
+                engine_output(
+                    &mut caller,
+                    out_ptr,
+                    out_len_ptr,
+                    "address",
+                    |engine, output| engine.address(output),
+                )
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "balance",
+            |mut caller: Caller<WasmHost>, out_ptr: u32, out_len_ptr: u32| {
+                engine_output(
+                    &mut caller,
+                    out_ptr,
+                    out_len_ptr,
+                    "balance",
+                    |engine, output| engine.balance(output),
+                )
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "value_transferred",
+            |mut caller: Caller<WasmHost>, out_ptr: u32, out_len_ptr: u32| {
+                engine_output(
+                    &mut caller,
+                    out_ptr,
+                    out_len_ptr,
+                    "value_transferred",
+                    |engine, output| engine.value_transferred(output),
+                )
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "block_number",
+            |mut caller: Caller<WasmHost>, out_ptr: u32, out_len_ptr: u32| {
+                engine_output(
+                    &mut caller,
+                    out_ptr,
+                    out_len_ptr,
+                    "block_number",
+                    |engine, output| engine.block_number(output),
+                )
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "now",
+            |mut caller: Caller<WasmHost>, out_ptr: u32, out_len_ptr: u32| {
+                engine_output(
+                    &mut caller,
+                    out_ptr,
+                    out_len_ptr,
+                    "now",
+                    |engine, output| engine.block_timestamp(output),
+                )
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "minimum_balance",
+            |mut caller: Caller<WasmHost>, out_ptr: u32, out_len_ptr: u32| {
+                engine_output(
+                    &mut caller,
+                    out_ptr,
+                    out_len_ptr,
+                    "minimum_balance",
+                    |engine, output| engine.minimum_balance(output),
+                )
+            },
+        )?
+        .func_wrap("seal0", "caller_is_origin", |caller: Caller<WasmHost>| {
+            u32::from(caller.data().engine.caller_is_origin())
+        })?
+        .func_wrap(
+            "seal0",
+            "deposit_event",
+            |mut caller: Caller<WasmHost>,
+             topics_ptr: u32,
+             topics_len: u32,
+             data_ptr: u32,
+             data_len: u32| {
+                let topics = read(&caller, topics_ptr, topics_len)?;
+                let data = read(&caller, data_ptr, data_len)?;
+                caller
+                    .data_mut()
+                    .engine
+                    .deposit_contract_event(&topics, &data)
+                    .map_err(|error| host_error("deposit_event", error))
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "debug_message",
+            |mut caller: Caller<WasmHost>, str_ptr: u32, str_len: u32| {
+                let message = read(&caller, str_ptr, str_len)?;
+                let engine = &mut caller.data_mut().engine;
+                engine.debug_message(&String::from_utf8_lossy(&message));
+                Ok::<_, Trap>(
+                    if engine.config.println_enabled {
+                        0
+                    } else {
+                        Error::LoggingDisabled.code()
+                    },
+                )
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "hash_blake2_128",
+            |mut caller: Caller<WasmHost>,
+             input_ptr: u32,
+             input_len: u32,
+             out_ptr: u32| {
+                let mut output = [0; 16];
+                Engine::hash_blake2_128(
+                    &read(&caller, input_ptr, input_len)?,
+                    &mut output,
+                );
+                write(&mut caller, out_ptr, &output)
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "hash_blake2_256",
+            |mut caller: Caller<WasmHost>,
+             input_ptr: u32,
+             input_len: u32,
+             out_ptr: u32| {
+                let mut output = [0; 32];
+                Engine::hash_blake2_256(
+                    &read(&caller, input_ptr, input_len)?,
+                    &mut output,
+                );
+                write(&mut caller, out_ptr, &output)
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "hash_sha2_256",
+            |mut caller: Caller<WasmHost>,
+             input_ptr: u32,
+             input_len: u32,
+             out_ptr: u32| {
+                let mut output = [0; 32];
+                Engine::hash_sha2_256(&read(&caller, input_ptr, input_len)?, &mut output);
+                write(&mut caller, out_ptr, &output)
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "hash_keccak_256",
+            |mut caller: Caller<WasmHost>,
+             input_ptr: u32,
+             input_len: u32,
+             out_ptr: u32| {
+                let mut output = [0; 32];
+                Engine::hash_keccak_256(
+                    &read(&caller, input_ptr, input_len)?,
+                    &mut output,
+                );
+                write(&mut caller, out_ptr, &output)
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "transfer",
+            |mut caller: Caller<WasmHost>,
+             account_ptr: u32,
+             account_len: u32,
+             value_ptr: u32,
+             value_len: u32| {
+                let account_id = read(&caller, account_ptr, account_len)?;
+                let value = read(&caller, value_ptr, value_len)?;
+                match caller.data_mut().engine.transfer(&account_id, &value) {
+                    Ok(()) => Ok(0),
+                    Err(Error::TransferFailed) => Ok(Error::TransferFailed.code()),
+                    Err(error) => Err(host_error("transfer", error)),
+                }
+            },
+        )?
+        .func_wrap(
+            "seal0",
+            "take_storage",
+            |mut caller: Caller<WasmHost>,
+             key_ptr: u32,
+             key_len: u32,
+             out_ptr: u32,
+             out_len_ptr: u32| {
+                let key = read(&caller, key_ptr, key_len)?;
+                match caller.data_mut().engine.take_storage(&key) {
+                    Ok(value) => {
+                        write_output(&mut caller, out_ptr, out_len_ptr, &value)?;
+                        Ok(0)
+                    }
+                    Err(error @ Error::KeyNotFound(_)) => Ok(error.code()),
+                    Err(error) => Err(host_error("take_storage", error)),
+                }
+            },
+        )?
+        .func_wrap(
+            "seal1",
+            "get_storage",
+            |mut caller: Caller<WasmHost>,
+             key_ptr: u32,
+             key_len: u32,
+             out_ptr: u32,
+             out_len_ptr: u32| {
+                let key = read(&caller, key_ptr, key_len)?;
+                match caller.data_mut().engine.get_storage(&key) {
+                    Ok(value) => {
+                        let value = value.to_vec();
+                        write_output(&mut caller, out_ptr, out_len_ptr, &value)?;
+                        Ok(0)
+                    }
+                    Err(error @ Error::KeyNotFound(_)) => Ok(error.code()),
+                    Err(error) => Err(host_error("get_storage", error)),
+                }
+            },
+        )?
+        .func_wrap(
+            "seal1",
+            "contains_storage",
+            |mut caller: Caller<WasmHost>, key_ptr: u32, key_len: u32| {
+                let key = read(&caller, key_ptr, key_len)?;
+                let size = caller
+                    .data_mut()
+                    .engine
+                    .try_contains_storage(&key)
+                    .map_err(|error| host_error("contains_storage", error))?;
+                Ok::<_, Trap>(size.unwrap_or(SENTINEL))
+            },
+        )?
+        .func_wrap(
+            "seal1",
+            "clear_storage",
+            |mut caller: Caller<WasmHost>, key_ptr: u32, key_len: u32| {
+                let key = read(&caller, key_ptr, key_len)?;
+                let size = caller
+                    .data_mut()
+                    .engine
+                    .try_clear_storage(&key)
+                    .map_err(|error| host_error("clear_storage", error))?;
+                Ok::<_, Trap>(size.unwrap_or(SENTINEL))
+            },
+        )?
+        .func_wrap(
+            "seal2",
+            "set_storage",
+            |mut caller: Caller<WasmHost>,
+             key_ptr: u32,
+             key_len: u32,
+             value_ptr: u32,
+             value_len: u32| {
+                let key = read(&caller, key_ptr, key_len)?;
+                let value = read(&caller, value_ptr, value_len)?;
+                caller
+                    .data_mut()
+                    .engine
+                    .try_set_storage(&key, &value)
+                    .map(|size| size.unwrap_or(SENTINEL))
+                    .map_err(|error| host_error("set_storage", error))
+            },
+        )?;
+    Ok(())
+}
+
+/// Returns the trap of the host function `name` failing with `error`.
+fn host_error(name: &str, error: Error) -> Trap {
+    Trap::new(format!("the host function `{name}` failed: {error:?}"))
+}
+
+/// Returns the memory of the contract.
+fn memory(caller: &Caller<WasmHost>) -> Result<Memory, Trap> {
+    caller
+        .data()
+        .memory
+        .ok_or_else(|| Trap::new("the contract imports no memory"))
+}
+
+/// Reads `len` bytes at `ptr` from the memory of the contract.
+fn read(caller: &Caller<WasmHost>, ptr: u32, len: u32) -> Result<Vec<u8>, Trap> {
+    let mut buffer = vec![0; len as usize];
+    memory(caller)?
+        .read(caller, ptr as usize, &mut buffer)
+        .map_err(|error| Trap::new(error.to_string()))?;
+    Ok(buffer)
+}
+
+/// Writes `data` at `ptr` to the memory of the contract.
+fn write(caller: &mut Caller<WasmHost>, ptr: u32, data: &[u8]) -> Result<(), Trap> {
+    memory(caller)?
+        .write(caller, ptr as usize, data)
+        .map_err(|error| Trap::new(error.to_string()))
+}
+
+/// Writes `data` to the output buffer at `out_ptr` and its length to `out_len_ptr`.
+///
+/// The contract passes the capacity of the buffer at `out_len_ptr`. It traps if
+/// `data` exceed the capacity, like on-chain.
+fn write_output(
+    caller: &mut Caller<WasmHost>,
+    out_ptr: u32,
+    out_len_ptr: u32,
+    data: &[u8],
+) -> Result<(), Trap> {
+    let capacity = output_capacity(caller, out_len_ptr)?;
+    if data.len() > capacity {
+        return Err(Trap::new(format!(
+            "the output buffer of {capacity} bytes is too small for {} bytes",
+            data.len()
+        )))
+    }
+    write(caller, out_ptr, data)?;
+    write(caller, out_len_ptr, &(data.len() as u32).to_le_bytes())
+}
+
+/// Writes the output of the host function `name` of the engine to the output buffer
+/// at `out_ptr`, see [`write_output`].
+fn engine_output(
+    caller: &mut Caller<WasmHost>,
+    out_ptr: u32,
+    out_len_ptr: u32,
+    name: &str,
+    f: impl FnOnce(&mut Engine, &mut &mut [u8]) -> Result<usize, crate::Error>,
+) -> Result<(), Trap> {
+    let mut buffer = vec![0; output_capacity(caller, out_len_ptr)?];
+    let len =
+        f(&mut caller.data_mut().engine, &mut &mut buffer[..]).map_err(|error| {
+            Trap::new(format!("the host function `{name}` failed: {error:?}"))
+        })?;
+    write(caller, out_ptr, &buffer[..len])?;
+    write(caller, out_len_ptr, &(len as u32).to_le_bytes())
+}
+
+/// Reads the capacity of an output buffer from `out_len_ptr`.
+fn output_capacity(caller: &Caller<WasmHost>, out_len_ptr: u32) -> Result<usize, Trap> {
+    let capacity = read(caller, out_len_ptr, 4)?;
+    let capacity = <[u8; 4]>::try_from(&capacity[..]).expect("read 4 bytes");
+    Ok(u32::from_le_bytes(capacity) as usize)
+}
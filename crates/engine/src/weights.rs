@@ -0,0 +1,200 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The gas charged by the host functions of the engine.
+//!
+//! Like on-chain, host functions which access storage, deposit events, hash, call,
+//! transfer or query the gas left charge their weight from the [`EngineConfig::weights`]
+//! of the engine, in addition to the gas consumed via [`Engine::consume_gas`]. The
+//! charges count towards the consumed gas, see [`Engine::gas_consumed`]. Running out of
+//! gas does not trap, except for hashing with [`EngineConfig::gas_metering`] enabled.
+//!
+//! [`EngineConfig::weights`]: crate::ext::EngineConfig::weights
+//! [`EngineConfig::gas_metering`]: crate::ext::EngineConfig::gas_metering
+
+use crate::ext::{
+    Engine,
+    Error,
+};
+
+/// The weights of the host functions.
+///
+/// A charge is the base weight plus the weight per unit times the number of units,
+/// e.g. bytes of a stored value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeightTable {
+    /// The base weight of writing or removing a storage cell.
+    pub storage_write_base: u64,
+    /// The weight of every written or removed byte of a storage value.
+    pub storage_write_per_byte: u64,
+    /// The base weight of reading a storage cell.
+    pub storage_read_base: u64,
+    /// The weight of every read byte of a storage value.
+    pub storage_read_per_byte: u64,
+    /// The base weight of depositing an event.
+    pub event_base: u64,
+    /// The weight of every topic of an event.
+    pub event_per_topic: u64,
+    /// The weight of every byte of the data of an event.
+    pub event_per_byte: u64,
+    /// The base weight of hashing.
+    pub hash_base: u64,
+    /// The weight of every hashed byte.
+    pub hash_per_byte: u64,
+    /// The base weight of calling a contract.
+    pub call_base: u64,
+    /// The base weight of transferring value.
+    pub transfer_base: u64,
+    /// The base weight of querying the gas left.
+    pub gas_left_base: u64,
+}
+
+impl WeightTable {
+    /// A table in which every host function is free, hence only the gas consumed via
+    /// [`Engine::consume_gas`] counts.
+    pub const fn zero() -> Self {
+        Self {
+            storage_write_base: 0,
+            storage_write_per_byte: 0,
+            storage_read_base: 0,
+            storage_read_per_byte: 0,
+            event_base: 0,
+            event_per_topic: 0,
+            event_per_byte: 0,
+            hash_base: 0,
+            hash_per_byte: 0,
+            call_base: 0,
+            transfer_base: 0,
+            gas_left_base: 0,
+        }
+    }
+
+    /// Returns the weights by their names in the order of declaration.
+    pub fn entries(&self) -> [(&'static str, u64); 12] {
+        [
+            ("storage_write_base", self.storage_write_base),
+            ("storage_write_per_byte", self.storage_write_per_byte),
+            ("storage_read_base", self.storage_read_base),
+            ("storage_read_per_byte", self.storage_read_per_byte),
+            ("event_base", self.event_base),
+            ("event_per_topic", self.event_per_topic),
+            ("event_per_byte", self.event_per_byte),
+            ("hash_base", self.hash_base),
+            ("hash_per_byte", self.hash_per_byte),
+            ("call_base", self.call_base),
+            ("transfer_base", self.transfer_base),
+            ("gas_left_base", self.gas_left_base),
+        ]
+    }
+}
+
+/// The default weights approximate the `ref_time` weights of `pallet-contracts` in
+/// picoseconds.
+impl Default for WeightTable {
+    fn default() -> Self {
+        Self {
+            storage_write_base: 250_000_000,
+            storage_write_per_byte: 300_000,
+            storage_read_base: 150_000_000,
+            storage_read_per_byte: 50_000,
+            event_base: 100_000_000,
+            event_per_topic: 120_000_000,
+            event_per_byte: 10_000,
+            hash_base: 10_000_000,
+            hash_per_byte: 3_000,
+            call_base: 400_000_000,
+            transfer_base: 200_000_000,
+            gas_left_base: 5_000_000,
+        }
+    }
+}
+
+/// Returns `base` plus `per_unit` times `units`, saturating.
+fn weight(base: u64, per_unit: u64, units: usize) -> u64 {
+    let units = u64::try_from(units).unwrap_or(u64::MAX);
+    base.saturating_add(per_unit.saturating_mul(units))
+}
+
+impl Engine {
+    /// Charges the weight of hashing `input_len` bytes.
+    ///
+    /// The hashing functions of the engine have no access to the engine, hence the
+    /// caller charges them. The weight is charged even if it exceeds the gas
+    /// remaining, use [`Engine::try_charge_hashing`] to respect the gas limit.
+    pub fn charge_hashing(&mut self, input_len: usize) {
+        let weights = self.config.weights;
+        self.consume_gas(weight(weights.hash_base, weights.hash_per_byte, input_len));
+    }
+
+    /// Charges the weight of hashing `input_len` bytes, see [`Engine::try_hash`].
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::OutOfGas`] if [`EngineConfig::gas_metering`] is enabled and the gas
+    ///   remaining does not cover the weight. No gas is charged in this case.
+    ///
+    /// [`EngineConfig::gas_metering`]: crate::ext::EngineConfig::gas_metering
+    pub fn try_charge_hashing(&mut self, input_len: usize) -> Result<(), Error> {
+        let weights = self.config.weights;
+        let weight = weight(weights.hash_base, weights.hash_per_byte, input_len);
+        if self.config.gas_metering && weight > self.gas_remaining() {
+            return Err(Error::OutOfGas)
+        }
+        self.consume_gas(weight);
+        Ok(())
+    }
+
+    /// Charges the weight of writing or removing a storage value of `len` bytes.
+    pub(crate) fn charge_storage_write(&mut self, len: usize) {
+        let weights = self.config.weights;
+        self.consume_gas(weight(
+            weights.storage_write_base,
+            weights.storage_write_per_byte,
+            len,
+        ));
+    }
+
+    /// Charges the weight of reading a storage value of `len` bytes.
+    pub(crate) fn charge_storage_read(&mut self, len: usize) {
+        let weights = self.config.weights;
+        self.consume_gas(weight(
+            weights.storage_read_base,
+            weights.storage_read_per_byte,
+            len,
+        ));
+    }
+
+    /// Charges the weight of depositing an event with `topics_count` topics and
+    /// `data_len` bytes of data.
+    pub(crate) fn charge_event(&mut self, topics_count: usize, data_len: usize) {
+        let weights = self.config.weights;
+        let topics = weight(weights.event_base, weights.event_per_topic, topics_count);
+        self.consume_gas(weight(topics, weights.event_per_byte, data_len));
+    }
+
+    /// Charges the base weight of calling a contract.
+    pub(crate) fn charge_call(&mut self) {
+        self.consume_gas(self.config.weights.call_base);
+    }
+
+    /// Charges the base weight of transferring value.
+    pub(crate) fn charge_transfer(&mut self) {
+        self.consume_gas(self.config.weights.transfer_base);
+    }
+
+    /// Charges the base weight of querying the gas left.
+    pub(crate) fn charge_gas_left(&mut self) {
+        self.consume_gas(self.config.weights.gas_left_base);
+    }
+}
@@ -0,0 +1,24 @@
+;; A contract emitting an anonymous event and an event with one topic on each call,
+;; the source of `events.wasm`.
+;;
+;; Topics are encoded like contracts do, i.e. the compact encoded number of topics
+;; followed by the concatenated topics.
+;;
+;; Memory layout:
+;;
+;; - 0: the topics of the anonymous event, no topics
+;; - 16..49: the topics of the other event, one topic of 32 bytes `7`
+;; - 64: the data of both events
+(module
+  (import "seal0" "deposit_event" (func $deposit_event (param i32 i32 i32 i32)))
+  (import "env" "memory" (memory 1 16))
+
+  (data (i32.const 16) "\04\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07\07")
+  (data (i32.const 64) "\01")
+
+  (func (export "deploy"))
+
+  (func (export "call")
+    (call $deposit_event (i32.const 0) (i32.const 1) (i32.const 64) (i32.const 1))
+    (call $deposit_event (i32.const 16) (i32.const 33) (i32.const 64) (i32.const 1)))
+)
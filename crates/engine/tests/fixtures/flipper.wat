@@ -0,0 +1,60 @@
+;; A minimal flipper contract, the source of `flipper.wasm`.
+;;
+;; Messages (selectors as in the ink! flipper example):
+;;
+;; - `new(init_value: bool)`: 0x9bae9d5e, the constructor
+;; - `flip()`: 0x633aa551
+;; - `get() -> bool`: 0x2f865bd9
+;;
+;; The value is stored under the key `[0; 4]`. Messages return their SCALE encoded
+;; `Result` like ink! messages do. Unknown selectors trap.
+;;
+;; Memory layout:
+;;
+;; - 0..4: the storage key
+;; - 16..20: the length of the buffer passed to the host
+;; - 32..: the input
+;; - 64: the stored value
+;; - 96..: the output
+(module
+  (import "seal0" "input" (func $input (param i32 i32)))
+  (import "seal0" "seal_return" (func $seal_return (param i32 i32 i32)))
+  (import "seal1" "get_storage" (func $get_storage (param i32 i32 i32 i32) (result i32)))
+  (import "seal2" "set_storage" (func $set_storage (param i32 i32 i32 i32) (result i32)))
+  (import "env" "memory" (memory 1 16))
+
+  (func $read_input (result i32)
+    (i32.store (i32.const 16) (i32.const 32))
+    (call $input (i32.const 32) (i32.const 16))
+    (i32.load (i32.const 32)))
+
+  (func $store_value (param $value i32)
+    (i32.store8 (i32.const 64) (local.get $value))
+    (drop (call $set_storage (i32.const 0) (i32.const 4) (i32.const 64) (i32.const 1))))
+
+  (func $load_value (result i32)
+    (i32.store (i32.const 16) (i32.const 1))
+    (if (call $get_storage (i32.const 0) (i32.const 4) (i32.const 64) (i32.const 16))
+      (then unreachable))
+    (i32.load8_u (i32.const 64)))
+
+  (func (export "deploy")
+    (if (i32.ne (call $read_input) (i32.const 0x5e9dae9b))
+      (then unreachable))
+    (call $store_value (i32.load8_u (i32.const 36))))
+
+  (func (export "call")
+    (local $selector i32)
+    (local.set $selector (call $read_input))
+    (if (i32.eq (local.get $selector) (i32.const 0x51a53a63))
+      (then
+        (call $store_value (i32.eqz (call $load_value)))
+        (i32.store8 (i32.const 96) (i32.const 0))
+        (call $seal_return (i32.const 0) (i32.const 96) (i32.const 1))))
+    (if (i32.eq (local.get $selector) (i32.const 0xd95b862f))
+      (then
+        (i32.store8 (i32.const 96) (i32.const 0))
+        (i32.store8 (i32.const 97) (call $load_value))
+        (call $seal_return (i32.const 0) (i32.const 96) (i32.const 2))))
+    unreachable)
+)
@@ -0,0 +1,11 @@
+;; A contract calling a host function the engine does not implement, the source of
+;; `unimplemented_import.wasm`.
+(module
+  (import "seal0" "weight_to_fee" (func $weight_to_fee (param i64 i32 i32)))
+  (import "env" "memory" (memory 1 16))
+
+  (func (export "deploy"))
+
+  (func (export "call")
+    (call $weight_to_fee (i64.const 0) (i32.const 0) (i32.const 0)))
+)
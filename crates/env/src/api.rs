@@ -41,6 +41,7 @@ use crate::{
     },
     types::Gas,
     Environment,
+    Error,
     Result,
 };
 use ink_storage_traits::Storable;
@@ -74,6 +75,21 @@ where
     })
 }
 
+/// Returns `Ok` if no value was transferred to the contract execution.
+///
+/// # Errors
+///
+/// [`Error::PaidUnpayableMessage`] if some value was transferred.
+pub fn deny_payment<E>() -> Result<()>
+where
+    E: Environment,
+{
+    if transferred_value::<E>() != E::Balance::from(0_u32) {
+        return Err(Error::PaidUnpayableMessage)
+    }
+    Ok(())
+}
+
 /// Returns the price for the specified amount of gas.
 ///
 /// # Errors
@@ -210,6 +226,7 @@ where
 /// # Errors
 ///
 /// - If the decoding of the typed value failed (`KeyNotFound`)
+/// - If the stored value does not fit into the static buffer (`BufferTooSmall`)
 pub fn get_contract_storage<K, R>(key: &K) -> Result<Option<R>>
 where
     K: scale::Encode,
@@ -319,9 +336,16 @@ where
     Args: scale::Encode,
     R: scale::Decode,
 {
-    <EnvInstance as OnInstance>::on_instance(|instance| {
-        TypedEnvBackend::invoke_contract::<E, Args, R>(instance, params)
-    })
+    #[cfg(feature = "std")]
+    {
+        crate::engine::off_chain::invoke_contract(params)
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        <EnvInstance as OnInstance>::on_instance(|instance| {
+            TypedEnvBackend::invoke_contract::<E, Args, R>(instance, params)
+        })
+    }
 }
 
 /// Invokes a contract message via delegate call and returns its result.
@@ -344,9 +368,16 @@ where
     Args: scale::Encode,
     R: scale::Decode,
 {
-    <EnvInstance as OnInstance>::on_instance(|instance| {
-        TypedEnvBackend::invoke_contract_delegate::<E, Args, R>(instance, params)
-    })
+    #[cfg(feature = "std")]
+    {
+        crate::engine::off_chain::invoke_contract_delegate(params)
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        <EnvInstance as OnInstance>::on_instance(|instance| {
+            TypedEnvBackend::invoke_contract_delegate::<E, Args, R>(instance, params)
+        })
+    }
 }
 
 /// Instantiates another contract.
@@ -519,6 +550,16 @@ pub fn debug_message(message: &str) {
     })
 }
 
+/// Appends the formatted message to the debug message buffer.
+///
+/// Messages without arguments are forwarded as they are, others are only formatted if
+/// debug messages are enabled, see [`debug_print`](crate::debug_print).
+pub fn debug_message_fmt(args: core::fmt::Arguments<'_>) {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        EnvBackend::debug_message_fmt(instance, args)
+    })
+}
+
 /// Conducts the crypto hash of the given input and stores the result in `output`.
 ///
 /// # Example
@@ -729,6 +770,25 @@ where
     })
 }
 
+/// Returns the number of times the current contract exists on the call stack in
+/// addition to the calling instance.
+///
+/// Returns `0` without reentrancy. Delegate calls execute in the context of the
+/// calling contract and do not count.
+///
+/// # Note
+///
+/// This is an unstable host function of `pallet-contracts`. Its behavior can change
+/// without further notice, use it only for testing.
+pub fn reentrance_count<E>() -> u32
+where
+    E: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        TypedEnvBackend::reentrance_count::<E>(instance)
+    })
+}
+
 /// Replace the contract code at the specified address with new code.
 ///
 /// # Note
@@ -852,9 +912,10 @@ where
 /// - If the runtime doesn't allow for the contract unstable feature.
 /// - If the runtime doesn't allow for dispatching this call from a contract.
 ///
-/// # Panics
+/// # Note
 ///
-/// Panics in the off-chain environment.
+/// The off-chain environment determines the outcome via the handler registered with
+/// `ink_env::test::register_runtime_call_handler`, without one every call fails.
 pub fn call_runtime<E, Call>(call: &Call) -> Result<()>
 where
     E: Environment,
@@ -129,6 +129,17 @@ pub trait EnvBackend {
     /// the case when the code is executing on-chain, then this will have no effect.
     fn debug_message(&mut self, content: &str);
 
+    /// Emit a custom debug message formatted from `args`, see
+    /// [`EnvBackend::debug_message`].
+    ///
+    /// Messages without arguments are emitted without being formatted.
+    fn debug_message_fmt(&mut self, args: core::fmt::Arguments<'_>) {
+        match args.as_str() {
+            Some(content) => self.debug_message(content),
+            None => self.debug_message(&ink_prelude::format!("{args}")),
+        }
+    }
+
     /// Conducts the crypto hash of the given input and stores the result in `output`.
     fn hash_bytes<H>(&mut self, input: &[u8], output: &mut <H as HashOutput>::Type)
     where
@@ -316,6 +327,9 @@ pub trait TypedEnvBackend: EnvBackend {
     /// **This will call into the latest `call_v2` host function.**
     ///
     /// For more details visit: [`invoke_contract`][`crate::invoke_contract`]
+    // The off-chain environment executes calls without holding on to the environment
+    // instance, since the called contract interacts with it.
+    #[cfg_attr(feature = "std", allow(dead_code))]
     fn invoke_contract<E, Args, R>(
         &mut self,
         call_data: &CallParams<E, Call<E>, Args, R>,
@@ -331,6 +345,9 @@ pub trait TypedEnvBackend: EnvBackend {
     ///
     /// For more details visit:
     /// [`invoke_contract_delegate`][`crate::invoke_contract_delegate`]
+    // The off-chain environment executes delegate calls without holding on to the
+    // environment instance, since the delegated code interacts with it.
+    #[cfg_attr(feature = "std", allow(dead_code))]
     fn invoke_contract_delegate<E, Args, R>(
         &mut self,
         call_data: &CallParams<E, DelegateCall<E>, Args, R>,
@@ -413,6 +430,16 @@ pub trait TypedEnvBackend: EnvBackend {
     where
         E: Environment;
 
+    /// Returns the number of times the current contract exists on the call stack in
+    /// addition to the calling instance.
+    ///
+    /// # Note
+    ///
+    /// For more details visit: [`reentrance_count`][`crate::reentrance_count`]
+    fn reentrance_count<E>(&mut self) -> u32
+    where
+        E: Environment;
+
     /// Retrieves the code hash of the contract at the given `account` id.
     ///
     /// # Note
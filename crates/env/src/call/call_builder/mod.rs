@@ -66,9 +66,10 @@ where
 ///
 /// # Example
 ///
-/// **Note:** The shown examples panic because there is currently no cross-calling
-///           support in the off-chain testing environment. However, this code
-///           should work fine in on-chain environments.
+/// **Note:** The shown examples panic because no contract is registered at the
+///           callee in the off-chain testing environment, see
+///           `ink_env::test::register_contract`. However, this code should work fine
+///           in on-chain environments.
 ///
 /// ## Example 1: No Return Value
 ///
@@ -138,9 +139,10 @@ where
 ///
 /// ## Example 3: Delegate call
 ///
-/// **Note:** The shown example panics because there is currently no delegate calling
-///           support in the off-chain testing environment. However, this code
-///           should work fine in on-chain environments.
+/// **Note:** The shown example panics because no code is registered under the code
+///           hash in the off-chain testing environment, see
+///           `ink_env::test::register_code`. However, this code should work fine in
+///           on-chain environments.
 ///
 /// ```should_panic
 /// # use ::ink_env::{
@@ -171,9 +173,10 @@ where
 /// If you want to handle these errors use the [`CallBuilder::try_invoke`] methods instead
 /// of the [`CallBuilder::invoke`] ones.
 ///
-/// **Note:** The shown examples panic because there is currently no cross-calling
-///           support in the off-chain testing environment. However, this code
-///           should work fine in on-chain environments.
+/// **Note:** The shown examples panic because no contract is registered at the
+///           callee in the off-chain testing environment, see
+///           `ink_env::test::register_contract`. However, this code should work fine
+///           in on-chain environments.
 ///
 /// ## Example: Handling a `LangError`
 ///
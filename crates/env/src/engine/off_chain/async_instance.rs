@@ -0,0 +1,97 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Task-local off-chain environments for async tests.
+//!
+//! By default the off-chain environment is local to the thread, hence tasks of an
+//! async runtime interleaving on the same thread share it. Within
+//! [`EngineGuard::scope`] the environment functions use an engine of their own
+//! instead, local to the task awaiting the scope.
+
+use super::{
+    EnvInstance,
+    InstanceCell,
+};
+use core::future::Future;
+use ink_engine::ext::Engine;
+
+tokio::task_local! {
+    static TASK_INSTANCE: InstanceCell;
+}
+
+/// Scopes an engine to an async task.
+///
+/// # Example
+///
+/// ```ignore
+/// EngineGuard::scope(Engine::new(), async {
+///     ink_env::test::set_caller::<DefaultEnvironment>(accounts.bob);
+///     // ...
+/// })
+/// .await;
+/// ```
+pub struct EngineGuard;
+
+impl EngineGuard {
+    /// Runs `f` with an off-chain environment backed by `engine`.
+    ///
+    /// All uses of the off-chain environment while polling `f` operate on `engine`,
+    /// also across await points in between which other tasks use their own engine
+    /// or the one of the thread. Scopes may be nested, the innermost one applies.
+    ///
+    /// # Panics
+    ///
+    /// If the `ink_engine` in use lacks host functions of the off-chain environment.
+    pub async fn scope<F>(engine: Engine, f: F) -> F::Output
+    where
+        F: Future,
+    {
+        let instance = InstanceCell::new(EnvInstance::with_engine(engine));
+        TASK_INSTANCE.scope(instance, f).await
+    }
+}
+
+/// Returns whether the current task is within [`EngineGuard::scope`].
+pub(super) fn in_scope() -> bool {
+    TASK_INSTANCE.try_with(|_| ()).is_ok()
+}
+
+/// Invokes `f` with the environment of the current task.
+///
+/// # Panics
+///
+/// If the current task is not within [`EngineGuard::scope`] or if its environment
+/// is already in use.
+pub(super) fn on_task_instance<F, R>(f: F) -> R
+where
+    F: FnOnce(&mut EnvInstance) -> R,
+{
+    TASK_INSTANCE.with(|instance| instance.with(f))
+}
+
+/// Returns the debug dump of the engine of the current task.
+///
+/// Returns `None` if the environment is in use.
+pub(super) fn engine_debug_dump() -> Option<String> {
+    TASK_INSTANCE
+        .try_with(|instance| {
+            instance
+                .instance
+                .try_borrow()
+                .ok()
+                .map(|instance| instance.engine.debug_dump())
+        })
+        .ok()
+        .flatten()
+}
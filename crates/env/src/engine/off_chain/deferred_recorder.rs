@@ -0,0 +1,136 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recorders whose hooks are deferred until the off-chain environment is released,
+//! see [`test_api::set_deferred_recorder`].
+//!
+//! The hooks of a recorder are invoked by the engine while the environment is in use,
+//! hence a hook using the test API, e.g. to read a balance, would use the
+//! environment reentrantly and panic. A [`DeferredRecorder`] queues the hooks instead
+//! and invokes them in order once the operation using the environment completes.
+//!
+//! [`test_api::set_deferred_recorder`]: super::test_api::set_deferred_recorder
+
+use core::cell::{
+    Cell,
+    RefCell,
+};
+use ink_engine::{
+    recorder::Recorder,
+    test_api::EmittedEvent,
+    AccountId,
+};
+use std::{
+    collections::VecDeque,
+    rc::Rc,
+};
+
+/// A queued recorder hook.
+type Hook = Box<dyn FnOnce()>;
+
+thread_local!(
+    /// The recorder hooks queued while the environment is in use.
+    static DEFERRED: RefCell<VecDeque<Hook>> = RefCell::new(VecDeque::new());
+    /// Whether the queued hooks are being invoked.
+    static RUNNING: Cell<bool> = const { Cell::new(false) };
+);
+
+/// Invokes the queued recorder hooks in order.
+///
+/// Hooks queued by the hooks themselves are invoked by the same run, after the
+/// ones queued before.
+pub(super) fn run_deferred_hooks() {
+    if RUNNING.with(|running| running.replace(true)) {
+        return
+    }
+    while let Some(hook) = DEFERRED.with(|deferred| deferred.borrow_mut().pop_front()) {
+        hook();
+    }
+    RUNNING.with(|running| running.set(false));
+}
+
+/// Forwards the statistics to a recorder once the environment is released.
+///
+/// Clones of the recorder, e.g. for clones of the engine, forward to the same
+/// recorder.
+#[derive(Clone)]
+pub(super) struct DeferredRecorder {
+    recorder: Rc<RefCell<Box<dyn Recorder>>>,
+}
+
+impl DeferredRecorder {
+    pub(super) fn new(recorder: Box<dyn Recorder>) -> Self {
+        Self {
+            recorder: Rc::new(RefCell::new(recorder)),
+        }
+    }
+
+    /// Queues `hook` to be invoked with the recorder.
+    fn defer<F>(&self, hook: F)
+    where
+        F: FnOnce(&mut dyn Recorder) + 'static,
+    {
+        let recorder = Rc::clone(&self.recorder);
+        DEFERRED.with(|deferred| {
+            deferred
+                .borrow_mut()
+                .push_back(Box::new(move || hook(&mut **recorder.borrow_mut())))
+        });
+    }
+}
+
+impl Recorder for DeferredRecorder {
+    fn inc_reads(&mut self, account_id: &AccountId) {
+        let account_id = account_id.clone();
+        self.defer(move |recorder| recorder.inc_reads(&account_id));
+    }
+
+    fn inc_writes(&mut self, account_id: &AccountId) {
+        let account_id = account_id.clone();
+        self.defer(move |recorder| recorder.inc_writes(&account_id));
+    }
+
+    fn inc_noop_clears(&mut self, account_id: &AccountId) {
+        let account_id = account_id.clone();
+        self.defer(move |recorder| recorder.inc_noop_clears(&account_id));
+    }
+
+    fn inc_new_cells(&mut self, account_id: &AccountId) {
+        let account_id = account_id.clone();
+        self.defer(move |recorder| recorder.inc_new_cells(&account_id));
+    }
+
+    fn record_batch_write(&mut self, account_id: &AccountId, count: usize) {
+        let account_id = account_id.clone();
+        self.defer(move |recorder| recorder.record_batch_write(&account_id, count));
+    }
+
+    fn record_event(&mut self, emitter: Option<&AccountId>, event: &EmittedEvent) {
+        let (emitter, event) = (emitter.cloned(), event.clone());
+        self.defer(move |recorder| recorder.record_event(emitter.as_ref(), &event));
+    }
+
+    fn record_println(&mut self, message: &str) {
+        let message = message.to_owned();
+        self.defer(move |recorder| recorder.record_println(&message));
+    }
+
+    fn reset(&mut self) {
+        self.defer(|recorder| recorder.reset());
+    }
+
+    fn clone_box(&self) -> Box<dyn Recorder> {
+        Box::new(self.clone())
+    }
+}
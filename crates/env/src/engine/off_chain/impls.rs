@@ -12,7 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::EnvInstance;
+use super::{
+    EnvInstance,
+    OffChainError,
+};
 use crate::{
     call::{
         Call,
@@ -25,7 +28,9 @@ use crate::{
         LimitParamsV1,
         LimitParamsV2,
     },
+    engine::OnInstance,
     event::{
+        topic_from_encoded,
         Event,
         TopicsBuilderBackend,
     },
@@ -37,18 +42,21 @@ use crate::{
         Keccak256,
         Sha2x256,
     },
-    Clear,
     EnvBackend,
     Environment,
     Result,
     TypedEnvBackend,
 };
-use ink_engine::ext::Engine;
+use ink_engine::ext::{
+    self,
+    Engine,
+};
 use ink_storage_traits::{
     decode_all,
     Storable,
 };
 use pallet_contracts_uapi::{
+    CallFlags,
     ReturnErrorCode,
     ReturnFlags,
 };
@@ -128,47 +136,105 @@ where
         T: scale::Encode,
     {
         let encoded = topic_value.encode();
-        let len_encoded = encoded.len();
-        let mut result = <E as Environment>::Hash::CLEAR_HASH;
-        let len_result = result.as_ref().len();
-        if len_encoded <= len_result {
-            result.as_mut()[..len_encoded].copy_from_slice(&encoded[..]);
-        } else {
-            let mut hash_output = <Blake2x256 as HashOutput>::Type::default();
-            <Blake2x256 as CryptoHash>::hash(&encoded[..], &mut hash_output);
-            let copy_len = core::cmp::min(hash_output.len(), len_result);
-            result.as_mut()[0..copy_len].copy_from_slice(&hash_output[0..copy_len]);
-        }
-        let off_hash = result.as_ref();
-        let off_hash = off_hash.to_vec();
-        self.topics.push(off_hash);
+        let topic = topic_from_encoded::<E>(&encoded);
+        self.topics.push(topic.as_ref().to_vec());
     }
 
+    /// Returns the topics SCALE encoded as `Vec<Vec<u8>>`, the format expected by
+    /// [`Engine::deposit_event`].
     fn output(self) -> Self::Output {
-        let mut all: Vec<u8> = Vec::new();
+        scale::Encode::encode(&self.topics)
+    }
+}
 
-        let topics_len_compact = &scale::Compact(self.topics.len() as u32);
-        let topics_encoded = &scale::Encode::encode(&topics_len_compact)[..];
-        all.append(&mut topics_encoded.to_vec());
+/// Collects the topics serialized by the off-chain [`TopicsBuilder`] without
+/// concatenating them.
+#[derive(Default)]
+pub struct TopicsCollector(TopicsBuilder);
+
+impl<E> TopicsBuilderBackend<E> for TopicsCollector
+where
+    E: Environment,
+{
+    type Output = Vec<Vec<u8>>;
 
-        self.topics.into_iter().for_each(|mut v| all.append(&mut v));
-        all
+    fn expect(&mut self, expected_topics: usize) {
+        TopicsBuilderBackend::<E>::expect(&mut self.0, expected_topics)
+    }
+
+    fn push_topic<T>(&mut self, topic_value: &T)
+    where
+        T: scale::Encode,
+    {
+        TopicsBuilderBackend::<E>::push_topic(&mut self.0, topic_value)
+    }
+
+    fn output(self) -> Self::Output {
+        self.0.topics
     }
 }
 
 impl EnvInstance {
     /// Returns the contract property value.
+    ///
+    /// Only the bytes written by `ext_fn` are decoded, errors of the engine
+    /// are propagated. In strict decoding mode all of them must be consumed.
     fn get_property<T>(
         &mut self,
-        ext_fn: fn(engine: &Engine, output: &mut &mut [u8]),
+        ext_fn: fn(
+            engine: &Engine,
+            output: &mut &mut [u8],
+        ) -> core::result::Result<usize, ink_engine::Error>,
     ) -> Result<T>
     where
         T: scale::Decode,
     {
         let mut full_scope: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
         let full_scope = &mut &mut full_scope[..];
-        ext_fn(&self.engine, full_scope);
-        scale::Decode::decode(&mut &full_scope[..]).map_err(Into::into)
+        let written = ext_fn(&self.engine, full_scope)?;
+        if self.strict_decoding {
+            return decode_exact(&full_scope[..written], |input| T::decode(input))
+        }
+        scale::Decode::decode(&mut &full_scope[..written]).map_err(Into::into)
+    }
+
+    /// Decodes a storage value of `written` bytes.
+    fn decode_storage<R>(&self, written: &[u8]) -> Result<R>
+    where
+        R: Storable,
+    {
+        decode_storage(self.strict_decoding, written)
+    }
+}
+
+/// Decodes a storage value of `written` bytes, exactly if `strict_decoding`.
+fn decode_storage<R>(strict_decoding: bool, written: &[u8]) -> Result<R>
+where
+    R: Storable,
+{
+    if strict_decoding {
+        return decode_exact(written, |input| R::decode(input))
+    }
+    decode_all(&mut &written[..]).map_err(Into::into)
+}
+
+/// Decodes `bytes` via `decode`, failing unless exactly all of them are consumed.
+fn decode_exact<T>(
+    bytes: &[u8],
+    decode: impl FnOnce(&mut &[u8]) -> core::result::Result<T, scale::Error>,
+) -> Result<T> {
+    let mut input = bytes;
+    let decoded = decode(&mut input);
+    let consumed = bytes.len() - input.len();
+    match decoded {
+        Ok(decoded) if input.is_empty() => Ok(decoded),
+        _ => {
+            Err(OffChainError::DecodingFailed {
+                expected_len: bytes.len(),
+                consumed,
+            }
+            .into())
+        }
     }
 }
 
@@ -188,13 +254,17 @@ impl EnvBackend for EnvInstance {
         K: scale::Encode,
         R: Storable,
     {
+        let strict_decoding = self.strict_decoding;
         match self.engine.get_storage(&key.encode()) {
-            Ok(res) => {
-                let decoded = decode_all(&mut &res[..])?;
+            Ok(value) if value.len() > BUFFER_SIZE => {
+                Err(ext::Error::BufferTooSmall(value.len()).into())
+            }
+            Ok(value) => {
+                let decoded = decode_storage(strict_decoding, value)?;
                 Ok(Some(decoded))
             }
-            Err(ReturnErrorCode::KeyNotFound) => Ok(None),
-            Err(_) => panic!("encountered unexpected error"),
+            Err(ext::Error::KeyNotFound(_)) => Ok(None),
+            Err(error) => panic!("encountered unexpected error: {error:?}"),
         }
     }
 
@@ -205,11 +275,11 @@ impl EnvBackend for EnvInstance {
     {
         match self.engine.take_storage(&key.encode()) {
             Ok(output) => {
-                let decoded = decode_all(&mut &output[..])?;
+                let decoded = self.decode_storage(&output)?;
                 Ok(Some(decoded))
             }
-            Err(ReturnErrorCode::KeyNotFound) => Ok(None),
-            Err(_) => panic!("encountered unexpected error"),
+            Err(ext::Error::KeyNotFound(_)) => Ok(None),
+            Err(error) => panic!("encountered unexpected error: {error:?}"),
         }
     }
 
@@ -231,14 +301,15 @@ impl EnvBackend for EnvInstance {
     where
         T: scale::Decode,
     {
-        unimplemented!("the off-chain env does not implement `input`")
+        self.get_property::<T>(Engine::input)
     }
 
-    fn return_value<R>(&mut self, _flags: ReturnFlags, _return_value: &R) -> !
+    fn return_value<R>(&mut self, flags: ReturnFlags, return_value: &R) -> !
     where
         R: scale::Encode,
     {
-        unimplemented!("the off-chain env does not implement `return_value`")
+        self.engine
+            .return_value(flags.bits(), &return_value.encode()[..])
     }
 
     fn debug_message(&mut self, message: &str) {
@@ -249,7 +320,9 @@ impl EnvBackend for EnvInstance {
     where
         H: CryptoHash,
     {
-        <H as CryptoHash>::hash(input, output)
+        self.engine
+            .try_hash(input, output, <H as CryptoHash>::hash)
+            .unwrap_or_else(|error| panic!("contract trapped: {error:?}"))
     }
 
     fn hash_encoded<H, T>(&mut self, input: &T, output: &mut <H as HashOutput>::Type)
@@ -258,7 +331,9 @@ impl EnvBackend for EnvInstance {
         T: scale::Encode,
     {
         let enc_input = &scale::Encode::encode(input)[..];
-        <H as CryptoHash>::hash(enc_input, output)
+        self.engine
+            .try_hash(enc_input, output, <H as CryptoHash>::hash)
+            .unwrap_or_else(|error| panic!("contract trapped: {error:?}"))
     }
 
     fn ecdsa_recover(
@@ -356,12 +431,15 @@ impl EnvBackend for EnvInstance {
         let enc_input = &scale::Encode::encode(input)[..];
         let mut output: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
 
-        self.engine
-            .call_chain_extension(id, enc_input, &mut &mut output[..]);
-        let (status, out): (u32, Vec<u8>) = scale::Decode::decode(&mut &output[..])
-            .unwrap_or_else(|error| {
-                panic!("could not decode `call_chain_extension` output: {error:?}")
-            });
+        let written =
+            self.engine
+                .call_chain_extension(id, enc_input, &mut &mut output[..]);
+        let (status, out): (u32, Vec<u8>) = scale::DecodeAll::decode_all(
+            &mut &output[..written],
+        )
+        .unwrap_or_else(|error| {
+            panic!("could not decode `call_chain_extension` output: {error:?}")
+        });
 
         status_to_result(status)?;
         let decoded = decode_to_result(&out[..])?;
@@ -387,17 +465,29 @@ impl TypedEnvBackend for EnvInstance {
     }
 
     fn gas_left<E: Environment>(&mut self) -> u64 {
-        self.get_property::<u64>(Engine::gas_left)
-            .unwrap_or_else(|error| {
-                panic!("could not read `gas_left` property: {error:?}")
-            })
+        let mut output = [0; 8];
+        let written =
+            self.engine
+                .gas_left(&mut &mut output[..])
+                .unwrap_or_else(|error| {
+                    panic!("could not read `gas_left` property: {error:?}")
+                });
+        scale::Decode::decode(&mut &output[..written])
+            .unwrap_or_else(|error| panic!("could not decode `gas_left`: {error:?}"))
     }
 
     fn block_timestamp<E: Environment>(&mut self) -> E::Timestamp {
-        self.get_property::<E::Timestamp>(Engine::block_timestamp)
+        let timestamp = self
+            .get_property::<u64>(Engine::block_timestamp)
             .unwrap_or_else(|error| {
                 panic!("could not read `block_timestamp` property: {error:?}")
-            })
+            });
+        E::Timestamp::try_from(timestamp).unwrap_or_else(|_| {
+            panic!(
+                "the block timestamp {timestamp} overflows the `Timestamp` type of the \
+                environment"
+            )
+        })
     }
 
     fn account_id<E: Environment>(&mut self) -> E::AccountId {
@@ -415,10 +505,12 @@ impl TypedEnvBackend for EnvInstance {
     }
 
     fn block_number<E: Environment>(&mut self) -> E::BlockNumber {
-        self.get_property::<E::BlockNumber>(Engine::block_number)
+        let block_number = self
+            .get_property::<u32>(Engine::block_number)
             .unwrap_or_else(|error| {
                 panic!("could not read `block_number` property: {error:?}")
-            })
+            });
+        E::BlockNumber::from(block_number)
     }
 
     fn minimum_balance<E: Environment>(&mut self) -> E::Balance {
@@ -460,7 +552,9 @@ impl TypedEnvBackend for EnvInstance {
         Args: scale::Encode,
         R: scale::Decode,
     {
-        unimplemented!("off-chain environment does not support contract invocation")
+        unimplemented!(
+            "off-chain environment executes contract invocations via `invoke_contract`"
+        )
     }
 
     fn invoke_contract_delegate<E, Args, R>(
@@ -474,7 +568,7 @@ impl TypedEnvBackend for EnvInstance {
     {
         let _code_hash = params.code_hash();
         unimplemented!(
-            "off-chain environment does not support delegated contract invocation"
+            "off-chain environment executes delegate calls via `invoke_contract_delegate`"
         )
     }
 
@@ -531,7 +625,10 @@ impl TypedEnvBackend for EnvInstance {
         E: Environment,
     {
         let buffer = scale::Encode::encode(&beneficiary);
-        self.engine.terminate(&buffer[..])
+        match self.engine.terminate(&buffer[..]) {
+            Ok(never) => match never {},
+            Err(error) => panic!("contract trapped: {error:?}"),
+        }
     }
 
     fn transfer<E>(&mut self, destination: E::AccountId, value: E::Balance) -> Result<()>
@@ -564,7 +661,23 @@ impl TypedEnvBackend for EnvInstance {
     where
         E: Environment,
     {
-        unimplemented!("off-chain environment does not support cross-contract calls")
+        self.engine.caller_is_origin()
+    }
+
+    fn reentrance_count<E>(&mut self) -> u32
+    where
+        E: Environment,
+    {
+        let mut output = [0; 4];
+        let written = self
+            .engine
+            .reentrance_count(&mut &mut output[..])
+            .unwrap_or_else(|error| {
+                panic!("could not read `reentrance_count` property: {error:?}")
+            });
+        scale::Decode::decode(&mut &output[..written]).unwrap_or_else(|error| {
+            panic!("could not decode `reentrance_count`: {error:?}")
+        })
     }
 
     fn code_hash<E>(&mut self, _account: &E::AccountId) -> Result<E::Hash>
@@ -581,11 +694,14 @@ impl TypedEnvBackend for EnvInstance {
         unimplemented!("off-chain environment does not support `own_code_hash`")
     }
 
-    fn call_runtime<E, Call>(&mut self, _call: &Call) -> Result<()>
+    fn call_runtime<E, Call>(&mut self, call: &Call) -> Result<()>
     where
         E: Environment,
+        Call: scale::Encode,
     {
-        unimplemented!("off-chain environment does not support `call_runtime`")
+        self.engine
+            .call_runtime(&scale::Encode::encode(call))
+            .map_err(Into::into)
     }
 
     fn lock_delegate_dependency<E>(&mut self, _code_hash: &E::Hash)
@@ -620,3 +736,123 @@ impl TypedEnvBackend for EnvInstance {
         unimplemented!("off-chain environment does not support delegate dependencies")
     }
 }
+
+/// Invokes a contract message and returns its result.
+///
+/// The called contract interacts with the environment, hence it is executed without
+/// holding on to the environment instance, unlike the methods of [`TypedEnvBackend`].
+/// Storage writes of a reverted execution are only rolled back with
+/// [`crate::test::CommitMode::OnFrameSuccess`], other state changes remain. The
+/// storage deposit limit of the call caps the storage deposit charged by it, see
+/// [`Engine::limit_frame_storage_deposit`].
+///
+/// # Errors
+///
+/// - If no contract is registered at the callee, see [`crate::test::register_contract`].
+/// - If the transferred value exceeds the balance of the calling contract.
+/// - If the returned value cannot be decoded, see [`decode_output`].
+pub(crate) fn invoke_contract<E, Args, R>(
+    params: &CallParams<E, Call<E>, Args, R>,
+) -> Result<ink_primitives::MessageResult<R>>
+where
+    E: Environment,
+    Args: scale::Encode,
+    R: scale::Decode,
+{
+    let entry_point = enter_contract_call(params)?;
+    let (_flags, data) = super::test_api::execute_entry_point(entry_point);
+    decode_output(&data)
+}
+
+/// Enters the call of `params` and returns the function to dispatch it to, see
+/// [`Engine::enter_call`].
+///
+/// The storage deposit limit of `params`, if any, applies to the entered execution.
+pub(crate) fn enter_contract_call<E, Args, R>(
+    params: &CallParams<E, Call<E>, Args, R>,
+) -> Result<fn()>
+where
+    E: Environment,
+    Args: scale::Encode,
+{
+    let call_flags = params.call_flags();
+    let input = if !call_flags.contains(CallFlags::FORWARD_INPUT)
+        && !call_flags.contains(CallFlags::CLONE_INPUT)
+    {
+        Some(scale::Encode::encode(params.exec_input()))
+    } else {
+        None
+    };
+    let storage_deposit_limit = params
+        .storage_deposit_limit()
+        .map(|limit| {
+            <u128 as scale::Decode>::decode(&mut &scale::Encode::encode(limit)[..])
+                .map_err(|_| ext::Error::InvalidValue)
+        })
+        .transpose()?;
+    let entry_point = <EnvInstance as OnInstance>::on_instance(|instance| {
+        let entry_point = instance.engine.enter_call(
+            &scale::Encode::encode(params.callee()),
+            &scale::Encode::encode(params.transferred_value()),
+            input,
+        )?;
+        if let Some(limit) = storage_deposit_limit {
+            instance.engine.limit_frame_storage_deposit(limit);
+        }
+        Ok::<_, ext::Error>(entry_point)
+    })?;
+    Ok(entry_point)
+}
+
+/// Decodes the output of a called contract.
+///
+/// Only the bytes the callee returned are decoded. If it returned no bytes, e.g.
+/// since it did not return via [`crate::return_value`], the output is decoded as
+/// `R` directly, which only succeeds for types without data like `()`.
+fn decode_output<R>(output: &[u8]) -> Result<ink_primitives::MessageResult<R>>
+where
+    R: scale::Decode,
+{
+    if output.is_empty() {
+        return scale::DecodeAll::decode_all(&mut &output[..])
+            .map(Ok)
+            .map_err(Into::into)
+    }
+    scale::DecodeAll::decode_all(&mut &output[..]).map_err(Into::into)
+}
+
+/// Invokes a contract message via delegate call and returns its result.
+///
+/// The delegated code interacts with the environment, hence it is executed without
+/// holding on to the environment instance, unlike the methods of [`TypedEnvBackend`].
+/// Storage writes of a reverted execution are only rolled back with
+/// [`crate::test::CommitMode::OnFrameSuccess`], other state changes remain.
+///
+/// # Errors
+///
+/// - If no code is registered under the code hash, see [`crate::test::register_code`].
+/// - If the returned value cannot be decoded, see [`decode_output`].
+pub(crate) fn invoke_contract_delegate<E, Args, R>(
+    params: &CallParams<E, DelegateCall<E>, Args, R>,
+) -> Result<ink_primitives::MessageResult<R>>
+where
+    E: Environment,
+    Args: scale::Encode,
+    R: scale::Decode,
+{
+    let call_flags = params.call_flags();
+    let input = if !call_flags.contains(CallFlags::FORWARD_INPUT)
+        && !call_flags.contains(CallFlags::CLONE_INPUT)
+    {
+        Some(scale::Encode::encode(params.exec_input()))
+    } else {
+        None
+    };
+    let entry_point = <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .delegate_call(&scale::Encode::encode(params.code_hash()), input)
+    })?;
+    let (_flags, data) = super::test_api::execute_entry_point(entry_point);
+    decode_output(&data)
+}
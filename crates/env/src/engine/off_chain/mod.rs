@@ -12,7 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(feature = "async-tests")]
+mod async_instance;
 mod call_data;
+mod deferred_recorder;
 mod impls;
 pub mod test_api;
 mod types;
@@ -20,32 +23,214 @@ mod types;
 #[cfg(test)]
 mod tests;
 
+pub(crate) use impls::{
+    invoke_contract,
+    invoke_contract_delegate,
+    TopicsCollector,
+};
+
 use super::OnInstance;
 use crate::Error;
 
+use core::cell::{
+    Cell,
+    RefCell,
+};
 use derive_more::From;
-use ink_engine::ext::Engine;
+use ink_engine::{
+    ext::Engine,
+    AccountId,
+    Capabilities,
+};
+use std::rc::Rc;
+
+/// The version of the engine API the off-chain environment requires.
+const REQUIRED_ENGINE_API_VERSION: u32 = 3;
+
+/// Returns the host functions of the engine the off-chain environment uses.
+fn required_capabilities() -> Capabilities {
+    Capabilities::STORAGE
+        | Capabilities::TRANSFER
+        | Capabilities::EVENTS
+        | Capabilities::DEBUG_MESSAGE
+        | Capabilities::TERMINATE
+        | Capabilities::CALL
+        | Capabilities::DELEGATE_CALL
+        | Capabilities::CALL_RUNTIME
+        | Capabilities::CHAIN_EXTENSION
+        | Capabilities::HASHING
+        | Capabilities::ECDSA_RECOVER
+        | Capabilities::GAS_LEFT
+        | Capabilities::REENTRANCE_COUNT
+}
+
+/// Checks that an engine with API `version` and `capabilities` provides everything
+/// the off-chain environment uses.
+///
+/// Returns a message naming the missing host functions otherwise.
+fn check_engine(
+    version: u32,
+    capabilities: Capabilities,
+) -> core::result::Result<(), String> {
+    let missing: Vec<&str> = capabilities.missing(required_capabilities()).collect();
+    if version < REQUIRED_ENGINE_API_VERSION || !missing.is_empty() {
+        let missing = if missing.is_empty() {
+            String::new()
+        } else {
+            format!(", the engine does not implement `{}`", missing.join("`, `"))
+        };
+        return Err(format!(
+            "the off-chain environment requires `ink_engine` API version \
+            {REQUIRED_ENGINE_API_VERSION}, found version {version}{missing}"
+        ))
+    }
+    Ok(())
+}
 
 /// The off-chain environment.
+///
+/// Clones share the registered callbacks, see [`Engine`].
+#[derive(Clone)]
 pub struct EnvInstance {
     engine: Engine,
+    /// The callbacks invoked whenever the chain advances by a block, see
+    /// [`test_api::on_block_advance`].
+    block_callbacks: Vec<BlockCallback>,
+    /// Whether decoding properties and storage values requires all bytes written
+    /// by the engine to be consumed, see [`test_api::set_strict_decoding`].
+    strict_decoding: bool,
+    /// The execution contexts replaced via [`test_api::push_execution_context`],
+    /// innermost last.
+    pushed_contexts: Vec<PushedContext>,
+}
+
+/// The part of the execution context set by [`test_api::push_execution_context`].
+#[derive(Clone)]
+struct PushedContext {
+    caller: Option<AccountId>,
+    callee: Option<AccountId>,
+    value_transferred: u128,
+    input: Vec<u8>,
+}
+
+/// Strict decoding is enabled by default in the tests of this crate.
+const STRICT_DECODING_DEFAULT: bool = cfg!(test);
+
+/// Invoked with the number of the new block, shared between clones of the
+/// environment.
+type BlockCallback = Rc<RefCell<dyn FnMut(u32)>>;
+
+impl EnvInstance {
+    /// Returns an environment backed by `engine`.
+    ///
+    /// # Panics
+    ///
+    /// If the `ink_engine` in use lacks host functions of the off-chain environment.
+    fn with_engine(engine: Engine) -> Self {
+        check_engine(ink_engine::ENGINE_API_VERSION, ink_engine::capabilities())
+            .unwrap_or_else(|message| panic!("{message}"));
+        EnvInstance {
+            engine,
+            block_callbacks: Vec::new(),
+            strict_decoding: STRICT_DECODING_DEFAULT,
+            pushed_contexts: Vec::new(),
+        }
+    }
+}
+
+/// An environment together with the operation currently using it.
+struct InstanceCell {
+    instance: RefCell<EnvInstance>,
+    /// The name of the operation currently using the environment, if any.
+    operation: Cell<Option<&'static str>>,
+}
+
+impl InstanceCell {
+    fn new(instance: EnvInstance) -> Self {
+        Self {
+            instance: RefCell::new(instance),
+            operation: Cell::new(None),
+        }
+    }
+
+    /// Invokes `f` with the environment, then the recorder hooks deferred meanwhile,
+    /// see [`deferred_recorder`].
+    ///
+    /// # Panics
+    ///
+    /// If the environment is already in use, e.g. because `f` is invoked from within
+    /// another operation on the environment. The message names both operations.
+    fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut EnvInstance) -> R,
+    {
+        let operation = operation_name::<F>();
+        let mut instance = self.instance.try_borrow_mut().unwrap_or_else(|_| {
+            panic!(
+                "the off-chain environment is already in use by `{}` and cannot be \
+                used by `{operation}` at the same time",
+                self.operation.get().unwrap_or("an unknown operation"),
+            )
+        });
+        self.operation.set(Some(operation));
+        let result = f(&mut instance);
+        self.operation.set(None);
+        drop(instance);
+        deferred_recorder::run_deferred_hooks();
+        result
+    }
+}
+
+/// Returns the name of the function defining the closure `F`.
+fn operation_name<F>() -> &'static str {
+    core::any::type_name::<F>().trim_end_matches("::{{closure}}")
 }
 
+thread_local!(
+    static INSTANCE: InstanceCell =
+        InstanceCell::new(EnvInstance::with_engine(Engine::new()));
+    /// Whether the current thread has used the off-chain environment.
+    static IN_USE: Cell<bool> = const { Cell::new(false) };
+);
+
 impl OnInstance for EnvInstance {
     fn on_instance<F, R>(f: F) -> R
     where
         F: FnOnce(&mut Self) -> R,
     {
-        use core::cell::RefCell;
-        thread_local!(
-            static INSTANCE: RefCell<EnvInstance> = RefCell::new(
-                EnvInstance {
-                    engine: Engine::new()
-                }
-            )
-        );
-        INSTANCE.with(|instance| f(&mut instance.borrow_mut()))
+        #[cfg(feature = "async-tests")]
+        if async_instance::in_scope() {
+            return async_instance::on_task_instance(f)
+        }
+        INSTANCE.with(|instance| {
+            IN_USE.with(|in_use| in_use.set(true));
+            instance.with(f)
+        })
+    }
+}
+
+/// Returns the debug dump of the engine of the current thread.
+///
+/// Returns `None` if the thread has not used the off-chain environment or if the
+/// environment is in use, e.g. because the current panic happened inside of it.
+fn engine_debug_dump() -> Option<String> {
+    #[cfg(feature = "async-tests")]
+    if async_instance::in_scope() {
+        return async_instance::engine_debug_dump()
+    }
+    if !IN_USE.try_with(Cell::get).unwrap_or(false) {
+        return None
     }
+    INSTANCE
+        .try_with(|instance| {
+            instance
+                .instance
+                .try_borrow()
+                .ok()
+                .map(|instance| instance.engine.debug_dump())
+        })
+        .ok()
+        .flatten()
 }
 
 #[derive(Debug, From, PartialEq, Eq)]
@@ -57,6 +242,62 @@ pub enum OffChainError {
     UninitializedExecutionContext,
     #[from(ignore)]
     UnregisteredChainExtension,
+    /// A property of the execution context was read in strict mode without having
+    /// been set, carries the name of the property.
+    #[from(ignore)]
+    UninitializedProperty(&'static str),
+    /// The caller was read in an execution dispatched by root or without origin.
+    #[from(ignore)]
+    UnsignedOrigin,
+    /// The passed key does not exist in storage.
+    #[from(ignore)]
+    KeyNotFound(Vec<u8>),
+    /// The callee reverted its state, carries the output of the callee.
+    #[from(ignore)]
+    CalleeReverted(Vec<u8>),
+    /// The transferred value could not be decoded as a balance.
+    #[from(ignore)]
+    InvalidValue,
+    /// The callee is no valid account id, carries the callee.
+    #[from(ignore)]
+    InvalidCallee(Vec<u8>),
+    /// The balance of the depositor does not cover the storage deposit.
+    #[from(ignore)]
+    StorageDepositLimitExhausted,
+    /// The contract holds locks preventing its termination.
+    #[from(ignore)]
+    TerminationDenied,
+    /// The balance of the origin does not cover the fee of the execution.
+    #[from(ignore)]
+    FeeWithdrawalFailed,
+    /// Writing the value would exceed the memory hard cap of the engine.
+    #[from(ignore)]
+    MemoryLimitExceeded,
+    /// The event exceeds a limit of the engine configuration.
+    #[from(ignore)]
+    EventLimitExceeded(ink_engine::ext::EventLimitViolation),
+    /// The gas left does not cover the weight of the chain extension method.
+    #[from(ignore)]
+    OutOfGas,
+    /// The chain extension method trapped.
+    #[from(ignore)]
+    ChainExtensionTrapped,
+    /// The uploaded code exceeds the maximum code length of the engine.
+    #[from(ignore)]
+    CodeTooLarge,
+    /// In strict decoding mode the decoder did not consume exactly the bytes
+    /// written by the engine.
+    #[from(ignore)]
+    DecodingFailed {
+        expected_len: usize,
+        consumed: usize,
+    },
+    /// The legacy test API is not supported by this engine, carries its name.
+    #[from(ignore)]
+    UnsupportedLegacyApi(&'static str),
+    /// The mocked callee has no function registered for the called selector.
+    #[from(ignore)]
+    UnknownSelector(ink_engine::UnknownSelector),
 }
 
 /// Errors encountered upon interacting with the accounts database.
@@ -67,4 +308,8 @@ pub enum AccountError {
     UnexpectedUserAccount,
     #[from(ignore)]
     NoAccountForId(Vec<u8>),
+    /// The all-zero account was used while forbidden, see
+    /// [`test_api::EngineConfig::allow_zero_address`].
+    #[from(ignore)]
+    ZeroAddressForbidden,
 }
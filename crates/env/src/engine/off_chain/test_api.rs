@@ -15,22 +15,131 @@
 //! Operations on the off-chain testing environment.
 
 use super::{
+    impls::enter_contract_call,
     EnvInstance,
+    OffChainError,
     OnInstance,
+    PushedContext,
 };
 use crate::{
+    call::{
+        Call,
+        CallParams,
+    },
+    hash::{
+        Blake2x256,
+        CryptoHash,
+        HashOutput,
+    },
     Environment,
     Result,
+    ReturnFlags,
 };
 use core::fmt::Debug;
-use ink_engine::test_api::RecordedDebugMessages;
-use std::panic::UnwindSafe;
+use ink_engine::{
+    ext::Engine,
+    test_api::RecordedDebugMessages,
+    ReturnValue,
+};
+use ink_primitives::Key;
+use std::{
+    cell::RefCell,
+    panic::{
+        self,
+        AssertUnwindSafe,
+        UnwindSafe,
+    },
+    rc::Rc,
+};
 
+#[cfg(feature = "async-tests")]
+pub use super::async_instance::EngineGuard;
 pub use super::call_data::CallData;
-pub use ink_engine::ChainExtension;
+pub use crate::topics::compute_topics_off_chain;
+#[cfg(feature = "allowances")]
+pub use ink_engine::allowances::AllowanceError;
+pub use ink_engine::{
+    activity::{
+        ActivityDiff,
+        ActivityLog,
+        ActivityLogError,
+    },
+    block_history::BlockNotRetained,
+    chain_params::{
+        ChainParamError,
+        RECOGNIZED_CHAIN_PARAMS,
+        STORAGE_DEPOSIT_LIMIT,
+    },
+    ext::{
+        EngineConfig,
+        EventLimit,
+        EventLimitViolation,
+        MisusePolicy,
+    },
+    nonce::AddressDerivationInput,
+    recorder::{
+        DroppedRecords,
+        InMemoryRecorder,
+        Recorder,
+    },
+    runtime_call::{
+        RuntimeCall,
+        RuntimeCallError,
+    },
+    scenario::{
+        Divergence,
+        Operation,
+        Scenario,
+        ScenarioReplayer,
+    },
+    snapshot::{
+        BalanceDiff,
+        CellChange,
+        CellDiff,
+        EventCountDiff,
+        Snapshot,
+        StateDiff,
+    },
+    state::{
+        EngineState,
+        StateError,
+    },
+    storage_overlay::CommitMode,
+    test_api::{
+        event_topics,
+        AccountInfo,
+        AccountKind,
+        CallOutcome,
+        Code,
+        ContractStatistics,
+        Diagnostic,
+        DroppedFrameItems,
+        Fault,
+        FrameItem,
+        FrameRecord,
+        RecordedCall,
+        StorageChange,
+        TransferRecord,
+        Warning,
+    },
+    weights::WeightTable,
+    AssetId,
+    ChainExtension,
+    ChainExtensionCall,
+    ChainExtensionOutcome,
+    ChainExtensionTrapped,
+    DuplicateSelector,
+    EntryPoints,
+    ExecContextSnapshot,
+    UnknownSelector,
+};
 
 /// Record for an emitted event.
-#[derive(Clone)]
+///
+/// Events are equal if their topics are equal in the order they were pushed and
+/// their data is equal. See [`EmittedEvent::eq_unordered_topics`] to ignore the
+/// order of the topics.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct EmittedEvent {
     /// Recorded topics of the emitted event.
     pub topics: Vec<Vec<u8>>,
@@ -38,6 +147,26 @@ pub struct EmittedEvent {
     pub data: Vec<u8>,
 }
 
+impl EmittedEvent {
+    /// Creates the record of an event with `topics` and the encoded `data`.
+    pub fn new(topics: Vec<Vec<u8>>, data: Vec<u8>) -> Self {
+        Self { topics, data }
+    }
+
+    /// Returns `true` if both events have the same topics in any order and the same
+    /// data.
+    ///
+    /// Useful to compare against events whose topics are sorted, e.g. by an indexer.
+    /// The data is always compared exactly.
+    pub fn eq_unordered_topics(&self, other: &Self) -> bool {
+        let mut topics = self.topics.iter().collect::<Vec<_>>();
+        let mut other_topics = other.topics.iter().collect::<Vec<_>>();
+        topics.sort_unstable();
+        other_topics.sort_unstable();
+        self.data == other.data && topics == other_topics
+    }
+}
+
 /// Sets the balance of the account to the given balance.
 ///
 /// # Note
@@ -85,6 +214,93 @@ where
     })
 }
 
+/// Sets the balance of `asset` held by `account_id` to `new_balance`.
+///
+/// Assets are independent of the native balance of the account.
+pub fn set_asset_balance<T>(
+    account_id: T::AccountId,
+    asset: AssetId,
+    new_balance: T::Balance,
+) where
+    T: Environment<Balance = u128>, // Just temporary for the MVP!
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.set_asset_balance(
+            &scale::Encode::encode(&account_id),
+            asset,
+            new_balance,
+        )
+    })
+}
+
+/// Returns the balance of `asset` held by `account_id`.
+///
+/// Accounts which have never held the asset have a balance of `0`.
+pub fn get_asset_balance<T>(account_id: T::AccountId, asset: AssetId) -> T::Balance
+where
+    T: Environment<Balance = u128>, // Just temporary for the MVP!
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .get_asset_balance(&scale::Encode::encode(&account_id), asset)
+    })
+}
+
+/// Allows `spender` to move up to `amount` of the balance of `owner`.
+#[cfg(feature = "allowances")]
+pub fn set_allowance<T>(owner: &T::AccountId, spender: &T::AccountId, amount: T::Balance)
+where
+    T: Environment<Balance = u128>, // Just temporary for the MVP!
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.set_allowance(
+            &scale::Encode::encode(owner),
+            &scale::Encode::encode(spender),
+            amount,
+        )
+    })
+}
+
+/// Returns the amount `spender` is allowed to move from the balance of `owner`.
+#[cfg(feature = "allowances")]
+pub fn allowance<T>(owner: &T::AccountId, spender: &T::AccountId) -> T::Balance
+where
+    T: Environment<Balance = u128>, // Just temporary for the MVP!
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.allowance(
+            &scale::Encode::encode(owner),
+            &scale::Encode::encode(spender),
+        )
+    })
+}
+
+/// Moves `amount` from the balance of `owner` to `dest` on behalf of `spender`.
+///
+/// # Errors
+///
+/// If the allowance of `spender` or the balance of `owner` is less than `amount`.
+#[cfg(feature = "allowances")]
+pub fn transfer_from<T>(
+    spender: &T::AccountId,
+    owner: &T::AccountId,
+    dest: &T::AccountId,
+    amount: T::Balance,
+) -> core::result::Result<(), AllowanceError>
+where
+    T: Environment<Balance = u128>, // Just temporary for the MVP!
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.transfer_from(
+            &scale::Encode::encode(spender),
+            &scale::Encode::encode(owner),
+            &scale::Encode::encode(dest),
+            amount,
+        )
+    })
+}
+
 /// Registers a new chain extension.
 pub fn register_chain_extension<E>(extension: E)
 where
@@ -98,6 +314,90 @@ where
     })
 }
 
+/// Registers a new chain extension and declares the `weight` of its method
+/// `func_id`, the full id as passed to `call_chain_extension`.
+///
+/// The weight is charged before the method is invoked from the gas left, i.e. the
+/// `gas_limit` set via [`set_engine_config`] minus the gas consumed so far. If the gas
+/// left does not cover it, the method is not invoked and the contract traps with
+/// [`ink_engine::ext::Error::OutOfGas`].
+pub fn register_chain_extension_with_weight<E>(func_id: u32, weight: u64, extension: E)
+where
+    E: ink_engine::ChainExtension + 'static,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .chain_extension_handler
+            .register_with_weight(func_id, weight, Box::new(extension));
+    })
+}
+
+/// Returns the invoked chain extension methods in the order they were called.
+///
+/// Calls which ran out of gas are not included, see
+/// [`out_of_gas_chain_extension_calls`].
+pub fn chain_extension_calls() -> Vec<ChainExtensionCall> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.chain_extension_handler.calls().to_vec()
+    })
+}
+
+/// Returns the status code returned by the last call of the chain extension method
+/// `func_id`, the full id as passed to `call_chain_extension`.
+///
+/// Returns `None` if the method has not been called or its last call trapped, see
+/// [`ink_engine::ChainExtension::try_call_with_engine`].
+pub fn last_chain_extension_status(func_id: u32) -> Option<u32> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.chain_extension_handler.last_status(func_id)
+    })
+}
+
+/// Returns the chain extension methods which were not invoked because the gas left
+/// did not cover their weight.
+pub fn out_of_gas_chain_extension_calls() -> Vec<ChainExtensionCall> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .chain_extension_handler
+            .out_of_gas_calls()
+            .to_vec()
+    })
+}
+
+/// Injects a fault into the off-chain environment.
+///
+/// The fault is consumed once it has been triggered by the corresponding
+/// environmental operation. See [`Fault`] for the available faults.
+pub fn inject_fault(fault: Fault) {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.inject_fault(fault);
+    })
+}
+
+/// Registers the handler which determines the outcome of runtime calls dispatched
+/// via [`crate::call_runtime`].
+///
+/// The handler is invoked with the SCALE encoded call. A rejected call fails with
+/// [`crate::ReturnErrorCode::CallRuntimeFailed`]. Without a handler every runtime
+/// call is rejected. The handler must not interact with the environment.
+pub fn register_runtime_call_handler<F>(handler: F)
+where
+    F: FnMut(&[u8]) -> core::result::Result<(), RuntimeCallError> + 'static,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.register_runtime_call_handler(handler);
+    })
+}
+
+/// Returns the runtime calls dispatched via [`crate::call_runtime`] in order.
+pub fn recorded_runtime_calls() -> Vec<RuntimeCall> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.recorded_runtime_calls().to_vec()
+    })
+}
+
 /// Returns the contents of the past performed environmental debug messages in order.
 pub fn recorded_debug_messages() -> RecordedDebugMessages {
     <EnvInstance as OnInstance>::on_instance(|instance| {
@@ -105,6 +405,71 @@ pub fn recorded_debug_messages() -> RecordedDebugMessages {
     })
 }
 
+/// Returns the contents of the past performed environmental debug messages in order
+/// and clears them, so that subsequent calls only return newly recorded messages.
+///
+/// Recorded events are not affected by this.
+pub fn take_recorded_printlns() -> RecordedDebugMessages {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.take_emitted_debug_messages()
+    })
+}
+
+/// Returns the recorded debug messages which contain `substr`, in order.
+pub fn printlns_containing(substr: &str) -> Vec<String> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .get_emitted_debug_messages()
+            .containing(substr)
+    })
+}
+
+/// Asserts that a debug message containing `substr` has been recorded.
+///
+/// # Panics
+///
+/// If no recorded debug message contains `substr`. The panic message lists all
+/// recorded debug messages.
+pub fn assert_println_emitted(substr: &str) {
+    let recorded: Vec<String> = recorded_debug_messages().into_iter().collect();
+    assert!(
+        recorded.iter().any(|message| message.contains(substr)),
+        "no debug message containing {substr:?} was recorded, recorded messages: {recorded:?}"
+    );
+}
+
+/// Disables debug messages like on a production chain.
+///
+/// Subsequent debug messages are dropped and a [`Warning::PrintlnDenied`] is recorded
+/// instead, see [`assert_no_printlns`]. Re-enable them via [`set_engine_config`]. The
+/// setting is kept when the environment is reset.
+pub fn deny_println() {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.config.println_enabled = false;
+    })
+}
+
+/// Asserts that no debug message has been emitted, neither recorded nor denied via
+/// [`deny_println`].
+///
+/// # Panics
+///
+/// If a debug message has been emitted. The panic message lists all of them.
+pub fn assert_no_printlns() {
+    let mut emitted: Vec<String> = recorded_debug_messages().into_iter().collect();
+    emitted.extend(recorded_warnings().into_iter().filter_map(|warning| {
+        match warning {
+            Warning::PrintlnDenied { message } => Some(message),
+            _ => None,
+        }
+    }));
+    assert!(
+        emitted.is_empty(),
+        "expected no debug messages, emitted messages: {emitted:?}"
+    );
+}
+
 /// Set to true to disable clearing storage
 ///
 /// # Note
@@ -119,173 +484,1378 @@ pub fn set_clear_storage_disabled(_disable: bool) {
 }
 
 /// Advances the chain by a single block.
+///
+/// The callbacks registered via [`on_block_advance`] are invoked once the block
+/// number and timestamp are updated. The state at the end of the finished block is
+/// retained to revert to, see [`revert_to_block`].
 pub fn advance_block<T>()
 where
     T: Environment,
 {
+    // The callbacks are invoked outside of the instance, so they can use the
+    // functions of this module.
+    let (block_number, mut callbacks) =
+        <EnvInstance as OnInstance>::on_instance(|instance| {
+            instance.engine.advance_block();
+            (
+                instance.engine.exec_context.block_number(),
+                core::mem::take(&mut instance.block_callbacks),
+            )
+        });
+    for callback in &callbacks {
+        (callback.borrow_mut())(block_number);
+    }
     <EnvInstance as OnInstance>::on_instance(|instance| {
-        instance.engine.advance_block();
+        callbacks.append(&mut instance.block_callbacks);
+        instance.block_callbacks = callbacks;
     })
 }
 
-/// Sets a caller for the next call.
-pub fn set_caller<T>(caller: T::AccountId)
+/// Advances the block timestamp by `ms` milliseconds without advancing the block
+/// number.
+///
+/// The advanced timestamp stays in effect until the next [`advance_block`], which
+/// derives the timestamp of the new block as usual. An advancement beyond the
+/// maximum timestamp saturates and is recorded as a warning, see
+/// [`recorded_warnings`].
+pub fn advance_time<T>(ms: T::Timestamp)
 where
-    T: Environment,
-    <T as Environment>::AccountId: From<[u8; 32]>,
+    T: Environment<Timestamp = u64>,
 {
     <EnvInstance as OnInstance>::on_instance(|instance| {
-        instance.engine.set_caller(scale::Encode::encode(&caller));
+        instance.engine.advance_time(ms);
     })
 }
 
-/// Sets the callee for the next call.
-pub fn set_callee<T>(callee: T::AccountId)
+/// Makes the block `block_number` the head of the chain again, as if the blocks
+/// after it had been retracted by a re-org.
+///
+/// Restores the storage, balances, block number and timestamp to the end of the
+/// block and drops the events emitted after it from [`recorded_events`]. The
+/// number of blocks retained to revert to is configured via
+/// [`EngineConfig::block_history_len`].
+///
+/// # Errors
+///
+/// If the block is neither the current block nor retained.
+pub fn revert_to_block<T>(
+    block_number: T::BlockNumber,
+) -> core::result::Result<(), BlockNotRetained>
 where
-    T: Environment,
-    <T as Environment>::AccountId: From<[u8; 32]>,
+    T: Environment<BlockNumber = u32>,
 {
     <EnvInstance as OnInstance>::on_instance(|instance| {
-        instance.engine.set_callee(scale::Encode::encode(&callee));
+        instance.engine.revert_to_block(block_number)
     })
 }
 
-/// Sets an account as a contract
-pub fn set_contract<T>(contract: T::AccountId)
-where
-    T: Environment,
-    <T as Environment>::AccountId: From<[u8; 32]>,
+/// Enters an execution context in which `callee` is called by `caller` with
+/// `endowment` and `call_data` as input.
+///
+/// Compatibility shim for tests written against the legacy off-chain environment.
+/// The execution happens in the current block and `gas_limit` is ignored, configure
+/// the gas limit of the engine via [`set_engine_config`] instead. Exit the context via
+/// [`pop_execution_context`].
+pub fn push_execution_context<T>(
+    caller: T::AccountId,
+    callee: T::AccountId,
+    gas_limit: T::Balance,
+    endowment: T::Balance,
+    call_data: CallData,
+) where
+    T: Environment<Balance = u128>, // Just temporary for the MVP!
 {
+    let _ = gas_limit;
     <EnvInstance as OnInstance>::on_instance(|instance| {
-        instance
-            .engine
-            .set_contract(scale::Encode::encode(&contract));
+        let context = &mut instance.engine.exec_context;
+        instance.pushed_contexts.push(PushedContext {
+            caller: context.caller().cloned(),
+            callee: context.callee().cloned(),
+            value_transferred: context.value_transferred(),
+            input: context.input().to_vec(),
+        });
+        context.set_caller(Some(scale::Encode::encode(&caller).into()));
+        context.set_callee(Some(scale::Encode::encode(&callee).into()));
+        context.set_value_transferred(endowment);
+        context.set_input(call_data.to_bytes().to_vec());
     })
 }
 
-/// Returns a boolean to indicate whether an account is a contract
-pub fn is_contract<T>(contract: T::AccountId) -> bool
+/// Exits the execution context entered via [`push_execution_context`], restoring
+/// the caller, callee, transferred value and input from before.
+///
+/// # Panics
+///
+/// If no execution context was entered via [`push_execution_context`].
+pub fn pop_execution_context() {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        let pushed = instance
+            .pushed_contexts
+            .pop()
+            .expect("no execution context was pushed via `push_execution_context`");
+        let context = &mut instance.engine.exec_context;
+        context.set_caller(pushed.caller);
+        context.set_callee(pushed.callee);
+        context.set_value_transferred(pushed.value_transferred);
+        context.set_input(pushed.input);
+    })
+}
+
+/// Sets the entropy of the current block.
+///
+/// Compatibility shim for tests written against the legacy off-chain environment.
+///
+/// # Errors
+///
+/// Always, contracts can no longer access on-chain randomness.
+#[deprecated(note = "on-chain randomness is not supported, use a chain extension")]
+pub fn set_block_entropy<T>(entropy: T::Hash) -> Result<()>
 where
     T: Environment,
-    <T as Environment>::AccountId: From<[u8; 32]>,
 {
-    <EnvInstance as OnInstance>::on_instance(|instance| {
-        instance
-            .engine
-            .is_contract(scale::Encode::encode(&contract))
-    })
+    let _ = entropy;
+    Err(OffChainError::UnsupportedLegacyApi("set_block_entropy").into())
 }
 
-/// Gets the currently set callee.
+/// Sets the rent allowance of the contract `account_id`.
 ///
-/// This is account id of the currently executing contract.
-pub fn callee<T>() -> T::AccountId
+/// Compatibility shim for tests written against the legacy off-chain environment.
+///
+/// # Errors
+///
+/// Always, storage rent was replaced by storage deposits, see
+/// [`get_storage_deposit`].
+#[deprecated(note = "storage rent is not supported, see `get_storage_deposit`")]
+pub fn set_account_rent_allowance<T>(
+    account_id: T::AccountId,
+    new_rent_allowance: T::Balance,
+) -> Result<()>
 where
     T: Environment,
+{
+    let _ = (account_id, new_rent_allowance);
+    Err(OffChainError::UnsupportedLegacyApi("set_account_rent_allowance").into())
+}
+
+/// Registers `callback` to be invoked with the number of the new block whenever
+/// the chain advances by a block via [`advance_block`].
+///
+/// The callbacks may use the functions of this module, e.g. to set the balance of
+/// an account on every block. Callbacks are invoked in the order they were
+/// registered, panics inside of a callback propagate to the caller of
+/// [`advance_block`].
+pub fn on_block_advance<T, F>(callback: F)
+where
+    T: Environment<BlockNumber = u32>,
+    F: FnMut(T::BlockNumber) + 'static,
 {
     <EnvInstance as OnInstance>::on_instance(|instance| {
-        let callee = instance.engine.get_callee();
-        scale::Decode::decode(&mut &callee[..])
-            .unwrap_or_else(|err| panic!("encoding failed: {err}"))
+        instance
+            .block_callbacks
+            .push(Rc::new(RefCell::new(callback)));
     })
 }
 
-/// Returns the total number of reads and writes of the contract's storage.
-pub fn get_contract_storage_rw<T>(account_id: &T::AccountId) -> (usize, usize)
+/// Sets the clock which derives the block timestamp from the block number whenever
+/// the chain advances by a block, e.g. via [`advance_block`].
+///
+/// By default the timestamp advances by the block time of the chain on every block.
+/// A clock may move backwards, this is recorded as a [`Warning`].
+pub fn set_clock<T, F>(clock: F)
 where
-    T: Environment,
+    T: Environment<BlockNumber = u32, Timestamp = u64>,
+    F: FnMut(T::BlockNumber) -> T::Timestamp + 'static,
 {
     <EnvInstance as OnInstance>::on_instance(|instance| {
-        instance
-            .engine
-            .get_contract_storage_rw(scale::Encode::encode(&account_id))
+        instance.engine.set_clock(clock);
     })
 }
 
-/// Sets the value transferred from the caller to the callee as part of the call.
+/// Sets the function computing the fee for an amount of gas returned by
+/// [`weight_to_fee`][`crate::weight_to_fee`].
 ///
-/// Please note that the acting accounts should be set with [`set_caller()`] and
-/// [`set_callee()`] beforehand.
-pub fn set_value_transferred<T>(value: T::Balance)
+/// By default the fee is the amount of gas multiplied by the gas price of the
+/// chain. A previously set function is replaced, also in the middle of a test.
+pub fn set_weight_to_fee_fn<T, F>(weight_to_fee: F)
 where
-    T: Environment<Balance = u128>, // Just temporary for the MVP!
+    T: Environment<Balance = u128>,
+    F: Fn(u64) -> T::Balance + 'static,
 {
     <EnvInstance as OnInstance>::on_instance(|instance| {
-        instance.engine.set_value_transferred(value);
+        instance.engine.set_weight_to_fee_fn(weight_to_fee);
     })
 }
 
-/// Transfers value from the caller account to the contract.
+/// Sets the function deriving the addresses of instantiated contracts.
 ///
-/// Please note that the acting accounts should be set with [`set_caller()`] and
-/// [`set_callee()`] beforehand.
-pub fn transfer_in<T>(value: T::Balance)
+/// By default addresses are derived like by `pallet-contracts`. A previously set
+/// function is replaced. The derived addresses must have the account length of the
+/// chain, instantiating panics otherwise.
+pub fn set_address_derivation<F>(derive: F)
 where
-    T: Environment<Balance = u128>, // Just temporary for the MVP!
+    F: Fn(&AddressDerivationInput) -> Vec<u8> + 'static,
 {
     <EnvInstance as OnInstance>::on_instance(|instance| {
-        let caller = instance
-            .engine
-            .exec_context
-            .caller
-            .as_ref()
-            .expect("no caller has been set")
-            .as_bytes()
-            .to_vec();
+        instance.engine.set_address_derivation(derive);
+    })
+}
 
-        let caller_old_balance = instance
-            .engine
-            .get_balance(caller.clone())
-            .unwrap_or_default();
+/// Returns the warnings recorded by the off-chain environment in order.
+pub fn recorded_warnings() -> Vec<Warning> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.get_recorded_warnings()
+    })
+}
 
-        let callee = instance.engine.get_callee();
-        let contract_old_balance = instance
-            .engine
-            .get_balance(callee.clone())
-            .unwrap_or_default();
+/// Enables recording [`Diagnostic`]s for patterns which likely hint at bugs, like
+/// overwriting a storage cell without reading it or transferring a value of zero.
+///
+/// Diagnostics are disabled again when the environment is reset, e.g. by
+/// [`run_test`].
+pub fn enable_diagnostics() {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.enable_diagnostics()
+    })
+}
 
-        instance
-            .engine
-            .set_balance(caller, caller_old_balance - value);
-        instance
-            .engine
-            .set_balance(callee, contract_old_balance + value);
-        instance.engine.set_value_transferred(value);
-    });
+/// Returns the diagnostics recorded since [`enable_diagnostics`], in order.
+pub fn diagnostics() -> Vec<Diagnostic> {
+    <EnvInstance as OnInstance>::on_instance(|instance| instance.engine.diagnostics())
 }
 
-/// Returns the amount of storage cells used by the account `account_id`.
+/// Returns the encoded keys of the storage of `account_id` which were read without
+/// ever having been written since [`enable_diagnostics`], in the order of the reads.
 ///
-/// Returns `None` if the `account_id` is non-existent.
-pub fn count_used_storage_cells<T>(account_id: &T::AccountId) -> Result<usize>
+/// The reads still return `None`, they are only recorded as
+/// [`Diagnostic::UninitializedRead`].
+pub fn uninitialized_reads<T>(account_id: &T::AccountId) -> Vec<Vec<u8>>
 where
     T: Environment,
 {
     <EnvInstance as OnInstance>::on_instance(|instance| {
         instance
             .engine
-            .count_used_storage_cells(&scale::Encode::encode(&account_id))
-            .map_err(Into::into)
+            .uninitialized_reads(&scale::Encode::encode(account_id))
     })
 }
 
-/// Sets the block timestamp for the next [`advance_block`] invocation.
-pub fn set_block_timestamp<T>(value: T::Timestamp)
-where
-    T: Environment<Timestamp = u64>,
-{
+/// Asserts that no diagnostic has been recorded since [`enable_diagnostics`].
+///
+/// # Panics
+///
+/// If a diagnostic has been recorded. The panic message lists all of them.
+pub fn assert_no_diagnostics() {
     <EnvInstance as OnInstance>::on_instance(|instance| {
-        instance.engine.set_block_timestamp(value);
+        instance.engine.assert_no_diagnostics()
     })
 }
 
-/// Sets the block number for the next [`advance_block`] invocation.
-pub fn set_block_number<T>(value: T::BlockNumber)
-where
-    T: Environment<BlockNumber = u32>,
-{
+/// Sets whether the all-zero account, which unset accounts decode to, may be funded,
+/// set as caller or callee and take part in transfers.
+///
+/// Forbidden by default, setting it panics then. The setting is kept when the
+/// environment is reset, see [`EngineConfig::allow_zero_address`].
+pub fn allow_zero_address(allowed: bool) {
     <EnvInstance as OnInstance>::on_instance(|instance| {
-        instance.engine.set_block_number(value);
+        instance.engine.config.allow_zero_address = allowed;
+    })
+}
+
+/// Sets the configuration of the off-chain environment.
+///
+/// The configuration is kept when the environment is reset, e.g. by [`run_test`].
+pub fn set_engine_config(config: EngineConfig) {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.config = config;
+    })
+}
+
+/// Sets whether decoding properties and storage values must consume exactly the
+/// bytes written by the engine.
+///
+/// In strict mode trailing bytes, e.g. of a `u64` value read as `u32`, yield
+/// [`OffChainError::DecodingFailed`] instead of a silently truncated value.
+/// Enabled by default in the tests of this crate. The mode is kept when the environment
+/// is reset.
+pub fn set_strict_decoding(strict: bool) {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.strict_decoding = strict;
+    })
+}
+
+/// Sets the maximum length of the data of an emitted event in bytes.
+///
+/// Emitting an event with more data panics, like the contract traps on-chain.
+/// Defaults to the limit of `pallet-contracts`, see [`EngineConfig`]. The limit is
+/// kept when the environment is reset.
+pub fn set_max_event_data_len(max_len: usize) {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.config.max_event_data_len = max_len;
+    })
+}
+
+/// Sets the maximum number of topics of an emitted event.
+///
+/// Emitting an event with more topics panics, like the contract traps on-chain.
+/// Defaults to the limit of `pallet-contracts`, see [`EngineConfig`]. The limit is
+/// kept when the environment is reset.
+pub fn set_max_topic_count(max_count: usize) {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.config.max_topic_count = max_count;
+    })
+}
+
+/// Returns the estimated number of bytes used by the off-chain database.
+///
+/// The memory caps set via [`set_engine_config`] apply to this figure, see
+/// [`ink_engine::ext::Engine::approximate_memory_usage`].
+pub fn approximate_memory_usage() -> usize {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.approximate_memory_usage()
+    })
+}
+
+/// Sets the account paying the storage deposits, `None` for the caller.
+///
+/// Storage deposits are only reserved if configured via [`set_engine_config`].
+pub fn set_storage_depositor<T>(depositor: Option<T::AccountId>)
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .set_storage_depositor(depositor.as_ref().map(scale::Encode::encode));
+    })
+}
+
+/// Returns the sum of the storage deposits reserved from the balance of
+/// `account_id`.
+pub fn get_storage_deposit<T>(account_id: T::AccountId) -> T::Balance
+where
+    T: Environment<Balance = u128>,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .get_storage_deposit(&scale::Encode::encode(&account_id))
+    })
+}
+
+/// Caps the net storage deposit charged by the next top-level execution, e.g. one
+/// dispatched via [`call_message`], and the executions nested in it. `None` for no
+/// cap.
+///
+/// Storage writes exceeding the cap make the contract trap, which reverts its frame.
+/// Nested calls can cap their own storage deposit via the `storage_deposit_limit` of
+/// their call parameters.
+pub fn set_storage_deposit_limit<T>(limit: Option<T::Balance>)
+where
+    T: Environment<Balance = u128>,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.set_storage_deposit_limit(limit);
+    })
+}
+
+/// Enables or disables the withdrawal of fees from the caller of top-level
+/// executions.
+///
+/// The fee is computed from the `gas_limit` set via [`set_engine_config`] and the
+/// gas price of the chain, see [`ink_engine::fee_accounting`].
+pub fn enable_fee_accounting(enabled: bool) {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.enable_fee_accounting(enabled)
+    })
+}
+
+/// Returns the sum of the fees paid by `account_id`, net of refunds.
+pub fn fees_paid<T>(account_id: T::AccountId) -> T::Balance
+where
+    T: Environment<Balance = u128>,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .fees_paid(&scale::Encode::encode(&account_id))
+    })
+}
+
+/// Simulates the consumption of `gas` by the ongoing top-level execution.
+///
+/// The fee for the consumed gas is not refunded when the execution ends.
+pub fn consume_gas(gas: u64) {
+    <EnvInstance as OnInstance>::on_instance(|instance| instance.engine.consume_gas(gas))
+}
+
+/// Returns the successful transfers in order.
+pub fn recorded_transfers() -> Vec<TransferRecord> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.recorded_transfers().to_vec()
+    })
+}
+
+/// Returns the transfers rejected because they would have created their destination
+/// account while [`EngineConfig::allow_implicit_account_creation`] is disabled.
+pub fn rejected_transfers() -> Vec<TransferRecord> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.rejected_transfers().to_vec()
+    })
+}
+
+/// Transfers `value` from `from` to `to` to set up a test without making `from` the
+/// callee, see [`ink_engine::ext::Engine::transfer_between`].
+///
+/// The transfer is recorded in [`fixture_transfers`], not in [`recorded_transfers`].
+/// Contracts still cannot move the funds of other accounts.
+///
+/// # Errors
+///
+/// If the balance of `from` does not cover `value` or `to` does not exist while
+/// [`EngineConfig::allow_implicit_account_creation`] is disabled.
+pub fn transfer_between<T>(
+    from: T::AccountId,
+    to: T::AccountId,
+    value: T::Balance,
+) -> Result<()>
+where
+    T: Environment<Balance = u128>,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .transfer_between(
+                &scale::Encode::encode(&from),
+                &scale::Encode::encode(&to),
+                value,
+            )
+            .map_err(Into::into)
+    })
+}
+
+/// Returns the number of recorded events, debug messages and calls dropped so far to
+/// stay within the retention limits of the [`EngineConfig`], e.g.
+/// [`EngineConfig::max_recorded_events`].
+pub fn dropped_records() -> DroppedRecords {
+    <EnvInstance as OnInstance>::on_instance(|instance| instance.engine.dropped_records())
+}
+
+/// Returns the successful fixture transfers in order, see [`transfer_between`].
+pub fn fixture_transfers() -> Vec<TransferRecord> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.fixture_transfers().to_vec()
+    })
+}
+
+/// Returns a snapshot of the balances, the contract storages and the number of
+/// events emitted by every contract.
+///
+/// Compare snapshots via [`Snapshot::diff`], e.g. to assert that an operation is
+/// free of side effects.
+pub fn snapshot() -> Snapshot {
+    <EnvInstance as OnInstance>::on_instance(|instance| instance.engine.snapshot())
+}
+
+/// Returns the accounts known to the off-chain environment ordered by their encoded
+/// ids, see [`ink_engine::ext::Engine::accounts`].
+pub fn accounts() -> Vec<AccountInfo> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.accounts().collect()
+    })
+}
+
+/// Returns the sum of the balances of all accounts.
+///
+/// # Panics
+///
+/// If the sum overflows.
+pub fn total_issuance<T>() -> T::Balance
+where
+    T: Environment<Balance = u128>, // Just temporary for the MVP!
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .total_issuance()
+            .expect("total issuance overflows")
+    })
+}
+
+/// Returns the gas consumed by the ongoing top-level execution, or since the last
+/// one if there is none.
+///
+/// Includes the weights charged by host functions, see [`set_weight_table`].
+pub fn gas_consumed() -> u64 {
+    <EnvInstance as OnInstance>::on_instance(|instance| instance.engine.gas_consumed())
+}
+
+/// Sets the weights charged by host functions, like storage accesses and events.
+///
+/// Only subsequent host functions are charged with the new weights. Defaults to an
+/// approximation of `pallet-contracts`, see [`WeightTable`]. The table is kept when
+/// the environment is reset.
+pub fn set_weight_table(table: WeightTable) {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.config.weights = table;
+    })
+}
+
+/// Returns the nonce of `account_id`.
+///
+/// The nonce is incremented for every call dispatched via [`call_message`] or
+/// [`call_constructor`] outside of other calls with `account_id` as caller, and by
+/// [`increment_nonce`].
+pub fn account_nonce<T>(account_id: T::AccountId) -> u64
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        let mut output = [0u8; 8];
+        let written = instance
+            .engine
+            .account_nonce(&scale::Encode::encode(&account_id), &mut &mut output[..])
+            .unwrap_or_else(|error| panic!("could not read account nonce: {error:?}"));
+        scale::Decode::decode(&mut &output[..written])
+            .unwrap_or_else(|error| panic!("could not decode account nonce: {error}"))
+    })
+}
+
+/// Increments the nonce of `account_id` by one.
+pub fn increment_nonce<T>(account_id: T::AccountId)
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .increment_nonce(&scale::Encode::encode(&account_id))
+    })
+}
+
+/// Returns the address of the contract the callee would instantiate from the code
+/// with `code_hash` with `input` and `salt`.
+///
+/// The nonce of the caller of the ongoing top-level call is part of the derivation,
+/// see [`ink_engine::nonce`].
+pub fn derive_contract_address<T>(
+    code_hash: T::Hash,
+    input: &[u8],
+    salt: &[u8],
+) -> T::AccountId
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        let address = instance.engine.derive_contract_address(
+            &scale::Encode::encode(&code_hash),
+            input,
+            salt,
+        );
+        scale::Decode::decode(&mut &address[..])
+            .unwrap_or_else(|error| panic!("could not decode contract address: {error}"))
+    })
+}
+
+/// Adds a lock for `reason` to the contract `account_id`.
+///
+/// A locked contract cannot terminate: [`crate::terminate_contract`] traps instead
+/// of removing the contract, leaving all balances untouched.
+pub fn lock_contract<T>(account_id: T::AccountId, reason: &str)
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .lock_contract(&scale::Encode::encode(&account_id), reason)
+    })
+}
+
+/// Removes one lock for `reason` from the contract `account_id`.
+///
+/// Returns `false` if the contract holds no lock for `reason`.
+pub fn unlock_contract<T>(account_id: T::AccountId, reason: &str) -> bool
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .unlock_contract(&scale::Encode::encode(&account_id), reason)
+    })
+}
+
+/// Sets a caller for the next call.
+pub fn set_caller<T>(caller: T::AccountId)
+where
+    T: Environment,
+    <T as Environment>::AccountId: From<[u8; 32]>,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.set_caller(scale::Encode::encode(&caller));
+    })
+}
+
+/// Enters a synthetic execution of the current callee called by the contract
+/// `caller`, as if the current caller had called `caller` which in turn called the
+/// callee.
+///
+/// Inside of it [`caller`][`crate::caller`] returns `caller` and
+/// [`caller_is_origin`][`crate::caller_is_origin`] returns `false`. The caller need
+/// not be a registered contract. Exit it via [`pop_caller_frame`], or use
+/// [`with_caller_frame`].
+pub fn push_caller_frame<T>(caller: T::AccountId)
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .push_caller_frame(scale::Encode::encode(&caller));
+    })
+}
+
+/// Exits the synthetic execution entered via [`push_caller_frame`].
+///
+/// # Panics
+///
+/// If the current execution was not entered via [`push_caller_frame`].
+pub fn pop_caller_frame() {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.pop_caller_frame();
+    })
+}
+
+/// Runs `f` in a synthetic execution of the current callee called by the contract
+/// `caller`, see [`push_caller_frame`].
+///
+/// The synthetic execution is exited also if `f` panics.
+pub fn with_caller_frame<T, F, R>(caller: T::AccountId, f: F) -> R
+where
+    T: Environment,
+    F: FnOnce() -> R,
+{
+    push_caller_frame::<T>(caller);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    pop_caller_frame();
+    result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+/// The origin of a top-level execution, i.e. who dispatched the transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin<AccountId> {
+    /// The transaction was signed by the account.
+    Signed(AccountId),
+    /// The transaction was dispatched by root, e.g. by governance.
+    Root,
+    /// The transaction was not signed.
+    None,
+}
+
+impl<AccountId> From<Origin<AccountId>> for ink_engine::Origin
+where
+    AccountId: scale::Encode,
+{
+    fn from(origin: Origin<AccountId>) -> Self {
+        match origin {
+            Origin::Signed(account_id) => {
+                ink_engine::Origin::Signed(scale::Encode::encode(&account_id))
+            }
+            Origin::Root => ink_engine::Origin::Root,
+            Origin::None => ink_engine::Origin::None,
+        }
+    }
+}
+
+/// Sets the origin of the next top-level execution.
+///
+/// A signed origin is the caller like set via [`set_caller`]. Executions dispatched
+/// by root or without origin have no caller, reading it traps like on-chain.
+/// Chain extensions can query the origin via `Engine::origin` in
+/// [`ink_engine::ChainExtension::call_with_engine`].
+pub fn set_origin<T>(origin: Origin<T::AccountId>)
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.set_origin(origin.into());
+    })
+}
+
+/// Runs `f` with `origin` as the origin of the top-level execution, see
+/// [`set_origin`].
+///
+/// The previous origin is restored afterwards, also if `f` panics.
+pub fn call_with_origin<T, F, R>(origin: Origin<T::AccountId>, f: F) -> R
+where
+    T: Environment,
+    F: FnOnce() -> R,
+{
+    let previous = <EnvInstance as OnInstance>::on_instance(|instance| {
+        let previous = instance.engine.origin();
+        instance.engine.set_origin(origin.into());
+        previous
+    });
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        match previous {
+            Some(previous) => instance.engine.set_origin(previous),
+            None => instance.engine.exec_context.set_caller(None),
+        }
+    });
+    result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+/// Runs `f` against a fork of the off-chain environment and returns its result.
+///
+/// The fork is a copy of the environment installed in its place while `f` runs,
+/// afterwards the original environment is restored, also if `f` panics. Hence, the
+/// storage writes, transfers and events of `f` do not affect the original. The
+/// registered callbacks, like chain extensions, are shared with the fork.
+pub fn with_fork<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let original = <EnvInstance as OnInstance>::on_instance(|instance| {
+        let fork = instance.clone();
+        core::mem::replace(instance, fork)
+    });
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    <EnvInstance as OnInstance>::on_instance(|instance| *instance = original);
+    result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+/// Sets the callee for the next call.
+pub fn set_callee<T>(callee: T::AccountId)
+where
+    T: Environment,
+    <T as Environment>::AccountId: From<[u8; 32]>,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.set_callee(scale::Encode::encode(&callee));
+    })
+}
+
+/// Sets an account as a contract
+pub fn set_contract<T>(contract: T::AccountId)
+where
+    T: Environment,
+    <T as Environment>::AccountId: From<[u8; 32]>,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .set_contract(scale::Encode::encode(&contract));
+    })
+}
+
+/// Returns a boolean to indicate whether an account is a contract
+pub fn is_contract<T>(contract: T::AccountId) -> bool
+where
+    T: Environment,
+    <T as Environment>::AccountId: From<[u8; 32]>,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .is_contract(scale::Encode::encode(&contract))
+    })
+}
+
+/// Returns the kind of the account `account_id`, which determines whether it is
+/// callable.
+///
+/// Calls of user accounts and of contracts which terminated themselves fail with
+/// [`ReturnErrorCode::NotCallable`](crate::ReturnErrorCode::NotCallable).
+pub fn account_kind<T>(account_id: T::AccountId) -> AccountKind
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .account_kind(&scale::Encode::encode(&account_id))
+    })
+}
+
+/// Gets the currently set callee.
+///
+/// This is account id of the currently executing contract.
+pub fn callee<T>() -> T::AccountId
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        let callee = instance.engine.get_callee();
+        scale::Decode::decode(&mut &callee[..])
+            .unwrap_or_else(|err| panic!("encoding failed: {err}"))
+    })
+}
+
+/// Returns the total number of reads and writes of the contract's storage.
+pub fn get_contract_storage_rw<T>(account_id: &T::AccountId) -> (usize, usize)
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .get_contract_storage_rw(scale::Encode::encode(&account_id))
+    })
+}
+
+/// Returns the number of clears of keys without a value in the contract's storage.
+///
+/// Such clears return `None` and are not counted as writes by
+/// [`get_contract_storage_rw`].
+pub fn count_noop_clears<T>(account_id: &T::AccountId) -> usize
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .count_noop_clears(&scale::Encode::encode(&account_id))
+    })
+}
+
+/// Returns the number of writes to the contract's storage which created a cell.
+///
+/// Such writes return `None` from [`set_contract_storage`](crate::set_contract_storage),
+/// writes overwriting a value are not counted.
+pub fn count_new_cells<T>(account_id: &T::AccountId) -> usize
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .count_new_cells(&scale::Encode::encode(&account_id))
+    })
+}
+
+/// Writes `entries` of keys and values into the storage of the contract
+/// `account_id` and returns the number of written entries.
+///
+/// Meant to set up large storages, e.g. of a `Mapping`, much faster than writing
+/// the entries one by one. The keys and values are encoded as by
+/// [`crate::set_contract_storage`]. The writes are reported as a single batch to
+/// the recorder and not charged for.
+pub fn seed_storage<T, K, V>(
+    account_id: T::AccountId,
+    entries: impl IntoIterator<Item = (K, V)>,
+) -> usize
+where
+    T: Environment,
+    K: scale::Encode,
+    V: ink_storage_traits::Storable,
+{
+    let entries = entries.into_iter().map(|(key, value)| {
+        let mut encoded = Vec::new();
+        ink_storage_traits::Storable::encode(&value, &mut encoded);
+        (scale::Encode::encode(&key), encoded)
+    });
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .seed_storage(&scale::Encode::encode(&account_id), entries)
+    })
+}
+
+/// Returns the key of the entry at `encoded_key` of a `Mapping` with the storage key
+/// `base`, as the contract passes it to [`crate::set_contract_storage`].
+///
+/// The key is the SCALE encoding of `(base, key)`, i.e. the little endian bytes of
+/// `base` followed by `encoded_key`. Like every key of the contract storage, the
+/// engine hashes it together with the account of the contract via blake2.
+pub fn mapping_storage_key(base: &Key, encoded_key: &[u8]) -> Vec<u8> {
+    let mut key = scale::Encode::encode(base);
+    key.extend_from_slice(encoded_key);
+    key
+}
+
+/// Writes `value` at `key` of the `Mapping` with the storage key `base` into the
+/// storage of the callee, see [`mapping_storage_key`].
+///
+/// The entry is seeded like by [`seed_storage`] and read back by the `Mapping` of
+/// the contract.
+pub fn seed_mapping_entry<K, V>(base: &Key, key: &K, value: &V)
+where
+    K: scale::Encode,
+    V: ink_storage_traits::Storable,
+{
+    let mut encoded = Vec::new();
+    ink_storage_traits::Storable::encode(value, &mut encoded);
+    let key = mapping_storage_key(base, &scale::Encode::encode(key));
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        let callee = instance.engine.get_callee();
+        instance.engine.seed_storage(&callee, [(key, encoded)]);
+    })
+}
+
+/// Returns the number of events emitted by the contract `account_id`.
+///
+/// Events emitted by a nested call count for the callee of that call.
+pub fn events_emitted<T>(account_id: &T::AccountId) -> u32
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .events_emitted(&scale::Encode::encode(&account_id))
+    })
+}
+
+/// Returns the number of bytes of the topics and data of the events emitted by the
+/// contract `account_id`.
+pub fn event_bytes_emitted<T>(account_id: &T::AccountId) -> u64
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .event_bytes_emitted(&scale::Encode::encode(&account_id))
+    })
+}
+
+/// Returns the storage accesses and emitted events of the contract `account_id`.
+pub fn contract_statistics<T>(account_id: &T::AccountId) -> ContractStatistics
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .contract_statistics(&scale::Encode::encode(&account_id))
+    })
+}
+
+/// Replaces the recorder of the statistics of the off-chain environment, e.g. to
+/// stream them to a file, and returns the previous one.
+///
+/// The statistics accessors, like [`get_contract_storage_rw`], only report statistics
+/// while an [`InMemoryRecorder`] is set.
+pub fn set_recorder(recorder: Box<dyn Recorder>) -> Box<dyn Recorder> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.set_recorder(recorder)
+    })
+}
+
+/// Replaces the recorder of the statistics of the off-chain environment like
+/// [`set_recorder`], but invokes its hooks only once the operation recording the
+/// statistics completes, in order.
+///
+/// The hooks can thereby use the test API, e.g. [`get_account_balance`], which
+/// would use the environment reentrantly and panic if invoked by a recorder set via
+/// [`set_recorder`]. The previous recorder is dropped.
+pub fn set_deferred_recorder(recorder: Box<dyn Recorder>) {
+    let recorder = super::deferred_recorder::DeferredRecorder::new(recorder);
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.set_recorder(Box::new(recorder));
+    })
+}
+
+/// Records every change of the storage cell at `key` of the contract `account_id`
+/// from now on, see [`storage_changes`].
+///
+/// Watching a cell does not affect the counts of [`get_contract_storage_rw`].
+pub fn watch_storage_key<T, K>(account_id: T::AccountId, key: &K)
+where
+    T: Environment,
+    K: scale::Encode,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .watch_storage_key(&scale::Encode::encode(&account_id), &key.encode())
+    })
+}
+
+/// Returns the changes of the watched storage cell at `key` of the contract
+/// `account_id` in the order they happened.
+///
+/// The values are the encoded values as written by the contract.
+pub fn storage_changes<T, K>(account_id: T::AccountId, key: &K) -> Vec<StorageChange>
+where
+    T: Environment,
+    K: scale::Encode,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .storage_changes(&scale::Encode::encode(&account_id), &key.encode())
+    })
+}
+
+/// Asserts that nothing is written to the storage of the contract `account_id` until
+/// the returned guard is dropped or checked, e.g. to verify that a message taking
+/// `&self` is read-only.
+///
+/// ```ignore
+/// let guard = ink_env::test::assert_no_storage_writes::<DefaultEnvironment>(&contract);
+/// flipper.get();
+/// guard.check();
+/// ```
+///
+/// Writes and removals count, even if they leave the value unchanged. Only the written
+/// keys are logged, the storage is not compared.
+pub fn assert_no_storage_writes<T>(account_id: &T::AccountId) -> NoStorageWrites
+where
+    T: Environment,
+{
+    let account_id = scale::Encode::encode(account_id);
+    let log = <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.start_storage_write_log(&account_id)
+    });
+    NoStorageWrites {
+        account_id,
+        log,
+        checked: false,
+    }
+}
+
+/// A guard asserting that nothing is written to the storage of a contract, see
+/// [`assert_no_storage_writes`].
+#[must_use = "the writes are only checked when the guard is dropped or checked"]
+#[derive(Debug)]
+pub struct NoStorageWrites {
+    account_id: Vec<u8>,
+    log: usize,
+    checked: bool,
+}
+
+impl NoStorageWrites {
+    /// Asserts that nothing has been written to the storage of the contract since
+    /// the guard was created.
+    ///
+    /// # Panics
+    ///
+    /// If anything has been written. The panic message lists the written keys.
+    pub fn check(mut self) {
+        self.assert_unwritten();
+    }
+
+    fn assert_unwritten(&mut self) {
+        self.checked = true;
+        let written = <EnvInstance as OnInstance>::on_instance(|instance| {
+            instance.engine.end_storage_write_log(self.log)
+        });
+        if !written.is_empty() {
+            let keys: Vec<String> = written.iter().map(|key| encode_hex(key)).collect();
+            panic!(
+                "expected no storage writes to {}, written keys: {}",
+                encode_hex(&self.account_id),
+                keys.join(", ")
+            );
+        }
+    }
+}
+
+impl Drop for NoStorageWrites {
+    fn drop(&mut self) {
+        if !self.checked && !std::thread::panicking() {
+            self.assert_unwritten();
+        }
+    }
+}
+
+/// Returns the encoded value at the storage `key` of the contract `account_id`, if
+/// any.
+///
+/// Reading the value does not affect the counts of [`get_contract_storage_rw`].
+pub fn get_storage_raw<T, K>(account_id: T::AccountId, key: &K) -> Option<Vec<u8>>
+where
+    T: Environment,
+    K: scale::Encode,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .get_storage_raw(&scale::Encode::encode(&account_id), &key.encode())
+    })
+}
+
+/// Returns the value at the storage `key` of the contract `account_id` decoded as
+/// `V`, `None` if there is no value.
+///
+/// # Errors
+///
+/// If the value does not decode as `V`, or if not all of it is consumed.
+pub fn get_decoded_storage<T, K, V>(
+    account_id: T::AccountId,
+    key: &K,
+) -> core::result::Result<Option<V>, scale::Error>
+where
+    T: Environment,
+    K: scale::Encode,
+    V: scale::Decode,
+{
+    get_storage_raw::<T, K>(account_id, key)
+        .map(|value| scale::DecodeAll::decode_all(&mut &value[..]))
+        .transpose()
+}
+
+/// Asserts that the value at the storage `key` of the contract `account_id` decodes
+/// as `V` and equals `expected`.
+///
+/// # Panics
+///
+/// If there is no value, or if it does not decode as `V` or differs from `expected`.
+/// The panic message shows the expected and the actual value, the latter as hex if
+/// it does not decode.
+pub fn assert_storage_value<T, K, V>(account_id: T::AccountId, key: &K, expected: V)
+where
+    T: Environment,
+    K: scale::Encode,
+    V: scale::Decode + PartialEq + Debug,
+{
+    let actual = match get_storage_raw::<T, K>(account_id, key) {
+        None => String::from("<absent>"),
+        Some(value) => {
+            match <V as scale::DecodeAll>::decode_all(&mut &value[..]) {
+                Ok(decoded) if decoded == expected => return,
+                Ok(decoded) => format!("{decoded:?}"),
+                Err(error) => {
+                    format!("<undecodable> {} ({error})", encode_hex(&value))
+                }
+            }
+        }
+    };
+    panic!(
+        "storage value at key {} differs\n  expected: {expected:?}\n  actual:   {actual}",
+        encode_hex(&key.encode())
+    )
+}
+
+/// Sets the value transferred from the caller to the callee as part of the call.
+///
+/// Please note that the acting accounts should be set with [`set_caller()`] and
+/// [`set_callee()`] beforehand.
+pub fn set_value_transferred<T>(value: T::Balance)
+where
+    T: Environment<Balance = u128>, // Just temporary for the MVP!
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.set_value_transferred(value);
+    })
+}
+
+/// Transfers value from the caller account to the contract.
+///
+/// Please note that the acting accounts should be set with [`set_caller()`] and
+/// [`set_callee()`] beforehand.
+pub fn transfer_in<T>(value: T::Balance)
+where
+    T: Environment<Balance = u128>, // Just temporary for the MVP!
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        let caller = instance
+            .engine
+            .exec_context
+            .caller()
+            .expect("no caller has been set")
+            .as_bytes()
+            .to_vec();
+
+        let caller_old_balance = instance
+            .engine
+            .get_balance(caller.clone())
+            .unwrap_or_default();
+
+        let callee = instance.engine.get_callee();
+        let contract_old_balance = instance
+            .engine
+            .get_balance(callee.clone())
+            .unwrap_or_default();
+
+        instance
+            .engine
+            .set_balance(caller, caller_old_balance - value);
+        instance
+            .engine
+            .set_balance(callee, contract_old_balance + value);
+        instance.engine.set_value_transferred(value);
+    });
+}
+
+/// Handle to a contract deployed with [`deploy_test_contract`].
+pub struct TestContract<T>
+where
+    T: Environment,
+{
+    /// The account id of the deployed contract.
+    pub account_id: T::AccountId,
+}
+
+/// Deploys a contract for testing under `account_id`.
+///
+/// The contract is set as the callee, funded with `initial_balance`, marked
+/// as a contract and starts out with an empty storage. Hence, environmental
+/// functions like `balance` work right away without further setup.
+pub fn deploy_test_contract<T>(
+    account_id: T::AccountId,
+    initial_balance: T::Balance,
+) -> TestContract<T>
+where
+    T: Environment<Balance = u128>, // Just temporary for the MVP!
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .deploy_test_contract(scale::Encode::encode(&account_id), initial_balance);
+    });
+    TestContract { account_id }
+}
+
+/// Deploys a contract for testing under an account id derived from `salt`.
+///
+/// The same `salt` always results in the same account id, different salts
+/// result in different account ids. See [`deploy_test_contract`] for details.
+pub fn deploy_test_contract_with_salt<T>(
+    salt: &[u8],
+    initial_balance: T::Balance,
+) -> TestContract<T>
+where
+    T: Environment<Balance = u128>, // Just temporary for the MVP!
+    <T as Environment>::AccountId: From<[u8; 32]>,
+{
+    let mut account_id = <Blake2x256 as HashOutput>::Type::default();
+    let input = [b"test-contract:", salt].concat();
+    <Blake2x256 as CryptoHash>::hash(&input[..], &mut account_id);
+    deploy_test_contract::<T>(T::AccountId::from(account_id), initial_balance)
+}
+
+/// Returns the amount of storage cells used by the account `account_id`.
+///
+/// Returns `None` if the `account_id` is non-existent.
+pub fn count_used_storage_cells<T>(account_id: &T::AccountId) -> Result<usize>
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .count_used_storage_cells(&scale::Encode::encode(&account_id))
+            .map_err(Into::into)
+    })
+}
+
+/// Returns the storage of the contract at `account_id` as key-value pairs.
+///
+/// The entries are ordered by their key bytes.
+pub fn storage_dump<T>(account_id: &T::AccountId) -> Vec<(Vec<u8>, Vec<u8>)>
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .storage_dump(&scale::Encode::encode(&account_id))
+    })
+}
+
+/// Returns the root hash of the storage of the contract at `account_id`.
+///
+/// Equal storages have equal roots, see `ink_engine::ext::Engine::storage_root`
+/// for the encoding.
+pub fn storage_root<T>(account_id: &T::AccountId) -> [u8; 32]
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .storage_root(&scale::Encode::encode(account_id))
+    })
+}
+
+/// Exports the balances and contract storages of the off-chain environment as JSON.
+///
+/// The export is deterministic: balances are ordered by account id bytes and
+/// storage entries by contract account id and key bytes.
+pub fn export_state_json() -> String {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.export_state_json()
+    })
+}
+
+/// Imports the balances and contract storages of a state exported by
+/// [`export_state_json`] on top of the current state.
+///
+/// States exported by former versions are migrated, see
+/// [`EngineState::migrate_v1_to_v2`].
+pub fn import_state(state: EngineState) -> core::result::Result<(), StateError> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.import_state(state)
+    })
+}
+
+/// Starts recording the interactions with the off-chain environment.
+///
+/// The recorded [`Scenario`] can be replayed against a fresh engine via
+/// `ink_engine::ext::Engine::replay`.
+pub fn start_recording_scenario() {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.start_recording_scenario()
+    })
+}
+
+/// Returns the interactions recorded so far as an encoded [`ActivityLog`], e.g. to
+/// diff them against the ones of another branch via [`ActivityLog::diff`].
+///
+/// The recording is not stopped. The log is empty if no recording was started via
+/// [`start_recording_scenario`].
+pub fn export_activity() -> Vec<u8> {
+    <EnvInstance as OnInstance>::on_instance(|instance| instance.engine.export_activity())
+}
+
+/// Stops recording the interactions with the off-chain environment and returns
+/// them.
+pub fn stop_recording_scenario() -> Scenario {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.stop_recording_scenario()
+    })
+}
+
+/// Sets the block timestamp for the next [`advance_block`] invocation.
+///
+/// The engine stores timestamps as `u64`, they are converted from and to the
+/// `Timestamp` type of the environment.
+///
+/// # Panics
+///
+/// If `value` does not fit into a `u64`.
+pub fn set_block_timestamp<T>(value: T::Timestamp)
+where
+    T: Environment,
+{
+    let value: u64 = value.try_into().unwrap_or_else(|_| {
+        panic!("the block timestamp overflows the `u64` timestamps of the engine")
+    });
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.set_block_timestamp(value);
+    })
+}
+
+/// Sets the block number for the next [`advance_block`] invocation.
+///
+/// The engine stores block numbers as `u32`, they are converted from and to the
+/// `BlockNumber` type of the environment.
+///
+/// # Panics
+///
+/// If `value` does not fit into a `u32`.
+pub fn set_block_number<T>(value: T::BlockNumber)
+where
+    T: Environment,
+{
+    let value: u32 = value.try_into().unwrap_or_else(|_| {
+        panic!("the block number overflows the `u32` block numbers of the engine")
+    });
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.set_block_number(value);
     })
 }
 
@@ -296,10 +1866,50 @@ where
     T: Environment,
     F: FnOnce(DefaultAccounts<T>) -> Result<()>,
     <T as Environment>::AccountId: From<[u8; 32]>,
+{
+    f(reset_with_default_accounts::<T>())
+}
+
+/// Runs the given async test function with the default configuration for the
+/// off-chain environment, see [`run_test`].
+///
+/// The test is executed on a current-thread runtime with an engine of its own which
+/// is used across all of its await points, see [`EngineGuard::scope`].
+///
+/// # Panics
+///
+/// If the runtime cannot be created.
+#[cfg(feature = "async-tests")]
+pub fn run_async_test<T, F, Fut>(f: F) -> Fut::Output
+where
+    T: Environment,
+    F: FnOnce(DefaultAccounts<T>) -> Fut,
+    Fut: core::future::Future,
+    <T as Environment>::AccountId: From<[u8; 32]>,
+{
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap_or_else(|error| {
+            panic!("failed to create the runtime of the async test: {error}")
+        });
+    runtime.block_on(EngineGuard::scope(Engine::new(), async move {
+        f(reset_with_default_accounts::<T>()).await
+    }))
+}
+
+/// Resets the off-chain environment and funds the default accounts, making alice
+/// the caller and callee.
+fn reset_with_default_accounts<T>() -> DefaultAccounts<T>
+where
+    T: Environment,
+    <T as Environment>::AccountId: From<[u8; 32]>,
 {
     let default_accounts = default_accounts::<T>();
     <EnvInstance as OnInstance>::on_instance(|instance| {
         instance.engine.initialize_or_reset();
+        instance.block_callbacks.clear();
+        instance.pushed_contexts.clear();
 
         let encoded_alice = scale::Encode::encode(&default_accounts.alice);
         instance.engine.set_caller(encoded_alice.clone());
@@ -325,7 +1935,57 @@ where
             .engine
             .set_balance(scale::Encode::encode(&default_accounts.frank), 0);
     });
-    f(default_accounts)
+    default_accounts
+}
+
+/// The maximum length in bytes of the engine state written for a failing test.
+const FAILURE_DUMP_LIMIT: usize = 4096;
+
+#[cfg(test)]
+std::thread_local!(
+    /// The engine state last written by the panic hook on the current thread.
+    pub(super) static LAST_FAILURE_DUMP: core::cell::RefCell<Option<String>> =
+        const { core::cell::RefCell::new(None) };
+);
+
+/// Installs a panic hook which writes the state of the off-chain engine to stderr
+/// when a test panics, see [`Engine::debug_dump`].
+///
+/// The hook is installed once per process, further calls have no effect. It chains
+/// to the previously installed hook and only writes the state for panics on threads
+/// which used the off-chain environment. Tests generated by `#[ink::test]` install
+/// it automatically.
+pub fn install_failure_dump() {
+    static INSTALL: std::sync::Once = std::sync::Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            previous(info);
+            if let Some(dump) = failure_dump() {
+                eprintln!("{dump}");
+                #[cfg(test)]
+                LAST_FAILURE_DUMP.with(|last| *last.borrow_mut() = Some(dump));
+            }
+        }))
+    })
+}
+
+/// Returns the state of the off-chain engine of the current thread as written by
+/// the hook installed via [`install_failure_dump`], truncated to 4096 bytes.
+///
+/// Returns `None` if the current thread has not used the off-chain environment or
+/// if the environment is in use.
+pub fn failure_dump() -> Option<String> {
+    let mut dump = super::engine_debug_dump()?;
+    if dump.len() > FAILURE_DUMP_LIMIT {
+        let mut end = FAILURE_DUMP_LIMIT;
+        while !dump.is_char_boundary(end) {
+            end -= 1;
+        }
+        dump.truncate(end);
+        dump.push_str("... (truncated)");
+    }
+    Some(dump)
 }
 
 /// Returns the default accounts for testing purposes:
@@ -364,6 +2024,78 @@ where
     pub frank: T::AccountId,
 }
 
+/// Returns the current state of the execution context of the off-chain environment.
+pub fn exec_context_snapshot() -> ExecContextSnapshot {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.exec_context_snapshot()
+    })
+}
+
+/// The state of the execution context with the types of the environment, see
+/// [`exec_context`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecContext<T>
+where
+    T: Environment,
+{
+    /// The caller of the current execution, if any.
+    pub caller: Option<T::AccountId>,
+    /// The callee of the current execution, if any.
+    pub callee: Option<T::AccountId>,
+    /// The value transferred to the callee.
+    pub value_transferred: T::Balance,
+    /// The current block number.
+    pub block_number: T::BlockNumber,
+    /// The current block timestamp.
+    pub timestamp: T::Timestamp,
+    /// The origin of the top-level execution, `None` if it is signed but no caller
+    /// has been set.
+    pub origin: Option<Origin<T::AccountId>>,
+}
+
+/// Returns the current state of the execution context, decoded into the types of
+/// the environment.
+///
+/// Within nested calls, e.g. via [`call_message`], the state is the one of the
+/// innermost call.
+pub fn exec_context<T>() -> ExecContext<T>
+where
+    T: Environment<BlockNumber = u32, Timestamp = u64, Balance = u128>,
+{
+    fn decode<A: scale::Decode>(account_id: Vec<u8>) -> A {
+        scale::Decode::decode(&mut &account_id[..])
+            .unwrap_or_else(|err| panic!("decoding account id failed: {err}"))
+    }
+
+    let snapshot = exec_context_snapshot();
+    ExecContext {
+        caller: snapshot.caller.map(decode),
+        callee: snapshot.callee.map(decode),
+        value_transferred: snapshot.value_transferred,
+        block_number: snapshot.block_number,
+        timestamp: snapshot.block_timestamp,
+        origin: snapshot.origin.map(|origin| {
+            match origin {
+                ink_engine::Origin::Signed(account_id) => {
+                    Origin::Signed(decode(account_id))
+                }
+                ink_engine::Origin::Root => Origin::Root,
+                ink_engine::Origin::None => Origin::None,
+            }
+        }),
+    }
+}
+
+/// Returns the value transferred to the current callee.
+pub fn value_transferred<T>() -> T::Balance
+where
+    T: Environment<Balance = u128>,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.exec_context.value_transferred()
+    })
+}
+
 /// Returns the recorded emitted events in order.
 pub fn recorded_events() -> impl Iterator<Item = EmittedEvent> {
     <EnvInstance as OnInstance>::on_instance(|instance| {
@@ -374,6 +2106,554 @@ pub fn recorded_events() -> impl Iterator<Item = EmittedEvent> {
     })
 }
 
+/// Returns the number of recorded emitted events, without cloning them like
+/// [`recorded_events`] does.
+pub fn recorded_event_count() -> usize {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.emitted_event_count()
+    })
+}
+
+/// Returns the recorded emitted events following the first `skip` ones, cloning
+/// only them.
+fn recorded_events_after(skip: usize) -> Vec<EmittedEvent> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .emitted_events()
+            .skip(skip)
+            .cloned()
+            .map(Into::into)
+            .collect()
+    })
+}
+
+/// Returns the first recorded event which decodes as `E` and satisfies `predicate`.
+///
+/// Events which do not decode as `E` are skipped. Use [`find_event_in`] to search a
+/// subset of the recorded events, e.g. the ones of a [`CallResult`].
+pub fn find_event<E>(predicate: impl Fn(&E) -> bool) -> Option<E>
+where
+    E: scale::Decode,
+{
+    find_event_in(recorded_events(), predicate)
+}
+
+/// Returns the first of `events` which decodes as `E` and satisfies `predicate`.
+///
+/// Events which do not decode as `E` are skipped.
+pub fn find_event_in<E>(
+    events: impl IntoIterator<Item = EmittedEvent>,
+    predicate: impl Fn(&E) -> bool,
+) -> Option<E>
+where
+    E: scale::Decode,
+{
+    events
+        .into_iter()
+        .filter_map(|event| decode_event(&event).ok())
+        .find(|event| predicate(event))
+}
+
+/// Asserts that an event which decodes as `E` and satisfies `predicate` has been
+/// recorded.
+///
+/// # Panics
+///
+/// If no recorded event matches. The panic message lists all recorded events,
+/// decoded as `E` where possible and as hex otherwise.
+pub fn assert_event_emitted<E>(predicate: impl Fn(&E) -> bool)
+where
+    E: scale::Decode + Debug,
+{
+    assert_event_emitted_in(recorded_events(), predicate)
+}
+
+/// Asserts that one of `events` decodes as `E` and satisfies `predicate`.
+///
+/// # Panics
+///
+/// If none of `events` matches. The panic message lists all `events`, decoded as
+/// `E` where possible and as hex otherwise.
+pub fn assert_event_emitted_in<E>(
+    events: impl IntoIterator<Item = EmittedEvent>,
+    predicate: impl Fn(&E) -> bool,
+) where
+    E: scale::Decode + Debug,
+{
+    let events: Vec<EmittedEvent> = events.into_iter().collect();
+    if find_event_in(events.iter().cloned(), predicate).is_some() {
+        return
+    }
+    let recorded: Vec<String> = events
+        .iter()
+        .map(|event| {
+            match decode_event::<E>(event) {
+                Ok(decoded) => format!("{decoded:?}"),
+                Err(_) => encode_hex(&event.data),
+            }
+        })
+        .collect();
+    panic!(
+        "no event of type `{}` matching the predicate was recorded, recorded events: {recorded:?}",
+        core::any::type_name::<E>()
+    )
+}
+
+/// Asserts that the recorded events equal `expected` in order, ignoring the order
+/// of the topics of every event.
+///
+/// The data of the events is always compared exactly, see
+/// [`EmittedEvent::eq_unordered_topics`].
+///
+/// # Panics
+///
+/// If the number of recorded events differs or any recorded event does not equal
+/// the expected one at its position.
+pub fn assert_events_unordered(expected: &[EmittedEvent]) {
+    let recorded: Vec<EmittedEvent> = recorded_events().collect();
+    assert_eq!(
+        recorded.len(),
+        expected.len(),
+        "expected {} events, recorded events: {recorded:?}",
+        expected.len()
+    );
+    for (index, (recorded, expected)) in recorded.iter().zip(expected).enumerate() {
+        assert!(
+            recorded.eq_unordered_topics(expected),
+            "event {index} does not match, recorded: {recorded:?}, expected: {expected:?}"
+        );
+    }
+}
+
+/// Returns `bytes` hex encoded with a `0x` prefix.
+fn encode_hex(bytes: &[u8]) -> String {
+    let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("0x{hex}")
+}
+
+/// Decodes the data of `event` as `E`, requiring all of it to be consumed.
+fn decode_event<E>(event: &EmittedEvent) -> core::result::Result<E, scale::Error>
+where
+    E: scale::Decode,
+{
+    scale::DecodeAll::decode_all(&mut &event.data[..])
+}
+
+/// Returns the outcome of the last completed call dispatched via [`call_message`]
+/// or [`call_constructor`], if any.
+///
+/// The counts of an outcome include the ones of the calls nested in it.
+pub fn last_call_outcome() -> Option<CallOutcome> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.last_call_outcome()
+    })
+}
+
+/// Returns the records of all execution frames in the order they were entered.
+///
+/// The first record is the root frame covering everything outside of calls
+/// dispatched via [`call_message`] and [`call_constructor`].
+pub fn recorded_frames() -> Vec<FrameRecord> {
+    <EnvInstance as OnInstance>::on_instance(|instance| instance.engine.recorded_frames())
+}
+
+/// Returns the raw input bytes of the last dispatched message, constructor or
+/// nested call, i.e. the selector followed by the encoded arguments.
+///
+/// The input is recorded before the function to dispatch to is resolved, hence it
+/// is available after a call failed with an unknown selector.
+pub fn last_dispatch_input() -> Option<Vec<u8>> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.last_dispatch_input()
+    })
+}
+
+/// Returns the inputs of all dispatched calls in the order they were entered,
+/// with their decoded selectors.
+pub fn recorded_calls() -> Vec<RecordedCall> {
+    <EnvInstance as OnInstance>::on_instance(|instance| instance.engine.recorded_calls())
+}
+
+/// Registers the entry points of the contract at `account_id`.
+///
+/// Calls to the contract via [`call_message`] and [`call_constructor`] are
+/// dispatched to `entry_points`. For ink! smart contracts these are provided by
+/// `ink::reflect::ContractEntryPoints::ENTRY_POINTS`.
+pub fn register_contract<T>(account_id: T::AccountId, entry_points: EntryPoints)
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .register_contract(scale::Encode::encode(&account_id), entry_points);
+    })
+}
+
+/// Registers the entry points of the code with `code_hash`.
+///
+/// Delegate calls of the code, e.g. via [`crate::invoke_contract_delegate`], are
+/// dispatched through the `call` entry point in the context of the calling contract.
+pub fn register_code<T>(code_hash: T::Hash, entry_points: EntryPoints)
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .register_code(scale::Encode::encode(&code_hash), entry_points);
+    })
+}
+
+/// Uploads `code` and returns its code hash.
+///
+/// The hash of bytes is their BLAKE2 256-bit hash, mocked code is identified by a
+/// synthetic hash of its entry points. Uploading identical code again yields the
+/// same hash. Contracts are only instantiated from uploaded or registered code.
+///
+/// # Errors
+///
+/// [`OffChainError::CodeTooLarge`] if the bytes exceed
+/// [`EngineConfig::max_code_len`].
+pub fn upload_code<T>(code: Code) -> Result<T::Hash>
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        let code_hash = instance.engine.upload_code(code)?;
+        Ok(scale::Decode::decode(&mut code_hash.as_bytes())
+            .expect("the code hash is a valid hash of the environment"))
+    })
+}
+
+/// Returns `true` if code has been uploaded or registered under `code_hash`.
+pub fn code_exists<T>(code_hash: T::Hash) -> bool
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .code_exists(&scale::Encode::encode(&code_hash))
+    })
+}
+
+/// Registers `handler` for the message with `selector` of the mocked contract at
+/// `account_id`.
+///
+/// Calls of the message via [`call_message`] are dispatched to `handler`, taking
+/// precedence over the entry points of a contract registered via
+/// [`register_contract`]. The handler reads its input via [`crate::decode_input`]
+/// and returns via [`crate::return_value`], like an entry point.
+///
+/// # Errors
+///
+/// If a handler was already registered for `selector` of the contract. The
+/// registered handler is kept in this case.
+pub fn register_contract_message<T>(
+    account_id: T::AccountId,
+    selector: [u8; 4],
+    handler: fn(),
+) -> core::result::Result<(), DuplicateSelector>
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.register_contract_message(
+            scale::Encode::encode(&account_id),
+            selector,
+            handler,
+        )
+    })
+}
+
+/// Registers `handler` for all messages of the mocked contract at `account_id`
+/// without a handler registered via [`register_contract_message`].
+///
+/// Allows to test how callers handle calls of unknown selectors. Replaces a
+/// fallback handler previously registered there.
+pub fn register_fallback_message<T>(account_id: T::AccountId, handler: fn())
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .register_fallback_message(scale::Encode::encode(&account_id), handler);
+    })
+}
+
+/// Returns the selectors with a handler registered via [`register_contract_message`]
+/// for the mocked contract at `account_id` in ascending order.
+pub fn registered_selectors<T>(account_id: T::AccountId) -> Vec<[u8; 4]>
+where
+    T: Environment,
+{
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance
+            .engine
+            .registered_selectors(&scale::Encode::encode(&account_id))
+    })
+}
+
+/// The result of dispatching a call to a contract.
+#[derive(Clone)]
+pub struct CallResult {
+    /// The flags the contract returned with.
+    pub flags: ReturnFlags,
+    /// The SCALE encoded data the contract returned.
+    pub data: Vec<u8>,
+    /// The events emitted by the contract during the call.
+    pub events: Vec<EmittedEvent>,
+}
+
+impl CallResult {
+    /// Returns `true` if the contract reverted the call.
+    pub fn did_revert(&self) -> bool {
+        self.flags.contains(ReturnFlags::REVERT)
+    }
+
+    /// Decodes the data the contract returned.
+    pub fn decode<R>(&self) -> Result<R>
+    where
+        R: scale::Decode,
+    {
+        scale::Decode::decode(&mut &self.data[..]).map_err(Into::into)
+    }
+}
+
+/// The net change of the storage deposits caused by a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageDeposit {
+    /// The call reserved more deposits than it refunded.
+    Charge(u128),
+    /// The call refunded more deposits than it reserved.
+    Refund(u128),
+}
+
+/// The result of a call executed via [`dry_run_call`].
+#[derive(Clone)]
+pub struct DryRunResult {
+    /// The gas consumed by the call, including the weights charged by host functions.
+    pub gas_consumed: u64,
+    /// The net change of the storage deposits, see [`ink_engine::storage_deposit`].
+    pub storage_deposit: StorageDeposit,
+    /// The flags the contract returned with.
+    pub flags: ReturnFlags,
+    /// The SCALE encoded data the contract returned.
+    pub return_data: Vec<u8>,
+    /// The events emitted during the call.
+    pub events: Vec<EmittedEvent>,
+}
+
+impl DryRunResult {
+    /// Returns `true` if the contract reverted the call.
+    pub fn did_revert(&self) -> bool {
+        self.flags.contains(ReturnFlags::REVERT)
+    }
+}
+
+/// Executes the call of `params` on a copy of the off-chain environment and
+/// discards the copy, leaving the environment untouched.
+///
+/// The call is entered and dispatched the same way as by [`crate::invoke_contract`],
+/// hence the gas consumed and the storage deposit match those of executing the call.
+/// Like the fee, the gas of a top-level call is counted from its start, the gas of
+/// a call nested in another execution from the time it is entered.
+///
+/// # Errors
+///
+/// If the call cannot be entered, e.g. since no contract is registered at the callee
+/// or its balance does not cover the transferred value.
+///
+/// # Panics
+///
+/// If the called contract panics. The environment is restored beforehand.
+pub fn dry_run_call<E, Args, R>(
+    params: &CallParams<E, Call<E>, Args, R>,
+) -> Result<DryRunResult>
+where
+    E: Environment,
+    Args: scale::Encode,
+{
+    let (original, top_level, gas_before, deposit_before, events_before) =
+        <EnvInstance as OnInstance>::on_instance(|instance| {
+            let engine = &instance.engine;
+            (
+                instance.clone(),
+                engine.exec_context.depth() == 0,
+                engine.gas_consumed(),
+                engine.total_storage_deposit(),
+                engine.emitted_event_count(),
+            )
+        });
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let entry_point = enter_contract_call(params)?;
+        let (flags, return_data) = execute_entry_point(entry_point);
+        let (gas_consumed, deposit_after) =
+            <EnvInstance as OnInstance>::on_instance(|instance| {
+                let engine = &instance.engine;
+                let gas_before = if top_level { 0 } else { gas_before };
+                (
+                    engine.gas_consumed().saturating_sub(gas_before),
+                    engine.total_storage_deposit(),
+                )
+            });
+        let storage_deposit = if deposit_after >= deposit_before {
+            StorageDeposit::Charge(deposit_after - deposit_before)
+        } else {
+            StorageDeposit::Refund(deposit_before - deposit_after)
+        };
+        Ok(DryRunResult {
+            gas_consumed,
+            storage_deposit,
+            flags: ReturnFlags::from_bits_truncate(flags),
+            return_data,
+            events: recorded_events_after(events_before),
+        })
+    }));
+    <EnvInstance as OnInstance>::on_instance(|instance| *instance = original);
+    result.unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+/// Dispatches a call of the message with `selector` to the contract at `callee`.
+///
+/// The input is the `selector` followed by the SCALE encoded `encoded_args`, it is
+/// dispatched through the `call` entry point of the registered contract the same
+/// way the smart contract runtime does. The call is executed in its own frame,
+/// see [`recorded_frames`]: the contract is the callee and the caller is the
+/// enclosing contract, or the current caller for top-level calls. The execution
+/// context is restored once the call returns.
+///
+/// Errors of the dispatcher, e.g. for an unknown selector, are returned by the
+/// contract as reverted [`CallResult`].
+///
+/// Handlers of mocked contracts take precedence over the entry points: the call is
+/// dispatched to the handler registered for `selector` via
+/// [`register_contract_message`], or else to the fallback handler registered via
+/// [`register_fallback_message`].
+///
+/// # Panics
+///
+/// - If no contract is registered at `callee`.
+/// - If fee accounting is enabled and the caller cannot pay the fee, see
+///   [`enable_fee_accounting`]. The message is not executed in this case.
+pub fn call_message<T>(
+    callee: T::AccountId,
+    selector: [u8; 4],
+    encoded_args: &[u8],
+) -> CallResult
+where
+    T: Environment,
+{
+    dispatch_call::<T>(
+        callee,
+        selector,
+        encoded_args,
+        |engine, callee, selector| {
+            engine.get_message_handler(callee, selector).or_else(|| {
+                engine
+                    .get_entry_points(callee)
+                    .map(|entry_points| entry_points.call)
+            })
+        },
+    )
+}
+
+/// Dispatches a call of the constructor with `selector` to the contract at `callee`.
+///
+/// Works like [`call_message`], but dispatches through the `deploy` entry point of
+/// the registered contract.
+///
+/// # Panics
+///
+/// If no contract is registered at `callee`.
+pub fn call_constructor<T>(
+    callee: T::AccountId,
+    selector: [u8; 4],
+    encoded_args: &[u8],
+) -> CallResult
+where
+    T: Environment,
+{
+    dispatch_call::<T>(callee, selector, encoded_args, |engine, callee, _| {
+        engine
+            .get_entry_points(callee)
+            .map(|entry_points| entry_points.deploy)
+    })
+}
+
+/// Resolves the function to dispatch a call with the selector to for the contract
+/// at the account, if any.
+type Resolve = fn(&Engine, &[u8], [u8; 4]) -> Option<fn()>;
+
+/// Dispatches a call to the function of the contract at `callee` resolved by
+/// `resolve`.
+fn dispatch_call<T>(
+    callee: T::AccountId,
+    selector: [u8; 4],
+    encoded_args: &[u8],
+    resolve: Resolve,
+) -> CallResult
+where
+    T: Environment,
+{
+    let (entry_point, emitted_before) =
+        <EnvInstance as OnInstance>::on_instance(|instance| {
+            let callee = scale::Encode::encode(&callee);
+            let input = [&selector[..], encoded_args].concat();
+            instance.engine.record_dispatch_input(&input);
+            let entry_point = resolve(&instance.engine, &callee, selector)
+                .unwrap_or_else(|| {
+                    match instance.engine.unknown_selector(&callee, selector) {
+                        Some(unknown) => panic!("{unknown}"),
+                        None => panic!("no contract registered at {callee:?}"),
+                    }
+                });
+            instance.engine.enter_frame(callee, input);
+            let emitted_before = instance.engine.emitted_event_count();
+            (entry_point, emitted_before)
+        });
+    let (flags, data) = execute_entry_point(entry_point);
+    CallResult {
+        flags: ReturnFlags::from_bits_truncate(flags),
+        data,
+        events: recorded_events_after(emitted_before),
+    }
+}
+
+/// Executes `entry_point` in the entered execution frame and exits the frame.
+///
+/// Returns the flags and data the contract returned with. Panics of the contract
+/// other than the one of `return_value` are propagated. The frame of an execution
+/// which reverted or panicked is reverted, see [`Engine::revert_frame`].
+pub(crate) fn execute_entry_point(entry_point: fn()) -> (u32, Vec<u8>) {
+    let result = panic::catch_unwind(AssertUnwindSafe(entry_point));
+    let (flags, data) = match result {
+        Ok(()) => (0, Vec::new()),
+        Err(payload) => {
+            match payload.downcast::<ReturnValue>() {
+                Ok(value) => (value.flags, value.data),
+                Err(payload) => {
+                    <EnvInstance as OnInstance>::on_instance(|instance| {
+                        instance.engine.revert_frame(Vec::new())
+                    });
+                    panic::resume_unwind(payload)
+                }
+            }
+        }
+    };
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        if flags & ReturnFlags::REVERT.bits() != 0 {
+            instance.engine.revert_frame(data.clone())
+        } else {
+            instance.engine.return_from_frame(data.clone())
+        }
+    });
+    (flags, data)
+}
+
 /// Tests if a contract terminates successfully after `self.env().terminate()`
 /// has been called.
 ///
@@ -432,3 +2712,25 @@ macro_rules! pay_with_call {
         $contract.$message($ ($params) ,*)
     }}
 }
+
+/// Sets the chain parameter `name` to the SCALE `encoded` value.
+///
+/// See [`RECOGNIZED_CHAIN_PARAMS`] for the parameters consulted by the engine, other
+/// names are only visible to chain extensions, e.g. via
+/// `Engine::decode_chain_param` in [`ChainExtension::call_with_engine`].
+pub fn set_chain_param(name: &str, encoded: Vec<u8>) {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.set_chain_param(name, encoded)
+    })
+}
+
+/// Returns the encoded value of the chain parameter `name`.
+///
+/// # Errors
+///
+/// If the parameter has not been set, see [`set_chain_param`].
+pub fn get_chain_param(name: &str) -> core::result::Result<Vec<u8>, ChainParamError> {
+    <EnvInstance as OnInstance>::on_instance(|instance| {
+        instance.engine.get_chain_param(name).map(<[u8]>::to_vec)
+    })
+}
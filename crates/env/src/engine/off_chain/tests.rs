@@ -12,9 +12,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::{
+    AccountError,
+    EnvInstance,
+    OffChainError,
+};
 use crate::{
-    engine::off_chain::impls::TopicsBuilder,
-    event::TopicsBuilderBackend,
+    engine::{
+        off_chain::impls::TopicsBuilder,
+        OnInstance,
+    },
+    event::{
+        state,
+        Event,
+        TopicsBuilder as EventTopicsBuilder,
+        TopicsBuilderBackend,
+    },
+    hash::Blake2x256,
+    test::Fault,
     Result,
 };
 
@@ -31,13 +46,3296 @@ fn topics_builder() -> Result<()> {
         // then
         assert_eq!(builder.topics.len(), 2);
 
+        // the topics are prefixed by their count and each topic by its length
         let topics_len_compact = &scale::Compact(2u32);
         let topics_len_encoded = scale::Encode::encode(&topics_len_compact);
         let output = TopicsBuilderBackend::<crate::DefaultEnvironment>::output(builder);
         #[rustfmt::skip]
-        let expected = vec![topics_len_encoded[0], 13, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 17, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let expected = vec![topics_len_encoded[0], 128, 13, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 128, 17, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
         assert_eq!(output, expected);
 
         Ok(())
     })
 }
+
+#[test]
+fn failing_storage_write_is_not_applied() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::test::inject_fault(Fault::FailNthStorageWrite(2));
+
+        // when
+        let res = std::panic::catch_unwind(|| {
+            crate::set_contract_storage(&1u32, &true);
+            crate::set_contract_storage(&2u32, &true);
+        });
+
+        // then
+        assert!(res.is_err());
+        assert_eq!(crate::get_contract_storage::<u32, bool>(&1u32)?, Some(true));
+        assert_eq!(crate::get_contract_storage::<u32, bool>(&2u32)?, None);
+        Ok(())
+    })
+}
+
+#[test]
+fn failing_storage_write_rolls_back_the_call() -> Result<()> {
+    fn deploy() {}
+    fn call() {
+        crate::set_contract_storage(&1u32, &true);
+        crate::set_contract_storage(&2u32, &true);
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::set_engine_config(crate::test::EngineConfig {
+            commit_mode: crate::test::CommitMode::OnFrameSuccess,
+            ..Default::default()
+        });
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            crate::test::EntryPoints { deploy, call },
+        );
+        crate::test::inject_fault(Fault::FailNthStorageWrite(2));
+
+        // when
+        let res = std::panic::catch_unwind(|| {
+            crate::test::call_message::<crate::DefaultEnvironment>(
+                accounts.charlie,
+                [0; 4],
+                &[],
+            )
+        });
+
+        // then
+        assert!(res.is_err());
+        for key in [1u32, 2] {
+            assert_eq!(
+                crate::test::get_storage_raw::<crate::DefaultEnvironment, _>(
+                    accounts.charlie,
+                    &key
+                ),
+                None
+            );
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn debug_message_assertions() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::debug_message("transfer: ok");
+        crate::debug_message("approve: ok");
+
+        // then
+        crate::test::assert_println_emitted("approve");
+        assert_eq!(
+            crate::test::printlns_containing("ok"),
+            vec!["transfer: ok", "approve: ok"]
+        );
+
+        // when
+        let taken: Vec<String> =
+            crate::test::take_recorded_printlns().into_iter().collect();
+
+        // then
+        assert_eq!(taken.len(), 2);
+        assert_eq!(
+            crate::test::recorded_debug_messages().into_iter().count(),
+            0
+        );
+        let res =
+            std::panic::catch_unwind(|| crate::test::assert_println_emitted("approve"));
+        assert!(res.is_err());
+        Ok(())
+    })
+}
+
+#[test]
+fn debug_println_records_the_formatted_message() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // when
+        crate::debug_print!("static");
+        crate::debug_println!("value: {}, flag: {:?}", 42, true);
+        crate::debug_println!();
+
+        // then
+        assert_eq!(
+            crate::test::recorded_debug_messages()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["static", "value: 42, flag: true\n", "\n"]
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn reading_unset_caller_fails_descriptively() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        <EnvInstance as OnInstance>::on_instance(|instance| {
+            instance.engine.exec_context.set_caller(None);
+        });
+
+        // when
+        let res = std::panic::catch_unwind(crate::caller::<crate::DefaultEnvironment>);
+
+        // then
+        let message = res
+            .expect_err("reading an unset caller must fail")
+            .downcast::<String>()
+            .expect("panic message must be a string");
+        assert!(message.contains(&format!(
+            "{:?}",
+            crate::Error::OffChain(OffChainError::UninitializedExecutionContext)
+        )));
+        Ok(())
+    })
+}
+
+#[test]
+fn reentrant_use_names_conflicting_operations() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // when
+        let res = <EnvInstance as OnInstance>::on_instance(|_| {
+            std::panic::catch_unwind(|| {
+                crate::test::set_caller::<crate::DefaultEnvironment>(accounts.bob)
+            })
+        });
+
+        // then
+        let message = res
+            .expect_err("reentrant use must fail")
+            .downcast::<String>()
+            .expect("panic message must be a string");
+        assert!(message.contains(
+            "already in use by `ink_env::engine::off_chain::tests::\
+            reentrant_use_names_conflicting_operations`"
+        ));
+        assert!(message.contains("test_api::set_caller<"));
+        Ok(())
+    })
+}
+
+#[test]
+fn deployed_test_contract_has_balance() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        let contract = crate::test::deploy_test_contract::<crate::DefaultEnvironment>(
+            [0x42; 32].into(),
+            1337,
+        );
+
+        // then
+        assert_eq!(crate::balance::<crate::DefaultEnvironment>(), 1337);
+        assert_eq!(
+            crate::account_id::<crate::DefaultEnvironment>(),
+            contract.account_id
+        );
+        assert!(crate::test::is_contract::<crate::DefaultEnvironment>(
+            contract.account_id
+        ));
+        assert_eq!(
+            crate::test::count_used_storage_cells::<crate::DefaultEnvironment>(
+                &contract.account_id
+            ),
+            Ok(0)
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn test_contracts_with_salt_get_distinct_accounts() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // when
+        let first = crate::test::deploy_test_contract_with_salt::<
+            crate::DefaultEnvironment,
+        >(b"first", 1);
+        let second = crate::test::deploy_test_contract_with_salt::<
+            crate::DefaultEnvironment,
+        >(b"second", 2);
+
+        // then
+        assert_ne!(first.account_id, second.account_id);
+        assert_eq!(crate::balance::<crate::DefaultEnvironment>(), 2);
+        assert_eq!(
+            crate::test::get_account_balance::<crate::DefaultEnvironment>(
+                first.account_id
+            ),
+            Ok(1)
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn engine_errors_preserve_context() {
+    use ink_engine::ext;
+
+    assert_eq!(
+        crate::Error::from(ext::Error::KeyNotFound(vec![0x42; 32])),
+        crate::Error::OffChain(OffChainError::KeyNotFound(vec![0x42; 32]))
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::TransferFailed),
+        crate::Error::ReturnError(crate::ReturnErrorCode::TransferFailed)
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::InvalidValue),
+        crate::Error::OffChain(OffChainError::InvalidValue)
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::InvalidCallee(vec![0x42; 20])),
+        crate::Error::OffChain(OffChainError::InvalidCallee(vec![0x42; 20]))
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::StorageDepositLimitExhausted),
+        crate::Error::OffChain(OffChainError::StorageDepositLimitExhausted)
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::TerminationDenied),
+        crate::Error::OffChain(OffChainError::TerminationDenied)
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::FeeWithdrawalFailed),
+        crate::Error::OffChain(OffChainError::FeeWithdrawalFailed)
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::EventLimitExceeded(Default::default())),
+        crate::Error::OffChain(OffChainError::EventLimitExceeded(Default::default()))
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::MemoryLimitExceeded),
+        crate::Error::OffChain(OffChainError::MemoryLimitExceeded)
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::OutOfGas),
+        crate::Error::OffChain(OffChainError::OutOfGas)
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::ChainExtensionTrapped),
+        crate::Error::OffChain(OffChainError::ChainExtensionTrapped)
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::UnknownSelector(Default::default())),
+        crate::Error::OffChain(OffChainError::UnknownSelector(Default::default()))
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::BufferTooSmall(16_385)),
+        crate::Error::BufferTooSmall
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::NoCallee),
+        crate::Error::OffChain(OffChainError::UninitializedExecutionContext)
+    );
+    assert_eq!(
+        crate::Error::from(ext::Error::NoBalance(vec![1; 32])),
+        crate::Error::OffChain(OffChainError::Account(AccountError::NoAccountForId(
+            vec![1; 32]
+        )))
+    );
+    assert_eq!(
+        crate::Error::from(ink_engine::Error::BufferTooSmall(33)),
+        crate::Error::BufferTooSmall
+    );
+    assert_eq!(
+        core::result::Result::<(), ext::Error>::from(ext::ReturnCode(77))
+            .map_err(crate::Error::from),
+        Err(crate::Error::Unknown(77))
+    );
+}
+
+#[derive(Debug, scale::Encode, scale::Decode)]
+struct Transferred {
+    amount: u8,
+    memo: [u8; 40],
+}
+
+impl Event for Transferred {
+    type RemainingTopics = [state::HasRemainingTopics; 2];
+
+    const SIGNATURE_TOPIC: Option<[u8; 32]> = None;
+
+    fn topics<E, B>(
+        &self,
+        builder: EventTopicsBuilder<state::Uninit, E, B>,
+    ) -> <B as TopicsBuilderBackend<E>>::Output
+    where
+        E: crate::Environment,
+        B: TopicsBuilderBackend<E>,
+    {
+        builder
+            .build::<Self>()
+            .push_topic(Some(&self.amount))
+            .push_topic(Some(&self.memo))
+            .finish()
+    }
+}
+
+#[test]
+fn computed_topics_match_recorded_topics() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        let event = Transferred {
+            amount: 13,
+            memo: [7; 40],
+        };
+        // the encoded `amount` padded to 32 bytes
+        let short_topic = [
+            13, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0,
+        ];
+        // the BLAKE2b-256 hash of the encoded `memo`
+        let long_topic = [
+            138, 138, 64, 228, 172, 57, 86, 221, 126, 110, 72, 157, 238, 41, 144, 41,
+            196, 235, 12, 173, 97, 251, 119, 133, 171, 127, 117, 67, 66, 183, 68, 127,
+        ];
+
+        // when
+        let topics =
+            crate::test::compute_topics_off_chain::<crate::DefaultEnvironment, _>(&event);
+        crate::emit_event::<crate::DefaultEnvironment, _>(event);
+
+        // then
+        assert_eq!(topics, vec![short_topic, long_topic]);
+        let recorded = crate::test::recorded_events()
+            .next()
+            .expect("event must have been recorded");
+        assert_eq!(
+            recorded.topics,
+            vec![short_topic.to_vec(), long_topic.to_vec()]
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn call_message_dispatches_input_to_entry_point() -> Result<()> {
+    fn deploy() {}
+    fn call() {
+        let (_selector, value) =
+            crate::decode_input::<([u8; 4], u32)>().expect("decoding input failed");
+        crate::return_value(crate::ReturnFlags::REVERT, &(value + 1));
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        let entry_points = crate::test::EntryPoints { deploy, call };
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            entry_points,
+        );
+
+        // when
+        let result = crate::test::call_message::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            [1, 2, 3, 4],
+            &scale::Encode::encode(&41u32),
+        );
+
+        // then
+        assert!(result.did_revert());
+        assert_eq!(result.decode::<u32>(), Ok(42));
+        assert_eq!(
+            crate::test::callee::<crate::DefaultEnvironment>(),
+            accounts.alice
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn deny_payment_fails_for_transferred_value() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        crate::test::set_value_transferred::<crate::DefaultEnvironment>(0);
+        assert_eq!(crate::deny_payment::<crate::DefaultEnvironment>(), Ok(()));
+
+        crate::test::set_value_transferred::<crate::DefaultEnvironment>(1);
+        assert_eq!(
+            crate::deny_payment::<crate::DefaultEnvironment>(),
+            Err(crate::Error::PaidUnpayableMessage)
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn nested_calls_record_events_per_frame() -> Result<()> {
+    fn deploy() {}
+    fn emit(amount: u8) {
+        crate::emit_event::<crate::DefaultEnvironment, _>(Transferred {
+            amount,
+            memo: [0; 40],
+        });
+    }
+    fn call_outer() {
+        emit(1);
+        let inner = crate::test::default_accounts::<crate::DefaultEnvironment>().django;
+        crate::test::call_message::<crate::DefaultEnvironment>(inner, [0; 4], &[]);
+    }
+    fn call_inner() {
+        assert_eq!(
+            crate::caller::<crate::DefaultEnvironment>(),
+            crate::test::default_accounts::<crate::DefaultEnvironment>().charlie
+        );
+        emit(2);
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            crate::test::EntryPoints {
+                deploy,
+                call: call_outer,
+            },
+        );
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.django,
+            crate::test::EntryPoints {
+                deploy,
+                call: call_inner,
+            },
+        );
+
+        // when
+        let result = crate::test::call_message::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            [0; 4],
+            &[],
+        );
+
+        // then
+        let amounts = |frame: &crate::test::FrameRecord| {
+            frame
+                .events()
+                .map(|event| event.data[0])
+                .collect::<Vec<_>>()
+        };
+        let frames = crate::test::recorded_frames();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(
+            frames[1].callee,
+            Some(scale::Encode::encode(&accounts.charlie))
+        );
+        assert_eq!(frames[1].depth, 1);
+        assert_eq!(amounts(&frames[1]), [1]);
+        assert_eq!(
+            frames[2].caller,
+            Some(scale::Encode::encode(&accounts.charlie))
+        );
+        assert_eq!(
+            frames[2].callee,
+            Some(scale::Encode::encode(&accounts.django))
+        );
+        assert_eq!(frames[2].depth, 2);
+        assert_eq!(amounts(&frames[2]), [2]);
+        assert_eq!(result.events.len(), 2);
+        Ok(())
+    })
+}
+
+#[test]
+fn mapping_storage_key_prefixes_the_encoded_key_with_the_base_key() {
+    assert_eq!(
+        crate::test::mapping_storage_key(&0x0102_0304, &[9, 8]),
+        [4, 3, 2, 1, 9, 8]
+    );
+}
+
+#[test]
+fn recorded_events_are_bounded_by_the_retention_limit() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::test::set_engine_config(crate::test::EngineConfig {
+            max_recorded_events: Some(10),
+            ..Default::default()
+        });
+
+        // when
+        for amount in 0..15 {
+            crate::emit_event::<crate::DefaultEnvironment, _>(Transferred {
+                amount,
+                memo: [0; 40],
+            });
+        }
+
+        // then
+        assert_eq!(crate::test::recorded_events().count(), 10);
+        assert_eq!(crate::test::dropped_records().events, 5);
+        crate::test::set_engine_config(Default::default());
+        Ok(())
+    })
+}
+
+#[test]
+fn last_call_outcome_carries_the_events_of_the_callee() -> Result<()> {
+    fn deploy() {}
+    fn call_outer() {
+        let inner = crate::test::default_accounts::<crate::DefaultEnvironment>().django;
+        crate::test::call_message::<crate::DefaultEnvironment>(inner, [0; 4], &[]);
+        let outcome = crate::test::last_call_outcome().expect("inner call completed");
+        assert_eq!(outcome.events.len(), 2);
+    }
+    fn call_inner() {
+        for amount in [1, 2] {
+            crate::emit_event::<crate::DefaultEnvironment, _>(Transferred {
+                amount,
+                memo: [0; 40],
+            });
+        }
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            crate::test::EntryPoints {
+                deploy,
+                call: call_outer,
+            },
+        );
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.django,
+            crate::test::EntryPoints {
+                deploy,
+                call: call_inner,
+            },
+        );
+
+        // when
+        crate::test::call_message::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            [0; 4],
+            &[],
+        );
+
+        // then
+        let outer = crate::test::last_call_outcome().expect("outer call completed");
+        assert_eq!(outer.events.len(), 2);
+        assert_eq!(crate::test::recorded_events().count(), 2);
+        Ok(())
+    })
+}
+
+#[test]
+fn last_call_outcome_includes_nested_calls() -> Result<()> {
+    fn deploy() {}
+    fn call_outer() {
+        crate::set_contract_storage(&1u8, &11u8);
+        crate::set_contract_storage(&2u8, &22u8);
+        let _ = crate::get_contract_storage::<u8, u8>(&1u8);
+        crate::emit_event::<crate::DefaultEnvironment, _>(Transferred {
+            amount: 1,
+            memo: [0; 40],
+        });
+        let inner = crate::test::default_accounts::<crate::DefaultEnvironment>().django;
+        crate::test::call_message::<crate::DefaultEnvironment>(inner, [0; 4], &[]);
+        crate::return_value::<u8>(crate::ReturnFlags::empty(), &42u8)
+    }
+    fn call_inner() {
+        crate::set_contract_storage(&3u8, &33u8);
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            crate::test::EntryPoints {
+                deploy,
+                call: call_outer,
+            },
+        );
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.django,
+            crate::test::EntryPoints {
+                deploy,
+                call: call_inner,
+            },
+        );
+        assert_eq!(crate::test::last_call_outcome(), None);
+
+        // when
+        crate::test::call_message::<crate::DefaultEnvironment>(
+            accounts.django,
+            [0; 4],
+            &[],
+        );
+        let inner = crate::test::last_call_outcome().expect("inner call completed");
+        crate::test::call_message::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            [0; 4],
+            &[],
+        );
+        let outer = crate::test::last_call_outcome().expect("outer call completed");
+
+        // then
+        assert_eq!(inner.storage_writes, 1);
+        assert_eq!(inner.storage_reads, 0);
+        assert_eq!(inner.events_emitted, 0);
+        assert!(inner.data.is_empty());
+        assert_eq!(outer.storage_writes, 3);
+        assert_eq!(outer.storage_reads, 1);
+        assert_eq!(outer.events_emitted, 1);
+        assert!(inner.gas_consumed > 0);
+        assert!(outer.gas_consumed > inner.gas_consumed);
+        assert_eq!(outer.gas_consumed, crate::test::gas_consumed());
+        assert_eq!(outer.data, scale::Encode::encode(&42u8));
+        Ok(())
+    })
+}
+
+#[test]
+fn storage_dump_is_ordered_by_key() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::set_contract_storage(&2u8, &22u8);
+        crate::set_contract_storage(&1u8, &11u8);
+
+        // when
+        let dump =
+            crate::test::storage_dump::<crate::DefaultEnvironment>(&accounts.alice);
+
+        // then
+        assert_eq!(dump, vec![(vec![1], vec![11]), (vec![2], vec![22])]);
+        assert!(crate::test::export_state_json()
+            .contains("{\"0x01\":\"0x0b\",\"0x02\":\"0x16\"}"));
+        Ok(())
+    })
+}
+
+#[test]
+fn clock_with_jump_sets_block_timestamp() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::test::set_clock::<crate::DefaultEnvironment, _>(|block_number| {
+            if block_number < 2 {
+                6
+            } else {
+                86_400_000
+            }
+        });
+
+        // when
+        crate::test::advance_block::<crate::DefaultEnvironment>();
+        let before_jump = crate::block_timestamp::<crate::DefaultEnvironment>();
+        crate::test::advance_block::<crate::DefaultEnvironment>();
+        let after_jump = crate::block_timestamp::<crate::DefaultEnvironment>();
+
+        // then
+        assert_eq!((before_jump, after_jump), (6, 86_400_000));
+        assert!(crate::test::recorded_warnings().is_empty());
+        Ok(())
+    })
+}
+
+#[test]
+fn advancing_time_leaves_the_block_number_unchanged() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::test::advance_block::<crate::DefaultEnvironment>();
+        let block_number = crate::block_number::<crate::DefaultEnvironment>();
+        let timestamp = crate::block_timestamp::<crate::DefaultEnvironment>();
+
+        // when
+        crate::test::advance_time::<crate::DefaultEnvironment>(60_000);
+
+        // then
+        assert_eq!(
+            crate::block_number::<crate::DefaultEnvironment>(),
+            block_number
+        );
+        assert_eq!(
+            crate::block_timestamp::<crate::DefaultEnvironment>(),
+            timestamp + 60_000
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn recorded_scenario_replays_against_fresh_engine() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::start_recording_scenario();
+        crate::test::set_callee::<crate::DefaultEnvironment>(accounts.alice);
+        crate::test::set_account_balance::<crate::DefaultEnvironment>(
+            accounts.alice,
+            1_000,
+        );
+        crate::set_contract_storage(&1u8, &11u8);
+        crate::transfer::<crate::DefaultEnvironment>(accounts.bob, 100)?;
+        crate::emit_event::<crate::DefaultEnvironment, _>(Transferred {
+            amount: 100,
+            memo: [0; 40],
+        });
+        let scenario = crate::test::stop_recording_scenario();
+
+        // when
+        let replayed = ink_engine::ext::Engine::replay(&scenario)
+            .unwrap_or_else(|divergence| panic!("replay diverged: {divergence:?}"));
+
+        // then
+        assert_eq!(
+            replayed.storage_dump(&scale::Encode::encode(&accounts.alice)),
+            crate::test::storage_dump::<crate::DefaultEnvironment>(&accounts.alice)
+        );
+        assert_eq!(
+            replayed.get_balance(scale::Encode::encode(&accounts.bob)),
+            Ok(100)
+        );
+        assert_eq!(replayed.get_emitted_events().count(), 1);
+        Ok(())
+    })
+}
+
+#[test]
+fn non_strict_mode_reads_default_properties() {
+    let caller = crate::test::default_accounts::<crate::DefaultEnvironment>().bob;
+    crate::test::set_caller::<crate::DefaultEnvironment>(caller);
+
+    assert_eq!(crate::caller::<crate::DefaultEnvironment>(), caller);
+    assert_eq!(crate::block_timestamp::<crate::DefaultEnvironment>(), 0);
+}
+
+#[test]
+#[should_panic(
+    expected = "could not read `caller` property: OffChain(UninitializedProperty(\"caller\"))"
+)]
+fn strict_mode_rejects_unset_caller() {
+    crate::test::set_engine_config(crate::test::EngineConfig {
+        strict: true,
+        ..Default::default()
+    });
+    crate::caller::<crate::DefaultEnvironment>();
+}
+
+#[test]
+fn strict_mode_reads_explicitly_set_caller() -> Result<()> {
+    crate::test::set_engine_config(crate::test::EngineConfig {
+        strict: true,
+        ..Default::default()
+    });
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        assert_eq!(crate::caller::<crate::DefaultEnvironment>(), accounts.alice);
+        assert_eq!(
+            crate::Error::from(ink_engine::Error::UninitializedProperty("caller")),
+            crate::Error::OffChain(OffChainError::UninitializedProperty("caller"))
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn mocked_messages_dispatch_by_selector_with_fallback() -> Result<()> {
+    fn flip() {
+        crate::return_value::<bool>(crate::ReturnFlags::empty(), &true)
+    }
+    fn get() {
+        crate::return_value::<u8>(crate::ReturnFlags::empty(), &7u8)
+    }
+    fn unknown() {
+        crate::return_value::<()>(crate::ReturnFlags::REVERT, &())
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        let contract = accounts.charlie;
+        crate::test::register_contract_message::<crate::DefaultEnvironment>(
+            contract,
+            [0, 0, 0, 1],
+            flip,
+        )
+        .expect("selector is not registered yet");
+        crate::test::register_contract_message::<crate::DefaultEnvironment>(
+            contract,
+            [0, 0, 0, 2],
+            get,
+        )
+        .expect("selector is not registered yet");
+        crate::test::register_fallback_message::<crate::DefaultEnvironment>(
+            contract, unknown,
+        );
+
+        // when
+        let duplicate = crate::test::register_contract_message::<crate::DefaultEnvironment>(
+            contract,
+            [0, 0, 0, 1],
+            get,
+        );
+        let flipped = crate::test::call_message::<crate::DefaultEnvironment>(
+            contract,
+            [0, 0, 0, 1],
+            &[],
+        );
+        let missed = crate::test::call_message::<crate::DefaultEnvironment>(
+            contract,
+            [0, 0, 0, 3],
+            &[],
+        );
+
+        // then
+        assert_eq!(
+            duplicate,
+            Err(crate::test::DuplicateSelector {
+                account_id: scale::Encode::encode(&contract),
+                selector: [0, 0, 0, 1],
+            })
+        );
+        assert_eq!(
+            crate::test::registered_selectors::<crate::DefaultEnvironment>(contract),
+            [[0, 0, 0, 1], [0, 0, 0, 2]]
+        );
+        assert!(!flipped.did_revert());
+        assert!(flipped.decode::<bool>()?);
+        assert!(missed.did_revert());
+        Ok(())
+    })
+}
+
+#[test]
+fn unknown_selector_reports_received_and_registered_selectors() -> Result<()> {
+    fn flip() {
+        crate::return_value::<bool>(crate::ReturnFlags::empty(), &true)
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        let contract = accounts.charlie;
+        crate::test::register_contract_message::<crate::DefaultEnvironment>(
+            contract,
+            [0, 0, 0, 1],
+            flip,
+        )
+        .expect("selector is not registered yet");
+
+        // when
+        let res = std::panic::catch_unwind(|| {
+            crate::test::call_message::<crate::DefaultEnvironment>(
+                contract,
+                [0, 0, 0, 3],
+                &[0x2a],
+            )
+        });
+
+        // then
+        let payload = res.err().expect("the selector is unknown");
+        let message = payload
+            .downcast_ref::<String>()
+            .expect("the panic carries a message");
+        assert!(message.contains("0x00000003"), "{message}");
+        assert!(message.contains("0x00000001"), "{message}");
+        assert_eq!(
+            crate::test::last_dispatch_input(),
+            Some(vec![0, 0, 0, 3, 0x2a])
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn storage_deposit_is_reserved_from_caller() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::set_engine_config(crate::test::EngineConfig {
+            storage_deposit_per_byte: Some(1),
+            storage_deposit_per_cell: Some(100),
+            ..Default::default()
+        });
+        let balance = crate::test::get_account_balance::<crate::DefaultEnvironment>(
+            accounts.alice,
+        )?;
+
+        // when
+        crate::set_contract_storage(&1u8, &[0u8; 4]);
+
+        // then
+        assert_eq!(
+            crate::test::get_storage_deposit::<crate::DefaultEnvironment>(accounts.alice),
+            104
+        );
+        assert_eq!(
+            crate::test::get_account_balance::<crate::DefaultEnvironment>(
+                accounts.alice
+            )?,
+            balance - 104
+        );
+
+        // when
+        crate::clear_contract_storage(&1u8);
+
+        // then
+        assert_eq!(
+            crate::test::get_storage_deposit::<crate::DefaultEnvironment>(accounts.alice),
+            0
+        );
+        assert_eq!(
+            crate::test::get_account_balance::<crate::DefaultEnvironment>(
+                accounts.alice
+            )?,
+            balance
+        );
+        crate::test::set_engine_config(Default::default());
+        Ok(())
+    })
+}
+
+#[test]
+fn storage_deposit_limit_caps_the_next_call() -> Result<()> {
+    fn deploy() {}
+    fn write_two_cells() {
+        crate::set_contract_storage(&1u8, &1u8);
+        crate::set_contract_storage(&2u8, &2u8);
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::set_engine_config(crate::test::EngineConfig {
+            storage_deposit_per_cell: Some(10),
+            commit_mode: crate::test::CommitMode::OnFrameSuccess,
+            ..Default::default()
+        });
+        for contract in [accounts.charlie, accounts.django] {
+            crate::test::register_contract::<crate::DefaultEnvironment>(
+                contract,
+                crate::test::EntryPoints {
+                    deploy,
+                    call: write_two_cells,
+                },
+            );
+        }
+        let deposit_of_two_cells = 2 * (10 + 1);
+
+        // when
+        crate::test::set_storage_deposit_limit::<crate::DefaultEnvironment>(Some(
+            deposit_of_two_cells,
+        ));
+        let generous = crate::test::call_message::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            [0; 4],
+            &[],
+        );
+        crate::test::set_storage_deposit_limit::<crate::DefaultEnvironment>(Some(
+            deposit_of_two_cells / 2,
+        ));
+        let tight = std::panic::catch_unwind(|| {
+            crate::test::call_message::<crate::DefaultEnvironment>(
+                accounts.django,
+                [0; 4],
+                &[],
+            )
+        });
+
+        // then
+        assert!(!generous.did_revert());
+        assert_eq!(
+            tight
+                .err()
+                .and_then(|trap| trap.downcast_ref::<String>().cloned())
+                .as_deref(),
+            Some("contract trapped: StorageDepositLimitExhausted")
+        );
+        assert_eq!(
+            crate::test::callee::<crate::DefaultEnvironment>(),
+            accounts.alice
+        );
+        crate::test::set_callee::<crate::DefaultEnvironment>(accounts.django);
+        assert_eq!(crate::get_contract_storage::<u8, u8>(&1u8), Ok(None));
+        crate::test::set_callee::<crate::DefaultEnvironment>(accounts.charlie);
+        assert_eq!(crate::get_contract_storage::<u8, u8>(&2u8), Ok(Some(2)));
+        crate::test::set_engine_config(Default::default());
+        Ok(())
+    })
+}
+
+fn emit_transferred(amount: u8) {
+    crate::emit_event::<crate::DefaultEnvironment, _>(Transferred {
+        amount,
+        memo: [0; 40],
+    });
+}
+
+#[test]
+fn revert_to_block_drops_later_state_and_events() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        for block in 1..=3u8 {
+            crate::test::advance_block::<crate::DefaultEnvironment>();
+            crate::set_contract_storage(&block, &block);
+            crate::test::set_account_balance::<crate::DefaultEnvironment>(
+                accounts.bob,
+                u128::from(block),
+            );
+            emit_transferred(block);
+        }
+
+        // when
+        let reverted = crate::test::revert_to_block::<crate::DefaultEnvironment>(1);
+
+        // then
+        assert_eq!(reverted, Ok(()));
+        assert_eq!(crate::block_number::<crate::DefaultEnvironment>(), 1);
+        assert_eq!(crate::get_contract_storage::<u8, u8>(&1)?, Some(1));
+        assert_eq!(crate::get_contract_storage::<u8, u8>(&2)?, None);
+        assert_eq!(crate::get_contract_storage::<u8, u8>(&3)?, None);
+        assert_eq!(
+            crate::test::get_account_balance::<crate::DefaultEnvironment>(accounts.bob)?,
+            1
+        );
+        assert_eq!(crate::test::recorded_events().count(), 1);
+        crate::test::assert_event_emitted::<Transferred>(|event| event.amount == 1);
+        Ok(())
+    })
+}
+
+#[test]
+fn find_event_matches_by_predicate() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        emit_transferred(1);
+        emit_transferred(2);
+
+        // when
+        let found = crate::test::find_event::<Transferred>(|event| event.amount == 2);
+        let missing = crate::test::find_event::<Transferred>(|event| event.amount == 3);
+
+        // then
+        assert_eq!(found.map(|event| event.amount), Some(2));
+        assert!(missing.is_none());
+        crate::test::assert_event_emitted::<Transferred>(|event| event.amount == 1);
+        let second = crate::test::recorded_events().skip(1);
+        assert!(crate::test::find_event_in::<Transferred>(second, |event| {
+            event.amount == 1
+        })
+        .is_none());
+        Ok(())
+    })
+}
+
+#[test]
+#[should_panic(
+    expected = "recorded events: [\"Transferred { amount: 1, memo: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0] }\"]"
+)]
+fn assert_event_emitted_lists_recorded_events() {
+    emit_transferred(1);
+    crate::test::assert_event_emitted::<Transferred>(|event| event.amount == 2);
+}
+
+#[test]
+#[should_panic(
+    expected = "no event of type `u8` matching the predicate was recorded, \
+    recorded events: [\"0x0100000000000000000000000000000000000000000000000000000000000000000000000000000000\"]"
+)]
+fn assert_event_emitted_lists_undecodable_events_as_hex() {
+    emit_transferred(1);
+    assert_eq!(crate::test::find_event::<u8>(|_| true), None);
+    crate::test::assert_event_emitted::<u8>(|_| true);
+}
+
+#[test]
+fn delegate_call_executes_code_in_context_of_caller() -> Result<()> {
+    const CODE_HASH: [u8; 32] = [0x42; 32];
+
+    fn deploy() {}
+    fn call_proxy() {
+        crate::set_contract_storage(&1u8, &10u8);
+        let result = crate::call::build_call::<crate::DefaultEnvironment>()
+            .delegate(CODE_HASH.into())
+            .exec_input(
+                crate::call::ExecutionInput::new(crate::call::Selector::new([0; 4]))
+                    .push_arg(5u8),
+            )
+            .returns::<u8>()
+            .invoke();
+        crate::return_value::<u8>(crate::ReturnFlags::empty(), &result)
+    }
+    fn call_logic() {
+        let accounts = crate::test::default_accounts::<crate::DefaultEnvironment>();
+        assert_eq!(crate::caller::<crate::DefaultEnvironment>(), accounts.alice);
+        assert_eq!(
+            crate::account_id::<crate::DefaultEnvironment>(),
+            accounts.charlie
+        );
+        let (_selector, increment): ([u8; 4], u8) = crate::decode_input().unwrap();
+        let value = crate::get_contract_storage::<u8, u8>(&1u8)
+            .unwrap()
+            .unwrap();
+        crate::set_contract_storage(&2u8, &(value + increment));
+        crate::return_value::<ink_primitives::MessageResult<u8>>(
+            crate::ReturnFlags::empty(),
+            &Ok(value + increment),
+        )
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            crate::test::EntryPoints {
+                deploy,
+                call: call_proxy,
+            },
+        );
+        crate::test::register_code::<crate::DefaultEnvironment>(
+            CODE_HASH.into(),
+            crate::test::EntryPoints {
+                deploy,
+                call: call_logic,
+            },
+        );
+
+        // when
+        let result = crate::test::call_message::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            [0; 4],
+            &[],
+        );
+
+        // then
+        assert_eq!(result.decode::<u8>()?, 15);
+        assert_eq!(
+            crate::test::storage_dump::<crate::DefaultEnvironment>(&accounts.charlie),
+            [(vec![1], vec![10]), (vec![2], vec![15])]
+        );
+        let frames = crate::test::recorded_frames();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[2].callee, frames[1].callee);
+        assert_eq!(frames[2].caller, frames[1].caller);
+        Ok(())
+    })
+}
+
+#[test]
+fn delegate_call_of_unknown_code_fails() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        let result = crate::call::build_call::<crate::DefaultEnvironment>()
+            .delegate([0x42; 32].into())
+            .exec_input(crate::call::ExecutionInput::new(
+                crate::call::Selector::new([0; 4]),
+            ))
+            .returns::<()>()
+            .try_invoke();
+        assert_eq!(
+            result,
+            Err(crate::Error::ReturnError(
+                crate::ReturnErrorCode::CodeNotFound
+            ))
+        );
+        assert_eq!(crate::test::recorded_frames().len(), 1);
+        Ok(())
+    })
+}
+
+#[test]
+fn runtime_calls_are_dispatched_to_handler() -> Result<()> {
+    #[derive(scale::Encode)]
+    enum RuntimeCall {
+        TransferKeepAlive { dest: [u8; 32], value: u128 },
+        Remark(Vec<u8>),
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        let transfer = RuntimeCall::TransferKeepAlive {
+            dest: [2; 32],
+            value: 10,
+        };
+        let accepted = scale::Encode::encode(&transfer);
+        crate::test::register_runtime_call_handler(move |call| {
+            if call == &accepted[..] {
+                Ok(())
+            } else {
+                Err(crate::test::RuntimeCallError::Filtered)
+            }
+        });
+
+        // when
+        let transferred = crate::call_runtime::<crate::DefaultEnvironment, _>(&transfer);
+        let remarked = crate::call_runtime::<crate::DefaultEnvironment, _>(
+            &RuntimeCall::Remark(vec![1, 2, 3]),
+        );
+
+        // then
+        assert_eq!(transferred, Ok(()));
+        assert_eq!(
+            remarked,
+            Err(crate::Error::ReturnError(
+                crate::ReturnErrorCode::CallRuntimeFailed
+            ))
+        );
+        let calls = crate::test::recorded_runtime_calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].origin, scale::Encode::encode(&accounts.alice));
+        assert_eq!(calls[0].call, scale::Encode::encode(&transfer));
+        assert_eq!(calls[0].result, Ok(()));
+        assert_eq!(
+            calls[1].result,
+            Err(crate::test::RuntimeCallError::Filtered)
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn locked_contract_cannot_terminate() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        let contract = accounts.charlie;
+        crate::test::set_callee::<crate::DefaultEnvironment>(contract);
+        crate::test::set_account_balance::<crate::DefaultEnvironment>(contract, 100);
+        crate::test::set_account_balance::<crate::DefaultEnvironment>(
+            accounts.django,
+            10,
+        );
+        crate::test::lock_contract::<crate::DefaultEnvironment>(contract, "code in use");
+
+        // when
+        let denied = std::panic::catch_unwind(|| {
+            crate::terminate_contract::<crate::DefaultEnvironment>(accounts.django)
+        })
+        .expect_err("locked contract terminated");
+
+        // then
+        assert_eq!(
+            denied.downcast_ref::<String>().map(String::as_str),
+            Some("contract trapped: TerminationDenied")
+        );
+        assert_eq!(
+            crate::test::get_account_balance::<crate::DefaultEnvironment>(contract),
+            Ok(100)
+        );
+        assert_eq!(
+            crate::test::get_account_balance::<crate::DefaultEnvironment>(
+                accounts.django
+            ),
+            Ok(10)
+        );
+
+        // when
+        let unlocked = crate::test::unlock_contract::<crate::DefaultEnvironment>(
+            contract,
+            "code in use",
+        );
+
+        // then
+        assert!(unlocked);
+        crate::test::assert_contract_termination::<crate::DefaultEnvironment, _>(
+            move || {
+                crate::terminate_contract::<crate::DefaultEnvironment>(accounts.django)
+            },
+            accounts.django,
+            100,
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn fees_are_withdrawn_from_caller_of_call() -> Result<()> {
+    fn deploy() {}
+    fn call() {
+        crate::test::consume_gas(300);
+        crate::emit_event::<crate::DefaultEnvironment, _>(Transferred {
+            amount: 1,
+            memo: [0; 40],
+        });
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            crate::test::EntryPoints { deploy, call },
+        );
+        crate::test::set_engine_config(crate::test::EngineConfig {
+            gas_limit: 1_000,
+            weights: crate::test::WeightTable::zero(),
+            ..Default::default()
+        });
+        crate::test::enable_fee_accounting(true);
+        crate::test::set_account_balance::<crate::DefaultEnvironment>(
+            accounts.alice,
+            150_000,
+        );
+
+        // when
+        crate::test::call_message::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            [0; 4],
+            &[],
+        );
+
+        // then
+        assert_eq!(
+            crate::test::fees_paid::<crate::DefaultEnvironment>(accounts.alice),
+            30_000
+        );
+        assert_eq!(
+            crate::test::get_account_balance::<crate::DefaultEnvironment>(accounts.alice),
+            Ok(120_000)
+        );
+
+        // when
+        crate::test::set_account_balance::<crate::DefaultEnvironment>(
+            accounts.alice,
+            99_999,
+        );
+        let failed = std::panic::catch_unwind(|| {
+            crate::test::call_message::<crate::DefaultEnvironment>(
+                accounts.charlie,
+                [0; 4],
+                &[],
+            )
+        });
+
+        // then
+        assert!(failed.is_err());
+        assert_eq!(crate::test::recorded_events().count(), 1);
+        assert_eq!(
+            crate::test::fees_paid::<crate::DefaultEnvironment>(accounts.alice),
+            30_000
+        );
+        assert_eq!(
+            crate::test::get_account_balance::<crate::DefaultEnvironment>(accounts.alice),
+            Ok(99_999)
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn clear_contract_storage_returns_the_previous_length() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::set_contract_storage(&1u8, &[7u8; 7]);
+
+        // when
+        let existing = crate::clear_contract_storage(&1u8);
+        let absent = crate::clear_contract_storage(&2u8);
+
+        // then
+        assert_eq!(existing, Some(7));
+        assert_eq!(absent, None);
+        assert_eq!(
+            crate::test::get_contract_storage_rw::<crate::DefaultEnvironment>(
+                &accounts.alice
+            ),
+            (0, 2)
+        );
+        assert_eq!(
+            crate::test::count_noop_clears::<crate::DefaultEnvironment>(&accounts.alice),
+            1
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn setting_storage_distinguishes_new_cells_from_overwrites() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // when
+        let first = crate::set_contract_storage(&1u8, &[7u8; 6]);
+        let overwrite = crate::set_contract_storage(&1u8, &2u8);
+
+        // then
+        assert_eq!(first, None);
+        assert_eq!(overwrite, Some(6));
+        assert_eq!(
+            crate::test::count_new_cells::<crate::DefaultEnvironment>(&accounts.alice),
+            1
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn seeded_storage_is_readable_by_the_contract() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // when
+        let seeded = crate::test::seed_storage::<crate::DefaultEnvironment, _, _>(
+            accounts.alice,
+            (0..1000u32).map(|n| (n, u64::from(n) * 2)),
+        );
+
+        // then
+        assert_eq!(seeded, 1000);
+        assert_eq!(crate::get_contract_storage::<u32, u64>(&999)?, Some(1998));
+        assert_eq!(crate::contains_contract_storage(&1000u32), None);
+        assert_eq!(
+            crate::test::get_contract_storage_rw::<crate::DefaultEnvironment>(
+                &accounts.alice
+            ),
+            (2, 1000)
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn watched_storage_key_records_changes() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::watch_storage_key::<crate::DefaultEnvironment, _>(
+            accounts.alice,
+            &1u8,
+        );
+        let rw_before = crate::test::get_contract_storage_rw::<crate::DefaultEnvironment>(
+            &accounts.alice,
+        );
+
+        // when
+        crate::set_contract_storage(&1u8, &10u32);
+        crate::set_contract_storage(&2u8, &20u32);
+        crate::set_contract_storage(&1u8, &11u32);
+        crate::clear_contract_storage(&1u8);
+
+        // then
+        let frame = 0;
+        assert_eq!(
+            crate::test::storage_changes::<crate::DefaultEnvironment, _>(
+                accounts.alice,
+                &1u8
+            ),
+            vec![
+                crate::test::StorageChange {
+                    frame,
+                    old_value: None,
+                    new_value: Some(scale::Encode::encode(&10u32)),
+                },
+                crate::test::StorageChange {
+                    frame,
+                    old_value: Some(scale::Encode::encode(&10u32)),
+                    new_value: Some(scale::Encode::encode(&11u32)),
+                },
+                crate::test::StorageChange {
+                    frame,
+                    old_value: Some(scale::Encode::encode(&11u32)),
+                    new_value: None,
+                },
+            ]
+        );
+        assert!(
+            crate::test::storage_changes::<crate::DefaultEnvironment, _>(
+                accounts.alice,
+                &2u8
+            )
+            .is_empty()
+        );
+        let (reads, writes) = rw_before;
+        assert_eq!(
+            crate::test::get_contract_storage_rw::<crate::DefaultEnvironment>(
+                &accounts.alice,
+            ),
+            (reads, writes + 4)
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn account_nonce_progresses_with_calls() -> Result<()> {
+    fn deploy() {}
+    fn call() {
+        let address = crate::test::derive_contract_address::<crate::DefaultEnvironment>(
+            Default::default(),
+            &[],
+            &[],
+        );
+        crate::return_value(crate::ReturnFlags::empty(), &address);
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            crate::test::EntryPoints { deploy, call },
+        );
+        let call = || {
+            crate::test::call_message::<crate::DefaultEnvironment>(
+                accounts.charlie,
+                [0; 4],
+                &[],
+            )
+            .decode::<<crate::DefaultEnvironment as crate::Environment>::AccountId>()
+            .expect("contract returned an address")
+        };
+        assert_eq!(
+            crate::test::account_nonce::<crate::DefaultEnvironment>(accounts.alice),
+            0
+        );
+
+        // when
+        let addresses = [call(), call(), call()];
+
+        // then
+        assert_eq!(
+            crate::test::account_nonce::<crate::DefaultEnvironment>(accounts.alice),
+            3
+        );
+        assert_ne!(addresses[0], addresses[1]);
+        assert_ne!(addresses[1], addresses[2]);
+        assert_eq!(
+            crate::test::account_nonce::<crate::DefaultEnvironment>(accounts.charlie),
+            0
+        );
+
+        // when
+        crate::test::increment_nonce::<crate::DefaultEnvironment>(accounts.bob);
+
+        // then
+        assert_eq!(
+            crate::test::account_nonce::<crate::DefaultEnvironment>(accounts.bob),
+            1
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn get_contract_storage_fails_for_value_exceeding_buffer() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        let value = vec![1u8; crate::BUFFER_SIZE];
+        crate::set_contract_storage(&1u8, &value);
+
+        // when
+        let result = crate::get_contract_storage::<u8, Vec<u8>>(&1u8);
+
+        // then
+        assert_eq!(result, Err(crate::Error::BufferTooSmall));
+        Ok(())
+    })
+}
+
+#[test]
+fn block_advance_callbacks_drip_rewards() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        let bob = accounts.bob;
+        let balance = crate::test::get_account_balance::<crate::DefaultEnvironment>(bob)?;
+        crate::test::on_block_advance::<crate::DefaultEnvironment, _>(move |_| {
+            let balance =
+                crate::test::get_account_balance::<crate::DefaultEnvironment>(bob)
+                    .expect("bob has a balance");
+            crate::test::set_account_balance::<crate::DefaultEnvironment>(
+                bob,
+                balance + 10,
+            );
+        });
+        let blocks = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let recorded = blocks.clone();
+        crate::test::on_block_advance::<crate::DefaultEnvironment, _>(move |number| {
+            recorded.borrow_mut().push(number);
+        });
+
+        // when
+        for _ in 0..5 {
+            crate::test::advance_block::<crate::DefaultEnvironment>();
+        }
+
+        // then
+        assert_eq!(
+            crate::test::get_account_balance::<crate::DefaultEnvironment>(bob),
+            Ok(balance + 50)
+        );
+        assert_eq!(*blocks.borrow(), [1, 2, 3, 4, 5]);
+        Ok(())
+    })
+}
+
+#[test]
+fn dry_run_call_matches_executing_the_call() -> Result<()> {
+    fn deploy() {}
+    fn call() {
+        let (_selector, value): ([u8; 4], u32) = crate::decode_input().unwrap();
+        crate::set_contract_storage(&1u8, &value);
+        crate::emit_event::<crate::DefaultEnvironment, _>(Transferred {
+            amount: 1,
+            memo: [0; 40],
+        });
+        crate::return_value::<ink_primitives::MessageResult<u32>>(
+            crate::ReturnFlags::empty(),
+            &Ok(value + 1),
+        )
+    }
+    fn params(
+        callee: <crate::DefaultEnvironment as crate::Environment>::AccountId,
+    ) -> crate::call::CallParams<
+        crate::DefaultEnvironment,
+        crate::call::Call<crate::DefaultEnvironment>,
+        crate::call::utils::ArgumentList<
+            crate::call::utils::Argument<u32>,
+            crate::call::utils::EmptyArgumentList,
+        >,
+        u32,
+    > {
+        crate::call::build_call::<crate::DefaultEnvironment>()
+            .call(callee)
+            .exec_input(
+                crate::call::ExecutionInput::new(crate::call::Selector::new([0; 4]))
+                    .push_arg(41u32),
+            )
+            .returns::<u32>()
+            .params()
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::set_engine_config(crate::test::EngineConfig {
+            storage_deposit_per_cell: Some(10),
+            storage_deposit_per_byte: Some(2),
+            ..Default::default()
+        });
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            crate::test::EntryPoints { deploy, call },
+        );
+        let balance = || {
+            crate::test::get_account_balance::<crate::DefaultEnvironment>(accounts.alice)
+        };
+        let balance_before = balance()?;
+
+        // when
+        let dry_run = crate::test::dry_run_call(&params(accounts.charlie))?;
+        let untouched = (
+            balance()?,
+            crate::test::recorded_events().count(),
+            crate::test::get_storage_raw::<crate::DefaultEnvironment, _>(
+                accounts.charlie,
+                &1u8,
+            ),
+        );
+        let result = params(accounts.charlie).invoke();
+
+        // then
+        assert_eq!(untouched, (balance_before, 0, None));
+        assert_eq!(result, 42);
+        assert!(!dry_run.did_revert());
+        assert_eq!(
+            dry_run.return_data,
+            scale::Encode::encode(&ink_primitives::MessageResult::Ok(42u32))
+        );
+        assert_eq!(dry_run.events.len(), 1);
+        assert_eq!(dry_run.gas_consumed, crate::test::gas_consumed());
+        assert!(dry_run.gas_consumed > 0);
+        assert_eq!(
+            dry_run.storage_deposit,
+            crate::test::StorageDeposit::Charge(balance_before - balance()?)
+        );
+        assert_eq!(
+            dry_run.storage_deposit,
+            crate::test::StorageDeposit::Charge(10 + 2 * 4)
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn calls_of_users_and_tombstones_are_not_callable() -> Result<()> {
+    fn deploy() {}
+    fn call() {
+        crate::terminate_contract::<crate::DefaultEnvironment>(crate::caller::<
+            crate::DefaultEnvironment,
+        >())
+    }
+    fn invoke(
+        callee: <crate::DefaultEnvironment as crate::Environment>::AccountId,
+    ) -> Result<ink_primitives::MessageResult<()>> {
+        crate::call::build_call::<crate::DefaultEnvironment>()
+            .call(callee)
+            .exec_input(crate::call::ExecutionInput::new(
+                crate::call::Selector::new([0; 4]),
+            ))
+            .returns::<()>()
+            .try_invoke()
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        let contract = accounts.charlie;
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            contract,
+            crate::test::EntryPoints { deploy, call },
+        );
+        let kind = crate::test::account_kind::<crate::DefaultEnvironment>;
+        assert_eq!(kind(contract), crate::test::AccountKind::Contract);
+
+        // when
+        let user = invoke(accounts.bob);
+        std::panic::catch_unwind(|| invoke(contract))
+            .expect_err("the contract did not terminate");
+        let tombstone = invoke(contract);
+
+        // then
+        let not_callable = Err(crate::Error::ReturnError(
+            crate::ReturnErrorCode::NotCallable,
+        ));
+        assert_eq!(user, not_callable);
+        assert_eq!(tombstone, not_callable);
+        assert_eq!(kind(accounts.bob), crate::test::AccountKind::User);
+        assert_eq!(kind(contract), crate::test::AccountKind::Tombstone);
+        Ok(())
+    })
+}
+
+#[test]
+fn reentrance_count_increases_with_every_reentrant_call() -> Result<()> {
+    thread_local! {
+        static COUNTS: core::cell::RefCell<Vec<u32>> =
+            const { core::cell::RefCell::new(Vec::new()) };
+    }
+    fn deploy() {}
+    fn call() {
+        let count = crate::reentrance_count::<crate::DefaultEnvironment>();
+        COUNTS.with(|counts| counts.borrow_mut().push(count));
+        if count < 2 {
+            crate::call::build_call::<crate::DefaultEnvironment>()
+                .call(crate::account_id::<crate::DefaultEnvironment>())
+                .exec_input(crate::call::ExecutionInput::new(
+                    crate::call::Selector::new([0; 4]),
+                ))
+                .returns::<()>()
+                .invoke();
+        }
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            crate::test::EntryPoints { deploy, call },
+        );
+
+        // when
+        let outermost = crate::reentrance_count::<crate::DefaultEnvironment>();
+        crate::test::call_message::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            [0; 4],
+            &[],
+        );
+
+        // then
+        assert_eq!(outermost, 0);
+        assert_eq!(COUNTS.with(|counts| counts.take()), [0, 1, 2]);
+        Ok(())
+    })
+}
+
+#[test]
+fn exec_context_reflects_the_innermost_call() -> Result<()> {
+    type ExecContext = crate::test::ExecContext<crate::DefaultEnvironment>;
+    thread_local! {
+        static INNER: core::cell::RefCell<Option<ExecContext>> =
+            const { core::cell::RefCell::new(None) };
+    }
+    fn deploy() {}
+    fn call() {
+        let context = crate::test::exec_context::<crate::DefaultEnvironment>();
+        INNER.with(|inner| *inner.borrow_mut() = Some(context));
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            crate::test::EntryPoints { deploy, call },
+        );
+        crate::test::set_caller::<crate::DefaultEnvironment>(accounts.bob);
+        crate::test::set_value_transferred::<crate::DefaultEnvironment>(5);
+        crate::test::advance_block::<crate::DefaultEnvironment>();
+
+        // when
+        let outer = crate::test::exec_context::<crate::DefaultEnvironment>();
+        crate::test::call_message::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            [0; 4],
+            &[],
+        );
+        let inner = INNER
+            .with(|inner| inner.take())
+            .expect("the contract was called");
+
+        // then
+        let expected = ExecContext {
+            caller: Some(accounts.bob),
+            callee: Some(accounts.alice),
+            value_transferred: 5,
+            block_number: 1,
+            timestamp: outer.timestamp,
+            origin: Some(crate::test::Origin::Signed(accounts.bob)),
+        };
+        assert_eq!(outer, expected);
+        assert_eq!(
+            inner,
+            ExecContext {
+                callee: Some(accounts.charlie),
+                ..expected
+            }
+        );
+        assert_eq!(
+            crate::test::value_transferred::<crate::DefaultEnvironment>(),
+            5
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn invoke_contract_decodes_returned_bytes_only() -> Result<()> {
+    fn deploy() {}
+    fn call_void() {}
+    fn call_u32() {
+        crate::return_value::<ink_primitives::MessageResult<u32>>(
+            crate::ReturnFlags::empty(),
+            &Ok(7),
+        )
+    }
+    fn invoke<R: scale::Decode>(
+        callee: <crate::DefaultEnvironment as crate::Environment>::AccountId,
+    ) -> Result<ink_primitives::MessageResult<R>> {
+        crate::call::build_call::<crate::DefaultEnvironment>()
+            .call(callee)
+            .exec_input(crate::call::ExecutionInput::new(
+                crate::call::Selector::new([0; 4]),
+            ))
+            .returns::<R>()
+            .try_invoke()
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            crate::test::EntryPoints {
+                deploy,
+                call: call_void,
+            },
+        );
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.django,
+            crate::test::EntryPoints {
+                deploy,
+                call: call_u32,
+            },
+        );
+
+        // when
+        let void = invoke::<()>(accounts.charlie);
+        let number = invoke::<u32>(accounts.django);
+        let missing = invoke::<Option<u32>>(accounts.charlie);
+        let unknown = invoke::<()>(accounts.eve);
+
+        // then
+        assert_eq!(void, Ok(Ok(())));
+        assert_eq!(number, Ok(Ok(7)));
+        assert!(matches!(missing, Err(crate::Error::Decode(_))));
+        assert_eq!(
+            unknown,
+            Err(crate::Error::ReturnError(
+                crate::ReturnErrorCode::NotCallable
+            ))
+        );
+        assert_eq!(crate::test::recorded_frames().len(), 4);
+        Ok(())
+    })
+}
+
+#[test]
+fn emitted_events_compare_with_unordered_topics() {
+    // given
+    let event = crate::test::EmittedEvent::new(vec![vec![1], vec![2], vec![3]], vec![7]);
+    let permuted =
+        crate::test::EmittedEvent::new(vec![vec![3], vec![1], vec![2]], vec![7]);
+    let other_data =
+        crate::test::EmittedEvent::new(vec![vec![3], vec![1], vec![2]], vec![8]);
+    let other_topics = crate::test::EmittedEvent::new(vec![vec![1], vec![2]], vec![7]);
+
+    // then
+    assert_ne!(event, permuted);
+    assert!(event.eq_unordered_topics(&permuted));
+    assert!(!event.eq_unordered_topics(&other_data));
+    assert!(!event.eq_unordered_topics(&other_topics));
+}
+
+#[test]
+fn assert_events_unordered_detects_data_mismatch() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        emit_transferred(1);
+        let recorded: Vec<_> = crate::test::recorded_events().collect();
+        let mut topics = recorded[0].topics.clone();
+        topics.reverse();
+        let data = recorded[0].data.clone();
+
+        // when
+        crate::test::assert_events_unordered(&[crate::test::EmittedEvent::new(
+            topics.clone(),
+            data,
+        )]);
+        let mismatch = std::panic::catch_unwind(|| {
+            crate::test::assert_events_unordered(&[crate::test::EmittedEvent::new(
+                topics,
+                scale::Encode::encode(&Transferred {
+                    amount: 2,
+                    memo: [0; 40],
+                }),
+            )])
+        });
+
+        // then
+        assert!(mismatch.is_err());
+        Ok(())
+    })
+}
+
+#[test]
+fn weight_to_fee_uses_registered_fee_curve() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        let linear = crate::weight_to_fee::<crate::DefaultEnvironment>(10);
+
+        // when
+        crate::test::set_weight_to_fee_fn::<crate::DefaultEnvironment, _>(|gas| {
+            let gas = u128::from(gas);
+            3 * gas * gas + 2 * gas + 1
+        });
+        let fees: Vec<u128> = [0, 10, 1_000]
+            .into_iter()
+            .map(crate::weight_to_fee::<crate::DefaultEnvironment>)
+            .collect();
+        crate::test::set_weight_to_fee_fn::<crate::DefaultEnvironment, _>(|gas| {
+            u128::from(gas) * 7
+        });
+        let replaced = crate::weight_to_fee::<crate::DefaultEnvironment>(10);
+
+        // then
+        assert_eq!(linear, 1_000);
+        assert_eq!(fees, [1, 321, 3_002_001]);
+        assert_eq!(replaced, 70);
+        Ok(())
+    })
+}
+
+#[test]
+fn engine_check_names_missing_host_functions() {
+    // given
+    let reduced = ink_engine::capabilities()
+        .without(ink_engine::Capabilities::CALL | ink_engine::Capabilities::HASHING);
+
+    // when
+    let supported =
+        super::check_engine(ink_engine::ENGINE_API_VERSION, ink_engine::capabilities());
+    let missing = super::check_engine(ink_engine::ENGINE_API_VERSION, reduced);
+    let outdated = super::check_engine(0, ink_engine::capabilities());
+
+    // then
+    assert_eq!(supported, Ok(()));
+    assert_eq!(
+        missing,
+        Err(format!(
+            "the off-chain environment requires `ink_engine` API version 3, found \
+            version {}, the engine does not implement `call`, `hashing`",
+            ink_engine::ENGINE_API_VERSION
+        ))
+    );
+    assert_eq!(
+        outdated,
+        Err(String::from(
+            "the off-chain environment requires `ink_engine` API version 3, found \
+            version 0"
+        ))
+    );
+}
+
+#[test]
+fn failure_dump_is_written_for_panics_of_engine_using_threads() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::test::install_failure_dump();
+        crate::debug_message("about to fail");
+
+        // when
+        let panicked = std::panic::catch_unwind(|| panic!("test failed"));
+        let dump = crate::test::LAST_FAILURE_DUMP.with(|last| last.borrow_mut().take());
+        let unrelated = std::thread::spawn(|| {
+            let panicked = std::panic::catch_unwind(|| panic!("unrelated failure"));
+            assert!(panicked.is_err());
+            crate::test::LAST_FAILURE_DUMP.with(|last| last.borrow_mut().take())
+        })
+        .join()
+        .expect("spawned thread panicked");
+
+        // then
+        assert!(panicked.is_err());
+        let dump = dump.expect("no engine state was written");
+        assert!(dump.starts_with("ink_engine state:\n"));
+        assert!(dump.contains(&format!("  caller: 0x{}\n", "01".repeat(32))));
+        assert!(dump.contains("  debug message: about to fail\n"));
+        assert_eq!(unrelated, None);
+        Ok(())
+    })
+}
+
+#[test]
+fn event_data_limit_is_enforced() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        let data_len = scale::Encode::encoded_size(&Transferred {
+            amount: 1,
+            memo: [0; 40],
+        });
+        crate::test::set_max_event_data_len(data_len);
+
+        // when
+        emit_transferred(1);
+        crate::test::set_max_event_data_len(data_len - 1);
+        let oversized = std::panic::catch_unwind(|| emit_transferred(2));
+        crate::test::set_engine_config(Default::default());
+
+        // then
+        let message = oversized
+            .expect_err("oversized event was emitted")
+            .downcast::<String>()
+            .expect("panic message is no string");
+        assert!(message.contains(&format!("actual: {data_len}")));
+        assert!(message.contains(&format!("allowed: {}", data_len - 1)));
+        assert_eq!(crate::test::recorded_events().count(), 1);
+        Ok(())
+    })
+}
+
+#[test]
+fn reverted_delegate_call_discards_storage_writes_on_frame_success() -> Result<()> {
+    const CODE_HASH: [u8; 32] = [0x42; 32];
+
+    fn deploy() {}
+    fn call_proxy() {
+        crate::set_contract_storage(&1u8, &10u8);
+        crate::call::build_call::<crate::DefaultEnvironment>()
+            .delegate(CODE_HASH.into())
+            .exec_input(crate::call::ExecutionInput::new(
+                crate::call::Selector::new([0; 4]),
+            ))
+            .returns::<()>()
+            .invoke();
+        let value = crate::get_contract_storage::<u8, u8>(&1u8)
+            .unwrap()
+            .unwrap();
+        crate::return_value::<u8>(crate::ReturnFlags::empty(), &value)
+    }
+    fn call_logic() {
+        crate::set_contract_storage(&1u8, &20u8);
+        assert_eq!(crate::get_contract_storage::<u8, u8>(&1u8), Ok(Some(20)));
+        crate::return_value::<ink_primitives::MessageResult<()>>(
+            crate::ReturnFlags::REVERT,
+            &Ok(()),
+        )
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::set_engine_config(crate::test::EngineConfig {
+            commit_mode: crate::test::CommitMode::OnFrameSuccess,
+            ..Default::default()
+        });
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            crate::test::EntryPoints {
+                deploy,
+                call: call_proxy,
+            },
+        );
+        crate::test::register_code::<crate::DefaultEnvironment>(
+            CODE_HASH.into(),
+            crate::test::EntryPoints {
+                deploy,
+                call: call_logic,
+            },
+        );
+
+        // when
+        let result = crate::test::call_message::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            [0; 4],
+            &[],
+        );
+        crate::test::set_engine_config(Default::default());
+
+        // then
+        assert_eq!(result.decode::<u8>()?, 10);
+        assert_eq!(
+            crate::test::storage_dump::<crate::DefaultEnvironment>(&accounts.charlie),
+            [(vec![1], vec![10])]
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn caller_frame_simulates_call_from_contract() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::set_callee::<crate::DefaultEnvironment>(accounts.bob);
+        let top_level = crate::caller_is_origin::<crate::DefaultEnvironment>();
+
+        // when
+        let (caller, caller_is_origin) = crate::test::with_caller_frame::<
+            crate::DefaultEnvironment,
+            _,
+            _,
+        >(accounts.charlie, || {
+            (
+                crate::caller::<crate::DefaultEnvironment>(),
+                crate::caller_is_origin::<crate::DefaultEnvironment>(),
+            )
+        });
+
+        // then
+        assert!(top_level);
+        assert_eq!(caller, accounts.charlie);
+        assert!(!caller_is_origin);
+        assert_eq!(crate::caller::<crate::DefaultEnvironment>(), accounts.alice);
+        assert!(crate::caller_is_origin::<crate::DefaultEnvironment>());
+        Ok(())
+    })
+}
+
+#[test]
+fn weighted_chain_extension_traps_when_out_of_gas() -> Result<()> {
+    struct MockExtension;
+    impl ink_engine::ChainExtension for MockExtension {
+        fn ext_id(&self) -> u16 {
+            0
+        }
+
+        fn call(&mut self, _func_id: u16, _input: &[u8], output: &mut Vec<u8>) -> u32 {
+            output.push(42);
+            0
+        }
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::test::set_engine_config(crate::test::EngineConfig {
+            gas_limit: 1_000,
+            ..Default::default()
+        });
+        crate::test::register_chain_extension_with_weight(1, 600, MockExtension);
+        let call = || {
+            crate::chain_extension::ChainExtensionMethod::build(1)
+                .input::<u8>()
+                .output::<u8, false>()
+                .ignore_error_code()
+                .call(&7)
+        };
+
+        // when
+        let first = call();
+        let second = std::panic::catch_unwind(call);
+
+        // then
+        assert_eq!(first, 42);
+        assert!(second.is_err());
+        assert_eq!(crate::test::chain_extension_calls().len(), 1);
+        let out_of_gas = crate::test::out_of_gas_chain_extension_calls();
+        assert_eq!(out_of_gas.len(), 1);
+        assert_eq!(out_of_gas[0].weight, 600);
+        Ok(())
+    })
+}
+
+#[test]
+fn storage_cells_decode_as_typed_values() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::set_callee::<crate::DefaultEnvironment>(accounts.bob);
+        crate::set_contract_storage(&1u8, &1337u32);
+        crate::set_contract_storage(&2u8, &7u8);
+
+        // when
+        let present = crate::test::get_decoded_storage::<crate::DefaultEnvironment, _, u32>(
+            accounts.bob,
+            &1u8,
+        );
+        let absent = crate::test::get_decoded_storage::<crate::DefaultEnvironment, _, u32>(
+            accounts.bob,
+            &3u8,
+        );
+        let corrupt = crate::test::get_decoded_storage::<crate::DefaultEnvironment, _, u32>(
+            accounts.bob,
+            &2u8,
+        );
+
+        // then
+        assert_eq!(present, Ok(Some(1337)));
+        assert_eq!(absent, Ok(None));
+        assert!(corrupt.is_err());
+        assert_eq!(
+            crate::test::get_storage_raw::<crate::DefaultEnvironment, _>(
+                accounts.bob,
+                &2u8
+            ),
+            Some(vec![7])
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn storage_value_assertion_shows_expected_and_actual() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::set_callee::<crate::DefaultEnvironment>(accounts.bob);
+        crate::set_contract_storage(&1u8, &1337u32);
+        crate::set_contract_storage(&2u8, &7u8);
+        let failure = |key: u8, expected: u32| {
+            let res = std::panic::catch_unwind(|| {
+                crate::test::assert_storage_value::<crate::DefaultEnvironment, _, _>(
+                    accounts.bob,
+                    &key,
+                    expected,
+                )
+            });
+            *res.expect_err("the assertion must fail")
+                .downcast::<String>()
+                .expect("the panic message is a string")
+        };
+
+        // when
+        crate::test::assert_storage_value::<crate::DefaultEnvironment, _, _>(
+            accounts.bob,
+            &1u8,
+            1337u32,
+        );
+
+        // then
+        assert_eq!(
+            failure(1, 42),
+            "storage value at key 0x01 differs\n  expected: 42\n  actual:   1337"
+        );
+        assert_eq!(
+            failure(3, 42),
+            "storage value at key 0x03 differs\n  expected: 42\n  actual:   <absent>"
+        );
+        assert!(failure(2, 42).contains("actual:   <undecodable> 0x07"));
+        Ok(())
+    })
+}
+
+#[test]
+fn fork_does_not_leak_storage_writes_and_events() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::set_callee::<crate::DefaultEnvironment>(accounts.bob);
+        crate::set_contract_storage(&1u8, &11u32);
+
+        // when
+        let in_fork = crate::test::with_fork(|| {
+            crate::set_contract_storage(&1u8, &22u32);
+            crate::set_contract_storage(&2u8, &33u32);
+            crate::emit_event::<crate::DefaultEnvironment, _>(Transferred {
+                amount: 1,
+                memo: [0; 40],
+            });
+            (
+                crate::get_contract_storage::<u8, u32>(&1u8),
+                crate::test::recorded_events().count(),
+            )
+        });
+
+        // then
+        assert_eq!(in_fork, (Ok(Some(22)), 1));
+        assert_eq!(crate::get_contract_storage::<u8, u32>(&1u8), Ok(Some(11)));
+        assert_eq!(crate::get_contract_storage::<u8, u32>(&2u8), Ok(None));
+        assert_eq!(crate::test::recorded_events().count(), 0);
+        Ok(())
+    })
+}
+
+#[test]
+fn fork_is_restored_when_closure_panics() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::set_callee::<crate::DefaultEnvironment>(accounts.bob);
+
+        // when
+        let res = std::panic::catch_unwind(|| {
+            crate::test::with_fork(|| {
+                crate::set_contract_storage(&1u8, &22u32);
+                panic!("failure inside of the fork")
+            })
+        });
+
+        // then
+        assert!(res.is_err());
+        assert_eq!(crate::get_contract_storage::<u8, u32>(&1u8), Ok(None));
+        Ok(())
+    })
+}
+
+#[test]
+fn root_origin_has_no_caller() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::set_origin::<crate::DefaultEnvironment>(
+            crate::test::Origin::Signed(accounts.bob),
+        );
+        let signed = crate::caller::<crate::DefaultEnvironment>();
+
+        // when
+        let res = crate::test::call_with_origin::<crate::DefaultEnvironment, _, _>(
+            crate::test::Origin::Root,
+            || std::panic::catch_unwind(crate::caller::<crate::DefaultEnvironment>),
+        );
+
+        // then
+        assert_eq!(signed, accounts.bob);
+        let message = res
+            .expect_err("reading the caller of a root origin must fail")
+            .downcast::<String>()
+            .expect("panic message must be a string");
+        assert!(message.contains(&format!(
+            "{:?}",
+            crate::Error::OffChain(OffChainError::UnsignedOrigin)
+        )));
+        assert_eq!(crate::caller::<crate::DefaultEnvironment>(), accounts.bob);
+        Ok(())
+    })
+}
+
+/// Returns `func_id` bytes of output.
+struct SizedOutputExtension;
+
+impl ink_engine::ChainExtension for SizedOutputExtension {
+    fn ext_id(&self) -> u16 {
+        0
+    }
+
+    fn call(&mut self, func_id: u16, _input: &[u8], output: &mut Vec<u8>) -> u32 {
+        output.resize(usize::from(func_id), 7);
+        0
+    }
+}
+
+#[test]
+fn chain_extension_returning_no_bytes_decodes_into_unit() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::test::register_chain_extension(SizedOutputExtension);
+
+        // when
+        crate::chain_extension::ChainExtensionMethod::build(0)
+            .input::<()>()
+            .output::<(), false>()
+            .ignore_error_code()
+            .call(&());
+
+        // then
+        assert_eq!(crate::test::last_chain_extension_status(0), Some(0));
+        Ok(())
+    })
+}
+
+#[test]
+#[should_panic(expected = "contract trapped: BufferTooSmall(16393)")]
+fn chain_extension_output_exceeding_the_buffer_traps() {
+    let _ = crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::test::register_chain_extension(SizedOutputExtension);
+
+        // when
+        crate::chain_extension::ChainExtensionMethod::build(
+            u32::try_from(crate::BUFFER_SIZE + 1).expect("fits into a func id"),
+        )
+        .input::<()>()
+        .output::<Vec<u8>, false>()
+        .ignore_error_code()
+        .call(&());
+        Ok(())
+    });
+}
+
+#[test]
+fn chain_extension_observes_origin() -> Result<()> {
+    struct OriginExtension;
+    impl ink_engine::ChainExtension for OriginExtension {
+        fn ext_id(&self) -> u16 {
+            0
+        }
+
+        fn call(&mut self, _func_id: u16, _input: &[u8], _output: &mut Vec<u8>) -> u32 {
+            unreachable!("the extension is called with the engine")
+        }
+
+        fn call_with_engine(
+            &mut self,
+            engine: &mut ink_engine::ext::Engine,
+            _func_id: u16,
+            _input: &[u8],
+            output: &mut Vec<u8>,
+        ) -> u32 {
+            scale::Encode::encode_to(&engine.origin(), output);
+            0
+        }
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::test::register_chain_extension(OriginExtension);
+        let call = || {
+            crate::chain_extension::ChainExtensionMethod::build(1)
+                .input::<()>()
+                .output::<Option<ink_engine::Origin>, false>()
+                .ignore_error_code()
+                .call(&())
+        };
+
+        // when
+        let observed = crate::test::call_with_origin::<crate::DefaultEnvironment, _, _>(
+            crate::test::Origin::None,
+            call,
+        );
+
+        // then
+        assert_eq!(observed, Some(ink_engine::Origin::None));
+        Ok(())
+    })
+}
+
+#[test]
+fn strict_decoding_rejects_mismatching_storage_type() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::set_contract_storage(&1u8, &7u32);
+        crate::set_contract_storage(&2u8, &7u64);
+
+        // when
+        let widened = crate::get_contract_storage::<u8, u64>(&1u8);
+        let truncated = crate::get_contract_storage::<u8, u32>(&2u8);
+
+        // then
+        assert_eq!(
+            widened,
+            Err(crate::Error::OffChain(OffChainError::DecodingFailed {
+                expected_len: 4,
+                consumed: 0,
+            }))
+        );
+        assert_eq!(
+            truncated,
+            Err(crate::Error::OffChain(OffChainError::DecodingFailed {
+                expected_len: 8,
+                consumed: 4,
+            }))
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn lenient_decoding_reads_property_prefix() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        <EnvInstance as OnInstance>::on_instance(|instance| {
+            let input = scale::Encode::encode(&(7u32, 8u32));
+            instance.engine.exec_context.set_input(input);
+        });
+
+        // when
+        let strict = crate::decode_input::<u32>();
+        crate::test::set_strict_decoding(false);
+        let lenient = crate::decode_input::<u32>();
+
+        // then
+        assert_eq!(
+            strict,
+            Err(crate::Error::OffChain(OffChainError::DecodingFailed {
+                expected_len: 8,
+                consumed: 4,
+            }))
+        );
+        assert_eq!(lenient, Ok(7));
+        Ok(())
+    })
+}
+
+#[test]
+fn weight_tables_scale_gas_consumed_by_message() -> Result<()> {
+    fn deploy() {}
+    fn call() {
+        crate::set_contract_storage(&1u8, &[7u8; 16]);
+        let _ = crate::get_contract_storage::<u8, [u8; 16]>(&1u8);
+        crate::emit_event::<crate::DefaultEnvironment, _>(Transferred {
+            amount: 1,
+            memo: [0; 40],
+        });
+        let mut output = [0; 32];
+        crate::hash_bytes::<Blake2x256>(&[1; 64], &mut output);
+    }
+    let table = crate::test::WeightTable {
+        storage_write_base: 100,
+        storage_write_per_byte: 1,
+        storage_read_base: 50,
+        storage_read_per_byte: 2,
+        event_base: 30,
+        event_per_topic: 10,
+        event_per_byte: 3,
+        hash_base: 5,
+        hash_per_byte: 1,
+        call_base: 40,
+        transfer_base: 70,
+        gas_left_base: 0,
+    };
+    let doubled = crate::test::WeightTable {
+        storage_write_base: 200,
+        storage_write_per_byte: 2,
+        storage_read_base: 100,
+        storage_read_per_byte: 4,
+        event_base: 60,
+        event_per_topic: 20,
+        event_per_byte: 6,
+        hash_base: 10,
+        hash_per_byte: 2,
+        call_base: 80,
+        transfer_base: 140,
+        gas_left_base: 0,
+    };
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::register_contract::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            crate::test::EntryPoints { deploy, call },
+        );
+        let gas_consumed = |table| {
+            crate::test::set_weight_table(table);
+            crate::test::call_message::<crate::DefaultEnvironment>(
+                accounts.charlie,
+                [0; 4],
+                &[],
+            );
+            crate::test::gas_consumed()
+        };
+
+        // when
+        let consumed = gas_consumed(table);
+        let doubled_consumed = gas_consumed(doubled);
+
+        // then
+        assert!(consumed > 0);
+        assert_eq!(doubled_consumed, 2 * consumed);
+        Ok(())
+    })
+}
+
+#[test]
+fn metered_hashing_of_unbounded_input_traps() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        let weights = crate::test::WeightTable::default();
+        crate::test::set_engine_config(crate::test::EngineConfig {
+            gas_metering: true,
+            gas_limit: weights.hash_base + 1024 * weights.hash_per_byte,
+            ..Default::default()
+        });
+        let mut output = [0; 32];
+
+        // when
+        crate::hash_bytes::<Blake2x256>(&[7; 1024], &mut output);
+        let consumed = crate::test::gas_consumed();
+        let res = std::panic::catch_unwind(|| {
+            let mut output = [0; 32];
+            crate::hash_bytes::<Blake2x256>(&[7; 1024 * 1024], &mut output);
+        });
+
+        // then
+        assert_eq!(consumed, weights.hash_base + 1024 * weights.hash_per_byte);
+        let message = res
+            .expect_err("hashing must run out of gas")
+            .downcast::<String>()
+            .expect("panic message must be a string");
+        assert!(message.contains("OutOfGas"));
+        assert_eq!(crate::test::gas_consumed(), consumed);
+        Ok(())
+    })
+}
+
+#[test]
+fn transfers_and_termination_conserve_total_issuance() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        let contract = accounts.eve;
+        crate::test::set_callee::<crate::DefaultEnvironment>(contract);
+        crate::test::set_account_balance::<crate::DefaultEnvironment>(contract, 100);
+        let issuance = crate::test::total_issuance::<crate::DefaultEnvironment>();
+
+        // when
+        crate::transfer::<crate::DefaultEnvironment>(accounts.bob, 30)?;
+        crate::transfer::<crate::DefaultEnvironment>(accounts.charlie, 20)?;
+        crate::test::assert_contract_termination::<crate::DefaultEnvironment, _>(
+            move || {
+                crate::terminate_contract::<crate::DefaultEnvironment>(accounts.django)
+            },
+            accounts.django,
+            50,
+        );
+
+        // then
+        assert_eq!(
+            crate::test::total_issuance::<crate::DefaultEnvironment>(),
+            issuance
+        );
+        let ids: Vec<Vec<u8>> = crate::test::accounts()
+            .into_iter()
+            .map(|account| account.id)
+            .collect();
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(ids, sorted);
+        Ok(())
+    })
+}
+
+#[test]
+fn transfer_to_unknown_account_fails_without_implicit_account_creation() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        let unknown: ink_primitives::AccountId = [0x42; 32].into();
+        crate::test::set_engine_config(crate::test::EngineConfig {
+            allow_implicit_account_creation: false,
+            ..Default::default()
+        });
+
+        // when
+        let rejected = crate::transfer::<crate::DefaultEnvironment>(unknown, 10);
+        let accepted = crate::transfer::<crate::DefaultEnvironment>(accounts.bob, 10);
+
+        // then
+        assert!(rejected.is_err());
+        assert_eq!(accepted, Ok(()));
+        let encode = scale::Encode::encode;
+        assert_eq!(
+            crate::test::rejected_transfers(),
+            [crate::test::TransferRecord {
+                from: encode(&accounts.alice),
+                to: encode(&unknown),
+                value: 10,
+                frame: 0,
+            }]
+        );
+        assert_eq!(
+            crate::test::recorded_transfers(),
+            [crate::test::TransferRecord {
+                from: encode(&accounts.alice),
+                to: encode(&accounts.bob),
+                value: 10,
+                frame: 0,
+            }]
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn transfer_between_funds_accounts_without_impersonating_the_sender() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        let bob_balance =
+            crate::test::get_account_balance::<crate::DefaultEnvironment>(accounts.bob)?;
+        let charlie_balance = crate::test::get_account_balance::<
+            crate::DefaultEnvironment,
+        >(accounts.charlie)?;
+
+        // when
+        crate::test::transfer_between::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            accounts.bob,
+            10,
+        )?;
+        let overdrawn = crate::test::transfer_between::<crate::DefaultEnvironment>(
+            accounts.charlie,
+            accounts.bob,
+            charlie_balance,
+        );
+
+        // then
+        assert!(overdrawn.is_err());
+        assert_eq!(
+            crate::test::get_account_balance::<crate::DefaultEnvironment>(accounts.bob),
+            Ok(bob_balance + 10)
+        );
+        assert_eq!(crate::test::fixture_transfers().len(), 1);
+        assert!(crate::test::recorded_transfers().is_empty());
+        Ok(())
+    })
+}
+
+#[test]
+fn chain_extension_statuses_are_translated_and_recorded() -> Result<()> {
+    #[derive(Debug, PartialEq, Eq)]
+    enum OracleError {
+        PriceUnavailable,
+    }
+    impl crate::chain_extension::FromStatusCode for OracleError {
+        fn from_status_code(status_code: u32) -> core::result::Result<(), Self> {
+            match status_code {
+                0 => Ok(()),
+                _ => Err(Self::PriceUnavailable),
+            }
+        }
+    }
+
+    /// Returns a price for method `1`, the domain error `1000` for method `2` and
+    /// traps for method `3`.
+    struct PriceOracle;
+    impl ink_engine::ChainExtension for PriceOracle {
+        fn ext_id(&self) -> u16 {
+            0
+        }
+
+        fn call(&mut self, _func_id: u16, _input: &[u8], _output: &mut Vec<u8>) -> u32 {
+            unreachable!("the oracle is called via `try_call_with_engine`")
+        }
+
+        fn try_call_with_engine(
+            &mut self,
+            _engine: &mut ink_engine::ext::Engine,
+            func_id: u16,
+            _input: &[u8],
+            output: &mut Vec<u8>,
+        ) -> core::result::Result<u32, ink_engine::ChainExtensionTrapped> {
+            match func_id {
+                1 => {
+                    scale::Encode::encode_to(&42u32, output);
+                    Ok(0)
+                }
+                2 => Ok(1000),
+                _ => Err(ink_engine::ChainExtensionTrapped),
+            }
+        }
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::test::register_chain_extension(PriceOracle);
+        let price = |func_id| {
+            crate::chain_extension::ChainExtensionMethod::build(func_id)
+                .input::<()>()
+                .output::<u32, false>()
+                .handle_error_code::<OracleError>()
+                .call(&())
+        };
+
+        // when
+        let available = price(1);
+        let unavailable = price(2);
+        let trapped = std::panic::catch_unwind(|| price(3));
+
+        // then
+        assert_eq!(available, Ok(42));
+        assert_eq!(unavailable, Err(OracleError::PriceUnavailable));
+        assert_eq!(crate::test::last_chain_extension_status(1), Some(0));
+        assert_eq!(crate::test::last_chain_extension_status(2), Some(1000));
+        let message = trapped
+            .expect_err("the trapping method must abort the contract")
+            .downcast::<String>()
+            .expect("panic message must be a string");
+        assert!(message.contains("ChainExtensionTrapped"));
+        assert_eq!(crate::test::last_chain_extension_status(3), None);
+        assert_eq!(
+            crate::test::chain_extension_calls()
+                .last()
+                .map(|call| call.outcome.clone()),
+            Some(crate::test::ChainExtensionOutcome::Trapped)
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn println_is_recorded_unless_denied() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::test::assert_no_printlns();
+
+        // when
+        crate::debug_message("allowed");
+
+        // then
+        crate::test::assert_println_emitted("allowed");
+        let allowed = std::panic::catch_unwind(crate::test::assert_no_printlns);
+        assert!(allowed.is_err());
+
+        // when
+        crate::test::take_recorded_printlns();
+        crate::test::deny_println();
+        crate::debug_message("denied");
+
+        // then
+        assert!(crate::test::recorded_debug_messages()
+            .into_iter()
+            .next()
+            .is_none());
+        assert_eq!(
+            crate::test::recorded_warnings(),
+            [crate::test::Warning::PrintlnDenied {
+                message: String::from("denied"),
+            }]
+        );
+        let denied = std::panic::catch_unwind(crate::test::assert_no_printlns)
+            .expect_err("denied debug messages must fail the assertion")
+            .downcast::<String>()
+            .expect("panic message must be a string");
+        assert!(denied.contains("denied"));
+        Ok(())
+    })
+}
+
+#[test]
+fn statistics_go_to_the_recorder_set() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::set_callee::<crate::DefaultEnvironment>(accounts.alice);
+        crate::set_contract_storage(&1u8, &11u8);
+
+        // when
+        let old =
+            crate::test::set_recorder(Box::new(crate::test::InMemoryRecorder::new()));
+        crate::debug_message("after");
+
+        // then
+        let old = old.in_memory().expect("the default recorder is in memory");
+        assert_eq!(old.writes(accounts.alice.as_ref()), 1);
+        assert!(old.debug_messages().clone().into_iter().next().is_none());
+        let (_, writes) = crate::test::get_contract_storage_rw::<crate::DefaultEnvironment>(
+            &accounts.alice,
+        );
+        assert_eq!(writes, 0);
+        crate::test::assert_println_emitted("after");
+        Ok(())
+    })
+}
+
+#[test]
+fn deferred_recorder_hooks_can_use_the_test_api() -> Result<()> {
+    use ink_engine::{
+        test_api::EmittedEvent,
+        AccountId,
+    };
+    use std::{
+        cell::RefCell,
+        rc::Rc,
+    };
+
+    /// Reads the balance of the writing contract on every write.
+    #[derive(Clone)]
+    struct BalanceRecorder(Rc<RefCell<Vec<u128>>>);
+    impl crate::test::Recorder for BalanceRecorder {
+        fn inc_reads(&mut self, _account_id: &AccountId) {}
+
+        fn inc_writes(&mut self, account_id: &AccountId) {
+            let account_id = scale::Decode::decode(&mut account_id.as_bytes())
+                .expect("invalid account id");
+            let balance =
+                crate::test::get_account_balance::<crate::DefaultEnvironment>(account_id)
+                    .expect("the contract has a balance");
+            self.0.borrow_mut().push(balance);
+        }
+
+        fn record_event(&mut self, _emitter: Option<&AccountId>, _event: &EmittedEvent) {}
+
+        fn record_println(&mut self, _message: &str) {}
+
+        fn clone_box(&self) -> Box<dyn crate::test::Recorder> {
+            Box::new(self.clone())
+        }
+    }
+
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        let balances = Rc::new(RefCell::new(Vec::new()));
+        crate::test::set_callee::<crate::DefaultEnvironment>(accounts.alice);
+        crate::test::set_account_balance::<crate::DefaultEnvironment>(accounts.alice, 42);
+        crate::test::set_deferred_recorder(Box::new(BalanceRecorder(balances.clone())));
+
+        // when
+        crate::set_contract_storage(&1u8, &11u8);
+        crate::set_contract_storage(&2u8, &22u8);
+
+        // then
+        assert_eq!(*balances.borrow(), [42, 42]);
+        Ok(())
+    })
+}
+
+#[test]
+fn imported_states_set_balances_and_storage() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        let alice = format!("0x{}", "01".repeat(32));
+        let mut state = crate::test::EngineState {
+            version: 2,
+            ..Default::default()
+        };
+        state.balances.insert(alice.clone(), String::from("42"));
+        state
+            .storage
+            .insert(alice, [(String::from("0x01"), String::from("0x0b"))].into());
+
+        // when
+        crate::test::import_state(state).expect("importing the state failed");
+
+        // then
+        assert_eq!(
+            crate::test::get_account_balance::<crate::DefaultEnvironment>(accounts.alice),
+            Ok(42)
+        );
+        assert_eq!(
+            crate::test::storage_dump::<crate::DefaultEnvironment>(&accounts.alice),
+            vec![(vec![1], vec![11])]
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn suspicious_patterns_are_diagnosed() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::set_callee::<crate::DefaultEnvironment>(accounts.alice);
+        crate::set_contract_storage(&1u8, &11u8);
+        crate::test::enable_diagnostics();
+
+        // when
+        crate::set_contract_storage(&1u8, &12u8);
+        crate::clear_contract_storage(&2u8);
+
+        // then
+        let diagnostics = crate::test::diagnostics();
+        assert!(matches!(
+            diagnostics.as_slice(),
+            [
+                crate::test::Diagnostic::OverwriteWithoutRead { frame: 0, .. },
+                crate::test::Diagnostic::ClearOfMissingKey { frame: 0, .. },
+            ]
+        ));
+        let res = std::panic::catch_unwind(crate::test::assert_no_diagnostics);
+        assert!(res.is_err());
+        Ok(())
+    })
+}
+
+#[test]
+fn reads_of_never_written_keys_are_diagnosed() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::test::set_callee::<crate::DefaultEnvironment>(accounts.alice);
+        crate::set_contract_storage(&1u8, &11u8);
+        crate::test::enable_diagnostics();
+
+        // when
+        let written = crate::get_contract_storage::<u8, u8>(&1u8)?;
+        let missing = crate::get_contract_storage::<u8, u8>(&2u8)?;
+
+        // then
+        assert_eq!((written, missing), (Some(11), None));
+        assert_eq!(
+            crate::test::uninitialized_reads::<crate::DefaultEnvironment>(
+                &accounts.alice
+            ),
+            [vec![2]]
+        );
+        assert_eq!(crate::test::diagnostics().len(), 1);
+        Ok(())
+    })
+}
+
+#[test]
+fn funding_the_zero_address_requires_opting_in() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        let zero = ink_primitives::AccountId::from([0; 32]);
+
+        // when
+        let res = std::panic::catch_unwind(|| {
+            crate::test::set_account_balance::<crate::DefaultEnvironment>(zero, 10)
+        });
+        crate::test::allow_zero_address(true);
+        crate::test::set_account_balance::<crate::DefaultEnvironment>(zero, 10);
+
+        // then
+        let message = res
+            .expect_err("funding the zero address must panic")
+            .downcast::<String>()
+            .expect("panic message must be a string");
+        assert!(message.contains("ZeroAddressForbidden"));
+        assert_eq!(
+            crate::test::get_account_balance::<crate::DefaultEnvironment>(zero),
+            Ok(10)
+        );
+        crate::test::allow_zero_address(false);
+        Ok(())
+    })
+}
+
+#[test]
+fn uploaded_code_is_deduplicated_by_hash() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        let code = vec![0, 97, 115, 109];
+
+        // when
+        let hash = crate::test::upload_code::<crate::DefaultEnvironment>(
+            crate::test::Code::Bytes(code.clone()),
+        )?;
+        let again = crate::test::upload_code::<crate::DefaultEnvironment>(
+            crate::test::Code::Bytes(code),
+        )?;
+
+        // then
+        assert_eq!(hash, again);
+        assert!(crate::test::code_exists::<crate::DefaultEnvironment>(hash));
+        assert!(!crate::test::code_exists::<crate::DefaultEnvironment>(
+            ink_primitives::Hash::from([7; 32])
+        ));
+        Ok(())
+    })
+}
+
+#[test]
+fn repeated_storage_reads_hit_the_read_cache() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::set_contract_storage(&1u8, &10u32);
+
+        // when
+        for _ in 0..3 {
+            assert_eq!(crate::get_contract_storage::<u8, u32>(&1u8)?, Some(10));
+        }
+        crate::set_contract_storage(&1u8, &11u32);
+        let updated = crate::get_contract_storage::<u8, u32>(&1u8)?;
+
+        // then
+        assert_eq!(updated, Some(11));
+        let statistics = crate::test::contract_statistics::<crate::DefaultEnvironment>(
+            &accounts.alice,
+        );
+        assert_eq!(statistics.cache_hits, 2);
+        assert_eq!(statistics.cache_misses, 2);
+        assert_eq!(statistics.storage_reads, 4);
+        Ok(())
+    })
+}
+
+#[test]
+fn gas_left_decreases_with_storage_writes() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::test::set_engine_config(crate::test::EngineConfig {
+            gas_limit: 1_000_000_000_000,
+            ..Default::default()
+        });
+        let before = crate::gas_left::<crate::DefaultEnvironment>();
+
+        // when
+        for n in 0..10u8 {
+            crate::set_contract_storage(&n, &n);
+        }
+        let after = crate::gas_left::<crate::DefaultEnvironment>();
+
+        // then
+        assert!(after < before);
+        Ok(())
+    })
+}
+
+#[test]
+fn chain_params_are_set_per_test_and_unknown_ones_are_reported() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        let unset = crate::test::get_chain_param(crate::test::STORAGE_DEPOSIT_LIMIT);
+
+        // when
+        crate::test::set_chain_param("max_proposals", scale::Encode::encode(&7u32));
+
+        // then
+        assert_eq!(
+            unset,
+            Err(crate::test::ChainParamError::Unset {
+                name: crate::test::STORAGE_DEPOSIT_LIMIT.into()
+            })
+        );
+        assert_eq!(
+            crate::test::get_chain_param("max_proposals"),
+            Ok(vec![7, 0, 0, 0])
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn snapshot_diff_detects_side_effects() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|_| {
+        // given
+        crate::set_contract_storage(&1u32, &true);
+        let before = crate::test::snapshot();
+
+        // when
+        crate::get_contract_storage::<u32, bool>(&1u32)?;
+        let read = before.diff(&crate::test::snapshot());
+        crate::set_contract_storage(&2u32, &true);
+        let written = before.diff(&crate::test::snapshot());
+
+        // then
+        assert!(read.is_empty(), "{read}");
+        assert_eq!(written.storage.len(), 1);
+        assert!(matches!(
+            written.storage[0].change,
+            crate::test::CellChange::Added { .. }
+        ));
+        Ok(())
+    })
+}
+
+#[test]
+fn no_storage_writes_guard_accepts_reads() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        crate::set_contract_storage(&1u8, &11u8);
+        let guard = crate::test::assert_no_storage_writes::<crate::DefaultEnvironment>(
+            &accounts.alice,
+        );
+
+        // when
+        let value = crate::get_contract_storage::<u8, u8>(&1u8)?;
+        crate::test::set_callee::<crate::DefaultEnvironment>(accounts.bob);
+        crate::set_contract_storage(&2u8, &22u8);
+
+        // then
+        assert_eq!(value, Some(11));
+        guard.check();
+        Ok(())
+    })
+}
+
+#[test]
+fn no_storage_writes_guard_names_the_written_keys() -> Result<()> {
+    crate::test::run_test::<crate::DefaultEnvironment, _>(|accounts| {
+        // given
+        let guard = crate::test::assert_no_storage_writes::<crate::DefaultEnvironment>(
+            &accounts.alice,
+        );
+
+        // when
+        crate::set_contract_storage(&7u8, &true);
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(guard)));
+
+        // then
+        let message = res
+            .expect_err("the write must be detected")
+            .downcast::<String>()
+            .expect("panic message must be a string");
+        assert_eq!(
+            *message,
+            format!(
+                "expected no storage writes to 0x{}, written keys: 0x07",
+                "01".repeat(32)
+            )
+        );
+        Ok(())
+    })
+}
+
+/// An environment with a wider block number and timestamp than the engine.
+#[derive(Clone)]
+enum WideEnvironment {}
+
+impl crate::Environment for WideEnvironment {
+    const MAX_EVENT_TOPICS: usize = 4;
+
+    type AccountId = <crate::DefaultEnvironment as crate::Environment>::AccountId;
+    type Balance = <crate::DefaultEnvironment as crate::Environment>::Balance;
+    type Hash = <crate::DefaultEnvironment as crate::Environment>::Hash;
+    type BlockNumber = u64;
+    type Timestamp = u128;
+    type ChainExtension =
+        <crate::DefaultEnvironment as crate::Environment>::ChainExtension;
+}
+
+/// An environment with a narrower timestamp than the engine.
+#[derive(Clone)]
+enum NarrowEnvironment {}
+
+impl crate::Environment for NarrowEnvironment {
+    const MAX_EVENT_TOPICS: usize = 4;
+
+    type AccountId = <crate::DefaultEnvironment as crate::Environment>::AccountId;
+    type Balance = <crate::DefaultEnvironment as crate::Environment>::Balance;
+    type Hash = <crate::DefaultEnvironment as crate::Environment>::Hash;
+    type BlockNumber = u32;
+    type Timestamp = u32;
+    type ChainExtension =
+        <crate::DefaultEnvironment as crate::Environment>::ChainExtension;
+}
+
+#[test]
+fn block_properties_round_trip_in_wider_types() -> Result<()> {
+    crate::test::run_test::<WideEnvironment, _>(|_| {
+        // when
+        crate::test::set_block_number::<WideEnvironment>(u64::from(u32::MAX));
+        crate::test::set_block_timestamp::<WideEnvironment>(u128::from(u64::MAX));
+
+        // then
+        assert_eq!(
+            crate::block_number::<WideEnvironment>(),
+            u64::from(u32::MAX)
+        );
+        assert_eq!(
+            crate::block_timestamp::<WideEnvironment>(),
+            u128::from(u64::MAX)
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn block_properties_round_trip_in_narrower_types() -> Result<()> {
+    crate::test::run_test::<NarrowEnvironment, _>(|_| {
+        // when
+        crate::test::set_block_number::<NarrowEnvironment>(7);
+        crate::test::set_block_timestamp::<NarrowEnvironment>(u32::MAX);
+
+        // then
+        assert_eq!(crate::block_number::<NarrowEnvironment>(), 7);
+        assert_eq!(crate::block_timestamp::<NarrowEnvironment>(), u32::MAX);
+        Ok(())
+    })
+}
+
+#[test]
+#[should_panic(
+    expected = "the block timestamp 4294967296 overflows the `Timestamp` type of the \
+                environment"
+)]
+fn block_timestamp_overflowing_the_environment_panics() {
+    crate::test::run_test::<NarrowEnvironment, _>(|_| {
+        crate::test::set_block_timestamp::<crate::DefaultEnvironment>(
+            u64::from(u32::MAX) + 1,
+        );
+        crate::block_timestamp::<NarrowEnvironment>();
+        Ok(())
+    })
+    .unwrap()
+}
+
+#[test]
+#[should_panic(
+    expected = "the block number overflows the `u32` block numbers of the engine"
+)]
+fn block_number_overflowing_the_engine_panics() {
+    crate::test::set_block_number::<WideEnvironment>(u64::from(u32::MAX) + 1);
+}
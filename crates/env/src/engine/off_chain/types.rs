@@ -21,6 +21,7 @@ use super::{
     Error,
     OffChainError,
 };
+use pallet_contracts_uapi::ReturnErrorCode;
 
 impl From<ink_engine::test_api::EmittedEvent> for EmittedEvent {
     fn from(evt: ink_engine::test_api::EmittedEvent) -> Self {
@@ -42,11 +43,82 @@ impl From<ink_engine::Error> for Error {
             ink_engine::Error::UnregisteredChainExtension => {
                 OffChainError::UnregisteredChainExtension
             }
+            ink_engine::Error::UninitializedProperty(property) => {
+                OffChainError::UninitializedProperty(property)
+            }
+            ink_engine::Error::UnsignedOrigin => OffChainError::UnsignedOrigin,
+            ink_engine::Error::BufferTooSmall(_) => return Error::BufferTooSmall,
         };
         Error::OffChain(e)
     }
 }
 
+impl From<ink_engine::ext::Error> for Error {
+    fn from(err: ink_engine::ext::Error) -> Self {
+        use ink_engine::ext::Error as EngineError;
+        let code = match err {
+            EngineError::KeyNotFound(key) => {
+                return Error::OffChain(OffChainError::KeyNotFound(key))
+            }
+            EngineError::CalleeReverted(output) => {
+                return Error::OffChain(OffChainError::CalleeReverted(output))
+            }
+            EngineError::InvalidValue => {
+                return Error::OffChain(OffChainError::InvalidValue)
+            }
+            EngineError::InvalidCallee(callee) => {
+                return Error::OffChain(OffChainError::InvalidCallee(callee))
+            }
+            EngineError::StorageDepositLimitExhausted => {
+                return Error::OffChain(OffChainError::StorageDepositLimitExhausted)
+            }
+            EngineError::TerminationDenied => {
+                return Error::OffChain(OffChainError::TerminationDenied)
+            }
+            EngineError::FeeWithdrawalFailed => {
+                return Error::OffChain(OffChainError::FeeWithdrawalFailed)
+            }
+            EngineError::EventLimitExceeded(violation) => {
+                return Error::OffChain(OffChainError::EventLimitExceeded(violation))
+            }
+            EngineError::MemoryLimitExceeded => {
+                return Error::OffChain(OffChainError::MemoryLimitExceeded)
+            }
+            EngineError::OutOfGas => return Error::OffChain(OffChainError::OutOfGas),
+            EngineError::ChainExtensionTrapped => {
+                return Error::OffChain(OffChainError::ChainExtensionTrapped)
+            }
+            EngineError::CodeTooLarge => {
+                return Error::OffChain(OffChainError::CodeTooLarge)
+            }
+            EngineError::UnknownSelector(unknown) => {
+                return Error::OffChain(OffChainError::UnknownSelector(unknown))
+            }
+            EngineError::NoCallee => {
+                return Error::OffChain(OffChainError::UninitializedExecutionContext)
+            }
+            EngineError::NoBalance(account) => {
+                return Error::OffChain(OffChainError::Account(
+                    AccountError::NoAccountForId(account),
+                ))
+            }
+            EngineError::BufferTooSmall(_) => return Error::BufferTooSmall,
+            EngineError::UnknownError(code) => return Error::Unknown(code),
+            EngineError::CalleeTrapped => ReturnErrorCode::CalleeTrapped,
+            EngineError::TransferFailed => ReturnErrorCode::TransferFailed,
+            EngineError::CodeNotFound => ReturnErrorCode::CodeNotFound,
+            EngineError::NotCallable => ReturnErrorCode::NotCallable,
+            EngineError::LoggingDisabled => ReturnErrorCode::LoggingDisabled,
+            EngineError::CallRuntimeFailed => ReturnErrorCode::CallRuntimeFailed,
+            EngineError::EcdsaRecoveryFailed => ReturnErrorCode::EcdsaRecoveryFailed,
+            EngineError::Sr25519VerifyFailed => ReturnErrorCode::Sr25519VerifyFailed,
+            EngineError::XcmExecutionFailed => ReturnErrorCode::XcmExecutionFailed,
+            EngineError::XcmSendFailed => ReturnErrorCode::XcmSendFailed,
+        };
+        Error::ReturnError(code)
+    }
+}
+
 impl From<ink_engine::AccountError> for AccountError {
     fn from(err: ink_engine::AccountError) -> Self {
         match err {
@@ -57,6 +129,9 @@ impl From<ink_engine::AccountError> for AccountError {
             ink_engine::AccountError::NoAccountForId(acc) => {
                 AccountError::NoAccountForId(acc)
             }
+            ink_engine::AccountError::ZeroAddressForbidden => {
+                AccountError::ZeroAddressForbidden
+            }
         }
     }
 }
@@ -29,6 +29,7 @@ use crate::{
         LimitParamsV2,
     },
     event::{
+        topic_from_encoded,
         Event,
         TopicsBuilderBackend,
     },
@@ -40,7 +41,6 @@ use crate::{
         Keccak256,
         Sha2x256,
     },
-    Clear,
     EnvBackend,
     Environment,
     FromLittleEndian,
@@ -140,24 +140,9 @@ where
     where
         T: scale::Encode,
     {
-        fn inner<E: Environment>(encoded: &mut [u8]) -> <E as Environment>::Hash {
-            let len_encoded = encoded.len();
-            let mut result = <E as Environment>::Hash::CLEAR_HASH;
-            let len_result = result.as_ref().len();
-            if len_encoded <= len_result {
-                result.as_mut()[..len_encoded].copy_from_slice(encoded);
-            } else {
-                let mut hash_output = <Blake2x256 as HashOutput>::Type::default();
-                <Blake2x256 as CryptoHash>::hash(encoded, &mut hash_output);
-                let copy_len = core::cmp::min(hash_output.len(), len_result);
-                result.as_mut()[0..copy_len].copy_from_slice(&hash_output[0..copy_len]);
-            }
-            result
-        }
-
         let mut split = self.scoped_buffer.split();
         let encoded = split.take_encoded(topic_value);
-        let result = inner::<E>(encoded);
+        let result = topic_from_encoded::<E>(encoded);
         self.scoped_buffer.append_encoded(&result);
     }
 
@@ -287,6 +272,10 @@ impl EnvBackend for EnvInstance {
     /// A no-op. Enable the `ink-debug` feature for debug messages.
     fn debug_message(&mut self, _content: &str) {}
 
+    #[cfg(not(feature = "ink-debug"))]
+    /// A no-op. Enable the `ink-debug` feature for debug messages.
+    fn debug_message_fmt(&mut self, _args: core::fmt::Arguments<'_>) {}
+
     #[cfg(feature = "ink-debug")]
     fn debug_message(&mut self, content: &str) {
         static mut DEBUG_ENABLED: bool = false;
@@ -676,6 +665,14 @@ impl TypedEnvBackend for EnvInstance {
         ext::caller_is_origin()
     }
 
+    fn reentrance_count<E>(&mut self) -> u32
+    where
+        E: Environment,
+    {
+        #[allow(deprecated)]
+        ext::reentrance_count()
+    }
+
     fn code_hash<E>(&mut self, account_id: &E::AccountId) -> Result<E::Hash>
     where
         E: Environment,
@@ -30,6 +30,13 @@ pub enum Error {
     OffChain(OffChainError),
     /// The error returned by the contract.
     ReturnError(ReturnErrorCode),
+    /// Value was transferred to a contract execution which does not accept payment.
+    PaidUnpayableMessage,
+    /// An error with a return code unknown to this crate, carries the raw code.
+    ///
+    /// Only returned by the off-chain environment.
+    #[from(ignore)]
+    Unknown(u32),
 }
 
 /// A result of environmental operations.
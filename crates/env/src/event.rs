@@ -14,7 +14,53 @@
 
 //! This module contains the implementation for the event topic logic.
 
-use crate::Environment;
+use crate::{
+    hash::{
+        Blake2x256,
+        CryptoHash,
+        HashOutput,
+    },
+    Environment,
+};
+use ink_primitives::Clear;
+
+/// Returns the topic for the SCALE `encoded` value of an event field.
+///
+/// Values whose encoding fits into `E::Hash` are padded with zeros, longer ones are
+/// hashed via BLAKE2 256-bit. Used by the on-chain and off-chain topics builders.
+#[doc(hidden)]
+pub fn topic_from_encoded<E>(encoded: &[u8]) -> <E as Environment>::Hash
+where
+    E: Environment,
+{
+    let len_encoded = encoded.len();
+    let mut result = <E as Environment>::Hash::CLEAR_HASH;
+    let len_result = result.as_ref().len();
+    if len_encoded <= len_result {
+        result.as_mut()[..len_encoded].copy_from_slice(encoded);
+    } else {
+        let mut hash_output = <Blake2x256 as HashOutput>::Type::default();
+        <Blake2x256 as CryptoHash>::hash(encoded, &mut hash_output);
+        let copy_len = core::cmp::min(hash_output.len(), len_result);
+        result.as_mut()[0..copy_len].copy_from_slice(&hash_output[0..copy_len]);
+    }
+    result
+}
+
+/// Returns the signature topic the `#[ink::event]` codegen derives for an event,
+/// i.e. the topic of [`Event::SIGNATURE_TOPIC`] as emitted.
+///
+/// The `signature` is the name of the event followed by the types of all of its
+/// fields as written in its definition, without whitespace, e.g.
+/// `"Transfer(Option<AccountId>,Option<AccountId>,Balance)"`.
+pub fn event_signature_topic<E>(signature: &str) -> <E as Environment>::Hash
+where
+    E: Environment,
+{
+    let mut signature_topic = <Blake2x256 as HashOutput>::Type::default();
+    <Blake2x256 as CryptoHash>::hash(signature.as_bytes(), &mut signature_topic);
+    topic_from_encoded::<E>(&scale::Encode::encode(&signature_topic))
+}
 
 /// The concrete implementation that is guided by the topics builder.
 ///
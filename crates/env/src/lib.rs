@@ -93,6 +93,8 @@ mod error;
 #[doc(hidden)]
 pub mod event;
 pub mod hash;
+#[cfg(any(feature = "std", test, doc))]
+pub mod topics;
 mod types;
 
 #[cfg(test)]
@@ -123,7 +125,10 @@ pub use self::{
         Error,
         Result,
     },
-    event::Event,
+    event::{
+        event_signature_topic,
+        Event,
+    },
     types::{
         AccountIdGuard,
         DefaultEnvironment,
@@ -133,58 +138,73 @@ pub use self::{
         NoChainExtension,
     },
 };
-use ink_primitives::Clear;
-
-cfg_if::cfg_if! {
-    if #[cfg(any(feature = "ink-debug", feature = "std"))] {
-        /// Required by the `debug_print*` macros below, because there is no guarantee that
-        /// contracts will have a direct `ink_prelude` dependency. In the future we could introduce
-        /// an "umbrella" crate containing all the `ink!` crates which could also host these macros.
-        #[doc(hidden)]
-        pub use ink_prelude::format;
-
-        /// Appends a formatted string to the `debug_message` buffer if message recording is
-        /// enabled in the contracts pallet and if the call is performed via RPC (**not** via an
-        /// extrinsic). The `debug_message` buffer will be:
-        ///  - Returned to the RPC caller.
-        ///  - Logged as a `debug!` message on the Substrate node, which will be printed to the
-        ///    node console's `stdout` when the log level is set to `-lruntime::contracts=debug`.
-        ///
-        /// # Note
-        ///
-        /// This depends on the `debug_message` interface which requires the
-        /// `"pallet-contracts/unstable-interface"` feature to be enabled in the target runtime.
-        #[macro_export]
-        macro_rules! debug_print {
-            ($($arg:tt)*) => ($crate::debug_message(&$crate::format!($($arg)*)));
-        }
 
-        /// Appends a formatted string to the `debug_message` buffer, as per [`debug_print`] but
-        /// with a newline appended.
-        ///
-        /// # Note
-        ///
-        /// This depends on the `debug_message` interface which requires the
-        /// `"pallet-contracts/unstable-interface"` feature to be enabled in the target runtime.
-        #[macro_export]
-        macro_rules! debug_println {
-            () => ($crate::debug_print!("\n"));
-            ($($arg:tt)*) => (
-                $crate::debug_print!("{}\n", $crate::format!($($arg)*));
-            )
-        }
-    } else {
-        #[macro_export]
-        /// Debug messages disabled. Enable the `ink-debug` feature for contract debugging.
-        macro_rules! debug_print {
-            ($($arg:tt)*) => ();
-        }
+/// Required by the `debug_print*` macros below, because there is no guarantee that
+/// contracts will have a direct `ink_prelude` dependency. In the future we could
+/// introduce an "umbrella" crate containing all the `ink!` crates which could also
+/// host these macros.
+#[cfg(any(feature = "ink-debug", feature = "std"))]
+#[doc(hidden)]
+pub use ink_prelude::format;
+
+/// Appends a formatted string to the `debug_message` buffer if message recording is
+/// enabled in the contracts pallet and if the call is performed via RPC (**not** via an
+/// extrinsic). The `debug_message` buffer will be:
+///  - Returned to the RPC caller.
+///  - Logged as a `debug!` message on the Substrate node, which will be printed to the
+///    node console's `stdout` when the log level is set to `-lruntime::contracts=debug`.
+///
+/// # Note
+///
+/// This depends on the `debug_message` interface which requires the
+/// `"pallet-contracts/unstable-interface"` feature to be enabled in the target runtime.
+///
+/// The message is formatted lazily: messages without arguments are forwarded
+/// without allocating, see [`debug_message_fmt`].
+#[cfg(any(feature = "ink-debug", feature = "std"))]
+#[macro_export]
+macro_rules! debug_print {
+    ($($arg:tt)*) => (
+        $crate::debug_message_fmt(::core::format_args!($($arg)*))
+    );
+}
 
-        #[macro_export]
-        /// Debug messages disabled. Enable the `ink-debug` feature for contract debugging.
-        macro_rules! debug_println {
-            () => ();
-            ($($arg:tt)*) => ();
-        }
-    }
+/// Appends a formatted string to the `debug_message` buffer, as per [`debug_print`] but
+/// with a newline appended.
+///
+/// # Note
+///
+/// This depends on the `debug_message` interface which requires the
+/// `"pallet-contracts/unstable-interface"` feature to be enabled in the target runtime.
+#[cfg(any(feature = "ink-debug", feature = "std"))]
+#[macro_export]
+macro_rules! debug_println {
+    () => ($crate::debug_print!("\n"));
+    ($($arg:tt)*) => (
+        $crate::debug_message_fmt(
+            ::core::format_args!("{}\n", ::core::format_args!($($arg)*))
+        )
+    )
+}
+
+/// Debug messages disabled. Enable the `ink-debug` feature for contract debugging.
+#[cfg(not(any(feature = "ink-debug", feature = "std")))]
+#[macro_export]
+macro_rules! debug_print {
+    ($($arg:tt)*) => ($crate::__discard_debug_message!($($arg)*));
+}
+
+/// Debug messages disabled. Enable the `ink-debug` feature for contract debugging.
+#[cfg(not(any(feature = "ink-debug", feature = "std")))]
+#[macro_export]
+macro_rules! debug_println {
+    ($($arg:tt)*) => ($crate::__discard_debug_message!($($arg)*));
+}
+
+/// The expansion of the `debug_print*` macros if debug messages are disabled: the
+/// arguments are neither formatted nor evaluated, the invocation compiles to nothing.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __discard_debug_message {
+    ($($arg:tt)*) => {};
 }
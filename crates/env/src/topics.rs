@@ -0,0 +1,47 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Computes event topics the way the off-chain environment does, e.g. to compare
+//! them with the topics computed by an indexer.
+
+use crate::{
+    engine::off_chain::TopicsCollector,
+    event::Event,
+    Environment,
+};
+
+/// Computes the topics of `event` the way the off-chain environment does, without
+/// emitting the event.
+///
+/// The result is exactly what [`crate::test::recorded_events`] returns as topics of
+/// the event once it has been emitted.
+///
+/// # Panics
+///
+/// If the `Hash` type of the environment is not 32 bytes long.
+pub fn compute_topics_off_chain<E, Evt>(event: &Evt) -> Vec<[u8; 32]>
+where
+    E: Environment,
+    Evt: Event,
+{
+    event
+        .topics::<E, _>(TopicsCollector::default().into())
+        .into_iter()
+        .map(|topic| {
+            <[u8; 32]>::try_from(&topic[..]).unwrap_or_else(|_| {
+                panic!("the topics of the environment are not 32 bytes long")
+            })
+        })
+        .collect()
+}
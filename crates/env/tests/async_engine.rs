@@ -0,0 +1,120 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "async-tests")]
+
+use ink_engine::ext::Engine;
+use ink_env::{
+    test::{
+        self,
+        EngineGuard,
+    },
+    DefaultEnvironment,
+};
+use tokio::{
+    runtime,
+    task,
+};
+
+/// Sets up the default accounts, makes `seed` the caller and credits it with
+/// `balance`, yielding to the other tasks in between.
+async fn use_engine(seed: u8, balance: u128) -> (ink_primitives::AccountId, u128) {
+    test::run_test::<DefaultEnvironment, _>(|_| Ok(())).unwrap();
+    task::yield_now().await;
+    let caller = ink_primitives::AccountId::from([seed; 32]);
+    test::set_caller::<DefaultEnvironment>(caller);
+    task::yield_now().await;
+    test::set_account_balance::<DefaultEnvironment>(caller, balance);
+    task::yield_now().await;
+    let balance = test::get_account_balance::<DefaultEnvironment>(caller).unwrap();
+    (ink_env::caller::<DefaultEnvironment>(), balance)
+}
+
+#[test]
+fn interleaving_tasks_use_their_own_engine() {
+    let runtime = runtime::Builder::new_current_thread().build().unwrap();
+    let local = task::LocalSet::new();
+
+    let (first, second) = local.block_on(&runtime, async {
+        let first =
+            task::spawn_local(EngineGuard::scope(Engine::new(), use_engine(0x42, 10)));
+        let second =
+            task::spawn_local(EngineGuard::scope(Engine::new(), use_engine(0x43, 20)));
+        (first.await.unwrap(), second.await.unwrap())
+    });
+
+    assert_eq!(first, ([0x42; 32].into(), 10));
+    assert_eq!(second, ([0x43; 32].into(), 20));
+}
+
+#[test]
+fn engine_of_the_thread_is_used_outside_of_scopes() {
+    let runtime = runtime::Builder::new_current_thread().build().unwrap();
+    let local = task::LocalSet::new();
+    let alice = test::default_accounts::<DefaultEnvironment>().alice;
+    test::run_test::<DefaultEnvironment, _>(|_| Ok(())).unwrap();
+
+    let (scoped, _) = local.block_on(&runtime, async {
+        EngineGuard::scope(Engine::new(), use_engine(0x42, 10)).await
+    });
+
+    assert_eq!(scoped, [0x42; 32].into());
+    assert_eq!(ink_env::caller::<DefaultEnvironment>(), alice);
+}
+
+#[test]
+fn async_tests_start_from_the_default_configuration() {
+    let bob = test::default_accounts::<DefaultEnvironment>().bob;
+    test::run_test::<DefaultEnvironment, _>(|_| Ok(())).unwrap();
+    test::set_caller::<DefaultEnvironment>(bob);
+
+    let (caller, balance) =
+        test::run_async_test::<DefaultEnvironment, _, _>(|accounts| {
+            async move {
+                task::yield_now().await;
+                let balance =
+                    test::get_account_balance::<DefaultEnvironment>(accounts.alice)
+                        .unwrap();
+                (ink_env::caller::<DefaultEnvironment>(), balance)
+            }
+        });
+
+    assert_eq!(caller, test::default_accounts::<DefaultEnvironment>().alice);
+    assert_eq!(balance, 1_000_000);
+    assert_eq!(ink_env::caller::<DefaultEnvironment>(), bob);
+}
+
+#[test]
+fn async_tests_keep_their_engine_across_await_points() {
+    let output = test::run_async_test::<DefaultEnvironment, _, _>(|_| {
+        async {
+            let local = task::LocalSet::new();
+            local
+                .run_until(async {
+                    let other = task::spawn_local(EngineGuard::scope(
+                        Engine::new(),
+                        use_engine(0x43, 20),
+                    ));
+                    let caller = ink_primitives::AccountId::from([0x42; 32]);
+                    test::set_caller::<DefaultEnvironment>(caller);
+                    task::yield_now().await;
+                    other.await.unwrap();
+                    ink_env::caller::<DefaultEnvironment>()
+                })
+                .await
+        }
+    });
+
+    assert_eq!(output, [0x42; 32].into());
+}
@@ -0,0 +1,28 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The `debug_print*` macros must compile to nothing if debug messages are disabled.
+
+/// The disabled macros expand to `__discard_debug_message!`, which must neither
+/// evaluate nor even resolve its arguments.
+#[test]
+fn disabled_debug_messages_compile_to_nothing() {
+    let evaluated = core::cell::Cell::new(false);
+
+    ink_env::__discard_debug_message!("{:?}", evaluated.set(true));
+    ink_env::__discard_debug_message!("{}", undefined_binding);
+    ink_env::__discard_debug_message!();
+
+    assert!(!evaluated.get());
+}
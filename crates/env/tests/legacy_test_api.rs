@@ -0,0 +1,88 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A test written against the legacy off-chain test API, which must keep compiling
+//! and passing unchanged.
+
+use ink_env::{
+    call::Selector,
+    test::{
+        self,
+        CallData,
+    },
+    DefaultEnvironment,
+};
+
+#[test]
+fn legacy_execution_context_sets_caller_callee_value_and_input() {
+    test::run_test::<DefaultEnvironment, _>(|accounts| {
+        let mut call_data = CallData::new(Selector::new([0xCA, 0xFE, 0xBA, 0xBE]));
+        call_data.push_arg(&42u32);
+
+        test::push_execution_context::<DefaultEnvironment>(
+            accounts.bob,
+            accounts.charlie,
+            1_000_000,
+            100,
+            call_data,
+        );
+        assert_eq!(ink_env::caller::<DefaultEnvironment>(), accounts.bob);
+        assert_eq!(
+            ink_env::account_id::<DefaultEnvironment>(),
+            accounts.charlie
+        );
+        assert_eq!(ink_env::transferred_value::<DefaultEnvironment>(), 100);
+        assert_eq!(
+            ink_env::decode_input::<([u8; 4], u32)>(),
+            Ok(([0xCA, 0xFE, 0xBA, 0xBE], 42))
+        );
+        test::pop_execution_context();
+
+        assert_eq!(ink_env::caller::<DefaultEnvironment>(), accounts.alice);
+        Ok(())
+    })
+    .unwrap()
+}
+
+#[test]
+fn legacy_block_events_and_balances_keep_working() {
+    test::run_test::<DefaultEnvironment, _>(|accounts| {
+        let block = ink_env::block_number::<DefaultEnvironment>();
+        test::advance_block::<DefaultEnvironment>();
+        assert_eq!(ink_env::block_number::<DefaultEnvironment>(), block + 1);
+
+        assert_eq!(test::recorded_events().count(), 0);
+        let balance = test::get_account_balance::<DefaultEnvironment>(accounts.bob)?;
+        assert_eq!(balance, 1_000);
+        Ok(())
+    })
+    .unwrap()
+}
+
+#[test]
+#[allow(deprecated)]
+fn legacy_unsupported_functions_return_errors() {
+    test::run_test::<DefaultEnvironment, _>(|accounts| {
+        assert!(
+            test::set_block_entropy::<DefaultEnvironment>([0x01; 32].into()).is_err()
+        );
+        assert!(test::set_account_rent_allowance::<DefaultEnvironment>(
+            accounts.alice,
+            10
+        )
+        .is_err());
+        Ok(())
+    })
+    .unwrap()
+}
@@ -82,7 +82,10 @@ impl GenerateCode for Dispatch<'_> {
         let constructor_decoder_type =
             self.generate_constructor_decoder_type(&constructors);
         let message_decoder_type = self.generate_message_decoder_type(&messages);
-        let entry_points = self.generate_entry_points(&constructors, &messages);
+        let dispatch_functions =
+            self.generate_dispatch_functions(&constructors, &messages);
+        let entry_points = self.generate_entry_points();
+        let off_chain_entry_points = self.generate_off_chain_entry_points();
         quote! {
             #contract_dispatchable_constructor_infos
             #contract_dispatchable_messages_infos
@@ -91,8 +94,15 @@ impl GenerateCode for Dispatch<'_> {
 
             #[cfg(not(any(test, feature = "std", feature = "ink-as-dependency")))]
             const _: () = {
+                #dispatch_functions
                 #entry_points
             };
+
+            #[cfg(all(any(test, feature = "std"), not(feature = "ink-as-dependency")))]
+            const _: () = {
+                #dispatch_functions
+                #off_chain_entry_points
+            };
         }
     }
 }
@@ -336,11 +346,12 @@ impl Dispatch<'_> {
         )
     }
 
-    /// Generates code for the entry points of the root ink! smart contract.
+    /// Generates code for the dispatch functions of the root ink! smart contract.
     ///
-    /// This generates the `deploy` and `call` functions with which the smart
-    /// contract runtime mainly interacts with the ink! smart contract.
-    fn generate_entry_points(
+    /// This generates the `internal_deploy` and `internal_call` functions which
+    /// decode the input and dispatch it to the constructors and messages. They
+    /// are shared by the on-chain and off-chain entry points.
+    fn generate_dispatch_functions(
         &self,
         constructors: &[ConstructorDispatchable],
         messages: &[MessageDispatchable],
@@ -422,7 +433,16 @@ impl Dispatch<'_> {
                     ::core::panic!("dispatching ink! message failed: {}", error)
                 })
             }
+        )
+    }
 
+    /// Generates code for the entry points of the root ink! smart contract.
+    ///
+    /// This generates the `deploy` and `call` functions with which the smart
+    /// contract runtime mainly interacts with the ink! smart contract.
+    fn generate_entry_points(&self) -> TokenStream2 {
+        let span = self.contract.module().storage().span();
+        quote_spanned!(span=>
             #[cfg(target_arch = "wasm32")]
             #[no_mangle]
             pub extern "C" fn call() {
@@ -448,6 +468,27 @@ impl Dispatch<'_> {
         )
     }
 
+    /// Generates code for the entry points of the root ink! smart contract in the
+    /// off-chain environment.
+    ///
+    /// These allow tests to dispatch encoded input through the contract the same
+    /// way the smart contract runtime does.
+    fn generate_off_chain_entry_points(&self) -> TokenStream2 {
+        let span = self.contract.module().storage().span();
+        let storage_ident = self.contract.module().storage().ident();
+        quote_spanned!(span=>
+            impl ::ink::reflect::ContractEntryPoints for #storage_ident {
+                fn deploy() {
+                    internal_deploy()
+                }
+
+                fn call() {
+                    internal_call()
+                }
+            }
+        )
+    }
+
     /// Generates code for the ink! constructor decoder type of the ink! smart contract.
     ///
     /// This type can be used in order to decode the input bytes received by a call to
@@ -15,7 +15,11 @@
 use crate::GenerateCode;
 use derive_more::From;
 use proc_macro2::TokenStream as TokenStream2;
-use quote::quote;
+use quote::{
+    quote,
+    quote_spanned,
+};
+use syn::spanned::Spanned as _;
 
 /// Generates code for the `[ink::test]` macro.
 #[derive(From)]
@@ -31,43 +35,58 @@ impl GenerateCode for InkTest<'_> {
         let attrs = &item_fn.attrs;
         let sig = &item_fn.sig;
         let fn_name = &sig.ident;
+        let fn_asyncness = &sig.asyncness;
         let fn_return_type = &sig.output;
         let fn_block = &item_fn.block;
         let vis = &item_fn.vis;
         let fn_args = &sig.inputs;
+        let test_name = fn_name.to_string();
         let expect_msg = format!(
-            "{}: the off-chain testing environment returned an error",
-            stringify!(#fn_name)
+            "{test_name}: the off-chain testing environment returned an error"
         );
-        match fn_return_type {
-            syn::ReturnType::Default => {
+        // Checked outside of the test function, which only exists in test builds.
+        let output_guard = match fn_return_type {
+            syn::ReturnType::Default => None,
+            syn::ReturnType::Type(_, ret_type) => {
+                let span = ret_type.span();
+                Some(quote_spanned!(span=>
+                    const _: () = ::ink::codegen::utils::consume_type::<
+                        ::ink::codegen::TestOutput<#ret_type>
+                    >();
+                ))
+            }
+        };
+        let run_test = match fn_asyncness {
+            None => {
                 quote! {
-                    #( #attrs )*
-                    #[test]
-                    #vis fn #fn_name( #fn_args ) {
-                        ::ink::env::test::run_test::<::ink::env::DefaultEnvironment, _>(|_| {
-                            {
-                                {
-                                    #fn_block
-                                };
-                                ::core::result::Result::Ok(())
-                            }
-                        })
-                        .unwrap_or_else(|error| ::core::panic!("{}: {:?}", #expect_msg, error));
-                    }
+                    ::ink::env::test::run_test::<::ink::env::DefaultEnvironment, _>(|_| {
+                        ::ink::codegen::TestResult::into_test_outcome(__ink_test_body(), #test_name);
+                        ::core::result::Result::Ok(())
+                    })
+                    .unwrap_or_else(|error| ::core::panic!("{}: {:?}", #expect_msg, error));
                 }
             }
-            syn::ReturnType::Type(rarrow, ret_type) => {
+            Some(_) => {
                 quote! {
-                    #( #attrs )*
-                    #[test]
-                    #vis fn #fn_name( #fn_args ) #rarrow #ret_type {
-                        ::ink::env::test::run_test::<::ink::env::DefaultEnvironment, _>(|_| {
-                            #fn_block
-                        })
-                    }
+                    ::ink::codegen::TestResult::into_test_outcome(
+                        ::ink::env::test::run_async_test::<::ink::env::DefaultEnvironment, _, _>(
+                            |_| __ink_test_body(),
+                        ),
+                        #test_name,
+                    );
                 }
             }
+        };
+        quote! {
+            #output_guard
+
+            #( #attrs )*
+            #[test]
+            #vis fn #fn_name( #fn_args ) {
+                #fn_asyncness fn __ink_test_body() #fn_return_type #fn_block
+                ::ink::env::test::install_failure_dump();
+                #run_test
+            }
         }
     }
 }
@@ -26,6 +26,13 @@ impl TryFrom<syn::ItemFn> for InkTest {
 
     fn try_from(item_fn: syn::ItemFn) -> Result<Self, Self::Error> {
         idents_lint::ensure_no_ink_identifiers(&item_fn)?;
+        #[cfg(not(feature = "async-tests"))]
+        if let Some(asyncness) = &item_fn.sig.asyncness {
+            return Err(format_err_spanned!(
+                asyncness,
+                "async ink! tests require the `async-tests` feature of ink!"
+            ))
+        }
         Ok(Self { item_fn })
     }
 }
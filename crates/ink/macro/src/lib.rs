@@ -889,6 +889,22 @@ pub fn storage_item(attr: TokenStream, item: TokenStream) -> TokenStream {
 ///     }
 /// }
 /// ```
+///
+/// A test returning `Result<(), E>` fails with the debug representation of the
+/// error if it returns `Err`, hence `E` must implement `Debug`.
+///
+/// ## Async tests
+///
+/// With the `async-tests` feature of ink! the test may be an `async fn`. It is
+/// executed on a current-thread runtime with an off-chain environment of its own,
+/// which is used across all await points, see `ink_env::test::run_async_test`.
+///
+/// ```ignore
+/// #[ink::test]
+/// async fn test3() -> Result<(), ink_env::Error> {
+///     // test code that awaits futures
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn test(attr: TokenStream, item: TokenStream) -> TokenStream {
     ink_test::generate(attr.into(), item.into()).into()
@@ -25,9 +25,5 @@ pub fn deny_payment<E>() -> Result<(), DispatchError>
 where
     E: Environment,
 {
-    let transferred = ink_env::transferred_value::<E>();
-    if transferred != <E as Environment>::Balance::from(0_u32) {
-        return Err(DispatchError::PaidUnpayableMessage)
-    }
-    Ok(())
+    ink_env::deny_payment::<E>().map_err(|_| DispatchError::PaidUnpayableMessage)
 }
@@ -17,6 +17,7 @@
 mod dispatch;
 mod env;
 mod implies_return;
+mod test_result;
 mod trait_def;
 pub mod utils;
 
@@ -32,6 +33,10 @@ pub use self::{
         StaticEnv,
     },
     implies_return::ImpliesReturn,
+    test_result::{
+        TestOutput,
+        TestResult,
+    },
     trait_def::{
         TraitCallBuilder,
         TraitCallForwarder,
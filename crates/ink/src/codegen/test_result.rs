@@ -0,0 +1,69 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use core::fmt::Debug;
+
+/// Trait used as bound for the return types of `#[ink::test]` functions.
+///
+/// # Note
+///
+/// Implemented for `()` and for `Result<(), E>` with an error type `E` that
+/// implements `Debug`. The bound is automatically generated by the `#[ink::test]`
+/// procedural macro.
+pub trait TestResult {
+    /// Fails the test named `test_name` with the debug payload of the error, if any.
+    fn into_test_outcome(self, test_name: &str);
+}
+
+impl TestResult for () {
+    fn into_test_outcome(self, _test_name: &str) {}
+}
+
+impl<E> TestResult for Result<(), E>
+where
+    E: Debug,
+{
+    fn into_test_outcome(self, test_name: &str) {
+        if let Err(error) = self {
+            panic!("{test_name}: the test returned an error: {error:?}")
+        }
+    }
+}
+
+/// Used to check if `T` is allowed as return type of an ink! test.
+///
+/// # Note
+///
+/// The return type of an ink! test must implement [`TestResult`].
+///
+/// # Example
+///
+/// This compiles since `Result<(), String>` fulfills the requirements of an ink!
+/// test output.
+///
+/// ```
+/// # use ink::codegen::TestOutput;
+/// const _: () = ink::codegen::utils::consume_type::<TestOutput<Result<(), String>>>();
+/// ```
+///
+/// This fails to compile since `Foo` does not fulfill all requirements.
+///
+/// ```compile_fail
+/// # use ink::codegen::TestOutput;
+/// // Foo is missing a `Debug` implementation.
+/// struct Foo {}
+/// const _: () = ink::codegen::utils::consume_type::<TestOutput<Result<(), Foo>>>();
+/// ```
+pub struct TestOutput<T>(T)
+where
+    T: TestResult;
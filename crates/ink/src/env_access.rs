@@ -1178,6 +1178,38 @@ where
         ink_env::caller_is_origin::<E>()
     }
 
+    /// Returns the number of times the current contract exists on the call stack in
+    /// addition to the calling instance.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[ink::contract]
+    /// # pub mod my_contract {
+    /// #     #[ink(storage)]
+    /// #     pub struct MyContract { }
+    /// #
+    /// #     impl MyContract {
+    /// #         #[ink(constructor)]
+    /// #         pub fn new() -> Self {
+    /// #             Self {}
+    /// #         }
+    /// #
+    /// #[ink(message)]
+    /// pub fn is_reentered(&self) -> bool {
+    ///     self.env().reentrance_count() > 0
+    /// }
+    /// #    }
+    /// # }
+    /// ```
+    ///
+    /// # Note
+    ///
+    /// For more details visit: [`ink_env::reentrance_count`]
+    pub fn reentrance_count(self) -> u32 {
+        ink_env::reentrance_count::<E>()
+    }
+
     /// Returns the code hash of the contract at the given `account` id.
     ///
     /// # Example
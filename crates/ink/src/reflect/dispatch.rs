@@ -483,6 +483,59 @@ pub trait ExecuteDispatchable {
     fn execute_dispatchable(self) -> Result<(), DispatchError>;
 }
 
+/// The entry points of an ink! smart contract in the off-chain environment.
+///
+/// # Note
+///
+/// Implemented by the ink! smart contract when it is compiled for the off-chain
+/// environment. These are the functions which are exported as `deploy` and `call`
+/// when compiling for the on-chain environment, they dispatch the input set for
+/// the contract execution to its constructors and messages respectively.
+///
+/// # Usage
+///
+/// ```
+/// #[ink::contract]
+/// pub mod contract {
+///     #[ink(storage)]
+///     pub struct Contract {}
+///
+///     impl Contract {
+///         #[ink(constructor)]
+///         pub fn constructor() -> Self {
+///             Contract {}
+///         }
+///
+///         #[ink(message)]
+///         pub fn message(&self) {}
+///     }
+/// }
+///
+/// use contract::Contract;
+/// use ink::reflect::ContractEntryPoints;
+///
+/// let account =
+///     ink::env::test::default_accounts::<ink::env::DefaultEnvironment>().charlie;
+/// ink::env::test::register_contract::<ink::env::DefaultEnvironment>(
+///     account,
+///     Contract::ENTRY_POINTS,
+/// );
+/// ```
+pub trait ContractEntryPoints {
+    /// The entry points to be registered with the off-chain environment.
+    #[cfg(feature = "std")]
+    const ENTRY_POINTS: ink_env::test::EntryPoints = ink_env::test::EntryPoints {
+        deploy: Self::deploy,
+        call: Self::call,
+    };
+
+    /// Dispatches the input to the ink! smart contract constructors.
+    fn deploy();
+
+    /// Dispatches the input to the ink! smart contract messages.
+    fn call();
+}
+
 /// An error that can occur during dispatch of ink! dispatchables.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum DispatchError {
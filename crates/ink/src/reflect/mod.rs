@@ -33,6 +33,7 @@ pub use self::{
         ConstructorOutput,
         ConstructorOutputValue,
         ContractConstructorDecoder,
+        ContractEntryPoints,
         ContractMessageDecoder,
         DecodeDispatch,
         DispatchError,
@@ -122,3 +122,29 @@ fn ui_tests_scale_derive_fail() {
     let t = trybuild::TestCases::new();
     t.compile_fail("tests/ui/scale_derive/fail/*.rs");
 }
+
+#[test]
+fn ui_tests_ink_test_pass() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/ink_test/pass/*.rs");
+}
+
+#[test]
+fn ui_tests_ink_test_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/ink_test/fail/*.rs");
+}
+
+#[test]
+#[cfg(feature = "async-tests")]
+fn ui_tests_ink_test_async_pass() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/ink_test/async/pass/*.rs");
+}
+
+#[test]
+#[cfg(not(feature = "async-tests"))]
+fn ui_tests_ink_test_async_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/ink_test/async/fail/*.rs");
+}
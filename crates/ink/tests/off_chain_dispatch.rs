@@ -0,0 +1,200 @@
+// Copyright (C) Use Ink (UK) Ltd.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+mod counter {
+    #[ink(storage)]
+    pub struct Counter {
+        value: u32,
+    }
+
+    #[ink(event)]
+    pub struct Incremented {
+        #[ink(topic)]
+        by: u32,
+    }
+
+    impl Counter {
+        #[ink(constructor)]
+        pub fn new(value: u32) -> Self {
+            Self { value }
+        }
+
+        #[ink(message)]
+        pub fn inc(&mut self, by: u32) {
+            self.value += by;
+            self.env().emit_event(Incremented { by });
+        }
+
+        #[ink(message)]
+        pub fn get(&self) -> u32 {
+            self.value
+        }
+
+        #[ink(message, payable)]
+        pub fn deposit(&mut self) -> Balance {
+            self.env().transferred_value()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::counter::Counter;
+    use ink::{
+        env::{
+            test,
+            DefaultEnvironment,
+        },
+        reflect::ContractEntryPoints,
+        selector_bytes,
+        LangError,
+        MessageResult,
+    };
+    use scale::Encode;
+
+    #[test]
+    fn messages_round_trip_through_dispatcher() {
+        let contract = test::default_accounts::<DefaultEnvironment>().charlie;
+        test::register_contract::<DefaultEnvironment>(contract, Counter::ENTRY_POINTS);
+
+        let deployed = test::call_constructor::<DefaultEnvironment>(
+            contract,
+            selector_bytes!("new"),
+            &5u32.encode(),
+        );
+        assert!(!deployed.did_revert());
+
+        let incremented = test::call_message::<DefaultEnvironment>(
+            contract,
+            selector_bytes!("inc"),
+            &3u32.encode(),
+        );
+        assert!(!incremented.did_revert());
+        assert_eq!(incremented.decode::<MessageResult<()>>(), Ok(Ok(())));
+        assert_eq!(incremented.events.len(), 1);
+
+        let value = test::call_message::<DefaultEnvironment>(
+            contract,
+            selector_bytes!("get"),
+            &[],
+        );
+        assert!(!value.did_revert());
+        assert!(value.events.is_empty());
+        assert_eq!(value.decode::<MessageResult<u32>>(), Ok(Ok(8)));
+    }
+
+    #[test]
+    fn event_topics_match_generated_event() {
+        let contract = test::default_accounts::<DefaultEnvironment>().charlie;
+        test::register_contract::<DefaultEnvironment>(contract, Counter::ENTRY_POINTS);
+        test::call_constructor::<DefaultEnvironment>(
+            contract,
+            selector_bytes!("new"),
+            &0u32.encode(),
+        );
+
+        let incremented = test::call_message::<DefaultEnvironment>(
+            contract,
+            selector_bytes!("inc"),
+            &3u32.encode(),
+        );
+
+        let expected = test::event_topics(Some("Incremented(u32)"), &[3u32.encode()]);
+        assert_eq!(incremented.events.len(), 1);
+        assert_eq!(incremented.events[0].topics, expected);
+        let signature_topic =
+            ink::env::event_signature_topic::<DefaultEnvironment>("Incremented(u32)");
+        assert_eq!(expected[0], signature_topic.as_ref());
+    }
+
+    #[test]
+    fn unknown_selector_is_reverted_by_dispatcher() {
+        let contract = test::default_accounts::<DefaultEnvironment>().charlie;
+        test::register_contract::<DefaultEnvironment>(contract, Counter::ENTRY_POINTS);
+
+        let result = test::call_message::<DefaultEnvironment>(
+            contract,
+            selector_bytes!("unknown"),
+            &[],
+        );
+
+        assert!(result.did_revert());
+        assert_eq!(
+            result.decode::<MessageResult<()>>(),
+            Ok(Err(LangError::CouldNotReadInput))
+        );
+    }
+
+    #[test]
+    fn payable_message_accepts_value() {
+        let contract = test::default_accounts::<DefaultEnvironment>().charlie;
+        test::register_contract::<DefaultEnvironment>(contract, Counter::ENTRY_POINTS);
+        test::call_constructor::<DefaultEnvironment>(
+            contract,
+            selector_bytes!("new"),
+            &0u32.encode(),
+        );
+
+        test::set_value_transferred::<DefaultEnvironment>(10);
+        let result = test::call_message::<DefaultEnvironment>(
+            contract,
+            selector_bytes!("deposit"),
+            &[],
+        );
+
+        assert_eq!(result.decode::<MessageResult<u128>>(), Ok(Ok(10)));
+    }
+
+    #[test]
+    fn non_payable_message_without_value_succeeds() {
+        let contract = test::default_accounts::<DefaultEnvironment>().charlie;
+        test::register_contract::<DefaultEnvironment>(contract, Counter::ENTRY_POINTS);
+        test::call_constructor::<DefaultEnvironment>(
+            contract,
+            selector_bytes!("new"),
+            &0u32.encode(),
+        );
+
+        test::set_value_transferred::<DefaultEnvironment>(0);
+        let result = test::call_message::<DefaultEnvironment>(
+            contract,
+            selector_bytes!("inc"),
+            &1u32.encode(),
+        );
+
+        assert!(!result.did_revert());
+    }
+
+    #[test]
+    #[should_panic(expected = "paid an unpayable message")]
+    fn non_payable_message_with_value_traps() {
+        let contract = test::default_accounts::<DefaultEnvironment>().charlie;
+        test::register_contract::<DefaultEnvironment>(contract, Counter::ENTRY_POINTS);
+        test::call_constructor::<DefaultEnvironment>(
+            contract,
+            selector_bytes!("new"),
+            &0u32.encode(),
+        );
+
+        test::set_value_transferred::<DefaultEnvironment>(10);
+        test::call_message::<DefaultEnvironment>(
+            contract,
+            selector_bytes!("inc"),
+            &1u32.encode(),
+        );
+    }
+}
@@ -0,0 +1,4 @@
+#[ink::test]
+async fn async_without_feature() {}
+
+fn main() {}
@@ -0,0 +1,15 @@
+#[ink::test]
+async fn async_no_return() {
+    let bob = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>().bob;
+    ink::env::test::set_caller::<ink::env::DefaultEnvironment>(bob);
+    core::future::ready(()).await;
+    assert_eq!(ink::env::caller::<ink::env::DefaultEnvironment>(), bob);
+}
+
+#[ink::test]
+async fn async_result_return() -> Result<(), String> {
+    core::future::ready(Ok::<_, String>(())).await?;
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,4 @@
+#[ink::test(env = ink::env::DefaultEnvironment)]
+fn attribute_input() {}
+
+fn main() {}
@@ -0,0 +1,8 @@
+struct Error;
+
+#[ink::test]
+fn non_debug_error() -> Result<(), Error> {
+    Ok(())
+}
+
+fn main() {}
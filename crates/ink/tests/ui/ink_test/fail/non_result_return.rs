@@ -0,0 +1,6 @@
+#[ink::test]
+fn non_result_return() -> u32 {
+    42
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+#[ink::test]
+fn non_unit_ok() -> Result<u32, String> {
+    Ok(42)
+}
+
+fn main() {}
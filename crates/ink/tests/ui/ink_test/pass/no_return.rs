@@ -0,0 +1,7 @@
+#[ink::test]
+fn no_return() {
+    let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+    assert_eq!(ink::env::caller::<ink::env::DefaultEnvironment>(), accounts.alice);
+}
+
+fn main() {}
@@ -0,0 +1,30 @@
+#[derive(Debug)]
+struct Error;
+
+fn fallible(fail: bool) -> Result<u32, Error> {
+    if fail {
+        return Err(Error)
+    }
+    Ok(42)
+}
+
+#[ink::test]
+fn env_result_return() -> ink::env::Result<()> {
+    ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(0);
+    Ok(())
+}
+
+#[ink::test]
+fn custom_error_return() -> Result<(), Error> {
+    assert_eq!(fallible(false)?, 42);
+    Ok(())
+}
+
+#[ink::test]
+#[should_panic(expected = "the test returned an error: Error")]
+fn err_fails_the_test() -> Result<(), Error> {
+    fallible(true)?;
+    Ok(())
+}
+
+fn main() {}
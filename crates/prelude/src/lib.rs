@@ -43,6 +43,7 @@ cfg_if! {
             borrow,
             boxed,
             format,
+            rc,
             string,
             vec,
         };
@@ -64,6 +65,7 @@ cfg_if! {
             borrow,
             boxed,
             format,
+            rc,
             string,
             vec,
         };
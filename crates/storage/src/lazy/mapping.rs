@@ -411,6 +411,22 @@ mod tests {
         .unwrap()
     }
 
+    #[test]
+    fn seeded_mapping_entries_are_read_by_the_mapping() {
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|accounts| {
+            let base = <ManualKey<123> as StorageKey>::KEY;
+            ink_env::test::seed_mapping_entry(&base, &accounts.bob, &1_000u128);
+
+            let mapping: Mapping<ink_primitives::AccountId, u128, ManualKey<123>> =
+                Mapping::new();
+            assert_eq!(mapping.get(accounts.bob), Some(1_000));
+            assert_eq!(mapping.get(accounts.alice), None);
+
+            Ok(())
+        })
+        .unwrap()
+    }
+
     #[test]
     fn gets_default_if_no_key_set() {
         ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {